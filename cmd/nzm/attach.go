@@ -5,7 +5,6 @@ import (
 	"fmt"
 	"time"
 
-	"github.com/Dicklesworthstone/ntm/internal/zellij"
 	"github.com/spf13/cobra"
 )
 
@@ -28,7 +27,7 @@ func init() {
 func runAttach(cmd *cobra.Command, args []string) error {
 	session := args[0]
 
-	client := zellij.NewClient()
+	client := newClient()
 
 	ctx, cancel := context.WithTimeout(context.Background(), 5*time.Second)
 	defer cancel()
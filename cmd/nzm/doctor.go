@@ -0,0 +1,107 @@
+package main
+
+import (
+	"context"
+	"errors"
+	"fmt"
+	"os"
+	"time"
+
+	"github.com/Dicklesworthstone/ntm/internal/i18n"
+	"github.com/Dicklesworthstone/ntm/internal/nzm"
+	"github.com/Dicklesworthstone/ntm/internal/output"
+	"github.com/Dicklesworthstone/ntm/internal/updater"
+	"github.com/spf13/cobra"
+)
+
+var doctorCmd = &cobra.Command{
+	Use:   "doctor [SESSION]",
+	Short: "Check that sessions run the expected plugin build",
+	Long: `Ask the nzm-agent plugin in each running session which build it is
+and check it against this CLI and the release manifest nzm self-update
+installed next to the plugin.
+
+A plugin is stale when it speaks another protocol or was built from other
+sources than the release, and tampered when it claims the release's
+sources but its file does not match the released one. Exits with 1 when
+any plugin is not ok.
+
+Examples:
+  # Check every running session
+  nzm doctor
+
+  # Check one session against a downloaded manifest
+  nzm doctor myproj --manifest ./nzm-manifest.json`,
+	Args: cobra.MaximumNArgs(1),
+	RunE: runDoctor,
+}
+
+var doctorManifest string
+
+func init() {
+	rootCmd.AddCommand(doctorCmd)
+
+	doctorCmd.Flags().StringVar(&doctorManifest, "manifest", "", "Release manifest to check against (default: the installed one)")
+}
+
+func runDoctor(cmd *cobra.Command, args []string) error {
+	opts := nzm.DoctorOptions{CLIVersion: Version}
+	if len(args) > 0 {
+		opts.Session = args[0]
+	}
+
+	manifestPath := doctorManifest
+	if manifestPath == "" {
+		manifestPath = updater.ManifestPath(installedPluginPath())
+	}
+	manifest, err := updater.LoadManifest(manifestPath)
+	switch {
+	case err == nil:
+		opts.Manifest = manifest
+	case doctorManifest != "" || !errors.Is(err, os.ErrNotExist):
+		return nzm.WithCode(nzm.CodeUsage, err)
+	}
+
+	ctx, cancel := context.WithTimeout(context.Background(), 30*time.Second)
+	defer cancel()
+
+	result, err := nzm.NewDoctor(newClient()).Check(ctx, opts)
+	if err != nil {
+		return err
+	}
+	if failed := result.Failed(); failed > 0 {
+		err = nzm.Errorf(nzm.CodeFailed, "%d of %d plugins need attention", failed, len(result.Checks))
+	}
+
+	formatter := output.NZMDefaultFormatter(jsonFlag)
+	if formatter.IsJSON() {
+		if jsonErr := formatter.JSON(result); jsonErr != nil {
+			return jsonErr
+		}
+		return err
+	}
+
+	if len(result.Checks) == 0 {
+		fmt.Println(i18n.T(i18n.NoSessions))
+	}
+	for _, check := range result.Checks {
+		version := "?"
+		if check.Plugin != nil {
+			version = check.Plugin.Version
+		}
+		switch check.Status {
+		case nzm.PluginOK:
+			fmt.Println(i18n.T(i18n.PluginOK, check.Session, version))
+		case nzm.PluginStale:
+			fmt.Println(i18n.T(i18n.PluginStale, check.Session, version))
+		case nzm.PluginTampered:
+			fmt.Println(i18n.T(i18n.PluginTampered, check.Session, version))
+		default:
+			fmt.Println(i18n.T(i18n.PluginUnknown, check.Session))
+		}
+		for _, problem := range check.Problems {
+			fmt.Printf("  - %s\n", problem)
+		}
+	}
+	return err
+}
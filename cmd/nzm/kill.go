@@ -5,9 +5,9 @@ import (
 	"fmt"
 	"time"
 
+	"github.com/Dicklesworthstone/ntm/internal/i18n"
 	"github.com/Dicklesworthstone/ntm/internal/nzm"
 	"github.com/Dicklesworthstone/ntm/internal/output"
-	"github.com/Dicklesworthstone/ntm/internal/zellij"
 	"github.com/spf13/cobra"
 )
 
@@ -42,7 +42,7 @@ func init() {
 }
 
 func runKill(cmd *cobra.Command, args []string) error {
-	client := zellij.NewClient()
+	client := newClient()
 	killer := nzm.NewKiller(client)
 
 	ctx, cancel := context.WithTimeout(context.Background(), 10*time.Second)
@@ -60,7 +60,7 @@ func runKill(cmd *cobra.Command, args []string) error {
 				"success": true,
 			})
 		}
-		fmt.Println("All sessions killed.")
+		fmt.Println(i18n.T(i18n.AllKilled))
 		return nil
 	}
 
@@ -86,6 +86,6 @@ func runKill(cmd *cobra.Command, args []string) error {
 		})
 	}
 
-	fmt.Printf("Session %q killed.\n", session)
+	fmt.Println(i18n.T(i18n.SessionKilled, session))
 	return nil
 }
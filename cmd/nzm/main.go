@@ -1,25 +1,35 @@
 package main
 
 import (
+	"encoding/json"
 	"fmt"
 	"os"
 
 	"github.com/Dicklesworthstone/ntm/internal/config"
+	"github.com/Dicklesworthstone/ntm/internal/i18n"
+	"github.com/Dicklesworthstone/ntm/internal/nzm"
+	"github.com/Dicklesworthstone/ntm/internal/zellij"
 	"github.com/spf13/cobra"
 )
 
+// Version is set at build time with -ldflags "-X main.Version=..."
+var Version = "dev"
+
 var (
 	// Global flags
-	cfgFile  string
-	jsonFlag bool
+	cfgFile        string
+	jsonFlag       bool
+	jsonErrorsFlag bool
+	sshFlag        string
 
 	// Global config (loaded once at startup)
 	cfg *config.NZMConfig
 )
 
 var rootCmd = &cobra.Command{
-	Use:   "nzm",
-	Short: "Named Zellij Manager - orchestrate AI coding agents",
+	Use:     "nzm",
+	Short:   "Named Zellij Manager - orchestrate AI coding agents",
+	Version: Version,
 	Long: `NZM (Named Zellij Manager) orchestrates multiple AI coding agents
 in a Zellij terminal multiplexer session.
 
@@ -27,25 +37,84 @@ It allows you to:
   - Spawn sessions with multiple Claude, Codex, or Gemini panes
   - Send commands and text to specific panes
   - View status of active sessions and agents
-  - Kill sessions when done`,
+  - Kill sessions when done
+
+Exit codes:
+  0  success
+  1  any other failure
+  2  invalid arguments, flags or params
+  3  session, pane or task not found
+  4  timed out
+  5  refused by a route hook or guard
+  6  busy: paused, held, queue full, stale target or agent starting
+  7  plugin or session unreachable
+
+With --json-errors, failures are written to stderr as one JSON object
+with "error", "code" and "exit_code" fields.
+
+With --ssh HOST (or zellij.ssh in the config), nzm drives the Zellij
+sessions on HOST, running zellij there over ssh. Paths such as
+zellij.plugin_path and --workdir then name files on HOST.`,
 	PersistentPreRunE: func(cmd *cobra.Command, args []string) error {
 		var err error
 		cfg, err = config.NZMLoad(cfgFile)
 		if err != nil {
 			return fmt.Errorf("loading config: %w", err)
 		}
+		if sshFlag != "" {
+			cfg.Zellij.SSH = sshFlag
+		}
+		cfg.DetectPluginPath()
+		i18n.SetLocale(i18n.Detect(cfg.Locale))
 		return nil
 	},
+	// main reports errors itself, as JSON with --json-errors
+	SilenceErrors: true,
 }
 
 func init() {
 	rootCmd.PersistentFlags().StringVar(&cfgFile, "config", "", "config file (default $HOME/.config/nzm/config.toml)")
 	rootCmd.PersistentFlags().BoolVar(&jsonFlag, "json", false, "output in JSON format")
+	rootCmd.PersistentFlags().BoolVar(&jsonErrorsFlag, "json-errors", false, "write errors to stderr as JSON")
+	rootCmd.PersistentFlags().StringVar(&sshFlag, "ssh", "", "drive the Zellij sessions on this host over ssh")
+}
+
+// newClient returns a Zellij client for the configured host
+func newClient() *zellij.Client {
+	return zellij.NewClient(zellij.WithRemote(cfg.Zellij.SSH))
 }
 
 func main() {
+	markUsageErrors(rootCmd)
 	if err := rootCmd.Execute(); err != nil {
-		fmt.Fprintln(os.Stderr, err)
-		os.Exit(1)
+		reportError(err)
+		os.Exit(nzm.ExitCode(err))
+	}
+}
+
+// reportError writes err to stderr, as a JSON object with --json-errors
+func reportError(err error) {
+	if jsonErrorsFlag {
+		_ = json.NewEncoder(os.Stderr).Encode(map[string]interface{}{
+			"error":     err.Error(),
+			"code":      nzm.ErrorCode(err),
+			"exit_code": nzm.ExitCode(err),
+		})
+		return
+	}
+	fmt.Fprintln(os.Stderr, err)
+}
+
+// markUsageErrors gives flag and argument errors the usage exit code
+func markUsageErrors(root *cobra.Command) {
+	root.SetFlagErrorFunc(func(_ *cobra.Command, err error) error {
+		return nzm.WithCode(nzm.CodeUsage, err)
+	})
+	for _, cmd := range root.Commands() {
+		if args := cmd.Args; args != nil {
+			cmd.Args = func(c *cobra.Command, a []string) error {
+				return nzm.WithCode(nzm.CodeUsage, args(c, a))
+			}
+		}
 	}
 }
@@ -0,0 +1,96 @@
+package main
+
+import (
+	"context"
+	"fmt"
+	"os"
+	"time"
+
+	"github.com/Dicklesworthstone/ntm/internal/clipboard"
+	"github.com/Dicklesworthstone/ntm/internal/nzm"
+	"github.com/Dicklesworthstone/ntm/internal/output"
+	"github.com/spf13/cobra"
+)
+
+var pasteCmd = &cobra.Command{
+	Use:   "paste SESSION TARGET",
+	Short: "Paste the system clipboard into a pane",
+	Long: `Read the local system clipboard and send it to a pane as one bracketed
+paste, so the agent receives it as pasted text rather than typed lines.
+
+Large clipboards are written in chunks, one per pacing tick, so the pane
+is not flooded. Targets are resolved as for nzm send.
+
+Examples:
+  # Paste into the first Claude pane
+  nzm paste myproj cc
+
+  # Paste and submit it
+  nzm paste myproj cc_2 --enter`,
+	Args: cobra.ExactArgs(2),
+	RunE: runPaste,
+}
+
+var (
+	pasteEnter      bool
+	pasteChunkBytes int
+)
+
+func init() {
+	rootCmd.AddCommand(pasteCmd)
+
+	pasteCmd.Flags().BoolVarP(&pasteEnter, "enter", "e", false, "Press Enter after the paste")
+	pasteCmd.Flags().IntVar(&pasteChunkBytes, "chunk-bytes", nzm.DefaultPasteChunkBytes, "Bytes written per pacing tick")
+}
+
+func runPaste(cmd *cobra.Command, args []string) error {
+	session := args[0]
+	target := args[1]
+
+	board, err := clipboard.New()
+	if err != nil {
+		return fmt.Errorf("no clipboard available: %w", err)
+	}
+	text, err := board.Paste()
+	if err != nil {
+		return fmt.Errorf("reading clipboard: %w", err)
+	}
+
+	ctx, cancel := context.WithTimeout(context.Background(), 30*time.Second)
+	defer cancel()
+
+	// A one-shot pipe passes the request as an argument, which the OS
+	// limits in size; a kept-open pipe writes it to stdin instead
+	client := newClient()
+	defer client.Close()
+	if _, err := client.OpenPipe(ctx, session); err != nil {
+		fmt.Fprintf(os.Stderr, "warning: could not keep a pipe open: %v\n", err)
+	}
+
+	pane, err := nzm.NewPaster(client).Paste(ctx, nzm.PasteOptions{
+		Session:    session,
+		Target:     target,
+		Text:       text,
+		ChunkBytes: pasteChunkBytes,
+		Enter:      pasteEnter,
+	})
+	if err != nil {
+		return err
+	}
+
+	formatter := output.NZMDefaultFormatter(jsonFlag)
+	if formatter.IsJSON() {
+		return formatter.JSON(map[string]interface{}{
+			"action":  "paste",
+			"session": session,
+			"target":  target,
+			"pane_id": pane.ID,
+			"bytes":   len(text),
+			"backend": board.Backend(),
+			"success": true,
+		})
+	}
+
+	// Silent success for text output, like send
+	return nil
+}
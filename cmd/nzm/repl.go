@@ -0,0 +1,141 @@
+package main
+
+import (
+	"bufio"
+	"context"
+	"fmt"
+	"io"
+	"os"
+	"strings"
+	"time"
+
+	"github.com/Dicklesworthstone/ntm/internal/nzm"
+	"github.com/spf13/cobra"
+	"golang.org/x/term"
+)
+
+var replCmd = &cobra.Command{
+	Use:   "repl SESSION",
+	Short: "Control a session interactively",
+	Long: `Start an interactive prompt for one session.
+
+Commands run as soon as they are entered, over one pipe to the plugin
+that stays open for the whole REPL. Tab completes commands and pane
+names; the arrow keys recall earlier lines.
+
+Commands:
+  ls                      list panes
+  send TARGET TEXT...     send text to a pane and press Enter
+  interrupt TARGET        send Ctrl+C to a pane
+  tail TARGET [LINES]     show the last lines of a pane
+  raw ACTION [JSON]       send any plugin action
+  help, exit
+
+Examples:
+  nzm repl myproj
+  nzm> send cc_1 "fix the tests"
+  nzm> tail cod_2`,
+	Args: cobra.ExactArgs(1),
+	RunE: runREPL,
+}
+
+// replTimeout bounds each REPL command
+const replTimeout = 30 * time.Second
+
+func init() {
+	rootCmd.AddCommand(replCmd)
+}
+
+func runREPL(cmd *cobra.Command, args []string) error {
+	session := args[0]
+
+	client := newClient()
+	defer client.Close()
+
+	ctx, cancel := context.WithCancel(context.Background())
+	defer cancel()
+	if _, err := client.OpenPipe(ctx, session); err != nil {
+		// Still usable, one pipe per command
+		fmt.Fprintf(os.Stderr, "warning: could not keep a pipe open: %v\n", err)
+	}
+
+	if !term.IsTerminal(int(os.Stdin.Fd())) {
+		return replLoop(ctx, nzm.NewREPL(client, session, os.Stdout), lineScanner(os.Stdin), os.Stderr)
+	}
+
+	state, err := term.MakeRaw(int(os.Stdin.Fd()))
+	if err != nil {
+		return fmt.Errorf("failed to set terminal mode: %w", err)
+	}
+	defer term.Restore(int(os.Stdin.Fd()), state)
+
+	terminal := term.NewTerminal(struct {
+		io.Reader
+		io.Writer
+	}{os.Stdin, os.Stdout}, "nzm> ")
+	repl := nzm.NewREPL(client, session, terminal)
+	terminal.AutoCompleteCallback = func(line string, pos int, key rune) (string, int, bool) {
+		if key != '\t' || pos != len(line) {
+			return "", 0, false
+		}
+		return completeLine(line, repl.Complete(line))
+	}
+	return replLoop(ctx, repl, terminal.ReadLine, terminal)
+}
+
+// replLoop runs lines from next until exit or end of input
+func replLoop(ctx context.Context, repl *nzm.REPL, next func() (string, error), errOut io.Writer) error {
+	for {
+		line, err := next()
+		if err == io.EOF {
+			return nil
+		}
+		if err != nil {
+			return err
+		}
+
+		cmdCtx, cancel := context.WithTimeout(ctx, replTimeout)
+		quit, err := repl.Execute(cmdCtx, line)
+		cancel()
+		if err != nil {
+			fmt.Fprintf(errOut, "error: %v\n", err)
+		}
+		if quit {
+			return nil
+		}
+	}
+}
+
+// lineScanner reads lines from a non-interactive input
+func lineScanner(r io.Reader) func() (string, error) {
+	scanner := bufio.NewScanner(r)
+	return func() (string, error) {
+		if scanner.Scan() {
+			return scanner.Text(), nil
+		}
+		if err := scanner.Err(); err != nil {
+			return "", err
+		}
+		return "", io.EOF
+	}
+}
+
+// completeLine extends the last word of line to the longest prefix the
+// candidates share, adding a space once only one candidate is left
+func completeLine(line string, candidates []string) (string, int, bool) {
+	if len(candidates) == 0 {
+		return "", 0, false
+	}
+	start := strings.LastIndexAny(line, " \t") + 1
+	word := candidates[0]
+	for _, c := range candidates[1:] {
+		for !strings.HasPrefix(c, word) {
+			word = word[:len(word)-1]
+		}
+	}
+	if len(candidates) == 1 {
+		word += " "
+	}
+	completed := line[:start] + word
+	return completed, len(completed), true
+}
@@ -0,0 +1,275 @@
+package main
+
+import (
+	"context"
+	"fmt"
+	"net/http"
+	"os"
+	"path/filepath"
+	"runtime"
+	"time"
+
+	"github.com/Dicklesworthstone/ntm/internal/config"
+	"github.com/Dicklesworthstone/ntm/internal/i18n"
+	"github.com/Dicklesworthstone/ntm/internal/output"
+	"github.com/Dicklesworthstone/ntm/internal/updater"
+	"github.com/Dicklesworthstone/ntm/internal/zellij"
+	"github.com/spf13/cobra"
+)
+
+var selfUpdateCmd = &cobra.Command{
+	Use:   "self-update",
+	Short: "Update nzm and its plugin to the latest release",
+	Long: `Download the latest release of nzm for this platform together with
+its nzm-agent plugin, verify them and install both, so the CLI and the
+plugin always speak the same protocol.
+
+The release's checksums file must carry the release workflow's
+signature, checked with cosign, and every download must match its
+checksum. The plugin is installed at zellij.plugin_path, or in
+~/.local/share/nzm when that is not a file path, with the release
+manifest next to it for nzm doctor. Sessions running the plugin from that
+path reload it.
+
+Examples:
+  # Update to the latest stable release
+  nzm self-update
+
+  # Only check whether an update is available
+  nzm self-update --check
+
+  # Follow prereleases as well
+  nzm self-update --channel beta`,
+	Args: cobra.NoArgs,
+	RunE: runSelfUpdate,
+}
+
+var (
+	selfUpdateChannel       string
+	selfUpdateCheck         bool
+	selfUpdateForce         bool
+	selfUpdateSkipSignature bool
+)
+
+func init() {
+	rootCmd.AddCommand(selfUpdateCmd)
+
+	selfUpdateCmd.Flags().StringVar(&selfUpdateChannel, "channel", updater.ChannelStable, "Release channel: stable or beta")
+	selfUpdateCmd.Flags().BoolVar(&selfUpdateCheck, "check", false, "Only check for an update")
+	selfUpdateCmd.Flags().BoolVarP(&selfUpdateForce, "force", "f", false, "Reinstall even if already up to date")
+	selfUpdateCmd.Flags().BoolVar(&selfUpdateSkipSignature, "insecure-skip-signature", false, "Skip the signature check (checksums are still verified)")
+}
+
+func runSelfUpdate(cmd *cobra.Command, args []string) error {
+	ctx, cancel := context.WithTimeout(context.Background(), 10*time.Minute)
+	defer cancel()
+	httpClient := &http.Client{Timeout: 5 * time.Minute}
+	formatter := output.NZMDefaultFormatter(jsonFlag)
+
+	release, err := updater.LatestNZMRelease(ctx, httpClient, selfUpdateChannel)
+	if err != nil {
+		return fmt.Errorf("checking for updates: %w", err)
+	}
+	latest := release.Version()
+	available := updater.IsNewer(Version, latest)
+
+	if selfUpdateCheck || (!available && !selfUpdateForce) {
+		if formatter.IsJSON() {
+			return formatter.JSON(map[string]interface{}{
+				"action":    "self_update",
+				"channel":   selfUpdateChannel,
+				"current":   Version,
+				"latest":    latest,
+				"available": available,
+				"updated":   false,
+			})
+		}
+		if available {
+			fmt.Println(i18n.T(i18n.UpdateFound, latest, Version))
+		} else {
+			fmt.Println(i18n.T(i18n.UpToDate, Version))
+		}
+		return nil
+	}
+
+	exe, err := os.Executable()
+	if err != nil {
+		return fmt.Errorf("locating the nzm binary: %w", err)
+	}
+	if exe, err = filepath.EvalSymlinks(exe); err != nil {
+		return fmt.Errorf("locating the nzm binary: %w", err)
+	}
+	pluginPath := installedPluginPath()
+
+	dir, err := os.MkdirTemp("", "nzm-update-*")
+	if err != nil {
+		return err
+	}
+	defer os.RemoveAll(dir)
+
+	files, err := downloadRelease(ctx, httpClient, release, dir)
+	if err != nil {
+		return err
+	}
+	manifest, err := verifyRelease(ctx, files, latest)
+	if err != nil {
+		return err
+	}
+
+	// The plugin first: a new plugin still answers an old CLI's requests
+	if err := updater.InstallFile(files[updater.PluginAsset], pluginPath, 0644); err != nil {
+		return fmt.Errorf("installing the plugin: %w", err)
+	}
+	if err := updater.InstallFile(files[updater.ManifestAsset], updater.ManifestPath(pluginPath), 0644); err != nil {
+		return fmt.Errorf("installing the manifest: %w", err)
+	}
+	if err := updater.InstallFile(files[updater.CLIAssetName(runtime.GOOS, runtime.GOARCH)], exe, 0755); err != nil {
+		return fmt.Errorf("installing the nzm binary: %w", err)
+	}
+
+	reloaded, skipped := reloadPlugin(ctx, pluginPath)
+
+	if formatter.IsJSON() {
+		return formatter.JSON(map[string]interface{}{
+			"action":      "self_update",
+			"channel":     selfUpdateChannel,
+			"current":     Version,
+			"latest":      latest,
+			"available":   available,
+			"updated":     true,
+			"binary":      exe,
+			"plugin_path": pluginPath,
+			"protocol":    manifest.Protocol,
+			"reloaded":    reloaded,
+			"skipped":     skipped,
+		})
+	}
+
+	fmt.Println(i18n.T(i18n.Updated, Version, latest))
+	fmt.Println(i18n.T(i18n.BinaryLabel, exe))
+	fmt.Println(i18n.T(i18n.PluginLabel, pluginPath))
+	for _, session := range reloaded {
+		fmt.Println(i18n.T(i18n.PluginReloaded, session))
+	}
+	for session, reason := range skipped {
+		fmt.Println(i18n.T(i18n.PluginSkipped, session, reason))
+	}
+	if cfg.Zellij.PluginPath != "" && cfg.Zellij.PluginPath != pluginPath {
+		fmt.Fprintf(os.Stderr, "warning: zellij.plugin_path is %q; new sessions will not use the update\n", cfg.Zellij.PluginPath)
+	}
+	return nil
+}
+
+// installedPluginPath is where self-update installs the plugin: the
+// configured file, or the default location when the plugin is configured
+// by alias
+func installedPluginPath() string {
+	if filepath.IsAbs(cfg.Zellij.PluginPath) {
+		return cfg.Zellij.PluginPath
+	}
+	return config.NZMDefaultPluginPath()
+}
+
+// downloadRelease fetches this platform's binary, the plugin, its
+// manifest and the signed checksums into dir, keyed by asset name
+func downloadRelease(ctx context.Context, client *http.Client, release *updater.NZMRelease, dir string) (map[string]string, error) {
+	names := []string{
+		updater.CLIAssetName(runtime.GOOS, runtime.GOARCH),
+		updater.PluginAsset,
+		updater.ManifestAsset,
+		updater.ChecksumsAsset,
+	}
+	if !selfUpdateSkipSignature {
+		names = append(names, updater.SignatureAsset, updater.CertificateAsset)
+	}
+
+	files := make(map[string]string, len(names))
+	for _, name := range names {
+		asset, ok := release.Asset(name)
+		if !ok {
+			return nil, fmt.Errorf("release %s has no %s", release.TagName, name)
+		}
+		path, err := updater.Download(ctx, client, asset, dir)
+		if err != nil {
+			return nil, err
+		}
+		files[name] = path
+	}
+	return files, nil
+}
+
+// verifyRelease checks the checksums file's signature, every download
+// against it, and that the manifest describes this plugin and release
+func verifyRelease(ctx context.Context, files map[string]string, version string) (*updater.Manifest, error) {
+	if selfUpdateSkipSignature {
+		fmt.Fprintln(os.Stderr, "warning: skipping the release signature check")
+	} else if err := updater.VerifySignature(ctx, files[updater.ChecksumsAsset], files[updater.SignatureAsset], files[updater.CertificateAsset]); err != nil {
+		return nil, err
+	}
+
+	data, err := os.ReadFile(files[updater.ChecksumsAsset])
+	if err != nil {
+		return nil, err
+	}
+	sums, err := updater.ParseChecksums(data)
+	if err != nil {
+		return nil, err
+	}
+	for _, name := range []string{updater.CLIAssetName(runtime.GOOS, runtime.GOARCH), updater.PluginAsset, updater.ManifestAsset} {
+		if err := updater.VerifyChecksum(sums, files[name]); err != nil {
+			return nil, err
+		}
+	}
+
+	manifest, err := updater.LoadManifest(files[updater.ManifestAsset])
+	if err != nil {
+		return nil, err
+	}
+	if manifest.Version != version {
+		return nil, fmt.Errorf("manifest is for %s, not %s", manifest.Version, version)
+	}
+	if manifest.Plugin.SHA256 != sums[updater.PluginAsset] {
+		return nil, fmt.Errorf("manifest does not describe the released plugin")
+	}
+	return manifest, nil
+}
+
+// reloadPlugin reloads the plugin in every session that loaded it from
+// pluginPath. Sessions that loaded it from elsewhere would get a second
+// copy, so they are skipped with the reason.
+func reloadPlugin(ctx context.Context, pluginPath string) ([]string, map[string]string) {
+	client := zellij.NewClient()
+	reloaded := []string{}
+	skipped := map[string]string{}
+
+	sessions, err := client.ListSessions(ctx)
+	if err != nil {
+		skipped["*"] = err.Error()
+		return reloaded, skipped
+	}
+	for _, session := range sessions {
+		if session.Exited {
+			continue
+		}
+		versionCtx, cancel := context.WithTimeout(ctx, 5*time.Second)
+		version, err := client.GetVersion(versionCtx, session.Name)
+		cancel()
+		switch {
+		case err != nil:
+			skipped[session.Name] = err.Error()
+		case version.PluginPath != pluginPath:
+			skipped[session.Name] = fmt.Sprintf("plugin loaded from %q", version.PluginPath)
+		default:
+			err := client.ReloadPlugin(ctx, session.Name, zellij.PluginURL(pluginPath), map[string]string{
+				"plugin_path": pluginPath,
+				"locale":      string(i18n.Current()),
+			})
+			if err != nil {
+				skipped[session.Name] = err.Error()
+			} else {
+				reloaded = append(reloaded, session.Name)
+			}
+		}
+	}
+	return reloaded, skipped
+}
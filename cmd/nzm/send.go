@@ -6,7 +6,6 @@ import (
 
 	"github.com/Dicklesworthstone/ntm/internal/nzm"
 	"github.com/Dicklesworthstone/ntm/internal/output"
-	"github.com/Dicklesworthstone/ntm/internal/zellij"
 	"github.com/spf13/cobra"
 )
 
@@ -53,7 +52,7 @@ func runSend(cmd *cobra.Command, args []string) error {
 		text = args[2]
 	}
 
-	client := zellij.NewClient()
+	client := newClient()
 	sender := nzm.NewSender(client)
 
 	ctx, cancel := context.WithTimeout(context.Background(), 10*time.Second)
@@ -6,9 +6,9 @@ import (
 	"os"
 	"time"
 
+	"github.com/Dicklesworthstone/ntm/internal/i18n"
 	"github.com/Dicklesworthstone/ntm/internal/nzm"
 	"github.com/Dicklesworthstone/ntm/internal/output"
-	"github.com/Dicklesworthstone/ntm/internal/zellij"
 	"github.com/spf13/cobra"
 )
 
@@ -72,7 +72,7 @@ func init() {
 func runSpawn(cmd *cobra.Command, args []string) error {
 	session := args[0]
 
-	client := zellij.NewClient()
+	client := newClient()
 	spawner := nzm.NewSpawner(client)
 
 	ctx, cancel := context.WithTimeout(context.Background(), 30*time.Second)
@@ -116,6 +116,7 @@ func runSpawn(cmd *cobra.Command, args []string) error {
 		CodCmd:      codCmd,
 		GmiCmd:      gmiCmd,
 		Detached:    spawnDetached,
+		Locale:      string(i18n.Current()),
 	}
 
 	result, err := spawner.Spawn(ctx, opts)
@@ -137,10 +138,10 @@ func runSpawn(cmd *cobra.Command, args []string) error {
 	}
 
 	if spawnDetached {
-		fmt.Printf("Created session %q with %d panes (detached)\n", result.Session, result.PaneCount)
-		fmt.Printf("  Layout: %s\n", result.LayoutPath)
-		fmt.Printf("  WorkDir: %s\n", result.WorkDir)
-		fmt.Printf("\nAttach with: nzm attach %s\n", result.Session)
+		fmt.Println(i18n.T(i18n.SessionCreated, result.Session, result.PaneCount))
+		fmt.Println(i18n.T(i18n.LayoutLabel, result.LayoutPath))
+		fmt.Println(i18n.T(i18n.WorkDirLabel, result.WorkDir))
+		fmt.Printf("\n%s\n", i18n.T(i18n.AttachHint, result.Session))
 	} else {
 		// When attached, we don't print anything as we're inside Zellij
 		os.Exit(0)
@@ -5,6 +5,7 @@ import (
 	"fmt"
 	"time"
 
+	"github.com/Dicklesworthstone/ntm/internal/i18n"
 	"github.com/Dicklesworthstone/ntm/internal/nzm"
 	"github.com/Dicklesworthstone/ntm/internal/output"
 	"github.com/Dicklesworthstone/ntm/internal/zellij"
@@ -37,7 +38,7 @@ func init() {
 }
 
 func runStatus(cmd *cobra.Command, args []string) error {
-	client := zellij.NewClient()
+	client := newClient()
 	status := nzm.NewStatus(client)
 
 	ctx, cancel := context.WithTimeout(context.Background(), 10*time.Second)
@@ -62,7 +63,7 @@ func runStatus(cmd *cobra.Command, args []string) error {
 
 	// Text output
 	if len(result.Sessions) == 0 {
-		fmt.Println("No NZM sessions found.")
+		fmt.Println(i18n.T(i18n.NoSessions))
 		return nil
 	}
 
@@ -70,16 +71,16 @@ func runStatus(cmd *cobra.Command, args []string) error {
 		// Session header
 		statusStr := ""
 		if sess.Attached {
-			statusStr = " (attached)"
+			statusStr = i18n.T(i18n.SessionAttached)
 		}
 		if sess.Exited {
-			statusStr = " (exited)"
+			statusStr = i18n.T(i18n.SessionExited)
 		}
-		fmt.Printf("Session: %s%s\n", sess.Name, statusStr)
+		fmt.Println(i18n.T(i18n.SessionHeader, sess.Name, statusStr))
 
 		// Agent counts
 		if len(sess.AgentCounts) > 0 {
-			fmt.Print("  Agents:")
+			fmt.Print(i18n.T(i18n.AgentsLabel))
 			for agentType, count := range sess.AgentCounts {
 				display := zellij.GetAgentTypeDisplay(agentType)
 				fmt.Printf(" %s:%d", display, count)
@@ -89,7 +90,7 @@ func runStatus(cmd *cobra.Command, args []string) error {
 
 		// Pane details (if querying specific session)
 		if opts.Session != "" && len(sess.Panes) > 0 {
-			fmt.Println("  Panes:")
+			fmt.Println(i18n.T(i18n.PanesLabel))
 			for _, pane := range sess.Panes {
 				focusStr := ""
 				if pane.IsFocused {
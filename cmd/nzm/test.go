@@ -0,0 +1,142 @@
+package main
+
+import (
+	"context"
+	"fmt"
+	"os"
+	"path/filepath"
+	"regexp"
+
+	"github.com/Dicklesworthstone/ntm/internal/i18n"
+	"github.com/Dicklesworthstone/ntm/internal/nzm"
+	"github.com/Dicklesworthstone/ntm/internal/output"
+	"github.com/spf13/cobra"
+)
+
+var testCmd = &cobra.Command{
+	Use:   "test FILE",
+	Short: "Run scenario tests against a session",
+	Long: `Run the scenarios in a YAML file against a running session and report
+the results, so agent workflows can be tested in CI.
+
+Each scenario is a list of steps:
+  spawn:  {as: NAME, command: CMD, args: [...], cwd: DIR}   open a command pane
+  send:   {pane: P, text: TEXT, keys: [...], enter: true}   send input
+  wait:   {pane: P, contains: TEXT | regex: RE, timeout: 30} wait for text
+  assert: {pane: P, contains: TEXT | regex: RE, source: frame} check text now
+  close:  P                                                  close a pane
+  action: {name: ACTION, params: {...}}                      any plugin action
+
+Panes are named by spawn's "as", or by any plugin selector. Teardown
+steps run even when a step fails, and spawned panes are closed at the end.
+
+Examples:
+  nzm test scenarios.yaml --session ci
+  nzm test scenarios.yaml --junit report.xml`,
+	Args: cobra.ExactArgs(1),
+	RunE: runTest,
+}
+
+var (
+	testSession string
+	testJUnit   string
+	testRun     string
+)
+
+func init() {
+	rootCmd.AddCommand(testCmd)
+
+	testCmd.Flags().StringVarP(&testSession, "session", "s", "", "Session to run in (default: the file's session)")
+	testCmd.Flags().StringVar(&testJUnit, "junit", "", "Write a JUnit XML report to this file")
+	testCmd.Flags().StringVar(&testRun, "run", "", "Only run scenarios whose name matches this regex")
+}
+
+func runTest(cmd *cobra.Command, args []string) error {
+	path := args[0]
+	file, err := nzm.LoadScenarios(path)
+	if err != nil {
+		return err
+	}
+
+	session := testSession
+	if session == "" {
+		session = file.Session
+	}
+	if session == "" {
+		return fmt.Errorf("no session: set session in %s or pass --session", path)
+	}
+
+	scenarios := file.Scenarios
+	if testRun != "" {
+		re, err := regexp.Compile(testRun)
+		if err != nil {
+			return fmt.Errorf("invalid --run: %w", err)
+		}
+		scenarios = nil
+		for _, s := range file.Scenarios {
+			if re.MatchString(s.Name) {
+				scenarios = append(scenarios, s)
+			}
+		}
+	}
+
+	client := newClient()
+	defer client.Close()
+	ctx, cancel := context.WithCancel(context.Background())
+	defer cancel()
+	if _, err := client.OpenPipe(ctx, session); err != nil {
+		fmt.Fprintf(os.Stderr, "warning: could not keep a pipe open: %v\n", err)
+	}
+
+	results := nzm.NewScenarioRunner(client, session).Run(ctx, scenarios)
+
+	if testJUnit != "" {
+		f, err := os.Create(testJUnit)
+		if err != nil {
+			return fmt.Errorf("creating JUnit report: %w", err)
+		}
+		err = nzm.WriteJUnit(f, filepath.Base(path), results)
+		if closeErr := f.Close(); err == nil {
+			err = closeErr
+		}
+		if err != nil {
+			return fmt.Errorf("writing JUnit report: %w", err)
+		}
+	}
+
+	failed := 0
+	for _, result := range results {
+		if !result.Passed {
+			failed++
+		}
+	}
+
+	formatter := output.NZMDefaultFormatter(jsonFlag)
+	if formatter.IsJSON() {
+		if err := formatter.JSON(map[string]interface{}{
+			"session": session,
+			"passed":  len(results) - failed,
+			"failed":  failed,
+			"results": results,
+		}); err != nil {
+			return err
+		}
+	} else {
+		for _, result := range results {
+			if result.Passed {
+				fmt.Printf("PASS  %s (%.1fs)\n", result.Name, result.Duration.Seconds())
+			} else {
+				fmt.Printf("FAIL  %s (%.1fs)\n      step %d: %s\n", result.Name, result.Duration.Seconds(), result.Step, result.Failure)
+			}
+			for _, e := range result.TeardownErrors {
+				fmt.Printf("      teardown: %s\n", e)
+			}
+		}
+		fmt.Printf("\n%s\n", i18n.T(i18n.TestSummary, len(results)-failed, failed))
+	}
+
+	if failed > 0 {
+		return fmt.Errorf("%d of %d scenarios failed", failed, len(results))
+	}
+	return nil
+}
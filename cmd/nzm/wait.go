@@ -0,0 +1,116 @@
+package main
+
+import (
+	"context"
+	"errors"
+	"fmt"
+	"time"
+
+	"github.com/Dicklesworthstone/ntm/internal/nzm"
+	"github.com/Dicklesworthstone/ntm/internal/output"
+	"github.com/spf13/cobra"
+)
+
+var waitCmd = &cobra.Command{
+	Use:   "wait SESSION",
+	Short: "Wait for panes, agents, text or tasks",
+	Long: `Wait until conditions in a session hold, so shell scripts can
+synchronize with agents without polling loops.
+
+Conditions can be combined and repeated:
+  --idle PANE          the agent in PANE is idle
+  --text REGEX         REGEX matches the output of --pane (default: focused)
+  --pane-exists SEL    SEL names an open pane
+  --task ID            the task finished
+
+By default every condition must hold (--all); with --any one is enough.
+Exits non-zero if the timeout passes first.
+
+Examples:
+  # Wait for the first Claude pane to go idle
+  nzm wait myproj --idle cc_1
+
+  # Wait for either a test summary or a Codex pane to appear
+  nzm wait myproj --text 'tests? passed' --pane cc_1 --pane-exists 'myproj__cod_*' --any
+
+  # Check once without waiting
+  nzm wait myproj --task build --timeout 0s`,
+	Args: cobra.ExactArgs(1),
+	RunE: runWait,
+}
+
+var (
+	waitIdle       []string
+	waitText       string
+	waitPane       string
+	waitSource     string
+	waitPaneExists []string
+	waitTasks      []string
+	waitAll        bool
+	waitAny        bool
+	waitTimeout    time.Duration
+)
+
+func init() {
+	rootCmd.AddCommand(waitCmd)
+
+	waitCmd.Flags().StringArrayVar(&waitIdle, "idle", nil, "Wait for the agent in this pane to be idle (repeatable)")
+	waitCmd.Flags().StringVar(&waitText, "text", "", "Wait for this regex in a pane's output")
+	waitCmd.Flags().StringVar(&waitPane, "pane", "", "Pane searched by --text (default: focused)")
+	waitCmd.Flags().StringVar(&waitSource, "source", "", "Where --text looks: output (default) or frame")
+	waitCmd.Flags().StringArrayVar(&waitPaneExists, "pane-exists", nil, "Wait for this selector to name an open pane (repeatable)")
+	waitCmd.Flags().StringArrayVar(&waitTasks, "task", nil, "Wait for this task to finish (repeatable)")
+	waitCmd.Flags().BoolVar(&waitAll, "all", false, "Wait for every condition (default)")
+	waitCmd.Flags().BoolVar(&waitAny, "any", false, "Wait for any one condition")
+	waitCmd.Flags().DurationVar(&waitTimeout, "timeout", nzm.DefaultWaitTimeout, "Maximum wait time, in whole seconds; 0 checks once")
+	waitCmd.MarkFlagsMutuallyExclusive("all", "any")
+}
+
+func runWait(cmd *cobra.Command, args []string) error {
+	session := args[0]
+
+	if waitPane != "" && waitText == "" {
+		return fmt.Errorf("--pane needs --text")
+	}
+
+	client := newClient()
+	result, err := nzm.NewWaiter(client).Wait(context.Background(), nzm.WaitOptions{
+		Session:    session,
+		Idle:       waitIdle,
+		Text:       waitText,
+		TextPane:   waitPane,
+		Source:     waitSource,
+		PaneExists: waitPaneExists,
+		Tasks:      waitTasks,
+		Any:        waitAny,
+		Timeout:    waitTimeout,
+	})
+	if result == nil {
+		return err
+	}
+
+	formatter := output.NZMDefaultFormatter(jsonFlag)
+	if formatter.IsJSON() {
+		if jsonErr := formatter.JSON(map[string]interface{}{
+			"action":     "wait",
+			"session":    session,
+			"met":        result.Met,
+			"conditions": result.Conditions,
+			"labels":     result.Labels,
+		}); jsonErr != nil {
+			return jsonErr
+		}
+	} else if errors.Is(err, nzm.ErrWaitTimeout) {
+		// Show which conditions held up the wait
+		for i, label := range result.Labels {
+			mark := "-"
+			if i < len(result.Conditions) && result.Conditions[i] {
+				mark = "+"
+			}
+			fmt.Printf("  %s %s\n", mark, label)
+		}
+	}
+
+	// Silent success for text output, like send
+	return err
+}
@@ -15,6 +15,7 @@ type ZellijConfig struct {
 	PluginPath     string `toml:"plugin_path"`      // Path to nzm-agent.wasm plugin
 	PaletteKey     string `toml:"palette_key"`      // Keybinding for command palette
 	AttachOnCreate bool   `toml:"attach_on_create"` // Auto-attach when creating session
+	SSH            string `toml:"ssh"`              // Host whose Zellij nzm drives over ssh (default: local)
 }
 
 // DefaultZellijConfig returns sensible Zellij defaults
@@ -45,8 +46,11 @@ func projectsBase(envVar string) string {
 		return envBase
 	}
 	home, _ := os.UserHomeDir()
-	if runtime.GOOS == "darwin" {
+	switch runtime.GOOS {
+	case "darwin":
 		return filepath.Join(home, "Developer")
+	case "windows":
+		return filepath.Join(home, "projects")
 	}
 	return "/data/projects"
 }
@@ -61,11 +65,26 @@ func NZMDefaultProjectsBase() string {
 	return projectsBase("NZM_PROJECTS_BASE")
 }
 
+// NZMDefaultPluginPath returns where nzm self-update installs the plugin
+func NZMDefaultPluginPath() string {
+	if runtime.GOOS == "windows" {
+		if local := os.Getenv("LOCALAPPDATA"); local != "" {
+			return filepath.Join(local, "nzm", "nzm-agent.wasm")
+		}
+	}
+	if xdg := os.Getenv("XDG_DATA_HOME"); xdg != "" {
+		return filepath.Join(xdg, "nzm", "nzm-agent.wasm")
+	}
+	home, _ := os.UserHomeDir()
+	return filepath.Join(home, ".local", "share", "nzm", "nzm-agent.wasm")
+}
+
 // NZMConfig represents the main configuration for NZM (Zellij version)
 type NZMConfig struct {
 	ProjectsBase  string            `toml:"projects_base"`
 	Theme         string            `toml:"theme"`        // UI Theme (mocha, macchiato, nord, latte, auto)
 	PaletteFile   string            `toml:"palette_file"` // Path to command_palette.md (optional)
+	Locale        string            `toml:"locale"`       // Language of human output (default: from LC_ALL, LC_MESSAGES or LANG)
 	Agents        AgentConfig       `toml:"agents"`
 	Palette       []PaletteCmd      `toml:"palette"`
 	PaletteState  PaletteState      `toml:"palette_state"`
@@ -153,10 +172,28 @@ func NZMLoad(path string) (*NZMConfig, error) {
 	if pluginPath := os.Getenv("NZM_PLUGIN_PATH"); pluginPath != "" {
 		cfg.Zellij.PluginPath = pluginPath
 	}
+	if locale := os.Getenv("NZM_LOCALE"); locale != "" {
+		cfg.Locale = locale
+	}
+	if host := os.Getenv("NZM_SSH"); host != "" {
+		cfg.Zellij.SSH = host
+	}
 
 	return cfg, nil
 }
 
+// DetectPluginPath uses the plugin nzm self-update installed when none is
+// configured. Sessions on an SSH host load plugins from that host's disk,
+// so it only applies to local ones; call it once --ssh is applied.
+func (c *NZMConfig) DetectPluginPath() {
+	if c.Zellij.PluginPath != "" || c.Zellij.SSH != "" {
+		return
+	}
+	if _, err := os.Stat(NZMDefaultPluginPath()); err == nil {
+		c.Zellij.PluginPath = NZMDefaultPluginPath()
+	}
+}
+
 // GetProjectDir returns the project directory for a session
 func (c *NZMConfig) GetProjectDir(session string) string {
 	base := ExpandHome(c.ProjectsBase)
@@ -94,9 +94,12 @@ func TestNZMDefaultProjectsBase(t *testing.T) {
 		home, _ := os.UserHomeDir()
 
 		var want string
-		if runtime.GOOS == "darwin" {
+		switch runtime.GOOS {
+		case "darwin":
 			want = filepath.Join(home, "Developer")
-		} else {
+		case "windows":
+			want = filepath.Join(home, "projects")
+		default:
 			want = "/data/projects"
 		}
 		if base != want {
@@ -251,3 +254,30 @@ plugin_path = "/file/plugin.wasm"
 		}
 	})
 }
+
+func TestNZMConfig_DetectPluginPath(t *testing.T) {
+	dataHome := t.TempDir()
+	t.Setenv("XDG_DATA_HOME", dataHome)
+	t.Setenv("LOCALAPPDATA", dataHome)
+	installed := NZMDefaultPluginPath()
+	if err := os.MkdirAll(filepath.Dir(installed), 0755); err != nil {
+		t.Fatal(err)
+	}
+	if err := os.WriteFile(installed, []byte("wasm"), 0644); err != nil {
+		t.Fatal(err)
+	}
+
+	cfg := NZMDefault()
+	cfg.DetectPluginPath()
+	if cfg.Zellij.PluginPath != installed {
+		t.Errorf("PluginPath = %q, want %q", cfg.Zellij.PluginPath, installed)
+	}
+
+	// A remote host loads the plugin from its own disk
+	cfg = NZMDefault()
+	cfg.Zellij.SSH = "devbox"
+	cfg.DetectPluginPath()
+	if cfg.Zellij.PluginPath != "" {
+		t.Errorf("PluginPath = %q, want empty for an SSH host", cfg.Zellij.PluginPath)
+	}
+}
@@ -0,0 +1,243 @@
+// Package i18n holds the message catalogs for nzm's human-readable output.
+//
+// Only text meant for people is translated. JSON output, error messages
+// and exit codes are the same in every locale, so scripts keep working
+// whatever the user's language.
+package i18n
+
+import (
+	"fmt"
+	"os"
+	"strings"
+	"sync/atomic"
+)
+
+// Locale is a supported output language
+type Locale string
+
+// Supported locales, matching the nzm-agent plugin's
+const (
+	En Locale = "en"
+	De Locale = "de"
+	Es Locale = "es"
+	Fr Locale = "fr"
+)
+
+// ParseLocale reads a locale name such as "de", "es-MX" or "fr_FR.UTF-8".
+// The C and POSIX locales are English.
+func ParseLocale(name string) (Locale, bool) {
+	lang := strings.ToLower(strings.TrimSpace(name))
+	if i := strings.IndexAny(lang, "_-.@"); i >= 0 {
+		lang = lang[:i]
+	}
+	switch lang {
+	case "en", "c", "posix":
+		return En, true
+	case "de":
+		return De, true
+	case "es":
+		return Es, true
+	case "fr":
+		return Fr, true
+	}
+	return "", false
+}
+
+// Detect returns the locale from the configured name, falling back to
+// LC_ALL, LC_MESSAGES and LANG, and then to English
+func Detect(configured string) Locale {
+	return detect(configured, os.Getenv)
+}
+
+func detect(configured string, getenv func(string) string) Locale {
+	if configured != "" {
+		if l, ok := ParseLocale(configured); ok {
+			return l
+		}
+	}
+	// The first variable that is set wins, even when it names a language
+	// without a catalog, as it does for other programs
+	for _, name := range []string{"LC_ALL", "LC_MESSAGES", "LANG"} {
+		if v := getenv(name); v != "" {
+			if l, ok := ParseLocale(v); ok {
+				return l
+			}
+			return En
+		}
+	}
+	return En
+}
+
+// Key identifies a message in the catalogs
+type Key string
+
+// Message keys
+const (
+	NoSessions      Key = "no_sessions"
+	SessionHeader   Key = "session_header"
+	SessionAttached Key = "session_attached"
+	SessionExited   Key = "session_exited"
+	AgentsLabel     Key = "agents_label"
+	PanesLabel      Key = "panes_label"
+	AllKilled       Key = "all_killed"
+	SessionKilled   Key = "session_killed"
+	SessionCreated  Key = "session_created"
+	LayoutLabel     Key = "layout_label"
+	WorkDirLabel    Key = "workdir_label"
+	AttachHint      Key = "attach_hint"
+	TestSummary     Key = "test_summary"
+	UpdateFound     Key = "update_found"
+	UpToDate        Key = "up_to_date"
+	Updated         Key = "updated"
+	BinaryLabel     Key = "binary_label"
+	PluginLabel     Key = "plugin_label"
+	PluginReloaded  Key = "plugin_reloaded"
+	PluginSkipped   Key = "plugin_skipped"
+	PluginOK        Key = "plugin_ok"
+	PluginStale     Key = "plugin_stale"
+	PluginTampered  Key = "plugin_tampered"
+	PluginUnknown   Key = "plugin_unknown"
+)
+
+var catalogs = map[Locale]map[Key]string{
+	En: {
+		NoSessions:      "No NZM sessions found.",
+		SessionHeader:   "Session: %s%s",
+		SessionAttached: " (attached)",
+		SessionExited:   " (exited)",
+		AgentsLabel:     "  Agents:",
+		PanesLabel:      "  Panes:",
+		AllKilled:       "All sessions killed.",
+		SessionKilled:   "Session %q killed.",
+		SessionCreated:  "Created session %q with %d panes (detached)",
+		LayoutLabel:     "  Layout: %s",
+		WorkDirLabel:    "  WorkDir: %s",
+		AttachHint:      "Attach with: nzm attach %s",
+		TestSummary:     "%d passed, %d failed",
+		UpdateFound:     "nzm %s is available (installed: %s)",
+		UpToDate:        "nzm %s is up to date",
+		Updated:         "Updated nzm %s -> %s",
+		BinaryLabel:     "  Binary: %s",
+		PluginLabel:     "  Plugin: %s",
+		PluginReloaded:  "  Reloaded the plugin in %s",
+		PluginSkipped:   "  Not reloaded in %s: %s",
+		PluginOK:        "%s: plugin %s is current",
+		PluginStale:     "%s: plugin %s is stale",
+		PluginTampered:  "%s: plugin %s does not match its release",
+		PluginUnknown:   "%s: plugin could not be checked",
+	},
+	De: {
+		NoSessions:      "Keine NZM-Sitzungen gefunden.",
+		SessionHeader:   "Sitzung: %s%s",
+		SessionAttached: " (verbunden)",
+		SessionExited:   " (beendet)",
+		AgentsLabel:     "  Agenten:",
+		PanesLabel:      "  Bereiche:",
+		AllKilled:       "Alle Sitzungen beendet.",
+		SessionKilled:   "Sitzung %q beendet.",
+		SessionCreated:  "Sitzung %q mit %d Bereichen erstellt (im Hintergrund)",
+		LayoutLabel:     "  Layout: %s",
+		WorkDirLabel:    "  Arbeitsverzeichnis: %s",
+		AttachHint:      "Verbinden mit: nzm attach %s",
+		TestSummary:     "%d bestanden, %d fehlgeschlagen",
+		UpdateFound:     "nzm %s ist verfügbar (installiert: %s)",
+		UpToDate:        "nzm %s ist aktuell",
+		Updated:         "nzm %s -> %s aktualisiert",
+		BinaryLabel:     "  Programm: %s",
+		PluginLabel:     "  Plugin: %s",
+		PluginReloaded:  "  Plugin in %s neu geladen",
+		PluginSkipped:   "  Nicht neu geladen in %s: %s",
+		PluginOK:        "%s: Plugin %s ist aktuell",
+		PluginStale:     "%s: Plugin %s ist veraltet",
+		PluginTampered:  "%s: Plugin %s entspricht nicht seinem Release",
+		PluginUnknown:   "%s: Plugin konnte nicht geprüft werden",
+	},
+	Es: {
+		NoSessions:      "No se encontraron sesiones de NZM.",
+		SessionHeader:   "Sesión: %s%s",
+		SessionAttached: " (conectada)",
+		SessionExited:   " (terminada)",
+		AgentsLabel:     "  Agentes:",
+		PanesLabel:      "  Paneles:",
+		AllKilled:       "Todas las sesiones cerradas.",
+		SessionKilled:   "Sesión %q cerrada.",
+		SessionCreated:  "Sesión %q creada con %d paneles (en segundo plano)",
+		LayoutLabel:     "  Diseño: %s",
+		WorkDirLabel:    "  Directorio de trabajo: %s",
+		AttachHint:      "Conéctese con: nzm attach %s",
+		TestSummary:     "%d correctos, %d fallidos",
+		UpdateFound:     "nzm %s está disponible (instalado: %s)",
+		UpToDate:        "nzm %s está actualizado",
+		Updated:         "nzm actualizado %s -> %s",
+		BinaryLabel:     "  Binario: %s",
+		PluginLabel:     "  Plugin: %s",
+		PluginReloaded:  "  Plugin recargado en %s",
+		PluginSkipped:   "  No recargado en %s: %s",
+		PluginOK:        "%s: el plugin %s está al día",
+		PluginStale:     "%s: el plugin %s está desactualizado",
+		PluginTampered:  "%s: el plugin %s no coincide con su versión publicada",
+		PluginUnknown:   "%s: no se pudo comprobar el plugin",
+	},
+	Fr: {
+		NoSessions:      "Aucune session NZM trouvée.",
+		SessionHeader:   "Session : %s%s",
+		SessionAttached: " (attachée)",
+		SessionExited:   " (terminée)",
+		AgentsLabel:     "  Agents :",
+		PanesLabel:      "  Volets :",
+		AllKilled:       "Toutes les sessions ont été fermées.",
+		SessionKilled:   "Session %q fermée.",
+		SessionCreated:  "Session %q créée avec %d volets (en arrière-plan)",
+		LayoutLabel:     "  Disposition : %s",
+		WorkDirLabel:    "  Répertoire de travail : %s",
+		AttachHint:      "Pour s'y attacher : nzm attach %s",
+		TestSummary:     "%d réussis, %d échoués",
+		UpdateFound:     "nzm %s est disponible (installé : %s)",
+		UpToDate:        "nzm %s est à jour",
+		Updated:         "nzm mis à jour %s -> %s",
+		BinaryLabel:     "  Binaire : %s",
+		PluginLabel:     "  Plugin : %s",
+		PluginReloaded:  "  Plugin rechargé dans %s",
+		PluginSkipped:   "  Non rechargé dans %s : %s",
+		PluginOK:        "%s : le plugin %s est à jour",
+		PluginStale:     "%s : le plugin %s est obsolète",
+		PluginTampered:  "%s : le plugin %s ne correspond pas à sa version publiée",
+		PluginUnknown:   "%s : le plugin n'a pas pu être vérifié",
+	},
+}
+
+var current atomic.Value
+
+// SetLocale sets the locale used by T
+func SetLocale(l Locale) {
+	current.Store(l)
+}
+
+// Current returns the locale used by T, English until SetLocale is called
+func Current() Locale {
+	if l, ok := current.Load().(Locale); ok {
+		return l
+	}
+	return En
+}
+
+// T formats the message for key in the current locale
+func T(key Key, args ...any) string {
+	return Text(Current(), key, args...)
+}
+
+// Text formats the message for key in locale, falling back to English
+// when the locale has no translation for it
+func Text(locale Locale, key Key, args ...any) string {
+	format, ok := catalogs[locale][key]
+	if !ok {
+		format, ok = catalogs[En][key]
+	}
+	if !ok {
+		return string(key)
+	}
+	if len(args) == 0 {
+		return format
+	}
+	return fmt.Sprintf(format, args...)
+}
@@ -0,0 +1,73 @@
+package i18n
+
+import "testing"
+
+func TestParseLocale(t *testing.T) {
+	tests := []struct {
+		name string
+		want Locale
+		ok   bool
+	}{
+		{"de_DE.UTF-8", De, true},
+		{"fr", Fr, true},
+		{"es-MX", Es, true},
+		{"C.UTF-8", En, true},
+		{"POSIX", En, true},
+		{"xx_YY", "", false},
+	}
+
+	for _, tt := range tests {
+		got, ok := ParseLocale(tt.name)
+		if got != tt.want || ok != tt.ok {
+			t.Errorf("ParseLocale(%q) = %q, %v; want %q, %v", tt.name, got, ok, tt.want, tt.ok)
+		}
+	}
+}
+
+func TestDetect(t *testing.T) {
+	env := func(vars map[string]string) func(string) string {
+		return func(name string) string { return vars[name] }
+	}
+
+	tests := []struct {
+		name       string
+		configured string
+		vars       map[string]string
+		want       Locale
+	}{
+		{"config wins", "fr", map[string]string{"LANG": "de_DE.UTF-8"}, Fr},
+		{"unknown config falls back to env", "tlh", map[string]string{"LANG": "de_DE.UTF-8"}, De},
+		{"LC_ALL before LANG", "", map[string]string{"LC_ALL": "es_ES.UTF-8", "LANG": "de_DE.UTF-8"}, Es},
+		{"LC_MESSAGES before LANG", "", map[string]string{"LC_MESSAGES": "fr_FR", "LANG": "de_DE"}, Fr},
+		{"first set variable wins", "", map[string]string{"LC_ALL": "ja_JP.UTF-8", "LANG": "de_DE"}, En},
+		{"nothing set", "", nil, En},
+	}
+
+	for _, tt := range tests {
+		if got := detect(tt.configured, env(tt.vars)); got != tt.want {
+			t.Errorf("%s: expected %q, got %q", tt.name, tt.want, got)
+		}
+	}
+}
+
+func TestText(t *testing.T) {
+	if got := Text(En, SessionKilled, "proj"); got != `Session "proj" killed.` {
+		t.Errorf("unexpected English text: %q", got)
+	}
+	if got := Text(De, SessionKilled, "proj"); got != `Sitzung "proj" beendet.` {
+		t.Errorf("unexpected German text: %q", got)
+	}
+	if got := Text(Locale("ja"), AllKilled); got != "All sessions killed." {
+		t.Errorf("expected English fallback, got %q", got)
+	}
+}
+
+func TestCatalogsAreComplete(t *testing.T) {
+	for locale, catalog := range catalogs {
+		for key := range catalogs[En] {
+			if _, ok := catalog[key]; !ok {
+				t.Errorf("locale %q is missing %q", locale, key)
+			}
+		}
+	}
+}
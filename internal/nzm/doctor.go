@@ -0,0 +1,153 @@
+package nzm
+
+import (
+	"context"
+	"fmt"
+
+	"github.com/Dicklesworthstone/ntm/internal/updater"
+	"github.com/Dicklesworthstone/ntm/internal/zellij"
+)
+
+// DoctorClient defines the operations doctor needs
+type DoctorClient interface {
+	ListSessions(ctx context.Context) ([]zellij.Session, error)
+	GetVersion(ctx context.Context, session string) (*zellij.PluginVersion, error)
+}
+
+// DoctorOptions configures the plugin checks
+type DoctorOptions struct {
+	Session    string            // Session to check (default: all running sessions)
+	CLIVersion string            // Version of this CLI, "dev" for local builds
+	Manifest   *updater.Manifest // Release the plugin should match; nil skips the hash checks
+}
+
+// Plugin check statuses
+const (
+	PluginOK       = "ok"
+	PluginStale    = "stale"    // Another build than the CLI's release
+	PluginTampered = "tampered" // Claims the release's sources, but its file differs
+	PluginUnknown  = "unknown"  // Could not be checked
+)
+
+// PluginCheck is the outcome of checking one session's plugin
+type PluginCheck struct {
+	Session  string                `json:"session"`
+	Status   string                `json:"status"`
+	Problems []string              `json:"problems,omitempty"`
+	Plugin   *zellij.PluginVersion `json:"plugin,omitempty"`
+}
+
+// DoctorResult holds the checks of every session
+type DoctorResult struct {
+	CLIVersion string            `json:"cli_version"`
+	Protocol   int               `json:"protocol"`
+	Manifest   *updater.Manifest `json:"manifest,omitempty"`
+	Checks     []PluginCheck     `json:"checks"`
+}
+
+// Failed counts the checks that did not pass
+func (r *DoctorResult) Failed() int {
+	n := 0
+	for _, c := range r.Checks {
+		if c.Status != PluginOK {
+			n++
+		}
+	}
+	return n
+}
+
+// Doctor checks that sessions run the plugin build the CLI expects
+type Doctor struct {
+	client DoctorClient
+}
+
+// NewDoctor creates a new Doctor
+func NewDoctor(client DoctorClient) *Doctor {
+	return &Doctor{client: client}
+}
+
+// Check asks each session's plugin which build it is and compares it
+// with the CLI and the release manifest
+func (d *Doctor) Check(ctx context.Context, opts DoctorOptions) (*DoctorResult, error) {
+	sessions, err := d.client.ListSessions(ctx)
+	if err != nil {
+		return nil, fmt.Errorf("failed to list sessions: %w", err)
+	}
+
+	result := &DoctorResult{
+		CLIVersion: opts.CLIVersion,
+		Protocol:   zellij.PluginProtocol,
+		Manifest:   opts.Manifest,
+		Checks:     []PluginCheck{},
+	}
+	found := false
+	for _, sess := range sessions {
+		if opts.Session != "" && sess.Name != opts.Session {
+			continue
+		}
+		found = true
+		if sess.Exited {
+			continue
+		}
+		result.Checks = append(result.Checks, d.checkSession(ctx, sess.Name, opts))
+	}
+	if opts.Session != "" && !found {
+		return nil, Errorf(CodeNotFound, "session %q not found", opts.Session)
+	}
+	return result, nil
+}
+
+func (d *Doctor) checkSession(ctx context.Context, session string, opts DoctorOptions) PluginCheck {
+	check := PluginCheck{Session: session, Status: PluginOK}
+	v, err := d.client.GetVersion(ctx, session)
+	if err != nil {
+		check.Status = PluginUnknown
+		if ErrorCode(err) == "unknown_action" {
+			check.Status = PluginStale
+			check.Problems = append(check.Problems, "plugin predates get_version")
+		} else {
+			check.Problems = append(check.Problems, err.Error())
+		}
+		return check
+	}
+	check.Plugin = v
+
+	// worsen only raises the status: tampered beats stale beats unknown
+	rank := map[string]int{PluginOK: 0, PluginUnknown: 1, PluginStale: 2, PluginTampered: 3}
+	worsen := func(status, format string, args ...any) {
+		if rank[status] > rank[check.Status] {
+			check.Status = status
+		}
+		check.Problems = append(check.Problems, fmt.Sprintf(format, args...))
+	}
+
+	if v.Protocol != zellij.PluginProtocol {
+		worsen(PluginStale, "plugin speaks protocol %d, the CLI %d", v.Protocol, zellij.PluginProtocol)
+	}
+
+	m := opts.Manifest
+	if m == nil {
+		if opts.CLIVersion != "dev" && v.Version != opts.CLIVersion {
+			worsen(PluginStale, "plugin is version %s, the CLI %s", v.Version, opts.CLIVersion)
+		}
+		return check
+	}
+
+	sameBuild := v.BuildSHA256 == m.Plugin.BuildSHA256
+	if !sameBuild {
+		worsen(PluginStale, "plugin %s was not built from release %s", v.Version, m.Version)
+	}
+	switch {
+	case v.PluginPath == "":
+		worsen(PluginUnknown, "plugin was loaded by alias, so its file is not checked")
+	case v.FileSHA256Error != "":
+		worsen(PluginUnknown, "plugin file could not be hashed: %s", v.FileSHA256Error)
+	case v.FileSHA256 == "":
+		worsen(PluginUnknown, "plugin file is still being hashed")
+	case v.FileSHA256 != m.Plugin.SHA256 && sameBuild:
+		worsen(PluginTampered, "plugin file %s does not match release %s", v.PluginPath, m.Version)
+	case v.FileSHA256 != m.Plugin.SHA256:
+		worsen(PluginStale, "plugin file %s is not the one of release %s", v.PluginPath, m.Version)
+	}
+	return check
+}
@@ -0,0 +1,140 @@
+package nzm
+
+import (
+	"context"
+	"strings"
+	"testing"
+
+	"github.com/Dicklesworthstone/ntm/internal/updater"
+	"github.com/Dicklesworthstone/ntm/internal/zellij"
+)
+
+// doctorMockClient for doctor tests
+type doctorMockClient struct {
+	sessions []zellij.Session
+	versions map[string]*zellij.PluginVersion
+	errs     map[string]error
+}
+
+func (m *doctorMockClient) ListSessions(ctx context.Context) ([]zellij.Session, error) {
+	return m.sessions, nil
+}
+
+func (m *doctorMockClient) GetVersion(ctx context.Context, session string) (*zellij.PluginVersion, error) {
+	if err := m.errs[session]; err != nil {
+		return nil, err
+	}
+	return m.versions[session], nil
+}
+
+func TestDoctor_Check(t *testing.T) {
+	manifest := &updater.Manifest{
+		Version:  "1.2.0",
+		Protocol: zellij.PluginProtocol,
+		Plugin:   updater.ManifestPlugin{File: "nzm-agent.wasm", SHA256: "file", BuildSHA256: "build"},
+	}
+	released := zellij.PluginVersion{
+		Version:     "1.2.0",
+		Protocol:    zellij.PluginProtocol,
+		BuildSHA256: "build",
+		PluginPath:  "/home/me/.local/share/nzm/nzm-agent.wasm",
+		FileSHA256:  "file",
+	}
+
+	tests := []struct {
+		name     string
+		version  func(v *zellij.PluginVersion)
+		err      error
+		manifest *updater.Manifest
+		want     string
+		problem  string
+	}{
+		{name: "release build", manifest: manifest, want: PluginOK},
+		{
+			name:     "older build",
+			version:  func(v *zellij.PluginVersion) { v.Version, v.BuildSHA256, v.FileSHA256 = "1.1.0", "old", "oldfile" },
+			manifest: manifest,
+			want:     PluginStale,
+			problem:  "not built from release 1.2.0",
+		},
+		{
+			name:     "patched file",
+			version:  func(v *zellij.PluginVersion) { v.FileSHA256 = "patched" },
+			manifest: manifest,
+			want:     PluginTampered,
+			problem:  "does not match release",
+		},
+		{
+			name:     "other protocol",
+			version:  func(v *zellij.PluginVersion) { v.Protocol = zellij.PluginProtocol + 1 },
+			manifest: manifest,
+			want:     PluginStale,
+			problem:  "protocol",
+		},
+		{
+			name:     "loaded by alias",
+			version:  func(v *zellij.PluginVersion) { v.PluginPath, v.FileSHA256 = "", "" },
+			manifest: manifest,
+			want:     PluginUnknown,
+			problem:  "alias",
+		},
+		{
+			name:    "no manifest, other version",
+			version: func(v *zellij.PluginVersion) { v.Version = "1.1.0" },
+			want:    PluginStale,
+			problem: "version 1.1.0, the CLI 1.2.0",
+		},
+		{
+			name:    "plugin without get_version",
+			err:     &zellij.PluginError{Code: "unknown_action", Message: "unknown action: get_version"},
+			want:    PluginStale,
+			problem: "predates get_version",
+		},
+		{
+			name: "unreachable plugin",
+			err:  zellij.ErrPipeClosed,
+			want: PluginUnknown,
+		},
+	}
+
+	for _, tt := range tests {
+		t.Run(tt.name, func(t *testing.T) {
+			v := released
+			if tt.version != nil {
+				tt.version(&v)
+			}
+			mock := &doctorMockClient{
+				sessions: []zellij.Session{{Name: "proj"}, {Name: "old", Exited: true}},
+				versions: map[string]*zellij.PluginVersion{"proj": &v},
+				errs:     map[string]error{"proj": tt.err},
+			}
+
+			result, err := NewDoctor(mock).Check(context.Background(), DoctorOptions{CLIVersion: "1.2.0", Manifest: tt.manifest})
+			if err != nil {
+				t.Fatalf("unexpected error: %v", err)
+			}
+			if len(result.Checks) != 1 {
+				t.Fatalf("expected exited sessions to be skipped, got %d checks", len(result.Checks))
+			}
+			check := result.Checks[0]
+			if check.Status != tt.want {
+				t.Errorf("expected %s, got %s (%v)", tt.want, check.Status, check.Problems)
+			}
+			if tt.problem != "" && !strings.Contains(strings.Join(check.Problems, "\n"), tt.problem) {
+				t.Errorf("expected a problem mentioning %q, got %v", tt.problem, check.Problems)
+			}
+			if (result.Failed() == 0) != (tt.want == PluginOK) {
+				t.Errorf("unexpected failure count %d", result.Failed())
+			}
+		})
+	}
+}
+
+func TestDoctor_SessionNotFound(t *testing.T) {
+	mock := &doctorMockClient{sessions: []zellij.Session{{Name: "proj"}}}
+
+	_, err := NewDoctor(mock).Check(context.Background(), DoctorOptions{Session: "nope", CLIVersion: "dev"})
+	if ErrorCode(err) != CodeNotFound {
+		t.Errorf("expected a not_found error, got %v", err)
+	}
+}
@@ -0,0 +1,114 @@
+package nzm
+
+import (
+	"context"
+	"errors"
+	"fmt"
+	"os/exec"
+
+	"github.com/Dicklesworthstone/ntm/internal/zellij"
+)
+
+// Exit codes of the nzm CLI, one per error class. Scripts branch on
+// them, so a code's meaning never changes once released.
+const (
+	ExitFailure     = 1 // Any other failure
+	ExitUsage       = 2 // Invalid arguments, flags or params
+	ExitNotFound    = 3 // A session, pane or task does not exist
+	ExitTimeout     = 4 // A wait or command ran out of time
+	ExitPermission  = 5 // A route hook or guard refused the request
+	ExitBusy        = 6 // The target is paused, held, full, stale or starting
+	ExitUnavailable = 7 // The plugin or session could not be reached
+)
+
+// Error codes the CLI gives its own failures; the rest come from the
+// plugin
+const (
+	CodeUsage       = "invalid_params"
+	CodeNotFound    = "not_found"
+	CodeTimeout     = "timeout"
+	CodeConflict    = "conflict"
+	CodeUnavailable = "unavailable"
+	CodeFailed      = "failed"
+)
+
+// exitCodes maps error codes to exit codes; unknown codes exit with
+// ExitFailure
+var exitCodes = map[string]int{
+	"invalid_params": ExitUsage,
+	"unknown_action": ExitUsage,
+	"not_found":      ExitNotFound,
+	"timeout":        ExitTimeout,
+	"denied":         ExitPermission,
+	"blocked":        ExitPermission,
+	"paused":         ExitBusy,
+	"held":           ExitBusy,
+	"queue_full":     ExitBusy,
+	"stale_target":   ExitBusy,
+	"stale_revision": ExitBusy,
+	"starting":       ExitBusy,
+	"conflict":       ExitBusy,
+	"shutting_down":  ExitUnavailable,
+	"unavailable":    ExitUnavailable,
+}
+
+// codedError is a CLI-side failure with an error code
+type codedError struct {
+	code string
+	err  error
+}
+
+func (e *codedError) Error() string {
+	return e.err.Error()
+}
+
+func (e *codedError) Unwrap() error {
+	return e.err
+}
+
+// ErrorCode returns the code given to the error
+func (e *codedError) ErrorCode() string {
+	return e.code
+}
+
+// Errorf formats an error that carries code
+func Errorf(code, format string, args ...any) error {
+	return &codedError{code: code, err: fmt.Errorf(format, args...)}
+}
+
+// WithCode attaches code to err, keeping its message; nil stays nil
+func WithCode(code string, err error) error {
+	if err == nil {
+		return nil
+	}
+	return &codedError{code: code, err: err}
+}
+
+// ErrorCode returns the stable code of err: the first code found in its
+// chain, "timeout" for deadlines, "unavailable" when zellij or the plugin
+// could not be reached, and "failed" otherwise
+func ErrorCode(err error) string {
+	var coded interface{ ErrorCode() string }
+	switch {
+	case err == nil:
+		return ""
+	case errors.As(err, &coded):
+		return coded.ErrorCode()
+	case errors.Is(err, ErrWaitTimeout), errors.Is(err, context.DeadlineExceeded):
+		return CodeTimeout
+	case errors.Is(err, zellij.ErrPipeClosed), errors.Is(err, exec.ErrNotFound):
+		return CodeUnavailable
+	}
+	return CodeFailed
+}
+
+// ExitCode returns the CLI exit code for err, 0 for nil
+func ExitCode(err error) int {
+	if err == nil {
+		return 0
+	}
+	if code, ok := exitCodes[ErrorCode(err)]; ok {
+		return code
+	}
+	return ExitFailure
+}
@@ -0,0 +1,51 @@
+package nzm
+
+import (
+	"context"
+	"errors"
+	"fmt"
+	"testing"
+
+	"github.com/Dicklesworthstone/ntm/internal/zellij"
+)
+
+func TestExitCode(t *testing.T) {
+	tests := []struct {
+		name string
+		err  error
+		code string
+		exit int
+	}{
+		{"nil", nil, "", 0},
+		{"plain", errors.New("boom"), CodeFailed, ExitFailure},
+		{"usage", SendOptions{}.Validate(), CodeUsage, ExitUsage},
+		{"not found", Errorf(CodeNotFound, "session %q not found", "x"), CodeNotFound, ExitNotFound},
+		{"plugin code", &zellij.PluginError{Code: "blocked", Message: "blocked by guard"}, "blocked", ExitPermission},
+		{"wrapped plugin code", fmt.Errorf("send_keys: %w", &zellij.PluginError{Code: "held"}), "held", ExitBusy},
+		{"plugin without code", &zellij.PluginError{Message: "old plugin"}, CodeFailed, ExitFailure},
+		{"unknown plugin code", &zellij.PluginError{Code: "brand_new"}, "brand_new", ExitFailure},
+		{"deadline", fmt.Errorf("list: %w", context.DeadlineExceeded), CodeTimeout, ExitTimeout},
+		{"wait", fmt.Errorf("%w after 5s", ErrWaitTimeout), CodeTimeout, ExitTimeout},
+		{"pipe closed", zellij.ErrPipeClosed, CodeUnavailable, ExitUnavailable},
+	}
+
+	for _, tt := range tests {
+		if got := ErrorCode(tt.err); got != tt.code {
+			t.Errorf("%s: expected code %q, got %q", tt.name, tt.code, got)
+		}
+		if got := ExitCode(tt.err); got != tt.exit {
+			t.Errorf("%s: expected exit %d, got %d", tt.name, tt.exit, got)
+		}
+	}
+}
+
+func TestWithCode_KeepsMessage(t *testing.T) {
+	base := errors.New("bad flag")
+	err := WithCode(CodeUsage, base)
+	if err.Error() != "bad flag" || !errors.Is(err, base) {
+		t.Errorf("expected the wrapped error, got %v", err)
+	}
+	if WithCode(CodeUsage, nil) != nil {
+		t.Error("expected nil to stay nil")
+	}
+}
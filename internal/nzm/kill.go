@@ -23,7 +23,7 @@ type KillOptions struct {
 // Validate checks if kill options are valid
 func (o KillOptions) Validate() error {
 	if o.Session == "" {
-		return fmt.Errorf("session name is required")
+		return Errorf(CodeUsage, "session name is required")
 	}
 	return nil
 }
@@ -51,7 +51,7 @@ func (k *Killer) Kill(ctx context.Context, opts KillOptions) error {
 			return fmt.Errorf("failed to check session: %w", err)
 		}
 		if !exists {
-			return fmt.Errorf("session %q not found", opts.Session)
+			return Errorf(CodeNotFound, "session %q not found", opts.Session)
 		}
 	}
 
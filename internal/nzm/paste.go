@@ -0,0 +1,81 @@
+package nzm
+
+import (
+	"context"
+	"fmt"
+	"strings"
+
+	"github.com/Dicklesworthstone/ntm/internal/zellij"
+)
+
+// DefaultPasteChunkBytes is how much of a paste the plugin writes per pacing tick
+const DefaultPasteChunkBytes = 4096
+
+// PasteClient defines the plugin operations for pasting
+type PasteClient interface {
+	ListPanes(ctx context.Context, session string) ([]zellij.PaneInfo, error)
+	SendPaste(ctx context.Context, session string, paneID uint32, text string, chunkBytes int, enter bool) error
+}
+
+// PasteOptions configures the paste operation
+type PasteOptions struct {
+	Session    string // Session name
+	Target     string // Target pane (name, type, or full pane name)
+	Text       string // Text to paste
+	ChunkBytes int    // Bytes written per pacing tick (0: DefaultPasteChunkBytes)
+	Enter      bool   // Press enter after the paste
+}
+
+// Validate checks if paste options are valid
+func (o PasteOptions) Validate() error {
+	if o.Session == "" {
+		return Errorf(CodeUsage, "session name is required")
+	}
+	if o.Target == "" {
+		return Errorf(CodeUsage, "target pane is required")
+	}
+	if o.Text == "" {
+		return Errorf(CodeUsage, "nothing to paste: the clipboard is empty")
+	}
+	if o.ChunkBytes < 0 {
+		return Errorf(CodeUsage, "chunk size must not be negative")
+	}
+	return nil
+}
+
+// Paster pastes text into panes
+type Paster struct {
+	client PasteClient
+}
+
+// NewPaster creates a new Paster
+func NewPaster(client PasteClient) *Paster {
+	return &Paster{client: client}
+}
+
+// Paste sends text to a pane as one bracketed paste and returns the pane.
+// Windows line endings are turned into newlines first.
+func (p *Paster) Paste(ctx context.Context, opts PasteOptions) (*zellij.PaneInfo, error) {
+	if err := opts.Validate(); err != nil {
+		return nil, err
+	}
+
+	panes, err := p.client.ListPanes(ctx, opts.Session)
+	if err != nil {
+		return nil, fmt.Errorf("failed to list panes: %w", err)
+	}
+	pane, err := findPane(panes, opts.Session, opts.Target)
+	if err != nil {
+		return nil, err
+	}
+
+	chunkBytes := opts.ChunkBytes
+	if chunkBytes == 0 {
+		chunkBytes = DefaultPasteChunkBytes
+	}
+	text := strings.ReplaceAll(opts.Text, "\r\n", "\n")
+	if err := p.client.SendPaste(ctx, opts.Session, pane.ID, text, chunkBytes, opts.Enter); err != nil {
+		return nil, err
+	}
+	return pane, nil
+}
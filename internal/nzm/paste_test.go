@@ -0,0 +1,69 @@
+package nzm
+
+import (
+	"context"
+	"testing"
+
+	"github.com/Dicklesworthstone/ntm/internal/zellij"
+)
+
+// mockPasteClient records pastes on top of mockPluginClient
+type mockPasteClient struct {
+	mockPluginClient
+	pastedChunk int
+}
+
+func (m *mockPasteClient) SendPaste(ctx context.Context, session string, paneID uint32, text string, chunkBytes int, enter bool) error {
+	m.sentPaneID = paneID
+	m.sentText = text
+	m.sentEnter = enter
+	m.pastedChunk = chunkBytes
+	return nil
+}
+
+func TestPasteOptions_Validate(t *testing.T) {
+	tests := []struct {
+		name    string
+		opts    PasteOptions
+		wantErr bool
+	}{
+		{"valid", PasteOptions{Session: "proj", Target: "cc_1", Text: "x"}, false},
+		{"empty session", PasteOptions{Target: "cc_1", Text: "x"}, true},
+		{"empty target", PasteOptions{Session: "proj", Text: "x"}, true},
+		{"empty clipboard", PasteOptions{Session: "proj", Target: "cc_1"}, true},
+		{"negative chunk", PasteOptions{Session: "proj", Target: "cc_1", Text: "x", ChunkBytes: -1}, true},
+	}
+
+	for _, tt := range tests {
+		if err := tt.opts.Validate(); (err != nil) != tt.wantErr {
+			t.Errorf("%s: Validate() error = %v, wantErr %v", tt.name, err, tt.wantErr)
+		}
+	}
+}
+
+func TestPaster_Paste(t *testing.T) {
+	client := &mockPasteClient{
+		mockPluginClient: mockPluginClient{
+			panes: []zellij.PaneInfo{{ID: 1, Title: "proj__cc_1"}, {ID: 2, Title: "proj__cc_2"}},
+		},
+	}
+
+	pane, err := NewPaster(client).Paste(context.Background(), PasteOptions{
+		Session: "proj",
+		Target:  "cc_2",
+		Text:    "line 1\r\nline 2\r\n",
+	})
+	if err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+
+	if pane.ID != 2 || client.sentPaneID != 2 {
+		t.Errorf("expected pane 2, got %d", client.sentPaneID)
+	}
+	if client.sentText != "line 1\nline 2\n" {
+		t.Errorf("expected Windows line endings converted, got %q", client.sentText)
+	}
+	if client.pastedChunk != DefaultPasteChunkBytes {
+		t.Errorf("expected default chunk size, got %d", client.pastedChunk)
+	}
+}
@@ -0,0 +1,295 @@
+package nzm
+
+import (
+	"context"
+	"encoding/json"
+	"fmt"
+	"io"
+	"sort"
+	"strconv"
+	"strings"
+
+	"github.com/Dicklesworthstone/ntm/internal/zellij"
+)
+
+// REPLClient defines the plugin operations the REPL uses
+type REPLClient interface {
+	PluginClient
+	SendPluginCommand(ctx context.Context, session string, req zellij.Request) (*zellij.Response, error)
+}
+
+// replCommands are the REPL's commands with their usage, for help and completion
+var replCommands = []struct {
+	name  string
+	usage string
+}{
+	{"ls", "ls                      list panes"},
+	{"send", "send TARGET TEXT...     send text to a pane and press Enter"},
+	{"interrupt", "interrupt TARGET        send Ctrl+C to a pane"},
+	{"tail", "tail TARGET [LINES]     show the last lines of a pane (default 20)"},
+	{"raw", "raw ACTION [JSON]       send any plugin action with JSON params"},
+	{"help", "help                    show this help"},
+	{"exit", "exit                    leave the REPL"},
+}
+
+// defaultTailLines is how many lines tail shows without a count
+const defaultTailLines = 20
+
+// REPL runs interactive commands against one session.
+// Targets are resolved as `nzm send` resolves them.
+type REPL struct {
+	client  REPLClient
+	session string
+	out     io.Writer
+	panes   []zellij.PaneInfo // last listing, for targets and completion
+}
+
+// NewREPL creates a REPL for session that writes its output to out
+func NewREPL(client REPLClient, session string, out io.Writer) *REPL {
+	return &REPL{client: client, session: session, out: out}
+}
+
+// Execute runs one command line. It reports whether the REPL should exit.
+func (r *REPL) Execute(ctx context.Context, line string) (bool, error) {
+	words, err := splitWords(line)
+	if err != nil {
+		return false, err
+	}
+	if len(words) == 0 {
+		return false, nil
+	}
+
+	cmd, args := words[0], words[1:]
+	switch cmd {
+	case "exit", "quit":
+		return true, nil
+	case "help", "?":
+		for _, c := range replCommands {
+			fmt.Fprintf(r.out, "  %s\n", c.usage)
+		}
+		return false, nil
+	case "ls":
+		return false, r.list(ctx)
+	case "send":
+		if len(args) < 2 {
+			return false, fmt.Errorf("usage: send TARGET TEXT...")
+		}
+		pane, err := r.target(ctx, args[0])
+		if err != nil {
+			return false, err
+		}
+		return false, r.client.SendKeys(ctx, r.session, pane.ID, strings.Join(args[1:], " "), true)
+	case "interrupt", "int":
+		if len(args) != 1 {
+			return false, fmt.Errorf("usage: interrupt TARGET")
+		}
+		pane, err := r.target(ctx, args[0])
+		if err != nil {
+			return false, err
+		}
+		return false, r.client.SendInterrupt(ctx, r.session, pane.ID)
+	case "tail":
+		if len(args) < 1 || len(args) > 2 {
+			return false, fmt.Errorf("usage: tail TARGET [LINES]")
+		}
+		lines := defaultTailLines
+		if len(args) == 2 {
+			n, err := strconv.Atoi(args[1])
+			if err != nil || n <= 0 {
+				return false, fmt.Errorf("LINES must be a positive number, got %q", args[1])
+			}
+			lines = n
+		}
+		return false, r.tail(ctx, args[0], lines)
+	case "raw":
+		if len(args) < 1 || len(args) > 2 {
+			return false, fmt.Errorf("usage: raw ACTION [JSON]")
+		}
+		var params map[string]any
+		if len(args) == 2 {
+			if err := json.Unmarshal([]byte(args[1]), &params); err != nil {
+				return false, fmt.Errorf("params must be a JSON object: %w", err)
+			}
+		}
+		return false, r.raw(ctx, args[0], params)
+	default:
+		return false, fmt.Errorf("unknown command %q (try help)", cmd)
+	}
+}
+
+// list prints the session's panes and remembers them for completion
+func (r *REPL) list(ctx context.Context) error {
+	panes, err := r.refresh(ctx)
+	if err != nil {
+		return err
+	}
+	for _, pane := range panes {
+		focus := ""
+		if pane.IsFocused {
+			focus = " *"
+		}
+		fmt.Fprintf(r.out, "[%d] %s%s\n", pane.ID, pane.Title, focus)
+	}
+	return nil
+}
+
+// refresh lists the session's panes
+func (r *REPL) refresh(ctx context.Context) ([]zellij.PaneInfo, error) {
+	panes, err := r.client.ListPanes(ctx, r.session)
+	if err != nil {
+		return nil, fmt.Errorf("failed to list panes: %w", err)
+	}
+	r.panes = panes
+	return panes, nil
+}
+
+// target resolves a target against a fresh pane listing
+func (r *REPL) target(ctx context.Context, target string) (*zellij.PaneInfo, error) {
+	panes, err := r.refresh(ctx)
+	if err != nil {
+		return nil, err
+	}
+	return findPane(panes, r.session, target)
+}
+
+// tail prints the last lines captured from a pane
+func (r *REPL) tail(ctx context.Context, target string, lines int) error {
+	pane, err := r.target(ctx, target)
+	if err != nil {
+		return err
+	}
+	resp, err := r.client.SendPluginCommand(ctx, r.session, zellij.Request{
+		Action: "dump_scrollback",
+		Params: map[string]any{
+			"selector": pane.ID,
+			"lines":    lines,
+		},
+	})
+	if err != nil {
+		return err
+	}
+	if !resp.Success {
+		return resp.Err()
+	}
+	if content, _ := resp.Data["content"].(string); content != "" {
+		fmt.Fprintln(r.out, content)
+	}
+	return nil
+}
+
+// raw sends any action and prints the data it returns
+func (r *REPL) raw(ctx context.Context, action string, params map[string]any) error {
+	resp, err := r.client.SendPluginCommand(ctx, r.session, zellij.Request{
+		Action: action,
+		Params: params,
+	})
+	if err != nil {
+		return err
+	}
+	if !resp.Success {
+		return resp.Err()
+	}
+	data, err := json.MarshalIndent(resp.Data, "", "  ")
+	if err != nil {
+		return err
+	}
+	fmt.Fprintln(r.out, string(data))
+	return nil
+}
+
+// Complete returns the candidates for the last word of a partial line:
+// command names first, then pane names for commands taking a target
+func (r *REPL) Complete(line string) []string {
+	// Without a trailing space the last word is the one being completed
+	words := strings.Fields(line)
+	prefix := ""
+	if len(words) > 0 && !strings.HasSuffix(line, " ") {
+		prefix = words[len(words)-1]
+		words = words[:len(words)-1]
+	}
+
+	var candidates []string
+	switch {
+	case len(words) == 0:
+		for _, c := range replCommands {
+			candidates = append(candidates, c.name)
+		}
+	case len(words) == 1 && takesTarget(words[0]):
+		for _, pane := range r.panes {
+			candidates = append(candidates, shortPaneName(pane.Title, r.session))
+		}
+	}
+
+	var matches []string
+	for _, c := range candidates {
+		if strings.HasPrefix(c, prefix) {
+			matches = append(matches, c)
+		}
+	}
+	sort.Strings(matches)
+	return matches
+}
+
+// takesTarget reports whether a command's first argument is a pane
+func takesTarget(cmd string) bool {
+	switch cmd {
+	case "send", "interrupt", "int", "tail":
+		return true
+	}
+	return false
+}
+
+// shortPaneName drops the session prefix from a pane title, as targets allow
+func shortPaneName(title, session string) string {
+	return strings.TrimPrefix(title, session+"__")
+}
+
+// splitWords splits a command line into words. Single and double quotes
+// group words; a backslash escapes the next character outside single quotes.
+func splitWords(line string) ([]string, error) {
+	var words []string
+	var word strings.Builder
+	inWord := false
+	var quote rune
+	escaped := false
+
+	for _, c := range line {
+		switch {
+		case escaped:
+			word.WriteRune(c)
+			escaped = false
+		case c == '\\' && quote != '\'':
+			escaped = true
+			inWord = true
+		case quote != 0:
+			if c == quote {
+				quote = 0
+			} else {
+				word.WriteRune(c)
+			}
+		case c == '"' || c == '\'':
+			quote = c
+			inWord = true
+		case c == ' ' || c == '\t':
+			if inWord {
+				words = append(words, word.String())
+				word.Reset()
+				inWord = false
+			}
+		default:
+			word.WriteRune(c)
+			inWord = true
+		}
+	}
+
+	if quote != 0 {
+		return nil, fmt.Errorf("unterminated %c quote", quote)
+	}
+	if escaped {
+		return nil, fmt.Errorf("trailing backslash")
+	}
+	if inWord {
+		words = append(words, word.String())
+	}
+	return words, nil
+}
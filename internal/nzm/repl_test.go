@@ -0,0 +1,131 @@
+package nzm
+
+import (
+	"bytes"
+	"context"
+	"reflect"
+	"strings"
+	"testing"
+
+	"github.com/Dicklesworthstone/ntm/internal/zellij"
+)
+
+// mockREPLClient records plugin commands on top of mockPluginClient
+type mockREPLClient struct {
+	mockPluginClient
+	requests []zellij.Request
+	response *zellij.Response
+}
+
+func (m *mockREPLClient) SendPluginCommand(ctx context.Context, session string, req zellij.Request) (*zellij.Response, error) {
+	m.requests = append(m.requests, req)
+	return m.response, nil
+}
+
+func newTestREPL() (*REPL, *mockREPLClient, *bytes.Buffer) {
+	client := &mockREPLClient{
+		mockPluginClient: mockPluginClient{
+			panes: []zellij.PaneInfo{
+				{ID: 1, Title: "proj__cc_1", IsFocused: true},
+				{ID: 2, Title: "proj__cod_1"},
+			},
+		},
+		response: &zellij.Response{Success: true, Data: map[string]any{"content": "line 1\nline 2"}},
+	}
+	var out bytes.Buffer
+	return NewREPL(client, "proj", &out), client, &out
+}
+
+func TestSplitWords(t *testing.T) {
+	tests := []struct {
+		line    string
+		want    []string
+		wantErr bool
+	}{
+		{line: "", want: nil},
+		{line: "ls", want: []string{"ls"}},
+		{line: `send cc_1 "fix the tests"`, want: []string{"send", "cc_1", "fix the tests"}},
+		{line: `send cc_1 'it\'s'`, wantErr: true},
+		{line: `send cc_1 it\'s`, want: []string{"send", "cc_1", "it's"}},
+		{line: `raw get_version ""`, want: []string{"raw", "get_version", ""}},
+		{line: `send cc_1 "open`, wantErr: true},
+	}
+
+	for _, tt := range tests {
+		got, err := splitWords(tt.line)
+		if (err != nil) != tt.wantErr {
+			t.Errorf("splitWords(%q) error = %v, wantErr %v", tt.line, err, tt.wantErr)
+			continue
+		}
+		if !tt.wantErr && !reflect.DeepEqual(got, tt.want) {
+			t.Errorf("splitWords(%q) = %q, want %q", tt.line, got, tt.want)
+		}
+	}
+}
+
+func TestREPL_SendResolvesTarget(t *testing.T) {
+	repl, client, _ := newTestREPL()
+
+	quit, err := repl.Execute(context.Background(), `send cod_1 "fix the tests"`)
+	if err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	if quit {
+		t.Error("expected the REPL to keep running")
+	}
+	if client.sentPaneID != 2 {
+		t.Errorf("expected pane 2, got %d", client.sentPaneID)
+	}
+	if client.sentText != "fix the tests" || !client.sentEnter {
+		t.Errorf("expected 'fix the tests' with Enter, got %q (enter=%v)", client.sentText, client.sentEnter)
+	}
+}
+
+func TestREPL_TailDumpsScrollback(t *testing.T) {
+	repl, client, out := newTestREPL()
+
+	if _, err := repl.Execute(context.Background(), "tail cc_1 5"); err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	if len(client.requests) != 1 || client.requests[0].Action != "dump_scrollback" {
+		t.Fatalf("expected one dump_scrollback request, got %+v", client.requests)
+	}
+	if got := client.requests[0].Params["lines"]; got != 5 {
+		t.Errorf("expected 5 lines, got %v", got)
+	}
+	if !strings.Contains(out.String(), "line 2") {
+		t.Errorf("expected captured lines in output, got %q", out.String())
+	}
+}
+
+func TestREPL_ExitAndUnknownCommands(t *testing.T) {
+	repl, _, _ := newTestREPL()
+
+	quit, err := repl.Execute(context.Background(), "exit")
+	if err != nil || !quit {
+		t.Errorf("expected exit to quit, got quit=%v err=%v", quit, err)
+	}
+	if _, err := repl.Execute(context.Background(), "frobnicate"); err == nil {
+		t.Error("expected error for unknown command")
+	}
+}
+
+func TestREPL_CompletesCommandsAndPanes(t *testing.T) {
+	repl, _, _ := newTestREPL()
+	if _, err := repl.Execute(context.Background(), "ls"); err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+
+	if got := repl.Complete("ta"); !reflect.DeepEqual(got, []string{"tail"}) {
+		t.Errorf("Complete(\"ta\") = %q", got)
+	}
+	if got := repl.Complete("send "); !reflect.DeepEqual(got, []string{"cc_1", "cod_1"}) {
+		t.Errorf("Complete(\"send \") = %q", got)
+	}
+	if got := repl.Complete("tail co"); !reflect.DeepEqual(got, []string{"cod_1"}) {
+		t.Errorf("Complete(\"tail co\") = %q", got)
+	}
+	if got := repl.Complete("send cc_1 fi"); len(got) != 0 {
+		t.Errorf("expected no completion for text, got %q", got)
+	}
+}
@@ -0,0 +1,454 @@
+package nzm
+
+import (
+	"bytes"
+	"context"
+	"encoding/xml"
+	"errors"
+	"fmt"
+	"io"
+	"os"
+	"sort"
+	"time"
+
+	"github.com/Dicklesworthstone/ntm/internal/zellij"
+	"gopkg.in/yaml.v3"
+)
+
+// ScenarioFile is a file of scenarios run against one session
+type ScenarioFile struct {
+	Session   string     `yaml:"session"` // Session to run in (overridable)
+	Scenarios []Scenario `yaml:"scenarios"`
+}
+
+// Scenario is a named list of steps. Teardown steps run even when a step
+// fails, and panes the scenario spawned are closed after them.
+type Scenario struct {
+	Name     string         `yaml:"name"`
+	Steps    []ScenarioStep `yaml:"steps"`
+	Teardown []ScenarioStep `yaml:"teardown"`
+}
+
+// ScenarioStep is one step; exactly one of its fields is set
+type ScenarioStep struct {
+	Spawn  *SpawnStep  `yaml:"spawn"`
+	Send   *SendStep   `yaml:"send"`
+	Wait   *MatchStep  `yaml:"wait"`   // Wait until text appears
+	Assert *MatchStep  `yaml:"assert"` // Check text is there now
+	Close  string      `yaml:"close"`  // Pane to close
+	Action *ActionStep `yaml:"action"`
+}
+
+// SpawnStep opens a command pane that later steps name by As
+type SpawnStep struct {
+	As       string   `yaml:"as"`
+	Command  string   `yaml:"command"`
+	Args     []string `yaml:"args"`
+	Cwd      string   `yaml:"cwd"`
+	Floating bool     `yaml:"floating"`
+}
+
+// SendStep sends text and keys to a pane
+type SendStep struct {
+	Pane  string   `yaml:"pane"`
+	Text  string   `yaml:"text"`
+	Keys  []string `yaml:"keys"`
+	Enter bool     `yaml:"enter"`
+}
+
+// MatchStep looks for text in a pane's output or visible frame
+type MatchStep struct {
+	Pane     string `yaml:"pane"`
+	Contains string `yaml:"contains"`
+	Regex    string `yaml:"regex"`
+	Source   string `yaml:"source"`  // output (default) or frame
+	Timeout  int    `yaml:"timeout"` // Seconds a wait lasts (default 30)
+}
+
+// ActionStep sends any plugin action and expects it to succeed
+type ActionStep struct {
+	Name   string         `yaml:"name"`
+	Params map[string]any `yaml:"params"`
+}
+
+// defaultWaitSecs is how long a wait step lasts without a timeout
+const defaultWaitSecs = 30
+
+// stepTimeout bounds each plugin command apart from waits
+const stepTimeout = 30 * time.Second
+
+// LoadScenarios reads and checks a scenario file
+func LoadScenarios(path string) (*ScenarioFile, error) {
+	data, err := os.ReadFile(path)
+	if err != nil {
+		return nil, err
+	}
+	return ParseScenarios(data)
+}
+
+// ParseScenarios parses and checks scenario YAML; unknown keys are errors
+func ParseScenarios(data []byte) (*ScenarioFile, error) {
+	var file ScenarioFile
+	dec := yaml.NewDecoder(bytes.NewReader(data))
+	dec.KnownFields(true)
+	if err := dec.Decode(&file); err != nil {
+		if errors.Is(err, io.EOF) {
+			return nil, fmt.Errorf("scenario file is empty")
+		}
+		return nil, fmt.Errorf("parsing scenarios: %w", err)
+	}
+	if err := file.Validate(); err != nil {
+		return nil, err
+	}
+	return &file, nil
+}
+
+// Validate checks every scenario and step
+func (f *ScenarioFile) Validate() error {
+	if len(f.Scenarios) == 0 {
+		return fmt.Errorf("no scenarios defined")
+	}
+	names := make(map[string]bool)
+	for _, s := range f.Scenarios {
+		if s.Name == "" {
+			return fmt.Errorf("every scenario needs a name")
+		}
+		if names[s.Name] {
+			return fmt.Errorf("scenario %q is defined twice", s.Name)
+		}
+		names[s.Name] = true
+		if len(s.Steps) == 0 {
+			return fmt.Errorf("scenario %q has no steps", s.Name)
+		}
+		spawned := make(map[string]bool)
+		for i, step := range append(append([]ScenarioStep{}, s.Steps...), s.Teardown...) {
+			if err := step.validate(spawned); err != nil {
+				return fmt.Errorf("scenario %q step %d: %w", s.Name, i+1, err)
+			}
+		}
+	}
+	return nil
+}
+
+// validate checks one step, recording the names spawn steps give panes
+func (s ScenarioStep) validate(spawned map[string]bool) error {
+	set := 0
+	for _, isSet := range []bool{s.Spawn != nil, s.Send != nil, s.Wait != nil, s.Assert != nil, s.Close != "", s.Action != nil} {
+		if isSet {
+			set++
+		}
+	}
+	if set != 1 {
+		return fmt.Errorf("needs exactly one of spawn, send, wait, assert, close or action")
+	}
+
+	switch {
+	case s.Spawn != nil:
+		if s.Spawn.As == "" || s.Spawn.Command == "" {
+			return fmt.Errorf("spawn needs as and command")
+		}
+		if spawned[s.Spawn.As] {
+			return fmt.Errorf("pane %q is spawned twice", s.Spawn.As)
+		}
+		spawned[s.Spawn.As] = true
+	case s.Send != nil:
+		if s.Send.Pane == "" || (s.Send.Text == "" && len(s.Send.Keys) == 0) {
+			return fmt.Errorf("send needs pane and text or keys")
+		}
+	case s.Wait != nil:
+		return s.Wait.validate("wait")
+	case s.Assert != nil:
+		return s.Assert.validate("assert")
+	case s.Action != nil:
+		if s.Action.Name == "" {
+			return fmt.Errorf("action needs name")
+		}
+	}
+	return nil
+}
+
+func (m *MatchStep) validate(kind string) error {
+	if m.Pane == "" {
+		return fmt.Errorf("%s needs pane", kind)
+	}
+	if (m.Contains == "") == (m.Regex == "") {
+		return fmt.Errorf("%s needs exactly one of contains or regex", kind)
+	}
+	if m.Source != "" && m.Source != "output" && m.Source != "frame" {
+		return fmt.Errorf("%s source must be output or frame, got %q", kind, m.Source)
+	}
+	if m.Timeout < 0 {
+		return fmt.Errorf("%s timeout must not be negative", kind)
+	}
+	return nil
+}
+
+// ScenarioClient defines the plugin operations scenarios use
+type ScenarioClient interface {
+	SendPluginCommand(ctx context.Context, session string, req zellij.Request) (*zellij.Response, error)
+}
+
+// ScenarioResult is the outcome of one scenario
+type ScenarioResult struct {
+	Name           string        `json:"name"`
+	Passed         bool          `json:"passed"`
+	Step           int           `json:"step,omitempty"`    // 1-based index of the failing step
+	Failure        string        `json:"failure,omitempty"` // Why it failed
+	TeardownErrors []string      `json:"teardown_errors,omitempty"`
+	Duration       time.Duration `json:"duration_ns"`
+}
+
+// ScenarioRunner runs scenarios against a session
+type ScenarioRunner struct {
+	client  ScenarioClient
+	session string
+}
+
+// NewScenarioRunner creates a ScenarioRunner for session
+func NewScenarioRunner(client ScenarioClient, session string) *ScenarioRunner {
+	return &ScenarioRunner{client: client, session: session}
+}
+
+// Run runs each scenario in turn; a failing scenario does not stop the rest
+func (r *ScenarioRunner) Run(ctx context.Context, scenarios []Scenario) []ScenarioResult {
+	results := make([]ScenarioResult, 0, len(scenarios))
+	for _, s := range scenarios {
+		results = append(results, r.runScenario(ctx, s))
+	}
+	return results
+}
+
+func (r *ScenarioRunner) runScenario(ctx context.Context, s Scenario) ScenarioResult {
+	start := time.Now()
+	result := ScenarioResult{Name: s.Name, Passed: true}
+	panes := make(map[string]uint32)
+
+	for i, step := range s.Steps {
+		if err := r.runStep(ctx, step, panes); err != nil {
+			result.Passed = false
+			result.Step = i + 1
+			result.Failure = err.Error()
+			break
+		}
+	}
+
+	for _, step := range s.Teardown {
+		if err := r.runStep(ctx, step, panes); err != nil {
+			result.TeardownErrors = append(result.TeardownErrors, err.Error())
+		}
+	}
+	names := make([]string, 0, len(panes))
+	for name := range panes {
+		names = append(names, name)
+	}
+	sort.Strings(names)
+	for _, name := range names {
+		if _, err := r.command(ctx, "close_pane", map[string]any{"pane_id": panes[name]}, stepTimeout); err != nil {
+			result.TeardownErrors = append(result.TeardownErrors, err.Error())
+		}
+	}
+
+	result.Duration = time.Since(start)
+	return result
+}
+
+// runStep runs one step; panes maps spawned pane names to ids
+func (r *ScenarioRunner) runStep(ctx context.Context, step ScenarioStep, panes map[string]uint32) error {
+	selector := func(pane string) any {
+		if id, ok := panes[pane]; ok {
+			return id
+		}
+		return pane
+	}
+
+	switch {
+	case step.Spawn != nil:
+		params := map[string]any{
+			"command":  step.Spawn.Command,
+			"args":     step.Spawn.Args,
+			"floating": step.Spawn.Floating,
+			"name":     step.Spawn.As,
+		}
+		if step.Spawn.Cwd != "" {
+			params["cwd"] = step.Spawn.Cwd
+		}
+		data, err := r.command(ctx, "run_command", params, stepTimeout)
+		if err != nil {
+			return err
+		}
+		id, ok := data["pane_id"].(float64)
+		if !ok {
+			return fmt.Errorf("run_command: no pane_id in reply")
+		}
+		panes[step.Spawn.As] = uint32(id)
+
+	case step.Send != nil:
+		params := map[string]any{
+			"pane_id": selector(step.Send.Pane),
+			"enter":   step.Send.Enter,
+		}
+		if step.Send.Text != "" {
+			params["text"] = step.Send.Text
+		}
+		if len(step.Send.Keys) > 0 {
+			params["keys"] = step.Send.Keys
+		}
+		_, err := r.command(ctx, "send_keys", params, stepTimeout)
+		return err
+
+	case step.Wait != nil:
+		timeout := step.Wait.Timeout
+		if timeout == 0 {
+			timeout = defaultWaitSecs
+		}
+		params := map[string]any{
+			"conditions":   []any{map[string]any{"text": step.Wait.params(selector)}},
+			"timeout_secs": timeout,
+		}
+		// The plugin answers once the text appears or its timeout passes
+		data, err := r.command(ctx, "wait", params, time.Duration(timeout)*time.Second+stepTimeout)
+		if err != nil {
+			return err
+		}
+		if met, _ := data["met"].(bool); !met {
+			return fmt.Errorf("timed out after %ds waiting for %s in %s", timeout, step.Wait.describe(), step.Wait.Pane)
+		}
+
+	case step.Assert != nil:
+		data, err := r.command(ctx, "assert_pane", step.Assert.params(selector), stepTimeout)
+		if err != nil {
+			return err
+		}
+		if passed, _ := data["passed"].(bool); !passed {
+			return fmt.Errorf("expected %s in %s", step.Assert.describe(), step.Assert.Pane)
+		}
+
+	case step.Close != "":
+		if _, err := r.command(ctx, "close_pane", map[string]any{"pane_id": selector(step.Close)}, stepTimeout); err != nil {
+			return err
+		}
+		delete(panes, step.Close)
+
+	case step.Action != nil:
+		_, err := r.command(ctx, step.Action.Name, step.Action.Params, stepTimeout)
+		return err
+	}
+	return nil
+}
+
+// params are the assert_pane params of a match step
+func (m *MatchStep) params(selector func(string) any) map[string]any {
+	params := map[string]any{"selector": selector(m.Pane)}
+	if m.Contains != "" {
+		params["contains"] = m.Contains
+	} else {
+		params["regex"] = m.Regex
+	}
+	if m.Source != "" {
+		params["source"] = m.Source
+	}
+	return params
+}
+
+func (m *MatchStep) describe() string {
+	if m.Contains != "" {
+		return fmt.Sprintf("%q", m.Contains)
+	}
+	return fmt.Sprintf("/%s/", m.Regex)
+}
+
+// command sends one plugin command and returns its data
+func (r *ScenarioRunner) command(ctx context.Context, action string, params map[string]any, timeout time.Duration) (map[string]any, error) {
+	ctx, cancel := context.WithTimeout(ctx, timeout)
+	defer cancel()
+
+	resp, err := r.client.SendPluginCommand(ctx, r.session, zellij.Request{Action: action, Params: params})
+	if err != nil {
+		return nil, fmt.Errorf("%s: %w", action, err)
+	}
+	if !resp.Success {
+		return nil, fmt.Errorf("%s: %w", action, resp.Err())
+	}
+	return resp.Data, nil
+}
+
+// junitSuites is the root of a JUnit XML report
+type junitSuites struct {
+	XMLName  xml.Name     `xml:"testsuites"`
+	Tests    int          `xml:"tests,attr"`
+	Failures int          `xml:"failures,attr"`
+	Time     string       `xml:"time,attr"`
+	Suites   []junitSuite `xml:"testsuite"`
+}
+
+type junitSuite struct {
+	Name     string      `xml:"name,attr"`
+	Tests    int         `xml:"tests,attr"`
+	Failures int         `xml:"failures,attr"`
+	Time     string      `xml:"time,attr"`
+	Cases    []junitCase `xml:"testcase"`
+}
+
+type junitCase struct {
+	Name      string        `xml:"name,attr"`
+	Classname string        `xml:"classname,attr"`
+	Time      string        `xml:"time,attr"`
+	Failure   *junitFailure `xml:"failure,omitempty"`
+	SystemErr string        `xml:"system-err,omitempty"`
+}
+
+type junitFailure struct {
+	Message string `xml:"message,attr"`
+	Text    string `xml:",chardata"`
+}
+
+// WriteJUnit writes results as a JUnit XML report with one suite
+func WriteJUnit(w io.Writer, suite string, results []ScenarioResult) error {
+	s := junitSuite{Name: suite, Tests: len(results)}
+	var total time.Duration
+	for _, result := range results {
+		c := junitCase{
+			Name:      result.Name,
+			Classname: suite,
+			Time:      junitSeconds(result.Duration),
+		}
+		if !result.Passed {
+			s.Failures++
+			c.Failure = &junitFailure{
+				Message: result.Failure,
+				Text:    fmt.Sprintf("step %d: %s", result.Step, result.Failure),
+			}
+		}
+		if len(result.TeardownErrors) > 0 {
+			var buf bytes.Buffer
+			for _, e := range result.TeardownErrors {
+				fmt.Fprintf(&buf, "teardown: %s\n", e)
+			}
+			c.SystemErr = buf.String()
+		}
+		total += result.Duration
+		s.Cases = append(s.Cases, c)
+	}
+	s.Time = junitSeconds(total)
+
+	report := junitSuites{
+		Tests:    s.Tests,
+		Failures: s.Failures,
+		Time:     s.Time,
+		Suites:   []junitSuite{s},
+	}
+	if _, err := io.WriteString(w, xml.Header); err != nil {
+		return err
+	}
+	enc := xml.NewEncoder(w)
+	enc.Indent("", "  ")
+	if err := enc.Encode(report); err != nil {
+		return err
+	}
+	_, err := io.WriteString(w, "\n")
+	return err
+}
+
+func junitSeconds(d time.Duration) string {
+	return fmt.Sprintf("%.3f", d.Seconds())
+}
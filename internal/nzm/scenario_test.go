@@ -0,0 +1,148 @@
+package nzm
+
+import (
+	"bytes"
+	"context"
+	"strings"
+	"testing"
+
+	"github.com/Dicklesworthstone/ntm/internal/zellij"
+)
+
+const testScenarios = `
+session: demo
+scenarios:
+  - name: echo round trip
+    steps:
+      - spawn: {as: probe, command: bash}
+      - send: {pane: probe, text: "echo hi", enter: true}
+      - wait: {pane: probe, contains: hi, timeout: 5}
+      - assert: {pane: probe, regex: "h.", source: frame}
+  - name: missing text
+    steps:
+      - spawn: {as: probe, command: bash}
+      - assert: {pane: probe, contains: nope}
+    teardown:
+      - action: {name: get_version}
+`
+
+// mockScenarioClient answers scenario commands and records them
+type mockScenarioClient struct {
+	requests []zellij.Request
+	nextPane float64
+}
+
+func (m *mockScenarioClient) SendPluginCommand(ctx context.Context, session string, req zellij.Request) (*zellij.Response, error) {
+	m.requests = append(m.requests, req)
+	data := map[string]any{}
+	switch req.Action {
+	case "run_command":
+		m.nextPane++
+		data["pane_id"] = m.nextPane
+	case "wait":
+		data["met"] = true
+	case "assert_pane":
+		data["passed"] = req.Params["contains"] != "nope"
+	}
+	return &zellij.Response{ID: req.ID, Success: true, Data: data}, nil
+}
+
+func (m *mockScenarioClient) actions() []string {
+	var actions []string
+	for _, req := range m.requests {
+		actions = append(actions, req.Action)
+	}
+	return actions
+}
+
+func TestParseScenarios(t *testing.T) {
+	file, err := ParseScenarios([]byte(testScenarios))
+	if err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	if file.Session != "demo" || len(file.Scenarios) != 2 {
+		t.Fatalf("unexpected file: %+v", file)
+	}
+	if got := file.Scenarios[0].Steps[2].Wait.Timeout; got != 5 {
+		t.Errorf("expected wait timeout 5, got %d", got)
+	}
+}
+
+func TestParseScenarios_Invalid(t *testing.T) {
+	tests := []struct {
+		name string
+		yaml string
+		want string
+	}{
+		{"empty", "", "empty"},
+		{"unknown key", "scenarios:\n  - name: a\n    stepz: []\n", "stepz"},
+		{"two kinds in a step", "scenarios:\n  - name: a\n    steps:\n      - close: x\n        action: {name: y}\n", "exactly one"},
+		{"match without text", "scenarios:\n  - name: a\n    steps:\n      - wait: {pane: x}\n", "contains or regex"},
+		{"duplicate spawn", "scenarios:\n  - name: a\n    steps:\n      - spawn: {as: p, command: sh}\n      - spawn: {as: p, command: sh}\n", "spawned twice"},
+	}
+
+	for _, tt := range tests {
+		_, err := ParseScenarios([]byte(tt.yaml))
+		if err == nil || !strings.Contains(err.Error(), tt.want) {
+			t.Errorf("%s: expected error containing %q, got %v", tt.name, tt.want, err)
+		}
+	}
+}
+
+func TestScenarioRunner_RunsStepsAndTearsDown(t *testing.T) {
+	file, err := ParseScenarios([]byte(testScenarios))
+	if err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	client := &mockScenarioClient{}
+
+	results := NewScenarioRunner(client, "demo").Run(context.Background(), file.Scenarios)
+
+	if len(results) != 2 {
+		t.Fatalf("expected 2 results, got %d", len(results))
+	}
+	if !results[0].Passed {
+		t.Errorf("expected first scenario to pass, got %+v", results[0])
+	}
+	if results[1].Passed || results[1].Step != 2 || !strings.Contains(results[1].Failure, "nope") {
+		t.Errorf("expected second scenario to fail at step 2, got %+v", results[1])
+	}
+
+	want := []string{
+		"run_command", "send_keys", "wait", "assert_pane", "close_pane",
+		"run_command", "assert_pane", "get_version", "close_pane",
+	}
+	if got := client.actions(); strings.Join(got, " ") != strings.Join(want, " ") {
+		t.Errorf("expected actions %v, got %v", want, got)
+	}
+	// Later steps name the spawned pane by its id
+	if got := client.requests[1].Params["pane_id"]; got != uint32(1) {
+		t.Errorf("expected send to pane 1, got %v", got)
+	}
+	if got := client.requests[8].Params["pane_id"]; got != uint32(2) {
+		t.Errorf("expected teardown to close pane 2, got %v", got)
+	}
+}
+
+func TestWriteJUnit(t *testing.T) {
+	results := []ScenarioResult{
+		{Name: "ok", Passed: true},
+		{Name: "bad", Step: 2, Failure: "expected \"x\" in p", TeardownErrors: []string{"close_pane: pane not found"}},
+	}
+
+	var buf bytes.Buffer
+	if err := WriteJUnit(&buf, "scenarios.yaml", results); err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	report := buf.String()
+	for _, want := range []string{
+		`<testsuites tests="2" failures="1"`,
+		`<testcase name="ok" classname="scenarios.yaml"`,
+		`<failure message="expected &#34;x&#34; in p">step 2: expected &#34;x&#34; in p</failure>`,
+		`<system-err>teardown: close_pane: pane not found`,
+	} {
+		if !strings.Contains(report, want) {
+			t.Errorf("expected report to contain %q, got:\n%s", want, report)
+		}
+	}
+}
@@ -27,13 +27,13 @@ type SendOptions struct {
 // Validate checks if send options are valid
 func (o SendOptions) Validate() error {
 	if o.Session == "" {
-		return fmt.Errorf("session name is required")
+		return Errorf(CodeUsage, "session name is required")
 	}
 	if o.Target == "" {
-		return fmt.Errorf("target pane is required")
+		return Errorf(CodeUsage, "target pane is required")
 	}
 	if !o.Interrupt && o.Text == "" {
-		return fmt.Errorf("text is required (or use --interrupt)")
+		return Errorf(CodeUsage, "text is required (or use --interrupt)")
 	}
 	return nil
 }
@@ -105,5 +105,5 @@ func findPane(panes []zellij.PaneInfo, session, target string) (*zellij.PaneInfo
 		}
 	}
 
-	return nil, fmt.Errorf("pane %q not found in session %q", target, session)
+	return nil, Errorf(CodeNotFound, "pane %q not found in session %q", target, session)
 }
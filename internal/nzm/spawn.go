@@ -32,18 +32,19 @@ type SpawnOptions struct {
 	CodCmd      string // Command for Codex panes
 	GmiCmd      string // Command for Gemini panes
 	Detached    bool   // Create session in background
+	Locale      string // Language of the plugin's UI
 }
 
 // Validate checks if spawn options are valid
 func (o SpawnOptions) Validate() error {
 	if o.Session == "" {
-		return fmt.Errorf("session name is required")
+		return Errorf(CodeUsage, "session name is required")
 	}
 	if err := zellij.ValidateSessionName(o.Session); err != nil {
-		return err
+		return WithCode(CodeUsage, err)
 	}
 	if o.CCCount == 0 && o.CodCount == 0 && o.GmiCount == 0 && !o.IncludeUser {
-		return fmt.Errorf("at least one agent or user pane is required")
+		return Errorf(CodeUsage, "at least one agent or user pane is required")
 	}
 	return nil
 }
@@ -78,7 +79,7 @@ func (s *Spawner) Spawn(ctx context.Context, opts SpawnOptions) (*SpawnResult, e
 		return nil, fmt.Errorf("failed to check session: %w", err)
 	}
 	if exists {
-		return nil, fmt.Errorf("session %q already exists", opts.Session)
+		return nil, Errorf(CodeConflict, "session %q already exists", opts.Session)
 	}
 
 	// Default working directory to current directory
@@ -102,6 +103,7 @@ func (s *Spawner) Spawn(ctx context.Context, opts SpawnOptions) (*SpawnResult, e
 		ClaudeCmd:   opts.ClaudeCmd,
 		CodCmd:      opts.CodCmd,
 		GmiCmd:      opts.GmiCmd,
+		Locale:      opts.Locale,
 	}
 
 	layoutKDL, err := zellij.GenerateLayout(layoutOpts)
@@ -65,7 +65,7 @@ func (s *Status) GetStatus(ctx context.Context, opts StatusOptions) (*StatusResu
 			}
 		}
 		if !found {
-			return nil, fmt.Errorf("session %q not found", opts.Session)
+			return nil, Errorf(CodeNotFound, "session %q not found", opts.Session)
 		}
 	}
 
@@ -0,0 +1,160 @@
+package nzm
+
+import (
+	"context"
+	"errors"
+	"fmt"
+	"regexp"
+	"time"
+
+	"github.com/Dicklesworthstone/ntm/internal/zellij"
+)
+
+// DefaultWaitTimeout is how long a wait lasts without --timeout
+const DefaultWaitTimeout = 30 * time.Second
+
+// maxWaitTimeout is the longest wait the plugin holds
+const maxWaitTimeout = time.Hour
+
+// ErrWaitTimeout is returned when a wait's conditions are not met in time
+var ErrWaitTimeout = errors.New("wait timed out")
+
+// WaitClient defines the plugin client operations wait needs
+type WaitClient interface {
+	SendPluginCommand(ctx context.Context, session string, req zellij.Request) (*zellij.Response, error)
+}
+
+// WaitOptions configures a wait; every condition set is part of it
+type WaitOptions struct {
+	Session    string
+	Idle       []string      // Panes whose agents must be idle
+	Text       string        // Regex to look for
+	TextPane   string        // Pane searched for Text (default: focused)
+	Source     string        // Where Text is searched: output (default) or frame
+	PaneExists []string      // Selectors that must name an open pane
+	Tasks      []string      // Tasks that must finish
+	Any        bool          // Met once one condition is, instead of all
+	Timeout    time.Duration // How long to wait; 0 checks once
+}
+
+// Validate checks if wait options are valid
+func (o WaitOptions) Validate() error {
+	if o.Session == "" {
+		return Errorf(CodeUsage, "session name is required")
+	}
+	if len(o.Idle) == 0 && o.Text == "" && len(o.PaneExists) == 0 && len(o.Tasks) == 0 {
+		return Errorf(CodeUsage, "at least one of --idle, --text, --pane-exists or --task is required")
+	}
+	if o.Text != "" {
+		if _, err := regexp.Compile(o.Text); err != nil {
+			return Errorf(CodeUsage, "invalid --text regex: %w", err)
+		}
+	}
+	if o.Source != "" && o.Source != "output" && o.Source != "frame" {
+		return Errorf(CodeUsage, "source must be output or frame, got %q", o.Source)
+	}
+	if o.Timeout < 0 || o.Timeout > maxWaitTimeout {
+		return Errorf(CodeUsage, "timeout must be between 0 and %s", maxWaitTimeout)
+	}
+	if o.Timeout%time.Second != 0 {
+		return Errorf(CodeUsage, "timeout must be whole seconds, got %s", o.Timeout)
+	}
+	return nil
+}
+
+// WaitResult reports which conditions were met, in the order of
+// Idle, Text, PaneExists and Tasks
+type WaitResult struct {
+	Met        bool     `json:"met"`
+	Conditions []bool   `json:"conditions"`
+	Labels     []string `json:"labels"`
+}
+
+// Waiter waits on plugin conditions
+type Waiter struct {
+	client WaitClient
+}
+
+// NewWaiter creates a new Waiter
+func NewWaiter(client WaitClient) *Waiter {
+	return &Waiter{client: client}
+}
+
+// Wait holds until the conditions are met or the timeout passes. The
+// result is returned with ErrWaitTimeout when they were not met.
+func (w *Waiter) Wait(ctx context.Context, opts WaitOptions) (*WaitResult, error) {
+	if err := opts.Validate(); err != nil {
+		return nil, err
+	}
+
+	conditions, labels := opts.conditions()
+	mode := "all"
+	if opts.Any {
+		mode = "any"
+	}
+	secs := int(opts.Timeout / time.Second)
+	req := zellij.Request{
+		Action: "wait",
+		Params: map[string]any{
+			"conditions":   conditions,
+			"mode":         mode,
+			"timeout_secs": secs,
+		},
+	}
+
+	// The plugin answers when its own timeout passes; allow for the trip
+	ctx, cancel := context.WithTimeout(ctx, opts.Timeout+stepTimeout)
+	defer cancel()
+
+	resp, err := w.client.SendPluginCommand(ctx, opts.Session, req)
+	if err != nil {
+		return nil, err
+	}
+	if !resp.Success {
+		return nil, fmt.Errorf("wait: %w", resp.Err())
+	}
+
+	result := &WaitResult{Labels: labels}
+	result.Met, _ = resp.Data["met"].(bool)
+	if met, ok := resp.Data["conditions"].([]any); ok {
+		for _, m := range met {
+			b, _ := m.(bool)
+			result.Conditions = append(result.Conditions, b)
+		}
+	}
+	if !result.Met {
+		return result, fmt.Errorf("%w after %ds", ErrWaitTimeout, secs)
+	}
+	return result, nil
+}
+
+// conditions are the plugin wait conditions and a label for each
+func (o WaitOptions) conditions() ([]any, []string) {
+	var conditions []any
+	var labels []string
+	for _, pane := range o.Idle {
+		conditions = append(conditions, map[string]any{"idle": pane})
+		labels = append(labels, "idle "+pane)
+	}
+	if o.Text != "" {
+		pane := o.TextPane
+		if pane == "" {
+			pane = "focused"
+		}
+		text := map[string]any{"selector": pane, "regex": o.Text}
+		if o.Source != "" {
+			text["source"] = o.Source
+		}
+		conditions = append(conditions, map[string]any{"text": text})
+		labels = append(labels, fmt.Sprintf("text /%s/ in %s", o.Text, pane))
+	}
+	for _, sel := range o.PaneExists {
+		conditions = append(conditions, map[string]any{"pane_exists": sel})
+		labels = append(labels, "pane exists "+sel)
+	}
+	for _, id := range o.Tasks {
+		conditions = append(conditions, map[string]any{"task": id})
+		labels = append(labels, "task "+id)
+	}
+	return conditions, labels
+}
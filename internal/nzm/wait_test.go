@@ -0,0 +1,102 @@
+package nzm
+
+import (
+	"context"
+	"errors"
+	"strings"
+	"testing"
+	"time"
+
+	"github.com/Dicklesworthstone/ntm/internal/zellij"
+)
+
+// mockWaitClient records the wait request and answers with data
+type mockWaitClient struct {
+	req  zellij.Request
+	data map[string]any
+}
+
+func (m *mockWaitClient) SendPluginCommand(ctx context.Context, session string, req zellij.Request) (*zellij.Response, error) {
+	m.req = req
+	return &zellij.Response{ID: req.ID, Success: true, Data: m.data}, nil
+}
+
+func TestWaitOptions_Validate(t *testing.T) {
+	tests := []struct {
+		name string
+		opts WaitOptions
+		want string
+	}{
+		{"no session", WaitOptions{Idle: []string{"cc_1"}}, "session"},
+		{"no condition", WaitOptions{Session: "proj"}, "at least one"},
+		{"bad regex", WaitOptions{Session: "proj", Text: "("}, "regex"},
+		{"bad source", WaitOptions{Session: "proj", Text: "x", Source: "screen"}, "source"},
+		{"too long", WaitOptions{Session: "proj", Tasks: []string{"t1"}, Timeout: 2 * time.Hour}, "timeout"},
+		{"fractional", WaitOptions{Session: "proj", Tasks: []string{"t1"}, Timeout: 1500 * time.Millisecond}, "whole seconds"},
+	}
+
+	for _, tt := range tests {
+		err := tt.opts.Validate()
+		if err == nil || !strings.Contains(err.Error(), tt.want) {
+			t.Errorf("%s: expected error containing %q, got %v", tt.name, tt.want, err)
+		}
+	}
+}
+
+func TestWaiter_SendsConditions(t *testing.T) {
+	client := &mockWaitClient{data: map[string]any{"met": true, "conditions": []any{true, false, true}}}
+
+	result, err := NewWaiter(client).Wait(context.Background(), WaitOptions{
+		Session:    "proj",
+		Idle:       []string{"cc_1"},
+		Text:       "done",
+		PaneExists: []string{"cod_*"},
+		Any:        true,
+		Timeout:    10 * time.Second,
+	})
+	if err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+
+	if client.req.Action != "wait" {
+		t.Fatalf("expected wait action, got %q", client.req.Action)
+	}
+	params := client.req.Params
+	if params["mode"] != "any" || params["timeout_secs"] != 10 {
+		t.Errorf("unexpected params: %v", params)
+	}
+	conditions := params["conditions"].([]any)
+	if len(conditions) != 3 {
+		t.Fatalf("expected 3 conditions, got %v", conditions)
+	}
+	text := conditions[1].(map[string]any)["text"].(map[string]any)
+	if text["selector"] != "focused" || text["regex"] != "done" {
+		t.Errorf("expected text condition on the focused pane, got %v", text)
+	}
+
+	if !result.Met || len(result.Conditions) != 3 || result.Conditions[1] {
+		t.Errorf("unexpected result: %+v", result)
+	}
+	if result.Labels[2] != "pane exists cod_*" {
+		t.Errorf("unexpected labels: %v", result.Labels)
+	}
+}
+
+func TestWaiter_NotMet(t *testing.T) {
+	client := &mockWaitClient{data: map[string]any{"met": false, "conditions": []any{false}, "timeout": true}}
+
+	result, err := NewWaiter(client).Wait(context.Background(), WaitOptions{
+		Session: "proj",
+		Tasks:   []string{"t1"},
+		Timeout: time.Second,
+	})
+	if !errors.Is(err, ErrWaitTimeout) {
+		t.Fatalf("expected ErrWaitTimeout, got %v", err)
+	}
+	if result == nil || result.Met {
+		t.Errorf("expected an unmet result, got %+v", result)
+	}
+	if client.req.Params["mode"] != "all" {
+		t.Errorf("expected mode all by default, got %v", client.req.Params["mode"])
+	}
+}
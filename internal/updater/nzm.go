@@ -0,0 +1,345 @@
+package updater
+
+import (
+	"bufio"
+	"bytes"
+	"context"
+	"crypto/sha256"
+	"encoding/hex"
+	"encoding/json"
+	"fmt"
+	"io"
+	"net/http"
+	"os"
+	"os/exec"
+	"path/filepath"
+	"runtime"
+	"strings"
+)
+
+const (
+	// NZMReleasesURL lists the releases of nzm and its plugin
+	NZMReleasesURL = "https://api.github.com/repos/theirongolddev/nzm/releases"
+
+	// NZMTagPrefix starts the tags of nzm releases, such as nzm-v1.2.0
+	NZMTagPrefix = "nzm-"
+
+	// nzmSignerIdentity matches the release workflow that signs checksums
+	nzmSignerIdentity = `^https://github\.com/theirongolddev/nzm/\.github/workflows/`
+
+	// nzmSignerIssuer is the OIDC issuer of GitHub Actions tokens
+	nzmSignerIssuer = "https://token.actions.githubusercontent.com"
+)
+
+// Release channels
+const (
+	ChannelStable = "stable" // Releases only
+	ChannelBeta   = "beta"   // Releases and prereleases
+)
+
+// Names of the assets of an nzm release besides the CLI binaries
+const (
+	PluginAsset      = "nzm-agent.wasm"
+	ManifestAsset    = "nzm-manifest.json"
+	ChecksumsAsset   = "checksums.txt"
+	SignatureAsset   = "checksums.txt.sig"
+	CertificateAsset = "checksums.txt.pem"
+)
+
+// NZMRelease is a GitHub release of nzm
+type NZMRelease struct {
+	TagName    string     `json:"tag_name"`
+	Name       string     `json:"name"`
+	HTMLURL    string     `json:"html_url"`
+	Draft      bool       `json:"draft"`
+	Prerelease bool       `json:"prerelease"`
+	Assets     []NZMAsset `json:"assets"`
+}
+
+// NZMAsset is a file attached to a release
+type NZMAsset struct {
+	Name               string `json:"name"`
+	Size               int64  `json:"size"`
+	BrowserDownloadURL string `json:"browser_download_url"`
+}
+
+// Version is the release's version without its tag prefix
+func (r *NZMRelease) Version() string {
+	return strings.TrimPrefix(strings.TrimPrefix(r.TagName, NZMTagPrefix), "v")
+}
+
+// Asset returns the asset with the given name
+func (r *NZMRelease) Asset(name string) (*NZMAsset, bool) {
+	for i := range r.Assets {
+		if r.Assets[i].Name == name {
+			return &r.Assets[i], true
+		}
+	}
+	return nil, false
+}
+
+// Manifest describes the plugin of a release. It is published as
+// nzm-manifest.json and kept next to the installed plugin.
+type Manifest struct {
+	Version  string         `json:"version"`
+	Protocol int            `json:"protocol"`
+	Plugin   ManifestPlugin `json:"plugin"`
+}
+
+// ManifestPlugin identifies a plugin build
+type ManifestPlugin struct {
+	File        string `json:"file"`
+	SHA256      string `json:"sha256"`       // Of the wasm file
+	BuildSHA256 string `json:"build_sha256"` // Of its sources, as get_version reports it
+}
+
+// ParseManifest parses a release manifest
+func ParseManifest(data []byte) (*Manifest, error) {
+	var m Manifest
+	if err := json.Unmarshal(data, &m); err != nil {
+		return nil, fmt.Errorf("parsing manifest: %w", err)
+	}
+	if m.Version == "" || m.Plugin.SHA256 == "" || m.Plugin.BuildSHA256 == "" {
+		return nil, fmt.Errorf("manifest is missing its version or plugin hashes")
+	}
+	return &m, nil
+}
+
+// LoadManifest reads a release manifest from a file
+func LoadManifest(path string) (*Manifest, error) {
+	data, err := os.ReadFile(path)
+	if err != nil {
+		return nil, err
+	}
+	return ParseManifest(data)
+}
+
+// ManifestPath is where the manifest of the plugin at pluginPath is kept
+func ManifestPath(pluginPath string) string {
+	return filepath.Join(filepath.Dir(pluginPath), ManifestAsset)
+}
+
+// CLIAssetName is the name of the CLI binary for a platform
+func CLIAssetName(goos, goarch string) string {
+	name := fmt.Sprintf("nzm_%s_%s", goos, goarch)
+	if goos == "windows" {
+		name += ".exe"
+	}
+	return name
+}
+
+// LatestNZMRelease returns the newest release on the channel
+func LatestNZMRelease(ctx context.Context, client *http.Client, channel string) (*NZMRelease, error) {
+	return latestNZMRelease(ctx, client, NZMReleasesURL, channel)
+}
+
+func latestNZMRelease(ctx context.Context, client *http.Client, url, channel string) (*NZMRelease, error) {
+	if channel != ChannelStable && channel != ChannelBeta {
+		return nil, fmt.Errorf("unknown channel %q (use %s or %s)", channel, ChannelStable, ChannelBeta)
+	}
+
+	req, err := http.NewRequestWithContext(ctx, http.MethodGet, url, nil)
+	if err != nil {
+		return nil, err
+	}
+	req.Header.Set("Accept", "application/vnd.github.v3+json")
+	req.Header.Set("User-Agent", "nzm-self-update")
+
+	resp, err := client.Do(req)
+	if err != nil {
+		return nil, err
+	}
+	defer resp.Body.Close()
+
+	if resp.StatusCode != http.StatusOK {
+		body, _ := io.ReadAll(io.LimitReader(resp.Body, 4096))
+		return nil, fmt.Errorf("GitHub API returned %d: %s", resp.StatusCode, strings.TrimSpace(string(body)))
+	}
+
+	var releases []NZMRelease
+	if err := json.NewDecoder(resp.Body).Decode(&releases); err != nil {
+		return nil, fmt.Errorf("failed to parse releases: %w", err)
+	}
+	return selectNZMRelease(releases, channel)
+}
+
+// selectNZMRelease picks the newest nzm release on the channel; the
+// repository's other releases are skipped
+func selectNZMRelease(releases []NZMRelease, channel string) (*NZMRelease, error) {
+	var latest *NZMRelease
+	for i := range releases {
+		r := &releases[i]
+		if r.Draft || !strings.HasPrefix(r.TagName, NZMTagPrefix) {
+			continue
+		}
+		if r.Prerelease && channel != ChannelBeta {
+			continue
+		}
+		if latest == nil || compareVersions(r.Version(), latest.Version()) > 0 {
+			latest = r
+		}
+	}
+	if latest == nil {
+		return nil, fmt.Errorf("no %s release of nzm found", channel)
+	}
+	return latest, nil
+}
+
+// IsNewer reports whether latest is a newer version than current. A
+// development build is older than any release.
+func IsNewer(current, latest string) bool {
+	if current == "" || current == "dev" {
+		return true
+	}
+	return compareVersions(latest, current) > 0
+}
+
+// Download writes the asset to a file in dir and returns its path
+func Download(ctx context.Context, client *http.Client, asset *NZMAsset, dir string) (string, error) {
+	req, err := http.NewRequestWithContext(ctx, http.MethodGet, asset.BrowserDownloadURL, nil)
+	if err != nil {
+		return "", err
+	}
+	req.Header.Set("User-Agent", "nzm-self-update")
+
+	resp, err := client.Do(req)
+	if err != nil {
+		return "", err
+	}
+	defer resp.Body.Close()
+	if resp.StatusCode != http.StatusOK {
+		return "", fmt.Errorf("downloading %s: status %d", asset.Name, resp.StatusCode)
+	}
+
+	path := filepath.Join(dir, filepath.Base(asset.Name))
+	out, err := os.Create(path)
+	if err != nil {
+		return "", err
+	}
+	if _, err := io.Copy(out, resp.Body); err != nil {
+		out.Close()
+		return "", fmt.Errorf("downloading %s: %w", asset.Name, err)
+	}
+	return path, out.Close()
+}
+
+// ParseChecksums parses sha256sum output into a map of file name to hash
+func ParseChecksums(data []byte) (map[string]string, error) {
+	sums := make(map[string]string)
+	scanner := bufio.NewScanner(bytes.NewReader(data))
+	for scanner.Scan() {
+		line := strings.TrimSpace(scanner.Text())
+		if line == "" {
+			continue
+		}
+		fields := strings.Fields(line)
+		if len(fields) != 2 || len(fields[0]) != sha256.Size*2 {
+			return nil, fmt.Errorf("malformed checksum line %q", line)
+		}
+		sums[strings.TrimPrefix(fields[1], "*")] = strings.ToLower(fields[0])
+	}
+	if err := scanner.Err(); err != nil {
+		return nil, err
+	}
+	return sums, nil
+}
+
+// FileSHA256 returns the hex sha256 of a file
+func FileSHA256(path string) (string, error) {
+	f, err := os.Open(path)
+	if err != nil {
+		return "", err
+	}
+	defer f.Close()
+
+	h := sha256.New()
+	if _, err := io.Copy(h, f); err != nil {
+		return "", err
+	}
+	return hex.EncodeToString(h.Sum(nil)), nil
+}
+
+// VerifyChecksum checks the file at path against its entry in sums
+func VerifyChecksum(sums map[string]string, path string) error {
+	name := filepath.Base(path)
+	want, ok := sums[name]
+	if !ok {
+		return fmt.Errorf("%s is not listed in %s", name, ChecksumsAsset)
+	}
+	got, err := FileSHA256(path)
+	if err != nil {
+		return err
+	}
+	if got != want {
+		return fmt.Errorf("checksum mismatch for %s: expected %s, got %s", name, want, got)
+	}
+	return nil
+}
+
+// VerifySignature checks that the release workflow signed the checksums
+// file, with cosign's keyless verification
+func VerifySignature(ctx context.Context, checksums, signature, certificate string) error {
+	cosign, err := exec.LookPath("cosign")
+	if err != nil {
+		return fmt.Errorf("cosign is needed to verify the release signature: %w", err)
+	}
+	cmd := exec.CommandContext(ctx, cosign, "verify-blob",
+		"--signature", signature,
+		"--certificate", certificate,
+		"--certificate-identity-regexp", nzmSignerIdentity,
+		"--certificate-oidc-issuer", nzmSignerIssuer,
+		checksums,
+	)
+	if out, err := cmd.CombinedOutput(); err != nil {
+		return fmt.Errorf("signature verification failed: %w: %s", err, strings.TrimSpace(string(out)))
+	}
+	return nil
+}
+
+// InstallFile copies src over dst atomically, keeping dst intact if the
+// copy fails. A running executable can be replaced this way as well.
+func InstallFile(src, dst string, mode os.FileMode) error {
+	if err := os.MkdirAll(filepath.Dir(dst), 0755); err != nil {
+		return err
+	}
+
+	in, err := os.Open(src)
+	if err != nil {
+		return err
+	}
+	defer in.Close()
+
+	// Write next to dst so the rename stays on one filesystem
+	tmp, err := os.CreateTemp(filepath.Dir(dst), "."+filepath.Base(dst)+".new-*")
+	if err != nil {
+		return err
+	}
+	defer os.Remove(tmp.Name())
+
+	if _, err := io.Copy(tmp, in); err != nil {
+		tmp.Close()
+		return err
+	}
+	if err := tmp.Sync(); err != nil {
+		tmp.Close()
+		return err
+	}
+	if err := tmp.Close(); err != nil {
+		return err
+	}
+	if err := os.Chmod(tmp.Name(), mode); err != nil {
+		return err
+	}
+
+	// Windows cannot rename over a running executable, but it can move it
+	// aside; removing it then only succeeds once it has exited
+	if runtime.GOOS == "windows" {
+		old := dst + ".old"
+		os.Remove(old)
+		if err := os.Rename(dst, old); err != nil && !os.IsNotExist(err) {
+			return err
+		}
+		defer os.Remove(old)
+	}
+	return os.Rename(tmp.Name(), dst)
+}
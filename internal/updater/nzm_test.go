@@ -0,0 +1,143 @@
+package updater
+
+import (
+	"bytes"
+	"context"
+	"encoding/json"
+	"io"
+	"net/http"
+	"os"
+	"path/filepath"
+	"strings"
+	"testing"
+)
+
+func TestLatestNZMRelease_Channels(t *testing.T) {
+	t.Parallel()
+	releases := []NZMRelease{
+		{TagName: "v9.0.0"}, // An ntm release in the same repository
+		{TagName: "nzm-v1.3.0-beta.1", Prerelease: true},
+		{TagName: "nzm-v1.2.0"},
+		{TagName: "nzm-v1.4.0", Draft: true},
+		{TagName: "nzm-v1.1.0"},
+	}
+	body, _ := json.Marshal(releases)
+	client := func() *http.Client {
+		return &http.Client{
+			Transport: &MockRoundTripper{
+				Response: &http.Response{
+					StatusCode: http.StatusOK,
+					Body:       io.NopCloser(bytes.NewBuffer(body)),
+				},
+			},
+		}
+	}
+
+	stable, err := latestNZMRelease(context.Background(), client(), "http://example.com", ChannelStable)
+	if err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	if stable.TagName != "nzm-v1.2.0" || stable.Version() != "1.2.0" {
+		t.Errorf("expected nzm-v1.2.0 on stable, got %s", stable.TagName)
+	}
+
+	beta, err := latestNZMRelease(context.Background(), client(), "http://example.com", ChannelBeta)
+	if err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	if beta.TagName != "nzm-v1.3.0-beta.1" {
+		t.Errorf("expected the prerelease on beta, got %s", beta.TagName)
+	}
+
+	if _, err := latestNZMRelease(context.Background(), client(), "http://example.com", "nightly"); err == nil {
+		t.Error("expected an error for an unknown channel")
+	}
+}
+
+func TestIsNewer(t *testing.T) {
+	t.Parallel()
+	if !IsNewer("dev", "1.0.0") {
+		t.Error("expected any release to be newer than a dev build")
+	}
+	if !IsNewer("1.0.0", "1.1.0") || IsNewer("1.1.0", "1.1.0") || IsNewer("1.2.0", "1.1.0") {
+		t.Error("unexpected version comparison")
+	}
+}
+
+func TestParseChecksums(t *testing.T) {
+	t.Parallel()
+	digest := strings.Repeat("ab", 32)
+	sums, err := ParseChecksums([]byte(digest + "  nzm_linux_amd64\n" + strings.ToUpper(digest) + " *nzm-agent.wasm\n\n"))
+	if err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	if sums["nzm_linux_amd64"] != digest || sums["nzm-agent.wasm"] != digest {
+		t.Errorf("unexpected sums: %v", sums)
+	}
+
+	if _, err := ParseChecksums([]byte("abc  file\n")); err == nil {
+		t.Error("expected an error for a short digest")
+	}
+}
+
+func TestVerifyChecksum(t *testing.T) {
+	t.Parallel()
+	path := filepath.Join(t.TempDir(), "nzm-agent.wasm")
+	if err := os.WriteFile(path, nil, 0644); err != nil {
+		t.Fatal(err)
+	}
+	empty := "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855"
+
+	if err := VerifyChecksum(map[string]string{"nzm-agent.wasm": empty}, path); err != nil {
+		t.Errorf("unexpected error: %v", err)
+	}
+	if err := VerifyChecksum(map[string]string{"nzm-agent.wasm": strings.Repeat("0", 64)}, path); err == nil || !strings.Contains(err.Error(), "mismatch") {
+		t.Errorf("expected a mismatch, got %v", err)
+	}
+	if err := VerifyChecksum(map[string]string{}, path); err == nil || !strings.Contains(err.Error(), "not listed") {
+		t.Errorf("expected an unlisted file error, got %v", err)
+	}
+}
+
+func TestParseManifest(t *testing.T) {
+	t.Parallel()
+	m, err := ParseManifest([]byte(`{"version":"1.2.0","protocol":1,"plugin":{"file":"nzm-agent.wasm","sha256":"aa","build_sha256":"bb"}}`))
+	if err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	if m.Protocol != 1 || m.Plugin.BuildSHA256 != "bb" {
+		t.Errorf("unexpected manifest: %+v", m)
+	}
+
+	if _, err := ParseManifest([]byte(`{"version":"1.2.0"}`)); err == nil {
+		t.Error("expected an error for a manifest without hashes")
+	}
+}
+
+func TestInstallFile_ReplacesTarget(t *testing.T) {
+	t.Parallel()
+	dir := t.TempDir()
+	src := filepath.Join(dir, "new")
+	dst := filepath.Join(dir, "bin", "nzm")
+	if err := os.WriteFile(src, []byte("v2"), 0644); err != nil {
+		t.Fatal(err)
+	}
+	if err := os.MkdirAll(filepath.Dir(dst), 0755); err != nil {
+		t.Fatal(err)
+	}
+	if err := os.WriteFile(dst, []byte("v1"), 0755); err != nil {
+		t.Fatal(err)
+	}
+
+	if err := InstallFile(src, dst, 0755); err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	data, err := os.ReadFile(dst)
+	if err != nil || string(data) != "v2" {
+		t.Errorf("expected the new content, got %q (%v)", data, err)
+	}
+	entries, _ := os.ReadDir(filepath.Dir(dst))
+	if len(entries) != 1 {
+		t.Errorf("expected no temporary files left, got %d entries", len(entries))
+	}
+}
@@ -0,0 +1,122 @@
+package zellij
+
+import (
+	"context"
+	"encoding/json"
+	"fmt"
+)
+
+// Result is the outcome of a call started with Async
+type Result[T any] struct {
+	Value T
+	Err   error
+}
+
+// Async runs call in its own goroutine and delivers the outcome on the
+// returned channel, which gets exactly one value. Any client method can
+// be started this way without blocking the caller:
+//
+//	panes := zellij.Async(ctx, func(ctx context.Context) ([]zellij.PaneInfo, error) {
+//		return client.ListPanes(ctx, session)
+//	})
+//
+// Cancelling ctx cancels the call. Calls for a session with an open Pipe
+// share it and are answered concurrently.
+func Async[T any](ctx context.Context, call func(context.Context) (T, error)) <-chan Result[T] {
+	ch := make(chan Result[T], 1)
+	go func() {
+		value, err := call(ctx)
+		ch <- Result[T]{Value: value, Err: err}
+	}()
+	return ch
+}
+
+// SendPluginCommandAsync starts a plugin command without waiting for its reply
+func (c *Client) SendPluginCommandAsync(ctx context.Context, session string, req Request) <-chan Result[*Response] {
+	return Async(ctx, func(ctx context.Context) (*Response, error) {
+		return c.SendPluginCommand(ctx, session, req)
+	})
+}
+
+// Event is one entry of the plugin's event log
+type Event struct {
+	Seq   uint64         `json:"seq"`
+	Type  string         `json:"type"`
+	Event map[string]any `json:"event"`
+	Gap   bool           `json:"gap,omitempty"` // events before this one were dropped from the log
+}
+
+// eventPollSecs is how long each next_event waits before asking again
+const eventPollSecs = 30
+
+// Events streams the plugin's events raised from now on, optionally only
+// those of the given types, until ctx is done.
+//
+// The stream uses a pipe of its own: the plugin holds each next_event
+// until an event arrives, which would stall other requests on a shared
+// pipe. The events channel is closed when the stream ends. The error
+// channel then gets one value: nil when ctx ended it, the failure
+// otherwise.
+func (c *Client) Events(ctx context.Context, session string, types ...string) (<-chan Event, <-chan error) {
+	events := make(chan Event)
+	errc := make(chan error, 1)
+
+	go func() {
+		defer close(events)
+		errc <- c.streamEvents(ctx, session, types, events)
+	}()
+	return events, errc
+}
+
+// streamEvents polls next_event, sending each event on events
+func (c *Client) streamEvents(ctx context.Context, session string, types []string, events chan<- Event) error {
+	send := func(ctx context.Context, req Request) (*Response, error) {
+		return c.sendOnce(ctx, session, req)
+	}
+	if p, err := c.startPipe(ctx, session); err == nil {
+		defer p.Close()
+		send = p.Send
+	}
+
+	var after *uint64
+	for {
+		params := map[string]any{"timeout_secs": eventPollSecs}
+		if len(types) > 0 {
+			params["types"] = types
+		}
+		if after != nil {
+			params["after"] = *after
+		}
+
+		resp, err := send(ctx, Request{Action: "next_event", Params: params})
+		if ctx.Err() != nil {
+			return nil
+		}
+		if err != nil {
+			return err
+		}
+		if !resp.Success {
+			return resp.Err()
+		}
+
+		raw, err := json.Marshal(resp.Data)
+		if err != nil {
+			return err
+		}
+		var event Event
+		if err := json.Unmarshal(raw, &event); err != nil {
+			return fmt.Errorf("failed to parse event: %w", err)
+		}
+		seq := event.Seq
+		after = &seq
+		if timeout, _ := resp.Data["timeout"].(bool); timeout {
+			continue
+		}
+
+		select {
+		case events <- event:
+		case <-ctx.Done():
+			return nil
+		}
+	}
+}
@@ -0,0 +1,90 @@
+package zellij
+
+import (
+	"bufio"
+	"context"
+	"encoding/json"
+	"fmt"
+	"io"
+	"testing"
+	"time"
+)
+
+func TestAsync_DeliversResult(t *testing.T) {
+	mock := &mockExecutor{output: `{"id":"1","success":true,"data":{"panes":[{"id":4,"title":"proj__cc_1"}]}}`}
+	client := NewClient(WithExecutor(mock))
+
+	result := <-Async(context.Background(), func(ctx context.Context) ([]PaneInfo, error) {
+		return client.ListPanes(ctx, "nzm-test")
+	})
+	if result.Err != nil {
+		t.Fatalf("unexpected error: %v", result.Err)
+	}
+	if len(result.Value) != 1 || result.Value[0].ID != 4 {
+		t.Errorf("unexpected panes: %+v", result.Value)
+	}
+}
+
+// eventExecutor starts a fake pipe whose plugin times out the first
+// next_event and then answers with the event after the one asked for
+type eventExecutor struct {
+	mockExecutor
+	afters chan any
+}
+
+func (e *eventExecutor) Start(_ context.Context, args ...string) (io.WriteCloser, io.ReadCloser, func() error, error) {
+	reqR, reqW := io.Pipe()
+	respR, respW := io.Pipe()
+	go func() {
+		defer respW.Close()
+		scanner := bufio.NewScanner(reqR)
+		first := true
+		for scanner.Scan() {
+			var req Request
+			if err := json.Unmarshal(scanner.Bytes(), &req); err != nil {
+				continue
+			}
+			e.afters <- req.Params["after"]
+			if first {
+				first = false
+				fmt.Fprintf(respW, `{"id":%q,"success":true,"data":{"timeout":true,"seq":7}}`+"\n", req.ID)
+				continue
+			}
+			after := uint64(req.Params["after"].(float64))
+			fmt.Fprintf(respW, `{"id":%q,"success":true,"data":{"seq":%d,"type":"agent_ready","event":{"pane_id":1}}}`+"\n", req.ID, after+1)
+		}
+	}()
+	return reqW, respR, nil, nil
+}
+
+func TestClient_EventsStreamsInOrder(t *testing.T) {
+	exec := &eventExecutor{afters: make(chan any, 16)}
+	client := NewClient(WithExecutor(exec))
+
+	ctx, cancel := context.WithTimeout(context.Background(), 5*time.Second)
+	events, errc := client.Events(ctx, "nzm-test", "agent_ready")
+
+	for _, want := range []uint64{8, 9} {
+		event, ok := <-events
+		if !ok {
+			t.Fatalf("stream ended early: %v", <-errc)
+		}
+		if event.Seq != want || event.Type != "agent_ready" {
+			t.Errorf("expected agent_ready %d, got %+v", want, event)
+		}
+	}
+	cancel()
+	for range events {
+	}
+	if err := <-errc; err != nil {
+		t.Errorf("expected nil error after cancel, got %v", err)
+	}
+
+	// The first poll starts from now; later ones continue from the last seq
+	if after := <-exec.afters; after != nil {
+		t.Errorf("expected no after on the first poll, got %v", after)
+	}
+	if after := <-exec.afters; after != float64(7) {
+		t.Errorf("expected after 7 once the poll timed out, got %v", after)
+	}
+}
@@ -5,8 +5,10 @@ import (
 	"bytes"
 	"context"
 	"fmt"
+	"io"
 	"os/exec"
 	"strings"
+	"sync"
 )
 
 // Executor runs commands and returns output
@@ -14,11 +16,22 @@ type Executor interface {
 	Run(ctx context.Context, args ...string) (string, error)
 }
 
-// realExecutor executes actual zellij commands
-type realExecutor struct{}
+// realExecutor executes actual zellij commands, over ssh when remote is set
+type realExecutor struct {
+	remote string
+}
+
+// command returns the zellij command for args, run on the remote host if
+// there is one
+func (e *realExecutor) command(ctx context.Context, args ...string) *exec.Cmd {
+	if e.remote == "" {
+		return exec.CommandContext(ctx, "zellij", args...)
+	}
+	return sshCommand(ctx, e.remote, false, RemoteCommand("zellij", args...))
+}
 
 func (e *realExecutor) Run(ctx context.Context, args ...string) (string, error) {
-	cmd := exec.CommandContext(ctx, "zellij", args...)
+	cmd := e.command(ctx, args...)
 	var stdout, stderr bytes.Buffer
 	cmd.Stdout = &stdout
 	cmd.Stderr = &stderr
@@ -33,10 +46,42 @@ func (e *realExecutor) Run(ctx context.Context, args ...string) (string, error)
 	return strings.TrimSpace(stdout.String()), nil
 }
 
+// Start runs a zellij command that keeps running until ctx is done or its
+// stdin is closed
+func (e *realExecutor) Start(ctx context.Context, args ...string) (io.WriteCloser, io.ReadCloser, func() error, error) {
+	cmd := e.command(ctx, args...)
+	stdin, err := cmd.StdinPipe()
+	if err != nil {
+		return nil, nil, nil, err
+	}
+	stdout, err := cmd.StdoutPipe()
+	if err != nil {
+		return nil, nil, nil, err
+	}
+	if err := cmd.Start(); err != nil {
+		return nil, nil, nil, fmt.Errorf("zellij %s: %w", strings.Join(args, " "), err)
+	}
+	return stdin, stdout, cmd.Wait, nil
+}
+
+// sshCommand runs a shell command line on host, built with RemoteCommand
+// since ssh hands it to the remote shell as is. tty allocates a terminal
+// for interactive commands.
+func sshCommand(ctx context.Context, host string, tty bool, command string) *exec.Cmd {
+	flag := "-T"
+	if tty {
+		flag = "-t"
+	}
+	return exec.CommandContext(ctx, "ssh", flag, "--", host, command)
+}
+
 // Client handles Zellij operations
 type Client struct {
 	exec   Executor
-	Remote string // For API compatibility - not actually used by Zellij
+	Remote string // SSH host running Zellij; empty for the local machine
+
+	mu    sync.Mutex
+	pipes map[string]*Pipe // long-lived plugin pipes by session
 }
 
 // ClientOption configures a Client
@@ -49,9 +94,8 @@ func WithExecutor(exec Executor) ClientOption {
 	}
 }
 
-// WithRemote sets the remote host for SSH-based sessions.
-// Note: Zellij doesn't natively support remote like tmux -L,
-// but this is kept for API compatibility.
+// WithRemote runs zellij on host over ssh, so the client drives that
+// host's sessions. An empty host keeps the client local.
 func WithRemote(host string) ClientOption {
 	return func(c *Client) {
 		c.Remote = host
@@ -66,6 +110,9 @@ func NewClient(opts ...ClientOption) *Client {
 	for _, opt := range opts {
 		opt(c)
 	}
+	if e, ok := c.exec.(*realExecutor); ok {
+		e.remote = c.Remote
+	}
 	return c
 }
 
@@ -96,13 +143,17 @@ func (c *Client) RunSilentContext(ctx context.Context, args ...string) error {
 	return c.RunSilent(ctx, args...)
 }
 
-// IsInstalled checks if zellij is available
+// IsInstalled checks if zellij is available, on the remote host if set
 func (c *Client) IsInstalled() bool {
+	if c.Remote != "" {
+		_, err := c.Run(context.Background(), "--version")
+		return err == nil
+	}
 	_, err := exec.LookPath("zellij")
 	return err == nil
 }
 
-// GetRemote returns the remote host (always empty for Zellij - not supported)
+// GetRemote returns the SSH host the client drives, empty when local
 func (c *Client) GetRemote() string {
 	return c.Remote
 }
@@ -38,6 +38,17 @@ func ShellQuote(s string) string {
 	return "'" + strings.ReplaceAll(s, "'", `'\''`) + "'"
 }
 
+// RemoteCommand quotes name and args into one POSIX shell command line,
+// as ssh runs it on the remote host
+func RemoteCommand(name string, args ...string) string {
+	quoted := make([]string, 0, len(args)+1)
+	quoted = append(quoted, ShellQuote(name))
+	for _, arg := range args {
+		quoted = append(quoted, ShellQuote(arg))
+	}
+	return strings.Join(quoted, " ")
+}
+
 // FormatPaneName formats a pane title according to NZM convention
 func FormatPaneName(session string, agentType string, index int, variant string) string {
 	base := fmt.Sprintf("%s__%s_%d", session, agentType, index)
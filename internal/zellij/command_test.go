@@ -1,6 +1,8 @@
 package zellij
 
 import (
+	"context"
+	"strings"
 	"testing"
 )
 
@@ -118,3 +120,29 @@ func TestFormatPaneName(t *testing.T) {
 		}
 	}
 }
+
+func TestRemoteCommand(t *testing.T) {
+	got := RemoteCommand("zellij", "--session", "my proj", "pipe", "it's")
+	want := `'zellij' '--session' 'my proj' 'pipe' 'it'\''s'`
+	if got != want {
+		t.Errorf("RemoteCommand() = %q, want %q", got, want)
+	}
+}
+
+func TestNewClient_RemoteRunsOverSSH(t *testing.T) {
+	c := NewClient(WithRemote("devbox"))
+	e, ok := c.exec.(*realExecutor)
+	if !ok || e.remote != "devbox" {
+		t.Fatalf("expected the executor to run on devbox, got %#v", c.exec)
+	}
+
+	cmd := e.command(context.Background(), "list-sessions")
+	want := []string{"ssh", "-T", "--", "devbox", "'zellij' 'list-sessions'"}
+	if strings.Join(cmd.Args, "|") != strings.Join(want, "|") {
+		t.Errorf("command args = %q, want %q", cmd.Args, want)
+	}
+
+	if local := NewClient().exec.(*realExecutor).command(context.Background(), "list-sessions"); local.Args[0] != "zellij" {
+		t.Errorf("expected a local client to run zellij directly, got %q", local.Args)
+	}
+}
@@ -3,6 +3,7 @@ package zellij
 import (
 	"fmt"
 	"os"
+	"path/filepath"
 	"regexp"
 	"strconv"
 	"strings"
@@ -22,11 +23,23 @@ type LayoutOptions struct {
 	ClaudeCmd   string // Command to run for Claude panes
 	CodCmd      string // Command to run for Codex panes
 	GmiCmd      string // Command to run for Gemini panes
+	Locale      string // Language of the plugin's UI (default: the plugin's)
 }
 
 // DefaultPluginPath is the default plugin location
 const DefaultPluginPath = "nzm-agent"
 
+// PluginURL is the location Zellij loads the plugin at path from: a file
+// URL for paths, or the path itself for aliases such as nzm-agent. Paths
+// starting with / count as absolute on every OS, since with --ssh they
+// name a file on the remote host.
+func PluginURL(path string) string {
+	if filepath.IsAbs(path) || strings.HasPrefix(path, "/") {
+		return "file:" + filepath.ToSlash(path)
+	}
+	return path
+}
+
 // GenerateLayout creates a KDL layout string for a Zellij session
 func GenerateLayout(opts LayoutOptions) (string, error) {
 	var sb strings.Builder
@@ -56,10 +69,24 @@ func GenerateLayout(opts LayoutOptions) (string, error) {
 
 	// Add plugin pane (minimal size, borderless)
 	sb.WriteString("    pane size=1 borderless=true {\n")
-	if strings.HasPrefix(pluginPath, "/") {
-		sb.WriteString(fmt.Sprintf("        plugin location=\"file:%s\"\n", pluginPath))
+	location := PluginURL(pluginPath)
+	// The plugin reads its configuration from the layout. Zellij does not
+	// tell it which file it was loaded from, so the path is passed too.
+	var config []string
+	if opts.Locale != "" {
+		config = append(config, fmt.Sprintf("locale %q", opts.Locale))
+	}
+	if location != pluginPath {
+		config = append(config, fmt.Sprintf("plugin_path %q", pluginPath))
+	}
+	if len(config) > 0 {
+		sb.WriteString(fmt.Sprintf("        plugin location=\"%s\" {\n", location))
+		for _, line := range config {
+			sb.WriteString("            " + line + "\n")
+		}
+		sb.WriteString("        }\n")
 	} else {
-		sb.WriteString(fmt.Sprintf("        plugin location=\"%s\"\n", pluginPath))
+		sb.WriteString(fmt.Sprintf("        plugin location=\"%s\"\n", location))
 	}
 	sb.WriteString("    }\n")
 
@@ -62,6 +62,40 @@ func TestGenerateLayout_IncludesPluginPane(t *testing.T) {
 	}
 }
 
+func TestGenerateLayout_PassesConfigToPlugin(t *testing.T) {
+	opts := LayoutOptions{
+		Session:    "test",
+		PluginPath: "/path/to/plugin.wasm",
+		Locale:     "de",
+	}
+
+	kdl, err := GenerateLayout(opts)
+	if err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+
+	want := "plugin location=\"file:/path/to/plugin.wasm\" {\n            locale \"de\"\n            plugin_path \"/path/to/plugin.wasm\"\n        }"
+	if !strings.Contains(kdl, want) {
+		t.Errorf("expected plugin config block %q, got:\n%s", want, kdl)
+	}
+}
+
+func TestPluginURL(t *testing.T) {
+	tests := []struct {
+		path string
+		want string
+	}{
+		{"nzm-agent", "nzm-agent"},
+		{"/home/me/.local/share/nzm/nzm-agent.wasm", "file:/home/me/.local/share/nzm/nzm-agent.wasm"},
+	}
+
+	for _, tt := range tests {
+		if got := PluginURL(tt.path); got != tt.want {
+			t.Errorf("PluginURL(%q) = %q, want %q", tt.path, got, tt.want)
+		}
+	}
+}
+
 func TestGenerateLayout_PaneNamingConvention(t *testing.T) {
 	opts := LayoutOptions{
 		Session:  "myproj",
@@ -0,0 +1,244 @@
+package zellij
+
+import (
+	"bufio"
+	"context"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"io"
+	"strings"
+	"sync"
+)
+
+// ErrPipeClosed is returned for requests on a pipe whose output has ended
+var ErrPipeClosed = errors.New("plugin pipe closed")
+
+// maxPipeLine bounds one response line; captures and artifacts can be large
+const maxPipeLine = 16 * 1024 * 1024
+
+// Starter starts a zellij command that keeps running, connected to its
+// stdin and stdout. Executors that implement it can open a Pipe.
+type Starter interface {
+	Start(ctx context.Context, args ...string) (stdin io.WriteCloser, stdout io.ReadCloser, wait func() error, err error)
+}
+
+// Pipe is one long-lived `zellij pipe` to the nzm-agent plugin.
+//
+// Requests are written to its stdin one JSON line at a time and the plugin
+// answers each with one JSON line tagged with the request id, so many
+// requests can be in flight at once and replies may arrive in any order.
+// A request the plugin holds open (wait, next_event, deferred commands)
+// stalls the lines written after it: Zellij stops reading the pipe's input
+// until the plugin answers.
+type Pipe struct {
+	stdin io.WriteCloser
+	wait  func() error
+
+	writeMu sync.Mutex // one request line at a time
+
+	mu      sync.Mutex
+	pending map[string]chan *Response
+	err     error // why the output ended, once it has
+	done    chan struct{}
+}
+
+// NewPipe wraps the input and output of a running `zellij pipe`.
+// wait, if not nil, is called by Close to reap the process.
+func NewPipe(stdin io.WriteCloser, stdout io.Reader, wait func() error) *Pipe {
+	p := &Pipe{
+		stdin:   stdin,
+		wait:    wait,
+		pending: make(map[string]chan *Response),
+		done:    make(chan struct{}),
+	}
+	go p.read(stdout)
+	return p
+}
+
+// read hands each response line to the request waiting for its id
+func (p *Pipe) read(stdout io.Reader) {
+	scanner := bufio.NewScanner(stdout)
+	scanner.Buffer(make([]byte, 64*1024), maxPipeLine)
+	for scanner.Scan() {
+		line := strings.TrimSpace(scanner.Text())
+		if line == "" {
+			continue
+		}
+		resp, err := ParseResponse(line)
+		if err != nil {
+			// Not a reply we can match; Zellij may print notices too
+			continue
+		}
+		p.mu.Lock()
+		ch, ok := p.pending[resp.ID]
+		delete(p.pending, resp.ID)
+		p.mu.Unlock()
+		if ok {
+			ch <- resp
+		}
+	}
+
+	err := scanner.Err()
+	if err == nil {
+		err = ErrPipeClosed
+	} else {
+		err = fmt.Errorf("%w: %v", ErrPipeClosed, err)
+	}
+	p.mu.Lock()
+	p.err = err
+	p.mu.Unlock()
+	close(p.done)
+}
+
+// Send writes a request and waits for the reply with the same id
+func (p *Pipe) Send(ctx context.Context, req Request) (*Response, error) {
+	if req.ID == "" {
+		req.ID = GenerateRequestID()
+	}
+	line, err := json.Marshal(req)
+	if err != nil {
+		return nil, fmt.Errorf("failed to marshal request: %w", err)
+	}
+
+	ch := make(chan *Response, 1)
+	p.mu.Lock()
+	if p.err != nil {
+		p.mu.Unlock()
+		return nil, p.err
+	}
+	if _, busy := p.pending[req.ID]; busy {
+		p.mu.Unlock()
+		return nil, fmt.Errorf("request id %q is already in flight", req.ID)
+	}
+	p.pending[req.ID] = ch
+	p.mu.Unlock()
+
+	p.writeMu.Lock()
+	_, err = p.stdin.Write(append(line, '\n'))
+	p.writeMu.Unlock()
+	if err != nil {
+		p.forget(req.ID)
+		return nil, fmt.Errorf("writing to plugin pipe: %w", err)
+	}
+
+	select {
+	case resp := <-ch:
+		return resp, nil
+	case <-ctx.Done():
+		p.forget(req.ID)
+		return nil, ctx.Err()
+	case <-p.done:
+		// The reply may have been read just before the output ended
+		select {
+		case resp := <-ch:
+			return resp, nil
+		default:
+			return nil, p.err
+		}
+	}
+}
+
+// forget stops waiting for a reply; a late one is dropped
+func (p *Pipe) forget(id string) {
+	p.mu.Lock()
+	delete(p.pending, id)
+	p.mu.Unlock()
+}
+
+// Done is closed once the pipe's output has ended
+func (p *Pipe) Done() <-chan struct{} {
+	return p.done
+}
+
+// Close ends the pipe's input and waits for `zellij pipe` to exit
+func (p *Pipe) Close() error {
+	p.writeMu.Lock()
+	err := p.stdin.Close()
+	p.writeMu.Unlock()
+	<-p.done
+	if p.wait != nil {
+		if waitErr := p.wait(); err == nil {
+			err = waitErr
+		}
+	}
+	return err
+}
+
+// OpenPipe starts a long-lived pipe to the plugin in session. Until it is
+// closed, plugin commands for the session go over it instead of starting
+// a `zellij pipe` process each. ctx bounds the pipe's whole lifetime.
+func (c *Client) OpenPipe(ctx context.Context, session string) (*Pipe, error) {
+	p, err := c.startPipe(ctx, session)
+	if err != nil {
+		return nil, err
+	}
+
+	c.mu.Lock()
+	if c.pipes == nil {
+		c.pipes = make(map[string]*Pipe)
+	}
+	old := c.pipes[session]
+	c.pipes[session] = p
+	c.mu.Unlock()
+	if old != nil {
+		old.Close()
+	}
+	return p, nil
+}
+
+// startPipe starts a pipe to the plugin in session without making it
+// the session's shared pipe
+func (c *Client) startPipe(ctx context.Context, session string) (*Pipe, error) {
+	starter, ok := c.exec.(Starter)
+	if !ok {
+		return nil, fmt.Errorf("executor cannot keep a pipe open")
+	}
+	if ctx == nil {
+		ctx = context.Background()
+	}
+
+	// Without a payload, `zellij pipe` sends each line of its stdin
+	stdin, stdout, wait, err := starter.Start(ctx,
+		"--session", session,
+		"pipe",
+		"--plugin", PluginPath,
+	)
+	if err != nil {
+		return nil, err
+	}
+	return NewPipe(stdin, stdout, wait), nil
+}
+
+// openPipe returns the session's pipe if one is open and still running
+func (c *Client) openPipe(session string) *Pipe {
+	c.mu.Lock()
+	defer c.mu.Unlock()
+	p := c.pipes[session]
+	if p == nil {
+		return nil
+	}
+	select {
+	case <-p.done:
+		delete(c.pipes, session)
+		return nil
+	default:
+		return p
+	}
+}
+
+// Close closes every pipe the client has open
+func (c *Client) Close() error {
+	c.mu.Lock()
+	pipes := c.pipes
+	c.pipes = nil
+	c.mu.Unlock()
+
+	var firstErr error
+	for _, p := range pipes {
+		if err := p.Close(); err != nil && firstErr == nil {
+			firstErr = err
+		}
+	}
+	return firstErr
+}
@@ -0,0 +1,160 @@
+package zellij
+
+import (
+	"bufio"
+	"context"
+	"encoding/json"
+	"errors"
+	"fmt"
+	"io"
+	"sync"
+	"testing"
+	"time"
+)
+
+// fakePlugin answers requests read from a pipe's input, holding each
+// batch of n requests and replying to them in reverse order
+func fakePlugin(t *testing.T, requests io.Reader, replies io.WriteCloser, n int) {
+	t.Helper()
+	go func() {
+		defer replies.Close()
+		scanner := bufio.NewScanner(requests)
+		var batch []Request
+		for scanner.Scan() {
+			var req Request
+			if err := json.Unmarshal(scanner.Bytes(), &req); err != nil {
+				continue
+			}
+			batch = append(batch, req)
+			if len(batch) < n {
+				continue
+			}
+			for i := len(batch) - 1; i >= 0; i-- {
+				fmt.Fprintf(replies, `{"id":%q,"success":true,"data":{"action":%q}}`+"\n", batch[i].ID, batch[i].Action)
+			}
+			batch = nil
+		}
+	}()
+}
+
+func TestPipe_MatchesRepliesByID(t *testing.T) {
+	reqR, reqW := io.Pipe()
+	respR, respW := io.Pipe()
+	fakePlugin(t, reqR, respW, 2)
+	p := NewPipe(reqW, respR, nil)
+
+	var wg sync.WaitGroup
+	results := make([]*Response, 2)
+	errs := make([]error, 2)
+	for i, action := range []string{"list_panes", "get_version"} {
+		wg.Add(1)
+		go func(i int, action string) {
+			defer wg.Done()
+			results[i], errs[i] = p.Send(context.Background(), Request{Action: action})
+		}(i, action)
+	}
+	wg.Wait()
+
+	for i, action := range []string{"list_panes", "get_version"} {
+		if errs[i] != nil {
+			t.Fatalf("request %d: unexpected error: %v", i, errs[i])
+		}
+		if got := results[i].Data["action"]; got != action {
+			t.Errorf("request %d: expected reply for %q, got %v", i, action, got)
+		}
+	}
+
+	if err := p.Close(); err != nil {
+		t.Errorf("unexpected close error: %v", err)
+	}
+}
+
+func TestPipe_SendFailsOnceOutputEnds(t *testing.T) {
+	_, reqW := io.Pipe()
+	respR, respW := io.Pipe()
+	p := NewPipe(reqW, respR, nil)
+	respW.Close()
+	<-p.Done()
+
+	_, err := p.Send(context.Background(), Request{Action: "list_panes"})
+	if !errors.Is(err, ErrPipeClosed) {
+		t.Errorf("expected ErrPipeClosed, got %v", err)
+	}
+}
+
+func TestPipe_SendHonoursContext(t *testing.T) {
+	reqR, reqW := io.Pipe()
+	respR, _ := io.Pipe()
+	// Read requests but never answer them
+	go io.Copy(io.Discard, reqR)
+	p := NewPipe(reqW, respR, nil)
+
+	ctx, cancel := context.WithTimeout(context.Background(), 20*time.Millisecond)
+	defer cancel()
+	_, err := p.Send(ctx, Request{Action: "wait"})
+	if !errors.Is(err, context.DeadlineExceeded) {
+		t.Errorf("expected deadline exceeded, got %v", err)
+	}
+}
+
+// startExecutor starts a fake `zellij pipe` answered by fakePlugin
+type startExecutor struct {
+	mockExecutor
+	started [][]string
+}
+
+func (e *startExecutor) Start(_ context.Context, args ...string) (io.WriteCloser, io.ReadCloser, func() error, error) {
+	e.started = append(e.started, args)
+	reqR, reqW := io.Pipe()
+	respR, respW := io.Pipe()
+	go func() {
+		scanner := bufio.NewScanner(reqR)
+		for scanner.Scan() {
+			var req Request
+			if err := json.Unmarshal(scanner.Bytes(), &req); err == nil {
+				fmt.Fprintf(respW, `{"id":%q,"success":true,"data":{"panes":[]}}`+"\n", req.ID)
+			}
+		}
+		respW.Close()
+	}()
+	return reqW, respR, nil, nil
+}
+
+func TestClient_SendPluginCommandUsesOpenPipe(t *testing.T) {
+	exec := &startExecutor{}
+	client := NewClient(WithExecutor(exec))
+
+	if _, err := client.OpenPipe(context.Background(), "nzm-test"); err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	defer client.Close()
+
+	for i := 0; i < 3; i++ {
+		if _, err := client.ListPanes(context.Background(), "nzm-test"); err != nil {
+			t.Fatalf("unexpected error: %v", err)
+		}
+	}
+
+	if len(exec.started) != 1 {
+		t.Errorf("expected 1 pipe started, got %d", len(exec.started))
+	}
+	if len(exec.calls) != 0 {
+		t.Errorf("expected no one-shot pipes, got %d", len(exec.calls))
+	}
+	// Other sessions still use one-shot pipes
+	exec.output = `{"id":"1","success":true,"data":{"panes":[]}}`
+	if _, err := client.ListPanes(context.Background(), "other"); err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	if len(exec.calls) != 1 {
+		t.Errorf("expected 1 one-shot pipe, got %d", len(exec.calls))
+	}
+}
+
+func TestClient_OpenPipeNeedsStarter(t *testing.T) {
+	client := NewClient(WithExecutor(&mockExecutor{}))
+
+	if _, err := client.OpenPipe(context.Background(), "nzm-test"); err == nil {
+		t.Fatal("expected error, got nil")
+	}
+}
@@ -4,6 +4,8 @@ import (
 	"context"
 	"encoding/json"
 	"fmt"
+	"sort"
+	"strings"
 	"sync/atomic"
 	"time"
 )
@@ -21,6 +23,34 @@ type Response struct {
 	Success bool           `json:"success"`
 	Data    map[string]any `json:"data,omitempty"`
 	Error   string         `json:"error,omitempty"`
+	Code    string         `json:"code,omitempty"` // Stable error class, such as not_found
+}
+
+// PluginError is a failure the plugin reported, with its stable code
+type PluginError struct {
+	Code    string // Such as not_found or invalid_params; empty from older plugins
+	Message string
+}
+
+func (e *PluginError) Error() string {
+	return e.Message
+}
+
+// ErrorCode returns the plugin's code for the failure, or "failed"
+func (e *PluginError) ErrorCode() string {
+	if e.Code == "" {
+		return "failed"
+	}
+	return e.Code
+}
+
+// Err returns the response's failure as a *PluginError, or nil when it
+// succeeded
+func (r *Response) Err() error {
+	if r.Success {
+		return nil
+	}
+	return &PluginError{Code: r.Code, Message: r.Error}
 }
 
 // PaneInfo represents a terminal pane
@@ -84,6 +114,19 @@ func (c *Client) SendPluginCommand(ctx context.Context, session string, req Requ
 		req.ID = GenerateRequestID()
 	}
 
+	// Reuse the session's long-lived pipe when one is open
+	if p := c.openPipe(session); p != nil {
+		return p.Send(ctx, req)
+	}
+	return c.sendOnce(ctx, session, req)
+}
+
+// sendOnce sends a command over a `zellij pipe` started for it alone
+func (c *Client) sendOnce(ctx context.Context, session string, req Request) (*Response, error) {
+	if req.ID == "" {
+		req.ID = GenerateRequestID()
+	}
+
 	// Serialize request to JSON
 	reqJSON, err := json.Marshal(req)
 	if err != nil {
@@ -116,7 +159,7 @@ func (c *Client) ListPanes(ctx context.Context, session string) ([]PaneInfo, err
 	}
 
 	if !resp.Success {
-		return nil, fmt.Errorf("%s", resp.Error)
+		return nil, resp.Err()
 	}
 
 	return resp.GetPanes()
@@ -137,7 +180,7 @@ func (c *Client) SendKeys(ctx context.Context, session string, paneID uint32, te
 	}
 
 	if !resp.Success {
-		return fmt.Errorf("%s", resp.Error)
+		return resp.Err()
 	}
 
 	return nil
@@ -156,7 +199,7 @@ func (c *Client) SendInterrupt(ctx context.Context, session string, paneID uint3
 	}
 
 	if !resp.Success {
-		return fmt.Errorf("%s", resp.Error)
+		return resp.Err()
 	}
 
 	return nil
@@ -175,7 +218,7 @@ func (c *Client) GetPaneInfo(ctx context.Context, session string, paneID uint32)
 	}
 
 	if !resp.Success {
-		return nil, fmt.Errorf("%s", resp.Error)
+		return nil, resp.Err()
 	}
 
 	// Extract pane from response
@@ -200,3 +243,87 @@ func (c *Client) GetPaneInfo(ctx context.Context, session string, paneID uint32)
 
 	return &pane, nil
 }
+
+// SendPaste sends text to a pane as one bracketed paste. With chunkBytes
+// above 0 the plugin writes it at most that many bytes per pacing tick.
+func (c *Client) SendPaste(ctx context.Context, session string, paneID uint32, text string, chunkBytes int, enter bool) error {
+	params := map[string]any{
+		"pane_id":   paneID,
+		"text":      text,
+		"bracketed": true,
+		"enter":     enter,
+	}
+	if chunkBytes > 0 {
+		params["chunk_bytes"] = chunkBytes
+	}
+	resp, err := c.SendPluginCommand(ctx, session, Request{
+		Action: "send_keys",
+		Params: params,
+	})
+	if err != nil {
+		return err
+	}
+
+	if !resp.Success {
+		return resp.Err()
+	}
+
+	return nil
+}
+
+// ReloadPlugin restarts the plugin at url in a session, loading the file
+// again, or starts it if it is not running. config is passed to it as its
+// configuration, which otherwise comes from the layout.
+func (c *Client) ReloadPlugin(ctx context.Context, session, url string, config map[string]string) error {
+	args := []string{"--session", session, "action", "start-or-reload-plugin"}
+	if len(config) > 0 {
+		keys := make([]string, 0, len(config))
+		for k := range config {
+			keys = append(keys, k)
+		}
+		sort.Strings(keys)
+		pairs := make([]string, len(keys))
+		for i, k := range keys {
+			pairs[i] = k + "=" + config[k]
+		}
+		args = append(args, "--configuration", strings.Join(pairs, ","))
+	}
+	args = append(args, url)
+	return c.RunSilent(ctx, args...)
+}
+
+// PluginProtocol is the protocol this client speaks, PROTOCOL_VERSION in
+// the plugin's ipc.rs
+const PluginProtocol = 1
+
+// PluginVersion is what the plugin reports about its build
+type PluginVersion struct {
+	Version         string            `json:"version"`
+	Protocol        int               `json:"protocol"`
+	BuildSHA256     string            `json:"build_sha256"`                // Of the sources it was built from
+	PluginPath      string            `json:"plugin_path"`                 // The file it was told it was loaded from
+	FileSHA256      string            `json:"file_sha256"`                 // Of that file, once hashed
+	FileSHA256Error string            `json:"file_sha256_error,omitempty"` // Why the file could not be hashed
+	ConfigErrors    map[string]string `json:"config_errors,omitempty"`
+}
+
+// GetVersion asks the plugin in a session which build it is
+func (c *Client) GetVersion(ctx context.Context, session string) (*PluginVersion, error) {
+	resp, err := c.SendPluginCommand(ctx, session, Request{Action: "get_version"})
+	if err != nil {
+		return nil, err
+	}
+	if !resp.Success {
+		return nil, resp.Err()
+	}
+
+	data, err := json.Marshal(resp.Data)
+	if err != nil {
+		return nil, err
+	}
+	var version PluginVersion
+	if err := json.Unmarshal(data, &version); err != nil {
+		return nil, fmt.Errorf("failed to parse get_version reply: %w", err)
+	}
+	return &version, nil
+}
@@ -4,6 +4,7 @@ import (
 	"context"
 	"encoding/json"
 	"errors"
+	"strings"
 	"testing"
 )
 
@@ -231,6 +232,20 @@ func TestClient_SendKeys_PaneNotFound(t *testing.T) {
 	}
 }
 
+func TestClient_SendKeys_CarriesErrorCode(t *testing.T) {
+	mock := &mockExecutor{output: `{"id":"1","success":false,"error":"queue held: project web","code":"held"}`}
+	client := NewClient(WithExecutor(mock))
+
+	err := client.SendKeys(context.Background(), "test-session", 1, "hello", true)
+	var pluginErr *PluginError
+	if !errors.As(err, &pluginErr) {
+		t.Fatalf("expected a *PluginError, got %T: %v", err, err)
+	}
+	if pluginErr.Code != "held" || pluginErr.ErrorCode() != "held" {
+		t.Errorf("expected code held, got %q", pluginErr.Code)
+	}
+}
+
 func TestClient_SendInterrupt(t *testing.T) {
 	mock := &mockExecutor{output: `{"id":"1","success":true,"data":{"action":"send_interrupt","pane_id":1}}`}
 	client := NewClient(WithExecutor(mock))
@@ -252,3 +267,34 @@ func TestGenerateRequestID(t *testing.T) {
 		t.Error("expected unique IDs")
 	}
 }
+
+func TestClient_ReloadPlugin(t *testing.T) {
+	mock := &mockExecutor{}
+	client := NewClient(WithExecutor(mock))
+
+	err := client.ReloadPlugin(context.Background(), "proj", "file:/opt/nzm-agent.wasm", map[string]string{
+		"plugin_path": "/opt/nzm-agent.wasm",
+		"locale":      "de",
+	})
+	if err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+
+	want := "--session proj action start-or-reload-plugin --configuration locale=de,plugin_path=/opt/nzm-agent.wasm file:/opt/nzm-agent.wasm"
+	if len(mock.calls) != 1 || strings.Join(mock.calls[0], " ") != want {
+		t.Errorf("expected %q, got %v", want, mock.calls)
+	}
+}
+
+func TestClient_GetVersion(t *testing.T) {
+	mock := &mockExecutor{output: `{"id":"1","success":true,"data":{"version":"0.1.0","protocol":1,"build_sha256":"aa","plugin_path":"/opt/nzm-agent.wasm","file_sha256":null,"config_errors":{}}}`}
+	client := NewClient(WithExecutor(mock))
+
+	version, err := client.GetVersion(context.Background(), "proj")
+	if err != nil {
+		t.Fatalf("unexpected error: %v", err)
+	}
+	if version.Protocol != 1 || version.BuildSHA256 != "aa" || version.PluginPath != "/opt/nzm-agent.wasm" || version.FileSHA256 != "" {
+		t.Errorf("unexpected version: %+v", version)
+	}
+}
@@ -5,6 +5,7 @@ import (
 	"fmt"
 	"os"
 	"os/exec"
+	"path/filepath"
 	"regexp"
 	"strconv"
 	"strings"
@@ -127,11 +128,21 @@ func (c *Client) KillSession(ctx context.Context, name string) error {
 
 // AttachSession attaches to an existing session
 func (c *Client) AttachSession(ctx context.Context, name string) error {
+	if c.Remote != "" {
+		return c.runRemoteTerminal(ctx, "attach", name)
+	}
 	return c.RunSilent(ctx, "attach", name)
 }
 
 // CreateSession creates a new session with a layout
 func (c *Client) CreateSession(ctx context.Context, name, layoutPath string) error {
+	if c.Remote != "" {
+		remotePath, err := c.copyLayout(ctx, layoutPath)
+		if err != nil {
+			return err
+		}
+		return c.runRemoteTerminal(ctx, "-s", name, "-n", remotePath)
+	}
 	// Use -n (--new-session-with-layout) to always create new session
 	// even when called from inside an existing Zellij session
 	return c.RunSilent(ctx, "-s", name, "-n", layoutPath)
@@ -140,6 +151,10 @@ func (c *Client) CreateSession(ctx context.Context, name, layoutPath string) err
 // CreateSessionDetached creates a new session in the background.
 // Zellij doesn't have a --detached flag, so we start the process in background.
 func (c *Client) CreateSessionDetached(ctx context.Context, name, layoutPath string) error {
+	if c.Remote != "" {
+		return c.createRemoteSessionDetached(ctx, name, layoutPath)
+	}
+
 	// Use -s (session name) and -n (new-session-with-layout) flags
 	// -n ensures new session is created even from inside existing Zellij session
 	cmd := exec.CommandContext(ctx, "zellij", "-s", name, "-n", layoutPath)
@@ -163,6 +178,54 @@ func (c *Client) CreateSessionDetached(ctx context.Context, name, layoutPath str
 	return nil
 }
 
+// createRemoteSessionDetached starts a session in the background on the
+// remote host. With its output redirected, it outlives the ssh command.
+func (c *Client) createRemoteSessionDetached(ctx context.Context, name, layoutPath string) error {
+	remotePath, err := c.copyLayout(ctx, layoutPath)
+	if err != nil {
+		return err
+	}
+	command := "nohup " + RemoteCommand("zellij", "-s", name, "-n", remotePath) + " </dev/null >/dev/null 2>&1 &"
+	if out, err := sshCommand(ctx, c.Remote, false, command).CombinedOutput(); err != nil {
+		return fmt.Errorf("starting zellij session on %s: %w: %s", c.Remote, err, strings.TrimSpace(string(out)))
+	}
+
+	// Wait briefly for session to be created, as locally
+	time.Sleep(500 * time.Millisecond)
+	return nil
+}
+
+// copyLayout copies a layout file into the remote host's temp directory
+// under the same name, since zellij reads layouts from its own disk, and
+// returns the remote path
+func (c *Client) copyLayout(ctx context.Context, layoutPath string) (string, error) {
+	f, err := os.Open(layoutPath)
+	if err != nil {
+		return "", err
+	}
+	defer f.Close()
+
+	command := `f="${TMPDIR:-/tmp}"/` + ShellQuote(filepath.Base(layoutPath)) + ` && cat >"$f" && printf '%s' "$f"`
+	cmd := sshCommand(ctx, c.Remote, false, command)
+	cmd.Stdin = f
+	out, err := cmd.Output()
+	if err != nil {
+		return "", fmt.Errorf("copying layout to %s: %w", c.Remote, err)
+	}
+	return string(out), nil
+}
+
+// runRemoteTerminal runs an interactive zellij command on the remote host
+// in this terminal. It lasts as long as the user stays in the session, so
+// ctx's deadline does not end it.
+func (c *Client) runRemoteTerminal(ctx context.Context, args ...string) error {
+	cmd := sshCommand(context.WithoutCancel(ctx), c.Remote, true, RemoteCommand("zellij", args...))
+	cmd.Stdin = os.Stdin
+	cmd.Stdout = os.Stdout
+	cmd.Stderr = os.Stderr
+	return cmd.Run()
+}
+
 // CreateSessionSimple creates a new session with just a directory (no layout)
 func (c *Client) CreateSessionSimple(ctx context.Context, name, directory string) error {
 	// Zellij doesn't support creating sessions without a layout like tmux does
@@ -220,7 +283,7 @@ func (c *Client) SplitPane(ctx context.Context, session, direction, directory st
 		return "", err
 	}
 	if !resp.Success {
-		return "", fmt.Errorf("%s", resp.Error)
+		return "", resp.Err()
 	}
 
 	// Get the new pane ID from response
@@ -247,7 +310,7 @@ func (c *Client) SetPaneTitle(ctx context.Context, session string, paneID string
 		return err
 	}
 	if !resp.Success {
-		return fmt.Errorf("%s", resp.Error)
+		return resp.Err()
 	}
 	return nil
 }
@@ -386,7 +449,7 @@ func (c *Client) KillPane(ctx context.Context, session, paneID string) error {
 		return err
 	}
 	if !resp.Success {
-		return fmt.Errorf("%s", resp.Error)
+		return resp.Err()
 	}
 	return nil
 }
@@ -424,7 +487,7 @@ func (c *Client) ZoomPane(ctx context.Context, session string, paneIndex int) er
 		return err
 	}
 	if !resp.Success {
-		return fmt.Errorf("%s", resp.Error)
+		return resp.Err()
 	}
 	return nil
 }
@@ -0,0 +1,54 @@
+//! Embed the identity of the sources this plugin is built from
+//!
+//! A wasm file cannot carry its own hash, so the build hashes what it is
+//! made from instead: the manifest and every file under `src/`, each
+//! preceded by its path. The digest is available to the crate as
+//! `NZM_BUILD_SHA256` and published in the release manifest next to the
+//! hash of the wasm file; scripts/nzm-release-manifest.sh computes it the
+//! same way.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use sha2::{Digest, Sha256};
+
+fn main() {
+    let root = PathBuf::from(std::env::var("CARGO_MANIFEST_DIR").expect("CARGO_MANIFEST_DIR is set by cargo"));
+    let mut files = vec![root.join("Cargo.toml")];
+    collect(&root.join("src"), &mut files);
+
+    // Sorted as byte strings, as `LC_ALL=C sort` does, so the release
+    // script can compute the same digest
+    let mut relative: Vec<(String, PathBuf)> = files
+        .into_iter()
+        .map(|file| (file.strip_prefix(&root).unwrap_or(&file).to_string_lossy().replace('\\', "/"), file))
+        .collect();
+    relative.sort();
+
+    let mut hasher = Sha256::new();
+    for (name, file) in &relative {
+        hasher.update(name.as_bytes());
+        hasher.update([0]);
+        hasher.update(fs::read(file).unwrap_or_default());
+        hasher.update([0]);
+    }
+    let digest: String = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+
+    println!("cargo:rustc-env=NZM_BUILD_SHA256={}", digest);
+    println!("cargo:rerun-if-changed=Cargo.toml");
+    println!("cargo:rerun-if-changed=src");
+}
+
+/// Every file below `dir`
+fn collect(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.flatten().map(|e| e.path()) {
+        if path.is_dir() {
+            collect(&path, files);
+        } else {
+            files.push(path);
+        }
+    }
+}
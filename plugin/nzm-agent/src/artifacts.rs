@@ -0,0 +1,221 @@
+//! Content-addressed store for captured output and extracted artifacts
+//!
+//! Artifacts live on the host under the plugin's `/data` directory so that
+//! responses, tasks, and events can reference them by id instead of
+//! embedding large text.
+
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::io;
+use std::mem::size_of;
+use std::path::PathBuf;
+
+/// Default location of the store inside the plugin sandbox
+pub const DEFAULT_ARTIFACT_ROOT: &str = "/data/artifacts";
+
+/// Index entry describing a stored artifact
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ArtifactMeta {
+    /// Hex sha256 of the content
+    pub id: String,
+    /// What produced it, e.g. `capture` or `block`
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pane_id: Option<u32>,
+    pub size: usize,
+}
+
+/// Artifact store backed by a host directory
+pub struct ArtifactStore {
+    root: PathBuf,
+    index: Vec<ArtifactMeta>,
+    loaded: bool,
+    /// Largest estimated size of the in-memory index
+    index_budget: usize,
+    /// Artifacts deleted to keep the index within budget
+    evicted: u64,
+}
+
+impl Default for ArtifactStore {
+    fn default() -> Self {
+        ArtifactStore::new(DEFAULT_ARTIFACT_ROOT)
+    }
+}
+
+impl ArtifactStore {
+    /// Create a store rooted at `root` (created lazily on first write)
+    pub fn new(root: impl Into<PathBuf>) -> Self {
+        ArtifactStore {
+            root: root.into(),
+            index: Vec::new(),
+            loaded: false,
+            index_budget: usize::MAX,
+            evicted: 0,
+        }
+    }
+
+    /// Compute the id content would be stored under
+    pub fn content_id(content: &str) -> String {
+        Sha256::digest(content.as_bytes())
+            .iter()
+            .map(|b| format!("{:02x}", b))
+            .collect()
+    }
+
+    /// Store content, returning its metadata (deduplicated by content)
+    pub fn put(&mut self, kind: &str, pane_id: Option<u32>, content: &str) -> io::Result<ArtifactMeta> {
+        self.load_index();
+        let id = Self::content_id(content);
+
+        if let Some(existing) = self.index.iter().find(|m| m.id == id) {
+            return Ok(existing.clone());
+        }
+
+        fs::create_dir_all(&self.root)?;
+        fs::write(self.root.join(&id), content)?;
+
+        let meta = ArtifactMeta {
+            id,
+            kind: kind.to_string(),
+            pane_id,
+            size: content.len(),
+        };
+        self.index.push(meta.clone());
+        self.evict_over_budget();
+        self.save_index()?;
+        Ok(meta)
+    }
+
+    /// Read an artifact's content by id as text
+    pub fn get(&self, id: &str) -> io::Result<String> {
+        let bytes = self.get_bytes(id)?;
+        String::from_utf8(bytes).map_err(|e| io::Error::new(io::ErrorKind::InvalidData, e))
+    }
+
+    /// Read an artifact's raw bytes by id
+    pub fn get_bytes(&self, id: &str) -> io::Result<Vec<u8>> {
+        // Ids are hex digests; refuse anything that could escape the root
+        if id.is_empty() || !id.chars().all(|c| c.is_ascii_hexdigit()) {
+            return Err(io::Error::new(io::ErrorKind::InvalidInput, "invalid artifact id"));
+        }
+        fs::read(self.root.join(id))
+    }
+
+    /// List stored artifacts in insertion order
+    pub fn list(&mut self) -> &[ArtifactMeta] {
+        self.load_index();
+        &self.index
+    }
+
+    /// Cap the index's estimated size; older artifacts are deleted to fit
+    pub fn set_index_budget(&mut self, bytes: usize) {
+        self.index_budget = bytes;
+    }
+
+    /// Estimated heap bytes of the loaded index
+    pub fn index_bytes(&self) -> usize {
+        self.index
+            .iter()
+            .map(|m| size_of::<ArtifactMeta>() + m.id.len() + m.kind.len())
+            .sum()
+    }
+
+    /// Number of artifacts deleted to keep the index within budget
+    pub fn evicted(&self) -> u64 {
+        self.evicted
+    }
+
+    /// Delete the oldest artifacts until the index fits its budget, always
+    /// keeping the newest
+    fn evict_over_budget(&mut self) {
+        while self.index.len() > 1 && self.index_bytes() > self.index_budget {
+            let meta = self.index.remove(0);
+            let _ = fs::remove_file(self.root.join(&meta.id));
+            self.evicted += 1;
+        }
+    }
+
+    fn index_path(&self) -> PathBuf {
+        self.root.join("index.json")
+    }
+
+    fn load_index(&mut self) {
+        if self.loaded {
+            return;
+        }
+        self.loaded = true;
+        if let Ok(json) = fs::read_to_string(self.index_path()) {
+            self.index = serde_json::from_str(&json).unwrap_or_default();
+        }
+    }
+
+    fn save_index(&self) -> io::Result<()> {
+        let json = serde_json::to_string(&self.index).map_err(io::Error::other)?;
+        fs::write(self.index_path(), json)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store(name: &str) -> ArtifactStore {
+        let root = std::env::temp_dir().join(format!("nzm-artifacts-{}-{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&root);
+        ArtifactStore::new(root)
+    }
+
+    #[test]
+    fn test_put_and_get_roundtrip() {
+        let mut store = temp_store("roundtrip");
+        let meta = store.put("capture", Some(1), "hello").unwrap();
+
+        assert_eq!(meta.size, 5);
+        assert_eq!(meta.id.len(), 64);
+        assert_eq!(store.get(&meta.id).unwrap(), "hello");
+    }
+
+    #[test]
+    fn test_put_deduplicates_content() {
+        let mut store = temp_store("dedupe");
+        let a = store.put("capture", Some(1), "same").unwrap();
+        let b = store.put("block", Some(2), "same").unwrap();
+
+        assert_eq!(a, b);
+        assert_eq!(store.list().len(), 1);
+    }
+
+    #[test]
+    fn test_index_persists_across_instances() {
+        let mut store = temp_store("persist");
+        store.put("capture", None, "one").unwrap();
+        store.put("capture", None, "two").unwrap();
+
+        let mut reopened = ArtifactStore::new(store.root.clone());
+        assert_eq!(reopened.list().len(), 2);
+    }
+
+    #[test]
+    fn test_index_budget_evicts_oldest() {
+        let mut store = temp_store("budget");
+        let first = store.put("capture", None, "one").unwrap();
+        let entry = store.index_bytes();
+        store.set_index_budget(entry * 2);
+        store.put("capture", None, "two").unwrap();
+        store.put("capture", None, "three").unwrap();
+
+        assert_eq!(store.list().len(), 2);
+        assert_eq!(store.evicted(), 1);
+        assert!(store.get(&first.id).is_err());
+        let oldest = store.list()[0].id.clone();
+        assert_eq!(store.get(&oldest).unwrap(), "two");
+    }
+
+    #[test]
+    fn test_get_rejects_path_traversal() {
+        let store = temp_store("traversal");
+        assert!(store.get("../index.json").is_err());
+        assert!(store.get("").is_err());
+    }
+}
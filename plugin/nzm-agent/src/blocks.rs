@@ -0,0 +1,232 @@
+//! Extraction of fenced code blocks and unified diffs from captured output
+
+use serde::{Deserialize, Serialize};
+
+/// Kind of an extracted block
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum BlockKind {
+    Code,
+    Diff,
+}
+
+/// A structured item extracted from pane output
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Block {
+    pub kind: BlockKind,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<String>,
+    pub content: String,
+}
+
+/// Remove ANSI escape sequences (CSI and OSC) from a line
+pub fn strip_ansi(line: &str) -> String {
+    let mut out = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c != '\x1b' {
+            out.push(c);
+            continue;
+        }
+        match chars.next() {
+            // CSI: ESC [ params... final byte in @..~
+            Some('[') => {
+                for c in chars.by_ref() {
+                    if ('@'..='~').contains(&c) {
+                        break;
+                    }
+                }
+            }
+            // OSC: ESC ] ... terminated by BEL or ESC \
+            Some(']') => {
+                while let Some(c) = chars.next() {
+                    if c == '\x07' {
+                        break;
+                    }
+                    if c == '\x1b' && chars.peek() == Some(&'\\') {
+                        chars.next();
+                        break;
+                    }
+                }
+            }
+            // Two-byte escape: drop both
+            _ => {}
+        }
+    }
+    out
+}
+
+/// Truncate `text` to at most `max` bytes without splitting a UTF-8 character
+pub fn truncate_utf8(text: &str, max: usize) -> &str {
+    if text.len() <= max {
+        return text;
+    }
+    let mut end = max;
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    &text[..end]
+}
+
+/// Extract fenced code blocks and bare unified diffs from output lines
+///
+/// Fences opened with ``` or ~~~ and tagged `diff`/`patch` are reported as
+/// diffs. Unterminated fences (agent still streaming) are skipped.
+pub fn extract_blocks(lines: &[String]) -> Vec<Block> {
+    let mut blocks = Vec::new();
+    let mut i = 0;
+
+    while i < lines.len() {
+        let trimmed = lines[i].trim_start();
+
+        if let Some(fence) = fence_marker(trimmed) {
+            let info = trimmed[fence.len()..].trim();
+            let language = info.split_whitespace().next().map(|l| l.to_string());
+            let indent = lines[i].len() - trimmed.len();
+
+            let end = lines[i + 1..]
+                .iter()
+                .position(|l| l.trim() == fence)
+                .map(|pos| i + 1 + pos);
+
+            match end {
+                Some(end) => {
+                    let content = lines[i + 1..end]
+                        .iter()
+                        .map(|l| strip_indent(l, indent))
+                        .collect::<Vec<_>>()
+                        .join("\n");
+                    let kind = match language.as_deref() {
+                        Some("diff") | Some("patch") => BlockKind::Diff,
+                        _ => BlockKind::Code,
+                    };
+                    blocks.push(Block { kind, language, content });
+                    i = end + 1;
+                }
+                None => break,
+            }
+            continue;
+        }
+
+        if is_diff_start(lines, i) {
+            let start = i;
+            i += 1;
+            while i < lines.len() && is_diff_line(&lines[i]) {
+                i += 1;
+            }
+            // Blank lines are valid context but should not trail the diff
+            while i > start + 1 && lines[i - 1].is_empty() {
+                i -= 1;
+            }
+            blocks.push(Block {
+                kind: BlockKind::Diff,
+                language: None,
+                content: lines[start..i].join("\n"),
+            });
+            continue;
+        }
+
+        i += 1;
+    }
+
+    blocks
+}
+
+fn fence_marker(trimmed: &str) -> Option<&'static str> {
+    ["```", "~~~"].into_iter().find(|f| trimmed.starts_with(f))
+}
+
+fn strip_indent(line: &str, indent: usize) -> &str {
+    let ws = line.len() - line.trim_start().len();
+    &line[ws.min(indent)..]
+}
+
+fn is_diff_start(lines: &[String], i: usize) -> bool {
+    let line = &lines[i];
+    line.starts_with("diff --git ")
+        || (line.starts_with("--- ")
+            && lines.get(i + 1).is_some_and(|next| next.starts_with("+++ ")))
+}
+
+fn is_diff_line(line: &str) -> bool {
+    const PREFIXES: &[&str] = &[
+        " ", "+", "-", "@@", "\\", "diff ", "index ", "new file", "deleted file",
+        "old mode", "new mode", "similarity", "rename ", "copy ", "Binary files",
+    ];
+    line.is_empty() || PREFIXES.iter().any(|p| line.starts_with(p))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &str) -> Vec<String> {
+        text.lines().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn test_strip_ansi_removes_color_codes() {
+        assert_eq!(strip_ansi("\x1b[1;32mok\x1b[0m done"), "ok done");
+        assert_eq!(strip_ansi("\x1b]0;title\x07text"), "text");
+        assert_eq!(strip_ansi("plain"), "plain");
+    }
+
+    #[test]
+    fn test_truncate_utf8_respects_char_boundaries() {
+        assert_eq!(truncate_utf8("hello", 10), "hello");
+        assert_eq!(truncate_utf8("hello", 3), "hel");
+        // "é" is two bytes; cutting in the middle backs off
+        assert_eq!(truncate_utf8("aé", 2), "a");
+    }
+
+    #[test]
+    fn test_extract_fenced_code_block() {
+        let blocks = extract_blocks(&lines("Here you go:\n```rust\nfn main() {}\n```\nDone."));
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].kind, BlockKind::Code);
+        assert_eq!(blocks[0].language.as_deref(), Some("rust"));
+        assert_eq!(blocks[0].content, "fn main() {}");
+    }
+
+    #[test]
+    fn test_extract_indented_fence_strips_indent() {
+        let blocks = extract_blocks(&lines("  ```py\n  x = 1\n    y = 2\n  ```"));
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].content, "x = 1\n  y = 2");
+    }
+
+    #[test]
+    fn test_diff_fence_is_diff_kind() {
+        let blocks = extract_blocks(&lines("```diff\n-a\n+b\n```"));
+        assert_eq!(blocks[0].kind, BlockKind::Diff);
+    }
+
+    #[test]
+    fn test_unterminated_fence_skipped() {
+        let blocks = extract_blocks(&lines("```sh\necho partial"));
+        assert!(blocks.is_empty());
+    }
+
+    #[test]
+    fn test_extract_bare_unified_diff() {
+        let text = "Applying:\ndiff --git a/x b/x\n--- a/x\n+++ b/x\n@@ -1 +1 @@\n-old\n+new\nAll done.";
+        let blocks = extract_blocks(&lines(text));
+
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0].kind, BlockKind::Diff);
+        assert!(blocks[0].content.starts_with("diff --git"));
+        assert!(blocks[0].content.ends_with("+new"));
+    }
+
+    #[test]
+    fn test_multiple_blocks_in_order() {
+        let blocks = extract_blocks(&lines("```\na\n```\ntext\n~~~toml\nb = 1\n~~~"));
+
+        assert_eq!(blocks.len(), 2);
+        assert_eq!(blocks[0].language, None);
+        assert_eq!(blocks[1].language.as_deref(), Some("toml"));
+    }
+}
@@ -0,0 +1,107 @@
+//! Debug-only latency and loss injection
+//!
+//! When enabled through the `chaos.*` config keys, the plugin randomly
+//! drops requests before they run or delays their effects, and delays or
+//! drops pane updates. Decisions come from a seeded generator so a failing
+//! run can be reproduced with the same seed.
+
+use crate::config::Config;
+
+/// What happens to a request or event
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Fate {
+    Now,
+    /// Run after this many seconds
+    Delay(f64),
+    Drop,
+}
+
+/// Seeded source of chaos decisions
+#[derive(Debug, Clone)]
+pub struct Chaos {
+    rng: u64,
+    drop_percent: u32,
+    max_delay_ms: u64,
+}
+
+impl Chaos {
+    /// Build from config; None when both drops and delays are disabled
+    pub fn from_config(config: &Config) -> Option<Chaos> {
+        if config.chaos_drop_percent == 0 && config.chaos_max_delay_ms == 0 {
+            return None;
+        }
+        Some(Chaos {
+            // xorshift must not start from zero
+            rng: config.chaos_seed.max(1),
+            drop_percent: config.chaos_drop_percent.min(100),
+            max_delay_ms: config.chaos_max_delay_ms,
+        })
+    }
+
+    /// Decide the fate of the next request or event
+    pub fn fate(&mut self) -> Fate {
+        if self.drop_percent > 0 && self.next() % 100 < self.drop_percent as u64 {
+            return Fate::Drop;
+        }
+        if self.max_delay_ms == 0 {
+            return Fate::Now;
+        }
+        match self.next() % (self.max_delay_ms + 1) {
+            0 => Fate::Now,
+            ms => Fate::Delay(ms as f64 / 1000.0),
+        }
+    }
+
+    fn next(&mut self) -> u64 {
+        // xorshift64
+        let mut x = self.rng;
+        x ^= x << 13;
+        x ^= x >> 7;
+        x ^= x << 17;
+        self.rng = x;
+        x
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chaos(drop_percent: u32, max_delay_ms: u64, seed: u64) -> Option<Chaos> {
+        Chaos::from_config(&Config {
+            chaos_drop_percent: drop_percent,
+            chaos_max_delay_ms: max_delay_ms,
+            chaos_seed: seed,
+            ..Config::default()
+        })
+    }
+
+    #[test]
+    fn test_disabled_by_default() {
+        assert!(Chaos::from_config(&Config::default()).is_none());
+    }
+
+    #[test]
+    fn test_same_seed_same_decisions() {
+        let mut a = chaos(30, 500, 7).unwrap();
+        let mut b = chaos(30, 500, 7).unwrap();
+        for _ in 0..50 {
+            assert_eq!(a.fate(), b.fate());
+        }
+    }
+
+    #[test]
+    fn test_always_drop_and_delay_bounds() {
+        let mut drop_all = chaos(100, 0, 1).unwrap();
+        assert!((0..20).all(|_| drop_all.fate() == Fate::Drop));
+
+        let mut delay_only = chaos(0, 250, 3).unwrap();
+        for _ in 0..100 {
+            match delay_only.fate() {
+                Fate::Delay(secs) => assert!(secs > 0.0 && secs <= 0.25),
+                Fate::Now => {}
+                Fate::Drop => panic!("drops are disabled"),
+            }
+        }
+    }
+}
@@ -0,0 +1,199 @@
+//! The plugin's clock
+//!
+//! The time is read through `now_ms` alone, and plugin.rs reads it and
+//! arms Zellij timers through the `Clock` trait. Tests drive both with a
+//! `ManualClock`, which fixes `now_ms` and fires timers as it is advanced,
+//! so time-based code runs natively without waiting. Times are kept as milliseconds since the Unix epoch and
+//! given to clients as RFC 3339 UTC text with milliseconds, such as
+//! `2026-10-16T08:30:00.250Z`, which sorts in time order as plain text.
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+use serde::Serializer;
+
+#[cfg(test)]
+thread_local! {
+    static FIXED_NOW: std::cell::Cell<Option<u64>> = const { std::cell::Cell::new(None) };
+}
+
+/// How early a timer may fire and still count as due, for rounding
+#[cfg(any(target_arch = "wasm32", test))]
+pub const TIMER_SLACK_MS: u64 = 10;
+
+/// Where plugin.rs reads the time and asks for `Event::Timer`s
+#[cfg(any(target_arch = "wasm32", test))]
+pub trait Clock {
+    /// Milliseconds since the Unix epoch
+    fn now_ms(&self) -> u64;
+
+    /// Ask for a timer event after `secs` seconds
+    fn set_timeout(&mut self, secs: f64);
+}
+
+/// The host's time and Zellij's timers
+#[cfg(target_arch = "wasm32")]
+#[derive(Debug, Default)]
+pub struct ZellijClock;
+
+#[cfg(target_arch = "wasm32")]
+impl Clock for ZellijClock {
+    fn now_ms(&self) -> u64 {
+        now_ms()
+    }
+
+    fn set_timeout(&mut self, secs: f64) {
+        zellij_tile::prelude::set_timeout(secs);
+    }
+}
+
+/// A clock tests advance by hand
+///
+/// It fixes `now_ms` on its thread while alive, so State sees the same
+/// time, and keeps timers until advancing passes them.
+#[cfg(test)]
+#[derive(Debug)]
+pub struct ManualClock {
+    /// Due time and requested seconds of each pending timer
+    timers: Vec<(u64, f64)>,
+}
+
+#[cfg(test)]
+impl ManualClock {
+    pub fn new(start_ms: u64) -> Self {
+        set_now_ms(Some(start_ms));
+        ManualClock { timers: Vec::new() }
+    }
+
+    /// Move the time forward, returning the seconds of the timers that
+    /// fired, as their `Event::Timer`s carry, in the order they were due
+    pub fn advance(&mut self, ms: u64) -> Vec<f64> {
+        let now = self.now_ms() + ms;
+        set_now_ms(Some(now));
+        self.timers.sort_by_key(|&(due, _)| due);
+        let due = self.timers.partition_point(|&(due, _)| due <= now);
+        self.timers.drain(..due).map(|(_, secs)| secs).collect()
+    }
+
+    /// Timers not yet fired
+    pub fn pending(&self) -> usize {
+        self.timers.len()
+    }
+}
+
+#[cfg(test)]
+impl Clock for ManualClock {
+    fn now_ms(&self) -> u64 {
+        now_ms()
+    }
+
+    fn set_timeout(&mut self, secs: f64) {
+        self.timers.push((now_ms() + (secs * 1000.0) as u64, secs));
+    }
+}
+
+#[cfg(test)]
+impl Drop for ManualClock {
+    fn drop(&mut self) {
+        set_now_ms(None);
+    }
+}
+
+/// Milliseconds since the Unix epoch
+pub fn now_ms() -> u64 {
+    #[cfg(test)]
+    if let Some(now) = FIXED_NOW.with(|fixed| fixed.get()) {
+        return now;
+    }
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or_default()
+}
+
+/// Fix the time `now_ms` reports on this thread, or let it run again
+#[cfg(test)]
+pub fn set_now_ms(now: Option<u64>) {
+    FIXED_NOW.with(|fixed| fixed.set(now));
+}
+
+/// RFC 3339 UTC text of a time in milliseconds since the Unix epoch
+pub fn rfc3339(ms: u64) -> String {
+    let secs = ms / 1000;
+    let (year, month, day) = civil_from_days((secs / 86_400) as i64);
+    let time = secs % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}.{:03}Z",
+        year,
+        month,
+        day,
+        time / 3600,
+        time / 60 % 60,
+        time % 60,
+        ms % 1000
+    )
+}
+
+/// The current time as RFC 3339 UTC text
+pub fn now_rfc3339() -> String {
+    rfc3339(now_ms())
+}
+
+/// Serialize milliseconds since the Unix epoch as RFC 3339 text
+pub fn serialize_ms<S: Serializer>(ms: &u64, serializer: S) -> Result<S::Ok, S::Error> {
+    serializer.serialize_str(&rfc3339(*ms))
+}
+
+/// Serialize an optional time as RFC 3339 text
+pub fn serialize_opt_ms<S: Serializer>(ms: &Option<u64>, serializer: S) -> Result<S::Ok, S::Error> {
+    match ms {
+        Some(ms) => serializer.serialize_str(&rfc3339(*ms)),
+        None => serializer.serialize_none(),
+    }
+}
+
+/// Year, month and day of a count of days since 1970-01-01
+pub fn civil_from_days(days: i64) -> (i64, u32, u32) {
+    // Howard Hinnant's days_from_civil, inverted
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let doe = z.rem_euclid(146_097);
+    let yoe = (doe - doe / 1460 + doe / 36_524 - doe / 146_096) / 365;
+    let doy = doe - (365 * yoe + yoe / 4 - yoe / 100);
+    let mp = (5 * doy + 2) / 153;
+    let day = (doy - (153 * mp + 2) / 5 + 1) as u32;
+    let month = if mp < 10 { mp + 3 } else { mp - 9 } as u32;
+    let year = yoe + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rfc3339() {
+        assert_eq!(rfc3339(0), "1970-01-01T00:00:00.000Z");
+        assert_eq!(rfc3339(1_709_164_805_250), "2024-02-29T00:00:05.250Z");
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+
+        set_now_ms(Some(60_000));
+        assert_eq!(now_rfc3339(), "1970-01-01T00:01:00.000Z");
+        set_now_ms(None);
+        assert!(now_ms() > 60_000);
+    }
+
+    #[test]
+    fn test_manual_clock_fires_timers_in_order() {
+        let mut clock = ManualClock::new(1_000);
+        clock.set_timeout(5.0);
+        clock.set_timeout(0.5);
+        assert_eq!(now_ms(), 1_000);
+        assert!(clock.advance(400).is_empty());
+        assert_eq!(clock.advance(5_000), vec![0.5, 5.0]);
+        assert_eq!((clock.now_ms(), clock.pending()), (6_400, 0));
+
+        clock.set_timeout(1.0);
+        drop(clock);
+        assert!(now_ms() > 60_000);
+    }
+}
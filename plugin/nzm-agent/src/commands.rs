@@ -1,6 +1,34 @@
-use crate::ipc::{Request, Response, SendKeysParams, PaneIdParam};
+use crate::composite::{condition_holds, substitute, CompositeAction};
+use crate::blocks::{extract_blocks, strip_ansi, truncate_utf8, Block, BlockKind};
+use crate::frame::build_frame;
+use crate::files;
+use crate::git::GitInfo;
+use crate::guards::GuardCondition;
+use crate::encoding::{self, encode_output, OutputEncoding};
+use crate::redact::was_redacted;
+use crate::scripting::RouteDecision;
+use crate::secrets::SecretRef;
+use crate::spawn::{self, PaneDirection};
+use crate::ipc::{AdoptPaneParams, Error, ErrorCode, ApplyPatchParams, ArtifactIdParam, AssertPaneParams, AssertSource, BroadcastParams, CaptureFrameParams, CompleteTaskParams, DispatchTaskParams, EnqueueTaskParams, JobIdParams, CheckpointParams, PaneSize, ResizePaneParams, ToggleFloatingParams, ToggleFullscreenParams, PinPaneParams, StackPanesParams, SwapPanesParams, PaneIdsParam, ResizeDirection, MovePaneToTabParams, NewTabParams, RenameTabParams, CloseTabParams, TabParams, ScrollDirection, ScrollEdge, ScrollPaneParams, ScrollTo, ListTurnsParams, TurnIdParam, UnlockWorktreeParams, ExportConversationParams, CapturePaneParams, DumpScrollbackParams, HandoverContextParams, FanoutParams, ExtractBlocksParams, GetFileParams, GitInfoParams, ListPanesParams, NewPaneParams, RunCommandParams, OpenFloatingCommandParams, ProjectScopeParams, ProjectParam, PutFileParams, RecallParams, ReplayHistoryParams, SelectorParam, SendSecretParams, ShutdownParams, SpawnAgentParams, StoreCaptureParams, TransactionParams, Request, Response, PROTOCOL_VERSION, SendInterruptParams, SendKeysParams, SendRawParams, NextEventParams, WaitCondition, WaitMode, WaitParams, PaneIdParam};
+use crate::selector::Selector;
+use crate::naming::AgentName;
 use crate::state::State;
+use crate::tasks::{StaleTaskPolicy, Task, TaskStatus, TaskTarget};
+use crate::clock;
+use crate::turns::{self, ConversationFormat, Turn};
+#[cfg(any(target_arch = "wasm32", test))]
+use crate::turns::TurnStatus;
+use crate::keys;
+use crate::events::{LoggedEvent, DEFAULT_POLL_TIMEOUT_SECS, MAX_POLL_TIMEOUT_SECS};
+use crate::fanout::DEFAULT_FANOUT_TIMEOUT_SECS;
+#[cfg(any(target_arch = "wasm32", test))]
+use crate::fanout::{self, PendingFanout};
+use crate::handover::{self, PendingHandover};
+use crate::integrity;
+use crate::write_queue::{self, JobStatus, Priority};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use zellij_tile::prelude::PaneInfo;
 
 /// DTO for pane information returned to CLI
 #[derive(Debug, Serialize, Deserialize)]
@@ -9,368 +37,6828 @@ pub struct PaneDto {
     pub title: String,
     pub is_focused: bool,
     pub is_floating: bool,
+    /// Stable handle of the pane's lifetime; ids get reused, handles do not
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handle: Option<String>,
 }
 
+impl From<&PaneInfo> for PaneDto {
+    fn from(p: &PaneInfo) -> Self {
+        PaneDto {
+            id: p.id,
+            title: p.title.clone(),
+            is_focused: p.is_focused,
+            is_floating: p.is_floating,
+            handle: None,
+        }
+    }
+}
+
+/// DTO for tab information returned to CLI
+#[derive(Debug, Serialize, Deserialize)]
+pub struct TabDto {
+    /// Position of the tab, from 0
+    pub index: usize,
+    pub name: String,
+    pub active: bool,
+    /// Terminal panes in the tab
+    pub pane_count: usize,
+}
+
+impl PaneDto {
+    /// DTO of a pane with the handle it holds in `state`
+    pub fn with_handle(p: &PaneInfo, state: &State) -> Self {
+        PaneDto {
+            handle: state.pane_handle(p.id).map(String::from),
+            ..PaneDto::from(p)
+        }
+    }
+}
+
+/// Built-in actions and a one-line summary of each, as listed by describe_actions
+pub const BUILTIN_ACTIONS: &[(&str, &str)] = &[
+    ("list_panes", "List all panes"),
+    ("list_tabs", "List tabs with their position, name, and pane count"),
+    ("get_pane_info", "Get one pane by id"),
+    ("resolve_selector", "List the panes a selector matches, without acting on them"),
+    ("focus_pane", "Bring a pane to the foreground and focus it"),
+    ("close_pane", "Close a terminal pane"),
+    ("resize_pane", "Grow or shrink a pane, or set a floating pane's size"),
+    ("toggle_floating", "Turn a pane floating or embed it in the tiled layout"),
+    ("toggle_fullscreen", "Maximize a pane over its tab, or restore the layout"),
+    ("pin_pane", "Pin a floating pane on top of the tiled panes, or unpin it"),
+    ("stack_panes", "Collapse tiled panes into a stack, optionally expanding one"),
+    ("unstack_panes", "Return stacked panes to the tiled layout, wherever Zellij places them"),
+    ("swap_panes", "Swap two tiled panes in one row or column of aligned panes, one neighbour at a time"),
+    ("new_tab", "Open a tab, optionally named and from a layout, and switch to it"),
+    ("go_to_tab", "Switch to a tab by position or name"),
+    ("rename_tab", "Rename a tab found by position or name"),
+    ("close_tab", "Close a tab and its panes, unless they have unfinished tasks"),
+    ("move_pane_to_tab", "Move a pane to another tab, by position or name"),
+    ("scroll_pane", "Scroll a pane's view by lines or pages, or to a position"),
+    ("send_keys", "Type text and named keys into a pane"),
+    ("send_raw", "Write base64-encoded bytes to a pane as they are"),
+    ("send_interrupt", "Send Ctrl+C, or another key, to a pane"),
+    ("pause_send", "Pause a paced send"),
+    ("resume_send", "Resume a paused send"),
+    ("abort_send", "Drop the unsent rest of a paced send"),
+    ("list_sends", "List paced sends and their progress"),
+    ("interrupt_all", "Interrupt every agent pane, abort their paced sends, and pause task dispatch"),
+    ("resume_automation", "Let task dispatch run again after a pause"),
+    ("send_secret", "Type a secret resolved on the host into a pane"),
+    ("checkpoint", "Mark the current end of a pane's output"),
+    ("extract_blocks", "Extract code blocks and diffs from a pane's output"),
+    ("apply_patch", "Apply a diff from a pane's output with git apply"),
+    ("put_file", "Write a file into a project directory on the host"),
+    ("get_file", "Read a file from a project directory on the host"),
+    ("git_info", "Get branch, dirty state, and ahead/behind counts of a project"),
+    ("store_capture", "Save a pane's output as an artifact"),
+    ("list_artifacts", "List stored artifacts"),
+    ("get_artifact", "Fetch an artifact's content, or a byte range of it"),
+    ("recall", "Return or re-send a recent capture"),
+    ("classify_pane", "Classify a pane with the script hook or its kind's status patterns"),
+    ("describe_actions", "List available actions"),
+    ("get_version", "Report the plugin version, protocol version, and sha256 of the loaded build"),
+    ("stats", "Report estimated memory per buffer, the budget, and evictions"),
+    ("broadcast", "Run one action on many panes, reporting each target's result"),
+    ("transaction", "Validate a group of actions and apply all of them or none"),
+    ("wait", "Wait until all or any of idle, text, pane and task conditions hold"),
+    ("next_event", "Wait for the next event after a sequence number, or a timeout"),
+    ("mirror_state", "Stream compact pane list updates over this pipe"),
+    ("capture_pane", "Return the text a terminal pane currently shows"),
+    ("dump_scrollback", "Return a pane's scrollback, or its last lines, or write it to a file"),
+    ("capture_frame", "Capture a pane's visible viewport as a rows x cols text grid"),
+    ("assert_pane", "Check a pane's output or frame for text or a regex"),
+    ("shutdown", "Persist transcripts, stop accepting requests, optionally unload"),
+    ("enqueue_task", "Queue a prompt for a pane"),
+    ("dispatch_task", "Send the next queued prompt, at most once"),
+    ("complete_task", "Record a dispatched task's result"),
+    ("list_tasks", "List queued, dispatched, and finished tasks"),
+    ("list_turns", "List conversation turns of agent panes, without responses"),
+    ("get_turn", "Get one conversation turn with its prompt and response"),
+    ("export_conversation", "Export an agent's turns as markdown or a chat messages array"),
+    ("handover_context", "Send an agent's conversation, or a summary of it, to its replacement"),
+    ("fanout", "Send one prompt to several agents and return their replies side by side, optionally with a judge's pick"),
+    ("list_agents", "List agent panes, detected by title or running command"),
+    ("project_status", "Report a project's agents, queue, sends, git state and recent events"),
+    ("list_kinds", "List built-in and configured agent kinds"),
+    ("list_schedules", "List scheduled actions with their next run and last outcome"),
+    ("new_pane", "Open a pane running a command, replying with its id once it appears"),
+    ("run_command", "Open a command pane through the plugin API and reply with its id"),
+    ("open_floating_command", "Open a floating command pane at a position and size in percent of the tab"),
+    ("spawn_agent", "Open a pane running an agent of a kind for a project"),
+    ("adopt_pane", "Register an existing pane as an agent, optionally renaming it"),
+    ("lock_worktree", "Claim a pane's project worktree so guarded sends to other panes are refused"),
+    ("unlock_worktree", "Release a project's worktree lock"),
+    ("export_history", "Export recent pane manifests for a bug report"),
+    ("replay_history", "Replay pane manifests through a fresh state and check its index"),
+];
+
+/// Actions whose outcome is only known once a host command has run, so
+/// they cannot take part in a transaction
+const DEFERRED_ACTIONS: &[&str] = &[
+    "apply_patch",
+    "send_secret",
+    "put_file",
+    "get_file",
+    "git_info",
+    "new_pane",
+    "run_command",
+    "open_floating_command",
+    "spawn_agent",
+    "fanout",
+    "next_event",
+    "wait",
+];
+
 /// Dispatch a request to the appropriate handler
-pub fn dispatch_command(req: &Request, state: &State) -> Response {
+///
+/// The user's `route` script hook, when present, may first reject the
+/// request or replace its params. Requests with `explain` set are
+/// described instead of executed, and an `if_revision` precondition is
+/// checked before anything else.
+pub fn dispatch_command(req: &Request, state: &mut State) -> Response {
+    if state.is_shutting_down() {
+        return Response::err_code(&req.id, ErrorCode::ShuttingDown, "plugin is shutting down");
+    }
+    if let Some(expected) = req.if_revision {
+        if expected != state.revision() {
+            return Response::err_code(&req.id, ErrorCode::StaleRevision, format!(
+                "stale revision: pane list is at revision {}, request expected {}",
+                state.revision(),
+                expected
+            ));
+        }
+    }
+    if req.explain {
+        return Response::ok(&req.id, explain_action(&req.id, &req.action, &req.params, state));
+    }
+
+    match route(req, state) {
+        Ok(Some(rewritten)) => dispatch_routed(&rewritten, state),
+        Ok(None) => dispatch_routed(req, state),
+        Err(resp) => resp,
+    }
+}
+
+/// Pass a request through the `route` script hook
+///
+/// Returns the request with its params replaced when the hook rewrote
+/// them, or the error response when it refused the request.
+fn route(req: &Request, state: &State) -> Result<Option<Request>, Response> {
+    let decision = route_decision(&req.action, &req.params, state).unwrap_or(Ok(RouteDecision::Allow));
+
+    match decision {
+        Ok(RouteDecision::Allow) => Ok(None),
+        Ok(RouteDecision::Rewrite(params)) => Ok(Some(Request {
+            id: req.id.clone(),
+            action: req.action.clone(),
+            params,
+            explain: false,
+            if_revision: None,
+        })),
+        Ok(RouteDecision::Deny(reason)) => Err(Response::err_code(&req.id, ErrorCode::Denied, format!("denied by route hook: {}", reason))),
+        Err(e) => Err(Response::err(&req.id, e)),
+    }
+}
+
+/// Ask the route hook about an action, or `None` when no script is
+/// configured. A script that failed to load denies every action rather
+/// than letting them through unchecked.
+fn route_decision(action: &str, params: &serde_json::Value, state: &State) -> Option<Result<RouteDecision, Error>> {
+    if let Some(e) = state.script_error() {
+        return Some(Err(Error::new(ErrorCode::Denied, format!("denied by route hook: script failed to load: {}", e))));
+    }
+    state.scripts().map(|hooks| hooks.route(action, params, state.panes()).map_err(Error::from))
+}
+
+/// Describe how an action would be routed, which panes it targets, and
+/// the effects it would produce, without executing it
+fn explain_action(id: &str, action: &str, params: &serde_json::Value, state: &mut State) -> serde_json::Value {
+    let mut plan = serde_json::json!({
+        "explain": true,
+        "action": action,
+        "params": params,
+        "policy": [],
+        "targets": [],
+        "effects": [],
+    });
+
+    let mut params = params.clone();
+    if let Some(decision) = route_decision(action, &params, state) {
+        let (check, error) = match decision {
+            Ok(RouteDecision::Allow) => (serde_json::json!({ "check": "route_hook", "result": "allow" }), None),
+            Ok(RouteDecision::Rewrite(rewritten)) => {
+                params = rewritten;
+                (serde_json::json!({ "check": "route_hook", "result": "rewrite", "params": params }), None)
+            }
+            Ok(RouteDecision::Deny(reason)) => (
+                serde_json::json!({ "check": "route_hook", "result": "deny", "reason": reason }),
+                Some(format!("denied by route hook: {}", reason)),
+            ),
+            Err(e) => (serde_json::json!({ "check": "route_hook", "result": "error" }), Some(e.message)),
+        };
+        plan["policy"] = serde_json::json!([check]);
+        if let Some(error) = error {
+            plan["valid"] = serde_json::json!(false);
+            plan["error"] = serde_json::json!(error);
+            return plan;
+        }
+    }
+    plan["targets"] = serde_json::json!(explain_targets(&params, state));
+
+    if let Some(composite) = state.config().composite_action(action).cloned() {
+        let args = match &params {
+            serde_json::Value::Null => serde_json::json!({}),
+            params => params.clone(),
+        };
+        let mut valid = true;
+        let mut steps = Vec::with_capacity(composite.steps.len());
+        for (index, step) in composite.steps.iter().enumerate() {
+            let mut entry = serde_json::json!({ "index": index, "name": step.action, "when": step.when });
+            if step.when.as_deref().is_some_and(|when| !condition_holds(when, &args)) {
+                entry["skipped"] = serde_json::json!(true);
+            } else {
+                match substitute(&step.params, &args) {
+                    Ok(step_params) => {
+                        let step_plan = explain_action(id, &step.action, &step_params, state);
+                        valid &= step_plan["valid"] != false;
+                        entry["plan"] = step_plan;
+                    }
+                    Err(e) => {
+                        valid = false;
+                        entry["error"] = serde_json::json!(e);
+                    }
+                }
+            }
+            steps.push(entry);
+        }
+        plan["steps"] = serde_json::json!(steps);
+        plan["valid"] = serde_json::json!(valid);
+        return plan;
+    }
+
+    let req = Request {
+        id: id.to_string(),
+        action: action.to_string(),
+        params,
+        explain: false,
+        if_revision: None,
+    };
+    let Some(resp) = dispatch_pure(&req, state) else {
+        // Handlers that change state are described without running
+        if BUILTIN_ACTIONS.iter().any(|(name, _)| *name == action) {
+            plan["valid"] = serde_json::json!(true);
+            plan["state_change"] = serde_json::json!(true);
+        } else {
+            plan["valid"] = serde_json::json!(false);
+            plan["error"] = serde_json::json!(format!("unknown action: {}", action));
+        }
+        return plan;
+    };
+    plan["valid"] = serde_json::json!(resp.success);
+    match (resp.data, resp.error) {
+        (Some(data), _) if data.get("action").is_some() => plan["effects"] = serde_json::json!([data]),
+        (_, Some(error)) => plan["error"] = serde_json::json!(error),
+        _ => {}
+    }
+    plan
+}
+
+/// Resolve the pane-targeting params of a request for explain mode
+fn explain_targets(params: &serde_json::Value, state: &State) -> Vec<serde_json::Value> {
+    let mut targets = Vec::new();
+    for key in ["selector", "send_to", "pane_id"] {
+        let Some(value) = params.get(key) else {
+            continue;
+        };
+        let Ok(selector) = serde_json::from_value::<Selector>(value.clone()) else {
+            continue;
+        };
+        let panes: Vec<PaneDto> = selector.resolve(state).into_iter().map(|pane| PaneDto::with_handle(pane, state)).collect();
+        targets.push(serde_json::json!({
+            "param": key,
+            "selector": selector.to_string(),
+            "panes": panes,
+        }));
+    }
+    targets
+}
+
+/// Dispatch a request that already passed routing
+fn dispatch_routed(req: &Request, state: &mut State) -> Response {
     match req.action.as_str() {
+        "close_tab" => handle_close_tab_validate(req, state),
+        "send_keys" => handle_send_keys_validate(req, state),
+        "pause_send" => handle_control_send(req, state, "pause"),
+        "resume_send" => handle_control_send(req, state, "resume"),
+        "abort_send" => handle_control_send(req, state, "abort"),
+        "interrupt_all" => handle_interrupt_all(req, state),
+        "resume_automation" => handle_resume_automation(req, state),
+        "checkpoint" => handle_checkpoint(req, state),
+        "extract_blocks" => handle_extract_blocks(req, state),
+        "store_capture" => handle_store_capture(req, state),
+        "list_artifacts" => handle_list_artifacts(req, state),
+        "get_artifact" => handle_get_artifact(req, state),
+        "recall" => handle_recall(req, state),
+        "stats" => handle_stats(req, state),
+        "transaction" => handle_transaction(req, state),
+        "broadcast" => handle_broadcast(req, state),
+        "wait" => handle_wait(req, state),
+        "capture_pane" => handle_capture_pane(req, state),
+        "dump_scrollback" => handle_dump_scrollback(req, state),
+        "shutdown" => handle_shutdown(req, state),
+        "enqueue_task" => handle_enqueue_task(req, state),
+        "dispatch_task" => handle_dispatch_task(req, state),
+        "complete_task" => handle_complete_task(req, state),
+        "export_history" => handle_export_history(req, state),
+        "replay_history" => handle_replay_history(req, state),
+        "project_status" => handle_project_status(req, state),
+        "adopt_pane" => handle_adopt_pane(req, state),
+        "lock_worktree" => handle_lock_worktree(req, state),
+        "unlock_worktree" => handle_unlock_worktree(req, state),
+        "list_tasks" => match state.tasks().load_error() {
+            Some(e) => Response::err(&req.id, e),
+            None => Response::ok(&req.id, serde_json::json!({ "tasks": state.tasks().list() })),
+        },
+        "export_conversation" => handle_export_conversation(req, state),
+        "handover_context" => handle_handover_context(req, state),
+        "fanout" => handle_fanout(req, state),
+        _ => match dispatch_pure(req, state) {
+            Some(resp) => resp,
+            None => match state.config().composite_action(&req.action).cloned() {
+                Some(composite) => handle_composite(req, &composite, state),
+                None => Response::err_code(&req.id, ErrorCode::UnknownAction, format!("unknown action: {}", req.action)),
+            },
+        },
+    }
+}
+
+/// Run the handler of an action that only reads plugin state
+///
+/// Explain mode and transactions run these alone; None for any other
+/// action. send_keys is planned here without allocating a job id or
+/// raising backpressure.
+fn dispatch_pure(req: &Request, state: &State) -> Option<Response> {
+    let resp = match req.action.as_str() {
         "list_panes" => handle_list_panes(req, state),
+        "list_tabs" => handle_list_tabs(req, state),
+        "list_schedules" => handle_list_schedules(req, state),
         "get_pane_info" => handle_get_pane_info(req, state),
-        "send_keys" => handle_send_keys_validate(req, state),
+        "resolve_selector" => handle_resolve_selector(req, state),
+        "focus_pane" => handle_focus_pane_validate(req, state),
+        "close_pane" => handle_close_pane_validate(req, state),
+        "resize_pane" => handle_resize_pane_validate(req, state),
+        "toggle_floating" => handle_toggle_floating_validate(req, state),
+        "toggle_fullscreen" => handle_toggle_fullscreen_validate(req, state),
+        "pin_pane" => handle_pin_pane_validate(req, state),
+        "stack_panes" => handle_stack_panes_validate(req, state),
+        "unstack_panes" => handle_unstack_panes_validate(req, state),
+        "swap_panes" => handle_swap_panes_validate(req, state),
+        "new_tab" => handle_new_tab_validate(req, state),
+        "go_to_tab" => handle_go_to_tab_validate(req, state),
+        "rename_tab" => handle_rename_tab_validate(req, state),
+        "move_pane_to_tab" => handle_move_pane_to_tab_validate(req, state),
+        "scroll_pane" => handle_scroll_pane_validate(req, state),
+        "send_keys" => match plan_send_keys(req, state, 0) {
+            Ok((data, _)) => Response::ok(&req.id, data),
+            Err(SendRefusal::Invalid(resp)) => resp,
+            Err(SendRefusal::QueueFull(depth)) => queue_full_response(req, state, "sends", depth).0,
+        },
+        "send_raw" => handle_send_raw_validate(req, state),
         "send_interrupt" => handle_send_interrupt_validate(req, state),
-        _ => Response {
-            id: req.id.clone(),
-            success: false,
-            data: None,
-            error: Some(format!("unknown action: {}", req.action)),
+        "list_sends" => Response::ok(&req.id, serde_json::json!({
+            "sends": state.send_jobs().collect::<Vec<_>>(),
+        })),
+        "apply_patch" => handle_apply_patch_validate(req, state),
+        "put_file" => handle_put_file_validate(req, state),
+        "get_file" => handle_get_file_validate(req, state),
+        "git_info" => handle_git_info(req, state),
+        "send_secret" => handle_send_secret_validate(req, state),
+        "classify_pane" => handle_classify_pane(req, state),
+        "describe_actions" => handle_describe_actions(req, state),
+        "get_version" => handle_get_version(req, state),
+        "next_event" => handle_next_event(req, state),
+        "capture_frame" => handle_capture_frame(req, state),
+        "assert_pane" => handle_assert_pane(req, state),
+        "list_agents" => handle_list_agents(req, state),
+        "new_pane" => handle_new_pane(req, state),
+        "run_command" => handle_run_command(req),
+        "open_floating_command" => handle_open_floating_command(req),
+        "spawn_agent" => handle_spawn_agent(req, state),
+        "list_kinds" => {
+            let mut data = serde_json::json!({ "kinds": state.kinds().kinds().collect::<Vec<_>>() });
+            add_config_errors(&mut data, state, "kind.");
+            Response::ok(&req.id, data)
+        }
+        "list_turns" => handle_list_turns(req, state),
+        "get_turn" => handle_get_turn(req, state),
+        "mirror_state" => Response::ok(&req.id, serde_json::json!({
+            "action": "mirror_state",
+            "revision": state.revision(),
+        })),
+        _ => return None,
+    };
+    Some(resp)
+}
+
+/// Deserialize request params, mapping failures to an error response
+///
+/// Missing params are treated as an empty object.
+fn parse_params<T: DeserializeOwned>(req: &Request) -> Result<T, Response> {
+    let params = match &req.params {
+        serde_json::Value::Null => serde_json::json!({}),
+        params => params.clone(),
+    };
+    serde_json::from_value(params)
+        .map_err(|e| Response::err_code(&req.id, ErrorCode::InvalidParams, format!("invalid params: {}", e)))
+}
+
+/// Handle list_panes action
+///
+/// With `since_revision`, only the panes added, changed, or removed since
+/// then are returned; if that revision is too old, the full list is sent.
+fn handle_list_panes(req: &Request, state: &State) -> Response {
+    let p: ListPanesParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    if let Some(delta) = p.since_revision.and_then(|rev| state.panes_since(rev)) {
+        let added: Vec<PaneDto> = delta.added.into_iter().map(|pane| PaneDto::with_handle(pane, state)).collect();
+        let changed: Vec<PaneDto> = delta.changed.into_iter().map(|pane| PaneDto::with_handle(pane, state)).collect();
+        return Response::ok(&req.id, serde_json::json!({
+            "delta": true,
+            "added": added,
+            "changed": changed,
+            "removed": delta.removed,
+            "revision": state.revision(),
+        }));
+    }
+
+    let panes: Vec<PaneDto> = state.panes().iter().map(|pane| PaneDto::with_handle(pane, state)).collect();
+    Response::ok(&req.id, serde_json::json!({
+        "panes": panes,
+        "revision": state.revision(),
+    }))
+}
+
+/// Handle list_tabs action: tabs as of the last tab update
+fn handle_list_tabs(req: &Request, state: &State) -> Response {
+    let tabs: Vec<TabDto> = state
+        .tabs()
+        .iter()
+        .map(|(position, name)| TabDto {
+            index: *position,
+            name: name.clone(),
+            active: state.active_tab() == Some(*position),
+            pane_count: state.panes().iter().filter(|pane| state.pane_tab(pane.id) == Some(*position)).count(),
+        })
+        .collect();
+    Response::ok(&req.id, serde_json::json!({ "tabs": tabs }))
+}
+
+/// Handle get_pane_info action
+fn handle_get_pane_info(req: &Request, state: &State) -> Response {
+    let p: PaneIdParam = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    match p.pane_id.resolve_one(state) {
+        Ok(pane) => Response::ok(&req.id, serde_json::json!({ "pane": PaneDto::with_handle(pane, state) })),
+        Err(e) => Response::err(&req.id, e),
+    }
+}
+
+/// Handle resolve_selector action: the panes a selector matches right now
+fn handle_resolve_selector(req: &Request, state: &State) -> Response {
+    let p: SelectorParam = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let panes: Vec<PaneDto> = p.selector.resolve(state).into_iter().map(|pane| PaneDto::with_handle(pane, state)).collect();
+    Response::ok(&req.id, serde_json::json!({
+        "selector": p.selector.to_string(),
+        "count": panes.len(),
+        "panes": panes,
+        "revision": state.revision(),
+    }))
+}
+
+/// Validate focus_pane params (focusing happens in plugin.rs with Zellij API)
+fn handle_focus_pane_validate(req: &Request, state: &State) -> Response {
+    let p: PaneIdParam = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let pane = match p.pane_id.resolve_one(state) {
+        Ok(pane) => pane,
+        Err(e) => return Response::err(&req.id, e),
+    };
+
+    Response::ok(&req.id, serde_json::json!({
+        "action": "focus_pane",
+        "pane_id": pane.id,
+    }))
+}
+
+/// Validate close_pane params (closing happens in plugin.rs with Zellij API)
+fn handle_close_pane_validate(req: &Request, state: &State) -> Response {
+    let p: PaneIdParam = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let pane = match p.pane_id.resolve_one(state) {
+        Ok(pane) => pane,
+        Err(_) if matches!(p.pane_id, Selector::Id(id) if state.is_plugin_pane(id)) => {
+            return Response::err(&req.id, format!("cannot close plugin pane: {}", p.pane_id));
+        }
+        Err(e) => return Response::err(&req.id, e),
+    };
+
+    Response::ok(&req.id, serde_json::json!({
+        "action": "close_pane",
+        "pane_id": pane.id,
+    }))
+}
+
+/// Validate toggle_floating params (toggling happens in plugin.rs with Zellij API)
+fn handle_toggle_floating_validate(req: &Request, state: &State) -> Response {
+    let p: ToggleFloatingParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let pane = match p.pane_id.resolve_one(state) {
+        Ok(pane) => pane,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    let floating = p.floating.unwrap_or(!pane.is_floating);
+    let mut data = serde_json::json!({
+        "pane_id": pane.id,
+        "is_floating": floating,
+        "changed": floating != pane.is_floating,
+    });
+    if floating != pane.is_floating {
+        data["action"] = serde_json::json!("toggle_floating");
+    }
+    Response::ok(&req.id, data)
+}
+
+/// Validate toggle_fullscreen params (toggling happens in plugin.rs with Zellij API)
+///
+/// Asking for `fullscreen: false` afterwards restores the layout, whether
+/// or not the pane is still fullscreen by then.
+fn handle_toggle_fullscreen_validate(req: &Request, state: &State) -> Response {
+    let p: ToggleFullscreenParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let pane = match p.pane_id.resolve_one(state) {
+        Ok(pane) => pane,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    let fullscreen = p.fullscreen.unwrap_or(!pane.is_fullscreen);
+    let mut data = serde_json::json!({
+        "pane_id": pane.id,
+        "is_fullscreen": fullscreen,
+        "changed": fullscreen != pane.is_fullscreen,
+    });
+    if fullscreen != pane.is_fullscreen {
+        data["action"] = serde_json::json!("toggle_fullscreen");
+    }
+    Response::ok(&req.id, data)
+}
+
+/// Validate pin_pane params (pinning happens in plugin.rs with Zellij API)
+///
+/// Zellij does not report whether a pane is pinned, so the request says
+/// which state it wants rather than flipping it.
+fn handle_pin_pane_validate(req: &Request, state: &State) -> Response {
+    let p: PinPaneParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let pane = match p.pane_id.resolve_one(state) {
+        Ok(pane) => pane,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    if !pane.is_floating {
+        return Response::err_code(&req.id, ErrorCode::InvalidParams, format!(
+            "invalid params: only floating panes can be pinned; pane {} is tiled",
+            pane.id
+        ));
+    }
+    Response::ok(&req.id, serde_json::json!({
+        "action": "pin_pane",
+        "pane_id": pane.id,
+        "pinned": p.pinned,
+    }))
+}
+
+/// Validate stack_panes params (stacking happens in plugin.rs with Zellij API)
+///
+/// A stack shows one pane at a time; the others collapse to their title
+/// bars. focus_pane expands a different one later.
+fn handle_stack_panes_validate(req: &Request, state: &State) -> Response {
+    let p: StackPanesParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let pane_ids = match tiled_panes(state, &p.pane_ids) {
+        Ok(pane_ids) => pane_ids,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    if pane_ids.len() < 2 {
+        return Response::err_code(&req.id, ErrorCode::InvalidParams, "invalid params: a stack needs at least 2 panes");
+    }
+    if let Some(expand) = p.expand.filter(|id| !pane_ids.contains(id)) {
+        return Response::err_code(&req.id, ErrorCode::InvalidParams, format!("invalid params: pane {} to expand is not in pane_ids", expand));
+    }
+    Response::ok(&req.id, serde_json::json!({
+        "action": "stack_panes",
+        "pane_ids": pane_ids,
+        "expand": p.expand,
+    }))
+}
+
+/// Validate unstack_panes params (unstacking happens in plugin.rs with Zellij API)
+///
+/// Zellij has no call to unstack panes, so plugin.rs floats them and
+/// embeds them again, which puts each back in the tiled layout on its own.
+/// That has limits a native call would not:
+///
+/// - panes come back where Zellij finds room for them, not where the
+///   stack was, and the rest of the layout is split again to fit them;
+/// - a listed pane that was not stacked is moved the same way;
+/// - the tab shows the panes floating for a moment and focus may follow
+///   the last pane embedded.
+fn handle_unstack_panes_validate(req: &Request, state: &State) -> Response {
+    let p: PaneIdsParam = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let pane_ids = match tiled_panes(state, &p.pane_ids) {
+        Ok(pane_ids) => pane_ids,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    if pane_ids.is_empty() {
+        return Response::err_code(&req.id, ErrorCode::InvalidParams, "invalid params: pane_ids is empty");
+    }
+    Response::ok(&req.id, serde_json::json!({
+        "action": "unstack_panes",
+        "pane_ids": pane_ids,
+    }))
+}
+
+/// Validate swap_panes params (swapping happens in plugin.rs with Zellij API)
+///
+/// Zellij only moves a pane by swapping it with a neighbour, so the two
+/// panes must lie in one row or column of panes that share full edges.
+/// plugin.rs moves the first pane `steps` times towards the other, which
+/// shifts the panes between back by one, then moves the other pane back
+/// `steps - 1` times to where the first one was. Zellij has no call to
+/// swap two panes directly, so:
+///
+/// - panes that are not in one such row or column cannot be swapped, even
+///   when a native swap could;
+/// - the tab redraws after each of the `2 * steps - 1` moves;
+/// - the path is worked out from the layout seen here, so panes opened or
+///   closed before plugin.rs runs the moves can leave the panes elsewhere.
+fn handle_swap_panes_validate(req: &Request, state: &State) -> Response {
+    let p: SwapPanesParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let (pane_id, other_pane_id) = match (p.pane_id.resolve_one(state), p.other_pane_id.resolve_one(state)) {
+        (Ok(pane), Ok(other)) => (pane.id, other.id),
+        (Err(e), _) | (_, Err(e)) => return Response::err(&req.id, e),
+    };
+    if let Err(e) = tiled_panes(state, &[pane_id, other_pane_id]) {
+        return Response::err(&req.id, e);
+    }
+    if pane_id == other_pane_id {
+        return Response::err_code(&req.id, ErrorCode::InvalidParams, "invalid params: cannot swap a pane with itself");
+    }
+    let Some((direction, steps)) = pane_path(state, pane_id, other_pane_id) else {
+        return Response::err_code(&req.id, ErrorCode::InvalidParams, format!(
+            "invalid params: panes {} and {} are not in one row or column of aligned panes",
+            pane_id, other_pane_id
+        ));
+    };
+    Response::ok(&req.id, serde_json::json!({
+        "action": "swap_panes",
+        "pane_id": pane_id,
+        "other_pane_id": other_pane_id,
+        "direction": direction,
+        "steps": steps,
+    }))
+}
+
+/// Direction and number of neighbours from one pane to another, through
+/// panes sharing full edges
+fn pane_path(state: &State, from: u32, to: u32) -> Option<(ResizeDirection, usize)> {
+    let start = state.get_pane(from)?;
+    [ResizeDirection::Left, ResizeDirection::Right, ResizeDirection::Up, ResizeDirection::Down]
+        .into_iter()
+        .find_map(|direction| {
+            let mut pane = start;
+            let mut steps = 0;
+            while let Some(next) = neighbour(state, pane, direction) {
+                steps += 1;
+                if next.id == to {
+                    return Some((direction, steps));
+                }
+                pane = next;
+            }
+            None
+        })
+}
+
+/// The tiled pane on the same tab covering the whole of one edge of `pane`
+fn neighbour<'a>(state: &'a State, pane: &PaneInfo, direction: ResizeDirection) -> Option<&'a PaneInfo> {
+    let tab = state.pane_tab(pane.id);
+    state
+        .panes()
+        .iter()
+        .filter(|p| p.id != pane.id && !p.is_floating && !p.is_suppressed && state.pane_tab(p.id) == tab)
+        .find(|p| {
+            let row = p.pane_y == pane.pane_y && p.pane_rows == pane.pane_rows;
+            let column = p.pane_x == pane.pane_x && p.pane_columns == pane.pane_columns;
+            match direction {
+                ResizeDirection::Left => row && p.pane_x + p.pane_columns == pane.pane_x,
+                ResizeDirection::Right => row && pane.pane_x + pane.pane_columns == p.pane_x,
+                ResizeDirection::Up => column && p.pane_y + p.pane_rows == pane.pane_y,
+                ResizeDirection::Down => column && pane.pane_y + pane.pane_rows == p.pane_y,
+            }
+        })
+}
+
+/// The given panes without repeats, each checked to be open and tiled
+fn tiled_panes(state: &State, pane_ids: &[u32]) -> Result<Vec<u32>, Error> {
+    let mut tiled = Vec::new();
+    for &pane_id in pane_ids {
+        let pane = state
+            .get_pane(pane_id)
+            .ok_or_else(|| Error::new(ErrorCode::NotFound, format!("pane not found: {}", pane_id)))?;
+        if pane.is_floating {
+            return Err(Error::new(
+                ErrorCode::InvalidParams,
+                format!("invalid params: pane {} is floating; only tiled panes stack", pane_id),
+            ));
+        }
+        if !tiled.contains(&pane_id) {
+            tiled.push(pane_id);
+        }
+    }
+    Ok(tiled)
+}
+
+/// Validate new_tab params (opening happens in plugin.rs with Zellij API)
+///
+/// Zellij appends the tab and focuses it, so agents spawned next land in
+/// it. A name already taken by a tab is refused, so tab lookups by name
+/// stay unambiguous.
+fn handle_new_tab_validate(req: &Request, state: &State) -> Response {
+    let p: NewTabParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    if let Some(name) = &p.name {
+        if name.is_empty() {
+            return Response::err_code(&req.id, ErrorCode::InvalidParams, "invalid params: name is empty");
+        }
+        if state.find_tab(name).is_some() {
+            return Response::err(&req.id, format!("tab already exists: {}", name));
+        }
+    }
+    if p.layout.as_deref() == Some("") {
+        return Response::err_code(&req.id, ErrorCode::InvalidParams, "invalid params: layout is empty");
+    }
+    Response::ok(&req.id, serde_json::json!({
+        "action": "new_tab",
+        "name": p.name,
+        "layout": p.layout,
+        "tab_index": state.tabs().len(),
+    }))
+}
+
+/// Position of the tab a request names by `tab_index` or `tab_name`, as of
+/// the last tab update
+fn find_tab(state: &State, tab_index: Option<usize>, tab_name: Option<&str>) -> Result<usize, Error> {
+    match (tab_index, tab_name) {
+        (Some(index), None) if state.tab_name(index).is_some() => Ok(index),
+        (Some(index), None) => Err(Error::new(ErrorCode::NotFound, format!("tab not found: index {}", index))),
+        (None, Some(name)) => state
+            .find_tab(name)
+            .ok_or_else(|| Error::new(ErrorCode::NotFound, format!("tab not found: {}", name))),
+        _ => Err(Error::new(ErrorCode::InvalidParams, "invalid params: give one of tab_index or tab_name")),
+    }
+}
+
+/// Validate go_to_tab params (switching happens in plugin.rs with Zellij API)
+fn handle_go_to_tab_validate(req: &Request, state: &State) -> Response {
+    let p: TabParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let tab_index = match find_tab(state, p.tab_index, p.tab_name.as_deref()) {
+        Ok(index) => index,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    let changed = state.active_tab() != Some(tab_index);
+    let mut data = serde_json::json!({
+        "tab_index": tab_index,
+        "tab_name": state.tab_name(tab_index),
+        "changed": changed,
+    });
+    if changed {
+        data["action"] = serde_json::json!("go_to_tab");
+    }
+    Response::ok(&req.id, data)
+}
+
+/// Validate rename_tab params (renaming happens in plugin.rs with Zellij API)
+///
+/// As with new_tab, a name another tab has is refused.
+fn handle_rename_tab_validate(req: &Request, state: &State) -> Response {
+    let p: RenameTabParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let tab_index = match find_tab(state, p.tab_index, p.tab_name.as_deref()) {
+        Ok(index) => index,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    if p.name.is_empty() {
+        return Response::err_code(&req.id, ErrorCode::InvalidParams, "invalid params: name is empty");
+    }
+    let old_name = state.tab_name(tab_index);
+    if state.find_tab(&p.name).is_some_and(|other| other != tab_index) {
+        return Response::err(&req.id, format!("tab already exists: {}", p.name));
+    }
+    let changed = old_name != Some(p.name.as_str());
+    let mut data = serde_json::json!({
+        "tab_index": tab_index,
+        "old_name": old_name,
+        "name": p.name,
+        "changed": changed,
+    });
+    if changed {
+        data["action"] = serde_json::json!("rename_tab");
+    }
+    Response::ok(&req.id, data)
+}
+
+/// Validate close_tab params (closing happens in plugin.rs with Zellij API)
+///
+/// A tab whose panes have pending or dispatched tasks is only closed with
+/// `force`; the tasks are then left to the stale task policy.
+fn handle_close_tab_validate(req: &Request, state: &mut State) -> Response {
+    let p: CloseTabParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let tab_index = match find_tab(state, p.tab_index, p.tab_name.as_deref()) {
+        Ok(index) => index,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    let pane_ids: Vec<u32> = state
+        .panes()
+        .iter()
+        .filter(|pane| state.pane_tab(pane.id) == Some(tab_index))
+        .map(|pane| pane.id)
+        .collect();
+    let unfinished: Vec<String> = state
+        .tasks()
+        .list()
+        .iter()
+        .filter(|task| matches!(task.status, TaskStatus::Pending | TaskStatus::Dispatched))
+        .filter(|task| pane_ids.contains(&task.pane_id))
+        .map(|task| task.id.clone())
+        .collect();
+    if !unfinished.is_empty() && !p.force {
+        return Response::err(&req.id, format!(
+            "unfinished tasks in tab {}: {} (pass force to close it anyway)",
+            tab_index,
+            unfinished.join(", ")
+        ));
+    }
+    Response::ok(&req.id, serde_json::json!({
+        "action": "close_tab",
+        "tab_index": tab_index,
+        "tab_name": state.tab_name(tab_index),
+        "pane_ids": pane_ids,
+        "unfinished_tasks": unfinished,
+    }))
+}
+
+/// Validate move_pane_to_tab params (moving happens in plugin.rs with Zellij API)
+///
+/// Tabs are looked up in the last tab update. A name with no tab is an
+/// error unless `create` asks for a new tab, which Zellij appends.
+fn handle_move_pane_to_tab_validate(req: &Request, state: &State) -> Response {
+    let p: MovePaneToTabParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let pane = match p.pane_id.resolve_one(state) {
+        Ok(pane) => pane,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    let tab_index = match (p.tab_index, p.tab_name.as_deref()) {
+        (Some(index), None) if state.tab_name(index).is_some() => Some(index),
+        (Some(index), None) => return Response::err_code(&req.id, ErrorCode::NotFound, format!("tab not found: index {}", index)),
+        (None, Some(name)) => match state.find_tab(name) {
+            Some(index) => Some(index),
+            None if p.create => None,
+            None => return Response::err_code(&req.id, ErrorCode::NotFound, format!("tab not found: {}", name)),
+        },
+        _ => return Response::err_code(&req.id, ErrorCode::InvalidParams, "invalid params: give one of tab_index or tab_name"),
+    };
+
+    let changed = tab_index.is_none() || tab_index != state.pane_tab(pane.id);
+    let mut data = serde_json::json!({
+        "pane_id": pane.id,
+        "tab_index": tab_index,
+        "tab_name": tab_index.and_then(|index| state.tab_name(index)).or(p.tab_name.as_deref()),
+        "created": tab_index.is_none(),
+        "changed": changed,
+        "focus": p.focus,
+    });
+    if changed {
+        data["action"] = serde_json::json!("move_pane_to_tab");
+    }
+    Response::ok(&req.id, data)
+}
+
+/// Percent of the tab Zellij moves a tiled pane edge by per resize step
+const RESIZE_STEP_PERCENT: i32 = 5;
+
+/// Validate resize_pane params (resizing happens in plugin.rs with Zellij API)
+///
+/// Relative sizes become a signed count of resize steps; absolute ones are
+/// only possible for floating panes, whose size Zellij can set directly.
+fn handle_resize_pane_validate(req: &Request, state: &State) -> Response {
+    let p: ResizePaneParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let pane = match p.pane_id.resolve_one(state) {
+        Ok(pane) => pane,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    let absolute = p.width.is_some() || p.height.is_some();
+    let steps = match (p.amount, p.percent, absolute) {
+        (Some(amount), None, false) => amount,
+        (None, Some(percent), false) => {
+            // Round half away from zero so a small request still moves
+            percent.signum() * ((percent.abs() + RESIZE_STEP_PERCENT / 2) / RESIZE_STEP_PERCENT).max(1)
+        }
+        (None, None, true) => {
+            if !pane.is_floating {
+                return Response::err_code(&req.id, ErrorCode::InvalidParams, format!(
+                    "invalid params: width and height only apply to floating panes; pane {} is tiled",
+                    pane.id
+                ));
+            }
+            if p.direction.is_some() {
+                return Response::err_code(&req.id, ErrorCode::InvalidParams, "invalid params: direction does not apply to width and height");
+            }
+            let size = |size: Option<PaneSize>, name: &str| -> Result<Option<String>, String> {
+                match size {
+                    None => Ok(None),
+                    Some(PaneSize::Cells(n)) if n > 0 => Ok(Some(n.to_string())),
+                    Some(PaneSize::Spec(spec)) => match spec.strip_suffix('%').unwrap_or(&spec).parse::<usize>() {
+                        Ok(n) if n > 0 && (n <= 100 || !spec.ends_with('%')) => Ok(Some(spec)),
+                        _ => Err(format!("invalid params: bad {}: {:?}", name, spec)),
+                    },
+                    Some(PaneSize::Cells(_)) => Err(format!("invalid params: {} must be positive", name)),
+                }
+            };
+            let (width, height) = match (size(p.width, "width"), size(p.height, "height")) {
+                (Ok(width), Ok(height)) => (width, height),
+                (Err(e), _) | (_, Err(e)) => return Response::err_code(&req.id, ErrorCode::InvalidParams, e),
+            };
+            return Response::ok(&req.id, serde_json::json!({
+                "action": "resize_pane",
+                "pane_id": pane.id,
+                "width": width,
+                "height": height,
+            }));
+        }
+        _ => {
+            return Response::err_code(&req.id, ErrorCode::InvalidParams, "invalid params: give one of amount, percent, or width/height");
+        }
+    };
+    if steps == 0 {
+        return Response::err_code(&req.id, ErrorCode::InvalidParams, "invalid params: resize by zero");
+    }
+    // Steps past the whole tab only repeat a resize that cannot happen
+    let steps = steps.clamp(-100 / RESIZE_STEP_PERCENT, 100 / RESIZE_STEP_PERCENT);
+
+    Response::ok(&req.id, serde_json::json!({
+        "action": "resize_pane",
+        "pane_id": pane.id,
+        "direction": p.direction,
+        "steps": steps,
+    }))
+}
+
+/// Most single-line or single-page scrolls one scroll_pane makes
+const MAX_SCROLL_STEPS: usize = 10_000;
+
+/// Validate scroll_pane params (scrolling happens in plugin.rs with Zellij API)
+///
+/// Zellij scrolls a line or a page per call, so the effect carries an
+/// optional end to jump to first, then signed line and page counts
+/// (positive scrolls down). A line position is reached from the top.
+fn handle_scroll_pane_validate(req: &Request, state: &State) -> Response {
+    let p: ScrollPaneParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let pane = match p.pane_id.resolve_one(state) {
+        Ok(pane) => pane,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    let (from, lines, pages) = match (p.direction, p.to) {
+        (Some(direction), None) => {
+            let (lines, pages) = match (p.lines, p.pages) {
+                (None, None) => (1, 0),
+                (lines, pages) => (lines.unwrap_or(0), pages.unwrap_or(0)),
+            };
+            if lines > MAX_SCROLL_STEPS || pages > MAX_SCROLL_STEPS {
+                return Response::err_code(&req.id, ErrorCode::InvalidParams, format!(
+                    "invalid params: scroll at most {} lines or pages",
+                    MAX_SCROLL_STEPS
+                ));
+            }
+            let sign = if direction == ScrollDirection::Up { -1 } else { 1 };
+            (None, sign * lines as i64, sign * pages as i64)
+        }
+        (None, Some(to)) if p.lines.is_none() && p.pages.is_none() => match to {
+            ScrollTo::Edge(edge) => (Some(edge), 0, 0),
+            ScrollTo::Line(line) if line <= MAX_SCROLL_STEPS => (Some(ScrollEdge::Top), line as i64, 0),
+            ScrollTo::Line(_) => {
+                return Response::err_code(&req.id, ErrorCode::InvalidParams, format!("invalid params: line position past {}", MAX_SCROLL_STEPS));
+            }
         },
+        _ => return Response::err_code(&req.id, ErrorCode::InvalidParams, "invalid params: give direction (with lines or pages) or to"),
+    };
+
+    Response::ok(&req.id, serde_json::json!({
+        "action": "scroll_pane",
+        "pane_id": pane.id,
+        "from": from,
+        "lines": lines,
+        "pages": pages,
+    }))
+}
+
+/// Why a send_keys request cannot go out
+enum SendRefusal {
+    Invalid(Response),
+    /// The paced send queue is full at this depth
+    QueueFull(usize),
+}
+
+/// Validate send_keys params (actual sending happens in plugin.rs with Zellij API)
+fn handle_send_keys_validate(req: &Request, state: &mut State) -> Response {
+    match plan_send_keys(req, state, 0) {
+        Ok((mut data, paced)) => {
+            if paced {
+                // Paced sends are jobs the caller can pause, resume, or abort
+                data["job_id"] = serde_json::json!(state.allocate_job_id());
+            }
+            Response::ok(&req.id, data)
+        }
+        Err(SendRefusal::Invalid(resp)) => resp,
+        Err(SendRefusal::QueueFull(depth)) => queue_full(req, state, "sends", depth),
+    }
+}
+
+/// The send_keys effect for a request and whether it is paced, before a
+/// job id is given to it
+///
+/// `reserved` paced sends, planned but not yet queued, count against the
+/// queue's capacity.
+fn plan_send_keys(req: &Request, state: &State, reserved: usize) -> Result<(serde_json::Value, bool), SendRefusal> {
+    let p: SendKeysParams = parse_params(req).map_err(SendRefusal::Invalid)?;
+    let invalid = |e: Error| SendRefusal::Invalid(Response::err(&req.id, e));
+
+    let pane_id = p.pane_id.resolve_one(state).map_err(invalid)?.id;
+    if p.chunk_bytes == Some(0) {
+        return Err(invalid(Error::new(ErrorCode::InvalidParams, "invalid params: chunk_bytes must be positive")));
+    }
+    check_guards(state, &req.action, pane_id).map_err(invalid)?;
+    if p.chunk_bytes.is_some() {
+        let depth = state
+            .send_jobs()
+            .filter(|j| matches!(j.status, JobStatus::Running | JobStatus::Paused))
+            .count()
+            + reserved;
+        if depth >= state.config().send_queue_capacity {
+            return Err(SendRefusal::QueueFull(depth));
+        }
+    }
+
+    let keys = keys::keys_text(&p.keys).map_err(invalid)?;
+    let text = match p.text {
+        Some(text) => text,
+        None if !keys.is_empty() => String::new(),
+        None => return Err(invalid(Error::new(ErrorCode::InvalidParams, "invalid params: send_keys needs text or keys"))),
+    };
+    let mut text = if p.bracketed {
+        write_queue::bracketed_paste(&text)
+    } else {
+        text
+    };
+    text.push_str(&keys);
+
+    // Return success with params for plugin.rs to execute
+    let data = serde_json::json!({
+        "action": "send_keys",
+        "pane_id": pane_id,
+        "text": text,
+        "enter": p.enter,
+        "priority": p.priority,
+        "chunk_bytes": p.chunk_bytes,
+    });
+    Ok((data, p.chunk_bytes.is_some()))
+}
+
+/// Largest write send_raw accepts, in decoded bytes
+const MAX_RAW_BYTES: usize = 64 * 1024;
+
+/// Validate send_raw params; bytes that JSON text would mangle go as base64
+fn handle_send_raw_validate(req: &Request, state: &State) -> Response {
+    let p: SendRawParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let pane = match p.pane_id.resolve_one(state) {
+        Ok(pane) => pane,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    let bytes = match encoding::decode_base64("bytes", &p.bytes) {
+        Ok(bytes) => bytes,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    if bytes.is_empty() || bytes.len() > MAX_RAW_BYTES {
+        return Response::err_code(&req.id, ErrorCode::InvalidParams, format!(
+            "invalid params: bytes decodes to {} bytes, send 1 to {}",
+            bytes.len(),
+            MAX_RAW_BYTES
+        ));
+    }
+    if let Err(e) = check_guards(state, &req.action, pane.id) {
+        return Response::err(&req.id, e);
+    }
+
+    Response::ok(&req.id, serde_json::json!({
+        "action": "send_raw",
+        "pane_id": pane.id,
+        "bytes": p.bytes,
+        "length": bytes.len(),
+        "priority": p.priority,
+    }))
+}
+
+/// Refuse a request because a queue is at capacity
+///
+/// The error carries the queue's depth and capacity, and a backpressure
+/// event tells mirror_state subscribers to slow down.
+fn queue_full(req: &Request, state: &mut State, queue: &str, depth: usize) -> Response {
+    let (response, info) = queue_full_response(req, state, queue, depth);
+    state.emit_event("backpressure", info);
+    response
+}
+
+/// The queue-full error and its backpressure details, without the event
+fn queue_full_response(req: &Request, state: &State, queue: &str, depth: usize) -> (Response, serde_json::Value) {
+    let capacity = match queue {
+        "tasks" => state.config().task_queue_capacity,
+        _ => state.config().send_queue_capacity,
+    };
+    let info = serde_json::json!({ "queue": queue, "depth": depth, "capacity": capacity });
+    let mut response = Response::err_code(&req.id, ErrorCode::QueueFull, format!("queue full: {} holds {} of {}", queue, depth, capacity));
+    response.data = Some(info.clone());
+    (response, info)
+}
+
+/// Handle pause_send, resume_send and abort_send actions
+///
+/// The job's status changes here; plugin.rs applies the change to its
+/// write queue.
+fn handle_control_send(req: &Request, state: &mut State, op: &str) -> Response {
+    let p: JobIdParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let Some(job) = state.send_job_mut(&p.job_id) else {
+        return Response::err_code(&req.id, ErrorCode::NotFound, format!("send not found: {}", p.job_id));
+    };
+    let next = match (op, job.status) {
+        ("pause", JobStatus::Running) => JobStatus::Paused,
+        ("resume", JobStatus::Paused) => JobStatus::Running,
+        ("abort", JobStatus::Running | JobStatus::Paused) => JobStatus::Aborted,
+        (_, status) => {
+            let status = serde_json::to_value(status).unwrap_or_default();
+            return Response::err(&req.id, format!(
+                "cannot {} send {}: it is {}",
+                op,
+                p.job_id,
+                status.as_str().unwrap_or("finished")
+            ));
+        }
+    };
+    job.status = next;
+
+    Response::ok(&req.id, serde_json::json!({
+        "action": "control_send",
+        "op": op,
+        "job_id": job.id,
+        "pane_id": job.pane_id,
+        "status": job.status,
+    }))
+}
+
+/// Validate send_interrupt params
+fn handle_send_interrupt_validate(req: &Request, state: &State) -> Response {
+    let p: SendInterruptParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let pane = match p.pane_id.resolve_one(state) {
+        Ok(pane) => pane,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    let key = p.key.as_deref().unwrap_or(keys::INTERRUPT_KEY);
+    let Some(sequence) = keys::key_sequence(key) else {
+        return Response::err_code(&req.id, ErrorCode::InvalidParams, format!("invalid params: unknown key: {}", key));
+    };
+
+    Response::ok(&req.id, serde_json::json!({
+        "action": "send_interrupt",
+        "pane_id": pane.id,
+        "priority": p.priority.unwrap_or(Priority::Urgent),
+        "keys": sequence,
+    }))
+}
+
+/// Handle interrupt_all action: stop every agent pane, optionally of one project
+///
+/// Paced sends to those panes are aborted and task dispatch is paused for
+/// the scope until resume_automation. plugin.rs writes the interrupts.
+fn handle_interrupt_all(req: &Request, state: &mut State) -> Response {
+    let p: ProjectScopeParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let interrupted: Vec<serde_json::Value> = state
+        .panes()
+        .iter()
+        .filter_map(|pane| Some((pane, state.agent_name(pane)?.0)))
+        .filter(|(_, name)| p.project.is_none() || name.project == p.project)
+        .map(|(pane, name)| {
+            let mut entry = serde_json::json!({ "pane_id": pane.id, "title": pane.title });
+            if let Ok(name) = serde_json::to_value(name) {
+                merge_json(&mut entry, name);
+            }
+            entry
+        })
+        .collect();
+    let pane_ids: Vec<u32> = interrupted
+        .iter()
+        .filter_map(|entry| entry["pane_id"].as_u64().map(|id| id as u32))
+        .collect();
+
+    let job_ids: Vec<String> = state
+        .send_jobs()
+        .filter(|j| pane_ids.contains(&j.pane_id) && matches!(j.status, JobStatus::Running | JobStatus::Paused))
+        .map(|j| j.id.clone())
+        .collect();
+    for id in &job_ids {
+        if let Some(job) = state.send_job_mut(id) {
+            job.status = JobStatus::Aborted;
+        }
+    }
+    state.pause_automation(p.project.clone(), "interrupt_all");
+
+    Response::ok(&req.id, serde_json::json!({
+        "action": "interrupt_all",
+        "pane_ids": pane_ids,
+        "job_ids": job_ids,
+        "interrupted": interrupted,
+        "paused": p.project.as_deref().unwrap_or("all"),
+    }))
+}
+
+/// Handle resume_automation action: lift a pause, or every pause without a project
+fn handle_resume_automation(req: &Request, state: &mut State) -> Response {
+    let p: ProjectScopeParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let resumed: Vec<String> = state
+        .resume_automation(p.project.as_deref())
+        .into_iter()
+        .map(|scope| scope.unwrap_or_else(|| "all".to_string()))
+        .collect();
+    Response::ok(&req.id, serde_json::json!({ "resumed": resumed }))
+}
+
+/// Validate send_secret params; the value is resolved on the host by plugin.rs
+fn handle_send_secret_validate(req: &Request, state: &State) -> Response {
+    let p: SendSecretParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let secret = match SecretRef::parse(&p.secret_ref) {
+        Ok(secret) => secret,
+        Err(e) => return Response::err_code(&req.id, ErrorCode::InvalidParams, format!("invalid params: {}", e)),
+    };
+
+    let pane_id = match p.selector.resolve_one(state) {
+        Ok(pane) => pane.id,
+        Err(e) => return Response::err(&req.id, e),
+    };
+
+    Response::ok(&req.id, serde_json::json!({
+        "action": "send_secret",
+        "pane_id": pane_id,
+        "secret_ref": secret.to_string(),
+        "enter": p.enter,
+        "priority": p.priority,
+    }))
+}
+
+/// Handle checkpoint action: mark the current end of a pane's output
+fn handle_checkpoint(req: &Request, state: &mut State) -> Response {
+    let p: CheckpointParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let pane_id = match p.selector.resolve_one(state) {
+        Ok(pane) => pane.id,
+        Err(e) => return Response::err(&req.id, e),
+    };
+
+    let line = state.set_checkpoint(pane_id, &p.name);
+    Response::ok(&req.id, serde_json::json!({
+        "pane_id": pane_id,
+        "name": p.name,
+        "line": line,
+    }))
+}
+
+/// Handle extract_blocks action: parse code blocks and diffs from captured output
+///
+/// Blocks share one `max_response_bytes` budget; once it runs out the rest
+/// are cut like captures are, and the reply is marked `truncated`.
+fn handle_extract_blocks(req: &Request, state: &mut State) -> Response {
+    let p: ExtractBlocksParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let pane_id = match p.selector.resolve_one(state) {
+        Ok(pane) => pane.id,
+        Err(e) => return Response::err(&req.id, e),
+    };
+
+    let blocks = match pane_blocks(state, pane_id, p.since_checkpoint.as_deref()) {
+        Ok(blocks) => blocks,
+        Err(e) => return Response::err(&req.id, e),
+    };
+
+    let redacted = blocks.iter().any(|b| was_redacted(&[&b.content]));
+    for block in &blocks {
+        state.push_capture(pane_id, block.content.clone());
+    }
+    if !p.store {
+        // One budget for all blocks, so many small ones cannot add up past it
+        let mut budget = state.config().max_response_bytes;
+        let mut items = Vec::with_capacity(blocks.len());
+        for block in blocks {
+            let mut item = serde_json::json!({
+                "kind": block.kind,
+                "language": block.language,
+            });
+            match budgeted_content(state, "block", Some(pane_id), &block.content, &mut budget) {
+                Ok(limited) => merge_json(&mut item, limited),
+                Err(e) => return Response::err(&req.id, e),
+            }
+            items.push(item);
+        }
+        let truncated = items.iter().any(|item| item.get("truncated").is_some());
+        let mut data = serde_json::json!({
+            "pane_id": pane_id,
+            "blocks": items,
+            "redacted": redacted,
+        });
+        if truncated {
+            data["truncated"] = true.into();
+        }
+        return Response::ok(&req.id, data);
+    }
+
+    let mut stored = Vec::with_capacity(blocks.len());
+    for block in blocks {
+        match state.artifacts().put("block", Some(pane_id), &block.content) {
+            Ok(meta) => stored.push(serde_json::json!({
+                "kind": block.kind,
+                "language": block.language,
+                "artifact_id": meta.id,
+                "size": meta.size,
+            })),
+            Err(e) => return Response::err(&req.id, format!("artifact store failed: {}", e)),
+        }
+    }
+    Response::ok(&req.id, serde_json::json!({
+        "pane_id": pane_id,
+        "blocks": stored,
+        "redacted": redacted,
+    }))
+}
+
+/// Handle store_capture action: save a pane's captured output as an artifact
+fn handle_store_capture(req: &Request, state: &mut State) -> Response {
+    let p: StoreCaptureParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let pane_id = match p.selector.resolve_one(state) {
+        Ok(pane) => pane.id,
+        Err(e) => return Response::err(&req.id, e),
+    };
+
+    let text = match pane_output(state, pane_id, p.since_checkpoint.as_deref()) {
+        Ok(lines) => lines.join("\n"),
+        Err(e) => return Response::err(&req.id, e),
+    };
+
+    let redacted = was_redacted(&[&text]);
+    state.push_capture(pane_id, text.clone());
+    match state.artifacts().put("capture", Some(pane_id), &text) {
+        Ok(meta) => Response::ok(&req.id, serde_json::json!({
+            "artifact": meta,
+            "redacted": redacted,
+        })),
+        Err(e) => Response::err(&req.id, format!("artifact store failed: {}", e)),
+    }
+}
+
+/// Handle list_artifacts action
+fn handle_list_artifacts(req: &Request, state: &mut State) -> Response {
+    Response::ok(&req.id, serde_json::json!({ "artifacts": state.artifacts().list() }))
+}
+
+/// Handle get_artifact action
+///
+/// Replies hold at most max_response_bytes; larger artifacts are read in
+/// ranges by passing each reply's `next_offset` as the next `offset`.
+fn handle_get_artifact(req: &Request, state: &mut State) -> Response {
+    let p: ArtifactIdParam = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let bytes = match state.artifacts().get_bytes(&p.id) {
+        Ok(bytes) => bytes,
+        Err(_) => return Response::err_code(&req.id, ErrorCode::NotFound, format!("artifact not found: {}", p.id)),
+    };
+
+    let Some(rest) = bytes.get(p.offset..) else {
+        return Response::err_code(&req.id, ErrorCode::InvalidParams, format!(
+            "invalid params: offset {} is past the end of artifact {} ({} bytes)",
+            p.offset,
+            p.id,
+            bytes.len()
+        ));
+    };
+    let max = p.length.unwrap_or(usize::MAX).min(state.config().max_response_bytes);
+    let raw = &rest[..rest.len().min(max)];
+    let returned = match p.encoding {
+        // Never split a character in text mode unless the range is too
+        // short to hold one; raw mode cuts at the byte limit
+        OutputEncoding::Text => Some(truncate_utf8_bytes(rest, max)).filter(|r| !r.is_empty()).unwrap_or(raw),
+        OutputEncoding::Base64 => raw,
+    };
+
+    let end = p.offset + returned.len();
+    let mut data = serde_json::json!({
+        "id": p.id,
+        "truncated": end < bytes.len(),
+        "total_bytes": bytes.len(),
+        "offset": p.offset,
+        "returned_bytes": returned.len(),
+    });
+    if end < bytes.len() {
+        data["next_offset"] = serde_json::json!(end);
+    }
+    if let Ok(encoded) = serde_json::to_value(encode_output(returned, p.encoding)) {
+        merge_json(&mut data, encoded);
+    }
+    Response::ok(&req.id, data)
+}
+
+/// Truncate possibly-invalid UTF-8 without cutting a valid character in half
+fn truncate_utf8_bytes(bytes: &[u8], max: usize) -> &[u8] {
+    if bytes.len() <= max {
+        return bytes;
+    }
+    match std::str::from_utf8(&bytes[..max]) {
+        Ok(_) => &bytes[..max],
+        // An incomplete trailing sequence has no error_len; drop it
+        Err(e) if e.error_len().is_none() => &bytes[..e.valid_up_to()],
+        Err(_) => &bytes[..max],
+    }
+}
+
+/// Handle recall action: return or re-send a recent capture
+fn handle_recall(req: &Request, state: &mut State) -> Response {
+    let p: RecallParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let Some(entry) = state.recall(p.n).cloned() else {
+        return Response::err(&req.id, format!(
+            "no capture at position {} ({} remembered)",
+            p.n,
+            state.capture_count()
+        ));
+    };
+
+    if let Some(target) = p.send_to {
+        let pane_id = match target.resolve_one(state) {
+            Ok(pane) => pane.id,
+            Err(e) => return Response::err(&req.id, e),
+        };
+        return Response::ok(&req.id, serde_json::json!({
+            "action": "send_keys",
+            "pane_id": pane_id,
+            "text": entry.text,
+            "enter": p.enter,
+            "source_pane_id": entry.pane_id,
+        }));
+    }
+
+    let mut data = serde_json::json!({ "n": p.n, "pane_id": entry.pane_id });
+    match limited_content(state, "capture", Some(entry.pane_id), &entry.text) {
+        Ok(limited) => merge_json(&mut data, limited),
+        Err(e) => return Response::err(&req.id, e),
+    }
+    Response::ok(&req.id, data)
+}
+
+/// Handle next_event action: the first matching event after `after`
+///
+/// When none has been raised yet, returns a next_event effect; plugin.rs
+/// holds the response until one is, or the timeout passes, and builds it
+/// with [`next_event_data`].
+fn handle_next_event(req: &Request, state: &State) -> Response {
+    let p: NextEventParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let timeout_secs = p.timeout_secs.unwrap_or(DEFAULT_POLL_TIMEOUT_SECS);
+    if timeout_secs > MAX_POLL_TIMEOUT_SECS {
+        return Response::err_code(&req.id, ErrorCode::InvalidParams, format!(
+            "invalid params: timeout_secs must be at most {}",
+            MAX_POLL_TIMEOUT_SECS
+        ));
+    }
+    let after = p.after.unwrap_or(state.event_log().last_seq());
+    let next = state.event_log().next_after(after, &p.types);
+    if next.is_some() || timeout_secs == 0 {
+        return Response::ok(&req.id, next_event_data(next, after));
+    }
+    Response::ok(&req.id, serde_json::json!({
+        "action": "next_event",
+        "after": after,
+        "types": p.types,
+        "timeout_secs": timeout_secs,
+    }))
+}
+
+/// A next_event response: the event, or a timeout that left `after` as is
+pub fn next_event_data(next: Option<(&LoggedEvent, bool)>, after: u64) -> serde_json::Value {
+    match next {
+        Some((event, gap)) => {
+            let mut data = serde_json::to_value(event).unwrap_or_default();
+            if gap {
+                // Events after `after` were dropped from the log
+                data["gap"] = true.into();
+            }
+            data
+        }
+        None => serde_json::json!({ "timeout": true, "seq": after }),
+    }
+}
+
+/// Handle wait action: check conditions, waiting for them when unmet
+///
+/// When they do not hold yet, returns a wait effect; plugin.rs holds the
+/// response and checks again with [`wait_result`] as panes, output and
+/// tasks change, until they hold or the timeout passes.
+fn handle_wait(req: &Request, state: &mut State) -> Response {
+    let p: WaitParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    if p.conditions.is_empty() {
+        return Response::err_code(&req.id, ErrorCode::InvalidParams, "invalid params: wait needs at least one condition");
+    }
+    let timeout_secs = p.timeout_secs.unwrap_or(DEFAULT_POLL_TIMEOUT_SECS);
+    if timeout_secs > MAX_POLL_TIMEOUT_SECS {
+        return Response::err_code(&req.id, ErrorCode::InvalidParams, format!(
+            "invalid params: timeout_secs must be at most {}",
+            MAX_POLL_TIMEOUT_SECS
+        ));
+    }
+    let data = match check_wait(state, &p) {
+        Ok(data) => data,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    if data["met"] == true || timeout_secs == 0 {
+        return Response::ok(&req.id, data);
+    }
+    Response::ok(&req.id, serde_json::json!({
+        "action": "wait",
+        "params": req.params,
+        "timeout_secs": timeout_secs,
+    }))
+}
+
+/// Check a held wait again; the response once it is met or `expired`
+#[cfg(any(target_arch = "wasm32", test))]
+pub fn wait_result(state: &mut State, request_id: &str, params: &serde_json::Value, expired: bool) -> Option<Response> {
+    let p: WaitParams = match serde_json::from_value(params.clone()) {
+        Ok(p) => p,
+        Err(e) => return Some(Response::err_code(request_id, ErrorCode::InvalidParams, format!("invalid params: {}", e))),
+    };
+    match check_wait(state, &p) {
+        Ok(mut data) => {
+            if data["met"] == true {
+                Some(Response::ok(request_id, data))
+            } else if expired {
+                data["timeout"] = true.into();
+                Some(Response::ok(request_id, data))
+            } else {
+                None
+            }
+        }
+        Err(e) => Some(Response::err(request_id, e)),
+    }
+}
+
+/// Whether a wait's conditions hold, each and combined
+fn check_wait(state: &mut State, p: &WaitParams) -> Result<serde_json::Value, Error> {
+    let mut results = Vec::new();
+    for condition in &p.conditions {
+        results.push(check_condition(state, condition)?);
+    }
+    let met = match p.mode {
+        WaitMode::All => results.iter().all(|&r| r),
+        WaitMode::Any => results.iter().any(|&r| r),
+    };
+    Ok(serde_json::json!({ "met": met, "conditions": results }))
+}
+
+/// Whether one wait condition holds
+///
+/// A pane that is not open yet, or any more, leaves its condition unmet;
+/// bad params and unknown tasks or checkpoints are errors.
+fn check_condition(state: &mut State, condition: &WaitCondition) -> Result<bool, Error> {
+    match condition {
+        WaitCondition::Idle(selector) | WaitCondition::Text(AssertPaneParams { selector, .. })
+            if selector.resolve(state).is_empty() =>
+        {
+            Ok(false)
+        }
+        WaitCondition::Idle(selector) => {
+            let pane = selector.resolve_one(state)?;
+            let (name, _) = state.agent_name(pane).ok_or_else(|| {
+                Error::new(ErrorCode::InvalidParams, format!("invalid params: pane {} is not an agent", pane.id))
+            })?;
+            Ok(state.kinds().status(&name.kind, state.pane_lines(pane.id)) == Some("idle"))
+        }
+        WaitCondition::Text(assert) => assert_pane(state, assert).map(|data| data["passed"] == true),
+        WaitCondition::PaneExists(selector) => Ok(!selector.resolve(state).is_empty()),
+        WaitCondition::Task(id) => match state.tasks().get(id) {
+            Some(task) => Ok(matches!(task.status, TaskStatus::Completed | TaskStatus::Failed)),
+            None => Err(Error::new(ErrorCode::NotFound, format!("task not found: {}", id))),
+        },
+    }
+}
+
+/// Handle capture_pane action: the visible viewport as text
+///
+/// The text is kept for `recall`. Trailing blank rows are dropped, and
+/// text past `max_response_bytes` is cut with the whole of it saved as an
+/// artifact.
+fn handle_capture_pane(req: &Request, state: &mut State) -> Response {
+    let p: CapturePaneParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    if state.get_pane(p.pane_id).is_none() {
+        return Response::err_code(&req.id, ErrorCode::NotFound, format!("pane not found: {}", p.pane_id));
+    }
+    let viewport = state.viewport_lines(p.pane_id);
+    let rows = viewport.len();
+    let mut lines: Vec<String> = match p.ansi {
+        true => viewport.to_vec(),
+        false => viewport.iter().map(|line| strip_ansi(line)).collect(),
+    };
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+    let text = lines.join("\n");
+
+    let mut data = serde_json::json!({
+        "pane_id": p.pane_id,
+        "rows": rows,
+        "truncated": false,
+        "redacted": was_redacted(&lines),
+    });
+    state.push_capture(p.pane_id, text.clone());
+    match limited_content(state, "capture", Some(p.pane_id), &text) {
+        Ok(content) => merge_json(&mut data, content),
+        Err(e) => return Response::err(&req.id, e),
+    }
+    Response::ok(&req.id, data)
+}
+
+/// Handle dump_scrollback action: a pane's captured lines, inline or to a file
+///
+/// Inline text is limited like captures. A file gets at most
+/// `files::MAX_FILE_BYTES`, keeping the newest whole lines, and is written
+/// on the host by plugin.rs as put_file does.
+fn handle_dump_scrollback(req: &Request, state: &mut State) -> Response {
+    let p: DumpScrollbackParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let pane_id = match p.selector.resolve_one(state) {
+        Ok(pane) => pane.id,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    if p.lines == Some(0) {
+        return Response::err_code(&req.id, ErrorCode::InvalidParams, "invalid params: lines must be positive");
+    }
+    let captured = state.pane_lines(pane_id);
+    let mut lines: Vec<String> = captured[captured.len() - p.lines.unwrap_or(captured.len()).min(captured.len())..]
+        .iter()
+        .map(|line| if p.ansi { line.clone() } else { strip_ansi(line) })
+        .collect();
+    while lines.last().is_some_and(|line| line.trim().is_empty()) {
+        lines.pop();
+    }
+    let mut data = serde_json::json!({
+        "pane_id": pane_id,
+        "total_lines": captured.len(),
+        "redacted": was_redacted(&lines),
+    });
+
+    let Some(path) = p.path else {
+        data["lines"] = lines.len().into();
+        data["truncated"] = false.into();
+        match limited_content(state, "scrollback", Some(pane_id), &lines.join("\n")) {
+            Ok(content) => merge_json(&mut data, content),
+            Err(e) => return Response::err(&req.id, e),
+        }
+        return Response::ok(&req.id, data);
+    };
+
+    let dir = work_dir(state, p.project, Some(&p.selector))
+        .and_then(|dir| files::check_relative_path(&path).map(|_| dir));
+    let dir = match dir {
+        Ok(dir) => dir,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    // Keep the newest lines that fit, with a newline after each
+    let mut size = 0;
+    let keep = lines
+        .iter()
+        .rev()
+        .take_while(|line| {
+            size += line.len() + 1;
+            size <= files::MAX_FILE_BYTES
+        })
+        .count();
+    let text: String = lines[lines.len() - keep..].iter().map(|line| format!("{}\n", line)).collect();
+    data["lines"] = keep.into();
+    data["truncated"] = (keep < lines.len()).into();
+    data["file"] = format!("{}/{}", dir, path).into();
+    Response::ok(&req.id, serde_json::json!({
+        "action": "put_file",
+        "cwd": dir,
+        "path": path,
+        "bytes": text.len(),
+        "content": files::encode_content(text.as_bytes()),
+        "reply": data,
+    }))
+}
+
+/// Handle capture_frame action: render the visible viewport as a fixed grid
+///
+/// The grid uses the pane's content size; when Zellij has not reported it,
+/// the captured viewport's own height and widest line are used.
+fn handle_capture_frame(req: &Request, state: &State) -> Response {
+    let p: CaptureFrameParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let pane = match p.selector.resolve_one(state) {
+        Ok(pane) => pane,
+        Err(e) => return Response::err(&req.id, e),
+    };
+
+    let viewport = state.viewport_lines(pane.id);
+    let rows = match pane.pane_content_rows {
+        0 => viewport.len(),
+        rows => rows,
+    };
+    let cols = match pane.pane_content_columns {
+        0 => viewport.iter().map(|l| strip_ansi(l).chars().count()).max().unwrap_or(0),
+        cols => cols,
+    };
+
+    let mut data = serde_json::json!({ "pane_id": pane.id });
+    if let Ok(frame) = serde_json::to_value(build_frame(viewport, rows, cols, p.attributes)) {
+        merge_json(&mut data, frame);
+    }
+    Response::ok(&req.id, data)
+}
+
+/// Lines of context returned around a match, or from the end on failure
+const ASSERT_CONTEXT_LINES: usize = 5;
+
+/// Handle assert_pane action: report whether a pane shows the expected text
+///
+/// A failed assertion is still a successful request; `passed` carries the
+/// result and `context` shows what the pane displayed.
+fn handle_assert_pane(req: &Request, state: &State) -> Response {
+    let p: AssertPaneParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    match assert_pane(state, &p) {
+        Ok(data) => Response::ok(&req.id, data),
+        Err(e) => Response::err(&req.id, e),
+    }
+}
+
+/// Check a pane's output or frame as assert_pane does
+fn assert_pane(state: &State, p: &AssertPaneParams) -> Result<serde_json::Value, Error> {
+    let matcher: Box<dyn Fn(&str) -> bool> = match (&p.contains, &p.regex) {
+        (Some(text), None) => {
+            let text = text.clone();
+            Box::new(move |line| line.contains(text.as_str()))
+        }
+        (None, Some(pattern)) => match regex::Regex::new(pattern) {
+            Ok(re) => Box::new(move |line| re.is_match(line)),
+            Err(e) => return Err(Error::new(ErrorCode::InvalidParams, format!("invalid params: bad regex: {}", e))),
+        },
+        _ => {
+            return Err(Error::new(
+                ErrorCode::InvalidParams,
+                "invalid params: exactly one of contains or regex is required",
+            ))
+        }
+    };
+
+    let pane = p.selector.resolve_one(state)?;
+
+    let lines: Vec<String> = match p.source {
+        AssertSource::Output => pane_output(state, pane.id, p.since_checkpoint.as_deref())?
+            .iter()
+            .map(|l| strip_ansi(l))
+            .collect(),
+        AssertSource::Frame => state.viewport_lines(pane.id).iter().map(|l| strip_ansi(l)).collect(),
+    };
+
+    let matched = lines.iter().position(|l| matcher(l));
+    let context = match matched {
+        Some(i) => &lines[i.saturating_sub(ASSERT_CONTEXT_LINES)..(i + ASSERT_CONTEXT_LINES + 1).min(lines.len())],
+        None => &lines[lines.len().saturating_sub(ASSERT_CONTEXT_LINES)..],
+    };
+
+    Ok(serde_json::json!({
+        "pane_id": pane.id,
+        "passed": matched.is_some(),
+        "line": matched,
+        "context": context,
+    }))
+}
+
+/// Handle shutdown action: persist transcripts and refuse further requests
+///
+/// Every pane's captured output and the recall history are written to the
+/// artifact store so they survive the session being killed.
+fn handle_shutdown(req: &Request, state: &mut State) -> Response {
+    let p: ShutdownParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let mut pending: Vec<(&str, u32, String)> = state
+        .panes()
+        .iter()
+        .map(|pane| ("transcript", pane.id, state.pane_lines(pane.id).join("\n")))
+        .filter(|(_, _, text)| !text.is_empty())
+        .collect();
+    pending.extend(state.captures().map(|c| ("capture", c.pane_id, c.text.clone())));
+
+    let mut persisted = Vec::with_capacity(pending.len());
+    for (kind, pane_id, text) in pending {
+        match state.artifacts().put(kind, Some(pane_id), &text) {
+            Ok(meta) => persisted.push(meta),
+            Err(e) => return Response::err(&req.id, format!("artifact store failed: {}", e)),
+        }
+    }
+    state.begin_shutdown();
+
+    Response::ok(&req.id, serde_json::json!({
+        "action": "shutdown",
+        "unload": p.unload,
+        "persisted": persisted,
+    }))
+}
+
+/// Handle enqueue_task action
+fn handle_enqueue_task(req: &Request, state: &mut State) -> Response {
+    let p: EnqueueTaskParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let pane_id = match p.selector.resolve_one(state) {
+        Ok(pane) => pane.id,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    if let Err(e) = check_guards(state, &req.action, pane_id) {
+        return Response::err(&req.id, e);
+    }
+    let depth = state.tasks().pending_count();
+    if depth >= state.config().task_queue_capacity {
+        return queue_full(req, state, "tasks", depth);
+    }
+
+    let target = TaskTarget {
+        pane_id,
+        handle: state.pane_handle(pane_id).map(String::from),
+        selector: Some(p.selector.to_string()),
+    };
+    match state.tasks().enqueue(target, &p.prompt, p.enter) {
+        Ok(task) => Response::ok(&req.id, serde_json::json!({ "task": task })),
+        Err(e) => Response::err(&req.id, format!("task store failed: {}", e)),
+    }
+}
+
+/// Handle dispatch_task action: mark a task dispatched, then send its prompt
+///
+/// The dispatched marker is on disk before plugin.rs writes the prompt, so
+/// a task is never sent twice even if the plugin reloads in between. Tasks
+/// of a project outside its dispatch hours stay pending.
+fn handle_dispatch_task(req: &Request, state: &mut State) -> Response {
+    let p: DispatchTaskParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let pane_filter = match &p.selector {
+        Some(selector) => match selector.resolve_one(state) {
+            Ok(pane) => Some(pane.id),
+            Err(e) => return Response::err(&req.id, e),
+        },
+        None => None,
+    };
+
+    let task = match &p.id {
+        Some(id) => state.tasks().get(id).cloned(),
+        None => state.tasks().next_pending(pane_filter).cloned(),
+    };
+    let Some(task) = task else {
+        return match p.id {
+            Some(id) => Response::err_code(&req.id, ErrorCode::NotFound, format!("task not found: {}", id)),
+            None => Response::err(&req.id, "no pending task"),
+        };
+    };
+
+    let pane_id = match task_target(state, &task) {
+        Ok(pane_id) => pane_id,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    let Some(pane) = state.get_pane(pane_id) else {
+        return Response::err_code(&req.id, ErrorCode::NotFound, format!("pane not found: {}", pane_id));
+    };
+    let project = state.agent_name(pane).and_then(|(name, _)| name.project);
+    if let Some(reason) = state.automation_paused(project.as_deref()) {
+        return Response::err_code(&req.id, ErrorCode::Paused, format!(
+            "automation paused for {}: {}",
+            project.as_deref().unwrap_or("all projects"),
+            reason
+        ));
+    }
+    if let Some(reason) = state.queue_held(project.as_deref(), clock::now_ms()) {
+        return Response::err_code(&req.id, ErrorCode::Held, format!(
+            "queue held for {}: {} (task {} stays pending)",
+            project.as_deref().unwrap_or("all projects"),
+            reason,
+            task.id
+        ));
+    }
+    // Input typed while the agent starts up is lost; the task waits
+    if state.agent_starting(pane_id) {
+        return Response::err_code(&req.id, ErrorCode::Starting, format!(
+            "agent starting: pane {} has not finished launching (task {} stays pending)",
+            pane_id, task.id
+        ));
+    }
+    // A blocked task stays pending for a later dispatch
+    if let Err(e) = check_guards(state, &req.action, pane_id) {
+        return Response::err(&req.id, e);
+    }
+    if let Err(e) = state.tasks().transition(&task.id, TaskStatus::Pending, TaskStatus::Dispatched, None) {
+        return Response::err(&req.id, e);
+    }
+
+    Response::ok(&req.id, serde_json::json!({
+        "action": "send_keys",
+        "pane_id": pane_id,
+        "text": task.prompt,
+        "enter": task.enter,
+        "task_id": task.id,
+    }))
+}
+
+/// The pane a task's prompt goes to, handling a stale target by policy
+///
+/// The target is stale when the pane with the task's id no longer holds
+/// the handle recorded at enqueue time. Tasks queued without a handle go
+/// to their pane id.
+fn task_target(state: &mut State, task: &Task) -> Result<u32, Error> {
+    let Some(handle) = task.handle.as_deref() else {
+        return Ok(task.pane_id);
+    };
+    let why = match state.pane_handle(task.pane_id) {
+        Some(current) if current == handle => return Ok(task.pane_id),
+        Some(_) => format!("pane {} was replaced by another pane", task.pane_id),
+        None => format!("pane {} was closed", task.pane_id),
+    };
+
+    if state.config().stale_task_policy == StaleTaskPolicy::Reroute {
+        // An id or handle names the old pane itself; other selectors may
+        // match its successor
+        let rerouted = task
+            .selector
+            .as_deref()
+            .map(Selector::parse)
+            .filter(|selector| !matches!(selector, Selector::Id(_) | Selector::Handle(_)))
+            .and_then(|selector| selector.resolve_one(state).ok().map(|pane| pane.id));
+        if let Some(pane_id) = rerouted {
+            let target = TaskTarget {
+                pane_id,
+                handle: state.pane_handle(pane_id).map(String::from),
+                selector: task.selector.clone(),
+            };
+            state.tasks().retarget(&task.id, target)?;
+            return Ok(pane_id);
+        }
+    }
+
+    let reason = format!("stale target: {}", why);
+    state.tasks().transition(&task.id, TaskStatus::Pending, TaskStatus::Failed, Some(reason.clone()))?;
+    Err(Error::new(ErrorCode::StaleTarget, format!("{} (task {} failed)", reason, task.id)))
+}
+
+/// Handle complete_task action: persist a dispatched task's outcome
+fn handle_complete_task(req: &Request, state: &mut State) -> Response {
+    let p: CompleteTaskParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let to = if p.failed { TaskStatus::Failed } else { TaskStatus::Completed };
+    match state.tasks().transition(&p.id, TaskStatus::Dispatched, to, p.result) {
+        Ok(task) => Response::ok(&req.id, serde_json::json!({ "task": task })),
+        Err(e) => Response::err(&req.id, e),
+    }
+}
+
+/// Turns of the panes a selector matches, oldest first
+///
+/// Turns are matched by handle, so a pane that reused a closed pane's id
+/// does not pick up its turns. A handle selector also matches a closed
+/// pane's turns.
+fn selected_turns<'a>(state: &'a State, selector: Option<&Selector>) -> Result<Vec<&'a Turn>, Error> {
+    let handles: Option<Vec<String>> = match selector {
+        None => None,
+        Some(Selector::Handle(handle)) => Some(vec![handle.clone()]),
+        Some(selector) => {
+            let panes = selector.resolve(state);
+            if panes.is_empty() {
+                return Err(Error::new(ErrorCode::NotFound, format!("pane not found: {}", selector)));
+            }
+            Some(panes.iter().filter_map(|pane| state.pane_handle(pane.id).map(String::from)).collect())
+        }
+    };
+    Ok(state
+        .turns()
+        .filter(|turn| match &handles {
+            None => true,
+            Some(handles) => turn.handle.as_ref().is_some_and(|h| handles.contains(h)),
+        })
+        .collect())
+}
+
+/// Handle list_turns action: conversation turns with response sizes
+fn handle_list_turns(req: &Request, state: &State) -> Response {
+    let p: ListTurnsParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let turns = match selected_turns(state, p.selector.as_ref()) {
+        Ok(turns) => turns,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    let turns: Vec<serde_json::Value> = turns
+        .into_iter()
+        .map(|turn| {
+            let mut summary = serde_json::to_value(turn).unwrap_or_default();
+            if let Some(summary) = summary.as_object_mut() {
+                summary.remove("response");
+                summary.insert("response_lines".to_string(), turn.response.len().into());
+            }
+            summary
+        })
+        .collect();
+    Response::ok(&req.id, serde_json::json!({ "turns": turns }))
+}
+
+/// Handle get_turn action: one conversation turn in full
+fn handle_get_turn(req: &Request, state: &State) -> Response {
+    let p: TurnIdParam = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    match state.get_turn(&p.id) {
+        Some(turn) => Response::ok(&req.id, serde_json::json!({ "turn": turn })),
+        None => Response::err_code(&req.id, ErrorCode::NotFound, format!("turn not found: {}", p.id)),
+    }
+}
+
+/// Handle export_conversation action: turns as markdown or chat messages
+///
+/// Oversized markdown is cut like captures are. A messages array keeps
+/// the leading messages that fit, with the whole export saved as an
+/// artifact.
+fn handle_export_conversation(req: &Request, state: &mut State) -> Response {
+    let p: ExportConversationParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let turns = match selected_turns(state, Some(&p.selector)) {
+        Ok(turns) => turns,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    let count = turns.len();
+    let pane_id = turns.last().map(|turn| turn.pane_id);
+    let reply = |turn: &Turn| state.turn_reply(turn);
+    match p.format {
+        ConversationFormat::Markdown => {
+            let markdown = turns::to_markdown(&turns, reply);
+            let mut data = serde_json::json!({ "format": p.format, "turns": count });
+            match limited_content(state, "conversation", pane_id, &markdown) {
+                Ok(content) => merge_json(&mut data, content),
+                Err(e) => return Response::err(&req.id, e),
+            }
+            Response::ok(&req.id, data)
+        }
+        ConversationFormat::Messages => {
+            let messages = turns::to_messages(&turns, reply);
+            let max = state.config().max_response_bytes;
+            let mut size = 0;
+            let fitting = messages
+                .iter()
+                .take_while(|message| {
+                    size += message.to_string().len();
+                    size <= max
+                })
+                .count();
+            let mut data = serde_json::json!({ "format": p.format, "turns": count });
+            if fitting < messages.len() {
+                let full = serde_json::Value::Array(messages.clone()).to_string();
+                let meta = match state.artifacts().put("conversation", pane_id, &full) {
+                    Ok(meta) => meta,
+                    Err(e) => return Response::err(&req.id, format!("artifact store failed: {}", e)),
+                };
+                data["truncated"] = true.into();
+                data["total_messages"] = messages.len().into();
+                data["artifact_id"] = meta.id.into();
+            }
+            data["messages"] = messages[..fitting].to_vec().into();
+            Response::ok(&req.id, data)
+        }
+    }
+}
+
+/// Turns handed over when handover_context does not say
+const DEFAULT_HANDOVER_TURNS: usize = 20;
+
+/// Handle handover_context action: prime a replacement with an agent's turns
+///
+/// Without `via` the prompt goes to the replacement now, through the task
+/// journal. With `via` the summary request is typed into that pane, and
+/// State sends the prompt once the summary turn completes.
+fn handle_handover_context(req: &Request, state: &mut State) -> Response {
+    let p: HandoverContextParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let to = match p.to.resolve_one(state) {
+        Ok(pane) => pane.id,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    let via = match &p.via {
+        Some(via) => match via.resolve_one(state) {
+            Ok(pane) if pane.id == to => {
+                return Response::err_code(&req.id, ErrorCode::InvalidParams, "invalid params: via must not be the replacement");
+            }
+            Ok(pane) if state.agent_name(pane).is_none() => {
+                return Response::err_code(&req.id, ErrorCode::InvalidParams, format!("invalid params: via pane {} is not an agent", pane.id));
+            }
+            Ok(pane) if state.agent_starting(pane.id) => {
+                return Response::err_code(&req.id, ErrorCode::Starting, format!("agent starting: pane {} has not finished launching", pane.id));
+            }
+            Ok(pane) => Some(pane.id),
+            Err(e) => return Response::err(&req.id, e),
+        },
+        None => None,
+    };
+    let turns = match selected_turns(state, Some(&p.from)) {
+        Ok(turns) => turns,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    let Some(last) = turns.last().copied() else {
+        return Response::err(&req.id, format!("no turns recorded for {}", p.from));
+    };
+    let recent = &turns[turns.len().saturating_sub(p.turns.unwrap_or(DEFAULT_HANDOVER_TURNS))..];
+    let conversation = turns::to_markdown(recent, |turn| state.turn_reply(turn));
+    let count = recent.len();
+    let config = state.config();
+    let prompt = handover::fill(p.template.as_deref().unwrap_or(&config.handover_template), last);
+    let summary_request = handover::fill(
+        p.summary_template.as_deref().unwrap_or(&config.handover_summary_template),
+        last,
+    );
+
+    let Some(via) = via else {
+        let (task_id, status) = state.deliver_prompt(to, &handover::with_context(&prompt, &conversation));
+        return Response::ok(&req.id, serde_json::json!({
+            "pane_id": to,
+            "turns": count,
+            "task_id": task_id,
+            "status": status,
+        }));
+    };
+    let id = state.start_handover(PendingHandover {
+        id: String::new(),
+        to_pane: to,
+        to_handle: state.pane_handle(to).map(String::from),
+        prompt,
+        via_pane: via,
+        summary_turn: None,
+    });
+    Response::ok(&req.id, serde_json::json!({
+        "action": "send_keys",
+        "pane_id": via,
+        "text": write_queue::bracketed_paste(&handover::with_context(&summary_request, &conversation)),
+        "enter": true,
+        "handover_id": id,
+        "to_pane_id": to,
+        "turns": count,
+        "status": "summarizing",
+    }))
+}
+
+/// Handle fanout action: send one prompt to several agents
+///
+/// Every target is checked before any prompt goes out. Returns a fanout
+/// effect; plugin.rs holds the response until the agents reply or the
+/// timeout passes, and builds it with [`fanout_response`].
+fn handle_fanout(req: &Request, state: &mut State) -> Response {
+    let p: FanoutParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    if p.text.is_empty() {
+        return Response::err_code(&req.id, ErrorCode::InvalidParams, "invalid params: text must not be empty");
+    }
+    if p.selectors.is_empty() && p.kinds.is_empty() {
+        return Response::err_code(&req.id, ErrorCode::InvalidParams, "invalid params: fanout needs selectors or kinds");
+    }
+    let timeout_secs = p.timeout_secs.unwrap_or(DEFAULT_FANOUT_TIMEOUT_SECS);
+    if timeout_secs == 0 {
+        return Response::err_code(&req.id, ErrorCode::InvalidParams, "invalid params: timeout_secs must be positive");
+    }
+    let mut kinds = Vec::new();
+    for kind in &p.kinds {
+        match state.kinds().resolve(kind) {
+            Some(kind) => kinds.push(kind.name.clone()),
+            None => return Response::err(&req.id, format!("unknown agent kind: {}", kind)),
+        }
+    }
+
+    let mut panes: Vec<u32> = Vec::new();
+    for selector in &p.selectors {
+        let found: Vec<u32> = selector.resolve(state).iter().map(|pane| pane.id).collect();
+        if found.is_empty() {
+            return Response::err_code(&req.id, ErrorCode::NotFound, format!("pane not found: {}", selector));
+        }
+        for id in found {
+            if state.get_pane(id).and_then(|pane| state.agent_name(pane)).is_none() {
+                return Response::err_code(&req.id, ErrorCode::InvalidParams, format!("invalid params: pane {} is not an agent", id));
+            }
+            if !panes.contains(&id) {
+                panes.push(id);
+            }
+        }
+    }
+    for pane in state.panes() {
+        let of_kind = state.agent_name(pane).is_some_and(|(name, _)| kinds.contains(&name.kind));
+        if of_kind && !panes.contains(&pane.id) {
+            panes.push(pane.id);
+        }
+    }
+    if panes.is_empty() {
+        return Response::err_code(&req.id, ErrorCode::NotFound, format!("pane not found: no agents of kind {}", kinds.join(", ")));
+    }
+    let judge = match &p.judge {
+        None => None,
+        Some(selector) => match selector.resolve_one(state) {
+            Ok(pane) if state.agent_name(pane).is_none() => {
+                return Response::err_code(&req.id, ErrorCode::InvalidParams, format!("invalid params: judge pane {} is not an agent", pane.id));
+            }
+            Ok(pane) if panes.contains(&pane.id) => {
+                return Response::err_code(&req.id, ErrorCode::InvalidParams, "invalid params: judge must not be one of the agents prompted");
+            }
+            Ok(pane) => Some(pane.id),
+            Err(e) => return Response::err(&req.id, e),
+        },
+    };
+    let template = p.judge_template.unwrap_or_else(|| state.config().fanout_judge_template.clone());
+    if judge.is_some() && !template.contains("{candidates}") {
+        return Response::err_code(&req.id, ErrorCode::InvalidParams, "invalid params: judge template has no {candidates}");
+    }
+
+    let targets: Vec<serde_json::Value> = panes
+        .into_iter()
+        .map(|id| {
+            let (task_id, status) = state.deliver_prompt(id, &p.text);
+            serde_json::json!({ "pane_id": id, "task_id": task_id, "status": status })
+        })
+        .collect();
+    let mut data = serde_json::json!({
+        "action": "fanout",
+        "prompt": p.text,
+        "targets": targets,
+        "timeout_secs": timeout_secs,
+    });
+    if let Some(judge) = judge {
+        data["judge"] = serde_json::json!({ "pane_id": judge, "template": template });
+    }
+    Response::ok(&req.id, data)
+}
+
+/// Build a fanout's response from the turns its prompts started
+///
+/// `complete` is false when the timeout passed before every agent replied;
+/// the replies so far are returned either way. Replies are numbered as the
+/// judge saw them, and the judge's entry gives the number it picked.
+#[cfg(any(target_arch = "wasm32", test))]
+pub fn fanout_response(state: &mut State, pending: &PendingFanout) -> Response {
+    let mut complete = true;
+    let mut replies = Vec::new();
+    for (index, (pane_id, task_id)) in pending.targets.iter().enumerate() {
+        let mut reply = serde_json::json!({ "candidate": index + 1, "pane_id": pane_id, "task_id": task_id });
+        complete &= turn_entry(state, &mut reply, *pane_id, task_id.as_deref());
+        replies.push(reply);
+    }
+    let mut data = serde_json::json!({
+        "replies": replies,
+        "complete": complete,
+        "elapsed_ms": clock::now_ms().saturating_sub(pending.started_ms),
+    });
+    if let Some(judge) = &pending.judge {
+        let mut entry = serde_json::json!({ "pane_id": judge.pane_id, "task_id": judge.task_id });
+        if !judge.asked {
+            entry["status"] = "closed".into();
+        } else if turn_entry(state, &mut entry, judge.pane_id, judge.task_id.as_deref()) {
+            let text = judge.task_id.as_deref().and_then(|id| state.turn_for_task(id)).map(|turn| state.turn_reply(turn));
+            let pick = text.and_then(|text| fanout::parse_pick(&text, pending.targets.len()));
+            entry["pick"] = pick.into();
+            entry["picked_pane_id"] = pick.map(|n| pending.targets[n - 1].0).into();
+        }
+        data["judge"] = entry;
+    }
+    Response::ok(&pending.request_id, data)
+}
+
+/// Fill a fanout entry from the turn a task started; true once complete
+#[cfg(any(target_arch = "wasm32", test))]
+fn turn_entry(state: &mut State, entry: &mut serde_json::Value, pane_id: u32, task_id: Option<&str>) -> bool {
+    let Some(turn) = task_id.and_then(|id| state.turn_for_task(id)).cloned() else {
+        // A prompt sent without a task has no turn to find
+        entry["status"] = if task_id.is_some() { "pending" } else { "untracked" }.into();
+        return task_id.is_none();
+    };
+    entry["turn_id"] = turn.id.clone().into();
+    entry["title"] = turn.title.clone().into();
+    entry["kind"] = turn.kind.clone().into();
+    entry["status"] = serde_json::to_value(turn.status).unwrap_or_default();
+    let text = state.turn_reply(&turn);
+    match limited_content(state, "fanout", Some(pane_id), &text) {
+        Ok(content) => merge_json(entry, content),
+        Err(e) => entry["error"] = e.into(),
+    }
+    turn.status == TurnStatus::Complete
+}
+
+/// Handle list_agents action: panes parsed with the naming convention
+fn handle_list_agents(req: &Request, state: &State) -> Response {
+    let agents: Vec<serde_json::Value> = state
+        .panes()
+        .iter()
+        .filter_map(|pane| {
+            let (name, detected_by) = state.agent_name(pane)?;
+            let mut agent = serde_json::json!({
+                "pane_id": pane.id,
+                "handle": state.pane_handle(pane.id),
+                "title": pane.title,
+                "detected_by": detected_by,
+                "ready": !state.agent_starting(pane.id),
+            });
+            if let Ok(name) = serde_json::to_value(name) {
+                merge_json(&mut agent, name);
+            }
+            Some(agent)
+        })
+        .collect();
+
+    let mut data = serde_json::json!({ "agents": agents });
+    if let Some(e) = state.naming_error() {
+        data["naming_error"] = serde_json::json!(e);
+    }
+    Response::ok(&req.id, data)
+}
+
+/// Events project_status includes, newest first
+const PROJECT_STATUS_EVENTS: usize = 10;
+
+/// Handle project_status action: everything known about a project at once
+///
+/// Git state comes from the git_info cache and is null when that has
+/// expired; git_info refreshes it. Events are the latest logged ones that
+/// name the project or one of its panes.
+fn handle_project_status(req: &Request, state: &mut State) -> Response {
+    let p: ProjectParam = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let mut pane_ids = Vec::new();
+    let mut agents = Vec::new();
+    for pane in state.panes() {
+        let Some((name, _)) = state.agent_name(pane).filter(|(name, _)| name.project.as_deref() == Some(&p.project)) else {
+            continue;
+        };
+        pane_ids.push(pane.id);
+        let status = match state.pane_status(pane) {
+            Some(Ok(status)) => serde_json::json!(status),
+            _ => serde_json::Value::Null,
+        };
+        agents.push(serde_json::json!({
+            "pane_id": pane.id,
+            "handle": state.pane_handle(pane.id),
+            "title": pane.title,
+            "kind": name.kind,
+            "index": name.index,
+            "ready": !state.agent_starting(pane.id),
+            "status": status,
+            "git": state
+                .agent_dir(pane)
+                .and_then(|dir| Some(git_info_data(&dir, state.cached_git_info(&dir)?, true))),
+        }));
+    }
+
+    let sends: Vec<_> = state.send_jobs().filter(|job| pane_ids.contains(&job.pane_id)).cloned().collect();
+    let git = files::project_dir(&state.config().projects_base, &p.project)
+        .ok()
+        .and_then(|dir| Some(git_info_data(&dir, state.cached_git_info(&dir)?, true)));
+    let events: Vec<_> = state
+        .event_log()
+        .iter()
+        .rev()
+        .filter(|e| {
+            e.event.get("project").and_then(|v| v.as_str()) == Some(p.project.as_str())
+                || e.event.get("pane_id").and_then(|v| v.as_u64()).is_some_and(|id| pane_ids.contains(&(id as u32)))
+        })
+        .take(PROJECT_STATUS_EVENTS)
+        .cloned()
+        .collect();
+    let worktree_lock = state.worktree_lock(&p.project);
+    let paused = state.automation_paused(Some(&p.project)).map(String::from);
+    let queue_held = state.queue_held(Some(&p.project), clock::now_ms());
+
+    let tasks = state.tasks().list();
+    let count = |status: TaskStatus| {
+        tasks.iter().filter(|t| t.status == status && pane_ids.contains(&t.pane_id)).count()
+    };
+    let (pending, dispatched) = (count(TaskStatus::Pending), count(TaskStatus::Dispatched));
+
+    Response::ok(&req.id, serde_json::json!({
+        "project": p.project,
+        "agents": agents,
+        "tasks": { "pending": pending, "dispatched": dispatched },
+        "sends": sends,
+        "git": git,
+        "worktree_lock": worktree_lock,
+        "paused": paused,
+        "queue_held": queue_held,
+        "events": events,
+    }))
+}
+
+/// Handle adopt_pane action: register a pane as an agent of a known kind
+///
+/// With `rename`, returns a rename_pane effect giving the pane a title in
+/// the configured convention, so the adoption survives a plugin reload.
+fn handle_adopt_pane(req: &Request, state: &mut State) -> Response {
+    let p: AdoptPaneParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let pane_id = match p.selector.resolve_one(state) {
+        Ok(pane) => pane.id,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    let kind = match state.kinds().resolve(&p.kind) {
+        Some(kind) => kind.name.clone(),
+        None => return Response::err(&req.id, format!("unknown agent kind: {}", p.kind)),
+    };
+
+    let index = match (p.index, p.rename) {
+        (Some(index), _) => Some(index),
+        // A title needs an index
+        (None, true) => Some(next_agent_index(state, &kind, p.project.as_deref(), Some(pane_id))),
+        (None, false) => None,
+    };
+    let name = AgentName {
+        project: p.project,
+        kind,
+        index,
+        preset: None,
+    };
+
+    let mut data = serde_json::json!({ "pane_id": pane_id });
+    if let Ok(value) = serde_json::to_value(&name) {
+        merge_json(&mut data, value);
+    }
+    if p.rename {
+        data["action"] = serde_json::json!("rename_pane");
+        data["title"] = serde_json::json!(state.naming().render(&name));
+    }
+    state.adopt_pane(pane_id, name);
+    Response::ok(&req.id, data)
+}
+
+/// The index after the highest one among a project's agents of a kind
+fn next_agent_index(state: &State, kind: &str, project: Option<&str>, except: Option<u32>) -> u32 {
+    state
+        .panes()
+        .iter()
+        .filter(|pane| Some(pane.id) != except)
+        .filter_map(|pane| state.agent_name(pane))
+        .filter(|(name, _)| name.kind == kind && name.project.as_deref() == project)
+        .filter_map(|(name, _)| name.index)
+        .max()
+        .map_or(1, |i| i + 1)
+}
+
+/// Handle new_pane action: open a pane running a command
+///
+/// plugin.rs opens the pane on the host and replies with its id once it
+/// shows up in a pane update. The host command names this plugin's
+/// session, since the `zellij` CLI would otherwise pick one itself.
+fn handle_new_pane(req: &Request, state: &State) -> Response {
+    let p: NewPaneParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+    let Some(session) = state.session_name() else {
+        return Response::err(&req.id, "session name not known yet; retry once the plugin has loaded");
+    };
+
+    if let Some(Err(e)) = p.cwd.as_deref().map(spawn::check_cwd) {
+        return Response::err(&req.id, e);
+    }
+    let env = match spawn::parse_env(&p.env) {
+        Ok(env) => env,
+        Err(e) => return Response::err(&req.id, e),
+    };
+
+    let command = match spawn::command_line(p.command.as_deref(), &p.args) {
+        Ok(command) => command,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    if p.name.as_deref().is_some_and(str::is_empty) {
+        return Response::err_code(&req.id, ErrorCode::InvalidParams, "invalid params: name may not be empty");
+    }
+    let direction = p.direction.or(p.floating.then_some(PaneDirection::Floating));
+
+    Response::ok(&req.id, serde_json::json!({
+        "action": "new_pane",
+        "session": session,
+        "command": spawn::spawn_command(&command, &[], &env, state.config()),
+        "cwd": p.cwd,
+        "direction": direction,
+        "name": p.name,
+    }))
+}
+
+/// Handle run_command action: open a command pane for a program
+///
+/// Unlike new_pane, which goes through the `zellij` CLI on the host, the
+/// pane is opened with the plugin API and its id comes back with
+/// CommandPaneOpened, so plugin.rs replies once the pane exists.
+fn handle_run_command(req: &Request) -> Response {
+    let p: RunCommandParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+    match run_command_effect(p) {
+        Ok(data) => Response::ok(&req.id, data),
+        Err(e) => Response::err(&req.id, e),
+    }
+}
+
+/// Handle open_floating_command action: run_command into a floating pane
+/// placed in percent of the tab
+fn handle_open_floating_command(req: &Request) -> Response {
+    let p: OpenFloatingCommandParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let mut coordinates = serde_json::Map::new();
+    for (name, value, min) in [("x", p.x, 0), ("y", p.y, 0), ("width", p.width, 1), ("height", p.height, 1)] {
+        match value {
+            Some(percent) if !(min..=100).contains(&percent) => {
+                return Response::err_code(&req.id, ErrorCode::InvalidParams, format!("invalid params: {} must be {} to 100 percent", name, min));
+            }
+            Some(percent) => {
+                coordinates.insert(name.to_string(), format!("{}%", percent).into());
+            }
+            None => {}
+        }
+    }
+    let command = RunCommandParams { command: p.command, args: p.args, cwd: p.cwd, floating: true, name: p.name };
+    match run_command_effect(command) {
+        Ok(mut data) => {
+            data["coordinates"] = coordinates.into();
+            Response::ok(&req.id, data)
+        }
+        Err(e) => Response::err(&req.id, e),
+    }
+}
+
+/// The effect opening a run_command pane
+fn run_command_effect(p: RunCommandParams) -> Result<serde_json::Value, Error> {
+    if p.command.trim().is_empty() {
+        return Err(Error::new(ErrorCode::InvalidParams, "invalid params: command may not be empty"));
+    }
+    if let Some(cwd) = p.cwd.as_deref() {
+        spawn::check_cwd(cwd)?;
+    }
+    if p.name.as_deref().is_some_and(str::is_empty) {
+        return Err(Error::new(ErrorCode::InvalidParams, "invalid params: name may not be empty"));
+    }
+
+    let mut command = vec![p.command];
+    command.extend(p.args);
+    Ok(serde_json::json!({
+        "action": "run_command",
+        "command": command,
+        "cwd": p.cwd,
+        "floating": p.floating,
+        "title": p.name,
+    }))
+}
+
+/// Handle spawn_agent action: open a pane running an agent in its project
+///
+/// The working directory comes from the `cwd` param, else the kind's
+/// template, else the project under projects_base. plugin.rs checks it
+/// exists before opening the pane, then titles the pane and registers it
+/// as the agent once it opens.
+fn handle_spawn_agent(req: &Request, state: &State) -> Response {
+    let p: SpawnAgentParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let Some(kind) = state.kinds().resolve(&p.kind) else {
+        return Response::err(&req.id, format!("unknown agent kind: {}", p.kind));
+    };
+    let Some(command) = kind.command.as_deref() else {
+        return Response::err(&req.id, format!("agent kind {} has no command", kind.name));
+    };
+    let command = match &p.preset {
+        Some(preset) => match kind.presets.get(preset) {
+            Some(args) => format!("{} {}", command, args),
+            None => return Response::err(&req.id, format!("agent kind {} has no preset {}", kind.name, preset)),
+        },
+        None => command.to_string(),
+    };
+    let env = match spawn::parse_env(&p.env) {
+        Ok(env) => env,
+        Err(e) => return Response::err(&req.id, e),
+    };
+
+    let index = p
+        .index
+        .unwrap_or_else(|| next_agent_index(state, &kind.name, Some(&p.project), None));
+    let name = AgentName {
+        project: Some(p.project.clone()),
+        kind: kind.name.clone(),
+        index: Some(index),
+        preset: p.preset,
+    };
+    let cwd = match p.cwd.as_deref().or(kind.cwd.as_deref()) {
+        Some(template) => spawn::render_cwd(template, &name),
+        None => files::project_dir(&state.config().projects_base, &p.project),
+    };
+    let cwd = match cwd {
+        Ok(cwd) => cwd,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    let command = match &kind.container {
+        Some(container) => spawn::container_command(container, &command, &env, &name),
+        None => command,
+    };
+    let mut data = serde_json::json!({
+        "action": "open_pane",
+        "command": spawn::spawn_command(&command, &kind.load_env, &env, state.config()),
+        "cwd": cwd,
+        "floating": p.floating,
+        "title": state.naming().render(&name),
+    });
+    if let Ok(value) = serde_json::to_value(&name) {
+        data["agent"] = value;
+    }
+    Response::ok(&req.id, data)
+}
+
+/// Handle lock_worktree action: claim the selected agent's project worktree
+fn handle_lock_worktree(req: &Request, state: &mut State) -> Response {
+    let p: SelectorParam = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let pane_id = match p.selector.resolve_one(state) {
+        Ok(pane) => pane.id,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    let project = match project_for(state, None, Some(&p.selector)) {
+        Ok(project) => project,
+        Err(e) => return Response::err(&req.id, e),
+    };
+
+    match state.lock_worktree(&project, pane_id) {
+        Ok(()) => Response::ok(&req.id, serde_json::json!({ "project": project, "pane_id": pane_id })),
+        Err(holder) => Response::err(&req.id, format!("worktree of {} is locked by pane {}", project, holder)),
+    }
+}
+
+/// Handle unlock_worktree action: release the selected agent's project worktree
+///
+/// Only the pane holding the lock may release it, unless `force` is set.
+fn handle_unlock_worktree(req: &Request, state: &mut State) -> Response {
+    let p: UnlockWorktreeParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let pane_id = match p.selector.resolve_one(state) {
+        Ok(pane) => pane.id,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    let project = match project_for(state, None, Some(&p.selector)) {
+        Ok(project) => project,
+        Err(e) => return Response::err(&req.id, e),
+    };
+    if let Some(holder) = state.worktree_lock(&project).filter(|holder| *holder != pane_id && !p.force) {
+        return Response::err(&req.id, format!(
+            "worktree of {} is locked by pane {}, not pane {}; pass force to release it",
+            project, holder, pane_id
+        ));
+    }
+
+    Response::ok(&req.id, serde_json::json!({
+        "project": project,
+        "released_from": state.unlock_worktree(&project),
+    }))
+}
+
+/// Check the configured guards before `action` sends to a pane
+///
+/// Guards apply to agent panes with a project. Git conditions use the
+/// cached git_info of the agent's directory. With none cached, or the
+/// cache expired, they block the send: a guard that cannot see the
+/// worktree does not let anything through. Likewise a `guard.*` key that
+/// could not be parsed blocks every guarded send until it is fixed.
+fn check_guards(state: &State, action: &str, pane_id: u32) -> Result<(), Error> {
+    let guards: Vec<_> = state.config().guards.iter().filter(|g| g.covers(action)).collect();
+    let broken = state.config().errors.iter().find(|(key, _)| key.starts_with("guard."));
+    if guards.is_empty() && broken.is_none() {
+        return Ok(());
+    }
+    let Some(pane) = state.get_pane(pane_id) else {
+        return Ok(());
+    };
+    let Some(project) = state.agent_name(pane).and_then(|(name, _)| name.project) else {
+        return Ok(());
+    };
+    if let Some((key, e)) = broken {
+        return Err(Error::new(
+            ErrorCode::Blocked,
+            format!("blocked by guard {}: it could not be parsed: {}", &key["guard.".len()..], e),
+        ));
+    }
+    let info = state.agent_dir(pane).and_then(|dir| state.cached_git_info(&dir));
+
+    for guard in guards {
+        let reason = match (guard.when, info) {
+            (GuardCondition::Locked, _) => state
+                .worktree_lock(&project)
+                .filter(|holder| *holder != pane_id)
+                .map(|holder| format!("worktree of {} is locked by pane {}", project, holder)),
+            (_, None) => Some(format!("git info unavailable for {}; refresh it with git_info", project)),
+            (GuardCondition::Conflicts, Some(info)) if info.conflicts > 0 => {
+                Some(format!("{} has {} merge conflicts", project, info.conflicts))
+            }
+            (GuardCondition::Dirty, Some(info)) if info.dirty => {
+                Some(format!("{} has {} uncommitted changes", project, info.changed))
+            }
+            (GuardCondition::Behind, Some(info)) if info.behind > 0 => {
+                Some(format!("{} is {} commits behind its upstream", project, info.behind))
+            }
+            _ => None,
+        };
+        if let Some(reason) = reason {
+            return Err(Error::new(ErrorCode::Blocked, format!("blocked by guard {}: {}", guard.name, reason)));
+        }
+    }
+    Ok(())
+}
+
+/// Handle export_history action: save recent manifests as an artifact
+fn handle_export_history(req: &Request, state: &mut State) -> Response {
+    let records = state.manifest_history();
+    let json = match serde_json::to_string(&records) {
+        Ok(json) => json,
+        Err(e) => return Response::err(&req.id, format!("export failed: {}", e)),
+    };
+    match state.artifacts().put("manifest_history", None, &json) {
+        Ok(meta) => Response::ok(&req.id, serde_json::json!({
+            "artifact_id": meta.id,
+            "records": records,
+        })),
+        Err(e) => Response::err(&req.id, format!("artifact store failed: {}", e)),
+    }
+}
+
+/// Handle replay_history action: feed manifests through a fresh State
+///
+/// Reports the pane ids, revision, and index problems after each step, and
+/// whether the result matches the live pane list.
+fn handle_replay_history(req: &Request, state: &mut State) -> Response {
+    let p: ReplayHistoryParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let records = match (p.records, p.artifact_id) {
+        (Some(records), _) => records,
+        (None, Some(id)) => {
+            let parsed = state
+                .artifacts()
+                .get(&id)
+                .ok()
+                .and_then(|json| serde_json::from_str(&json).ok());
+            match parsed {
+                Some(records) => records,
+                None => return Response::err(&req.id, format!("no manifest history in artifact: {}", id)),
+            }
+        }
+        (None, None) => state.manifest_history(),
+    };
+
+    let mut replay = State::default();
+    let mut steps = Vec::with_capacity(records.len());
+    for (step, record) in records.iter().enumerate() {
+        replay.update_panes(record.to_manifest());
+        let panes: Vec<u32> = replay.panes().iter().map(|p| p.id).collect();
+        steps.push(serde_json::json!({
+            "step": step,
+            "revision": replay.revision(),
+            "panes": panes,
+            "problems": replay.index_problems(),
+        }));
+    }
+
+    let signature = |s: &State| -> Vec<(u32, String)> {
+        s.panes().iter().map(|p| (p.id, p.title.clone())).collect()
+    };
+    Response::ok(&req.id, serde_json::json!({
+        "steps": steps,
+        "matches_live": signature(&replay) == signature(state),
+    }))
+}
+
+/// Handle classify_pane action: run the user's classify hook on a pane
+fn handle_classify_pane(req: &Request, state: &State) -> Response {
+    let p: SelectorParam = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let pane = match p.selector.resolve_one(state) {
+        Ok(pane) => pane,
+        Err(e) => return Response::err(&req.id, e),
+    };
+
+    match state.pane_status(pane) {
+        Some(Ok(status)) => Response::ok(&req.id, serde_json::json!({
+            "pane_id": pane.id,
+            "status": status,
+        })),
+        Some(Err(e)) => Response::err(&req.id, e),
+        None => Response::err(&req.id, match state.script_error() {
+            Some(e) => format!("no classify hook: {}", e),
+            None => "no classify hook configured".to_string(),
+        }),
+    }
+}
+
+/// Handle stats action: estimated memory held by the plugin's buffers
+fn handle_stats(req: &Request, state: &mut State) -> Response {
+    let memory = state.memory_usage();
+    Response::ok(&req.id, serde_json::json!({
+        "memory": memory,
+        "budget": state.config().memory,
+        "panes": state.panes().len(),
+        "revision": state.revision(),
+    }))
+}
+
+/// Handle describe_actions action: list built-in and composite actions
+fn handle_describe_actions(req: &Request, state: &State) -> Response {
+    let mut actions: Vec<serde_json::Value> = BUILTIN_ACTIONS
+        .iter()
+        .map(|(name, description)| serde_json::json!({
+            "name": name,
+            "description": description,
+            "composite": false,
+        }))
+        .collect();
+    for composite in &state.config().composite_actions {
+        actions.push(serde_json::json!({
+            "name": composite.name,
+            "description": composite.description,
+            "composite": true,
+            "args": composite.arg_names(),
+            "steps": composite.steps.iter().map(|s| s.action.as_str()).collect::<Vec<_>>(),
+        }));
+    }
+    let mut data = serde_json::json!({ "actions": actions });
+    add_config_errors(&mut data, state, "action.");
+    Response::ok(&req.id, data)
+}
+
+/// Report the config keys with a prefix that could not be parsed, in a
+/// reply listing what those keys configure
+fn add_config_errors(data: &mut serde_json::Value, state: &State, prefix: &str) {
+    let errors: serde_json::Map<String, serde_json::Value> = state
+        .config()
+        .errors
+        .iter()
+        .filter(|(key, _)| key.starts_with(prefix))
+        .map(|(key, e)| (key.clone(), serde_json::json!(e)))
+        .collect();
+    if !errors.is_empty() {
+        data["config_errors"] = serde_json::Value::Object(errors);
+    }
+}
+
+/// Handle get_version action: report what build is loaded
+///
+/// `build_sha256` is fixed at compile time. `file_sha256` is null until
+/// the file at `plugin_path` has been hashed, and always when the key is
+/// unset. `config_errors` lists every config key that could not be parsed.
+fn handle_get_version(req: &Request, state: &State) -> Response {
+    let mut data = serde_json::json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "protocol": PROTOCOL_VERSION,
+        "build_sha256": integrity::BUILD_SHA256,
+        "plugin_path": state.config().plugin_path,
+        "file_sha256": null,
+        "config_errors": state.config().errors,
+    });
+    match state.plugin_hash() {
+        Some(Ok(hash)) => data["file_sha256"] = hash.as_str().into(),
+        Some(Err(e)) => data["file_sha256_error"] = e.as_str().into(),
+        None => {}
+    }
+    Response::ok(&req.id, data)
+}
+
+/// Handle transaction action: validate every operation before any effect runs
+///
+/// Only actions whose handlers read state alone may take part. Every
+/// operation is validated, route hook included, before any of them is
+/// handled for real, so a failing operation leaves State as it was and
+/// plugin.rs performs no writes.
+///
+/// Operations are planned in order with the effects of the earlier ones
+/// in mind: paced sends take a place in the send queue each, and no
+/// operation may target a pane an earlier one closes.
+fn handle_transaction(req: &Request, state: &mut State) -> Response {
+    let p: TransactionParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    if p.requests.is_empty() {
+        return Response::err_code(&req.id, ErrorCode::InvalidParams, "invalid params: transaction has no requests");
+    }
+
+    let mut ops = Vec::with_capacity(p.requests.len());
+    let mut paced = 0;
+    let mut closed: Vec<(u32, usize)> = Vec::new();
+    for (index, op) in p.requests.into_iter().enumerate() {
+        let op_req = Request {
+            id: req.id.clone(),
+            action: op.action,
+            params: op.params,
+            explain: false,
+            if_revision: None,
+        };
+        let op_req = match route(&op_req, state) {
+            Ok(rewritten) => rewritten.unwrap_or(op_req),
+            Err(resp) => return operation_failed(req, index, &op_req.action, resp),
+        };
+        let planned = match op_req.action.as_str() {
+            action if DEFERRED_ACTIONS.contains(&action) => None,
+            "send_keys" => Some(match plan_send_keys(&op_req, state, paced) {
+                Ok((data, is_paced)) => {
+                    paced += usize::from(is_paced);
+                    Response::ok(&req.id, data)
+                }
+                Err(SendRefusal::Invalid(resp)) => resp,
+                Err(SendRefusal::QueueFull(depth)) => queue_full_response(&op_req, state, "sends", depth).0,
+            }),
+            _ => dispatch_pure(&op_req, state),
+        };
+        let Some(resp) = planned else {
+            return Response::err(&req.id, format!(
+                "operation {} ({}) cannot run inside a transaction",
+                index, op_req.action
+            ));
+        };
+        if !resp.success {
+            return operation_failed(req, index, &op_req.action, resp);
+        }
+        let pane_id = resp.data.as_ref().and_then(|data| data["pane_id"].as_u64()).map(|id| id as u32);
+        if let Some((pane_id, by)) = pane_id.and_then(|id| closed.iter().find(|(closed, _)| *closed == id)) {
+            let resp = Response::err_code(&req.id, ErrorCode::NotFound, format!("pane {} is closed by operation {}", pane_id, by));
+            return operation_failed(req, index, &op_req.action, resp);
+        }
+        if let (Some(pane_id), "close_pane") = (pane_id, op_req.action.as_str()) {
+            closed.push((pane_id, index));
+        }
+        ops.push(op_req);
+    }
+
+    let results: Vec<_> = ops.iter().map(|op| dispatch_routed(op, state).data).collect();
+
+    Response::ok(&req.id, serde_json::json!({
+        "action": "transaction",
+        "results": results,
+    }))
+}
+
+/// Error of a transaction one of whose operations failed
+fn operation_failed(req: &Request, index: usize, action: &str, resp: Response) -> Response {
+    Response::err(&req.id, format!(
+        "operation {} ({}) failed, nothing was applied: {}",
+        index,
+        action,
+        resp.error.unwrap_or_default()
+    ))
+}
+
+/// Handle broadcast action: run one action per target pane
+///
+/// Unlike a transaction, a failing target does not stop the others. Each
+/// target gets an entry with its own success flag, error code, and effect,
+/// and plugin.rs runs the effects of the targets that succeeded.
+fn handle_broadcast(req: &Request, state: &mut State) -> Response {
+    let p: BroadcastParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let action = p.action.as_str();
+    if action == "transaction"
+        || action == "broadcast"
+        || DEFERRED_ACTIONS.contains(&action)
+        || state.config().composite_action(action).is_some()
+    {
+        return Response::err_code(&req.id, ErrorCode::InvalidParams, format!("invalid params: {} cannot be broadcast", p.action));
+    }
+
+    let mut targets = Vec::new();
+    let mut seen = Vec::new();
+    for selector in &p.selectors {
+        let panes: Vec<u32> = selector.resolve(state).iter().map(|pane| pane.id).collect();
+        if panes.is_empty() {
+            targets.push(serde_json::json!({
+                "selector": selector.to_string(),
+                "pane_id": null,
+                "success": false,
+                "code": ErrorCode::NotFound,
+                "error": format!("pane not found: {}", selector),
+            }));
+        }
+        for pane_id in panes {
+            if seen.contains(&pane_id) {
+                continue;
+            }
+            seen.push(pane_id);
+
+            let mut params = match &p.params {
+                serde_json::Value::Null => serde_json::json!({}),
+                params => params.clone(),
+            };
+            if let Some(params) = params.as_object_mut() {
+                params.insert("pane_id".to_string(), serde_json::json!(pane_id));
+                params.insert("selector".to_string(), serde_json::json!(pane_id));
+            }
+            let resp = dispatch_command(&Request {
+                id: req.id.clone(),
+                action: p.action.clone(),
+                params,
+                explain: false,
+                if_revision: None,
+            }, state);
+
+            let mut target = serde_json::json!({
+                "selector": selector.to_string(),
+                "pane_id": pane_id,
+                "success": resp.success,
+            });
+            match resp.error {
+                Some(error) => {
+                    target["code"] = serde_json::json!(resp.code);
+                    target["error"] = serde_json::json!(error);
+                }
+                None => target["effect"] = resp.data.unwrap_or(serde_json::Value::Null),
+            }
+            targets.push(target);
+        }
+    }
+
+    let failed = targets.iter().filter(|t| t["success"] == false).count();
+    Response::ok(&req.id, serde_json::json!({
+        "action": "broadcast",
+        "succeeded": targets.len() - failed,
+        "failed": failed,
+        "targets": targets,
+    }))
+}
+
+/// Handle list_schedules action: configured schedules and their runs
+fn handle_list_schedules(req: &Request, state: &State) -> Response {
+    let now = clock::now_ms();
+    let schedules: Vec<serde_json::Value> = state
+        .config()
+        .schedules
+        .iter()
+        .map(|schedule| {
+            let run = state.schedule_state(&schedule.name);
+            let next_ms = match run {
+                Some(run) => run.next_ms,
+                None => schedule.next_run(now, state.config().utc_offset_minutes),
+            };
+            let last_run = run.and_then(|run| run.last_run.as_ref()).map(|last| serde_json::json!({
+                "at": clock::rfc3339(last.at_ms),
+                "success": last.error.is_none(),
+                "error": last.error,
+            }));
+            serde_json::json!({
+                "name": schedule.name,
+                "cron": schedule.expr,
+                "action": schedule.action,
+                "jitter_secs": schedule.jitter_secs,
+                "next_run": next_ms.map(clock::rfc3339),
+                "last_run": last_run,
+            })
+        })
+        .collect();
+    let mut data = serde_json::json!({ "schedules": schedules });
+    add_config_errors(&mut data, state, "schedule.");
+    Response::ok(&req.id, data)
+}
+
+/// Run the schedules due at `now_ms` through the dispatcher, returning
+/// their responses for plugin.rs to execute the effects of
+///
+/// Nothing runs while automation is paused for every project; the skipped
+/// run is recorded as failed.
+#[cfg(any(target_arch = "wasm32", test))]
+pub fn run_schedules(state: &mut State, now_ms: u64) -> Vec<Response> {
+    let mut responses = Vec::new();
+    for schedule in state.due_schedules(now_ms) {
+        let req = Request {
+            id: format!("schedule:{}", schedule.name),
+            action: schedule.action.clone(),
+            params: schedule.params.clone(),
+            explain: false,
+            if_revision: None,
+        };
+        let resp = match state.automation_paused(None) {
+            Some(reason) => Response::err_code(&req.id, ErrorCode::Paused, format!("automation paused for all projects: {}", reason)),
+            None => dispatch_command(&req, state),
+        };
+        state.record_schedule_run(&schedule.name, now_ms, resp.error.clone());
+        responses.push(resp);
+    }
+    responses
+}
+
+/// Run a composite action's steps in order, stopping at the first failure
+///
+/// Every step is routed (route hook included) and, when its handler only
+/// reads state, tried with `dispatch_pure` before any step runs for real,
+/// so a step that would fail cannot leave an earlier step's state change
+/// behind. Steps whose handlers change state can only be checked by
+/// running them. The effects of all steps are returned together for
+/// plugin.rs to execute in order.
+fn handle_composite(req: &Request, composite: &CompositeAction, state: &mut State) -> Response {
+    let args = match &req.params {
+        serde_json::Value::Null => serde_json::json!({}),
+        params => params.clone(),
+    };
+
+    let mut planned = Vec::with_capacity(composite.steps.len());
+    for (index, step) in composite.steps.iter().enumerate() {
+        if step.when.as_deref().is_some_and(|when| !condition_holds(when, &args)) {
+            planned.push(None);
+            continue;
+        }
+        if state.config().composite_action(&step.action).is_some() {
+            return Response::err(&req.id, format!(
+                "step {} ({}): composite actions cannot be nested",
+                index, step.action
+            ));
+        }
+        let params = match substitute(&step.params, &args) {
+            Ok(params) => params,
+            Err(e) => return Response::err(&req.id, format!("step {} ({}): {}", index, step.action, e)),
+        };
+
+        let step_req = Request {
+            id: req.id.clone(),
+            action: step.action.clone(),
+            params,
+            explain: false,
+            if_revision: None,
+        };
+        let step_req = match route(&step_req, state) {
+            Ok(rewritten) => rewritten.unwrap_or(step_req),
+            Err(resp) => return step_failed(req, index, &step.action, resp),
+        };
+        if let Some(resp) = dispatch_pure(&step_req, state).filter(|resp| !resp.success) {
+            return step_failed(req, index, &step.action, resp);
+        }
+        planned.push(Some(step_req));
+    }
+
+    let mut steps = Vec::with_capacity(planned.len());
+    for (index, (step, step_req)) in composite.steps.iter().zip(planned).enumerate() {
+        let Some(step_req) = step_req else {
+            steps.push(serde_json::json!({ "index": index, "name": step.action, "skipped": true }));
+            continue;
+        };
+        let resp = dispatch_routed(&step_req, state);
+        if !resp.success {
+            return step_failed(req, index, &step.action, resp);
+        }
+        steps.push(serde_json::json!({
+            "index": index,
+            "name": step.action,
+            "skipped": false,
+            "result": resp.data,
+        }));
+    }
+
+    Response::ok(&req.id, serde_json::json!({
+        "action": "composite",
+        "name": composite.name,
+        "steps": steps,
+    }))
+}
+
+/// Error of a composite action one of whose steps failed
+fn step_failed(req: &Request, index: usize, action: &str, resp: Response) -> Response {
+    Response::err(&req.id, format!(
+        "step {} ({}) failed: {}",
+        index,
+        action,
+        resp.error.unwrap_or_default()
+    ))
+}
+
+/// Render captured text for a response, enforcing the configured size limit
+///
+/// Oversized text is cut at `max_response_bytes` and the full content is
+/// saved to the artifact store so the caller can fetch it separately.
+fn limited_content(state: &mut State, kind: &str, pane_id: Option<u32>, text: &str) -> Result<serde_json::Value, String> {
+    let mut budget = state.config().max_response_bytes;
+    budgeted_content(state, kind, pane_id, text, &mut budget)
+}
+
+/// Like limited_content, for one of several texts in a response that
+/// share the byte budget; what this text returns is taken off it
+fn budgeted_content(
+    state: &mut State,
+    kind: &str,
+    pane_id: Option<u32>,
+    text: &str,
+    budget: &mut usize,
+) -> Result<serde_json::Value, String> {
+    if text.len() <= *budget {
+        *budget -= text.len();
+        return Ok(serde_json::json!({ "content": text }));
+    }
+
+    let meta = state
+        .artifacts()
+        .put(kind, pane_id, text)
+        .map_err(|e| format!("artifact store failed: {}", e))?;
+    let returned = truncate_utf8(text, *budget);
+    *budget -= returned.len();
+    Ok(serde_json::json!({
+        "content": returned,
+        "truncated": true,
+        "total_bytes": text.len(),
+        "returned_bytes": returned.len(),
+        "artifact_id": meta.id,
+    }))
+}
+
+/// Copy the fields of `extra` into `target` (both must be JSON objects)
+fn merge_json(target: &mut serde_json::Value, extra: serde_json::Value) {
+    if let (Some(target), serde_json::Value::Object(extra)) = (target.as_object_mut(), extra) {
+        target.extend(extra);
+    }
+}
+
+/// Validate apply_patch params and pick the diff to apply
+fn handle_apply_patch_validate(req: &Request, state: &State) -> Response {
+    let p: ApplyPatchParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    if p.cwd.is_empty() {
+        return Response::err_code(&req.id, ErrorCode::InvalidParams, "invalid params: cwd must not be empty");
+    }
+
+    let pane_id = match p.selector.resolve_one(state) {
+        Ok(pane) => pane.id,
+        Err(e) => return Response::err(&req.id, e),
+    };
+
+    let diffs: Vec<Block> = match pane_blocks(state, pane_id, p.since_checkpoint.as_deref()) {
+        Ok(blocks) => blocks.into_iter().filter(|b| b.kind == BlockKind::Diff).collect(),
+        Err(e) => return Response::err(&req.id, e),
+    };
+
+    let diff = match p.index {
+        Some(i) => diffs.get(i),
+        None => diffs.last(),
+    };
+    let Some(diff) = diff else {
+        return Response::err(&req.id, format!("no diff found in pane {}", pane_id));
+    };
+
+    Response::ok(&req.id, serde_json::json!({
+        "action": "apply_patch",
+        "pane_id": pane_id,
+        "cwd": p.cwd,
+        "patch": diff.content,
+        "dry_run": p.dry_run,
+    }))
+}
+
+/// Validate put_file params; the file is written on the host by plugin.rs
+fn handle_put_file_validate(req: &Request, state: &State) -> Response {
+    let p: PutFileParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let checked = work_dir(state, p.project, p.selector.as_ref()).and_then(|dir| {
+        files::check_relative_path(&p.path)?;
+        let bytes = files::decode_content(&p.content)?;
+        Ok((dir, bytes.len()))
+    });
+    let (dir, bytes) = match checked {
+        Ok(checked) => checked,
+        Err(e) => return Response::err(&req.id, e),
+    };
+
+    Response::ok(&req.id, serde_json::json!({
+        "action": "put_file",
+        "cwd": dir,
+        "path": p.path,
+        "bytes": bytes,
+        "content": p.content,
+    }))
+}
+
+/// Validate get_file params; the file is read on the host by plugin.rs
+fn handle_get_file_validate(req: &Request, state: &State) -> Response {
+    let p: GetFileParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let dir = work_dir(state, p.project, p.selector.as_ref())
+        .and_then(|dir| files::check_relative_path(&p.path).map(|_| dir));
+    let dir = match dir {
+        Ok(dir) => dir,
+        Err(e) => return Response::err(&req.id, e),
+    };
+
+    Response::ok(&req.id, serde_json::json!({
+        "action": "get_file",
+        "cwd": dir,
+        "path": p.path,
+        "encoding": p.encoding,
+    }))
+}
+
+/// Build the get_file reply from the bytes read on the host
+///
+/// Files over the response limit are stored as artifacts, like captures.
+#[cfg(any(target_arch = "wasm32", test))]
+pub fn file_response(state: &mut State, request_id: &str, path: &str, bytes: &[u8], encoding: OutputEncoding) -> Response {
+    if bytes.len() > files::MAX_FILE_BYTES {
+        return Response::err(request_id, format!(
+            "file too large: {} is over {} bytes",
+            path,
+            files::MAX_FILE_BYTES
+        ));
+    }
+    let encoded = encode_output(bytes, encoding);
+    let mut data = serde_json::json!({
+        "path": path,
+        "bytes": bytes.len(),
+        "encoding": encoded.encoding,
+    });
+    if encoded.lossy {
+        data["lossy"] = serde_json::json!(true);
+    }
+    match limited_content(state, "file", None, &encoded.content) {
+        Ok(content) => merge_json(&mut data, content),
+        Err(e) => return Response::err(request_id, e),
+    }
+    Response::ok(request_id, data)
+}
+
+/// Handle git_info action: answer from the cache or ask plugin.rs to run git
+fn handle_git_info(req: &Request, state: &State) -> Response {
+    let p: GitInfoParams = match parse_params(req) {
+        Ok(p) => p,
+        Err(resp) => return resp,
+    };
+
+    let dir = match work_dir(state, p.project, p.selector.as_ref()) {
+        Ok(dir) => dir,
+        Err(e) => return Response::err(&req.id, e),
+    };
+
+    match state.cached_git_info(&dir).filter(|_| !p.refresh) {
+        Some(info) => Response::ok(&req.id, git_info_data(&dir, info, true)),
+        None => Response::ok(&req.id, serde_json::json!({
+            "action": "git_info",
+            "cwd": dir,
+        })),
+    }
+}
+
+/// Render git info for a reply
+pub fn git_info_data(dir: &str, info: &GitInfo, cached: bool) -> serde_json::Value {
+    let mut data = serde_json::json!({ "cwd": dir, "cached": cached });
+    if let Ok(info) = serde_json::to_value(info) {
+        merge_json(&mut data, info);
+    }
+    data
+}
+
+/// The project a file or git action targets: given directly, or the selected agent's
+fn project_for(state: &State, project: Option<String>, selector: Option<&Selector>) -> Result<String, Error> {
+    match (project, selector) {
+        (Some(project), _) => Ok(project),
+        (None, Some(selector)) => {
+            let pane = selector.resolve_one(state)?;
+            state
+                .agent_name(pane)
+                .and_then(|(name, _)| name.project)
+                .ok_or_else(|| format!("pane {} has no project; pass project", pane.id).into())
+        }
+        (None, None) => Err(Error::new(ErrorCode::InvalidParams, "invalid params: project or selector is required")),
+    }
+}
+
+/// Directory a request works in: the given project's, else the selected
+/// pane's (see [`State::agent_dir`])
+fn work_dir(state: &State, project: Option<String>, selector: Option<&Selector>) -> Result<String, Error> {
+    if let (None, Some(selector)) = (&project, selector) {
+        if let Some(dir) = state.agent_dir(selector.resolve_one(state)?) {
+            return Ok(dir);
+        }
+    }
+    project_for(state, project, selector).and_then(|project| files::project_dir(&state.config().projects_base, &project))
+}
+
+/// Get a pane's captured output, optionally only lines after a checkpoint
+fn pane_output<'a>(state: &'a State, pane_id: u32, since_checkpoint: Option<&str>) -> Result<&'a [String], Error> {
+    match since_checkpoint {
+        Some(name) => state.lines_since_checkpoint(pane_id, name),
+        None => Ok(state.pane_lines(pane_id)),
+    }
+}
+
+/// Extract blocks from a pane's captured output, optionally since a checkpoint
+fn pane_blocks(state: &State, pane_id: u32, since_checkpoint: Option<&str>) -> Result<Vec<Block>, Error> {
+    let lines = pane_output(state, pane_id, since_checkpoint)?;
+    let cleaned: Vec<String> = lines.iter().map(|l| strip_ansi(l)).collect();
+    Ok(extract_blocks(&cleaned))
+}
+
+/// Validate send_keys parameters
+#[allow(dead_code)]
+pub fn validate_send_keys_params(_params: &SendKeysParams) -> Result<(), String> {
+    // pane_id validation happens when we look it up
+    // text can be empty (might just press enter)
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::artifacts::ArtifactStore;
+    use crate::tasks::TaskStore;
+    use crate::config::Config;
+    use crate::guards::Guard;
+    use crate::kinds::AgentKind;
+    use crate::fanout::FanoutJudge;
+    use crate::events::PendingPoll;
+    use crate::write_queue::SendJob;
+    use zellij_tile::prelude::{PaneInfo, PaneManifest, TabInfo};
+
+    fn create_test_pane(id: u32, title: &str, is_plugin: bool) -> PaneInfo {
+        PaneInfo {
+            id,
+            is_plugin,
+            title: title.to_string(),
+            is_focused: id == 1, // First pane is focused
+            is_fullscreen: false,
+            is_floating: false,
+            is_suppressed: false,
+            ..Default::default()
+        }
+    }
+
+    fn create_manifest_with_panes(panes: Vec<PaneInfo>) -> PaneManifest {
+        let mut manifest = PaneManifest::default();
+        manifest.panes.insert(0, panes);
+        manifest
+    }
+
+    fn create_test_state() -> State {
+        let mut state = State::default();
+        let root = std::env::temp_dir().join(format!(
+            "nzm-commands-{}-{:?}",
+            std::process::id(),
+            std::thread::current().id()
+        ));
+        let _ = std::fs::remove_dir_all(&root);
+        state.set_task_store(TaskStore::new(root.join("tasks.json")));
+        state.set_artifact_store(ArtifactStore::new(root));
+        state.set_session_name("test".to_string());
+        state.update_panes(create_manifest_with_panes(vec![
+            create_test_pane(1, "proj__cc_1", false),
+            create_test_pane(2, "proj__cc_2", false),
+        ]));
+        state
+    }
+
+    #[test]
+    fn test_handle_list_panes_returns_pane_array() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "123".to_string(),
+            action: "list_panes".to_string(),
+            params: serde_json::Value::Null,
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert!(result.success);
+        assert_eq!(result.id, "123");
+        let data = result.data.unwrap();
+        let panes: Vec<PaneDto> = serde_json::from_value(data["panes"].clone()).unwrap();
+        assert_eq!(panes.len(), 2);
+        assert_eq!(panes[0].id, 1);
+        assert_eq!(panes[0].title, "proj__cc_1");
+        assert!(panes[0].is_focused);
+    }
+
+    #[test]
+    fn test_handle_list_panes_empty_state() {
+        let mut state = State::default();
+        let req = Request {
+            id: "1".to_string(),
+            action: "list_panes".to_string(),
+            params: serde_json::Value::Null,
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert!(result.success);
+        let data = result.data.unwrap();
+        let panes: Vec<PaneDto> = serde_json::from_value(data["panes"].clone()).unwrap();
+        assert!(panes.is_empty());
+    }
+
+    #[test]
+    fn test_handle_get_pane_info_found() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "1".to_string(),
+            action: "get_pane_info".to_string(),
+            params: serde_json::json!({"pane_id": 1}),
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert!(result.success);
+        let data = result.data.unwrap();
+        let pane: PaneDto = serde_json::from_value(data["pane"].clone()).unwrap();
+        assert_eq!(pane.id, 1);
+        assert_eq!(pane.title, "proj__cc_1");
+    }
+
+    #[test]
+    fn test_handle_get_pane_info_not_found() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "1".to_string(),
+            action: "get_pane_info".to_string(),
+            params: serde_json::json!({"pane_id": 999}),
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("pane not found"));
+    }
+
+    #[test]
+    fn test_handle_send_keys_valid() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "1".to_string(),
+            action: "send_keys".to_string(),
+            params: serde_json::json!({
+                "pane_id": 1,
+                "text": "hello",
+                "enter": true
+            }),
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert!(result.success);
+        let data = result.data.unwrap();
+        assert_eq!(data["pane_id"], 1);
+        assert_eq!(data["text"], "hello");
+        assert_eq!(data["enter"], true);
+    }
+
+    #[test]
+    fn test_handle_send_keys_pane_not_found() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "1".to_string(),
+            action: "send_keys".to_string(),
+            params: serde_json::json!({
+                "pane_id": 999,
+                "text": "hello",
+                "enter": false
+            }),
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("pane not found"));
+    }
+
+    #[test]
+    fn test_handle_send_keys_invalid_params() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "1".to_string(),
+            action: "send_keys".to_string(),
+            params: serde_json::json!({"wrong_field": 123}),
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("invalid params"));
+    }
+
+    #[test]
+    fn test_handle_send_interrupt_valid() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "1".to_string(),
+            action: "send_interrupt".to_string(),
+            params: serde_json::json!({"pane_id": 1}),
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert!(result.success);
+        let data = result.data.unwrap();
+        assert_eq!(data["action"], "send_interrupt");
+        assert_eq!(data["pane_id"], 1);
+        assert_eq!(data["keys"], "\x03");
+    }
+
+    #[test]
+    fn test_send_interrupt_with_another_key() {
+        let mut state = create_test_state();
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "send_interrupt".to_string(),
+            params: serde_json::json!({"pane_id": 1, "key": "Escape"}),
+            ..Default::default()
+        };
+
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["keys"], "\x1b");
+        req.params = serde_json::json!({"pane_id": 1, "key": "ctrl+1"});
+        assert_eq!(dispatch_command(&req, &mut state).error.unwrap(), "invalid params: unknown key: ctrl+1");
+    }
+
+    #[test]
+    fn test_handle_unknown_action() {
+        let mut state = State::default();
+        let req = Request {
+            id: "1".to_string(),
+            action: "unknown_action".to_string(),
+            params: serde_json::Value::Null,
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("unknown action"));
+    }
+
+    #[test]
+    fn test_validate_send_keys_params_valid() {
+        let params = SendKeysParams {
+            pane_id: Selector::Id(1),
+            text: Some("hello".to_string()),
+            enter: true,
+            priority: Priority::Normal,
+            chunk_bytes: None,
+            bracketed: false,
+            keys: Vec::new(),
+        };
+        assert!(validate_send_keys_params(&params).is_ok());
+    }
+
+    #[test]
+    fn test_validate_send_keys_params_empty_text() {
+        let params = SendKeysParams {
+            pane_id: Selector::Id(1),
+            text: Some("".to_string()),
+            enter: false,
+            priority: Priority::Normal,
+            chunk_bytes: None,
+            bracketed: false,
+            keys: Vec::new(),
+        };
+        // Empty text is allowed (might just press enter)
+        assert!(validate_send_keys_params(&params).is_ok());
+    }
+
+    #[test]
+    fn test_handle_checkpoint_then_extract_blocks_since() {
+        let mut state = create_test_state();
+        state.update_pane_contents(1, vec!["```sh".into(), "old".into(), "```".into()]);
+
+        let req = Request {
+            id: "1".to_string(),
+            action: "checkpoint".to_string(),
+            params: serde_json::json!({"selector": "proj__cc_1", "name": "task"}),
+            ..Default::default()
+        };
+        let result = dispatch_command(&req, &mut state);
+        assert!(result.success);
+        assert_eq!(result.data.unwrap()["line"], 3);
+
+        state.update_pane_contents(1, vec![
+            "```sh".into(), "old".into(), "```".into(),
+            "\x1b[32m```rust\x1b[0m".into(), "let x = 1;".into(), "```".into(),
+        ]);
+
+        let req = Request {
+            id: "2".to_string(),
+            action: "extract_blocks".to_string(),
+            params: serde_json::json!({"selector": 1, "since_checkpoint": "task"}),
+            ..Default::default()
+        };
+        let result = dispatch_command(&req, &mut state);
+
+        assert!(result.success);
+        let data = result.data.unwrap();
+        let blocks = data["blocks"].as_array().unwrap();
+        assert_eq!(blocks.len(), 1);
+        assert_eq!(blocks[0]["kind"], "code");
+        assert_eq!(blocks[0]["language"], "rust");
+        assert_eq!(blocks[0]["content"], "let x = 1;");
+    }
+
+    #[test]
+    fn test_handle_extract_blocks_unknown_checkpoint() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "1".to_string(),
+            action: "extract_blocks".to_string(),
+            params: serde_json::json!({"selector": 1, "since_checkpoint": "nope"}),
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("checkpoint not found"));
+    }
+
+    #[test]
+    fn test_handle_apply_patch_picks_last_diff() {
+        let mut state = create_test_state();
+        state.update_pane_contents(1, vec![
+            "```diff".into(), "-first".into(), "```".into(),
+            "```diff".into(), "-second".into(), "+fixed".into(), "```".into(),
+        ]);
+        let req = Request {
+            id: "1".to_string(),
+            action: "apply_patch".to_string(),
+            params: serde_json::json!({"selector": 1, "cwd": "/repo", "dry_run": true}),
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert!(result.success);
+        let data = result.data.unwrap();
+        assert_eq!(data["action"], "apply_patch");
+        assert_eq!(data["patch"], "-second\n+fixed");
+        assert_eq!(data["cwd"], "/repo");
+        assert_eq!(data["dry_run"], true);
+    }
+
+    #[test]
+    fn test_handle_apply_patch_no_diff() {
+        let mut state = create_test_state();
+        state.update_pane_contents(1, vec!["no patches here".into()]);
+        let req = Request {
+            id: "1".to_string(),
+            action: "apply_patch".to_string(),
+            params: serde_json::json!({"selector": 1, "cwd": "/repo"}),
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("no diff found"));
+    }
+
+    #[test]
+    fn test_store_capture_then_get_artifact() {
+        let mut state = create_test_state();
+        state.update_pane_contents(2, vec!["line one".into(), "line two".into()]);
+
+        let req = Request {
+            id: "1".to_string(),
+            action: "store_capture".to_string(),
+            params: serde_json::json!({"selector": "proj__cc_2"}),
+            ..Default::default()
+        };
+        let result = dispatch_command(&req, &mut state);
+        assert!(result.success);
+        let artifact = result.data.unwrap()["artifact"].clone();
+        assert_eq!(artifact["kind"], "capture");
+        assert_eq!(artifact["pane_id"], 2);
+
+        let req = Request {
+            id: "2".to_string(),
+            action: "get_artifact".to_string(),
+            params: serde_json::json!({"id": artifact["id"]}),
+            ..Default::default()
+        };
+        let result = dispatch_command(&req, &mut state);
+        assert!(result.success);
+        assert_eq!(result.data.unwrap()["content"], "line one\nline two");
+
+        let req = Request {
+            id: "3".to_string(),
+            action: "list_artifacts".to_string(),
+            params: serde_json::Value::Null,
+            ..Default::default()
+        };
+        let result = dispatch_command(&req, &mut state);
+        assert_eq!(result.data.unwrap()["artifacts"].as_array().unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_extract_blocks_store_returns_artifact_ids() {
+        let mut state = create_test_state();
+        state.update_pane_contents(1, vec!["```sh".into(), "make".into(), "```".into()]);
+        let req = Request {
+            id: "1".to_string(),
+            action: "extract_blocks".to_string(),
+            params: serde_json::json!({"selector": 1, "store": true}),
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert!(result.success);
+        let data = result.data.unwrap();
+        let block = &data["blocks"][0];
+        assert!(block.get("content").is_none());
+        assert_eq!(block["size"], 4);
+        let id = block["artifact_id"].as_str().unwrap();
+        assert_eq!(state.artifacts().get(id).unwrap(), "make");
+    }
+
+    #[test]
+    fn test_get_artifact_missing() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "1".to_string(),
+            action: "get_artifact".to_string(),
+            params: serde_json::json!({"id": "abc123"}),
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("artifact not found"));
+    }
+
+    #[test]
+    fn test_extract_blocks_truncates_oversized_content() {
+        let mut state = create_test_state();
+        state.set_config(Config { max_response_bytes: 4, ..Config::default() });
+        state.update_pane_contents(1, vec!["```".into(), "0123456789".into(), "```".into()]);
+        let req = Request {
+            id: "1".to_string(),
+            action: "extract_blocks".to_string(),
+            params: serde_json::json!({"selector": 1}),
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert!(result.success);
+        let block = result.data.unwrap()["blocks"][0].clone();
+        assert_eq!(block["content"], "0123");
+        assert_eq!(block["truncated"], true);
+        assert_eq!(block["total_bytes"], 10);
+        assert_eq!(block["returned_bytes"], 4);
+        let id = block["artifact_id"].as_str().unwrap();
+        assert_eq!(state.artifacts().get(id).unwrap(), "0123456789");
+    }
+
+    #[test]
+    fn test_extract_blocks_share_one_budget() {
+        let mut state = create_test_state();
+        state.set_config(Config { max_response_bytes: 4, ..Config::default() });
+        state.update_pane_contents(1, ["```", "abc", "```", "```", "def", "```", "```", "ghi", "```"].map(String::from).to_vec());
+        let req = Request {
+            id: "1".to_string(),
+            action: "extract_blocks".to_string(),
+            params: serde_json::json!({"selector": 1}),
+            ..Default::default()
+        };
+
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+
+        assert_eq!(data["truncated"], true);
+        let blocks = data["blocks"].as_array().unwrap();
+        assert_eq!(blocks.iter().map(|b| b["content"].as_str().unwrap()).collect::<Vec<_>>(), ["abc", "d", ""]);
+        assert!(blocks[0].get("truncated").is_none());
+        assert_eq!((blocks[1]["truncated"].as_bool(), blocks[2]["total_bytes"].as_u64()), (Some(true), Some(3)));
+        let id = blocks[2]["artifact_id"].as_str().unwrap();
+        assert_eq!(state.artifacts().get(id).unwrap(), "ghi");
+    }
+
+    #[test]
+    fn test_extract_blocks_small_content_not_truncated() {
+        let mut state = create_test_state();
+        state.update_pane_contents(1, vec!["```".into(), "ok".into(), "```".into()]);
+        let req = Request {
+            id: "1".to_string(),
+            action: "extract_blocks".to_string(),
+            params: serde_json::json!({"selector": 1}),
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        let data = result.data.unwrap();
+        assert!(data.get("truncated").is_none());
+        let block = data["blocks"][0].clone();
+        assert_eq!(block["content"], "ok");
+        assert!(block.get("truncated").is_none());
+    }
+
+    #[test]
+    fn test_get_artifact_non_utf8_is_lossy_or_base64() {
+        let mut state = create_test_state();
+        let root = std::env::temp_dir().join(format!("nzm-latin1-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&root);
+        std::fs::create_dir_all(&root).unwrap();
+        std::fs::write(root.join("abcd"), b"caf\xe9").unwrap();
+        state.set_artifact_store(ArtifactStore::new(root));
+
+        let req = Request {
+            id: "1".to_string(),
+            action: "get_artifact".to_string(),
+            params: serde_json::json!({"id": "abcd"}),
+            ..Default::default()
+        };
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["content"], "caf\u{fffd}");
+        assert_eq!(data["lossy"], true);
+
+        let req = Request {
+            id: "2".to_string(),
+            action: "get_artifact".to_string(),
+            params: serde_json::json!({"id": "abcd", "encoding": "base64"}),
+            ..Default::default()
+        };
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["encoding"], "base64");
+        assert_eq!(data["content"], "Y2Fm6Q==");
+    }
+
+    #[test]
+    fn test_get_artifact_reads_ranges() {
+        let mut state = create_test_state();
+        let id = state.artifacts().put("capture", None, "0123456789").unwrap().id;
+        state.set_config(Config { max_response_bytes: 4, ..Config::default() });
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "get_artifact".to_string(),
+            params: serde_json::json!({"id": id}),
+            ..Default::default()
+        };
+
+        let mut content = String::new();
+        loop {
+            let data = dispatch_command(&req, &mut state).data.unwrap();
+            content.push_str(data["content"].as_str().unwrap());
+            let Some(next) = data.get("next_offset") else {
+                assert_eq!(data["truncated"], false);
+                break;
+            };
+            req.params = serde_json::json!({"id": id, "offset": next});
+        }
+        assert_eq!(content, "0123456789");
+
+        req.params = serde_json::json!({"id": id, "offset": 2, "length": 3});
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["content"].as_str(), data["next_offset"].as_u64()), (Some("234"), Some(5)));
+
+        req.params = serde_json::json!({"id": id, "offset": 11});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().contains("past the end"));
+    }
+
+    #[test]
+    fn test_truncate_utf8_bytes_drops_partial_char() {
+        let bytes = "aé".as_bytes();
+        assert_eq!(truncate_utf8_bytes(bytes, 2), b"a");
+        assert_eq!(truncate_utf8_bytes(b"ab\xff", 2), b"ab");
+        assert_eq!(truncate_utf8_bytes(b"short", 10), b"short");
+    }
+
+    #[test]
+    fn test_store_capture_reports_redaction() {
+        let mut state = create_test_state();
+        state.update_pane_contents(1, vec!["contact: dev@example.com".into()]);
+        let req = Request {
+            id: "1".to_string(),
+            action: "store_capture".to_string(),
+            params: serde_json::json!({"selector": 1}),
+            ..Default::default()
+        };
+
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+
+        assert_eq!(data["redacted"], true);
+        let id = data["artifact"]["id"].as_str().unwrap();
+        assert_eq!(state.artifacts().get(id).unwrap(), "contact: [REDACTED:email]");
+    }
+
+    #[test]
+    fn test_handle_send_secret_returns_reference_only() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "1".to_string(),
+            action: "send_secret".to_string(),
+            params: serde_json::json!({"selector": "proj__cc_1", "secret_ref": "env:API_KEY", "enter": true}),
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert!(result.success);
+        let data = result.data.unwrap();
+        assert_eq!(data["action"], "send_secret");
+        assert_eq!(data["pane_id"], 1);
+        assert_eq!(data["secret_ref"], "env:API_KEY");
+    }
+
+    #[test]
+    fn test_handle_send_secret_requires_provider() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "1".to_string(),
+            action: "send_secret".to_string(),
+            params: serde_json::json!({"selector": 1, "secret_ref": "work/openai"}),
+            ..Default::default()
+        };
+
+        let error = dispatch_command(&req, &mut state).error.unwrap();
+
+        assert!(error.starts_with("invalid params: secret_ref needs a provider"));
+        assert!(!error.contains("work/openai"));
+    }
+
+    #[test]
+    fn test_handle_send_secret_invalid_ref() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "1".to_string(),
+            action: "send_secret".to_string(),
+            params: serde_json::json!({"selector": 1, "secret_ref": "env:plaintext-value"}),
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert!(!result.success);
+        let error = result.error.unwrap();
+        assert!(error.contains("invalid params"));
+        assert!(!error.contains("plaintext-value"));
+    }
+
+    #[test]
+    fn test_recall_returns_recent_capture() {
+        let mut state = create_test_state();
+        state.update_pane_contents(1, vec!["```".into(), "first".into(), "```".into(), "```".into(), "second".into(), "```".into()]);
+        let req = Request {
+            id: "1".to_string(),
+            action: "extract_blocks".to_string(),
+            params: serde_json::json!({"selector": 1}),
+            ..Default::default()
+        };
+        dispatch_command(&req, &mut state);
+
+        let req = Request {
+            id: "2".to_string(),
+            action: "recall".to_string(),
+            params: serde_json::json!({"n": 1}),
+            ..Default::default()
+        };
+        let result = dispatch_command(&req, &mut state);
+
+        assert!(result.success);
+        let data = result.data.unwrap();
+        assert_eq!(data["content"], "first");
+        assert_eq!(data["pane_id"], 1);
+    }
+
+    #[test]
+    fn test_recall_send_to_produces_send_keys_effect() {
+        let mut state = create_test_state();
+        state.push_capture(1, "moved text".to_string());
+        let req = Request {
+            id: "1".to_string(),
+            action: "recall".to_string(),
+            params: serde_json::json!({"send_to": "proj__cc_2", "enter": true}),
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        let data = result.data.unwrap();
+        assert_eq!(data["action"], "send_keys");
+        assert_eq!(data["pane_id"], 2);
+        assert_eq!(data["text"], "moved text");
+        assert_eq!(data["source_pane_id"], 1);
+    }
+
+    #[test]
+    fn test_recall_out_of_range() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "1".to_string(),
+            action: "recall".to_string(),
+            params: serde_json::json!({"n": 3}),
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert!(!result.success);
+        assert!(result.error.unwrap().contains("no capture at position 3"));
+    }
+
+    #[test]
+    fn test_route_hook_denies_request() {
+        let mut state = create_test_state();
+        state.set_config(Config {
+            script: Some(r#"fn route(action, params, panes) { if action == "send_keys" { "read-only session" } }"#.to_string()),
+            ..Config::default()
+        });
+        let req = Request {
+            id: "1".to_string(),
+            action: "send_keys".to_string(),
+            params: serde_json::json!({"pane_id": 1, "text": "rm -rf /"}),
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert!(!result.success);
+        assert_eq!(result.error.unwrap(), "denied by route hook: read-only session");
+    }
+
+    #[test]
+    fn test_route_hook_fails_closed_when_script_does_not_load() {
+        let mut state = create_test_state();
+        state.set_config(Config {
+            script: Some("fn route(".to_string()),
+            ..Config::default()
+        });
+        let req = Request {
+            id: "1".to_string(),
+            action: "send_keys".to_string(),
+            params: serde_json::json!({"pane_id": 1, "text": "hi"}),
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert!(!result.success);
+        assert_eq!(result.code, Some(ErrorCode::Denied));
+        assert!(result.error.unwrap().starts_with("denied by route hook: script failed to load: script error"));
+    }
+
+    #[test]
+    fn test_route_hook_rewrites_params() {
+        let mut state = create_test_state();
+        state.set_config(Config {
+            script: Some(r#"fn route(action, params, panes) { if action == "send_keys" { params.pane_id = 2; params } }"#.to_string()),
+            ..Config::default()
+        });
+        let req = Request {
+            id: "1".to_string(),
+            action: "send_keys".to_string(),
+            params: serde_json::json!({"pane_id": 1, "text": "hi"}),
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert_eq!(result.data.unwrap()["pane_id"], 2);
+    }
+
+    #[test]
+    fn test_classify_pane_uses_script() {
+        let mut state = create_test_state();
+        state.set_config(Config {
+            script: Some(r#"fn classify(pane, lines) { if pane.title.contains("cc") { "claude" } else { "other" } }"#.to_string()),
+            ..Config::default()
+        });
+        let req = Request {
+            id: "1".to_string(),
+            action: "classify_pane".to_string(),
+            params: serde_json::json!({"selector": 1}),
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert_eq!(result.data.unwrap()["status"], "claude");
+    }
+
+    #[test]
+    fn test_classify_pane_without_script() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "1".to_string(),
+            action: "classify_pane".to_string(),
+            params: serde_json::json!({"selector": 1}),
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert_eq!(result.error.unwrap(), "no classify hook configured");
+    }
+
+    fn state_with_composite(json: &str) -> State {
+        let mut state = create_test_state();
+        let mut map = std::collections::BTreeMap::new();
+        map.insert("action.ship_it".to_string(), json.to_string());
+        state.set_config(Config::from_map(&map));
+        state
+    }
+
+    #[test]
+    fn test_composite_action_runs_steps_in_order() {
+        let mut state = state_with_composite(r#"{"steps":[
+            {"action":"send_keys","params":{"pane_id":"${pane}","text":"git push","enter":true}},
+            {"action":"send_keys","params":{"pane_id":"${pane}","text":"${note}"},"when":"note"},
+            {"action":"send_interrupt","params":{"pane_id":"${pane}"},"when":"abort"}
+        ]}"#);
+        let req = Request {
+            id: "1".to_string(),
+            action: "ship_it".to_string(),
+            params: serde_json::json!({"pane": 2, "note": "done"}),
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert!(result.success);
+        let data = result.data.unwrap();
+        assert_eq!(data["action"], "composite");
+        let steps = data["steps"].as_array().unwrap();
+        assert_eq!(steps[0]["result"]["text"], "git push");
+        assert_eq!(steps[1]["result"]["text"], "done");
+        assert_eq!(steps[1]["result"]["pane_id"], 2);
+        assert_eq!(steps[2]["skipped"], true);
+    }
+
+    #[test]
+    fn test_composite_action_stops_at_failed_step() {
+        let mut state = state_with_composite(r#"{"steps":[
+            {"action":"send_keys","params":{"pane_id":"${pane}","text":"x"}}
+        ]}"#);
+        let req = Request {
+            id: "1".to_string(),
+            action: "ship_it".to_string(),
+            params: serde_json::json!({"pane": 99}),
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert!(!result.success);
+        assert_eq!(result.error.unwrap(), "step 0 (send_keys) failed: pane not found: 99");
+    }
+
+    #[test]
+    fn test_composite_action_changes_nothing_when_a_later_step_fails() {
+        let mut state = state_with_composite(r#"{"steps":[
+            {"action":"dispatch_task"},
+            {"action":"get_pane_info","params":{"pane_id":99}}
+        ]}"#);
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "enqueue_task".to_string(),
+            params: serde_json::json!({"selector": "proj__cc_2", "prompt": "run tests"}),
+            ..Default::default()
+        };
+        let task_id = dispatch_command(&req, &mut state).data.unwrap()["task"]["id"].clone();
+
+        req.action = "ship_it".to_string();
+        req.params = serde_json::Value::Null;
+        let result = dispatch_command(&req, &mut state);
+
+        assert_eq!(result.error.unwrap(), "step 1 (get_pane_info) failed: pane not found: 99");
+        req.action = "list_tasks".to_string();
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["tasks"][0]["id"], task_id);
+        assert_eq!(data["tasks"][0]["status"], "pending");
+    }
+
+    #[test]
+    fn test_describe_actions_lists_composites() {
+        let mut state = state_with_composite(r#"{"description":"push it","steps":[
+            {"action":"send_keys","params":{"pane_id":"${pane}","text":"git push"}}
+        ]}"#);
+        let req = Request {
+            id: "1".to_string(),
+            action: "describe_actions".to_string(),
+            params: serde_json::Value::Null,
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        let actions = result.data.unwrap()["actions"].as_array().unwrap().clone();
+        assert!(actions.iter().any(|a| a["name"] == "list_panes"));
+        let ship_it = actions.iter().find(|a| a["name"] == "ship_it").unwrap();
+        assert_eq!(ship_it["composite"], true);
+        assert_eq!(ship_it["args"], serde_json::json!(["pane"]));
+    }
+
+    #[test]
+    fn test_explain_reports_targets_and_effects() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "1".to_string(),
+            action: "send_keys".to_string(),
+            params: serde_json::json!({"pane_id": 2, "text": "ls", "enter": true}),
+            explain: true,
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        let plan = result.data.unwrap();
+        assert_eq!(plan["explain"], true);
+        assert_eq!(plan["valid"], true);
+        assert_eq!(plan["targets"][0]["panes"][0]["title"], "proj__cc_2");
+        assert_eq!(plan["effects"][0]["action"], "send_keys");
+        assert_eq!(plan["effects"][0]["text"], "ls");
+    }
+
+    #[test]
+    fn test_explain_does_not_change_state() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "1".to_string(),
+            action: "checkpoint".to_string(),
+            params: serde_json::json!({"selector": 1, "name": "before"}),
+            explain: true,
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert_eq!(result.data.unwrap()["state_change"], true);
+        assert!(state.lines_since_checkpoint(1, "before").is_err());
+    }
+
+    #[test]
+    fn test_explain_leaves_state_unchanged() {
+        let mut state = create_test_state();
+        state.update_pane_contents(1, vec!["out".to_string()]);
+        let mut config = state.config().clone();
+        config.task_queue_capacity = 0;
+        config.max_response_bytes = 4;
+        state.set_config(config);
+        let explain = |state: &mut State, action: &str, params: serde_json::Value| {
+            let req = Request {
+                id: "1".to_string(),
+                action: action.to_string(),
+                params,
+                explain: true,
+                ..Default::default()
+            };
+            dispatch_command(&req, state).data.unwrap()
+        };
+
+        let plan = explain(&mut state, "send_keys", serde_json::json!({"pane_id": 1, "text": "ls", "chunk_bytes": 1}));
+        assert_eq!(plan["valid"], true);
+        assert!(plan["effects"][0].get("job_id").is_none());
+        for (action, params) in [
+            ("capture_pane", serde_json::json!({"pane_id": 1})),
+            ("store_capture", serde_json::json!({"selector": 1})),
+            ("export_conversation", serde_json::json!({"selector": 1})),
+            ("enqueue_task", serde_json::json!({"selector": 1, "prompt": "p"})),
+        ] {
+            assert_eq!(explain(&mut state, action, params)["state_change"], true, "{}", action);
+        }
+        assert!(state.take_events().is_empty());
+        assert!(state.artifacts().list().is_empty());
+        assert_eq!(state.tasks().list().len(), 0);
+
+        // The job id explain did not take goes to the first real send
+        let mut req = Request {
+            id: "2".to_string(),
+            action: "send_keys".to_string(),
+            params: serde_json::json!({"pane_id": 1, "text": "ls", "chunk_bytes": 1}),
+            ..Default::default()
+        };
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["job_id"], "j1");
+
+        // A full queue is reported without a backpressure event
+        let mut config = state.config().clone();
+        config.send_queue_capacity = 0;
+        state.set_config(config);
+        req.explain = true;
+        let plan = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(plan["valid"], false);
+        assert!(plan["error"].as_str().unwrap().starts_with("queue full"));
+        assert!(state.take_events().is_empty());
+    }
+
+    #[test]
+    fn test_explain_reports_route_denial() {
+        let mut state = create_test_state();
+        state.set_config(Config {
+            script: Some(r#"fn route(action, params, panes) { "no" }"#.to_string()),
+            ..Config::default()
+        });
+        let req = Request {
+            id: "1".to_string(),
+            action: "list_panes".to_string(),
+            params: serde_json::Value::Null,
+            explain: true,
+            ..Default::default()
+        };
+
+        let plan = dispatch_command(&req, &mut state).data.unwrap();
+
+        assert_eq!(plan["valid"], false);
+        assert_eq!(plan["policy"][0]["result"], "deny");
+        assert_eq!(plan["error"], "denied by route hook: no");
+    }
+
+    #[test]
+    fn test_explain_composite_steps() {
+        let mut state = state_with_composite(r#"{"steps":[
+            {"action":"send_keys","params":{"pane_id":"${pane}","text":"git push"}},
+            {"action":"send_interrupt","params":{"pane_id":"${pane}"},"when":"abort"}
+        ]}"#);
+        let req = Request {
+            id: "1".to_string(),
+            action: "ship_it".to_string(),
+            params: serde_json::json!({"pane": 1}),
+            explain: true,
+            ..Default::default()
+        };
+
+        let plan = dispatch_command(&req, &mut state).data.unwrap();
+
+        assert_eq!(plan["valid"], true);
+        assert_eq!(plan["steps"][0]["plan"]["effects"][0]["text"], "git push");
+        assert_eq!(plan["steps"][1]["skipped"], true);
+    }
+
+    #[test]
+    fn test_transaction_returns_all_effects() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "1".to_string(),
+            action: "transaction".to_string(),
+            params: serde_json::json!({"requests": [
+                {"action": "send_keys", "params": {"pane_id": 1, "text": "a"}},
+                {"action": "send_interrupt", "params": {"pane_id": 2}},
+            ]}),
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert!(result.success);
+        let data = result.data.unwrap();
+        assert_eq!(data["action"], "transaction");
+        assert_eq!(data["results"][0]["action"], "send_keys");
+        assert_eq!(data["results"][1]["action"], "send_interrupt");
+    }
+
+    #[test]
+    fn test_transaction_fails_as_a_whole() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "1".to_string(),
+            action: "transaction".to_string(),
+            params: serde_json::json!({"requests": [
+                {"action": "send_keys", "params": {"pane_id": 1, "text": "a"}},
+                {"action": "send_keys", "params": {"pane_id": 9, "text": "b"}},
+            ]}),
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert!(!result.success);
+        assert!(result.data.is_none());
+        assert_eq!(
+            result.error.unwrap(),
+            "operation 1 (send_keys) failed, nothing was applied: pane not found: 9"
+        );
+    }
+
+    #[test]
+    fn test_transaction_plans_operations_in_order() {
+        let mut state = create_test_state();
+        state.set_config(Config { send_queue_capacity: 1, ..Config::default() });
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "transaction".to_string(),
+            params: serde_json::json!({"requests": [
+                {"action": "send_keys", "params": {"pane_id": 1, "text": "a", "chunk_bytes": 8}},
+                {"action": "send_keys", "params": {"pane_id": 2, "text": "b", "chunk_bytes": 8}},
+            ]}),
+            ..Default::default()
+        };
+        assert_eq!(
+            dispatch_command(&req, &mut state).error.unwrap(),
+            "operation 1 (send_keys) failed, nothing was applied: queue full: sends holds 1 of 1"
+        );
+
+        req.params = serde_json::json!({"requests": [
+            {"action": "close_pane", "params": {"pane_id": 2}},
+            {"action": "send_keys", "params": {"pane_id": 1, "text": "a", "chunk_bytes": 8}},
+            {"action": "focus_pane", "params": {"pane_id": 2}},
+        ]});
+        assert_eq!(
+            dispatch_command(&req, &mut state).error.unwrap(),
+            "operation 2 (focus_pane) failed, nothing was applied: pane 2 is closed by operation 0"
+        );
+    }
+
+    #[test]
+    fn test_transaction_rejects_stateful_and_deferred_actions() {
+        let mut state = create_test_state();
+        for action in ["checkpoint", "apply_patch", "transaction"] {
+            let req = Request {
+                id: "1".to_string(),
+                action: "transaction".to_string(),
+                params: serde_json::json!({"requests": [{"action": action, "params": {}}]}),
+                ..Default::default()
+            };
+
+            let result = dispatch_command(&req, &mut state);
+
+            assert!(result.error.unwrap().contains("cannot run inside a transaction"));
+        }
+    }
+
+    #[test]
+    fn test_if_revision_rejects_stale_requests() {
+        let mut state = create_test_state();
+        let revision = state.revision();
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "send_interrupt".to_string(),
+            params: serde_json::json!({"pane_id": 1}),
+            if_revision: Some(revision),
+            ..Default::default()
+        };
+        assert!(dispatch_command(&req, &mut state).success);
+
+        state.update_panes(create_manifest_with_panes(vec![create_test_pane(1, "proj__cc_1", false)]));
+        req.if_revision = Some(revision);
+        let result = dispatch_command(&req, &mut state);
+
+        assert!(!result.success);
+        assert!(result.error.unwrap().starts_with("stale revision"));
+    }
+
+    #[test]
+    fn test_list_panes_reports_revision() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "1".to_string(),
+            action: "list_panes".to_string(),
+            params: serde_json::Value::Null,
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert_eq!(result.data.unwrap()["revision"], state.revision());
+    }
+
+    #[test]
+    fn test_list_panes_since_revision_returns_delta() {
+        let mut state = create_test_state();
+        let seen = state.revision();
+        state.update_panes(create_manifest_with_panes(vec![
+            create_test_pane(1, "proj__cc_1", false),
+            create_test_pane(3, "proj__cc_3", false),
+        ]));
+        let req = Request {
+            id: "1".to_string(),
+            action: "list_panes".to_string(),
+            params: serde_json::json!({"since_revision": seen}),
+            ..Default::default()
+        };
+
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+
+        assert_eq!(data["delta"], true);
+        assert_eq!(data["added"][0]["id"], 3);
+        assert_eq!(data["changed"], serde_json::json!([]));
+        assert_eq!(data["removed"], serde_json::json!([2]));
+        assert_eq!(data["revision"], state.revision());
+    }
+
+    #[test]
+    fn test_mirror_state_returns_effect() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "1".to_string(),
+            action: "mirror_state".to_string(),
+            params: serde_json::Value::Null,
+            ..Default::default()
+        };
+
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+
+        assert_eq!(data["action"], "mirror_state");
+        assert_eq!(data["revision"], state.revision());
+    }
+
+    #[test]
+    fn test_capture_frame_uses_pane_size() {
+        let mut state = State::default();
+        let mut pane = create_test_pane(1, "tui", false);
+        pane.pane_content_rows = 2;
+        pane.pane_content_columns = 4;
+        state.update_panes(create_manifest_with_panes(vec![pane]));
+        state.update_pane_contents(1, vec!["scrolled".to_string(), "\x1b[1mtop\x1b[0m".to_string()]);
+        state.set_viewport_rows(1, 1);
+        let req = Request {
+            id: "1".to_string(),
+            action: "capture_frame".to_string(),
+            params: serde_json::json!({"selector": 1, "attributes": true}),
+            ..Default::default()
+        };
+
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+
+        assert_eq!(data["rows"], 2);
+        assert_eq!(data["cols"], 4);
+        assert_eq!(data["lines"], serde_json::json!(["top ", "    "]));
+        assert_eq!(data["attributes"][0][0]["sgr"], "1");
+    }
+
+    #[test]
+    fn test_capture_pane_returns_viewport() {
+        let mut state = create_test_state();
+        let lines = ["scrolled", "\x1b[1m$ make\x1b[0m", "built", "", ""];
+        state.update_pane_contents(1, lines.iter().map(|l| l.to_string()).collect());
+        state.set_viewport_rows(1, 4);
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "capture_pane".to_string(),
+            params: serde_json::json!({"pane_id": 1}),
+            ..Default::default()
+        };
+
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["rows"].as_u64(), data["content"].as_str()), (Some(4), Some("$ make\nbuilt")));
+        assert_eq!(data["truncated"], false);
+        assert_eq!(state.captures().next().unwrap().text, "$ make\nbuilt");
+
+        state.set_config(Config { max_response_bytes: 4, ..Config::default() });
+        req.params = serde_json::json!({"pane_id": 1, "ansi": true});
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["content"].as_str(), data["truncated"].as_bool()), (Some("\x1b[1m"), Some(true)));
+        assert!(data["artifact_id"].is_string());
+
+        req.params = serde_json::json!({"pane_id": 9});
+        assert_eq!(dispatch_command(&req, &mut state).error.unwrap(), "pane not found: 9");
+    }
+
+    #[test]
+    fn test_dump_scrollback_inline_and_to_file() {
+        let mut state = create_test_state();
+        let lines = ["one", "\x1b[1mtwo\x1b[0m", "three", ""];
+        state.update_pane_contents(1, lines.iter().map(|l| l.to_string()).collect());
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "dump_scrollback".to_string(),
+            params: serde_json::json!({"selector": 1, "lines": 3}),
+            ..Default::default()
+        };
+
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["content"].as_str(), data["lines"].as_u64()), (Some("two\nthree"), Some(2)));
+        assert_eq!((data["total_lines"].as_u64(), data["truncated"].as_bool()), (Some(4), Some(false)));
+
+        req.params = serde_json::json!({"selector": 1, "path": "logs/pane1.txt"});
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["action"].as_str(), data["cwd"].as_str()), (Some("put_file"), Some("/data/projects/proj")));
+        assert_eq!(files::decode_content(data["content"].as_str().unwrap()).unwrap(), b"one\ntwo\nthree\n");
+        assert_eq!(data["reply"]["file"], "/data/projects/proj/logs/pane1.txt");
+
+        req.params = serde_json::json!({"selector": 1, "path": "../x"});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().starts_with("invalid params"));
+        req.params = serde_json::json!({"selector": 1, "lines": 0});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().starts_with("invalid params"));
+    }
+
+    #[test]
+    fn test_assert_pane_pass_and_fail() {
+        let mut state = create_test_state();
+        state.update_pane_contents(1, vec!["building".to_string(), "\x1b[32mPASS\x1b[0m 3 tests".to_string()]);
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "assert_pane".to_string(),
+            params: serde_json::json!({"selector": 1, "regex": "^PASS \\d+ tests$"}),
+            ..Default::default()
+        };
+
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["passed"], true);
+        assert_eq!(data["line"], 1);
+
+        req.params = serde_json::json!({"selector": 1, "contains": "FAIL"});
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["passed"], false);
+        assert_eq!(data["context"], serde_json::json!(["building", "PASS 3 tests"]));
+    }
+
+    #[test]
+    fn test_assert_pane_requires_one_matcher() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "1".to_string(),
+            action: "assert_pane".to_string(),
+            params: serde_json::json!({"selector": 1, "contains": "a", "regex": "b"}),
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert!(result.error.unwrap().contains("exactly one of contains or regex"));
+    }
+
+    #[test]
+    fn test_shutdown_persists_and_refuses_requests() {
+        let mut state = create_test_state();
+        state.update_pane_contents(1, vec!["final output".to_string()]);
+        state.push_capture(2, "captured".to_string());
+        let req = Request {
+            id: "1".to_string(),
+            action: "shutdown".to_string(),
+            params: serde_json::json!({"unload": true}),
+            ..Default::default()
+        };
+
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+
+        assert_eq!(data["unload"], true);
+        let persisted = data["persisted"].as_array().unwrap();
+        assert_eq!(persisted.len(), 2);
+        assert_eq!(persisted[0]["kind"], "transcript");
+        let id = persisted[0]["id"].as_str().unwrap();
+        assert_eq!(state.artifacts().get(id).unwrap(), "final output");
+
+        let after = dispatch_command(&req, &mut state);
+        assert_eq!(after.error.unwrap(), "plugin is shutting down");
+    }
+
+    #[test]
+    fn test_task_lifecycle_dispatches_once() {
+        let mut state = create_test_state();
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "enqueue_task".to_string(),
+            params: serde_json::json!({"selector": "proj__cc_2", "prompt": "run tests"}),
+            ..Default::default()
+        };
+        let task_id = dispatch_command(&req, &mut state).data.unwrap()["task"]["id"].clone();
+
+        req.action = "dispatch_task".to_string();
+        req.params = serde_json::Value::Null;
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["action"], "send_keys");
+        assert_eq!(data["pane_id"], 2);
+        assert_eq!(data["text"], "run tests");
+        assert_eq!(data["task_id"], task_id);
+
+        req.params = serde_json::json!({"id": task_id});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().contains("not pending"));
+
+        req.action = "complete_task".to_string();
+        req.params = serde_json::json!({"id": task_id, "result": "ok"});
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["task"]["status"], "completed");
+    }
+
+    #[test]
+    fn test_export_and_replay_history() {
+        let mut state = create_test_state();
+        state.update_panes(create_manifest_with_panes(vec![create_test_pane(2, "proj__cc_2", false)]));
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "export_history".to_string(),
+            params: serde_json::Value::Null,
+            ..Default::default()
+        };
+        let artifact_id = dispatch_command(&req, &mut state).data.unwrap()["artifact_id"].clone();
+
+        req.action = "replay_history".to_string();
+        req.params = serde_json::json!({"artifact_id": artifact_id});
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+
+        assert_eq!(data["matches_live"], true);
+        let steps = data["steps"].as_array().unwrap();
+        assert_eq!(steps.len(), 2);
+        assert_eq!(steps[0]["panes"], serde_json::json!([1, 2]));
+        assert_eq!(steps[1]["panes"], serde_json::json!([2]));
+        assert_eq!(steps[1]["problems"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_list_agents_uses_configured_convention() {
+        let mut state = create_test_state();
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "list_agents".to_string(),
+            params: serde_json::Value::Null,
+            ..Default::default()
+        };
+
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["agents"][0]["project"], "proj");
+        assert_eq!(data["agents"][0]["kind"], "cc");
+        assert_eq!(data["agents"][1]["index"], 2);
+
+        state.set_config(Config {
+            title_format: "{kind}/{project}#{index}".to_string(),
+            ..Config::default()
+        });
+        req.id = "2".to_string();
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["agents"], serde_json::json!([]));
+    }
+
+    #[test]
+    fn test_classify_pane_falls_back_to_kind_status() {
+        let mut state = create_test_state();
+        let mut map = std::collections::BTreeMap::new();
+        map.insert(
+            "kind.cc".to_string(),
+            r#"{"aliases": ["claude"], "status": [{"state": "working", "regex": "esc to interrupt"}]}"#.to_string(),
+        );
+        state.set_config(Config::from_map(&map));
+        state.update_pane_contents(1, vec!["thinking (esc to interrupt)".to_string()]);
+
+        let req = Request {
+            id: "1".to_string(),
+            action: "classify_pane".to_string(),
+            params: serde_json::json!({"selector": 1}),
+            ..Default::default()
+        };
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["status"], "working");
+
+        let req = Request {
+            id: "2".to_string(),
+            action: "list_kinds".to_string(),
+            params: serde_json::Value::Null,
+            ..Default::default()
+        };
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["kinds"][0]["status"][0]["state"], "working");
+        assert_eq!(data["kinds"][2]["name"], "gmi");
+    }
+
+    #[test]
+    fn test_adopt_pane_registers_and_renames() {
+        let mut state = create_test_state();
+        state.update_panes(create_manifest_with_panes(vec![
+            create_test_pane(1, "proj__cc_1", false),
+            create_test_pane(2, "proj__cc_2", false),
+            create_test_pane(3, "shell", false),
+        ]));
+        let req = Request {
+            id: "1".to_string(),
+            action: "adopt_pane".to_string(),
+            params: serde_json::json!({"selector": "shell", "kind": "claude", "project": "proj", "rename": true}),
+            ..Default::default()
+        };
+
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+
+        assert_eq!(data["action"], "rename_pane");
+        assert_eq!(data["title"], "proj__cc_3");
+        let (name, sources) = state.agent_name(state.get_pane(3).unwrap()).unwrap();
+        assert_eq!((name.kind.as_str(), name.index), ("cc", Some(3)));
+        assert_eq!(sources, vec!["adopted"]);
+    }
+
+    #[test]
+    fn test_adopt_pane_rejects_unknown_kind() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "1".to_string(),
+            action: "adopt_pane".to_string(),
+            params: serde_json::json!({"selector": 2, "kind": "nope"}),
+            ..Default::default()
+        };
+
+        let result = dispatch_command(&req, &mut state);
+
+        assert_eq!(result.error.unwrap(), "unknown agent kind: nope");
+    }
+
+    #[test]
+    fn test_broadcast_reports_each_target() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "1".to_string(),
+            action: "broadcast".to_string(),
+            params: serde_json::json!({
+                "action": "send_keys",
+                "params": {"text": "hi", "enter": true},
+                "selectors": ["proj__*", 2, "missing"],
+            }),
+            ..Default::default()
+        };
+
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+
+        assert_eq!(data["succeeded"], 2);
+        assert_eq!(data["failed"], 1);
+        let targets = data["targets"].as_array().unwrap();
+        assert_eq!(targets.len(), 3);
+        assert_eq!(targets[0]["effect"]["action"], "send_keys");
+        assert_eq!(targets[1]["pane_id"], 2);
+        assert_eq!(targets[2]["code"], "not_found");
+    }
+
+    #[test]
+    fn test_broadcast_continues_past_failures() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "1".to_string(),
+            action: "broadcast".to_string(),
+            params: serde_json::json!({"action": "send_keys", "selectors": [1, 2]}),
+            ..Default::default()
+        };
+
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+
+        assert_eq!(data["failed"], 2);
+        assert_eq!(data["targets"][1]["code"], "invalid_params");
+    }
+
+    #[test]
+    fn test_send_priorities_reach_the_effect() {
+        let mut state = create_test_state();
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "send_interrupt".to_string(),
+            params: serde_json::json!({"pane_id": 1}),
+            ..Default::default()
+        };
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["priority"], "urgent");
+
+        req.action = "send_keys".to_string();
+        req.params = serde_json::json!({"pane_id": 1, "text": "x", "priority": "bulk", "chunk_bytes": 64});
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["priority"], "bulk");
+        assert_eq!(data["chunk_bytes"], 64);
+
+        req.params = serde_json::json!({"pane_id": 1, "text": "x", "priority": "asap"});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().contains("invalid params"));
+    }
+
+    #[test]
+    fn test_control_send_follows_job_status() {
+        let mut state = create_test_state();
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "send_keys".to_string(),
+            params: serde_json::json!({"pane_id": 1, "text": "long paste", "chunk_bytes": 4}),
+            ..Default::default()
+        };
+        let job_id = dispatch_command(&req, &mut state).data.unwrap()["job_id"].clone();
+        assert_eq!(job_id, "j1");
+        state.track_send_job(SendJob {
+            id: "j1".to_string(),
+            pane_id: 1,
+            status: JobStatus::Running,
+            chunks_sent: 1,
+            chunks_total: 3,
+        });
+
+        req.action = "pause_send".to_string();
+        req.params = serde_json::json!({"job_id": "j1"});
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["action"], "control_send");
+        assert_eq!(data["status"], "paused");
+
+        let err = dispatch_command(&req, &mut state).error.unwrap();
+        assert_eq!(err, "cannot pause send j1: it is paused");
+
+        req.action = "abort_send".to_string();
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["status"], "aborted");
+
+        req.action = "list_sends".to_string();
+        req.params = serde_json::Value::Null;
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["sends"][0]["status"], "aborted");
+
+        req.action = "resume_send".to_string();
+        req.params = serde_json::json!({"job_id": "j9"});
+        assert_eq!(dispatch_command(&req, &mut state).error.unwrap(), "send not found: j9");
+    }
+
+    #[test]
+    fn test_send_keys_bracketed_paste() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "1".to_string(),
+            action: "send_keys".to_string(),
+            params: serde_json::json!({"pane_id": 1, "text": "a\x1b[201~b", "bracketed": true, "chunk_bytes": 4096}),
+            ..Default::default()
+        };
+
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+
+        assert_eq!(data["text"], "\x1b[200~ab\x1b[201~");
+        assert_eq!(data["job_id"], "j1");
+    }
+
+    #[test]
+    fn test_send_keys_paste_wraps_multiline_text() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "1".to_string(),
+            action: "send_keys".to_string(),
+            params: serde_json::json!({"pane_id": 1, "text": "fix it\nthen test", "paste": true, "enter": true}),
+            ..Default::default()
+        };
+
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+
+        assert_eq!(data["text"], "\x1b[200~fix it\nthen test\x1b[201~");
+        assert_eq!(data["enter"], true);
+    }
+
+    #[test]
+    fn test_send_raw_checks_base64_and_size() {
+        let mut state = create_test_state();
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "send_raw".to_string(),
+            params: serde_json::json!({"pane_id": 1, "bytes": "G1s/MTA0OWgA/w=="}),
+            ..Default::default()
+        };
+
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["action"].as_str(), data["length"].as_u64()), (Some("send_raw"), Some(10)));
+        req.params = serde_json::json!({"pane_id": 1, "bytes": "not base64!"});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().starts_with("invalid params: bytes is not base64"));
+        req.params = serde_json::json!({"pane_id": 1, "bytes": ""});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().starts_with("invalid params: bytes decodes to 0"));
+    }
+
+    #[test]
+    fn test_send_keys_named_keys_follow_text() {
+        let mut state = create_test_state();
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "send_keys".to_string(),
+            params: serde_json::json!({"pane_id": 1, "text": "y", "keys": ["Down", "Enter"]}),
+            ..Default::default()
+        };
+
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["text"], "y\x1b[B\r");
+        req.params = serde_json::json!({"pane_id": 1, "keys": ["Escape"]});
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["text"], "\x1b");
+        req.params = serde_json::json!({"pane_id": 1, "keys": ["Hyper"]});
+        assert_eq!(dispatch_command(&req, &mut state).error.unwrap(), "invalid params: unknown key: Hyper");
+    }
+
+    #[test]
+    fn test_put_file_resolves_project_directory() {
+        let mut state = create_test_state();
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "put_file".to_string(),
+            params: serde_json::json!({"selector": 2, "path": "docs/spec.md", "content": "aGk="}),
+            ..Default::default()
+        };
+
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["action"], "put_file");
+        assert_eq!(data["cwd"], "/data/projects/proj");
+        assert_eq!(data["bytes"], 2);
+
+        req.params = serde_json::json!({"project": "api", "path": "../x", "content": "aGk="});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().contains("'..'"));
+
+        req.params = serde_json::json!({"path": "x", "content": "aGk="});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().contains("project or selector"));
+    }
+
+    #[test]
+    fn test_file_actions_use_the_pane_working_directory() {
+        let mut state = create_test_state();
+        state.set_pane_cwd(2, "/home/me/wt/proj-2");
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "get_file".to_string(),
+            params: serde_json::json!({"selector": 2, "path": "out.txt"}),
+            ..Default::default()
+        };
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["cwd"], "/home/me/wt/proj-2");
+
+        // An explicit project still names the project directory
+        req.params = serde_json::json!({"selector": 2, "project": "proj", "path": "out.txt"});
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["cwd"], "/data/projects/proj");
+    }
+
+    #[test]
+    fn test_get_file_validates_and_limits_reply() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "1".to_string(),
+            action: "get_file".to_string(),
+            params: serde_json::json!({"project": "api", "path": "out/result.json"}),
+            ..Default::default()
+        };
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["cwd"], "/data/projects/api");
+        assert_eq!(data["encoding"], "text");
+
+        state.set_config(Config { max_response_bytes: 4, ..Config::default() });
+        let data = file_response(&mut state, "1", "out/result.json", b"0123456789", OutputEncoding::Text).data.unwrap();
+        assert_eq!(data["content"], "0123");
+        assert_eq!(data["bytes"], 10);
+        let id = data["artifact_id"].as_str().unwrap();
+        assert_eq!(state.artifacts().get(id).unwrap(), "0123456789");
+
+        let big = vec![b'x'; files::MAX_FILE_BYTES + 1];
+        let err = file_response(&mut state, "1", "big", &big, OutputEncoding::Text).error.unwrap();
+        assert!(err.starts_with("file too large"));
+    }
+
+    #[test]
+    fn test_git_info_uses_cache_until_refresh() {
+        let mut state = create_test_state();
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "git_info".to_string(),
+            params: serde_json::json!({"selector": 1}),
+            ..Default::default()
+        };
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["action"], "git_info");
+        assert_eq!(data["cwd"], "/data/projects/proj");
+
+        state.cache_git_info("/data/projects/proj", GitInfo {
+            branch: Some("main".to_string()),
+            dirty: true,
+            changed: 1,
+            ..GitInfo::default()
+        });
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert!(data.get("action").is_none());
+        assert_eq!(data["cached"], true);
+        assert_eq!(data["branch"], "main");
+        assert_eq!(data["dirty"], true);
+
+        req.params = serde_json::json!({"selector": 1, "refresh": true});
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["action"], "git_info");
+    }
+
+    #[test]
+    fn test_guard_blocks_dispatch_on_conflicts() {
+        let mut state = create_test_state();
+        state.set_config(Config {
+            guards: vec![Guard::parse("no_conflicts", r#"{"when": "conflicts"}"#).unwrap()],
+            ..Config::default()
+        });
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "enqueue_task".to_string(),
+            params: serde_json::json!({"selector": 1, "prompt": "fix it"}),
+            ..Default::default()
+        };
+        // Without git info the guard cannot tell, and blocks
+        assert_eq!(
+            dispatch_command(&req, &mut state).error.unwrap(),
+            "blocked by guard no_conflicts: git info unavailable for proj; refresh it with git_info"
+        );
+        state.cache_git_info("/data/projects/proj", GitInfo::default());
+        assert!(dispatch_command(&req, &mut state).success);
+
+        state.cache_git_info("/data/projects/proj", GitInfo { conflicts: 2, ..GitInfo::default() });
+        req.action = "dispatch_task".to_string();
+        req.params = serde_json::json!({});
+        let err = dispatch_command(&req, &mut state).error.unwrap();
+        assert_eq!(err, "blocked by guard no_conflicts: proj has 2 merge conflicts");
+        assert_eq!(state.tasks().next_pending(None).unwrap().status, TaskStatus::Pending);
+
+        req.action = "send_keys".to_string();
+        req.params = serde_json::json!({"pane_id": 1, "text": "hi"});
+        assert!(!dispatch_command(&req, &mut state).success);
+    }
+
+    #[test]
+    fn test_unparsable_guard_blocks_sends() {
+        let mut state = create_test_state();
+        let mut map = std::collections::BTreeMap::new();
+        map.insert("guard.clean".to_string(), r#"{"when": "never"}"#.to_string());
+        state.set_config(Config::from_map(&map));
+        let req = Request {
+            id: "1".to_string(),
+            action: "send_keys".to_string(),
+            params: serde_json::json!({"pane_id": 1, "text": "hi"}),
+            ..Default::default()
+        };
+
+        let err = dispatch_command(&req, &mut state).error.unwrap();
+
+        assert!(err.starts_with("blocked by guard clean: it could not be parsed: invalid guard clean"));
+        let req = Request { action: "get_version".to_string(), ..req };
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert!(data["config_errors"]["guard.clean"].is_string());
+    }
+
+    #[test]
+    fn test_worktree_lock_guard() {
+        let mut state = create_test_state();
+        state.set_config(Config {
+            guards: vec![Guard::parse("one_writer", r#"{"when": "locked", "actions": ["send_keys"]}"#).unwrap()],
+            ..Config::default()
+        });
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "lock_worktree".to_string(),
+            params: serde_json::json!({"selector": 1}),
+            ..Default::default()
+        };
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["project"], "proj");
+        req.params = serde_json::json!({"selector": 2});
+        assert_eq!(
+            dispatch_command(&req, &mut state).error.unwrap(),
+            "worktree of proj is locked by pane 1"
+        );
+
+        req.action = "send_keys".to_string();
+        req.params = serde_json::json!({"pane_id": 1, "text": "hi"});
+        assert!(dispatch_command(&req, &mut state).success);
+        req.params = serde_json::json!({"pane_id": 2, "text": "hi"});
+        let err = dispatch_command(&req, &mut state).error.unwrap();
+        assert!(err.starts_with("blocked by guard one_writer: worktree of proj is locked by pane 1"));
+
+        req.action = "unlock_worktree".to_string();
+        req.params = serde_json::json!({"selector": 2});
+        assert_eq!(
+            dispatch_command(&req, &mut state).error.unwrap(),
+            "worktree of proj is locked by pane 1, not pane 2; pass force to release it"
+        );
+        req.params = serde_json::json!({"selector": 1});
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["released_from"], 1);
+        req.action = "send_keys".to_string();
+        req.params = serde_json::json!({"pane_id": 2, "text": "hi"});
+        assert!(dispatch_command(&req, &mut state).success);
+    }
+
+    #[test]
+    fn test_spawn_agent_takes_next_index() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "1".to_string(),
+            action: "spawn_agent".to_string(),
+            params: serde_json::json!({
+                "kind": "claude",
+                "project": "proj",
+                "env": {"MODEL": "opus", "API_KEY": {"secret_ref": "env:ANTHROPIC_API_KEY"}},
+            }),
+            ..Default::default()
+        };
+
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+
+        assert_eq!(data["action"], "open_pane");
+        assert_eq!(data["title"], "proj__cc_3");
+        assert_eq!(data["agent"]["index"], 3);
+        assert_eq!(data["cwd"], "/data/projects/proj");
+        let script = data["command"][2].as_str().unwrap();
+        assert!(script.contains("export MODEL='opus'\n"));
+        assert!(script.contains("'ANTHROPIC_API_KEY'"));
+        assert!(script.ends_with("exec claude"));
+    }
+
+    #[test]
+    fn test_new_pane_validates_params() {
+        let mut state = create_test_state();
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "new_pane".to_string(),
+            params: serde_json::json!({"command": "htop", "cwd": "relative/dir"}),
+            ..Default::default()
+        };
+        assert!(dispatch_command(&req, &mut state).error.unwrap().contains("cwd must be absolute"));
+
+        req.params = serde_json::json!({"command": "htop", "env": {"TOKEN": {"secret_ref": "vault:x"}}});
+        let err = dispatch_command(&req, &mut state).error.unwrap();
+        assert!(err.starts_with("invalid params: env TOKEN"));
+
+        req.params = serde_json::json!({"command": "htop", "cwd": "/tmp"});
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["command"][2], "exec htop");
+        assert!(data.get("agent").is_none());
+
+        req.params = serde_json::json!({"args": ["-x"]});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().contains("args given without command"));
+
+        req.params = serde_json::json!({"direction": "left"});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().starts_with("invalid params"));
+    }
+
+    #[test]
+    fn test_new_pane_direction_and_name() {
+        let mut state = create_test_state();
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "new_pane".to_string(),
+            params: serde_json::json!({"command": "tail", "args": ["-f", "a log"], "direction": "down", "name": "logs"}),
+            ..Default::default()
+        };
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["action"], "new_pane");
+        assert_eq!(data["session"], "test");
+        assert_eq!(data["command"][2], "exec tail '-f' 'a log'");
+        assert_eq!(data["direction"], "down");
+        assert_eq!(data["name"], "logs");
+
+        req.params = serde_json::json!({"floating": true});
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["command"][2], "exec \"${SHELL:-sh}\"");
+        assert_eq!(data["direction"], "floating");
+    }
+
+    #[test]
+    fn test_spawn_agent_cwd_template() {
+        let mut state = create_test_state();
+        let aider = AgentKind::parse("aider", r#"{"command": "aider", "cwd": "~/src/{project}"}"#).unwrap();
+        state.set_config(Config { agent_kinds: vec![aider], ..Config::default() });
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "spawn_agent".to_string(),
+            params: serde_json::json!({"kind": "aider", "project": "api"}),
+            ..Default::default()
+        };
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["cwd"], "~/src/api");
+
+        req.params = serde_json::json!({"kind": "aider", "project": "api", "cwd": "/work/{project}-{index}"});
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["cwd"], "/work/api-1");
+
+        req.params = serde_json::json!({"kind": "aider", "project": "api", "cwd": "work/{project}"});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().contains("cwd must be absolute"));
+    }
+
+    #[test]
+    fn test_spawn_agent_in_container() {
+        let mut state = create_test_state();
+        let cc = AgentKind::parse("cc", r#"{"command": "claude", "container": {"runtime": "docker", "name": "{project}-dev"}}"#).unwrap();
+        state.set_config(Config { agent_kinds: vec![cc], ..Config::default() });
+        let req = Request {
+            id: "1".to_string(),
+            action: "spawn_agent".to_string(),
+            params: serde_json::json!({"kind": "cc", "project": "api", "env": {"MODEL": "opus"}}),
+            ..Default::default()
+        };
+
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert!(data["command"][2].as_str().unwrap().ends_with("exec docker exec -it -e MODEL 'api-dev' sh -c 'claude'"));
+        assert_eq!(data["cwd"], "/data/projects/api");
+    }
+
+    #[test]
+    fn test_spawn_agent_loads_env() {
+        let mut state = create_test_state();
+        let cc = AgentKind::parse("cc", r#"{"command": "claude", "load_env": ["mise"]}"#).unwrap();
+        state.set_config(Config { agent_kinds: vec![cc], ..Config::default() });
+        let req = Request {
+            id: "1".to_string(),
+            action: "spawn_agent".to_string(),
+            params: serde_json::json!({"kind": "cc", "project": "api"}),
+            ..Default::default()
+        };
+
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["command"][2], "command -v mise >/dev/null && eval \"$(mise env -s bash)\"\nexec claude");
+    }
+
+    #[test]
+    fn test_spawn_agent_with_preset() {
+        let mut state = create_test_state();
+        let cc = AgentKind::parse("cc", r#"{"command": "claude", "presets": {"opus": "--model opus"}}"#).unwrap();
+        state.set_config(Config { agent_kinds: vec![cc], ..Config::default() });
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "spawn_agent".to_string(),
+            params: serde_json::json!({"kind": "cc", "project": "proj", "preset": "opus"}),
+            ..Default::default()
+        };
+
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert!(data["command"][2].as_str().unwrap().ends_with("exec claude --model opus"));
+        assert_eq!(data["agent"]["preset"], "opus");
+        assert_eq!(data["title"], "proj__cc_3");
+
+        // The registry remembers the preset once the pane is registered
+        let name = serde_json::from_value(data["agent"].clone()).unwrap();
+        state.adopt_pane(2, name);
+        req.action = "list_agents".to_string();
+        req.params = serde_json::json!({});
+        let agents = dispatch_command(&req, &mut state).data.unwrap()["agents"].clone();
+        assert_eq!(agents[1]["preset"], "opus");
+
+        req.action = "spawn_agent".to_string();
+        req.params = serde_json::json!({"kind": "cc", "project": "proj", "preset": "haiku"});
+        assert_eq!(dispatch_command(&req, &mut state).error.unwrap(), "agent kind cc has no preset haiku");
+    }
+
+    #[test]
+    fn test_interrupt_all_pauses_dispatch() {
+        let mut state = create_test_state();
+        state.update_panes(create_manifest_with_panes(vec![
+            create_test_pane(1, "proj__cc_1", false),
+            create_test_pane(2, "proj__cc_2", false),
+            create_test_pane(3, "other__cod_1", false),
+            create_test_pane(4, "shell", false),
+        ]));
+        state.track_send_job(SendJob {
+            id: "j1".to_string(),
+            pane_id: 2,
+            status: JobStatus::Running,
+            chunks_sent: 1,
+            chunks_total: 4,
+        });
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "enqueue_task".to_string(),
+            params: serde_json::json!({"selector": 1, "prompt": "go"}),
+            ..Default::default()
+        };
+        assert!(dispatch_command(&req, &mut state).success);
+
+        req.action = "interrupt_all".to_string();
+        req.params = serde_json::json!({"project": "proj"});
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["pane_ids"], serde_json::json!([1, 2]));
+        assert_eq!(data["job_ids"], serde_json::json!(["j1"]));
+        assert_eq!(data["interrupted"][1]["title"], "proj__cc_2");
+        assert_eq!(state.send_jobs().next().unwrap().status, JobStatus::Aborted);
+
+        req.action = "dispatch_task".to_string();
+        req.params = serde_json::json!({});
+        let err = dispatch_command(&req, &mut state).error.unwrap();
+        assert_eq!(err, "automation paused for proj: interrupt_all");
+
+        req.action = "resume_automation".to_string();
+        req.params = serde_json::json!({"project": "proj"});
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["resumed"], serde_json::json!(["proj"]));
+        req.action = "dispatch_task".to_string();
+        req.params = serde_json::json!({});
+        assert!(dispatch_command(&req, &mut state).success);
+    }
+
+    #[test]
+    fn test_dispatch_held_outside_dispatch_hours() {
+        let mut state = create_test_state();
+        // A window starting an hour from now is closed now
+        let opens = (crate::hours::minute_of_day(clock::now_ms(), 0) + 60) % (24 * 60);
+        let window = format!("{}-{}", crate::hours::format_time(opens), crate::hours::format_time((opens + 60) % (24 * 60)));
+        let mut config = state.config().clone();
+        config.dispatch_hours.insert(Some("proj".to_string()), crate::hours::DispatchHours::parse(&window).unwrap());
+        state.set_config(config);
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "enqueue_task".to_string(),
+            params: serde_json::json!({"selector": 1, "prompt": "go"}),
+            ..Default::default()
+        };
+        assert!(dispatch_command(&req, &mut state).success);
+
+        req.action = "dispatch_task".to_string();
+        req.params = serde_json::json!({});
+        let resp = dispatch_command(&req, &mut state);
+        let reason = format!("outside dispatch hours {}, opens at {}", window, crate::hours::format_time(opens));
+        assert_eq!(resp.error.unwrap(), format!("queue held for proj: {} (task t1 stays pending)", reason));
+        assert_eq!(resp.code, Some(ErrorCode::Held));
+        assert_eq!(state.tasks().pending_count(), 1);
+
+        req.action = "project_status".to_string();
+        req.params = serde_json::json!({"project": "proj"});
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["queue_held"], reason);
+
+        // Sending directly is not held
+        req.action = "send_keys".to_string();
+        req.params = serde_json::json!({"pane_id": 1, "text": "hi"});
+        assert!(dispatch_command(&req, &mut state).success);
+    }
+
+    #[test]
+    fn test_schedules_run_when_due() {
+        let mut state = create_test_state();
+        let mut config = state.config().clone();
+        let json = r#"{"cron": "* * * * *", "action": "enqueue_task", "params": {"selector": 1, "prompt": "update deps"}}"#;
+        config.schedules.push(crate::schedule::Schedule::parse("deps", json).unwrap());
+        state.set_config(config);
+        let now = clock::now_ms();
+
+        // The first check only times the schedule
+        assert!(run_schedules(&mut state, now).is_empty());
+        let responses = run_schedules(&mut state, now + 60_000);
+        assert_eq!(responses.len(), 1);
+        assert!(responses[0].success);
+        assert_eq!(state.tasks().pending_count(), 1);
+
+        state.pause_automation(None, "interrupt_all");
+        let responses = run_schedules(&mut state, now + 120_000);
+        assert_eq!(responses[0].code, Some(ErrorCode::Paused));
+
+        let req = Request {
+            id: "1".to_string(),
+            action: "list_schedules".to_string(),
+            params: serde_json::json!({}),
+            ..Default::default()
+        };
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        let schedule = &data["schedules"][0];
+        assert_eq!((schedule["name"].as_str(), schedule["cron"].as_str()), (Some("deps"), Some("* * * * *")));
+        assert_eq!(schedule["last_run"]["success"], false);
+        assert!(schedule["next_run"].as_str().unwrap() > clock::rfc3339(now + 120_000).as_str());
+    }
+
+    #[test]
+    fn test_focus_pane_validates_pane() {
+        let mut state = create_test_state();
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "focus_pane".to_string(),
+            params: serde_json::json!({"pane_id": 2}),
+            ..Default::default()
+        };
+
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["action"], "focus_pane");
+        assert_eq!(data["pane_id"], 2);
+
+        req.params = serde_json::json!({"pane_id": 99});
+        assert_eq!(dispatch_command(&req, &mut state).error.unwrap(), "pane not found: 99");
+    }
+
+    #[test]
+    fn test_pane_actions_accept_the_focused_selector() {
+        let mut state = create_test_state();
+
+        for (action, params) in [
+            ("send_keys", serde_json::json!({"pane_id": "focused", "text": "hi"})),
+            ("focus_pane", serde_json::json!({"pane_id": "focused"})),
+            ("close_pane", serde_json::json!({"pane_id": "."})),
+            ("send_interrupt", serde_json::json!({"pane_id": "focused"})),
+            ("resize_pane", serde_json::json!({"pane_id": "focused", "amount": 1})),
+            ("send_raw", serde_json::json!({"pane_id": "focused", "bytes": "aGk="})),
+            ("toggle_floating", serde_json::json!({"pane_id": "focused"})),
+            ("toggle_fullscreen", serde_json::json!({"pane_id": "."})),
+            ("scroll_pane", serde_json::json!({"pane_id": "focused", "direction": "up"})),
+        ] {
+            let req = Request {
+                id: "1".to_string(),
+                action: action.to_string(),
+                params,
+                ..Default::default()
+            };
+            let data = dispatch_command(&req, &mut state).data.unwrap();
+            assert_eq!(data["pane_id"], 1, "{}", action);
+        }
+    }
+
+    #[test]
+    fn test_close_pane_refuses_plugin_panes() {
+        let mut state = create_test_state();
+        state.update_panes(create_manifest_with_panes(vec![
+            create_test_pane(1, "proj__cc_1", false),
+            create_test_pane(5, "nzm-agent", true),
+        ]));
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "close_pane".to_string(),
+            params: serde_json::json!({"pane_id": 1}),
+            ..Default::default()
+        };
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["action"], "close_pane");
+
+        req.params = serde_json::json!({"pane_id": 5});
+        assert_eq!(dispatch_command(&req, &mut state).error.unwrap(), "cannot close plugin pane: 5");
+
+        req.params = serde_json::json!({"pane_id": 9});
+        assert_eq!(dispatch_command(&req, &mut state).error.unwrap(), "pane not found: 9");
+    }
+
+    #[test]
+    fn test_resolve_selector_lists_matches() {
+        let mut state = create_test_state();
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "resolve_selector".to_string(),
+            params: serde_json::json!({"selector": "proj__*"}),
+            ..Default::default()
+        };
+
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["selector"], "proj__*");
+        assert_eq!(data["count"], 2);
+        assert_eq!(data["panes"][1]["title"], "proj__cc_2");
+        assert!(data.get("action").is_none());
+
+        req.params = serde_json::json!({"selector": "nothing"});
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["selector"], "title:nothing");
+        assert_eq!(data["count"], 0);
+    }
+
+    #[test]
+    fn test_handles_in_responses_and_selectors() {
+        let mut state = create_test_state();
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "list_panes".to_string(),
+            params: serde_json::json!({}),
+            ..Default::default()
+        };
+        let panes: Vec<PaneDto> = serde_json::from_value(dispatch_command(&req, &mut state).data.unwrap()["panes"].clone()).unwrap();
+        let handle = panes[1].handle.clone().unwrap();
+
+        req.action = "resolve_selector".to_string();
+        req.params = serde_json::json!({"selector": handle});
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["panes"][0]["id"], 2);
+        assert_eq!(data["selector"], format!("handle:{}", handle));
+
+        // The pane closes and Zellij reuses its id
+        state.update_panes(create_manifest_with_panes(vec![create_test_pane(1, "proj__cc_1", false)]));
+        state.update_panes(create_manifest_with_panes(vec![
+            create_test_pane(1, "proj__cc_1", false),
+            create_test_pane(2, "shell", false),
+        ]));
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["count"], 0);
+    }
+
+    #[test]
+    fn test_dispatch_fails_task_for_replaced_pane() {
+        let mut state = create_test_state();
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "enqueue_task".to_string(),
+            params: serde_json::json!({"selector": 2, "prompt": "run tests"}),
+            ..Default::default()
+        };
+        let task = dispatch_command(&req, &mut state).data.unwrap()["task"].clone();
+        assert_eq!(task["handle"], state.pane_handle(2).unwrap());
+
+        // Pane 2 closes and a shell inherits its id
+        state.update_panes(create_manifest_with_panes(vec![create_test_pane(1, "proj__cc_1", false)]));
+        state.update_panes(create_manifest_with_panes(vec![
+            create_test_pane(1, "proj__cc_1", false),
+            create_test_pane(2, "shell", false),
+        ]));
+        req.action = "dispatch_task".to_string();
+        req.params = serde_json::json!({});
+        let resp = dispatch_command(&req, &mut state);
+        let err = resp.error.unwrap();
+        assert!(err.starts_with("stale target: pane 2 was replaced"), "{}", err);
+        assert_eq!(state.tasks().get("t1").unwrap().status, TaskStatus::Failed);
+        assert_eq!(dispatch_command(&req, &mut state).error.unwrap(), "no pending task");
+    }
+
+    #[test]
+    fn test_dispatch_reroutes_stale_task_by_selector() {
+        let mut state = create_test_state();
+        state.set_config(Config { stale_task_policy: StaleTaskPolicy::Reroute, ..Config::default() });
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "enqueue_task".to_string(),
+            params: serde_json::json!({"selector": "title:proj__cc_2", "prompt": "a"}),
+            ..Default::default()
+        };
+        dispatch_command(&req, &mut state);
+        req.params = serde_json::json!({"selector": 1, "prompt": "b"});
+        dispatch_command(&req, &mut state);
+
+        // The agent was restarted in a new pane; pane 1 closed
+        state.update_panes(create_manifest_with_panes(vec![create_test_pane(5, "proj__cc_2", false)]));
+        req.action = "dispatch_task".to_string();
+        req.params = serde_json::json!({});
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["pane_id"].as_u64(), data["task_id"].as_str()), (Some(5), Some("t1")));
+        let handle = state.pane_handle(5).map(String::from);
+        assert_eq!(state.tasks().get("t1").unwrap().handle, handle);
+
+        // An id selector names the closed pane itself
+        let err = dispatch_command(&req, &mut state).error.unwrap();
+        assert!(err.starts_with("stale target: pane 1 was closed"), "{}", err);
+    }
+
+    #[test]
+    fn test_resize_pane_relative() {
+        let mut state = create_test_state();
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "resize_pane".to_string(),
+            params: serde_json::json!({"pane_id": 1, "direction": "right", "amount": 3}),
+            ..Default::default()
+        };
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["direction"].as_str(), data["steps"].as_i64()), (Some("right"), Some(3)));
+
+        req.params = serde_json::json!({"pane_id": 1, "percent": -12});
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["steps"], -2);
+        req.params = serde_json::json!({"pane_id": 1, "percent": 1});
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["steps"], 1);
+        req.params = serde_json::json!({"pane_id": 1, "amount": 1000000});
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["steps"], 20);
+
+        for params in [
+            serde_json::json!({"pane_id": 1, "amount": 1, "percent": 5}),
+            serde_json::json!({"pane_id": 1}),
+            serde_json::json!({"pane_id": 1, "amount": 0}),
+            serde_json::json!({"pane_id": 1, "width": 80}),
+        ] {
+            req.params = params;
+            assert!(dispatch_command(&req, &mut state).error.unwrap().starts_with("invalid params"));
+        }
+        req.params = serde_json::json!({"pane_id": 9, "amount": 1});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().starts_with("pane not found"));
+    }
+
+    #[test]
+    fn test_resize_floating_pane_absolute() {
+        let mut state = create_test_state();
+        let mut floating = create_test_pane(3, "scratch", false);
+        floating.is_floating = true;
+        state.update_panes(create_manifest_with_panes(vec![floating]));
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "resize_pane".to_string(),
+            params: serde_json::json!({"pane_id": 3, "width": "50%", "height": 20}),
+            ..Default::default()
+        };
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["width"].as_str(), data["height"].as_str()), (Some("50%"), Some("20")));
+        assert!(data.get("steps").is_none());
+
+        req.params = serde_json::json!({"pane_id": 3, "width": "150%"});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().contains("bad width"));
+    }
+
+    #[test]
+    fn test_pin_floating_pane() {
+        let mut state = create_test_state();
+        let mut floating = create_test_pane(3, "monitor", false);
+        floating.is_floating = true;
+        state.update_panes(create_manifest_with_panes(vec![create_test_pane(1, "proj__cc_1", false), floating]));
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "pin_pane".to_string(),
+            params: serde_json::json!({"pane_id": 3}),
+            ..Default::default()
+        };
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data, serde_json::json!({"action": "pin_pane", "pane_id": 3, "pinned": true}));
+
+        req.params = serde_json::json!({"pane_id": 3, "pinned": false});
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["pinned"], false);
+        req.params = serde_json::json!({"pane_id": 1});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().contains("only floating panes"));
+        req.params = serde_json::json!({"pane_id": "title:monitor"});
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["pane_id"], 3);
+    }
+
+    #[test]
+    fn test_stack_and_unstack_panes() {
+        let mut state = create_test_state();
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "stack_panes".to_string(),
+            params: serde_json::json!({"pane_ids": [1, 2, 1], "expand": 2}),
+            ..Default::default()
+        };
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data, serde_json::json!({"action": "stack_panes", "pane_ids": [1, 2], "expand": 2}));
+
+        req.params = serde_json::json!({"pane_ids": [1, 1]});
+        assert_eq!(dispatch_command(&req, &mut state).error.unwrap(), "invalid params: a stack needs at least 2 panes");
+        req.params = serde_json::json!({"pane_ids": [1, 2], "expand": 3});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().contains("not in pane_ids"));
+        req.params = serde_json::json!({"pane_ids": [1, 9]});
+        assert_eq!(dispatch_command(&req, &mut state).error.unwrap(), "pane not found: 9");
+
+        req.action = "unstack_panes".to_string();
+        req.params = serde_json::json!({"pane_ids": [2]});
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["pane_ids"], serde_json::json!([2]));
+        req.params = serde_json::json!({"pane_ids": []});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().starts_with("invalid params"));
+    }
+
+    #[test]
+    fn test_swap_panes_along_a_row() {
+        let mut state = create_test_state();
+        // 1 2 3 in a row, 4 below 1 but narrower
+        let panes = [(1, 0, 0, 20, 10), (2, 20, 0, 20, 10), (3, 40, 0, 20, 10), (4, 0, 10, 10, 10)]
+            .map(|(id, x, y, columns, rows)| PaneInfo {
+                pane_x: x,
+                pane_y: y,
+                pane_columns: columns,
+                pane_rows: rows,
+                ..create_test_pane(id, "p", false)
+            });
+        state.update_panes(create_manifest_with_panes(panes.to_vec()));
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "swap_panes".to_string(),
+            params: serde_json::json!({"pane_id": 3, "other_pane_id": 1}),
+            ..Default::default()
+        };
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["direction"].as_str(), data["steps"].as_u64()), (Some("left"), Some(2)));
+
+        req.params = serde_json::json!({"pane_id": 1, "other_pane_id": 4});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().contains("not in one row or column"));
+        req.params = serde_json::json!({"pane_id": 2, "other_pane_id": 2});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().contains("itself"));
+
+        // Pane 1 is focused
+        req.params = serde_json::json!({"pane_id": "focused", "other_pane_id": 2});
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["pane_id"].as_u64(), data["direction"].as_str()), (Some(1), Some("right")));
+    }
+
+    #[test]
+    fn test_project_status() {
+        let mut state = create_test_state();
+        state.update_panes(create_manifest_with_panes(vec![
+            create_test_pane(1, "proj__cc_1", false),
+            create_test_pane(2, "proj__cc_2", false),
+            create_test_pane(3, "other__cc_1", false),
+        ]));
+        let cc = AgentKind::parse("cc", r#"{"command": "claude", "status": [{"state": "idle", "regex": "^> $"}]}"#).unwrap();
+        state.set_config(Config { agent_kinds: vec![cc], ..Config::default() });
+        state.update_pane_contents(1, vec!["> ".to_string()]);
+        state.cache_git_info("/data/projects/proj", GitInfo { branch: Some("main".to_string()), ..GitInfo::default() });
+        state.emit_event("agent_ready", serde_json::json!({"pane_id": 2}));
+        state.emit_event("agent_ready", serde_json::json!({"pane_id": 3}));
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "enqueue_task".to_string(),
+            params: serde_json::json!({"selector": 2, "prompt": "a"}),
+            ..Default::default()
+        };
+        assert!(dispatch_command(&req, &mut state).success);
+
+        req.action = "project_status".to_string();
+        req.params = serde_json::json!({"project": "proj"});
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        let agents = data["agents"].as_array().unwrap();
+        assert_eq!(agents.len(), 2);
+        assert_eq!((agents[0]["status"].as_str(), agents[1]["status"].as_str()), (Some("idle"), None));
+        assert_eq!(data["tasks"], serde_json::json!({"pending": 1, "dispatched": 0}));
+        assert_eq!(data["git"]["branch"], "main");
+        assert_eq!(data["events"].as_array().unwrap().len(), 1);
+        assert_eq!(data["events"][0]["event"]["pane_id"], 2);
+
+        req.params = serde_json::json!({"project": "none"});
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert!(data["agents"].as_array().unwrap().is_empty() && data["git"].is_null());
+    }
+
+    #[test]
+    fn test_full_task_queue_rejects_enqueue() {
+        let mut state = create_test_state();
+        state.set_config(Config { task_queue_capacity: 1, ..Config::default() });
+        let req = Request {
+            id: "1".to_string(),
+            action: "enqueue_task".to_string(),
+            params: serde_json::json!({"selector": 1, "prompt": "a"}),
+            ..Default::default()
+        };
+        assert!(dispatch_command(&req, &mut state).success);
+
+        let resp = dispatch_command(&req, &mut state);
+        assert_eq!(resp.error.as_deref(), Some("queue full: tasks holds 1 of 1"));
+        assert_eq!(resp.data.unwrap(), serde_json::json!({"queue": "tasks", "depth": 1, "capacity": 1}));
+        let events = state.take_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!((events[0].0.as_str(), &events[0].1["depth"]), ("backpressure", &serde_json::json!(1)));
+        assert_eq!(resp.code, Some(ErrorCode::QueueFull));
+    }
+
+    #[test]
+    fn test_full_send_queue_rejects_paced_sends() {
+        let mut state = create_test_state();
+        state.set_config(Config { send_queue_capacity: 1, ..Config::default() });
+        state.track_send_job(SendJob {
+            id: "j1".to_string(),
+            pane_id: 1,
+            status: JobStatus::Running,
+            chunks_sent: 0,
+            chunks_total: 3,
+        });
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "send_keys".to_string(),
+            params: serde_json::json!({"pane_id": 2, "text": "long", "chunk_bytes": 2}),
+            ..Default::default()
+        };
+        assert!(dispatch_command(&req, &mut state).error.unwrap().starts_with("queue full: sends"));
+
+        // Unpaced writes do not queue behind ticks
+        req.params = serde_json::json!({"pane_id": 2, "text": "short"});
+        assert!(dispatch_command(&req, &mut state).success);
+    }
+
+    #[test]
+    fn test_toggle_floating() {
+        let mut state = create_test_state();
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "toggle_floating".to_string(),
+            params: serde_json::json!({"pane_id": 1}),
+            ..Default::default()
+        };
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["action"].as_str(), data["is_floating"].as_bool()), (Some("toggle_floating"), Some(true)));
+
+        // Already tiled: nothing to do
+        req.params = serde_json::json!({"pane_id": 1, "floating": false});
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert!(data.get("action").is_none());
+        assert_eq!(data["changed"], false);
+
+        req.params = serde_json::json!({"pane_id": 9});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().starts_with("pane not found"));
+    }
+
+    #[test]
+    fn test_stats_reports_memory() {
+        let mut state = create_test_state();
+        state.update_pane_contents(1, vec!["hello".to_string(); 4]);
+        state.push_capture(1, "captured".to_string());
+        let req = Request {
+            id: "1".to_string(),
+            action: "stats".to_string(),
+            params: serde_json::json!({}),
+            ..Default::default()
+        };
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+
+        let memory = &data["memory"];
+        assert!(memory["output"].as_u64().unwrap() >= 20);
+        assert!(memory["captures"].as_u64().unwrap() >= 8);
+        assert!(memory["history"].as_u64().unwrap() > 0);
+        let parts = ["output", "captures", "history", "artifacts", "tasks", "events"];
+        assert_eq!(memory["total"].as_u64(), Some(parts.iter().map(|k| memory[*k].as_u64().unwrap()).sum()));
+        assert_eq!(data["budget"]["output"], crate::memory::DEFAULT_OUTPUT_BUDGET);
     }
-}
 
-/// Handle list_panes action
-fn handle_list_panes(req: &Request, state: &State) -> Response {
-    let panes: Vec<PaneDto> = state.panes().iter().map(|p| PaneDto {
-        id: p.id,
-        title: p.title.clone(),
-        is_focused: p.is_focused,
-        is_floating: p.is_floating,
-    }).collect();
+    #[test]
+    fn test_toggle_fullscreen_and_restore() {
+        let mut state = create_test_state();
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "toggle_fullscreen".to_string(),
+            params: serde_json::json!({"pane_id": 2, "fullscreen": true}),
+            ..Default::default()
+        };
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["action"].as_str(), data["is_fullscreen"].as_bool()), (Some("toggle_fullscreen"), Some(true)));
 
-    Response {
-        id: req.id.clone(),
-        success: true,
-        data: Some(serde_json::json!({ "panes": panes })),
-        error: None,
+        let mut maximized = create_test_pane(2, "proj__cc_2", false);
+        maximized.is_fullscreen = true;
+        state.update_panes(create_manifest_with_panes(vec![create_test_pane(1, "proj__cc_1", false), maximized]));
+        req.params = serde_json::json!({"pane_id": 2, "fullscreen": true});
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["changed"], false);
+
+        req.params = serde_json::json!({"pane_id": 2});
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["action"].as_str(), data["is_fullscreen"].as_bool()), (Some("toggle_fullscreen"), Some(false)));
     }
-}
 
-/// Handle get_pane_info action
-fn handle_get_pane_info(req: &Request, state: &State) -> Response {
-    let params: Result<PaneIdParam, _> = serde_json::from_value(req.params.clone());
-
-    match params {
-        Ok(p) => {
-            match state.get_pane(p.pane_id) {
-                Some(pane) => Response {
-                    id: req.id.clone(),
-                    success: true,
-                    data: Some(serde_json::json!({
-                        "pane": PaneDto {
-                            id: pane.id,
-                            title: pane.title.clone(),
-                            is_focused: pane.is_focused,
-                            is_floating: pane.is_floating,
-                        }
-                    })),
-                    error: None,
-                },
-                None => Response {
-                    id: req.id.clone(),
-                    success: false,
-                    data: None,
-                    error: Some(format!("pane not found: {}", p.pane_id)),
-                },
-            }
-        }
-        Err(e) => Response {
-            id: req.id.clone(),
-            success: false,
-            data: None,
-            error: Some(format!("invalid params: {}", e)),
-        },
+    #[test]
+    fn test_dispatch_waits_for_spawned_agent_to_launch() {
+        let mut state = create_test_state();
+        let cc = AgentKind::parse("cc", r#"{"command": "claude", "ready": "^Welcome"}"#).unwrap();
+        state.set_config(Config { agent_kinds: vec![cc], ..Config::default() });
+        let name = AgentName { project: Some("proj".to_string()), kind: "cc".to_string(), index: Some(2), preset: None };
+        state.adopt_pane(2, name);
+        state.mark_starting(2);
+        assert!(state.take_events().is_empty());
+
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "enqueue_task".to_string(),
+            params: serde_json::json!({"selector": 2, "prompt": "run tests"}),
+            ..Default::default()
+        };
+        dispatch_command(&req, &mut state);
+        req.action = "dispatch_task".to_string();
+        req.params = serde_json::json!({});
+        let resp = dispatch_command(&req, &mut state);
+        assert!(resp.error.unwrap().starts_with("agent starting: pane 2"));
+        assert_eq!(state.tasks().get("t1").unwrap().status, TaskStatus::Pending);
+        req.action = "list_agents".to_string();
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["agents"][1]["ready"], false);
+
+        state.update_pane_contents(2, vec!["Loading".to_string(), "Welcome back".to_string()]);
+        let events = state.take_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!((events[0].0.as_str(), &events[0].1["pane_id"]), ("agent_ready", &serde_json::json!(2)));
+        req.action = "dispatch_task".to_string();
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["pane_id"], 2);
     }
-}
 
-/// Validate send_keys params (actual sending happens in lib.rs with Zellij API)
-fn handle_send_keys_validate(req: &Request, state: &State) -> Response {
-    let params: Result<SendKeysParams, _> = serde_json::from_value(req.params.clone());
+    #[test]
+    fn test_agent_is_made_ready_when_its_pattern_never_matches() {
+        let mut state = create_test_state();
+        let cc = AgentKind::parse("cc", r#"{"command": "claude", "ready": "^Welcome", "ready_timeout_secs": 30}"#).unwrap();
+        state.set_config(Config { agent_kinds: vec![cc], ..Config::default() });
+        let name = AgentName { project: Some("proj".to_string()), kind: "cc".to_string(), index: Some(2), preset: None };
+        state.adopt_pane(2, name);
+        assert_eq!(state.mark_starting(2), Some(30));
 
-    match params {
-        Ok(p) => {
-            // Verify pane exists
-            if state.get_pane(p.pane_id).is_none() {
-                return Response {
-                    id: req.id.clone(),
-                    success: false,
-                    data: None,
-                    error: Some(format!("pane not found: {}", p.pane_id)),
-                };
-            }
+        state.expire_starting(clock::now_ms());
+        assert!(state.agent_starting(2));
+        assert!(state.take_events().is_empty());
 
-            // Return success with params for lib.rs to execute
-            Response {
-                id: req.id.clone(),
-                success: true,
-                data: Some(serde_json::json!({
-                    "action": "send_keys",
-                    "pane_id": p.pane_id,
-                    "text": p.text,
-                    "enter": p.enter,
-                })),
-                error: None,
-            }
-        }
-        Err(e) => Response {
-            id: req.id.clone(),
-            success: false,
-            data: None,
-            error: Some(format!("invalid params: {}", e)),
-        },
+        state.expire_starting(clock::now_ms() + 30_000);
+        assert!(!state.agent_starting(2));
+        let events = state.take_events();
+        assert_eq!(events.len(), 1);
+        assert_eq!((events[0].0.as_str(), &events[0].1["timed_out"]), ("agent_ready", &serde_json::json!(true)));
     }
-}
 
-/// Validate send_interrupt params
-fn handle_send_interrupt_validate(req: &Request, state: &State) -> Response {
-    let params: Result<PaneIdParam, _> = serde_json::from_value(req.params.clone());
-
-    match params {
-        Ok(p) => {
-            if state.get_pane(p.pane_id).is_none() {
-                return Response {
-                    id: req.id.clone(),
-                    success: false,
-                    data: None,
-                    error: Some(format!("pane not found: {}", p.pane_id)),
-                };
-            }
+    #[test]
+    fn test_initial_messages_sent_when_agent_is_ready() {
+        let mut state = create_test_state();
+        let cc = AgentKind::parse(
+            "cc",
+            r#"{"command": "claude", "ready": "^Welcome", "initial": ["Read CONVENTIONS.md", "Use worktree {project}-{index}"]}"#,
+        )
+        .unwrap();
+        state.set_config(Config { agent_kinds: vec![cc], ..Config::default() });
+        let name = |index| AgentName { project: Some("proj".to_string()), kind: "cc".to_string(), index: Some(index), preset: None };
+        state.adopt_pane(1, name(1));
+        state.adopt_pane(2, name(2));
+        state.mark_starting(1);
+        state.mark_starting(2);
+        assert!(state.take_effects().is_empty());
 
-            Response {
-                id: req.id.clone(),
-                success: true,
-                data: Some(serde_json::json!({
-                    "action": "send_interrupt",
-                    "pane_id": p.pane_id,
-                })),
-                error: None,
-            }
-        }
-        Err(e) => Response {
-            id: req.id.clone(),
-            success: false,
-            data: None,
-            error: Some(format!("invalid params: {}", e)),
-        },
+        state.update_pane_contents(2, vec!["Welcome".to_string()]);
+        let effects = state.take_effects();
+        assert_eq!(effects.len(), 2);
+        assert_eq!((effects[1]["text"].as_str(), effects[1]["pane_id"].as_u64()), (Some("Use worktree proj-2"), Some(2)));
+        let events = state.take_events();
+        assert_eq!(events[0].1["initial_tasks"], serde_json::json!(["t1", "t2"]));
+        assert_eq!(state.tasks().get("t1").unwrap().status, TaskStatus::Dispatched);
+
+        // Paused automation leaves them queued for dispatch_task
+        state.pause_automation(Some("proj".to_string()), "test");
+        state.update_pane_contents(1, vec!["Welcome".to_string()]);
+        assert!(state.take_effects().is_empty());
+        assert_eq!(state.tasks().get("t3").unwrap().status, TaskStatus::Pending);
     }
-}
 
-/// Validate send_keys parameters
-#[allow(dead_code)]
-pub fn validate_send_keys_params(_params: &SendKeysParams) -> Result<(), String> {
-    // pane_id validation happens when we look it up
-    // text can be empty (might just press enter)
-    Ok(())
-}
+    #[test]
+    fn test_list_tabs() {
+        let mut state = create_test_state();
+        let mut manifest = create_manifest_with_panes(vec![create_test_pane(1, "a", false), create_test_pane(2, "b", false)]);
+        manifest.panes.insert(1, vec![create_test_pane(3, "c", false)]);
+        state.update_panes(manifest);
+        let tab = |position, name: &str, active| TabInfo { position, name: name.to_string(), active, ..Default::default() };
+        state.update_tabs(&[tab(1, "review", true), tab(0, "main", false), tab(2, "empty", false)]);
+        let req = Request {
+            id: "1".to_string(),
+            action: "list_tabs".to_string(),
+            params: serde_json::json!({}),
+            ..Default::default()
+        };
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use zellij_tile::prelude::{PaneInfo, PaneManifest};
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["tabs"], serde_json::json!([
+            {"index": 0, "name": "main", "active": false, "pane_count": 2},
+            {"index": 1, "name": "review", "active": true, "pane_count": 1},
+            {"index": 2, "name": "empty", "active": false, "pane_count": 0},
+        ]));
+    }
 
-    fn create_test_pane(id: u32, title: &str, is_plugin: bool) -> PaneInfo {
-        PaneInfo {
-            id,
-            is_plugin,
-            title: title.to_string(),
-            is_focused: id == 1, // First pane is focused
-            is_fullscreen: false,
-            is_floating: false,
-            is_suppressed: false,
+    #[test]
+    fn test_new_tab_refuses_taken_names() {
+        let mut state = create_test_state();
+        state.update_tabs(&[TabInfo { position: 0, name: "main".to_string(), ..Default::default() }]);
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "new_tab".to_string(),
+            params: serde_json::json!({"name": "proj", "layout": "compact"}),
             ..Default::default()
-        }
+        };
+
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data, serde_json::json!({"action": "new_tab", "name": "proj", "layout": "compact", "tab_index": 1}));
+
+        req.params = serde_json::json!({"name": "main"});
+        assert_eq!(dispatch_command(&req, &mut state).error.unwrap(), "tab already exists: main");
+        req.params = serde_json::json!({"layout": ""});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().starts_with("invalid params"));
     }
 
-    fn create_manifest_with_panes(panes: Vec<PaneInfo>) -> PaneManifest {
-        let mut manifest = PaneManifest::default();
-        manifest.panes.insert(0, panes);
-        manifest
+    #[test]
+    fn test_go_to_tab_by_index_and_name() {
+        let mut state = create_test_state();
+        let tab = |position, name: &str, active| TabInfo { position, name: name.to_string(), active, ..Default::default() };
+        state.update_tabs(&[tab(0, "main", true), tab(1, "review", false)]);
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "go_to_tab".to_string(),
+            params: serde_json::json!({"tab_name": "review"}),
+            ..Default::default()
+        };
+
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["action"].as_str(), data["tab_index"].as_u64()), (Some("go_to_tab"), Some(1)));
+        req.params = serde_json::json!({"tab_index": 0});
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["changed"].as_bool(), data["tab_name"].as_str()), (Some(false), Some("main")));
+        assert!(data.get("action").is_none());
+
+        req.params = serde_json::json!({"tab_index": 5});
+        assert_eq!(dispatch_command(&req, &mut state).error.unwrap(), "tab not found: index 5");
+        req.params = serde_json::json!({});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().starts_with("invalid params"));
     }
 
-    fn create_test_state() -> State {
-        let mut state = State::default();
-        state.update_panes(create_manifest_with_panes(vec![
-            create_test_pane(1, "proj__cc_1", false),
-            create_test_pane(2, "proj__cc_2", false),
-        ]));
-        state
+    #[test]
+    fn test_rename_tab() {
+        let mut state = create_test_state();
+        let tab = |position, name: &str| TabInfo { position, name: name.to_string(), ..Default::default() };
+        state.update_tabs(&[tab(0, "main"), tab(1, "Tab #2")]);
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "rename_tab".to_string(),
+            params: serde_json::json!({"tab_index": 1, "name": "myproject-agents"}),
+            ..Default::default()
+        };
+
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data, serde_json::json!({
+            "action": "rename_tab",
+            "tab_index": 1,
+            "old_name": "Tab #2",
+            "name": "myproject-agents",
+            "changed": true,
+        }));
+
+        req.params = serde_json::json!({"tab_name": "Tab #2", "name": "main"});
+        assert_eq!(dispatch_command(&req, &mut state).error.unwrap(), "tab already exists: main");
+        req.params = serde_json::json!({"tab_name": "main", "name": "main"});
+        assert!(dispatch_command(&req, &mut state).data.unwrap().get("action").is_none());
     }
 
     #[test]
-    fn test_handle_list_panes_returns_pane_array() {
-        let state = create_test_state();
-        let req = Request {
-            id: "123".to_string(),
-            action: "list_panes".to_string(),
-            params: serde_json::Value::Null,
+    fn test_close_tab_refuses_unfinished_tasks() {
+        let mut state = create_test_state();
+        let mut manifest = create_manifest_with_panes(vec![create_test_pane(1, "proj__cc_1", false)]);
+        manifest.panes.insert(1, vec![create_test_pane(2, "proj__cc_2", false)]);
+        state.update_panes(manifest);
+        let tab = |position, name: &str| TabInfo { position, name: name.to_string(), ..Default::default() };
+        state.update_tabs(&[tab(0, "main"), tab(1, "review")]);
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "enqueue_task".to_string(),
+            params: serde_json::json!({"selector": 2, "prompt": "review"}),
+            ..Default::default()
         };
+        assert!(dispatch_command(&req, &mut state).success);
 
-        let result = dispatch_command(&req, &state);
+        req.action = "close_tab".to_string();
+        req.params = serde_json::json!({"tab_name": "review"});
+        let err = dispatch_command(&req, &mut state).error.unwrap();
+        assert_eq!(err, "unfinished tasks in tab 1: t1 (pass force to close it anyway)");
 
-        assert!(result.success);
-        assert_eq!(result.id, "123");
-        let data = result.data.unwrap();
-        let panes: Vec<PaneDto> = serde_json::from_value(data["panes"].clone()).unwrap();
-        assert_eq!(panes.len(), 2);
-        assert_eq!(panes[0].id, 1);
-        assert_eq!(panes[0].title, "proj__cc_1");
-        assert!(panes[0].is_focused);
+        req.params = serde_json::json!({"tab_name": "review", "force": true});
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["action"].as_str(), data["tab_index"].as_u64()), (Some("close_tab"), Some(1)));
+        assert_eq!((data["pane_ids"].clone(), data["unfinished_tasks"].clone()), (serde_json::json!([2]), serde_json::json!(["t1"])));
+
+        req.params = serde_json::json!({"tab_index": 0});
+        assert!(dispatch_command(&req, &mut state).success);
     }
 
     #[test]
-    fn test_handle_list_panes_empty_state() {
-        let state = State::default();
-        let req = Request {
+    fn test_move_pane_to_tab_by_index_and_name() {
+        let mut state = create_test_state();
+        let tab = |position, name: &str| TabInfo { position, name: name.to_string(), ..Default::default() };
+        state.update_tabs(&[tab(1, "review"), tab(0, "main")]);
+        assert_eq!(state.tabs(), &[(0, "main".to_string()), (1, "review".to_string())]);
+
+        let mut req = Request {
             id: "1".to_string(),
-            action: "list_panes".to_string(),
-            params: serde_json::Value::Null,
+            action: "move_pane_to_tab".to_string(),
+            params: serde_json::json!({"pane_id": 2, "tab_name": "review"}),
+            ..Default::default()
         };
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["action"].as_str(), data["tab_index"].as_u64()), (Some("move_pane_to_tab"), Some(1)));
 
-        let result = dispatch_command(&req, &state);
+        req.params = serde_json::json!({"pane_id": 2, "tab_index": 0});
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["changed"].as_bool(), data["tab_name"].as_str()), (Some(false), Some("main")));
+        assert!(data.get("action").is_none());
 
-        assert!(result.success);
-        let data = result.data.unwrap();
-        let panes: Vec<PaneDto> = serde_json::from_value(data["panes"].clone()).unwrap();
-        assert!(panes.is_empty());
+        req.params = serde_json::json!({"pane_id": 2, "tab_name": "scratch"});
+        assert_eq!(dispatch_command(&req, &mut state).error.unwrap(), "tab not found: scratch");
+        req.params = serde_json::json!({"pane_id": 2, "tab_name": "scratch", "create": true});
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["created"].as_bool(), data["tab_index"].is_null()), (Some(true), true));
+        req.params = serde_json::json!({"pane_id": 2, "tab_index": 0, "tab_name": "main"});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().starts_with("invalid params"));
+        req.params = serde_json::json!({"pane_id": "focused", "tab_index": 1});
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["pane_id"], 1);
     }
 
     #[test]
-    fn test_handle_get_pane_info_found() {
-        let state = create_test_state();
-        let req = Request {
+    fn test_scroll_pane_by_count_and_position() {
+        let mut state = create_test_state();
+        let mut req = Request {
             id: "1".to_string(),
-            action: "get_pane_info".to_string(),
-            params: serde_json::json!({"pane_id": 1}),
+            action: "scroll_pane".to_string(),
+            params: serde_json::json!({"pane_id": 1, "direction": "up", "pages": 2}),
+            ..Default::default()
         };
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["from"].is_null(), data["lines"].as_i64(), data["pages"].as_i64()), (true, Some(0), Some(-2)));
 
-        let result = dispatch_command(&req, &state);
+        req.params = serde_json::json!({"pane_id": 1, "direction": "down"});
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["lines"], 1);
 
-        assert!(result.success);
-        let data = result.data.unwrap();
-        let pane: PaneDto = serde_json::from_value(data["pane"].clone()).unwrap();
-        assert_eq!(pane.id, 1);
-        assert_eq!(pane.title, "proj__cc_1");
+        req.params = serde_json::json!({"pane_id": 1, "to": "bottom"});
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["from"], "bottom");
+        req.params = serde_json::json!({"pane_id": 1, "to": 40});
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["from"].as_str(), data["lines"].as_i64()), (Some("top"), Some(40)));
+
+        req.params = serde_json::json!({"pane_id": 1, "to": "top", "lines": 3});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().starts_with("invalid params"));
+        req.params = serde_json::json!({"pane_id": 1, "direction": "down", "lines": 1_000_000});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().starts_with("invalid params"));
     }
 
     #[test]
-    fn test_handle_get_pane_info_not_found() {
-        let state = create_test_state();
-        let req = Request {
+    fn test_turns_track_prompts_to_agents() {
+        let mut state = create_test_state();
+        let cc = AgentKind::parse("cc", r#"{"command": "claude", "status": [{"state": "idle", "regex": "^> $"}]}"#).unwrap();
+        state.set_config(Config { agent_kinds: vec![cc], ..Config::default() });
+        state.update_pane_contents(2, vec!["> ".to_string()]);
+        let id = state.begin_turn(2, "run tests", Some("t1".to_string())).unwrap();
+        state.update_pane_contents(2, vec!["> run tests".to_string(), "3 passed".to_string(), "> ".to_string()]);
+        state.begin_turn(1, "lint", None);
+
+        let mut req = Request {
             id: "1".to_string(),
-            action: "get_pane_info".to_string(),
-            params: serde_json::json!({"pane_id": 999}),
+            action: "list_turns".to_string(),
+            params: serde_json::json!({"selector": 2}),
+            ..Default::default()
         };
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        let turns = data["turns"].as_array().unwrap();
+        assert_eq!(turns.len(), 1);
+        assert_eq!((turns[0]["status"].as_str(), turns[0]["response_lines"].as_u64()), (Some("complete"), Some(3)));
+        assert!(turns[0].get("response").is_none());
+        req.params = serde_json::json!({});
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["turns"].as_array().unwrap().len(), 2);
 
-        let result = dispatch_command(&req, &state);
+        // The pane closes; its handle still finds the turn
+        let handle = state.pane_handle(2).unwrap().to_string();
+        state.update_panes(create_manifest_with_panes(vec![create_test_pane(1, "proj__cc_1", false)]));
+        req.params = serde_json::json!({"selector": format!("handle:{}", handle)});
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["turns"][0]["id"], id.as_str());
 
-        assert!(!result.success);
-        assert!(result.error.unwrap().contains("pane not found"));
+        req.action = "get_turn".to_string();
+        req.params = serde_json::json!({"id": id});
+        let turn = dispatch_command(&req, &mut state).data.unwrap()["turn"].clone();
+        assert_eq!((turn["prompt"].as_str(), turn["task_id"].as_str()), (Some("run tests"), Some("t1")));
+        assert_eq!(turn["response"][1], "3 passed");
+        req.params = serde_json::json!({"id": "u9"});
+        assert_eq!(dispatch_command(&req, &mut state).error.unwrap(), "turn not found: u9");
     }
 
     #[test]
-    fn test_handle_send_keys_valid() {
-        let state = create_test_state();
-        let req = Request {
+    fn test_export_conversation_formats() {
+        let mut state = create_test_state();
+        let cc = AgentKind::parse("cc", r#"{"command": "claude", "status": [{"state": "idle", "regex": "^> $"}]}"#).unwrap();
+        state.set_config(Config { agent_kinds: vec![cc], ..Config::default() });
+        state.update_pane_contents(2, vec!["> ".to_string()]);
+        state.begin_turn(2, "run tests", None);
+        state.update_pane_contents(2, vec!["> run tests".to_string(), "3 passed".to_string(), "> ".to_string()]);
+
+        let mut req = Request {
             id: "1".to_string(),
-            action: "send_keys".to_string(),
-            params: serde_json::json!({
-                "pane_id": 1,
-                "text": "hello",
-                "enter": true
-            }),
+            action: "export_conversation".to_string(),
+            params: serde_json::json!({"selector": 2, "format": "messages"}),
+            ..Default::default()
         };
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["messages"], serde_json::json!([
+            {"role": "user", "content": "run tests"},
+            {"role": "assistant", "content": "3 passed"},
+        ]));
 
-        let result = dispatch_command(&req, &state);
+        req.params = serde_json::json!({"selector": 2});
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["content"], "# proj__cc_2\n\n## User\n\nrun tests\n\n## Assistant\n\n```text\n3 passed\n```\n");
 
-        assert!(result.success);
-        let data = result.data.unwrap();
-        assert_eq!(data["pane_id"], 1);
-        assert_eq!(data["text"], "hello");
-        assert_eq!(data["enter"], true);
+        let mut config = state.config().clone();
+        config.max_response_bytes = 50;
+        state.set_config(config);
+        req.params = serde_json::json!({"selector": 2, "format": "messages"});
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["messages"].as_array().unwrap().len(), data["total_messages"].as_u64()), (1, Some(2)));
+        assert!(data["artifact_id"].is_string());
     }
 
     #[test]
-    fn test_handle_send_keys_pane_not_found() {
-        let state = create_test_state();
+    fn test_handover_context_to_replacement() {
+        let mut state = create_test_state();
+        let cc = AgentKind::parse("cc", r#"{"command": "claude", "status": [{"state": "idle", "regex": "^> $"}]}"#).unwrap();
+        state.set_config(Config { agent_kinds: vec![cc], ..Config::default() });
+        state.update_pane_contents(1, vec!["> ".to_string()]);
+        state.begin_turn(1, "fix the parser", None);
+        state.update_pane_contents(1, vec!["> fix the parser".to_string(), "fixed".to_string(), "> ".to_string()]);
+
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "handover_context".to_string(),
+            params: serde_json::json!({"from": 1, "to": 2, "template": "{kind} said: {context}"}),
+            ..Default::default()
+        };
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["status"].as_str(), data["turns"].as_u64()), (Some("sent"), Some(1)));
+        let effects = state.take_effects();
+        let text = effects[0]["text"].as_str().unwrap();
+        assert!(text.starts_with("\x1b[200~cc said: # proj__cc_1"), "{}", text);
+        assert!(text.contains("fixed"));
+        assert_eq!(state.tasks().get("t1").unwrap().status, TaskStatus::Dispatched);
+
+        req.params = serde_json::json!({"from": 2, "to": 1});
+        assert_eq!(dispatch_command(&req, &mut state).error.unwrap(), "no turns recorded for 2");
+    }
+
+    #[test]
+    fn test_handover_context_via_summarizer() {
+        let mut state = create_test_state();
+        let cc = AgentKind::parse("cc", r#"{"command": "claude", "status": [{"state": "idle", "regex": "^> $"}]}"#).unwrap();
+        state.set_config(Config { agent_kinds: vec![cc], ..Config::default() });
+        state.update_panes(create_manifest_with_panes(vec![
+            create_test_pane(1, "proj__cc_1", false),
+            create_test_pane(2, "proj__cc_2", false),
+            create_test_pane(3, "proj__cc_3", false),
+        ]));
+        state.begin_turn(1, "fix the parser", None);
+        state.update_pane_contents(1, vec!["> fix the parser".to_string(), "fixed".to_string(), "> ".to_string()]);
+
         let req = Request {
             id: "1".to_string(),
-            action: "send_keys".to_string(),
-            params: serde_json::json!({
-                "pane_id": 999,
-                "text": "hello",
-                "enter": false
-            }),
+            action: "handover_context".to_string(),
+            params: serde_json::json!({"from": 1, "to": 2, "via": 3, "template": "Summary: {context}"}),
+            ..Default::default()
         };
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["action"].as_str(), data["pane_id"].as_u64()), (Some("send_keys"), Some(3)));
+        assert!(state.take_effects().is_empty());
 
-        let result = dispatch_command(&req, &state);
+        // plugin.rs types the request and ties its turn to the handover
+        state.update_pane_contents(3, vec!["> ".to_string()]);
+        let turn = state.begin_turn(3, data["text"].as_str().unwrap(), None).unwrap();
+        state.attach_handover(data["handover_id"].as_str().unwrap(), &turn);
+        state.update_pane_contents(3, vec!["> Summarize".to_string(), "Parser fixed.".to_string(), "> ".to_string()]);
 
-        assert!(!result.success);
-        assert!(result.error.unwrap().contains("pane not found"));
+        let effects = state.take_effects();
+        assert_eq!((effects[0]["pane_id"].as_u64(), effects[0]["text"].as_str()), (Some(2), Some("Summary: Parser fixed.")));
+        let events = state.take_events();
+        assert_eq!((events[0].0.as_str(), events[0].1["status"].as_str()), ("handover", Some("sent")));
     }
 
     #[test]
-    fn test_handle_send_keys_invalid_params() {
-        let state = create_test_state();
-        let req = Request {
+    fn test_fanout_collects_replies_side_by_side() {
+        let mut state = create_test_state();
+        let cc = AgentKind::parse("cc", r#"{"command": "claude", "status": [{"state": "idle", "regex": "^> $"}]}"#).unwrap();
+        state.set_config(Config { agent_kinds: vec![cc], ..Config::default() });
+        let mut req = Request {
             id: "1".to_string(),
-            action: "send_keys".to_string(),
-            params: serde_json::json!({"wrong_field": 123}),
+            action: "fanout".to_string(),
+            params: serde_json::json!({"kinds": ["cc"], "text": "name a color", "timeout_secs": 60}),
+            ..Default::default()
         };
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["action"].as_str(), data["targets"].as_array().unwrap().len()), (Some("fanout"), 2));
 
-        let result = dispatch_command(&req, &state);
+        // plugin.rs sends the prompts and holds the response
+        let targets = (1..=2).map(|id| (id, Some(format!("t{}", id)))).collect();
+        state.track_fanout(PendingFanout::new("1", Some("cli-1"), "name a color".to_string(), targets, 60));
+        for effect in state.take_effects() {
+            let id = effect["pane_id"].as_u64().unwrap() as u32;
+            state.begin_turn(id, "name a color", effect["task_id"].as_str().map(String::from));
+        }
+        state.update_pane_contents(1, vec!["> name a color".to_string(), "red".to_string(), "> ".to_string()]);
+        assert!(state.take_finished_fanouts().0.is_empty());
+        state.update_pane_contents(2, vec!["> name a color".to_string(), "blue".to_string(), "> ".to_string()]);
 
-        assert!(!result.success);
-        assert!(result.error.unwrap().contains("invalid params"));
+        let pending = state.take_finished_fanouts().0.pop().unwrap();
+        let data = fanout_response(&mut state, &pending).data.unwrap();
+        let replies: Vec<_> = data["replies"].as_array().unwrap().iter().map(|r| r["content"].as_str()).collect();
+        assert_eq!(replies, [Some("red"), Some("blue")]);
+        assert_eq!(data["complete"], true);
+
+        req.params = serde_json::json!({"selectors": [1], "text": ""});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().starts_with("invalid params"));
+        req.params = serde_json::json!({"kinds": ["nope"], "text": "hi"});
+        assert_eq!(dispatch_command(&req, &mut state).error.unwrap(), "unknown agent kind: nope");
     }
 
     #[test]
-    fn test_handle_send_interrupt_valid() {
-        let state = create_test_state();
-        let req = Request {
+    fn test_fanout_judge_picks_a_reply() {
+        let mut state = create_test_state();
+        let cc = AgentKind::parse("cc", r#"{"command": "claude", "status": [{"state": "idle", "regex": "^> $"}]}"#).unwrap();
+        state.set_config(Config { agent_kinds: vec![cc], ..Config::default() });
+        state.update_panes(create_manifest_with_panes(vec![
+            create_test_pane(1, "proj__cc_1", false),
+            create_test_pane(2, "proj__cc_2", false),
+            create_test_pane(3, "proj__cc_3", false),
+        ]));
+        let mut req = Request {
             id: "1".to_string(),
-            action: "send_interrupt".to_string(),
-            params: serde_json::json!({"pane_id": 1}),
+            action: "fanout".to_string(),
+            params: serde_json::json!({"selectors": [1, 2], "text": "name a color", "judge": 3}),
+            ..Default::default()
         };
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["judge"]["pane_id"], 3);
 
-        let result = dispatch_command(&req, &state);
+        let mut pending = PendingFanout::new("1", Some("cli-1"), "name a color".to_string(), vec![(1, Some("t1".to_string())), (2, Some("t2".to_string()))], 60);
+        pending.judge = Some(FanoutJudge { pane_id: 3, template: "{candidates}".to_string(), asked: false, task_id: None });
+        state.track_fanout(pending);
+        for (id, color) in [(1, "red"), (2, "blue")] {
+            state.begin_turn(id, "name a color", Some(format!("t{}", id)));
+            state.update_pane_contents(id, vec!["> name a color".to_string(), color.to_string(), "> ".to_string()]);
+        }
+        state.take_effects();
 
-        assert!(result.success);
-        let data = result.data.unwrap();
-        assert_eq!(data["action"], "send_interrupt");
-        assert_eq!(data["pane_id"], 1);
+        // The replies go to the judge, and the fanout waits for its turn
+        let (finished, waits) = state.take_finished_fanouts();
+        assert!(finished.is_empty());
+        assert_eq!(waits, [60]);
+        let effects = state.take_effects();
+        let text = effects[0]["text"].as_str().unwrap();
+        assert!(text.contains("## Candidate 2 (proj__cc_2)"), "{}", text);
+        state.begin_turn(3, text, effects[0]["task_id"].as_str().map(String::from));
+        state.update_pane_contents(3, vec!["> ## Candidate 1".to_string(), "Blue is calmer.".to_string(), "PICK: 2".to_string(), "> ".to_string()]);
+
+        let pending = state.take_finished_fanouts().0.pop().unwrap();
+        let data = fanout_response(&mut state, &pending).data.unwrap();
+        assert_eq!((data["judge"]["pick"].as_u64(), data["judge"]["picked_pane_id"].as_u64()), (Some(2), Some(2)));
+
+        req.params = serde_json::json!({"selectors": [1, 2], "text": "hi", "judge": 2});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().starts_with("invalid params: judge must not"));
     }
 
     #[test]
-    fn test_handle_unknown_action() {
-        let state = State::default();
-        let req = Request {
+    fn test_next_event_returns_logged_or_waits() {
+        let mut state = create_test_state();
+        state.emit_event("agent_ready", serde_json::json!({"pane_id": 1}));
+        state.emit_event("handover", serde_json::json!({"id": "h1"}));
+        let mut req = Request {
             id: "1".to_string(),
-            action: "unknown_action".to_string(),
-            params: serde_json::Value::Null,
+            action: "next_event".to_string(),
+            params: serde_json::json!({"after": 0, "types": ["handover"]}),
+            ..Default::default()
         };
 
-        let result = dispatch_command(&req, &state);
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["seq"].as_u64(), data["type"].as_str()), (Some(2), Some("handover")));
+        assert_eq!(data["event"]["id"], "h1");
 
-        assert!(!result.success);
-        assert!(result.error.unwrap().contains("unknown action"));
+        req.params = serde_json::json!({"timeout_secs": 0});
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap(), serde_json::json!({"timeout": true, "seq": 2}));
+
+        req.params = serde_json::json!({"types": ["agent_ready"]});
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["action"].as_str(), data["after"].as_u64()), (Some("next_event"), Some(2)));
+
+        // plugin.rs holds the poll until a matching event
+        state.track_poll(PendingPoll {
+            request_id: "1".to_string(),
+            cli_id: "cli-1".to_string(),
+            after: 2,
+            kinds: vec!["agent_ready".to_string()],
+            deadline_ms: u64::MAX,
+        });
+        state.emit_event("handover", serde_json::json!({}));
+        assert!(state.take_answered_polls().is_empty());
+        state.emit_event("agent_ready", serde_json::json!({"pane_id": 2}));
+        let (poll, next) = state.take_answered_polls().pop().unwrap();
+        assert_eq!((poll.request_id.as_str(), next.unwrap().0.seq), ("1", 4));
     }
 
     #[test]
-    fn test_validate_send_keys_params_valid() {
-        let params = SendKeysParams {
-            pane_id: 1,
-            text: "hello".to_string(),
-            enter: true,
+    fn test_wait_checks_conditions_and_holds() {
+        let mut state = create_test_state();
+        let cc = AgentKind::parse("cc", r#"{"command": "claude", "status": [{"state": "idle", "regex": "^> $"}]}"#).unwrap();
+        state.set_config(Config { agent_kinds: vec![cc], ..Config::default() });
+        state.update_pane_contents(1, vec!["working".to_string()]);
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "wait".to_string(),
+            params: serde_json::json!({"conditions": [{"idle": 1}, {"pane_exists": "logs"}], "mode": "any"}),
+            ..Default::default()
         };
-        assert!(validate_send_keys_params(&params).is_ok());
+
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["action"].as_str(), data["timeout_secs"].as_u64()), (Some("wait"), Some(30)));
+        let params = data["params"].clone();
+        assert!(wait_result(&mut state, "1", &params, false).is_none());
+        let data = wait_result(&mut state, "1", &params, true).unwrap().data.unwrap();
+        assert_eq!((data["met"].as_bool(), data["timeout"].as_bool()), (Some(false), Some(true)));
+
+        state.update_pane_contents(1, vec!["done".to_string(), "> ".to_string()]);
+        let data = wait_result(&mut state, "1", &params, false).unwrap().data.unwrap();
+        assert_eq!(data["conditions"], serde_json::json!([true, false]));
+
+        req.params = serde_json::json!({"conditions": [{"text": {"selector": 1, "regex": "^do"}}, {"idle": 1}]});
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["met"], true);
+        req.params = serde_json::json!({"conditions": [{"task": "t9"}], "timeout_secs": 0});
+        assert_eq!(dispatch_command(&req, &mut state).error.unwrap(), "task not found: t9");
+        req.params = serde_json::json!({"conditions": [{"idle": 9}], "timeout_secs": 0});
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["met"], false);
     }
 
     #[test]
-    fn test_validate_send_keys_params_empty_text() {
-        let params = SendKeysParams {
-            pane_id: 1,
-            text: "".to_string(),
-            enter: false,
+    fn test_run_command_params() {
+        let mut state = create_test_state();
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "run_command".to_string(),
+            params: serde_json::json!({"command": "tail", "args": ["-f", "a log"], "cwd": "~/logs", "floating": true, "name": "logs"}),
+            ..Default::default()
         };
-        // Empty text is allowed (might just press enter)
-        assert!(validate_send_keys_params(&params).is_ok());
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data, serde_json::json!({
+            "action": "run_command",
+            "command": ["tail", "-f", "a log"],
+            "cwd": "~/logs",
+            "floating": true,
+            "title": "logs",
+        }));
+
+        req.params = serde_json::json!({"command": " "});
+        assert_eq!(dispatch_command(&req, &mut state).error.unwrap(), "invalid params: command may not be empty");
+        req.params = serde_json::json!({"command": "ls", "cwd": "logs"});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().contains("cwd must be absolute"));
+        req.params = serde_json::json!({"args": ["-l"]});
+        assert!(dispatch_command(&req, &mut state).error.unwrap().starts_with("invalid params"));
+    }
+
+    #[test]
+    fn test_get_version() {
+        let mut state = create_test_state();
+        let req = Request {
+            id: "1".to_string(),
+            action: "get_version".to_string(),
+            params: serde_json::Value::Null,
+            ..Default::default()
+        };
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(data["protocol"], PROTOCOL_VERSION);
+        assert_eq!(data["build_sha256"].as_str().map(integrity::parse_hash), Some(Some(integrity::BUILD_SHA256.to_string())));
+        assert!(data["file_sha256"].is_null());
+
+        state.set_plugin_hash(Ok("ab".repeat(32)));
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["file_sha256"], "ab".repeat(32));
+        state.set_plugin_hash(Err("no such file".to_string()));
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!((data["file_sha256"].is_null(), data["file_sha256_error"].as_str()), (true, Some("no such file")));
+    }
+
+    #[test]
+    fn test_open_floating_command_placement() {
+        let mut state = create_test_state();
+        let mut req = Request {
+            id: "1".to_string(),
+            action: "open_floating_command".to_string(),
+            params: serde_json::json!({"command": "tail", "args": ["-f", "log"], "x": 0, "y": 50, "width": 40}),
+            ..Default::default()
+        };
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["action"], "run_command");
+        assert_eq!(data["floating"], true);
+        assert_eq!(data["coordinates"], serde_json::json!({"x": "0%", "y": "50%", "width": "40%"}));
+
+        req.params = serde_json::json!({"command": "tail", "height": 0});
+        assert_eq!(dispatch_command(&req, &mut state).error.unwrap(), "invalid params: height must be 1 to 100 percent");
+        req.params = serde_json::json!({"command": "tail", "x": 101});
+        assert_eq!(dispatch_command(&req, &mut state).error.unwrap(), "invalid params: x must be 0 to 100 percent");
+        req.params = serde_json::json!({"command": "", "x": 10});
+        assert_eq!(dispatch_command(&req, &mut state).error.unwrap(), "invalid params: command may not be empty");
     }
 }
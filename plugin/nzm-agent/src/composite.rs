@@ -0,0 +1,242 @@
+//! User-defined composite actions declared in config
+//!
+//! An `action.<name>` config key holds JSON describing a sequence of
+//! existing actions:
+//!
+//! ```json
+//! {"description": "push and notify",
+//!  "steps": [
+//!    {"action": "send_keys", "params": {"pane_id": "${pane}", "text": "git push", "enter": true}},
+//!    {"action": "send_keys", "params": {"pane_id": "${pane}", "text": "${note}"}, "when": "note"}
+//!  ]}
+//! ```
+//!
+//! A string that is exactly `${arg}` is replaced by the argument's JSON
+//! value; `${arg}` inside a longer string is replaced by its text. A step's
+//! `when` is `arg`, `!arg`, `arg == value` or `arg != value`.
+
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+
+/// One step of a composite action
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompositeStep {
+    pub action: String,
+    #[serde(default)]
+    pub params: Value,
+    /// Condition on the caller's arguments; the step is skipped when false
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub when: Option<String>,
+}
+
+/// A named sequence of actions exposed as a first-class action
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct CompositeAction {
+    #[serde(default)]
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    pub steps: Vec<CompositeStep>,
+}
+
+impl CompositeAction {
+    /// Parse the JSON value of an `action.<name>` config key
+    pub fn parse(name: &str, json: &str) -> Result<Self, String> {
+        let mut action: CompositeAction =
+            serde_json::from_str(json).map_err(|e| format!("invalid composite action {}: {}", name, e))?;
+        if action.steps.is_empty() {
+            return Err(format!("composite action {} has no steps", name));
+        }
+        action.name = name.to_string();
+        Ok(action)
+    }
+
+    /// Names of the arguments referenced by placeholders and conditions
+    pub fn arg_names(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        for step in &self.steps {
+            collect_placeholders(&step.params, &mut names);
+            if let Some(when) = &step.when {
+                let name = Condition::parse(when).name;
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+            }
+        }
+        names
+    }
+}
+
+/// Replace `${arg}` placeholders in `template` with values from `args`
+pub fn substitute(template: &Value, args: &Value) -> Result<Value, String> {
+    Ok(match template {
+        Value::String(s) => {
+            if let Some(name) = whole_placeholder(s) {
+                return lookup(args, name).cloned();
+            }
+            let mut out = String::with_capacity(s.len());
+            let mut rest = s.as_str();
+            while let Some(start) = rest.find("${") {
+                let Some(len) = rest[start..].find('}') else {
+                    break;
+                };
+                out.push_str(&rest[..start]);
+                out.push_str(&value_text(lookup(args, &rest[start + 2..start + len])?));
+                rest = &rest[start + len + 1..];
+            }
+            out.push_str(rest);
+            Value::String(out)
+        }
+        Value::Array(items) => Value::Array(
+            items.iter().map(|v| substitute(v, args)).collect::<Result<_, _>>()?,
+        ),
+        Value::Object(map) => Value::Object(
+            map.iter()
+                .map(|(k, v)| substitute(v, args).map(|v| (k.clone(), v)))
+                .collect::<Result<_, _>>()?,
+        ),
+        other => other.clone(),
+    })
+}
+
+/// Evaluate a step's `when` condition against the caller's arguments
+pub fn condition_holds(when: &str, args: &Value) -> bool {
+    Condition::parse(when).eval(args)
+}
+
+struct Condition {
+    name: String,
+    negate: bool,
+    compare: Option<String>,
+}
+
+impl Condition {
+    fn parse(when: &str) -> Condition {
+        let when = when.trim();
+        for (op, negate) in [("!=", true), ("==", false)] {
+            if let Some((name, value)) = when.split_once(op) {
+                return Condition {
+                    name: name.trim().to_string(),
+                    negate,
+                    compare: Some(value.trim().to_string()),
+                };
+            }
+        }
+        match when.strip_prefix('!') {
+            Some(name) => Condition { name: name.trim().to_string(), negate: true, compare: None },
+            None => Condition { name: when.to_string(), negate: false, compare: None },
+        }
+    }
+
+    fn eval(&self, args: &Value) -> bool {
+        let value = args.get(&self.name).unwrap_or(&Value::Null);
+        let result = match &self.compare {
+            Some(expected) => value_text(value) == *expected,
+            None => is_truthy(value),
+        };
+        result != self.negate
+    }
+}
+
+fn whole_placeholder(s: &str) -> Option<&str> {
+    let name = s.strip_prefix("${")?.strip_suffix('}')?;
+    (!name.contains('}')).then_some(name)
+}
+
+fn lookup<'a>(args: &'a Value, name: &str) -> Result<&'a Value, String> {
+    args.get(name).ok_or_else(|| format!("missing argument: {}", name))
+}
+
+fn value_text(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => String::new(),
+        other => other.to_string(),
+    }
+}
+
+fn is_truthy(value: &Value) -> bool {
+    match value {
+        Value::Null => false,
+        Value::Bool(b) => *b,
+        Value::Number(n) => n.as_f64() != Some(0.0),
+        Value::String(s) => !s.is_empty(),
+        Value::Array(a) => !a.is_empty(),
+        Value::Object(o) => !o.is_empty(),
+    }
+}
+
+fn collect_placeholders(template: &Value, names: &mut Vec<String>) {
+    match template {
+        Value::String(s) => {
+            let mut rest = s.as_str();
+            while let Some(start) = rest.find("${") {
+                let Some(len) = rest[start..].find('}') else {
+                    break;
+                };
+                let name = rest[start + 2..start + len].to_string();
+                if !names.contains(&name) {
+                    names.push(name);
+                }
+                rest = &rest[start + len + 1..];
+            }
+        }
+        Value::Array(items) => items.iter().for_each(|v| collect_placeholders(v, names)),
+        Value::Object(map) => map.values().for_each(|v| collect_placeholders(v, names)),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_parse_composite_action() {
+        let action = CompositeAction::parse(
+            "ship_it",
+            r#"{"description":"push","steps":[{"action":"send_keys","params":{"pane_id":"${pane}","text":"git push"}}]}"#,
+        )
+        .unwrap();
+
+        assert_eq!(action.name, "ship_it");
+        assert_eq!(action.description.as_deref(), Some("push"));
+        assert_eq!(action.steps.len(), 1);
+        assert_eq!(action.arg_names(), vec!["pane"]);
+    }
+
+    #[test]
+    fn test_parse_rejects_empty_and_malformed() {
+        assert!(CompositeAction::parse("x", r#"{"steps":[]}"#).is_err());
+        assert!(CompositeAction::parse("x", "not json").is_err());
+    }
+
+    #[test]
+    fn test_substitute_whole_and_inline_placeholders() {
+        let template = json!({"pane_id": "${pane}", "text": "echo ${msg} #${pane}", "enter": true});
+        let args = json!({"pane": 3, "msg": "hi"});
+
+        let params = substitute(&template, &args).unwrap();
+
+        assert_eq!(params, json!({"pane_id": 3, "text": "echo hi #3", "enter": true}));
+    }
+
+    #[test]
+    fn test_substitute_missing_argument() {
+        let err = substitute(&json!({"pane_id": "${pane}"}), &json!({})).unwrap_err();
+        assert_eq!(err, "missing argument: pane");
+    }
+
+    #[test]
+    fn test_conditions() {
+        let args = json!({"flag": true, "mode": "fast", "empty": ""});
+
+        assert!(condition_holds("flag", &args));
+        assert!(!condition_holds("!flag", &args));
+        assert!(!condition_holds("empty", &args));
+        assert!(!condition_holds("absent", &args));
+        assert!(condition_holds("mode == fast", &args));
+        assert!(condition_holds("mode != slow", &args));
+    }
+}
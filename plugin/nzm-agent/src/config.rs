@@ -0,0 +1,644 @@
+//! Plugin configuration read from the layout's plugin block
+
+use std::collections::BTreeMap;
+
+use crate::composite::CompositeAction;
+use crate::dashboard::RenderMode;
+use crate::guards::Guard;
+use crate::hours::{self, DispatchHours};
+use crate::handover::{DEFAULT_HANDOVER_TEMPLATE, DEFAULT_SUMMARY_TEMPLATE};
+use crate::fanout::DEFAULT_JUDGE_TEMPLATE;
+use crate::i18n::Locale;
+use crate::idle::{IdleAction, IdlePolicy};
+use crate::kinds::AgentKind;
+use crate::memory::MemoryBudget;
+use crate::naming::DEFAULT_TITLE_FORMAT;
+use crate::schedule::Schedule;
+use crate::tasks::StaleTaskPolicy;
+
+/// Default cap on text returned inline in a single response
+pub const DEFAULT_MAX_RESPONSE_BYTES: usize = 256 * 1024;
+
+/// A user-defined redaction pattern
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RedactionRule {
+    pub name: String,
+    pub pattern: String,
+    /// Selector limiting which panes the rule applies to (all when unset)
+    pub panes: Option<String>,
+}
+
+/// Default number of recent captures kept for `recall`
+pub const DEFAULT_CAPTURE_HISTORY: usize = 20;
+
+/// Default number of PaneUpdate manifests kept for replay debugging
+pub const DEFAULT_MANIFEST_HISTORY: usize = 50;
+pub const DEFAULT_TURN_HISTORY: usize = 500;
+
+/// Default directory holding one subdirectory per project, as in the CLI
+pub const DEFAULT_PROJECTS_BASE: &str = "/data/projects";
+
+/// Default time a git_info result is reused before git runs again
+pub const DEFAULT_GIT_INFO_TTL_MS: u64 = 5000;
+
+/// Default interval between chunks of a paced send
+pub const DEFAULT_SEND_PACE_MS: u64 = 20;
+
+/// Default number of a pane's last lines status patterns are matched on
+pub const DEFAULT_STATUS_LINES: usize = 40;
+
+/// Default number of completed or failed tasks kept in the task journal
+pub const DEFAULT_TASK_HISTORY: usize = 500;
+
+/// Default number of pending tasks enqueue_task accepts
+pub const DEFAULT_TASK_QUEUE_CAPACITY: usize = 1000;
+
+/// Default number of unfinished paced sends send_keys accepts
+pub const DEFAULT_SEND_QUEUE_CAPACITY: usize = 64;
+
+/// Default time a pane's writes wait for a secret to resolve
+pub const DEFAULT_WRITE_RESERVATION_TTL_SECS: u64 = 30;
+
+/// Runtime configuration for the agent plugin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Config {
+    /// Largest captured text returned inline; longer text is truncated
+    /// and the full content saved as an artifact
+    pub max_response_bytes: usize,
+    /// Number of recent captures kept addressable by `recall`
+    pub capture_history: usize,
+    /// Number of recent pane manifests kept for `replay_history`
+    pub manifest_history: usize,
+    /// Number of recent conversation turns kept for `list_turns`
+    pub turn_history: usize,
+    /// Pending tasks allowed before enqueue_task reports a full queue
+    pub task_queue_capacity: usize,
+    /// Completed or failed tasks kept; older ones leave the journal
+    pub task_history: usize,
+    /// Last lines of a pane that agent kind status patterns are run on
+    pub status_lines: usize,
+    /// Unfinished paced sends allowed before send_keys reports a full queue
+    pub send_queue_capacity: usize,
+    /// Byte caps of evictable buffers from `memory.<buffer>` keys
+    pub memory: MemoryBudget,
+    /// Interval between chunks of sends with `chunk_bytes`
+    pub send_pace_ms: u64,
+    /// How long a pane's writes wait behind a secret still resolving
+    pub write_reservation_ttl_secs: u64,
+    /// Host directory holding one subdirectory per project
+    pub projects_base: String,
+    /// How long a git_info result is reused
+    pub git_info_ttl_ms: u64,
+    /// Apply the built-in secret patterns (API keys, tokens, emails)
+    pub redact_builtin: bool,
+    /// Extra patterns from `redact.<name>` / `redact.<name>.panes` keys
+    pub redaction_rules: Vec<RedactionRule>,
+    /// Identity file passed to `age --decrypt`
+    pub age_identity: Option<String>,
+    /// Inline Rhai source for user hooks
+    pub script: Option<String>,
+    /// Path to a Rhai file for user hooks, inside `/data` (used when `script` is unset)
+    pub script_file: Option<String>,
+    /// Host path of the loaded `.wasm`, hashed for get_version
+    pub plugin_path: Option<String>,
+    /// Composite actions from `action.<name>` keys
+    pub composite_actions: Vec<CompositeAction>,
+    /// Agent kinds from `kind.<name>` keys, layered over the built-ins
+    pub agent_kinds: Vec<AgentKind>,
+    /// Pre-send guard rules from `guard.<name>` keys
+    pub guards: Vec<Guard>,
+    /// Recurring actions from `schedule.<name>` keys
+    pub schedules: Vec<Schedule>,
+    /// `action.*`, `kind.*`, `guard.*` and `schedule.*` keys that could
+    /// not be parsed, with why; the other keys still apply
+    pub errors: BTreeMap<String, String>,
+    /// What dispatch_task does when a task's pane was closed or replaced
+    pub stale_task_policy: StaleTaskPolicy,
+    /// Language of the plugin pane, from a name such as `de_DE.UTF-8`
+    pub locale: Locale,
+    /// Dashboard style: `standard`, or `accessible` for plain labeled text
+    pub render_mode: RenderMode,
+    /// Use ASCII instead of Unicode default status glyphs
+    pub ascii_glyphs: bool,
+    /// Show each agent's branch in the dashboard, refreshing git info in
+    /// the background
+    pub dashboard_git: bool,
+    /// Status glyphs from `glyph.<status>` keys
+    pub status_glyphs: BTreeMap<String, String>,
+    /// Text that, appearing in an agent pane, pauses automation for its project
+    pub safe_word: Option<String>,
+    /// What happens to agents idle too long, from `idle_timeout_secs` and
+    /// `idle_action`
+    pub idle: Option<IdlePolicy>,
+    /// When queued tasks may be dispatched, by project (None: every
+    /// project), from `dispatch_hours` keys
+    pub dispatch_hours: BTreeMap<Option<String>, DispatchHours>,
+    /// Offset from UTC of dispatch hours and schedules, in minutes
+    pub utc_offset_minutes: i32,
+    /// Agent pane title format with `{project}`, `{kind}`, `{index}`
+    pub title_format: String,
+    /// Opening prompt handover_context sends a replacement agent
+    pub handover_template: String,
+    /// Request handover_context sends an agent asked to summarize
+    pub handover_summary_template: String,
+    /// Request fanout sends its judge, with `{prompt}` and `{candidates}`
+    pub fanout_judge_template: String,
+    /// Regex with named groups overriding how titles are parsed
+    pub title_pattern: Option<String>,
+    /// Debug: percentage of effects and pane updates to drop
+    pub chaos_drop_percent: u32,
+    /// Debug: upper bound on random delays of effects and pane updates
+    pub chaos_max_delay_ms: u64,
+    /// Debug: seed for chaos decisions, to reproduce a run
+    pub chaos_seed: u64,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            max_response_bytes: DEFAULT_MAX_RESPONSE_BYTES,
+            capture_history: DEFAULT_CAPTURE_HISTORY,
+            manifest_history: DEFAULT_MANIFEST_HISTORY,
+            turn_history: DEFAULT_TURN_HISTORY,
+            task_history: DEFAULT_TASK_HISTORY,
+            status_lines: DEFAULT_STATUS_LINES,
+            task_queue_capacity: DEFAULT_TASK_QUEUE_CAPACITY,
+            send_queue_capacity: DEFAULT_SEND_QUEUE_CAPACITY,
+            memory: MemoryBudget::default(),
+            send_pace_ms: DEFAULT_SEND_PACE_MS,
+            write_reservation_ttl_secs: DEFAULT_WRITE_RESERVATION_TTL_SECS,
+            projects_base: DEFAULT_PROJECTS_BASE.to_string(),
+            git_info_ttl_ms: DEFAULT_GIT_INFO_TTL_MS,
+            redact_builtin: true,
+            redaction_rules: Vec::new(),
+            age_identity: None,
+            script: None,
+            script_file: None,
+            plugin_path: None,
+            composite_actions: Vec::new(),
+            agent_kinds: Vec::new(),
+            guards: Vec::new(),
+            schedules: Vec::new(),
+            errors: BTreeMap::new(),
+            stale_task_policy: StaleTaskPolicy::default(),
+            locale: Locale::default(),
+            render_mode: RenderMode::default(),
+            ascii_glyphs: false,
+            dashboard_git: false,
+            status_glyphs: BTreeMap::new(),
+            safe_word: None,
+            idle: None,
+            dispatch_hours: BTreeMap::new(),
+            utc_offset_minutes: 0,
+            title_format: DEFAULT_TITLE_FORMAT.to_string(),
+            handover_template: DEFAULT_HANDOVER_TEMPLATE.to_string(),
+            handover_summary_template: DEFAULT_SUMMARY_TEMPLATE.to_string(),
+            fanout_judge_template: DEFAULT_JUDGE_TEMPLATE.to_string(),
+            title_pattern: None,
+            chaos_drop_percent: 0,
+            chaos_max_delay_ms: 0,
+            chaos_seed: 1,
+        }
+    }
+}
+
+impl Config {
+    /// Build configuration from the key/value pairs Zellij passes to `load`
+    ///
+    /// Unknown keys are ignored and malformed values fall back to defaults.
+    pub fn from_map(map: &BTreeMap<String, String>) -> Self {
+        let mut config = Config::default();
+        if let Some(v) = map.get("max_response_bytes").and_then(|v| v.parse().ok()) {
+            config.max_response_bytes = v;
+        }
+        if let Some(v) = map.get("capture_history").and_then(|v| v.parse().ok()) {
+            config.capture_history = v;
+        }
+        if let Some(v) = map.get("manifest_history").and_then(|v| v.parse().ok()) {
+            config.manifest_history = v;
+        }
+        if let Some(v) = map.get("turn_history").and_then(|v| v.parse().ok()) {
+            config.turn_history = v;
+        }
+        if let Some(v) = map.get("task_queue_capacity").and_then(|v| v.parse().ok()) {
+            config.task_queue_capacity = v;
+        }
+        if let Some(v) = map.get("task_history").and_then(|v| v.parse().ok()) {
+            config.task_history = v;
+        }
+        if let Some(v) = map.get("status_lines").and_then(|v| v.parse().ok()) {
+            config.status_lines = v;
+        }
+        if let Some(v) = map.get("send_queue_capacity").and_then(|v| v.parse().ok()) {
+            config.send_queue_capacity = v;
+        }
+        if let Some(v) = map.get("send_pace_ms").and_then(|v| v.parse().ok()) {
+            config.send_pace_ms = v;
+        }
+        if let Some(v) = map.get("write_reservation_ttl_secs").and_then(|v| v.parse().ok()) {
+            config.write_reservation_ttl_secs = v;
+        }
+        if let Some(v) = map.get("projects_base") {
+            config.projects_base = v.clone();
+        }
+        if let Some(v) = map.get("locale").and_then(|v| Locale::parse(v)) {
+            config.locale = v;
+        }
+        if let Some(v) = map.get("render_mode").and_then(|v| RenderMode::parse(v)) {
+            config.render_mode = v;
+        }
+        if let Some(v) = map.get("stale_task_policy").and_then(|v| StaleTaskPolicy::parse(v)) {
+            config.stale_task_policy = v;
+        }
+        if let Some(v) = map.get("glyphs") {
+            config.ascii_glyphs = v == "ascii";
+        }
+        for (key, glyph) in map {
+            if let Some(status) = key.strip_prefix("glyph.").filter(|_| !glyph.is_empty()) {
+                config.status_glyphs.insert(status.to_string(), glyph.clone());
+            }
+        }
+        for (key, value) in map {
+            if let (Some(buffer), Ok(bytes)) = (key.strip_prefix("memory."), value.parse()) {
+                config.memory.set(buffer, bytes);
+            }
+        }
+        if let Some(v) = map.get("safe_word").filter(|v| !v.is_empty()) {
+            config.safe_word = Some(v.clone());
+        }
+        if let Some(timeout_secs) = map.get("idle_timeout_secs").and_then(|v| v.parse().ok()).filter(|&secs| secs > 0) {
+            let action = map.get("idle_action").and_then(|v| IdleAction::parse(v)).unwrap_or(IdleAction::Notify);
+            config.idle = Some(IdlePolicy { timeout_secs, action });
+        }
+        for (key, value) in map {
+            let project = match key.strip_prefix("dispatch_hours") {
+                Some("") => None,
+                Some(rest) => match rest.strip_prefix('.') {
+                    Some(project) => Some(project.to_string()),
+                    None => continue,
+                },
+                None => continue,
+            };
+            if let Some(hours) = DispatchHours::parse(value) {
+                config.dispatch_hours.insert(project, hours);
+            }
+        }
+        if let Some(v) = map.get("utc_offset").and_then(|v| hours::parse_offset(v)) {
+            config.utc_offset_minutes = v;
+        }
+        if let Some(v) = map.get("git_info_ttl_ms").and_then(|v| v.parse().ok()) {
+            config.git_info_ttl_ms = v;
+        }
+        if let Some(v) = map.get("dashboard_git").and_then(|v| v.parse().ok()) {
+            config.dashboard_git = v;
+        }
+        if let Some(v) = map.get("redact_builtin").and_then(|v| v.parse().ok()) {
+            config.redact_builtin = v;
+        }
+        if let Some(v) = map.get("age_identity") {
+            config.age_identity = Some(v.clone());
+        }
+        if let Some(v) = map.get("title_format") {
+            config.title_format = v.clone();
+        }
+        if let Some(v) = map.get("handover_template") {
+            config.handover_template = v.clone();
+        }
+        if let Some(v) = map.get("handover_summary_template") {
+            config.handover_summary_template = v.clone();
+        }
+        if let Some(v) = map.get("fanout_judge_template") {
+            config.fanout_judge_template = v.clone();
+        }
+        config.title_pattern = map.get("title_pattern").cloned();
+        if let Some(v) = map.get("chaos.drop_percent").and_then(|v| v.parse().ok()) {
+            config.chaos_drop_percent = v;
+        }
+        if let Some(v) = map.get("chaos.max_delay_ms").and_then(|v| v.parse().ok()) {
+            config.chaos_max_delay_ms = v;
+        }
+        if let Some(v) = map.get("chaos.seed").and_then(|v| v.parse().ok()) {
+            config.chaos_seed = v;
+        }
+        config.script = map.get("script").cloned();
+        config.script_file = map.get("script_file").cloned();
+        config.plugin_path = map.get("plugin_path").filter(|v| !v.is_empty()).cloned();
+        for (key, json) in map {
+            let Some(name) = key.strip_prefix("action.") else {
+                continue;
+            };
+            match CompositeAction::parse(name, json) {
+                Ok(action) => config.composite_actions.push(action),
+                Err(e) => {
+                    config.errors.insert(key.clone(), e);
+                }
+            }
+        }
+        for (key, json) in map {
+            let Some(name) = key.strip_prefix("kind.") else {
+                continue;
+            };
+            match AgentKind::parse(name, json) {
+                Ok(kind) => config.agent_kinds.push(kind),
+                Err(e) => {
+                    config.errors.insert(key.clone(), e);
+                }
+            }
+        }
+        for (key, json) in map {
+            let Some(name) = key.strip_prefix("guard.") else {
+                continue;
+            };
+            match Guard::parse(name, json) {
+                Ok(guard) => config.guards.push(guard),
+                Err(e) => {
+                    config.errors.insert(key.clone(), e);
+                }
+            }
+        }
+        for (key, json) in map {
+            let Some(name) = key.strip_prefix("schedule.") else {
+                continue;
+            };
+            match Schedule::parse(name, json) {
+                Ok(schedule) => config.schedules.push(schedule),
+                Err(e) => {
+                    config.errors.insert(key.clone(), e);
+                }
+            }
+        }
+        for (key, pattern) in map {
+            let Some(name) = key.strip_prefix("redact.") else {
+                continue;
+            };
+            if name.contains('.') {
+                continue;
+            }
+            config.redaction_rules.push(RedactionRule {
+                name: name.to_string(),
+                pattern: pattern.clone(),
+                panes: map.get(&format!("redact.{}.panes", name)).cloned(),
+            });
+        }
+        config
+    }
+
+    /// Look up a composite action by name
+    pub fn composite_action(&self, name: &str) -> Option<&CompositeAction> {
+        self.composite_actions.iter().find(|a| a.name == name)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_empty_map_uses_defaults() {
+        assert_eq!(Config::from_map(&BTreeMap::new()), Config::default());
+    }
+
+    #[test]
+    fn test_parses_max_response_bytes() {
+        let mut map = BTreeMap::new();
+        map.insert("max_response_bytes".to_string(), "1024".to_string());
+        assert_eq!(Config::from_map(&map).max_response_bytes, 1024);
+    }
+
+    #[test]
+    fn test_malformed_value_falls_back() {
+        let mut map = BTreeMap::new();
+        map.insert("max_response_bytes".to_string(), "lots".to_string());
+        assert_eq!(Config::from_map(&map).max_response_bytes, DEFAULT_MAX_RESPONSE_BYTES);
+    }
+
+    #[test]
+    fn test_parses_redaction_rules() {
+        let mut map = BTreeMap::new();
+        map.insert("redact_builtin".to_string(), "false".to_string());
+        map.insert("redact.ticket".to_string(), r"SEC-\d+".to_string());
+        map.insert("redact.ticket.panes".to_string(), "secure__*".to_string());
+        map.insert("redact.host".to_string(), "internal\\.corp".to_string());
+
+        let config = Config::from_map(&map);
+
+        assert!(!config.redact_builtin);
+        assert_eq!(config.redaction_rules.len(), 2);
+        let ticket = config.redaction_rules.iter().find(|r| r.name == "ticket").unwrap();
+        assert_eq!(ticket.pattern, r"SEC-\d+");
+        assert_eq!(ticket.panes.as_deref(), Some("secure__*"));
+        let host = config.redaction_rules.iter().find(|r| r.name == "host").unwrap();
+        assert!(host.panes.is_none());
+    }
+
+    #[test]
+    fn test_parses_secret_provider_settings() {
+        let mut map = BTreeMap::new();
+        map.insert("age_identity".to_string(), "/keys/id.txt".to_string());
+
+        let config = Config::from_map(&map);
+
+        assert_eq!(config.age_identity.as_deref(), Some("/keys/id.txt"));
+    }
+
+    #[test]
+    fn test_parses_composite_actions() {
+        let mut map = BTreeMap::new();
+        map.insert(
+            "action.ship_it".to_string(),
+            r#"{"steps":[{"action":"send_keys","params":{"pane_id":"${pane}","text":"git push"}}]}"#.to_string(),
+        );
+        map.insert("action.broken".to_string(), "{".to_string());
+
+        let config = Config::from_map(&map);
+
+        assert_eq!(config.composite_actions.len(), 1);
+        assert_eq!(config.composite_action("ship_it").unwrap().steps[0].action, "send_keys");
+        assert!(config.composite_action("broken").is_none());
+        assert!(config.errors["action.broken"].starts_with("invalid composite action broken"));
+    }
+
+    #[test]
+    fn test_parses_chaos_settings() {
+        let mut map = BTreeMap::new();
+        map.insert("chaos.drop_percent".to_string(), "10".to_string());
+        map.insert("chaos.max_delay_ms".to_string(), "250".to_string());
+        map.insert("chaos.seed".to_string(), "42".to_string());
+
+        let config = Config::from_map(&map);
+
+        assert_eq!(config.chaos_drop_percent, 10);
+        assert_eq!(config.chaos_max_delay_ms, 250);
+        assert_eq!(config.chaos_seed, 42);
+    }
+
+    #[test]
+    fn test_parses_agent_kinds() {
+        let mut map = BTreeMap::new();
+        map.insert("kind.goose".to_string(), r#"{"aliases": ["gs"], "command": "goose session"}"#.to_string());
+        map.insert("kind.broken".to_string(), "{".to_string());
+
+        let config = Config::from_map(&map);
+
+        assert_eq!(config.agent_kinds.len(), 1);
+        assert_eq!(config.agent_kinds[0].name, "goose");
+        assert_eq!(config.agent_kinds[0].aliases, vec!["gs"]);
+        assert_eq!(config.errors.keys().collect::<Vec<_>>(), vec!["kind.broken"]);
+    }
+
+    #[test]
+    fn test_parses_locale_and_render_mode() {
+        let mut map = BTreeMap::new();
+        map.insert("locale".to_string(), "fr_FR.UTF-8".to_string());
+        assert_eq!(Config::from_map(&map).locale, Locale::Fr);
+
+        map.insert("locale".to_string(), "tlh".to_string());
+        assert_eq!(Config::from_map(&map).locale, Locale::En);
+        assert_eq!(Config::from_map(&map).render_mode, RenderMode::Standard);
+
+        map.insert("render_mode".to_string(), "accessible".to_string());
+        assert_eq!(Config::from_map(&map).render_mode, RenderMode::Accessible);
+    }
+
+    #[test]
+    fn test_parses_glyphs() {
+        let mut map = BTreeMap::new();
+        map.insert("glyphs".to_string(), "ascii".to_string());
+        map.insert("glyph.idle".to_string(), "+".to_string());
+        map.insert("glyph.error".to_string(), String::new());
+
+        let config = Config::from_map(&map);
+
+        assert!(config.ascii_glyphs);
+        assert_eq!(config.status_glyphs.len(), 1);
+        assert_eq!(config.status_glyphs["idle"], "+");
+    }
+
+    #[test]
+    fn test_parses_memory_budget() {
+        let mut map = BTreeMap::new();
+        map.insert("memory.output".to_string(), "1048576".to_string());
+        map.insert("memory.history".to_string(), "lots".to_string());
+
+        let config = Config::from_map(&map);
+
+        assert_eq!(config.memory.output, 1048576);
+        assert_eq!(config.memory.history, MemoryBudget::default().history);
+    }
+
+    #[test]
+    fn test_parses_safe_word() {
+        let mut map = BTreeMap::new();
+        map.insert("safe_word".to_string(), String::new());
+        assert_eq!(Config::from_map(&map).safe_word, None);
+
+        map.insert("safe_word".to_string(), "#nzm-stop".to_string());
+        assert_eq!(Config::from_map(&map).safe_word.as_deref(), Some("#nzm-stop"));
+    }
+
+    #[test]
+    fn test_parses_idle_policy() {
+        let mut map = BTreeMap::new();
+        map.insert("idle_action".to_string(), "close".to_string());
+        assert_eq!(Config::from_map(&map).idle, None);
+
+        map.insert("idle_timeout_secs".to_string(), "600".to_string());
+        assert_eq!(Config::from_map(&map).idle, Some(IdlePolicy { timeout_secs: 600, action: IdleAction::Close }));
+
+        map.insert("idle_action".to_string(), "hibernate".to_string());
+        assert_eq!(Config::from_map(&map).idle.unwrap().action, IdleAction::Notify);
+    }
+
+    #[test]
+    fn test_parses_dispatch_hours() {
+        let mut map = BTreeMap::new();
+        map.insert("dispatch_hours".to_string(), "09:00-17:00".to_string());
+        map.insert("dispatch_hours.nightly".to_string(), "22:00-06:00".to_string());
+        map.insert("dispatch_hours.broken".to_string(), "evenings".to_string());
+        map.insert("utc_offset".to_string(), "-05:00".to_string());
+        let config = Config::from_map(&map);
+
+        assert_eq!(config.dispatch_hours.len(), 2);
+        assert_eq!(config.dispatch_hours[&Some("nightly".to_string())].to_string(), "22:00-06:00");
+        assert_eq!(config.utc_offset_minutes, -300);
+    }
+
+    #[test]
+    fn test_parses_schedules() {
+        let mut map = BTreeMap::new();
+        map.insert("schedule.deps".to_string(), r#"{"cron": "@daily", "action": "update_deps", "jitter_secs": 60}"#.to_string());
+        map.insert("schedule.broken".to_string(), r#"{"cron": "daily", "action": "x"}"#.to_string());
+        let config = Config::from_map(&map);
+
+        assert_eq!(config.schedules.len(), 1);
+        assert_eq!((config.schedules[0].name.as_str(), config.schedules[0].jitter_secs), ("deps", 60));
+    }
+
+    #[test]
+    fn test_parses_guards() {
+        let mut map = BTreeMap::new();
+        map.insert("guard.no_conflicts".to_string(), r#"{"when": "conflicts"}"#.to_string());
+        map.insert("guard.broken".to_string(), r#"{"when": "never"}"#.to_string());
+
+        let config = Config::from_map(&map);
+
+        assert_eq!(config.guards.len(), 1);
+        assert_eq!(config.guards[0].name, "no_conflicts");
+        assert!(config.errors["guard.broken"].starts_with("invalid guard broken"));
+    }
+
+    #[test]
+    fn test_parses_title_schema() {
+        let mut map = BTreeMap::new();
+        assert_eq!(Config::from_map(&map).title_format, "{project}__{kind}_{index}");
+
+        map.insert("title_format".to_string(), "{kind}/{project}#{index}".to_string());
+        map.insert("title_pattern".to_string(), "^(?P<kind>\\w+)$".to_string());
+        let config = Config::from_map(&map);
+
+        assert_eq!(config.title_format, "{kind}/{project}#{index}");
+        assert!(config.title_pattern.is_some());
+    }
+
+    #[test]
+    fn test_parses_send_pace() {
+        let mut map = BTreeMap::new();
+        map.insert("send_pace_ms".to_string(), "5".to_string());
+        assert_eq!(Config::from_map(&map).send_pace_ms, 5);
+
+        map.insert("send_pace_ms".to_string(), "fast".to_string());
+        assert_eq!(Config::from_map(&map).send_pace_ms, DEFAULT_SEND_PACE_MS);
+    }
+
+    #[test]
+    fn test_parses_write_reservation_ttl() {
+        let mut map = BTreeMap::new();
+        assert_eq!(Config::from_map(&map).write_reservation_ttl_secs, DEFAULT_WRITE_RESERVATION_TTL_SECS);
+        map.insert("write_reservation_ttl_secs".to_string(), "5".to_string());
+        assert_eq!(Config::from_map(&map).write_reservation_ttl_secs, 5);
+    }
+
+    #[test]
+    fn test_parses_task_history() {
+        let mut map = BTreeMap::new();
+        assert_eq!(Config::from_map(&map).task_history, DEFAULT_TASK_HISTORY);
+        map.insert("task_history".to_string(), "10".to_string());
+        assert_eq!(Config::from_map(&map).task_history, 10);
+    }
+
+    #[test]
+    fn test_parses_status_lines() {
+        let mut map = BTreeMap::new();
+        assert_eq!(Config::from_map(&map).status_lines, DEFAULT_STATUS_LINES);
+        map.insert("status_lines".to_string(), "5".to_string());
+        assert_eq!(Config::from_map(&map).status_lines, 5);
+    }
+
+    #[test]
+    fn test_parses_dashboard_git() {
+        let mut map = BTreeMap::new();
+        assert!(!Config::from_map(&map).dashboard_git);
+        map.insert("dashboard_git".to_string(), "true".to_string());
+        assert!(Config::from_map(&map).dashboard_git);
+    }
+}
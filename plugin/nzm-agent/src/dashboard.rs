@@ -0,0 +1,328 @@
+//! Text drawn in the plugin's own pane
+//!
+//! Below the status line, one line per agent pane gives its title and the
+//! status its kind's patterns recognise. The accessible render mode drops
+//! color and glyphs for spelled-out labels and orders agents by title, so
+//! screen readers and monochrome terminals get stable plain text.
+//!
+//! Standard mode's glyphs can be replaced per status with `glyph.<status>`
+//! config keys (`glyph.default` for the rest), and `glyphs = "ascii"`
+//! switches the defaults to ASCII for fonts that show the symbols as boxes.
+//!
+//! With `dashboard_git`, each agent line also names the branch of the
+//! agent's directory, marked `*` when it has uncommitted changes. The last
+//! known git info is shown; housekeeping refreshes it in the background.
+
+#[cfg(any(target_arch = "wasm32", test))]
+use crate::i18n::{text, Message};
+#[cfg(any(target_arch = "wasm32", test))]
+use crate::state::State;
+#[cfg(any(target_arch = "wasm32", test))]
+use crate::clock;
+#[cfg(any(target_arch = "wasm32", test))]
+use crate::git::GitInfo;
+
+/// How the dashboard is drawn
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum RenderMode {
+    /// Colored status glyphs, agents in pane order
+    #[default]
+    Standard,
+    /// Plain labeled text, agents sorted by title
+    Accessible,
+}
+
+impl RenderMode {
+    /// Parse the `render_mode` config value
+    pub fn parse(name: &str) -> Option<RenderMode> {
+        match name {
+            "standard" => Some(RenderMode::Standard),
+            "accessible" => Some(RenderMode::Accessible),
+            _ => None,
+        }
+    }
+}
+
+/// Default glyphs by status; `default` covers every other status
+#[cfg(any(target_arch = "wasm32", test))]
+const UNICODE_GLYPHS: &[(&str, &str)] = &[("idle", "●"), ("working", "◐"), ("error", "✖"), ("default", "○")];
+#[cfg(any(target_arch = "wasm32", test))]
+const ASCII_GLYPHS: &[(&str, &str)] = &[("idle", "*"), ("working", "~"), ("error", "!"), ("default", "?")];
+
+/// Glyph for a status: a configured one, else the default set's
+#[cfg(any(target_arch = "wasm32", test))]
+fn status_glyph<'a>(state: &'a State, status: Option<&str>) -> &'a str {
+    let config = state.config();
+    let status = status.unwrap_or("default");
+    let defaults = if config.ascii_glyphs { ASCII_GLYPHS } else { UNICODE_GLYPHS };
+    let default = |key: &str| defaults.iter().find(|(s, _)| *s == key).map(|(_, g)| *g);
+    config
+        .status_glyphs
+        .get(status)
+        .map(|g| g.as_str())
+        .or_else(|| default(status))
+        .or_else(|| config.status_glyphs.get("default").map(|g| g.as_str()))
+        .or_else(|| default("default"))
+        .unwrap_or("?")
+}
+
+/// One agent line's content before rendering
+#[cfg(any(target_arch = "wasm32", test))]
+struct AgentLine<'a> {
+    pane_id: u32,
+    title: &'a str,
+    status: Option<&'a str>,
+    paused: bool,
+    held: bool,
+    git: Option<&'a GitInfo>,
+}
+
+/// The status line: plugin name and how many panes are tracked
+#[cfg(any(target_arch = "wasm32", test))]
+pub fn status_line(state: &State) -> String {
+    let locale = state.config().locale;
+    format!(
+        "{} | {}: {}",
+        text(locale, Message::Title),
+        text(locale, Message::Panes),
+        state.panes().len()
+    )
+}
+
+/// The whole dashboard, at most `rows` lines of at most `cols` characters
+#[cfg(any(target_arch = "wasm32", test))]
+pub fn render(state: &State, rows: usize, cols: usize) -> Vec<String> {
+    let locale = state.config().locale;
+    let mode = state.config().render_mode;
+    let now = clock::now_ms();
+    let mut agents: Vec<AgentLine> = state
+        .panes()
+        .iter()
+        .filter_map(|pane| {
+            let (name, _) = state.agent_name(pane)?;
+            Some(AgentLine {
+                pane_id: pane.id,
+                title: &pane.title,
+                status: state.kinds().status(&name.kind, state.pane_lines(pane.id)),
+                paused: state.automation_paused(name.project.as_deref()).is_some(),
+                held: state.queue_held(name.project.as_deref(), now).is_some(),
+                git: state
+                    .config()
+                    .dashboard_git
+                    .then(|| state.agent_dir(pane))
+                    .flatten()
+                    .and_then(|dir| state.last_git_info(&dir)),
+            })
+        })
+        .collect();
+    if mode == RenderMode::Accessible {
+        agents.sort_by(|a, b| a.title.cmp(b.title).then(a.pane_id.cmp(&b.pane_id)));
+    }
+
+    let mut lines = vec![truncate(&status_line(state), cols)];
+    for agent in agents {
+        let status = agent.status.unwrap_or(text(locale, Message::Unknown));
+        let paused = if agent.paused {
+            format!(" ({})", text(locale, Message::Paused))
+        } else if agent.held {
+            format!(" ({})", text(locale, Message::QueueHeld))
+        } else {
+            String::new()
+        };
+        // A detached HEAD has no branch to name
+        let branch = agent.git.map(|git| (git.branch.as_deref().unwrap_or("HEAD"), git.dirty));
+        let line = match mode {
+            RenderMode::Accessible => {
+                let git = match branch {
+                    Some((branch, true)) => format!(
+                        ", {} {}, {}",
+                        text(locale, Message::Branch),
+                        branch,
+                        text(locale, Message::Dirty)
+                    ),
+                    Some((branch, false)) => format!(", {} {}", text(locale, Message::Branch), branch),
+                    None => String::new(),
+                };
+                truncate(
+                    &format!(
+                        "{} {}, {} {}: {}{}{}",
+                        text(locale, Message::Agent),
+                        agent.title,
+                        text(locale, Message::Pane),
+                        agent.pane_id,
+                        status,
+                        paused,
+                        git
+                    ),
+                    cols,
+                )
+            }
+            RenderMode::Standard => {
+                let git = match branch {
+                    Some((branch, dirty)) => format!("  {}{}", branch, if dirty { "*" } else { "" }),
+                    None => String::new(),
+                };
+                // Width is measured without the color codes
+                let glyph = status_glyph(state, agent.status);
+                let plain = truncate(&format!("{} {}  {}{}{}", glyph, agent.title, status, paused, git), cols);
+                match plain.strip_prefix(glyph) {
+                    Some(rest) => format!("\u{1b}[{}m{}\u{1b}[0m{}", status_color(agent.status), glyph, rest),
+                    None => plain,
+                }
+            }
+        };
+        lines.push(line);
+    }
+    lines.truncate(rows.max(1));
+    lines
+}
+
+/// Dashboard content as last drawn, so updates that leave it unchanged
+/// can skip the redraw
+#[cfg(any(target_arch = "wasm32", test))]
+#[derive(Debug, Default)]
+pub struct LastDrawn {
+    lines: Vec<String>,
+}
+
+#[cfg(any(target_arch = "wasm32", test))]
+impl LastDrawn {
+    /// Whether the dashboard now differs from what was last drawn
+    ///
+    /// Compares the content at an unlimited size, so a resize does not
+    /// count; Zellij redraws on resize by itself.
+    pub fn changed(&mut self, state: &State) -> bool {
+        let lines = render(state, usize::MAX, usize::MAX);
+        if lines == self.lines {
+            return false;
+        }
+        self.lines = lines;
+        true
+    }
+}
+
+/// ANSI foreground color of a status
+#[cfg(any(target_arch = "wasm32", test))]
+fn status_color(status: Option<&str>) -> u8 {
+    match status {
+        Some("idle") | Some("ready") => 32,
+        Some("working") | Some("busy") => 33,
+        Some("error") | Some("failed") => 31,
+        _ => 90,
+    }
+}
+
+/// Cut a line to `cols` characters
+#[cfg(any(target_arch = "wasm32", test))]
+fn truncate(line: &str, cols: usize) -> String {
+    line.chars().take(cols).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+    use crate::i18n::Locale;
+    use crate::kinds::AgentKind;
+    use zellij_tile::prelude::{PaneInfo, PaneManifest};
+
+    fn state_with_agents() -> State {
+        let mut state = State::default();
+        let cc = AgentKind::parse("cc", r#"{"command": "claude", "status": [{"state": "idle", "regex": "^> $"}]}"#).unwrap();
+        state.set_config(Config { agent_kinds: vec![cc], ..Config::default() });
+        let mut manifest = PaneManifest::default();
+        manifest.panes.insert(0, vec![
+            PaneInfo { id: 4, title: "proj__cc_2".to_string(), ..Default::default() },
+            PaneInfo { id: 7, title: "shell".to_string(), ..Default::default() },
+            PaneInfo { id: 2, title: "proj__cc_1".to_string(), ..Default::default() },
+        ]);
+        state.update_panes(manifest);
+        state.update_pane_contents(2, vec!["> ".to_string()]);
+        state
+    }
+
+    #[test]
+    fn test_status_line_is_localized() {
+        let mut state = State::default();
+        assert_eq!(status_line(&state), "NZM Agent | Panes: 0");
+
+        state.set_config(Config { locale: Locale::De, ..Config::default() });
+        assert_eq!(status_line(&state), "NZM-Agent | Bereiche: 0");
+    }
+
+    #[test]
+    fn test_standard_mode_colors_glyphs() {
+        let state = state_with_agents();
+        let lines = render(&state, 10, 80);
+
+        assert_eq!(lines.len(), 3);
+        assert_eq!(lines[1], "\u{1b}[90m○\u{1b}[0m proj__cc_2  unknown");
+        assert_eq!(lines[2], "\u{1b}[32m●\u{1b}[0m proj__cc_1  idle");
+    }
+
+    #[test]
+    fn test_configured_and_ascii_glyphs() {
+        let mut state = state_with_agents();
+        let mut config = state.config().clone();
+        config.ascii_glyphs = true;
+        config.status_glyphs.insert("idle".to_string(), "OK".to_string());
+        state.set_config(config);
+
+        let lines = render(&state, 10, 80);
+
+        assert_eq!(lines[1], "\u{1b}[90m?\u{1b}[0m proj__cc_2  unknown");
+        assert_eq!(lines[2], "\u{1b}[32mOK\u{1b}[0m proj__cc_1  idle");
+    }
+
+    #[test]
+    fn test_accessible_mode_is_plain_and_sorted() {
+        let mut state = state_with_agents();
+        let mut config = state.config().clone();
+        config.render_mode = RenderMode::Accessible;
+        state.set_config(config);
+        state.pause_automation(Some("proj".to_string()), "test");
+
+        let lines = render(&state, 10, 80);
+
+        assert_eq!(lines[1], "Agent proj__cc_1, pane 2: idle (paused)");
+        assert_eq!(lines[2], "Agent proj__cc_2, pane 4: unknown (paused)");
+        assert!(lines.iter().all(|line| !line.contains('\u{1b}')));
+        assert_eq!(render(&state, 1, 9), vec!["NZM Agent"]);
+    }
+
+    #[test]
+    fn test_redraw_only_when_content_changes() {
+        let mut state = state_with_agents();
+        let mut drawn = LastDrawn::default();
+        assert!(drawn.changed(&state));
+
+        // Output that leaves every status as it was
+        state.update_pane_contents(4, vec!["thinking".to_string()]);
+        assert!(!drawn.changed(&state));
+
+        state.update_pane_contents(2, vec!["working".to_string()]);
+        assert!(drawn.changed(&state));
+        assert!(!drawn.changed(&state));
+    }
+
+    #[test]
+    fn test_dashboard_git_shows_branch() {
+        let mut state = state_with_agents();
+        state.cache_git_info("/data/projects/proj", GitInfo {
+            branch: Some("main".to_string()),
+            dirty: true,
+            ..GitInfo::default()
+        });
+        assert_eq!(render(&state, 10, 80)[2], "\u{1b}[32m●\u{1b}[0m proj__cc_1  idle");
+
+        let mut config = state.config().clone();
+        config.dashboard_git = true;
+        state.set_config(config);
+        assert_eq!(render(&state, 10, 80)[2], "\u{1b}[32m●\u{1b}[0m proj__cc_1  idle  main*");
+
+        let mut config = state.config().clone();
+        config.render_mode = RenderMode::Accessible;
+        state.set_config(config);
+        assert_eq!(render(&state, 10, 80)[1], "Agent proj__cc_1, pane 2: idle, branch main, uncommitted changes");
+    }
+}
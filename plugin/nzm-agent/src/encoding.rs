@@ -0,0 +1,89 @@
+//! Safe rendering of captured bytes that may not be valid UTF-8
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use serde::{Deserialize, Serialize};
+
+use crate::ipc::{Error, ErrorCode};
+
+/// How raw output bytes are rendered into a JSON response
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum OutputEncoding {
+    /// UTF-8 text; invalid sequences become U+FFFD and `lossy` is set
+    #[default]
+    Text,
+    /// Raw bytes, base64-encoded
+    Base64,
+}
+
+/// Output bytes rendered for a response
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EncodedOutput {
+    pub content: String,
+    pub encoding: OutputEncoding,
+    /// True when invalid UTF-8 was replaced in text mode
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    pub lossy: bool,
+}
+
+/// Decode base64 bytes given in a request param
+pub fn decode_base64(param: &str, content: &str) -> Result<Vec<u8>, Error> {
+    STANDARD
+        .decode(content)
+        .map_err(|e| Error::new(ErrorCode::InvalidParams, format!("invalid params: {} is not base64: {}", param, e)))
+}
+
+/// Render bytes in the requested encoding without ever failing
+pub fn encode_output(bytes: &[u8], encoding: OutputEncoding) -> EncodedOutput {
+    match encoding {
+        OutputEncoding::Text => {
+            let text = String::from_utf8_lossy(bytes);
+            EncodedOutput {
+                lossy: matches!(text, std::borrow::Cow::Owned(_)),
+                content: text.into_owned(),
+                encoding,
+            }
+        }
+        OutputEncoding::Base64 => EncodedOutput {
+            content: STANDARD.encode(bytes),
+            encoding,
+            lossy: false,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_valid_utf8_is_not_lossy() {
+        let out = encode_output("héllo".as_bytes(), OutputEncoding::Text);
+        assert_eq!(out.content, "héllo");
+        assert!(!out.lossy);
+    }
+
+    #[test]
+    fn test_latin1_bytes_replaced_with_flag() {
+        // "caf\xe9" is Latin-1 for "café"
+        let out = encode_output(b"caf\xe9", OutputEncoding::Text);
+        assert_eq!(out.content, "caf\u{fffd}");
+        assert!(out.lossy);
+    }
+
+    #[test]
+    fn test_base64_roundtrip_preserves_bytes() {
+        let raw = b"\x00\xff\x1b[0m";
+        let out = encode_output(raw, OutputEncoding::Base64);
+        assert_eq!(out.encoding, OutputEncoding::Base64);
+        assert_eq!(STANDARD.decode(&out.content).unwrap(), raw);
+    }
+
+    #[test]
+    fn test_lossy_flag_skipped_when_false() {
+        let json = serde_json::to_value(encode_output(b"ok", OutputEncoding::Text)).unwrap();
+        assert!(json.get("lossy").is_none());
+        assert_eq!(json["encoding"], "text");
+    }
+}
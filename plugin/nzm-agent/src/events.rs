@@ -0,0 +1,112 @@
+//! Numbered log of recent events, for `next_event` long polls
+//!
+//! Every event raised for mirror_state subscribers is also kept here with a
+//! sequence number, so a client that cannot hold a stream open, such as a
+//! shell script running `zellij pipe`, can ask for the first event after
+//! the last one it saw. The request waits for a matching event or its
+//! timeout, whichever comes first.
+//!
+//! The log keeps the last `EVENT_LOG_CAP` events. A client that falls
+//! further behind gets the oldest kept event, marked as following a gap.
+
+use std::collections::VecDeque;
+use std::mem::size_of;
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// Events kept for polling clients
+pub const EVENT_LOG_CAP: usize = 1000;
+
+/// Seconds next_event waits when the request does not say
+pub const DEFAULT_POLL_TIMEOUT_SECS: u64 = 30;
+
+/// Longest wait next_event accepts
+pub const MAX_POLL_TIMEOUT_SECS: u64 = 3600;
+
+/// An event and its place in the log
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct LoggedEvent {
+    pub seq: u64,
+    #[serde(rename = "type")]
+    pub kind: String,
+    pub event: Value,
+}
+
+/// Recent events, oldest first
+#[derive(Debug, Default)]
+pub struct EventLog {
+    events: VecDeque<LoggedEvent>,
+    last_seq: u64,
+}
+
+impl EventLog {
+    /// Log an event, returning its sequence number
+    pub fn push(&mut self, kind: &str, event: Value) -> u64 {
+        self.last_seq += 1;
+        self.events.push_back(LoggedEvent { seq: self.last_seq, kind: kind.to_string(), event });
+        while self.events.len() > EVENT_LOG_CAP {
+            self.events.pop_front();
+        }
+        self.last_seq
+    }
+
+    /// Sequence number of the latest event, 0 before the first
+    pub fn last_seq(&self) -> u64 {
+        self.last_seq
+    }
+
+    /// The first event after `seq` whose type is in `kinds` (any type when
+    /// empty), and whether events after `seq` were dropped from the log
+    pub fn next_after(&self, seq: u64, kinds: &[String]) -> Option<(&LoggedEvent, bool)> {
+        let gap = self.events.front().is_some_and(|first| first.seq > seq + 1);
+        self.events
+            .iter()
+            .find(|e| e.seq > seq && (kinds.is_empty() || kinds.contains(&e.kind)))
+            .map(|e| (e, gap))
+    }
+
+    /// Kept events, oldest first
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &LoggedEvent> {
+        self.events.iter()
+    }
+
+    /// Estimated heap bytes of the kept events
+    pub fn approx_bytes(&self) -> usize {
+        self.events
+            .iter()
+            .map(|e| size_of::<LoggedEvent>() + e.kind.len() + e.event.to_string().len())
+            .sum()
+    }
+}
+
+/// A next_event request waiting for an event
+#[derive(Debug, Clone)]
+pub struct PendingPoll {
+    pub request_id: String,
+    pub cli_id: String,
+    pub after: u64,
+    pub kinds: Vec<String>,
+    /// Milliseconds since the Unix epoch
+    pub deadline_ms: u64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_next_after_filters_and_reports_gaps() {
+        let mut log = EventLog::default();
+        for i in 0..EVENT_LOG_CAP + 2 {
+            let kind = if i % 2 == 0 { "agent_ready" } else { "handover" };
+            log.push(kind, serde_json::json!({ "i": i }));
+        }
+
+        let (event, gap) = log.next_after(0, &[]).unwrap();
+        assert_eq!((event.seq, gap), (3, true));
+        let (event, gap) = log.next_after(10, &["handover".to_string()]).unwrap();
+        assert_eq!((event.seq, event.kind.as_str(), gap), (12, "handover", false));
+        assert!(log.next_after(log.last_seq(), &[]).is_none());
+    }
+}
@@ -0,0 +1,169 @@
+//! Sending one prompt to several agents and collecting the replies
+//!
+//! `fanout` delivers the same prompt to each target agent and holds its
+//! response until the turn each prompt starts is complete, or until
+//! `timeout_secs` pass. The replies then come back side by side, one entry
+//! per agent, to compare agents or models on the same task.
+//!
+//! Prompts are journaled as tasks, and a turn is matched to its agent by
+//! task id. An agent still launching gets the prompt once it is ready, and
+//! one with automation paused once it resumes; either may time out first.
+//!
+//! With `judge`, the replies then go to a reviewer agent through the
+//! `fanout_judge_template` config key, which takes `{prompt}` and
+//! `{candidates}`: the replies as numbered markdown sections. The judge
+//! gets the same timeout again, and its pick is read from the last line of
+//! its reply of the form `PICK: <number>`.
+
+use crate::clock;
+use crate::turns::{self, TurnStatus};
+
+/// Seconds a fanout waits for replies when the request does not say
+pub const DEFAULT_FANOUT_TIMEOUT_SECS: u64 = 600;
+
+pub const DEFAULT_JUDGE_TEMPLATE: &str = "Several agents were given the same task. Compare their replies \
+     and pick the best one.\n\nThe task:\n\n{prompt}\n\n{candidates}\n\nGive your reasons briefly, \
+     then end with a line of the form PICK: <number>.";
+
+/// The reviewer of a fanout's replies
+#[derive(Debug, Clone)]
+pub struct FanoutJudge {
+    pub pane_id: u32,
+    pub template: String,
+    /// Whether the replies went to the judge, and the task carrying them
+    pub asked: bool,
+    pub task_id: Option<String>,
+}
+
+/// A fanout request waiting for its replies
+#[derive(Debug, Clone)]
+pub struct PendingFanout {
+    pub request_id: String,
+    pub cli_id: Option<String>,
+    pub prompt: String,
+    /// Target panes and the task that carries the prompt to each
+    pub targets: Vec<(u32, Option<String>)>,
+    pub judge: Option<FanoutJudge>,
+    pub timeout_secs: u64,
+    /// Milliseconds since the Unix epoch
+    pub started_ms: u64,
+    pub deadline_ms: u64,
+}
+
+impl PendingFanout {
+    /// A fanout whose prompts just went out
+    pub fn new(request_id: &str, cli_id: Option<&str>, prompt: String, targets: Vec<(u32, Option<String>)>, timeout_secs: u64) -> Self {
+        let mut pending = PendingFanout {
+            request_id: request_id.to_string(),
+            cli_id: cli_id.map(String::from),
+            prompt,
+            targets,
+            judge: None,
+            timeout_secs,
+            started_ms: clock::now_ms(),
+            deadline_ms: 0,
+        };
+        pending.extend(pending.started_ms);
+        pending
+    }
+
+    /// Whether every target, or the judge once asked, has replied, or the
+    /// timeout has passed
+    ///
+    /// `status` gives the status of the turn carrying a task, if it began.
+    /// A prompt that could not be journaled has no reply to wait for.
+    pub fn is_done(&self, now_ms: u64, status: impl Fn(&str) -> Option<TurnStatus>) -> bool {
+        let replied = |task_id: &Option<String>| {
+            task_id.as_deref().is_none_or(|task_id| status(task_id) == Some(TurnStatus::Complete))
+        };
+        now_ms >= self.deadline_ms
+            || match &self.judge {
+                Some(judge) if judge.asked => replied(&judge.task_id),
+                _ => self.targets.iter().all(|(_, task_id)| replied(task_id)),
+            }
+    }
+
+    /// Restart the timeout, for the judge's turn
+    pub fn extend(&mut self, now_ms: u64) {
+        self.deadline_ms = now_ms.saturating_add(self.timeout_secs.saturating_mul(1000));
+    }
+}
+
+/// The prompt asking a judge to compare replies
+///
+/// `candidates` holds the title and reply of each target, in order. Text
+/// put in for one placeholder is never taken for the other.
+pub fn judge_prompt(template: &str, prompt: &str, candidates: &[(String, Option<String>)]) -> String {
+    let candidates = candidates
+        .iter()
+        .enumerate()
+        .map(|(i, (title, reply))| {
+            let reply = match reply {
+                Some(reply) => turns::code_block(reply),
+                None => "(no reply)".to_string(),
+            };
+            format!("## Candidate {} ({})\n\n{}", i + 1, title, reply)
+        })
+        .collect::<Vec<_>>()
+        .join("\n\n");
+    template
+        .split("{candidates}")
+        .map(|part| part.replace("{prompt}", prompt))
+        .collect::<Vec<_>>()
+        .join(&candidates)
+}
+
+/// The candidate a judge picked, numbered from 1, from its last pick line
+#[cfg(any(target_arch = "wasm32", test))]
+pub fn parse_pick(reply: &str, candidates: usize) -> Option<usize> {
+    reply.lines().rev().find_map(|line| {
+        let line = line.trim().trim_matches('*').trim();
+        let rest = line.get(..5).filter(|head| head.eq_ignore_ascii_case("pick:")).map(|_| &line[5..])?;
+        let digits: String = rest.trim_start().chars().take_while(char::is_ascii_digit).collect();
+        digits.parse().ok().filter(|n| (1..=candidates).contains(n))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_done_when_all_complete_or_timed_out() {
+        let mut pending = PendingFanout {
+            request_id: "1".to_string(),
+            cli_id: None,
+            prompt: "name a color".to_string(),
+            targets: vec![(1, Some("t1".to_string())), (2, Some("t2".to_string())), (3, None)],
+            judge: None,
+            timeout_secs: 1,
+            started_ms: 0,
+            deadline_ms: 100,
+        };
+        let one_open = |task: &str| match task {
+            "t1" => Some(TurnStatus::Complete),
+            _ => Some(TurnStatus::Open),
+        };
+
+        assert!(!pending.is_done(50, one_open));
+        assert!(!pending.is_done(50, |_| None));
+        assert!(pending.is_done(50, |_| Some(TurnStatus::Complete)));
+        assert!(pending.is_done(100, one_open));
+
+        pending.judge = Some(FanoutJudge { pane_id: 4, template: String::new(), asked: true, task_id: Some("t1".to_string()) });
+        assert!(pending.is_done(50, one_open));
+        pending.extend(50);
+        assert_eq!(pending.deadline_ms, 1050);
+    }
+
+    #[test]
+    fn test_judge_prompt_and_pick() {
+        let candidates = vec![("a__cc_1".to_string(), Some("red".to_string())), ("a__cc_2".to_string(), None)];
+        let prompt = judge_prompt("{prompt}|{candidates}", "say {candidates}", &candidates);
+        assert_eq!(prompt, "say {candidates}|## Candidate 1 (a__cc_1)\n\n```text\nred\n```\n\n## Candidate 2 (a__cc_2)\n\n(no reply)");
+
+        assert_eq!(parse_pick("PICK: 1\nreasons\n**Pick: 2**", 2), Some(2));
+        assert_eq!(parse_pick("PICK: <number>\npick: 3", 2), None);
+        assert_eq!(parse_pick("no pick", 2), None);
+    }
+}
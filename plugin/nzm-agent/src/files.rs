@@ -0,0 +1,133 @@
+//! Moving files in and out of agent project directories on the host
+//!
+//! The plugin cannot see the host filesystem outside its sandbox, so file
+//! transfers run as host commands in the agent's directory: where the
+//! plugin opened its pane, or `<projects_base>/<project>`. Paths are
+//! relative to that directory and may not leave it.
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+
+use crate::ipc::{Error, ErrorCode};
+
+/// Largest file put_file accepts
+pub const MAX_FILE_BYTES: usize = 1024 * 1024;
+
+/// Base64 is passed as several arguments; Linux caps each one at 128 KiB
+#[cfg(any(target_arch = "wasm32", test))]
+const ARG_CHUNK: usize = 64 * 1024;
+
+/// Check that `path` is relative and stays inside the project directory
+pub fn check_relative_path(path: &str) -> Result<(), Error> {
+    if path.is_empty() || path.starts_with('/') {
+        return Err(Error::new(ErrorCode::InvalidParams, format!("invalid params: path must be relative: {:?}", path)));
+    }
+    if path.split('/').any(|part| part == "..") {
+        return Err(Error::new(ErrorCode::InvalidParams, format!("invalid params: path may not contain '..': {}", path)));
+    }
+    Ok(())
+}
+
+/// Directory of a project under the configured base
+pub fn project_dir(base: &str, project: &str) -> Result<String, Error> {
+    if project.is_empty() || project.contains('/') || project == "." || project == ".." {
+        return Err(Error::new(ErrorCode::InvalidParams, format!("invalid params: bad project name: {:?}", project)));
+    }
+    Ok(format!("{}/{}", base.trim_end_matches('/'), project))
+}
+
+/// Decode base64 file content, enforcing the size limit
+pub fn decode_content(content: &str) -> Result<Vec<u8>, Error> {
+    let bytes = STANDARD
+        .decode(content)
+        .map_err(|e| Error::new(ErrorCode::InvalidParams, format!("invalid params: content is not base64: {}", e)))?;
+    if bytes.len() > MAX_FILE_BYTES {
+        return Err(Error::new(
+            ErrorCode::InvalidParams,
+            format!("invalid params: content is {} bytes, the limit is {}", bytes.len(), MAX_FILE_BYTES),
+        ));
+    }
+    Ok(bytes)
+}
+
+/// Encode file content for put_file
+pub fn encode_content(bytes: &[u8]) -> String {
+    STANDARD.encode(bytes)
+}
+
+/// Command line that writes base64 `content` to `path` (run in the project
+/// directory), creating parent directories and replacing the file atomically
+#[cfg(any(target_arch = "wasm32", test))]
+pub fn put_file_command(path: &str, content: &str) -> Vec<String> {
+    let mut args = vec![
+        "sh".to_string(),
+        "-c".to_string(),
+        "set -e; p=\"$1\"; shift; mkdir -p \"$(dirname \"$p\")\"; \
+         printf '%s' \"$@\" | base64 -d > \"$p.nzm-tmp\"; mv \"$p.nzm-tmp\" \"$p\""
+            .to_string(),
+        "sh".to_string(),
+        path.to_string(),
+    ];
+    // Base64 is ASCII, so byte offsets are character boundaries
+    let mut rest = content;
+    while !rest.is_empty() {
+        let (chunk, tail) = rest.split_at(ARG_CHUNK.min(rest.len()));
+        args.push(chunk.to_string());
+        rest = tail;
+    }
+    args
+}
+
+/// Command line that prints `path` (run in the project directory), reading
+/// one byte past the limit so oversized files can be detected
+#[cfg(any(target_arch = "wasm32", test))]
+pub fn get_file_command(path: &str) -> Vec<String> {
+    vec![
+        "sh".to_string(),
+        "-c".to_string(),
+        "head -c \"$2\" < \"$1\"".to_string(),
+        "sh".to_string(),
+        path.to_string(),
+        (MAX_FILE_BYTES + 1).to_string(),
+    ]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_paths_stay_inside_the_project() {
+        assert!(check_relative_path("docs/spec.md").is_ok());
+        assert!(check_relative_path("/etc/passwd").is_err());
+        assert!(check_relative_path("a/../../b").is_err());
+        assert!(check_relative_path("").is_err());
+
+        assert_eq!(project_dir("/data/projects/", "api").unwrap(), "/data/projects/api");
+        assert!(project_dir("/data/projects", "../x").is_err());
+    }
+
+    #[test]
+    fn test_decode_content() {
+        assert_eq!(decode_content("aGk=").unwrap(), b"hi");
+        assert!(decode_content("not base64!").unwrap_err().message.contains("not base64"));
+    }
+
+    #[test]
+    fn test_put_file_command_splits_large_content() {
+        let content = "QUJD".repeat(ARG_CHUNK / 2);
+        let args = put_file_command("spec.md", &content);
+
+        assert_eq!(args[4], "spec.md");
+        assert_eq!(args.len(), 7);
+        assert_eq!(args[5..].concat(), content);
+    }
+
+    #[test]
+    fn test_get_file_command_reads_one_byte_past_the_limit() {
+        let args = get_file_command("out/result.json");
+
+        assert_eq!(args[4], "out/result.json");
+        assert_eq!(args[5], (MAX_FILE_BYTES + 1).to_string());
+    }
+}
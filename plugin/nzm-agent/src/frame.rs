@@ -0,0 +1,153 @@
+//! Fixed-size text frames of a pane's visible viewport
+//!
+//! A frame is exactly `rows` lines of exactly `cols` characters, so two
+//! frames of the same pane can be compared cell by cell. Each character
+//! counts as one column; wide characters are not measured.
+
+use serde::{Deserialize, Serialize};
+
+/// A run of cells drawn with the same SGR attributes
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AttrRun {
+    pub col: usize,
+    pub len: usize,
+    /// SGR parameters in effect, e.g. `1;32`
+    pub sgr: String,
+}
+
+/// The visible viewport of a pane as a text grid
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Frame {
+    pub rows: usize,
+    pub cols: usize,
+    pub lines: Vec<String>,
+    /// Styled runs per row, present when attributes were requested
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub attributes: Option<Vec<Vec<AttrRun>>>,
+}
+
+/// Build a frame from viewport lines (which may contain ANSI escapes)
+///
+/// Missing rows are blank; longer lines are cut at `cols`.
+pub fn build_frame(viewport: &[String], rows: usize, cols: usize, with_attributes: bool) -> Frame {
+    let mut lines = Vec::with_capacity(rows);
+    let mut attributes = Vec::with_capacity(rows);
+
+    for row in 0..rows {
+        let (text, runs) = match viewport.get(row) {
+            Some(line) => parse_line(line, cols),
+            None => (String::new(), Vec::new()),
+        };
+        let width = text.chars().count();
+        lines.push(format!("{}{}", text, " ".repeat(cols - width)));
+        attributes.push(runs);
+    }
+
+    Frame {
+        rows,
+        cols,
+        lines,
+        attributes: with_attributes.then_some(attributes),
+    }
+}
+
+/// Split a line into at most `cols` visible characters and its styled runs
+fn parse_line(line: &str, cols: usize) -> (String, Vec<AttrRun>) {
+    let mut text = String::new();
+    let mut runs: Vec<AttrRun> = Vec::new();
+    let mut sgr = String::new();
+    let mut col = 0;
+    let mut chars = line.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        if c == '\x1b' {
+            match chars.next() {
+                Some('[') => {
+                    let mut params = String::new();
+                    let mut final_byte = None;
+                    for c in chars.by_ref() {
+                        if ('@'..='~').contains(&c) {
+                            final_byte = Some(c);
+                            break;
+                        }
+                        params.push(c);
+                    }
+                    if final_byte == Some('m') {
+                        sgr = apply_sgr(&sgr, &params);
+                    }
+                }
+                Some(']') => {
+                    while let Some(c) = chars.next() {
+                        if c == '\x07' {
+                            break;
+                        }
+                        if c == '\x1b' && chars.peek() == Some(&'\\') {
+                            chars.next();
+                            break;
+                        }
+                    }
+                }
+                _ => {}
+            }
+            continue;
+        }
+        if col >= cols {
+            break;
+        }
+        text.push(c);
+        if !sgr.is_empty() {
+            match runs.last_mut() {
+                Some(run) if run.sgr == sgr && run.col + run.len == col => run.len += 1,
+                _ => runs.push(AttrRun { col, len: 1, sgr: sgr.clone() }),
+            }
+        }
+        col += 1;
+    }
+    (text, runs)
+}
+
+/// Combine the current SGR state with a new `ESC[...m` sequence
+fn apply_sgr(current: &str, params: &str) -> String {
+    if params.is_empty() || params == "0" {
+        return String::new();
+    }
+    match params.strip_prefix("0;") {
+        Some(rest) => rest.to_string(),
+        None if current.is_empty() => params.to_string(),
+        None => format!("{};{}", current, params),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|l| l.to_string()).collect()
+    }
+
+    #[test]
+    fn test_frame_pads_and_truncates() {
+        let frame = build_frame(&lines(&["hello world", "hi"]), 3, 5, false);
+
+        assert_eq!(frame.lines, vec!["hello", "hi   ", "     "]);
+        assert!(frame.attributes.is_none());
+    }
+
+    #[test]
+    fn test_frame_strips_escapes_and_records_runs() {
+        let frame = build_frame(&lines(&["\x1b[1;32mok\x1b[0m done"]), 1, 10, true);
+
+        assert_eq!(frame.lines[0], "ok done   ");
+        let runs = &frame.attributes.unwrap()[0];
+        assert_eq!(runs, &vec![AttrRun { col: 0, len: 2, sgr: "1;32".to_string() }]);
+    }
+
+    #[test]
+    fn test_sgr_accumulates_until_reset() {
+        assert_eq!(apply_sgr("1", "31"), "1;31");
+        assert_eq!(apply_sgr("1;31", "0"), "");
+        assert_eq!(apply_sgr("1", "0;4"), "4");
+        assert_eq!(apply_sgr("1", ""), "");
+    }
+}
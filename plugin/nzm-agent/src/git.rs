@@ -0,0 +1,103 @@
+//! Git branch and working tree state of a project directory
+
+use serde::Serialize;
+
+/// Summary of `git status` for a project
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct GitInfo {
+    /// Current branch; None when HEAD is detached
+    pub branch: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upstream: Option<String>,
+    pub dirty: bool,
+    /// Changed, staged, and untracked entries
+    pub changed: usize,
+    /// Unmerged paths left by a merge, rebase or cherry-pick
+    pub conflicts: usize,
+    pub ahead: u32,
+    pub behind: u32,
+}
+
+/// Command line printing machine-readable status with branch headers
+#[cfg(target_arch = "wasm32")]
+pub fn git_status_command() -> Vec<String> {
+    ["git", "status", "--porcelain=v2", "--branch"]
+        .iter()
+        .map(|a| a.to_string())
+        .collect()
+}
+
+/// Parse `git status --porcelain=v2 --branch` output
+#[cfg(any(target_arch = "wasm32", test))]
+pub fn parse_status(output: &str) -> GitInfo {
+    let mut info = GitInfo::default();
+    for line in output.lines() {
+        if let Some(head) = line.strip_prefix("# branch.head ") {
+            info.branch = (head != "(detached)").then(|| head.to_string());
+        } else if let Some(upstream) = line.strip_prefix("# branch.upstream ") {
+            info.upstream = Some(upstream.to_string());
+        } else if let Some(ab) = line.strip_prefix("# branch.ab ") {
+            for part in ab.split_whitespace() {
+                if let Some(n) = part.strip_prefix('+') {
+                    info.ahead = n.parse().unwrap_or(0);
+                } else if let Some(n) = part.strip_prefix('-') {
+                    info.behind = n.parse().unwrap_or(0);
+                }
+            }
+        } else if !line.starts_with('#') && !line.is_empty() {
+            info.changed += 1;
+            if line.starts_with("u ") {
+                info.conflicts += 1;
+            }
+        }
+    }
+    info.dirty = info.changed > 0;
+    info
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_branch_and_changes() {
+        let output = "\
+# branch.oid 1234abcd
+# branch.head feature/x
+# branch.upstream origin/feature/x
+# branch.ab +2 -1
+1 .M N... 100644 100644 100644 aaa bbb src/lib.rs
+? notes.txt
+";
+        let info = parse_status(output);
+
+        assert_eq!(info.branch.as_deref(), Some("feature/x"));
+        assert_eq!(info.upstream.as_deref(), Some("origin/feature/x"));
+        assert_eq!((info.ahead, info.behind), (2, 1));
+        assert!(info.dirty);
+        assert_eq!(info.changed, 2);
+        assert_eq!(info.conflicts, 0);
+    }
+
+    #[test]
+    fn test_parse_unmerged_entries() {
+        let output = "\
+# branch.head main
+u UU N... 100644 100644 100644 100644 aaa bbb ccc src/lib.rs
+1 M. N... 100644 100644 100644 aaa bbb README.md
+";
+        let info = parse_status(output);
+
+        assert_eq!(info.conflicts, 1);
+        assert_eq!(info.changed, 2);
+    }
+
+    #[test]
+    fn test_parse_clean_detached() {
+        let info = parse_status("# branch.oid abc\n# branch.head (detached)\n");
+
+        assert_eq!(info.branch, None);
+        assert!(!info.dirty);
+        assert_eq!((info.ahead, info.behind), (0, 0));
+    }
+}
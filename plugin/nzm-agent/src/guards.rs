@@ -0,0 +1,85 @@
+//! Pre-send guard rules declared in config
+//!
+//! A `guard.<name>` config key holds JSON naming a condition on the target
+//! pane's project and the actions it blocks:
+//!
+//! ```json
+//! {"when": "conflicts", "actions": ["dispatch_task"]}
+//! ```
+//!
+//! Git conditions use the most recent `git_info` result for the project, so
+//! a scheduler should refresh it before dispatching; with no result cached
+//! the guard does not block.
+
+use serde::{Deserialize, Serialize};
+
+/// Actions a guard blocks when none are listed
+const DEFAULT_GUARDED: &[&str] = &["send_keys", "send_raw", "enqueue_task", "dispatch_task"];
+
+/// What a guard checks about the target pane's project
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum GuardCondition {
+    /// The worktree has unmerged paths
+    Conflicts,
+    /// The worktree has uncommitted changes
+    Dirty,
+    /// The branch is behind its upstream
+    Behind,
+    /// Another pane holds the project's worktree lock
+    Locked,
+}
+
+/// A named rule refusing actions while its condition holds
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Guard {
+    #[serde(default)]
+    pub name: String,
+    pub when: GuardCondition,
+    #[serde(default)]
+    pub actions: Vec<String>,
+}
+
+impl Guard {
+    /// Parse the JSON value of a `guard.<name>` config key
+    pub fn parse(name: &str, json: &str) -> Result<Self, String> {
+        let mut guard: Guard =
+            serde_json::from_str(json).map_err(|e| format!("invalid guard {}: {}", name, e))?;
+        guard.name = name.to_string();
+        Ok(guard)
+    }
+
+    /// Whether this guard applies to `action`
+    pub fn covers(&self, action: &str) -> bool {
+        if self.actions.is_empty() {
+            DEFAULT_GUARDED.contains(&action)
+        } else {
+            self.actions.iter().any(|a| a == action)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_guard() {
+        let guard = Guard::parse("no_conflicts", r#"{"when": "conflicts", "actions": ["dispatch_task"]}"#).unwrap();
+
+        assert_eq!(guard.name, "no_conflicts");
+        assert_eq!(guard.when, GuardCondition::Conflicts);
+        assert!(guard.covers("dispatch_task"));
+        assert!(!guard.covers("send_keys"));
+        assert!(Guard::parse("x", r#"{"when": "sunny"}"#).is_err());
+    }
+
+    #[test]
+    fn test_default_actions() {
+        let guard = Guard::parse("lock", r#"{"when": "locked"}"#).unwrap();
+
+        assert!(guard.covers("send_keys"));
+        assert!(guard.covers("enqueue_task"));
+        assert!(!guard.covers("list_panes"));
+    }
+}
@@ -0,0 +1,70 @@
+//! Stable handles for pane lifetimes
+//!
+//! Zellij hands a closed pane's id to a later pane, so an id kept by a
+//! long-running client may come to name a different pane. Each pane the
+//! plugin sees gets a handle, in UUID form, for as long as it stays open;
+//! a handle is never given out again, so a stale one matches nothing.
+//!
+//! Handles are derived from the time the plugin loaded and a counter, so a
+//! reloaded plugin does not repeat the handles of its previous run.
+
+use sha2::{Digest, Sha256};
+
+use crate::clock;
+
+/// Hands out pane handles
+#[derive(Debug, Clone)]
+pub struct HandleGen {
+    seed: u64,
+    next: u64,
+}
+
+impl Default for HandleGen {
+    fn default() -> Self {
+        HandleGen { seed: clock::now_ms(), next: 0 }
+    }
+}
+
+impl HandleGen {
+    /// A handle never returned before
+    pub fn next_handle(&mut self) -> String {
+        self.next += 1;
+        let mut hasher = Sha256::new();
+        hasher.update(self.seed.to_le_bytes());
+        hasher.update(self.next.to_le_bytes());
+        let mut bytes = [0u8; 16];
+        bytes.copy_from_slice(&hasher.finalize()[..16]);
+        // Version 4 and RFC 4122 variant bits, as random UUIDs have
+        bytes[6] = (bytes[6] & 0x0f) | 0x40;
+        bytes[8] = (bytes[8] & 0x3f) | 0x80;
+        let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+        format!("{}-{}-{}-{}-{}", &hex[..8], &hex[8..12], &hex[12..16], &hex[16..20], &hex[20..])
+    }
+}
+
+/// Whether `s` has the form of a handle
+pub fn is_handle(s: &str) -> bool {
+    let groups: Vec<&str> = s.split('-').collect();
+    groups.len() == 5
+        && groups.iter().zip([8, 4, 4, 4, 12]).all(|(group, len)| {
+            group.len() == len && group.chars().all(|c| c.is_ascii_hexdigit() && !c.is_ascii_uppercase())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_handles_are_unique_uuids() {
+        let mut handles = HandleGen::default();
+        let a = handles.next_handle();
+        let b = handles.next_handle();
+
+        assert_ne!(a, b);
+        assert!(is_handle(&a) && is_handle(&b));
+        assert_eq!(&a[14..15], "4");
+        assert!(!is_handle("proj__cc_1"));
+        assert!(!is_handle("0000000-0000-4000-8000-000000000000"));
+    }
+}
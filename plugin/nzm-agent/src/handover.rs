@@ -0,0 +1,81 @@
+//! Handing an agent's conversation over to a replacement
+//!
+//! `handover_context` renders the recent turns of an agent, usually one
+//! that died, and sends them to its replacement as the opening prompt,
+//! through the `handover_template` config key. With `via`, the turns first
+//! go to another agent pane with `handover_summary_template`, and its
+//! reply stands in for them once that turn completes.
+//!
+//! Templates take `{title}`, `{kind}` and `{project}` of the old agent
+//! and `{context}`: the turns as markdown, or the summary.
+//!
+//! A replacement still launching holds the prompt until it is ready, after
+//! its kind's initial messages.
+
+use crate::turns::Turn;
+
+pub const DEFAULT_HANDOVER_TEMPLATE: &str = "You are taking over from another {kind} agent ({title}). \
+     This is what happened so far:\n\n{context}\n\nContinue where it left off.";
+
+pub const DEFAULT_SUMMARY_TEMPLATE: &str = "Summarize this {kind} agent session ({title}) for an agent taking \
+     it over: the goal, decisions made, work done, open problems and next steps.\n\n{context}";
+
+/// A handover waiting for its summary
+#[derive(Debug, Clone)]
+pub struct PendingHandover {
+    pub id: String,
+    /// Replacement pane, and its handle so a pane reusing the id is not
+    /// sent the prompt
+    pub to_pane: u32,
+    pub to_handle: Option<String>,
+    /// The handover prompt with everything but `{context}` filled in
+    pub prompt: String,
+    pub via_pane: u32,
+    /// Turn that asked for the summary, once the request was typed
+    pub summary_turn: Option<String>,
+}
+
+/// Fill a template's agent fields from the old agent's latest turn
+///
+/// `{context}` is left for [`with_context`], so text in the context is
+/// never taken for a placeholder.
+pub fn fill(template: &str, turn: &Turn) -> String {
+    template
+        .replace("{title}", &turn.title)
+        .replace("{kind}", &turn.kind)
+        .replace("{project}", turn.project.as_deref().unwrap_or(""))
+}
+
+/// Put the context into a filled template
+pub fn with_context(filled: &str, context: &str) -> String {
+    filled.replace("{context}", context)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::turns::TurnStatus;
+
+    #[test]
+    fn test_context_is_not_expanded() {
+        let turn = Turn {
+            id: "u1".to_string(),
+            pane_id: 1,
+            handle: None,
+            title: "api__cc_1".to_string(),
+            kind: "cc".to_string(),
+            project: Some("api".to_string()),
+            prompt: String::new(),
+            response: Vec::new(),
+            status: TurnStatus::Complete,
+            task_id: None,
+            sent_at_ms: 0,
+            completed_at_ms: None,
+        };
+
+        let filled = fill("{kind} on {project}: {context}", &turn);
+        assert_eq!(filled, "cc on api: {context}");
+        assert_eq!(with_context(&filled, "see {title}"), "cc on api: see {title}");
+        assert!(fill(DEFAULT_HANDOVER_TEMPLATE, &turn).contains("cc agent (api__cc_1)"));
+    }
+}
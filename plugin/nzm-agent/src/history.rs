@@ -0,0 +1,185 @@
+//! Bounded history of PaneManifest updates for replay debugging
+//!
+//! Each PaneUpdate is recorded in a serializable form so it can be exported
+//! with a bug report and replayed through a fresh State to reproduce
+//! indexing problems.
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::mem::size_of;
+use zellij_tile::prelude::{PaneInfo, PaneManifest};
+
+/// The pane fields State depends on
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PaneRecord {
+    pub id: u32,
+    pub title: String,
+    #[serde(default)]
+    pub is_plugin: bool,
+    #[serde(default)]
+    pub is_focused: bool,
+    #[serde(default)]
+    pub is_floating: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub terminal_command: Option<String>,
+}
+
+/// One recorded PaneUpdate: panes per tab, in tab order
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ManifestRecord {
+    pub tabs: Vec<(usize, Vec<PaneRecord>)>,
+}
+
+impl ManifestRecord {
+    /// Capture a manifest (tabs sorted for a stable export)
+    pub fn from_manifest(manifest: &PaneManifest) -> Self {
+        let mut tabs: Vec<(usize, Vec<PaneRecord>)> = manifest
+            .panes
+            .iter()
+            .map(|(tab, panes)| (*tab, panes.iter().map(PaneRecord::from).collect()))
+            .collect();
+        tabs.sort_by_key(|(tab, _)| *tab);
+        ManifestRecord { tabs }
+    }
+
+    /// Estimated heap bytes of the record
+    pub fn approx_bytes(&self) -> usize {
+        self.tabs
+            .iter()
+            .flat_map(|(_, panes)| panes)
+            .map(|pane| {
+                size_of::<PaneRecord>() + pane.title.len() + pane.terminal_command.as_ref().map_or(0, |c| c.len())
+            })
+            .sum::<usize>()
+            + size_of::<ManifestRecord>()
+    }
+
+    /// Rebuild a manifest to feed back into State
+    pub fn to_manifest(&self) -> PaneManifest {
+        let mut manifest = PaneManifest::default();
+        for (tab, panes) in &self.tabs {
+            manifest.panes.insert(*tab, panes.iter().map(PaneInfo::from).collect());
+        }
+        manifest
+    }
+}
+
+impl From<&PaneInfo> for PaneRecord {
+    fn from(p: &PaneInfo) -> Self {
+        PaneRecord {
+            id: p.id,
+            title: p.title.clone(),
+            is_plugin: p.is_plugin,
+            is_focused: p.is_focused,
+            is_floating: p.is_floating,
+            terminal_command: p.terminal_command.clone(),
+        }
+    }
+}
+
+impl From<&PaneRecord> for PaneInfo {
+    fn from(r: &PaneRecord) -> Self {
+        PaneInfo {
+            id: r.id,
+            title: r.title.clone(),
+            is_plugin: r.is_plugin,
+            is_focused: r.is_focused,
+            is_floating: r.is_floating,
+            terminal_command: r.terminal_command.clone(),
+            ..Default::default()
+        }
+    }
+}
+
+/// Ring of the most recent manifests, oldest first
+#[derive(Debug, Default)]
+pub struct ManifestHistory {
+    records: VecDeque<ManifestRecord>,
+}
+
+impl ManifestHistory {
+    /// Record a manifest, keeping at most `cap` of them within `max_bytes`
+    ///
+    /// Returns how many records were evicted for size; the newest record is
+    /// always kept.
+    pub fn record(&mut self, manifest: &PaneManifest, cap: usize, max_bytes: usize) -> usize {
+        self.records.push_back(ManifestRecord::from_manifest(manifest));
+        while self.records.len() > cap {
+            self.records.pop_front();
+        }
+        let mut evicted = 0;
+        while self.records.len() > 1 && self.approx_bytes() > max_bytes {
+            self.records.pop_front();
+            evicted += 1;
+        }
+        evicted
+    }
+
+    /// Estimated heap bytes of all records
+    pub fn approx_bytes(&self) -> usize {
+        self.records.iter().map(ManifestRecord::approx_bytes).sum()
+    }
+
+    /// Recorded manifests, oldest first
+    pub fn records(&self) -> Vec<ManifestRecord> {
+        self.records.iter().cloned().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn manifest(tabs: &[(usize, &[(u32, &str)])]) -> PaneManifest {
+        let mut manifest = PaneManifest::default();
+        for (tab, panes) in tabs {
+            manifest.panes.insert(*tab, panes
+                .iter()
+                .map(|(id, title)| PaneInfo {
+                    id: *id,
+                    title: title.to_string(),
+                    ..Default::default()
+                })
+                .collect());
+        }
+        manifest
+    }
+
+    #[test]
+    fn test_record_evicts_oldest_over_budget() {
+        let mut history = ManifestHistory::default();
+        history.record(&manifest(&[(0, &[(1, "p")])]), 10, usize::MAX);
+        let one = history.approx_bytes();
+
+        assert_eq!(history.record(&manifest(&[(0, &[(2, "p")])]), 10, one), 1);
+        assert_eq!(history.records()[0].tabs[0].1[0].id, 2);
+        // The newest record stays even when it alone is over budget
+        assert_eq!(history.record(&manifest(&[(0, &[(3, "p")])]), 10, 0), 1);
+        assert_eq!(history.records().len(), 1);
+    }
+
+    #[test]
+    fn test_record_roundtrip() {
+        let original = manifest(&[(1, &[(3, "c")]), (0, &[(1, "a"), (2, "b")])]);
+        let record = ManifestRecord::from_manifest(&original);
+
+        assert_eq!(record.tabs[0].0, 0);
+        let json = serde_json::to_string(&record).unwrap();
+        let parsed: ManifestRecord = serde_json::from_str(&json).unwrap();
+        let rebuilt = parsed.to_manifest();
+        assert_eq!(rebuilt.panes[&0][1].title, "b");
+        assert_eq!(rebuilt.panes[&1][0].id, 3);
+    }
+
+    #[test]
+    fn test_history_is_bounded() {
+        let mut history = ManifestHistory::default();
+        for i in 0..5 {
+            history.record(&manifest(&[(0, &[(i, "p")])]), 3, usize::MAX);
+        }
+
+        let records = history.records();
+        assert_eq!(records.len(), 3);
+        assert_eq!(records[0].tabs[0].1[0].id, 2);
+    }
+}
@@ -0,0 +1,123 @@
+//! Windows of the day when queued tasks may be dispatched
+//!
+//! The `dispatch_hours` config key holds windows for every project, and
+//! `dispatch_hours.<project>` windows for one project, such as
+//! `22:00-06:00` or `09:00-12:00,13:00-17:30`. A window ending before it
+//! starts runs past midnight. Times are at the `utc_offset` config key,
+//! such as `+02:00`, or UTC when unset, as the plugin cannot read the
+//! host's time zone.
+//!
+//! Outside its windows a project's queue is held: dispatch_task leaves its
+//! tasks pending. Prompts sent directly, such as with send_keys, still go
+//! out.
+
+use std::fmt;
+
+const MINUTES_PER_DAY: u32 = 24 * 60;
+
+/// Windows of the day, as minutes after midnight; the end is exclusive
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DispatchHours {
+    windows: Vec<(u32, u32)>,
+}
+
+impl DispatchHours {
+    /// Parse comma-separated `HH:MM-HH:MM` windows
+    pub fn parse(value: &str) -> Option<Self> {
+        let windows = value
+            .split(',')
+            .map(|window| {
+                let (start, end) = window.trim().split_once('-')?;
+                Some((parse_time(start)?, parse_time(end)?))
+            })
+            .collect::<Option<Vec<_>>>()?;
+        Some(DispatchHours { windows })
+    }
+
+    /// Whether a minute of the day falls in a window
+    pub fn contains(&self, minute: u32) -> bool {
+        self.windows.iter().any(|&(start, end)| match start.cmp(&end) {
+            std::cmp::Ordering::Less => (start..end).contains(&minute),
+            std::cmp::Ordering::Greater => minute >= start || minute < end,
+            // A window from a time to itself is the whole day
+            std::cmp::Ordering::Equal => true,
+        })
+    }
+
+    /// Minute of the day the next window opens after `minute`
+    pub fn next_open(&self, minute: u32) -> Option<u32> {
+        self.windows
+            .iter()
+            .map(|&(start, _)| start)
+            .min_by_key(|&start| (start + MINUTES_PER_DAY - minute) % MINUTES_PER_DAY)
+    }
+}
+
+impl fmt::Display for DispatchHours {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let windows: Vec<String> = self
+            .windows
+            .iter()
+            .map(|&(start, end)| format!("{}-{}", format_time(start), format_time(end)))
+            .collect();
+        f.write_str(&windows.join(","))
+    }
+}
+
+/// Minutes after midnight of an `HH:MM` time
+fn parse_time(time: &str) -> Option<u32> {
+    let (hours, minutes) = time.trim().split_once(':')?;
+    let (hours, minutes): (u32, u32) = (hours.parse().ok()?, minutes.parse().ok()?);
+    (hours < 24 && minutes < 60).then_some(hours * 60 + minutes)
+}
+
+/// `HH:MM` of a minute of the day
+pub fn format_time(minute: u32) -> String {
+    format!("{:02}:{:02}", minute / 60, minute % 60)
+}
+
+/// Parse a `+HH:MM` or `-HH:MM` offset from UTC into minutes
+pub fn parse_offset(value: &str) -> Option<i32> {
+    let (sign, rest) = match value.as_bytes().first()? {
+        b'+' => (1, &value[1..]),
+        b'-' => (-1, &value[1..]),
+        _ => (1, value),
+    };
+    let minutes = parse_time(rest)? as i32;
+    (minutes <= 14 * 60).then_some(sign * minutes)
+}
+
+/// Minute of the day at an offset from UTC
+pub fn minute_of_day(now_ms: u64, offset_minutes: i32) -> u32 {
+    let minutes = (now_ms / 60_000) as i64 + offset_minutes as i64;
+    minutes.rem_euclid(MINUTES_PER_DAY as i64) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_windows_past_midnight() {
+        let hours = DispatchHours::parse("22:00-06:00, 12:00-13:30").unwrap();
+        assert!(hours.contains(23 * 60));
+        assert!(hours.contains(5 * 60 + 59));
+        assert!(!hours.contains(6 * 60));
+        assert!(hours.contains(13 * 60));
+        assert_eq!(hours.next_open(8 * 60), Some(12 * 60));
+        assert_eq!(hours.next_open(14 * 60), Some(22 * 60));
+        assert_eq!(hours.to_string(), "22:00-06:00,12:00-13:30");
+
+        assert!(DispatchHours::parse("9-17").is_none());
+        assert!(DispatchHours::parse("09:00-24:00").is_none());
+    }
+
+    #[test]
+    fn test_offsets() {
+        assert_eq!(parse_offset("+02:00"), Some(120));
+        assert_eq!(parse_offset("-05:30"), Some(-330));
+        assert_eq!(parse_offset("+15:00"), None);
+        // 1970-01-01 00:30 UTC
+        assert_eq!(minute_of_day(30 * 60_000, -60), 23 * 60 + 30);
+    }
+}
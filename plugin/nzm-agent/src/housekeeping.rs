@@ -0,0 +1,144 @@
+//! Periodic checks for idle agents and due schedules, and git refreshes
+//! for the dashboard
+//!
+//! One housekeeping timer runs while the config has an idle timeout,
+//! schedules, or `dashboard_git`. Zellij's timers are shared, so any timer firing runs the
+//! checks; only the housekeeping timer, or one firing after it was due,
+//! arms the next one, so there is one housekeeping timer at a time.
+
+use crate::clock::{Clock, TIMER_SLACK_MS};
+use crate::commands;
+use crate::ipc::Response;
+use crate::schedule::SCHEDULE_CHECK_SECS;
+use crate::state::State;
+
+/// Interval between dashboard git refreshes
+pub const DASHBOARD_GIT_SECS: u64 = 15;
+
+#[derive(Debug, Default)]
+pub struct Housekeeping {
+    /// When the pending housekeeping timer is due, in milliseconds since
+    /// the Unix epoch
+    due_ms: u64,
+}
+
+impl Housekeeping {
+    /// Wind down idle agents, run due schedules, refresh the dashboard's
+    /// git info, and arm the next housekeeping timer
+    ///
+    /// Returns the schedules' responses and git_info effects for plugin.rs
+    /// to execute; idle effects are left in State.
+    pub fn tick(&mut self, state: &mut State, clock: &mut impl Clock) -> Vec<Response> {
+        let config = state.config();
+        let idle_secs = config.idle.as_ref().map(|policy| policy.check_secs());
+        let schedule_secs = (!config.schedules.is_empty()).then_some(SCHEDULE_CHECK_SECS);
+        let git_secs = config.dashboard_git.then_some(DASHBOARD_GIT_SECS);
+        let Some(secs) = idle_secs.into_iter().chain(schedule_secs).chain(git_secs).min() else {
+            return Vec::new();
+        };
+        let refresh_git = git_secs.is_some();
+        let now = clock.now_ms();
+        state.check_idle(now);
+        let mut responses = commands::run_schedules(state, now);
+        if refresh_git {
+            responses.extend(stale_git(state));
+        }
+        if now + TIMER_SLACK_MS >= self.due_ms {
+            self.due_ms = now + secs * 1000;
+            clock.set_timeout(secs as f64);
+        }
+        responses
+    }
+}
+
+/// git_info effects for agent directories whose git info has expired
+fn stale_git(state: &State) -> Vec<Response> {
+    let mut dirs: Vec<String> = state
+        .panes()
+        .iter()
+        .filter(|pane| state.agent_name(pane).is_some())
+        .filter_map(|pane| state.agent_dir(pane))
+        .filter(|dir| state.cached_git_info(dir).is_none())
+        .collect();
+    dirs.sort();
+    dirs.dedup();
+    dirs.into_iter()
+        .map(|dir| Response::ok("dashboard_git", serde_json::json!({ "action": "git_info", "cwd": dir })))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::ManualClock;
+    use crate::config::Config;
+    use crate::idle::{IdleAction, IdlePolicy};
+    use crate::schedule::Schedule;
+    use zellij_tile::prelude::{PaneInfo, PaneManifest};
+
+    #[test]
+    fn test_one_timer_drives_idle_checks_and_schedules() {
+        // A minute boundary
+        let mut clock = ManualClock::new(1_800_000_000_000);
+        let mut state = State::default();
+        state.set_config(Config {
+            idle: Some(IdlePolicy { timeout_secs: 20, action: IdleAction::Notify }),
+            schedules: vec![Schedule::parse("panes", r#"{"cron": "* * * * *", "action": "list_panes"}"#).unwrap()],
+            script: Some(r#"fn classify(pane, lines) { "idle" }"#.to_string()),
+            ..Config::default()
+        });
+        let mut manifest = PaneManifest::default();
+        manifest.panes.insert(0, vec![PaneInfo { id: 1, title: "proj__cc_1".to_string(), ..Default::default() }]);
+        state.update_panes(manifest);
+        state.update_pane_contents(1, vec![">".to_string()]);
+
+        let mut housekeeping = Housekeeping::default();
+        assert!(housekeeping.tick(&mut state, &mut clock).is_empty());
+        let mut responses = Vec::new();
+        for _ in 0..8 {
+            // Another subsystem's timer runs the checks without arming more
+            clock.set_timeout(1.0);
+            for _ in clock.advance(15_000) {
+                responses.extend(housekeeping.tick(&mut state, &mut clock));
+            }
+            assert_eq!(clock.pending(), 1);
+        }
+
+        assert_eq!(responses.len(), 2);
+        assert!(responses.iter().all(|resp| resp.success && resp.id == "schedule:panes"));
+        let idle: Vec<_> = state.take_events().into_iter().filter(|(kind, _)| kind == "idle_timeout").collect();
+        assert_eq!(idle.len(), 1);
+        assert_eq!(idle[0].1["idle_secs"], 30);
+    }
+
+    #[test]
+    fn test_dashboard_git_refreshes_expired_directories() {
+        let mut clock = ManualClock::new(0);
+        let mut state = State::default();
+        state.set_config(Config { dashboard_git: true, ..Config::default() });
+        let mut manifest = PaneManifest::default();
+        manifest.panes.insert(0, vec![
+            PaneInfo { id: 1, title: "proj__cc_1".to_string(), ..Default::default() },
+            PaneInfo { id: 2, title: "proj__cc_2".to_string(), ..Default::default() },
+            PaneInfo { id: 3, title: "shell".to_string(), ..Default::default() },
+        ]);
+        state.update_panes(manifest);
+        let mut housekeeping = Housekeeping::default();
+
+        let responses = housekeeping.tick(&mut state, &mut clock);
+        assert_eq!(responses.len(), 1);
+        assert_eq!(responses[0].data.as_ref().unwrap()["cwd"], "/data/projects/proj");
+        assert_eq!(clock.pending(), 1);
+
+        state.cache_git_info("/data/projects/proj", Default::default());
+        assert!(housekeeping.tick(&mut state, &mut clock).is_empty());
+    }
+
+    #[test]
+    fn test_no_timer_without_work() {
+        let mut clock = ManualClock::new(0);
+        let mut state = State::default();
+        assert!(Housekeeping::default().tick(&mut state, &mut clock).is_empty());
+        assert_eq!(clock.pending(), 0);
+    }
+}
@@ -0,0 +1,116 @@
+//! Message catalogs for text shown in the plugin pane
+//!
+//! Only what a person reads is translated. Responses, error messages and
+//! error codes stay in English so scripts keep working in any locale.
+
+/// A supported display language
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Locale {
+    #[default]
+    En,
+    De,
+    Es,
+    Fr,
+}
+
+impl Locale {
+    /// Parse a locale name such as `de`, `de_DE` or `de_DE.UTF-8`;
+    /// unsupported languages get None
+    pub fn parse(name: &str) -> Option<Locale> {
+        let lang = name.split(['_', '-', '.', '@']).next()?.to_ascii_lowercase();
+        match lang.as_str() {
+            "en" | "c" | "posix" => Some(Locale::En),
+            "de" => Some(Locale::De),
+            "es" => Some(Locale::Es),
+            "fr" => Some(Locale::Fr),
+            _ => None,
+        }
+    }
+}
+
+/// A translatable string
+#[cfg(any(target_arch = "wasm32", test))]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Message {
+    /// Name of the plugin in its pane
+    Title,
+    /// Label of the pane count
+    Panes,
+    /// Label of an agent line in accessible mode
+    Agent,
+    /// Label of a pane id in accessible mode
+    Pane,
+    /// Status of an agent no pattern recognised
+    Unknown,
+    /// Marks an agent whose project's automation is paused
+    Paused,
+    /// Marks an agent whose project is outside its dispatch hours
+    QueueHeld,
+    /// Label of an agent's git branch in accessible mode
+    Branch,
+    /// Marks a branch with uncommitted changes in accessible mode
+    Dirty,
+}
+
+/// Text of a message in a locale
+#[cfg(any(target_arch = "wasm32", test))]
+pub fn text(locale: Locale, message: Message) -> &'static str {
+    match (locale, message) {
+        (Locale::En, Message::Title) => "NZM Agent",
+        (Locale::En, Message::Panes) => "Panes",
+        (Locale::En, Message::Agent) => "Agent",
+        (Locale::En, Message::Pane) => "pane",
+        (Locale::En, Message::Unknown) => "unknown",
+        (Locale::En, Message::Paused) => "paused",
+        (Locale::En, Message::QueueHeld) => "queue held",
+        (Locale::En, Message::Branch) => "branch",
+        (Locale::En, Message::Dirty) => "uncommitted changes",
+        (Locale::De, Message::Title) => "NZM-Agent",
+        (Locale::De, Message::Panes) => "Bereiche",
+        (Locale::De, Message::Agent) => "Agent",
+        (Locale::De, Message::Pane) => "Bereich",
+        (Locale::De, Message::Unknown) => "unbekannt",
+        (Locale::De, Message::Paused) => "pausiert",
+        (Locale::De, Message::QueueHeld) => "Warteschlange angehalten",
+        (Locale::De, Message::Branch) => "Branch",
+        (Locale::De, Message::Dirty) => "nicht committete Änderungen",
+        (Locale::Es, Message::Title) => "Agente NZM",
+        (Locale::Es, Message::Panes) => "Paneles",
+        (Locale::Es, Message::Agent) => "Agente",
+        (Locale::Es, Message::Pane) => "panel",
+        (Locale::Es, Message::Unknown) => "desconocido",
+        (Locale::Es, Message::Paused) => "en pausa",
+        (Locale::Es, Message::QueueHeld) => "cola retenida",
+        (Locale::Es, Message::Branch) => "rama",
+        (Locale::Es, Message::Dirty) => "cambios sin confirmar",
+        (Locale::Fr, Message::Title) => "Agent NZM",
+        (Locale::Fr, Message::Panes) => "Volets",
+        (Locale::Fr, Message::Agent) => "Agent",
+        (Locale::Fr, Message::Pane) => "volet",
+        (Locale::Fr, Message::Unknown) => "inconnu",
+        (Locale::Fr, Message::Paused) => "en pause",
+        (Locale::Fr, Message::QueueHeld) => "file retenue",
+        (Locale::Fr, Message::Branch) => "branche",
+        (Locale::Fr, Message::Dirty) => "modifications non validées",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_locale_names() {
+        assert_eq!(Locale::parse("de_DE.UTF-8"), Some(Locale::De));
+        assert_eq!(Locale::parse("fr"), Some(Locale::Fr));
+        assert_eq!(Locale::parse("es-MX"), Some(Locale::Es));
+        assert_eq!(Locale::parse("C.UTF-8"), Some(Locale::En));
+        assert_eq!(Locale::parse("xx_YY"), None);
+    }
+
+    #[test]
+    fn test_text() {
+        assert_eq!(text(Locale::En, Message::Panes), "Panes");
+        assert_eq!(text(Locale::De, Message::Panes), "Bereiche");
+    }
+}
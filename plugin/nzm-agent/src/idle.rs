@@ -0,0 +1,123 @@
+//! Winding down agents left idle
+//!
+//! With the `idle_timeout_secs` config key set, an agent whose status has
+//! been `idle` for that long gets the `idle_action`:
+//!
+//! - `notify` (the default): only the `idle_timeout` event
+//! - `send:<text>`: type the text and Enter, such as `send:/compact`
+//! - `keys:<key>,<key>`: press keys named as for send_keys, such as
+//!   `keys:ctrl+z`
+//! - `close`: close the pane
+//!
+//! A script's `fn idle(pane, idle_secs)` hook can keep an agent running by
+//! returning false or a reason string. The action runs once per idle
+//! stretch: the agent has to turn busy before it can be wound down again.
+//! Agents whose automation is paused are left alone.
+
+use serde_json::Value;
+
+use crate::keys;
+
+/// Longest time between idle checks
+pub const IDLE_CHECK_SECS: u64 = 15;
+
+/// What happens to an agent idle past the timeout
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum IdleAction {
+    Notify,
+    Send(String),
+    Keys(Vec<String>),
+    Close,
+}
+
+impl IdleAction {
+    /// Parse an `idle_action` value; unknown keys make it invalid
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "notify" => return Some(IdleAction::Notify),
+            "close" => return Some(IdleAction::Close),
+            _ => {}
+        }
+        if let Some(text) = value.strip_prefix("send:").filter(|text| !text.is_empty()) {
+            return Some(IdleAction::Send(text.to_string()));
+        }
+        let keys: Vec<String> = value
+            .strip_prefix("keys:")?
+            .split(',')
+            .map(|key| key.trim().to_string())
+            .filter(|key| !key.is_empty())
+            .collect();
+        if keys.is_empty() || keys::keys_text(&keys).is_err() {
+            return None;
+        }
+        Some(IdleAction::Keys(keys))
+    }
+
+    /// Name of the action, for events
+    pub fn name(&self) -> &'static str {
+        match self {
+            IdleAction::Notify => "notify",
+            IdleAction::Send(_) => "send",
+            IdleAction::Keys(_) => "keys",
+            IdleAction::Close => "close",
+        }
+    }
+
+    /// The effect carrying out the action on a pane, if it does anything
+    pub fn effect(&self, pane_id: u32) -> Option<Value> {
+        let effect = match self {
+            IdleAction::Notify => return None,
+            IdleAction::Send(text) => serde_json::json!({
+                "action": "send_keys",
+                "pane_id": pane_id,
+                "text": text,
+                "enter": true,
+            }),
+            IdleAction::Keys(names) => serde_json::json!({
+                "action": "send_keys",
+                "pane_id": pane_id,
+                "text": keys::keys_text(names).ok()?,
+                "enter": false,
+            }),
+            IdleAction::Close => serde_json::json!({
+                "action": "close_pane",
+                "pane_id": pane_id,
+            }),
+        };
+        Some(effect)
+    }
+}
+
+/// Idle timeout and what to do when it passes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct IdlePolicy {
+    pub timeout_secs: u64,
+    pub action: IdleAction,
+}
+
+impl IdlePolicy {
+    /// Seconds between idle checks, so an agent is caught soon after its
+    /// timeout passes
+    pub fn check_secs(&self) -> u64 {
+        self.timeout_secs.clamp(1, IDLE_CHECK_SECS)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_actions() {
+        assert_eq!(IdleAction::parse("close"), Some(IdleAction::Close));
+        assert_eq!(IdleAction::parse("send:/compact"), Some(IdleAction::Send("/compact".to_string())));
+        assert_eq!(IdleAction::parse("keys:ctrl+z, enter"), Some(IdleAction::Keys(vec!["ctrl+z".to_string(), "enter".to_string()])));
+        assert_eq!(IdleAction::parse("keys:hyper+z"), None);
+        assert_eq!(IdleAction::parse("send:"), None);
+        assert_eq!(IdleAction::parse("suspend"), None);
+
+        let effect = IdleAction::Keys(vec!["ctrl+z".to_string()]).effect(3).unwrap();
+        assert_eq!(effect["text"], "\x1a");
+        assert!(IdleAction::Notify.effect(3).is_none());
+    }
+}
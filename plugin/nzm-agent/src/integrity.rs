@@ -0,0 +1,48 @@
+//! Identity of the loaded plugin binary
+//!
+//! The build embeds a sha256 of the sources it was compiled from (see
+//! build.rs), which is what the running code actually is. The file
+//! Zellij loaded is hashed as well, as a second value: Zellij does not
+//! tell a plugin where its `.wasm` came from, so the session passes it as
+//! the `plugin_path` config key, and once permissions are granted the file
+//! is hashed on the host. The file may have been replaced since it was
+//! loaded, so only the embedded value speaks for the running build.
+//! get_version reports both for the CLI to compare with the release
+//! manifest.
+
+/// sha256 of the sources this build was compiled from
+pub const BUILD_SHA256: &str = env!("NZM_BUILD_SHA256");
+
+/// Command line printing the sha256 of `path`, with the GNU tool or the
+/// BSD one macOS ships
+#[cfg(target_arch = "wasm32")]
+pub fn hash_command(path: &str) -> Vec<String> {
+    vec![
+        "sh".to_string(),
+        "-c".to_string(),
+        "sha256sum -- \"$1\" 2>/dev/null || shasum -a 256 -- \"$1\"".to_string(),
+        "sh".to_string(),
+        path.to_string(),
+    ]
+}
+
+/// The hex digest from `sha256sum` or `shasum` output
+#[cfg(any(target_arch = "wasm32", test))]
+pub fn parse_hash(output: &str) -> Option<String> {
+    let digest = output.split_whitespace().next()?;
+    (digest.len() == 64 && digest.bytes().all(|b| b.is_ascii_hexdigit())).then(|| digest.to_ascii_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_hash() {
+        let digest = "e3b0c44298fc1c149afbf4c8996fb92427ae41e4649b934ca495991b7852b855";
+        assert_eq!(parse_hash(&format!("{}  /opt/nzm-agent.wasm\n", digest)).as_deref(), Some(digest));
+        assert_eq!(parse_hash(&format!("{} *nzm-agent.wasm", digest.to_uppercase())).as_deref(), Some(digest));
+        assert_eq!(parse_hash("sha256sum: nzm-agent.wasm: No such file or directory"), None);
+        assert_eq!(parse_hash(""), None);
+    }
+}
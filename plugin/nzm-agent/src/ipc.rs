@@ -1,17 +1,40 @@
+use std::collections::BTreeMap;
+use std::fmt;
+
 use serde::{Deserialize, Serialize};
 use serde_json::Value;
 
+use crate::encoding::OutputEncoding;
+use crate::history::ManifestRecord;
+use crate::selector::Selector;
+use crate::spawn::{EnvValue, PaneDirection};
+use crate::turns::ConversationFormat;
+use crate::write_queue::Priority;
+
+/// Version of the request and response format, raised on changes an
+/// older CLI cannot follow
+///
+/// get_version reports it with the plugin version, so an installer can
+/// tell when the CLI and the loaded plugin no longer match.
+pub const PROTOCOL_VERSION: u32 = 1;
+
 /// Request from CLI to plugin via zellij pipe
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Default, Deserialize)]
 pub struct Request {
     pub id: String,
     pub action: String,
     #[serde(default)]
     pub params: Value,
+    /// Describe what the request would do instead of doing it
+    #[serde(default)]
+    pub explain: bool,
+    /// Reject the request unless the pane list is still at this revision
+    #[serde(default)]
+    pub if_revision: Option<u64>,
 }
 
 /// Response from plugin to CLI
-#[derive(Debug, Serialize)]
+#[derive(Debug, Clone, Serialize)]
 pub struct Response {
     pub id: String,
     pub success: bool,
@@ -19,21 +42,895 @@ pub struct Response {
     pub data: Option<Value>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub error: Option<String>,
+    /// Stable class of the error
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<ErrorCode>,
+}
+
+impl Response {
+    /// Build a successful response carrying `data`
+    pub fn ok(id: &str, data: Value) -> Self {
+        Response {
+            id: id.to_string(),
+            success: true,
+            data: Some(data),
+            error: None,
+            code: None,
+        }
+    }
+
+    /// Serialize as one newline-terminated JSON line
+    ///
+    /// A long-lived `zellij pipe` fed from stdin carries many requests over
+    /// the same CLI pipe; newline framing lets the client split replies and
+    /// match them back to requests by id.
+    pub fn to_line(&self) -> Option<String> {
+        serde_json::to_string(self).ok().map(|json| json + "\n")
+    }
+
+    /// Build a failed response from an error; plain messages are `failed`
+    pub fn err(id: &str, error: impl Into<Error>) -> Self {
+        let error = error.into();
+        Response {
+            id: id.to_string(),
+            success: false,
+            data: None,
+            error: Some(error.message),
+            code: Some(error.code),
+        }
+    }
+
+    /// Build a failed response with an explicit code
+    pub fn err_code(id: &str, code: ErrorCode, message: impl Into<String>) -> Self {
+        Self::err(id, Error::new(code, message))
+    }
+}
+
+/// Stable code for an error, for callers that branch on failures
+///
+/// Messages are for people and may change; codes are not. The CLI maps
+/// them to its exit codes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorCode {
+    /// A pane, tab, task, artifact or other named thing does not exist
+    NotFound,
+    InvalidParams,
+    /// The route hook refused the request
+    Denied,
+    /// A guard refused the request
+    Blocked,
+    /// Automation is paused for the project
+    Paused,
+    /// The project is outside its dispatch hours
+    Held,
+    QueueFull,
+    /// The pane a task was bound to is gone or changed
+    StaleTarget,
+    /// The pane list moved past the request's `if_revision`
+    StaleRevision,
+    /// The agent has not finished launching
+    Starting,
+    UnknownAction,
+    ShuttingDown,
+    /// Anything else
+    Failed,
+}
+
+/// An error message and its code, for helpers whose failures become
+/// responses
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Error {
+    pub code: ErrorCode,
+    pub message: String,
+}
+
+impl Error {
+    pub fn new(code: ErrorCode, message: impl Into<String>) -> Self {
+        Error { code, message: message.into() }
+    }
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.message)
+    }
+}
+
+impl From<String> for Error {
+    fn from(message: String) -> Self {
+        Error::new(ErrorCode::Failed, message)
+    }
+}
+
+impl From<&str> for Error {
+    fn from(message: &str) -> Self {
+        Error::new(ErrorCode::Failed, message)
+    }
 }
 
 /// Parameters for send_keys action
 #[derive(Debug, Deserialize)]
 pub struct SendKeysParams {
-    pub pane_id: u32,
-    pub text: String,
+    /// Pane id, or any selector naming one pane, such as `focused`
+    pub pane_id: Selector,
+    /// Required unless `keys` are given
+    #[serde(default)]
+    pub text: Option<String>,
+    /// Keys sent after the text, such as `Escape`, `Up` or `ctrl+d`
+    #[serde(default)]
+    pub keys: Vec<String>,
     #[serde(default)]
     pub enter: bool,
+    #[serde(default)]
+    pub priority: Priority,
+    /// Send in chunks of at most this many bytes, one per pacing tick
+    #[serde(default)]
+    pub chunk_bytes: Option<usize>,
+    /// Wrap the text in bracketed-paste markers so it arrives as one paste;
+    /// also accepted as `paste`
+    #[serde(default, alias = "paste")]
+    pub bracketed: bool,
+}
+
+/// Parameters for send_raw action
+#[derive(Debug, Deserialize)]
+pub struct SendRawParams {
+    /// Pane id, or any selector naming one pane, such as `focused`
+    pub pane_id: Selector,
+    /// Base64 of the bytes written to the pane as they are
+    pub bytes: String,
+    #[serde(default)]
+    pub priority: Priority,
+}
+
+/// Parameters for send_interrupt action
+#[derive(Debug, Deserialize)]
+pub struct SendInterruptParams {
+    /// Pane id, or any selector naming one pane, such as `focused`
+    pub pane_id: Selector,
+    /// Defaults to urgent, so the interrupt goes ahead of queued sends
+    #[serde(default)]
+    pub priority: Option<Priority>,
+    /// Key sent, such as `escape` for agents that stop on it (default: `ctrl+c`)
+    #[serde(default)]
+    pub key: Option<String>,
+}
+
+/// Parameters for pause_send, resume_send and abort_send actions
+#[derive(Debug, Deserialize)]
+pub struct JobIdParams {
+    pub job_id: String,
+}
+
+/// Parameters for list_panes action
+#[derive(Debug, Default, Deserialize)]
+pub struct ListPanesParams {
+    /// Only report panes added, changed, or removed after this revision
+    #[serde(default)]
+    pub since_revision: Option<u64>,
 }
 
 /// Parameters for actions that target a single pane
 #[derive(Debug, Deserialize)]
 pub struct PaneIdParam {
+    /// Pane id, or any selector naming one pane, such as `focused`
+    pub pane_id: Selector,
+}
+
+/// Parameters for toggle_floating action
+#[derive(Debug, Deserialize)]
+pub struct ToggleFloatingParams {
+    /// Pane id, or any selector naming one pane, such as `focused`
+    pub pane_id: Selector,
+    /// State wanted; a pane already in it is left alone (default: flip)
+    #[serde(default)]
+    pub floating: Option<bool>,
+}
+
+/// Parameters for toggle_fullscreen action
+#[derive(Debug, Deserialize)]
+pub struct ToggleFullscreenParams {
+    /// Pane id, or any selector naming one pane, such as `focused`
+    pub pane_id: Selector,
+    /// State wanted; a pane already in it is left alone (default: flip)
+    #[serde(default)]
+    pub fullscreen: Option<bool>,
+}
+
+/// Parameters for pin_pane action
+#[derive(Debug, Deserialize)]
+pub struct PinPaneParams {
+    /// Pane id, or any selector naming one pane, such as `focused`
+    pub pane_id: Selector,
+    /// Keep the pane above tiled panes even when floating panes are hidden;
+    /// false unpins it
+    #[serde(default = "default_true")]
+    pub pinned: bool,
+}
+
+/// Parameters for stack_panes action
+#[derive(Debug, Deserialize)]
+pub struct StackPanesParams {
+    pub pane_ids: Vec<u32>,
+    /// Pane left expanded (default: the one Zellij keeps open)
+    #[serde(default)]
+    pub expand: Option<u32>,
+}
+
+/// Parameters for swap_panes action
+#[derive(Debug, Deserialize)]
+pub struct SwapPanesParams {
+    /// Pane id, or any selector naming one pane, such as `focused`
+    pub pane_id: Selector,
+    /// Must share a row or column of aligned panes with `pane_id`; also
+    /// any selector naming one pane
+    pub other_pane_id: Selector,
+}
+
+/// Parameters for unstack_panes action
+#[derive(Debug, Deserialize)]
+pub struct PaneIdsParam {
+    /// Tiled panes to take out of their stack; each is floated and embedded
+    /// again, so it may land anywhere in the layout
+    pub pane_ids: Vec<u32>,
+}
+
+/// Parameters for new_tab action
+#[derive(Debug, Deserialize)]
+pub struct NewTabParams {
+    #[serde(default)]
+    pub name: Option<String>,
+    /// Built-in layout name, or path of a `.kdl` layout file
+    #[serde(default)]
+    pub layout: Option<String>,
+}
+
+/// Parameters for go_to_tab action; give `tab_index` or `tab_name`
+#[derive(Debug, Deserialize)]
+pub struct TabParams {
+    #[serde(default)]
+    pub tab_index: Option<usize>,
+    #[serde(default)]
+    pub tab_name: Option<String>,
+}
+
+/// Parameters for rename_tab action; give `tab_index` or `tab_name`
+#[derive(Debug, Deserialize)]
+pub struct RenameTabParams {
+    #[serde(default)]
+    pub tab_index: Option<usize>,
+    #[serde(default)]
+    pub tab_name: Option<String>,
+    /// The new name
+    pub name: String,
+}
+
+/// Parameters for close_tab action; give `tab_index` or `tab_name`
+#[derive(Debug, Deserialize)]
+pub struct CloseTabParams {
+    #[serde(default)]
+    pub tab_index: Option<usize>,
+    #[serde(default)]
+    pub tab_name: Option<String>,
+    /// Close the tab even if its panes have unfinished tasks
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// Parameters for move_pane_to_tab action; give `tab_index` or `tab_name`
+#[derive(Debug, Deserialize)]
+pub struct MovePaneToTabParams {
+    /// Pane id, or any selector naming one pane, such as `focused`
+    pub pane_id: Selector,
+    #[serde(default)]
+    pub tab_index: Option<usize>,
+    #[serde(default)]
+    pub tab_name: Option<String>,
+    /// Open a tab with `tab_name` when there is none
+    #[serde(default)]
+    pub create: bool,
+    /// Switch to the tab along with the pane
+    #[serde(default)]
+    pub focus: bool,
+}
+
+/// Edge of a pane that resize_pane moves
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResizeDirection {
+    Left,
+    Right,
+    Up,
+    Down,
+}
+
+/// Absolute size of a floating pane: cells, or a string such as `"40%"`
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum PaneSize {
+    Cells(usize),
+    Spec(String),
+}
+
+/// Parameters for resize_pane action
+///
+/// Give `amount` or `percent` to grow (positive) or shrink (negative) the
+/// pane, or `width` and/or `height` to size a floating pane outright.
+#[derive(Debug, Deserialize)]
+pub struct ResizePaneParams {
+    /// Pane id, or any selector naming one pane, such as `focused`
+    pub pane_id: Selector,
+    /// Edge to move (default: every edge)
+    #[serde(default)]
+    pub direction: Option<ResizeDirection>,
+    /// Resize steps of Zellij's
+    #[serde(default)]
+    pub amount: Option<i32>,
+    /// Percent of the tab, rounded to whole resize steps
+    #[serde(default)]
+    pub percent: Option<i32>,
+    #[serde(default)]
+    pub width: Option<PaneSize>,
+    #[serde(default)]
+    pub height: Option<PaneSize>,
+}
+
+/// Way scroll_pane moves a pane's view
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScrollDirection {
+    Up,
+    Down,
+}
+
+/// End of the scrollback a view can jump to
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScrollEdge {
+    Top,
+    Bottom,
+}
+
+/// Absolute scroll position: an end, or a line counted from the top
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum ScrollTo {
+    Line(usize),
+    Edge(ScrollEdge),
+}
+
+/// Parameters for scroll_pane action
+///
+/// Give `direction` with `lines` or `pages` (default: one line), or `to`.
+#[derive(Debug, Deserialize)]
+pub struct ScrollPaneParams {
+    /// Pane id, or any selector naming one pane, such as `focused`
+    pub pane_id: Selector,
+    #[serde(default)]
+    pub direction: Option<ScrollDirection>,
+    #[serde(default)]
+    pub lines: Option<usize>,
+    #[serde(default)]
+    pub pages: Option<usize>,
+    #[serde(default)]
+    pub to: Option<ScrollTo>,
+}
+
+/// Parameters for actions that target a single pane by selector
+#[derive(Debug, Deserialize)]
+pub struct SelectorParam {
+    pub selector: Selector,
+}
+
+/// Parameters for unlock_worktree action
+#[derive(Debug, Deserialize)]
+pub struct UnlockWorktreeParams {
+    pub selector: Selector,
+    /// Release the lock even when another pane holds it
+    #[serde(default)]
+    pub force: bool,
+}
+
+/// Parameters for capture_frame action
+#[derive(Debug, Deserialize)]
+pub struct CaptureFrameParams {
+    pub selector: Selector,
+    /// Include SGR color/style runs for each row
+    #[serde(default)]
+    pub attributes: bool,
+}
+
+/// Parameters for capture_pane action
+#[derive(Debug, Deserialize)]
+pub struct CapturePaneParams {
     pub pane_id: u32,
+    /// Keep escape sequences instead of returning plain text
+    #[serde(default)]
+    pub ansi: bool,
+}
+
+/// Parameters for dump_scrollback action
+#[derive(Debug, Deserialize)]
+pub struct DumpScrollbackParams {
+    pub selector: Selector,
+    /// Only the last this many lines (default: all captured)
+    #[serde(default)]
+    pub lines: Option<usize>,
+    #[serde(default)]
+    pub ansi: bool,
+    /// Write to this path, relative to the pane's working directory, instead
+    /// of returning the text
+    #[serde(default)]
+    pub path: Option<String>,
+    /// Project whose directory `path` is in (default: the pane's directory)
+    #[serde(default)]
+    pub project: Option<String>,
+}
+
+/// What assert_pane inspects
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AssertSource {
+    /// Captured output (scrollback and viewport)
+    #[default]
+    Output,
+    /// The visible viewport as rendered by capture_frame
+    Frame,
+}
+
+/// Parameters for assert_pane action
+#[derive(Debug, Deserialize)]
+pub struct AssertPaneParams {
+    pub selector: Selector,
+    #[serde(default)]
+    pub contains: Option<String>,
+    #[serde(default)]
+    pub regex: Option<String>,
+    #[serde(default)]
+    pub source: AssertSource,
+    #[serde(default)]
+    pub since_checkpoint: Option<String>,
+}
+
+/// Parameters for shutdown action
+#[derive(Debug, Default, Deserialize)]
+pub struct ShutdownParams {
+    /// Unload the plugin once state is persisted
+    #[serde(default)]
+    pub unload: bool,
+}
+
+/// Parameters for adopt_pane action
+#[derive(Debug, Deserialize)]
+pub struct AdoptPaneParams {
+    pub selector: Selector,
+    /// Agent kind name or alias
+    pub kind: String,
+    #[serde(default)]
+    pub project: Option<String>,
+    /// Index within the project (default: next free one when renaming)
+    #[serde(default)]
+    pub index: Option<u32>,
+    /// Rename the pane to the configured title convention
+    #[serde(default)]
+    pub rename: bool,
+}
+
+/// Parameters for new_pane action
+#[derive(Debug, Deserialize)]
+pub struct NewPaneParams {
+    /// Shell command line to run (default: the user's shell)
+    #[serde(default)]
+    pub command: Option<String>,
+    /// Arguments added to the command, each quoted as one word
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Working directory, absolute or under `~/` (default: Zellij's)
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Variables to export, inline or by secret reference
+    #[serde(default)]
+    pub env: BTreeMap<String, EnvValue>,
+    /// Where the pane goes (default: wherever Zellij puts it)
+    #[serde(default)]
+    pub direction: Option<PaneDirection>,
+    /// Same as direction "floating"
+    #[serde(default)]
+    pub floating: bool,
+    /// Pane title
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// Parameters for run_command action
+#[derive(Debug, Deserialize)]
+pub struct RunCommandParams {
+    /// Program to run, looked up on PATH; not passed through a shell
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Working directory, absolute or under `~/` (default: Zellij's)
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Open as a floating pane instead of an embedded one
+    #[serde(default)]
+    pub floating: bool,
+    /// Pane title
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// Parameters for open_floating_command action
+///
+/// Placement is in percent of the tab, measured from its top left corner.
+/// Zellij picks whatever is left out.
+#[derive(Debug, Deserialize)]
+pub struct OpenFloatingCommandParams {
+    /// Program to run, looked up on PATH; not passed through a shell
+    pub command: String,
+    #[serde(default)]
+    pub args: Vec<String>,
+    /// Working directory, absolute or under `~/` (default: Zellij's)
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Pane title
+    #[serde(default)]
+    pub name: Option<String>,
+    #[serde(default)]
+    pub x: Option<u32>,
+    #[serde(default)]
+    pub y: Option<u32>,
+    #[serde(default)]
+    pub width: Option<u32>,
+    #[serde(default)]
+    pub height: Option<u32>,
+}
+
+/// Parameters for spawn_agent action
+#[derive(Debug, Deserialize)]
+pub struct SpawnAgentParams {
+    /// Agent kind name or alias
+    pub kind: String,
+    pub project: String,
+    /// Index within the project (default: the next free one)
+    #[serde(default)]
+    pub index: Option<u32>,
+    /// Named preset of the kind whose arguments are added to its command
+    #[serde(default)]
+    pub preset: Option<String>,
+    /// Working directory template overriding the kind's
+    #[serde(default)]
+    pub cwd: Option<String>,
+    /// Variables to export, inline or by secret reference
+    #[serde(default)]
+    pub env: BTreeMap<String, EnvValue>,
+    #[serde(default)]
+    pub floating: bool,
+}
+
+/// Parameters for next_event action
+#[derive(Debug, Deserialize)]
+pub struct NextEventParams {
+    /// Sequence number of the last event seen; without it only events
+    /// raised from now on count
+    #[serde(default)]
+    pub after: Option<u64>,
+    /// Event types to wait for (default: any)
+    #[serde(default)]
+    pub types: Vec<String>,
+    /// Seconds to wait (default: 30); 0 returns at once
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+}
+
+/// A condition wait checks
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum WaitCondition {
+    /// The agent in the pane is idle by its kind's status patterns
+    Idle(Selector),
+    /// The pane's output or frame matches, as assert_pane checks
+    Text(AssertPaneParams),
+    /// The selector names at least one open pane
+    PaneExists(Selector),
+    /// The task completed or failed
+    Task(String),
+}
+
+/// Whether wait needs every condition or one of them
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WaitMode {
+    #[default]
+    All,
+    Any,
+}
+
+/// Parameters for wait action
+#[derive(Debug, Deserialize)]
+pub struct WaitParams {
+    pub conditions: Vec<WaitCondition>,
+    #[serde(default)]
+    pub mode: WaitMode,
+    /// Seconds to wait (default: 30); 0 checks once
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+}
+
+/// Parameters for interrupt_all and resume_automation actions
+#[derive(Debug, Deserialize)]
+pub struct ProjectScopeParams {
+    /// Limit to one project's agents (default: all of them)
+    #[serde(default)]
+    pub project: Option<String>,
+}
+
+/// Parameters for project_status action
+#[derive(Debug, Deserialize)]
+pub struct ProjectParam {
+    pub project: String,
+}
+
+/// Parameters for enqueue_task action
+#[derive(Debug, Deserialize)]
+pub struct EnqueueTaskParams {
+    pub selector: Selector,
+    pub prompt: String,
+    #[serde(default = "default_true")]
+    pub enter: bool,
+}
+
+/// Parameters for dispatch_task action
+#[derive(Debug, Default, Deserialize)]
+pub struct DispatchTaskParams {
+    /// Task to dispatch (default: the oldest pending task)
+    #[serde(default)]
+    pub id: Option<String>,
+    /// Limit the default choice to tasks for this pane
+    #[serde(default)]
+    pub selector: Option<Selector>,
+}
+
+/// Parameters for complete_task action
+#[derive(Debug, Deserialize)]
+pub struct CompleteTaskParams {
+    pub id: String,
+    #[serde(default)]
+    pub result: Option<String>,
+    #[serde(default)]
+    pub failed: bool,
+}
+
+fn default_true() -> bool {
+    true
+}
+
+/// Parameters for replay_history action
+#[derive(Debug, Default, Deserialize)]
+pub struct ReplayHistoryParams {
+    /// Manifests to replay, e.g. from a bug report (default: live history)
+    #[serde(default)]
+    pub records: Option<Vec<ManifestRecord>>,
+    /// Replay an exported history stored as an artifact
+    #[serde(default)]
+    pub artifact_id: Option<String>,
+}
+
+/// Parameters for checkpoint action
+#[derive(Debug, Deserialize)]
+pub struct CheckpointParams {
+    pub selector: Selector,
+    pub name: String,
+}
+
+/// Parameters for extract_blocks action
+#[derive(Debug, Deserialize)]
+pub struct ExtractBlocksParams {
+    pub selector: Selector,
+    /// Only consider output captured after this checkpoint
+    #[serde(default)]
+    pub since_checkpoint: Option<String>,
+    /// Save each block to the artifact store and return ids instead of content
+    #[serde(default)]
+    pub store: bool,
+}
+
+/// Parameters for store_capture action
+#[derive(Debug, Deserialize)]
+pub struct StoreCaptureParams {
+    pub selector: Selector,
+    #[serde(default)]
+    pub since_checkpoint: Option<String>,
+}
+
+/// Parameters for list_turns action
+#[derive(Debug, Default, Deserialize)]
+pub struct ListTurnsParams {
+    /// Limit to the turns of matching panes; a handle also finds the turns
+    /// of a closed pane (default: every pane)
+    #[serde(default)]
+    pub selector: Option<Selector>,
+}
+
+/// Parameters for export_conversation action
+#[derive(Debug, Deserialize)]
+pub struct ExportConversationParams {
+    /// Panes whose turns are exported; a handle also finds a closed pane's
+    pub selector: Selector,
+    #[serde(default)]
+    pub format: ConversationFormat,
+}
+
+/// Parameters for handover_context action
+#[derive(Debug, Deserialize)]
+pub struct HandoverContextParams {
+    /// Agent whose turns are handed over; a handle also finds a closed pane
+    pub from: Selector,
+    /// Replacement agent
+    pub to: Selector,
+    /// Agent pane asked to summarize the turns first
+    #[serde(default)]
+    pub via: Option<Selector>,
+    /// Most recent turns included (default: 20)
+    #[serde(default)]
+    pub turns: Option<usize>,
+    /// Override the configured templates
+    #[serde(default)]
+    pub template: Option<String>,
+    #[serde(default)]
+    pub summary_template: Option<String>,
+}
+
+/// Parameters for fanout action
+#[derive(Debug, Deserialize)]
+pub struct FanoutParams {
+    /// Agents to prompt
+    #[serde(default)]
+    pub selectors: Vec<Selector>,
+    /// Prompt every agent of these kinds too
+    #[serde(default)]
+    pub kinds: Vec<String>,
+    pub text: String,
+    /// Seconds to wait for the replies, and again for the judge (default: 600)
+    #[serde(default)]
+    pub timeout_secs: Option<u64>,
+    /// Agent asked to pick the best reply
+    #[serde(default)]
+    pub judge: Option<Selector>,
+    /// Override the configured judge template
+    #[serde(default)]
+    pub judge_template: Option<String>,
+}
+
+/// Parameters for get_turn action
+#[derive(Debug, Deserialize)]
+pub struct TurnIdParam {
+    pub id: String,
+}
+
+/// Parameters for get_artifact action
+#[derive(Debug, Deserialize)]
+pub struct ArtifactIdParam {
+    pub id: String,
+    #[serde(default)]
+    pub encoding: OutputEncoding,
+    /// Byte to start at; a truncated reply gives the next one as `next_offset`
+    #[serde(default)]
+    pub offset: usize,
+    /// Most bytes to return (default and cap: max_response_bytes)
+    #[serde(default)]
+    pub length: Option<usize>,
+}
+
+/// Parameters for send_secret action
+#[derive(Debug, Deserialize)]
+pub struct SendSecretParams {
+    pub selector: Selector,
+    /// Reference such as `env:API_KEY`; never the value itself
+    pub secret_ref: String,
+    #[serde(default)]
+    pub enter: bool,
+    #[serde(default)]
+    pub priority: Priority,
+}
+
+/// Parameters for put_file action
+///
+/// The directory is the given project's, else the selected pane's working
+/// directory, else its agent's project.
+#[derive(Debug, Deserialize)]
+pub struct PutFileParams {
+    #[serde(default)]
+    pub project: Option<String>,
+    #[serde(default)]
+    pub selector: Option<Selector>,
+    /// Path relative to the pane's or project's directory
+    pub path: String,
+    /// File content, base64-encoded
+    pub content: String,
+}
+
+/// Parameters for git_info action
+#[derive(Debug, Deserialize)]
+pub struct GitInfoParams {
+    #[serde(default)]
+    pub project: Option<String>,
+    #[serde(default)]
+    pub selector: Option<Selector>,
+    /// Run git even if a recent result is cached
+    #[serde(default)]
+    pub refresh: bool,
+}
+
+/// Parameters for get_file action
+///
+/// The directory is chosen like put_file's.
+#[derive(Debug, Deserialize)]
+pub struct GetFileParams {
+    #[serde(default)]
+    pub project: Option<String>,
+    #[serde(default)]
+    pub selector: Option<Selector>,
+    /// Path relative to the pane's or project's directory
+    pub path: String,
+    #[serde(default)]
+    pub encoding: OutputEncoding,
+}
+
+/// Parameters for recall action
+#[derive(Debug, Deserialize)]
+pub struct RecallParams {
+    /// Position in the history, 0 being the most recent capture
+    #[serde(default)]
+    pub n: usize,
+    /// Re-send the recalled text to this pane instead of returning it
+    #[serde(default)]
+    pub send_to: Option<Selector>,
+    #[serde(default)]
+    pub enter: bool,
+}
+
+/// Parameters for apply_patch action
+#[derive(Debug, Deserialize)]
+pub struct ApplyPatchParams {
+    /// Pane whose output contains the diff
+    pub selector: Selector,
+    /// Worktree the patch is applied to
+    pub cwd: String,
+    #[serde(default)]
+    pub since_checkpoint: Option<String>,
+    /// Index among the extracted diffs (default: the last one)
+    #[serde(default)]
+    pub index: Option<usize>,
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// One operation inside a transaction
+#[derive(Debug, Deserialize)]
+pub struct TransactionOp {
+    pub action: String,
+    #[serde(default)]
+    pub params: Value,
+}
+
+/// Parameters for transaction action
+#[derive(Debug, Deserialize)]
+pub struct TransactionParams {
+    pub requests: Vec<TransactionOp>,
+}
+
+/// Parameters for broadcast action
+#[derive(Debug, Deserialize)]
+pub struct BroadcastParams {
+    /// Action run once per target pane
+    pub action: String,
+    /// Params for each run; `pane_id` and `selector` are set per target
+    #[serde(default)]
+    pub params: Value,
+    /// Panes to target; a prefix selector covers a whole project
+    pub selectors: Vec<Selector>,
 }
 
 #[cfg(test)]
@@ -56,8 +953,8 @@ mod tests {
 
         assert_eq!(req.action, "send_keys");
         let params: SendKeysParams = serde_json::from_value(req.params).unwrap();
-        assert_eq!(params.pane_id, 3);
-        assert_eq!(params.text, "hello");
+        assert_eq!(params.pane_id, Selector::Id(3));
+        assert_eq!(params.text.as_deref(), Some("hello"));
         assert!(params.enter);
     }
 
@@ -69,6 +966,7 @@ mod tests {
         assert_eq!(req.id, "789");
         assert_eq!(req.action, "list_panes");
         assert!(req.params.is_null());
+        assert!(!req.explain);
     }
 
     #[test]
@@ -92,6 +990,7 @@ mod tests {
             success: true,
             data: Some(serde_json::json!({"panes": []})),
             error: None,
+            code: None,
         };
         let json = serde_json::to_string(&resp).unwrap();
 
@@ -108,6 +1007,7 @@ mod tests {
             success: false,
             data: None,
             error: Some("pane not found".to_string()),
+            code: None,
         };
         let json = serde_json::to_string(&resp).unwrap();
 
@@ -121,8 +1021,8 @@ mod tests {
         let json = r#"{"pane_id":1,"text":"test"}"#;
         let params: SendKeysParams = serde_json::from_str(json).unwrap();
 
-        assert_eq!(params.pane_id, 1);
-        assert_eq!(params.text, "test");
+        assert_eq!(params.pane_id, Selector::Id(1));
+        assert_eq!(params.text.as_deref(), Some("test"));
         assert!(!params.enter); // Default is false
     }
 
@@ -131,6 +1031,58 @@ mod tests {
         let json = r#"{"pane_id":42}"#;
         let param: PaneIdParam = serde_json::from_str(json).unwrap();
 
-        assert_eq!(param.pane_id, 42);
+        assert_eq!(param.pane_id, Selector::Id(42));
+
+        let param: PaneIdParam = serde_json::from_str(r#"{"pane_id":"focused"}"#).unwrap();
+        assert_eq!(param.pane_id, Selector::Focused);
+    }
+
+    #[test]
+    fn test_response_helpers() {
+        let ok = Response::ok("1", serde_json::json!({"x": 1}));
+        assert!(ok.success);
+        assert_eq!(ok.data.unwrap()["x"], 1);
+
+        let err = Response::err("2", "boom");
+        assert!(!err.success);
+        assert_eq!(err.id, "2");
+        assert_eq!(err.error.unwrap(), "boom");
+    }
+
+    #[test]
+    fn test_extract_blocks_params() {
+        let json = r#"{"selector":"proj__cc_1","since_checkpoint":"before"}"#;
+        let params: ExtractBlocksParams = serde_json::from_str(json).unwrap();
+
+        assert_eq!(params.selector, Selector::Title("proj__cc_1".to_string()));
+        assert_eq!(params.since_checkpoint.as_deref(), Some("before"));
+
+        let json = r#"{"selector":3}"#;
+        let params: ExtractBlocksParams = serde_json::from_str(json).unwrap();
+        assert_eq!(params.selector, Selector::Id(3));
+        assert!(params.since_checkpoint.is_none());
+    }
+
+    #[test]
+    fn test_response_to_line_is_newline_framed() {
+        let line = Response::ok("7", serde_json::json!({"a": 1})).to_line().unwrap();
+
+        assert!(line.ends_with('\n'));
+        assert_eq!(line.matches('\n').count(), 1);
+        let parsed: serde_json::Value = serde_json::from_str(line.trim_end()).unwrap();
+        assert_eq!(parsed["id"], "7");
+    }
+
+    #[test]
+    fn test_error_codes() {
+        let json = Response::err_code("1", ErrorCode::NotFound, "turn not found: 4").to_line().unwrap();
+        assert!(json.contains(r#""code":"not_found""#));
+        let json = Response::err_code("1", ErrorCode::StaleRevision, "stale revision: at 3").to_line().unwrap();
+        assert!(json.contains(r#""code":"stale_revision""#));
+
+        // The code is never guessed from the message
+        assert_eq!(Response::err("1", "pane not found: 7").code, Some(ErrorCode::Failed));
+        let error = Error::new(ErrorCode::InvalidParams, "invalid params: missing field `text`");
+        assert_eq!(Response::err("1", error).code, Some(ErrorCode::InvalidParams));
     }
 }
@@ -0,0 +1,108 @@
+//! Requests sent from Zellij keybindings
+//!
+//! A `MessagePlugin` keybinding names the action and may carry its params
+//! as a JSON payload. Actions that target a pane get the focused one unless
+//! the payload gives `pane_id` or `selector`:
+//!
+//! ```kdl
+//! bind "Alt i" { MessagePlugin "nzm-agent" { name "send_interrupt"; }; }
+//! bind "Alt k" {
+//!     MessagePlugin "nzm-agent" {
+//!         name "send_keys"
+//!         payload "{\"text\": \"/compact\", \"enter\": true}"
+//!     }
+//! }
+//! ```
+//!
+//! A payload holding a whole request (with `action`) is used as is. There
+//! is no pipe to reply on, so results only show in the plugin's log.
+
+use serde_json::Value;
+
+use crate::ipc::Request;
+use crate::state::State;
+
+/// Id given to requests built from a keybinding
+pub const KEYBIND_REQUEST_ID: &str = "keybind";
+
+/// Build the request a keybinding message stands for
+pub fn keybind_request(name: &str, payload: Option<&str>, state: &State) -> Result<Request, String> {
+    let payload = payload.map(str::trim).filter(|p| !p.is_empty());
+    let mut params = match payload {
+        Some(payload) => {
+            let value: Value = serde_json::from_str(payload)
+                .map_err(|e| format!("keybinding {}: payload is not JSON: {}", name, e))?;
+            if value.get("action").is_some() {
+                return serde_json::from_value(value).map_err(|e| format!("keybinding {}: {}", name, e));
+            }
+            value
+        }
+        None => Value::Object(Default::default()),
+    };
+    let Some(object) = params.as_object_mut() else {
+        return Err(format!("keybinding {}: payload must be a JSON object", name));
+    };
+
+    if !object.contains_key("pane_id") && !object.contains_key("selector") {
+        let pane = state
+            .focused_pane()
+            .ok_or_else(|| format!("keybinding {}: no focused pane", name))?;
+        // Handlers read whichever of the two they take
+        object.insert("pane_id".to_string(), pane.id.into());
+        object.insert("selector".to_string(), pane.id.into());
+    }
+
+    Ok(Request {
+        id: KEYBIND_REQUEST_ID.to_string(),
+        action: name.to_string(),
+        params,
+        explain: false,
+        if_revision: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::dispatch_command;
+    use zellij_tile::prelude::{PaneInfo, PaneManifest};
+
+    fn state_with_focus() -> State {
+        let mut state = State::default();
+        let mut manifest = PaneManifest::default();
+        manifest.panes.insert(0, vec![
+            PaneInfo { id: 1, title: "proj__cc_1".to_string(), ..Default::default() },
+            PaneInfo { id: 2, title: "proj__cc_2".to_string(), is_focused: true, ..Default::default() },
+        ]);
+        state.update_panes(manifest);
+        state
+    }
+
+    #[test]
+    fn test_targets_focused_pane() {
+        let mut state = state_with_focus();
+        let req = keybind_request("send_keys", Some(r#"{"text": "/compact", "enter": true}"#), &state).unwrap();
+
+        let data = dispatch_command(&req, &mut state).data.unwrap();
+        assert_eq!(data["pane_id"], 2);
+        assert_eq!(data["text"], "/compact");
+
+        let req = keybind_request("send_interrupt", None, &state).unwrap();
+        assert_eq!(dispatch_command(&req, &mut state).data.unwrap()["pane_id"], 2);
+    }
+
+    #[test]
+    fn test_explicit_target_and_full_requests() {
+        let state = state_with_focus();
+
+        let req = keybind_request("send_interrupt", Some(r#"{"pane_id": 1}"#), &state).unwrap();
+        assert_eq!(req.params["pane_id"], 1);
+        assert!(req.params.get("selector").is_none());
+
+        let req = keybind_request("x", Some(r#"{"id": "k1", "action": "list_panes"}"#), &state).unwrap();
+        assert_eq!((req.id.as_str(), req.action.as_str()), ("k1", "list_panes"));
+
+        assert!(keybind_request("send_keys", Some("[1]"), &state).unwrap_err().contains("JSON object"));
+        assert!(keybind_request("send_keys", Some("{"), &state).unwrap_err().contains("not JSON"));
+    }
+}
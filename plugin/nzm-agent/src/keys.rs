@@ -0,0 +1,193 @@
+//! Key encoding for send_keys and send_interrupt
+//!
+//! Keys are named the way they are labelled, so TUIs such as permission
+//! prompts and menus can be driven without spelling out escape sequences.
+//! Each name becomes the bytes an xterm-compatible terminal sends for the
+//! key; names are case-insensitive:
+//!
+//! - `Enter`, `Tab`, `BackTab`, `Escape`, `Backspace`, `Space`
+//! - `Up`, `Down`, `Left`, `Right`, `Home`, `End`, `PageUp`, `PageDown`,
+//!   `Insert`, `Delete`
+//! - `F1` to `F12`
+//! - any single character, such as `y` or `/`
+//!
+//! Modifiers go in front, joined with `+` or `-`: `ctrl+c`, `alt+b`,
+//! `shift+tab`, `ctrl+alt+x`, `ctrl+Left`. Ctrl works with letters and
+//! `@[\]^_?` and Space, Shift with letters and Tab, and Alt with anything
+//! by sending Escape first. The cursor, editing and function keys take
+//! any modifiers as xterm's parameter, as in `\x1b[1;5D` for `ctrl+Left`.
+//!
+//! Arrows use the normal cursor key mode. A pane that switched to
+//! application mode still reads them, as most applications accept both.
+
+use crate::ipc::{Error, ErrorCode};
+
+const SHIFT: u8 = 1;
+const ALT: u8 = 2;
+const CTRL: u8 = 4;
+
+/// Key send_interrupt sends when the request does not name one
+pub const INTERRUPT_KEY: &str = "ctrl+c";
+
+/// How a named key is encoded
+enum Key {
+    /// A fixed sequence that takes no xterm modifier parameter
+    Plain(&'static str),
+    /// `\x1b[<final>`, or `\x1bO<final>` when `ss3`; modified as
+    /// `\x1b[1;<m><final>`
+    Final(char, bool),
+    /// `\x1b[<n>~`; modified as `\x1b[<n>;<m>~`
+    Tilde(u8),
+}
+
+fn named_key(name: &str) -> Option<Key> {
+    let key = match name {
+        "enter" | "return" => Key::Plain("\r"),
+        "tab" => Key::Plain("\t"),
+        "backtab" => Key::Plain("\x1b[Z"),
+        "escape" | "esc" => Key::Plain("\x1b"),
+        "backspace" => Key::Plain("\x7f"),
+        "space" => Key::Plain(" "),
+        "up" => Key::Final('A', false),
+        "down" => Key::Final('B', false),
+        "right" => Key::Final('C', false),
+        "left" => Key::Final('D', false),
+        "home" => Key::Final('H', false),
+        "end" => Key::Final('F', false),
+        "f1" => Key::Final('P', true),
+        "f2" => Key::Final('Q', true),
+        "f3" => Key::Final('R', true),
+        "f4" => Key::Final('S', true),
+        "pageup" => Key::Tilde(5),
+        "pagedown" => Key::Tilde(6),
+        "insert" => Key::Tilde(2),
+        "delete" => Key::Tilde(3),
+        "f5" => Key::Tilde(15),
+        "f6" => Key::Tilde(17),
+        "f7" => Key::Tilde(18),
+        "f8" => Key::Tilde(19),
+        "f9" => Key::Tilde(20),
+        "f10" => Key::Tilde(21),
+        "f11" => Key::Tilde(23),
+        "f12" => Key::Tilde(24),
+        _ => return None,
+    };
+    Some(key)
+}
+
+/// Split leading modifiers off a key name
+fn split_modifiers(name: &str) -> (u8, &str) {
+    let mut modifiers = 0;
+    let mut rest = name;
+    while let Some(i) = rest.find(['+', '-']).filter(|&i| i > 0 && i + 1 < rest.len()) {
+        let modifier = match rest[..i].to_ascii_lowercase().as_str() {
+            "shift" => SHIFT,
+            "alt" | "meta" => ALT,
+            "ctrl" | "control" => CTRL,
+            _ => break,
+        };
+        modifiers |= modifier;
+        rest = &rest[i + 1..];
+    }
+    (modifiers, rest)
+}
+
+/// The bytes a terminal sends for a key, with any modifiers
+pub fn key_sequence(name: &str) -> Option<String> {
+    let (modifiers, base) = split_modifiers(name);
+    let lower = base.to_ascii_lowercase();
+    let param = modifiers + 1;
+    match named_key(&lower) {
+        Some(Key::Final(c, ss3)) if modifiers == 0 => Some(format!("\x1b{}{}", if ss3 { 'O' } else { '[' }, c)),
+        Some(Key::Final(c, _)) => Some(format!("\x1b[1;{}{}", param, c)),
+        Some(Key::Tilde(n)) if modifiers == 0 => Some(format!("\x1b[{}~", n)),
+        Some(Key::Tilde(n)) => Some(format!("\x1b[{};{}~", n, param)),
+        _ if modifiers & ALT != 0 => {
+            // Alt sends Escape before the key
+            let rest = modifiers & !ALT;
+            plain_sequence(rest, &lower, base).map(|seq| format!("\x1b{}", seq))
+        }
+        _ => plain_sequence(modifiers, &lower, base),
+    }
+}
+
+/// A key without an xterm modifier form, under Shift or Ctrl
+fn plain_sequence(modifiers: u8, lower: &str, base: &str) -> Option<String> {
+    let c = match named_key(lower) {
+        Some(Key::Plain(seq)) if modifiers == 0 => return Some(seq.to_string()),
+        Some(Key::Plain("\t")) if modifiers == SHIFT => return Some("\x1b[Z".to_string()),
+        Some(Key::Plain(" ")) => ' ',
+        Some(_) => return None,
+        None => {
+            let mut chars = base.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => c,
+                _ => return None,
+            }
+        }
+    };
+    match modifiers {
+        0 => Some(c.to_string()),
+        SHIFT if c.is_ascii_alphabetic() => Some(c.to_ascii_uppercase().to_string()),
+        CTRL => match c.to_ascii_uppercase() {
+            ' ' => Some("\0".to_string()),
+            '?' => Some("\x7f".to_string()),
+            c @ ('@'..='_') => Some(char::from(c as u8 & 0x1f).to_string()),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+/// The bytes for a list of keys, in order
+pub fn keys_text(names: &[String]) -> Result<String, Error> {
+    names
+        .iter()
+        .map(|name| {
+            key_sequence(name)
+                .ok_or_else(|| Error::new(ErrorCode::InvalidParams, format!("invalid params: unknown key: {}", name)))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_named_keys() {
+        let keys = ["Escape", "up", "Enter", "F2", "Ctrl-C"].map(String::from);
+        assert_eq!(keys_text(&keys).unwrap(), "\x1b\x1b[A\r\x1bOQ\x03");
+        assert_eq!(
+            keys_text(&["F13".to_string()]).unwrap_err(),
+            Error::new(ErrorCode::InvalidParams, "invalid params: unknown key: F13")
+        );
+        assert!(key_sequence("Ctrl-1").is_none());
+    }
+
+    #[test]
+    fn test_modifiers() {
+        let cases = [
+            ("ctrl+d", "\x04"),
+            ("ctrl+[", "\x1b"),
+            ("ctrl+space", "\0"),
+            ("alt+b", "\x1bb"),
+            ("alt+enter", "\x1b\r"),
+            ("ctrl+alt+x", "\x1b\x18"),
+            ("shift+tab", "\x1b[Z"),
+            ("shift+a", "A"),
+            ("ctrl+left", "\x1b[1;5D"),
+            ("shift+alt+up", "\x1b[1;4A"),
+            ("ctrl+F1", "\x1b[1;5P"),
+            ("ctrl+delete", "\x1b[3;5~"),
+            ("+", "+"),
+            ("y", "y"),
+        ];
+        for (name, sequence) in cases {
+            assert_eq!(key_sequence(name).as_deref(), Some(sequence), "{}", name);
+        }
+        assert!(key_sequence("shift+enter").is_none());
+        assert!(key_sequence("hyper+a").is_none());
+        assert_eq!(key_sequence(INTERRUPT_KEY).as_deref(), Some("\x03"));
+    }
+}
@@ -0,0 +1,344 @@
+//! Registry of agent kinds
+//!
+//! The built-in kinds are `cc` (Claude Code), `cod` (Codex) and `gmi`
+//! (Gemini). More are declared with a `kind.<name>` config key holding JSON:
+//!
+//! ```json
+//! {"aliases": ["aider-chat"], "command": "aider --no-auto-commits",
+//!  "dialect": "aider", "cwd": "~/src/{project}",
+//!  "presets": {"opus": "--model opus", "sonnet": "--model sonnet"},
+//!  "status": [{"state": "idle", "regex": "^> $"},
+//!             {"state": "working", "regex": "Tokens: .* sent"}],
+//!  "ready": "^> $", "ready_timeout_secs": 60,
+//!  "initial": ["Read CONVENTIONS.md", "Work in the {project}-{index} worktree"],
+//!  "load_env": ["direnv", "mise"],
+//!  "container": {"runtime": "docker", "name": "{project}-dev", "workdir": "/src"}}
+//! ```
+//!
+//! A user kind with a built-in name replaces the built-in. Presets are
+//! arguments appended to the command when spawn_agent names one. `ready`
+//! matches the banner or prompt an agent shows once it has launched; until
+//! then a spawned agent gets no tasks, since input typed while the CLI
+//! starts up is lost. Kinds without one are ready as soon as they open.
+//! An agent that has not matched `ready` after `ready_timeout_secs`
+//! (default 120) is made ready anyway, and its `agent_ready` event says
+//! `timed_out`, so a pattern that never matches cannot hold it forever.
+//! The `initial` messages, with `{project}`, `{kind}` and `{index}` filled
+//! in, are then sent as the agent's first prompts. `load_env` names tools
+//! (`direnv`, `mise`, `asdf`) whose environment for the working directory
+//! is loaded before the command starts, so a spawned agent has the same
+//! toolchain as a shell opened there. With `container`,
+//! spawn_agent starts the agent inside the project's container, through
+//! `docker exec` or, with `{"runtime": "devcontainer"}`, `devcontainer
+//! exec` in the project directory.
+
+use std::collections::BTreeMap;
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+use crate::config::DEFAULT_STATUS_LINES;
+use crate::spawn::{Container, EnvLoader};
+
+/// Default time a spawned agent has to show its ready pattern
+pub const DEFAULT_READY_TIMEOUT_SECS: u64 = 120;
+
+/// A status recognised from a pane's recent output
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct StatusPattern {
+    pub state: String,
+    pub regex: String,
+}
+
+/// An agent kind and how to recognise and start it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgentKind {
+    #[serde(default)]
+    pub name: String,
+    /// Other names accepted wherever a kind is expected
+    #[serde(default)]
+    pub aliases: Vec<String>,
+    /// Command line that starts the agent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<String>,
+    /// Prompt/response dialect, for consumers that parse agent output
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub dialect: Option<String>,
+    /// Working directory template for spawn_agent, with `{project}`,
+    /// `{kind}` and `{index}` (default: the project under projects_base)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    /// Named sets of extra command arguments, such as model choices
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub presets: BTreeMap<String, String>,
+    /// Checked in order against recent output; the first match wins
+    #[serde(default)]
+    pub status: Vec<StatusPattern>,
+    /// Output showing a spawned agent has finished launching
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ready: Option<String>,
+    /// Seconds to wait for `ready` before giving up on it
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ready_timeout_secs: Option<u64>,
+    /// Prompts sent once a spawned agent is ready, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub initial: Vec<String>,
+    /// Tools whose environment is loaded before the command starts, in order
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub load_env: Vec<EnvLoader>,
+    /// Container spawn_agent starts the command in
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub container: Option<Container>,
+}
+
+impl AgentKind {
+    /// Parse the JSON value of a `kind.<name>` config key
+    pub fn parse(name: &str, json: &str) -> Result<Self, String> {
+        let mut kind: AgentKind =
+            serde_json::from_str(json).map_err(|e| format!("invalid agent kind {}: {}", name, e))?;
+        for pattern in &kind.status {
+            Regex::new(&pattern.regex)
+                .map_err(|e| format!("invalid agent kind {}: status {}: {}", name, pattern.state, e))?;
+        }
+        if let Some(ready) = &kind.ready {
+            Regex::new(ready).map_err(|e| format!("invalid agent kind {}: ready: {}", name, e))?;
+        }
+        if let Some(Container::Docker { name: container, .. }) = &kind.container {
+            if container.trim().is_empty() {
+                return Err(format!("invalid agent kind {}: container name may not be empty", name));
+            }
+        }
+        kind.name = name.to_string();
+        Ok(kind)
+    }
+
+    fn builtin(name: &str, aliases: &[&str], command: &str) -> Self {
+        AgentKind {
+            name: name.to_string(),
+            aliases: aliases.iter().map(|a| a.to_string()).collect(),
+            command: Some(command.to_string()),
+            dialect: Some(name.to_string()),
+            cwd: None,
+            presets: BTreeMap::new(),
+            status: Vec::new(),
+            ready: None,
+            ready_timeout_secs: None,
+            initial: Vec::new(),
+            load_env: Vec::new(),
+            container: None,
+        }
+    }
+
+    /// Whether `name` is this kind's name or one of its aliases
+    pub fn answers_to(&self, name: &str) -> bool {
+        self.name.eq_ignore_ascii_case(name) || self.aliases.iter().any(|a| a.eq_ignore_ascii_case(name))
+    }
+}
+
+/// Built-in and configured kinds with compiled status patterns
+#[derive(Debug, Clone)]
+pub struct KindRegistry {
+    kinds: Vec<CompiledKind>,
+    /// Lines with text, counted from the bottom, that status patterns see
+    status_lines: usize,
+}
+
+/// A kind with its patterns compiled
+#[derive(Debug, Clone)]
+struct CompiledKind {
+    kind: AgentKind,
+    status: Vec<(String, Regex)>,
+    ready: Option<Regex>,
+}
+
+impl Default for KindRegistry {
+    fn default() -> Self {
+        KindRegistry::new(&[])
+    }
+}
+
+impl KindRegistry {
+    /// Build the registry from configured kinds layered over the built-ins
+    pub fn new(configured: &[AgentKind]) -> Self {
+        let mut kinds = vec![
+            AgentKind::builtin("cc", &["claude", "claude-code"], "claude"),
+            AgentKind::builtin("cod", &["codex"], "codex"),
+            AgentKind::builtin("gmi", &["gemini"], "gemini"),
+        ];
+        for kind in configured {
+            match kinds.iter_mut().find(|k| k.name == kind.name) {
+                Some(existing) => *existing = kind.clone(),
+                None => kinds.push(kind.clone()),
+            }
+        }
+        let kinds = kinds
+            .into_iter()
+            .map(|kind| {
+                // Patterns were validated when the config was parsed
+                let status = kind
+                    .status
+                    .iter()
+                    .filter_map(|p| Some((p.state.clone(), Regex::new(&p.regex).ok()?)))
+                    .collect();
+                let ready = kind.ready.as_deref().and_then(|r| Regex::new(r).ok());
+                CompiledKind { kind, status, ready }
+            })
+            .collect();
+        KindRegistry { kinds, status_lines: DEFAULT_STATUS_LINES }
+    }
+
+    /// Set how many of a pane's last lines status patterns are run on
+    pub fn set_status_lines(&mut self, n: usize) {
+        self.status_lines = n;
+    }
+
+    /// Look up a kind by name or alias
+    pub fn resolve(&self, name: &str) -> Option<&AgentKind> {
+        self.kinds.iter().map(|c| &c.kind).find(|k| k.answers_to(name))
+    }
+
+    /// Find the kind whose program a pane's terminal command runs
+    ///
+    /// Compares the program's file name, skipping `env`, `npx` and
+    /// `VAR=value` prefixes, with each kind's command, name and aliases.
+    pub fn detect_command(&self, command: &str) -> Option<&AgentKind> {
+        let program = program_name(command)?;
+        self.kinds().find(|kind| {
+            kind.answers_to(program) || kind.command.as_deref().and_then(program_name) == Some(program)
+        })
+    }
+
+    /// All kinds, built-ins first
+    pub fn kinds(&self) -> impl Iterator<Item = &AgentKind> {
+        self.kinds.iter().map(|c| &c.kind)
+    }
+
+    /// Status of a kind's pane from its recent lines, if a pattern matches
+    ///
+    /// Only the last `status_lines` lines up to the last one with text are
+    /// matched, so a long scrollback costs no more than a short one and
+    /// old output cannot decide the status.
+    pub fn status(&self, kind: &str, lines: &[String]) -> Option<&str> {
+        let compiled = self.kinds.iter().find(|c| c.kind.answers_to(kind))?;
+        let end = lines.iter().rposition(|line| !line.trim().is_empty()).map_or(0, |i| i + 1);
+        let lines = &lines[end.saturating_sub(self.status_lines)..end];
+        compiled
+            .status
+            .iter()
+            .find(|(_, re)| lines.iter().any(|line| re.is_match(line)))
+            .map(|(state, _)| state.as_str())
+    }
+
+    /// Whether a kind's pane shows it has finished launching
+    ///
+    /// Kinds without a ready pattern, and unknown kinds, are always ready.
+    pub fn is_ready(&self, kind: &str, lines: &[String]) -> bool {
+        match self.kinds.iter().find(|c| c.kind.answers_to(kind)).and_then(|c| c.ready.as_ref()) {
+            Some(ready) => lines.iter().any(|line| ready.is_match(line)),
+            None => true,
+        }
+    }
+
+    /// Seconds a kind's spawned agent has to show its ready pattern
+    pub fn ready_timeout_secs(&self, kind: &str) -> u64 {
+        self.resolve(kind)
+            .and_then(|kind| kind.ready_timeout_secs)
+            .unwrap_or(DEFAULT_READY_TIMEOUT_SECS)
+    }
+}
+
+/// File name of the program a command line runs
+fn program_name(command: &str) -> Option<&str> {
+    let program = command
+        .split_whitespace()
+        .find(|word| !word.contains('=') && *word != "env" && *word != "npx")?;
+    program.rsplit('/').next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn aider() -> AgentKind {
+        AgentKind::parse(
+            "aider",
+            r#"{"aliases": ["aider-chat"], "command": "aider",
+                "status": [{"state": "idle", "regex": "^> $"}, {"state": "working", "regex": "sent"}]}"#,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn test_builtins_resolve_by_alias() {
+        let registry = KindRegistry::default();
+
+        assert_eq!(registry.resolve("Claude").unwrap().name, "cc");
+        assert_eq!(registry.resolve("cod").unwrap().command.as_deref(), Some("codex"));
+        assert!(registry.resolve("aider").is_none());
+    }
+
+    #[test]
+    fn test_configured_kinds_extend_and_override() {
+        let mut codex = AgentKind::parse("cod", r#"{"command": "codex --full-auto"}"#).unwrap();
+        codex.aliases.push("openai".to_string());
+        let registry = KindRegistry::new(&[aider(), codex]);
+
+        assert_eq!(registry.resolve("aider-chat").unwrap().name, "aider");
+        assert_eq!(registry.resolve("openai").unwrap().command.as_deref(), Some("codex --full-auto"));
+        assert!(registry.resolve("codex").is_none());
+        assert_eq!(registry.kinds().count(), 4);
+    }
+
+    #[test]
+    fn test_status_patterns_in_order() {
+        let registry = KindRegistry::new(&[aider()]);
+        let lines = vec!["Tokens: 2k sent".to_string(), "> ".to_string()];
+
+        assert_eq!(registry.status("aider", &lines), Some("idle"));
+        assert_eq!(registry.status("aider", &lines[..1]), Some("working"));
+        assert_eq!(registry.status("cc", &lines), None);
+    }
+
+    #[test]
+    fn test_status_sees_only_the_last_lines() {
+        let mut registry = KindRegistry::new(&[aider()]);
+        registry.set_status_lines(2);
+        let lines: Vec<String> = ["> ", "Tokens: 2k sent", "editing", "", ""].iter().map(|s| s.to_string()).collect();
+
+        // The idle prompt is three lines up; blank rows below do not count
+        assert_eq!(registry.status("aider", &lines), Some("working"));
+        registry.set_status_lines(3);
+        assert_eq!(registry.status("aider", &lines), Some("idle"));
+    }
+
+    #[test]
+    fn test_ready_pattern() {
+        let kind = AgentKind::parse("aider", r#"{"command": "aider", "ready": "^aider v"}"#).unwrap();
+        let registry = KindRegistry::new(&[kind]);
+
+        assert!(!registry.is_ready("aider", &["Loading...".to_string()]));
+        assert!(registry.is_ready("aider", &["aider v0.50".to_string()]));
+        assert!(registry.is_ready("cc", &[]));
+    }
+
+    #[test]
+    fn test_invalid_kind_rejected() {
+        assert!(AgentKind::parse("x", "{").is_err());
+        assert!(AgentKind::parse("x", r#"{"ready": "("}"#).unwrap_err().contains("ready"));
+        assert!(AgentKind::parse("x", r#"{"status": [{"state": "idle", "regex": "("}]}"#).is_err());
+        assert!(AgentKind::parse("x", r#"{"container": {"runtime": "docker", "name": ""}}"#).unwrap_err().contains("container"));
+        assert!(AgentKind::parse("x", r#"{"container": {"runtime": "podman"}}"#).is_err());
+        assert!(AgentKind::parse("x", r#"{"load_env": ["nix"]}"#).is_err());
+    }
+
+    #[test]
+    fn test_detect_kind_from_command() {
+        let registry = KindRegistry::new(&[aider()]);
+
+        assert_eq!(registry.detect_command("/usr/local/bin/claude --resume").unwrap().name, "cc");
+        assert_eq!(registry.detect_command("env FOO=1 codex").unwrap().name, "cod");
+        assert_eq!(registry.detect_command("npx aider-chat").unwrap().name, "aider");
+        assert!(registry.detect_command("bash -l").is_none());
+        assert!(registry.detect_command("").is_none());
+    }
+}
@@ -1,6 +1,50 @@
 mod ipc;
 mod state;
 mod commands;
+mod selector;
+mod blocks;
+mod patch;
+mod artifacts;
+mod config;
+mod encoding;
+mod redact;
+mod secrets;
+mod scripting;
+mod composite;
+mod write_queue;
+mod frame;
+mod tasks;
+mod history;
+mod naming;
+mod kinds;
+mod files;
+mod git;
+mod handles;
+mod guards;
+mod spawn;
+mod i18n;
+mod dashboard;
+mod memory;
+mod turns;
+mod handover;
+mod fanout;
+mod keys;
+mod events;
+mod integrity;
+mod idle;
+mod hours;
+mod schedule;
+mod clock;
+
+// Only used by the plugin; host builds them for their tests
+#[cfg(any(target_arch = "wasm32", test))]
+mod mirror;
+#[cfg(any(target_arch = "wasm32", test))]
+mod chaos;
+#[cfg(any(target_arch = "wasm32", test))]
+mod keybind;
+#[cfg(any(target_arch = "wasm32", test))]
+mod housekeeping;
 
 // Only include plugin code when building for WASM
 #[cfg(target_arch = "wasm32")]
@@ -8,8 +52,20 @@ mod plugin;
 
 // Re-export for external use
 pub use ipc::{Request, Response, SendKeysParams, PaneIdParam};
-pub use state::State;
-pub use commands::{dispatch_command, PaneDto};
+pub use state::{CaptureEntry, PaneDelta, State};
+pub use commands::{dispatch_command, PaneDto, TabDto};
+pub use selector::Selector;
+pub use blocks::{Block, BlockKind};
+pub use patch::ApplyReport;
+pub use artifacts::{ArtifactMeta, ArtifactStore};
+pub use config::{Config, RedactionRule};
+pub use encoding::OutputEncoding;
+pub use frame::{AttrRun, Frame};
+pub use tasks::{StaleTaskPolicy, Task, TaskStatus, TaskStore, TaskTarget};
+pub use history::{ManifestRecord, PaneRecord};
+pub use naming::{AgentName, TitleSchema};
+pub use kinds::{AgentKind, KindRegistry};
+pub use turns::{Turn, TurnStatus};
 
 // Plugin entry point (WASM only)
 #[cfg(target_arch = "wasm32")]
@@ -0,0 +1,112 @@
+//! Approximate memory accounting for the plugin's buffers
+//!
+//! The host kills a WASM plugin whose memory grows too large, so the
+//! buffers that grow with use are capped in bytes, and the oldest entries
+//! are evicted first once a cap is reached:
+//!
+//! - `output`: captured pane lines, the oldest scrollback of the largest
+//!   pane going first
+//! - `captures`: recent captures kept for `recall`
+//! - `history`: pane manifests kept for `replay_history`
+//! - `artifacts`: the artifact index; evicting an entry deletes the
+//!   artifact from the host too
+//!
+//! Caps are set with `memory.<buffer>` config keys, in bytes. Sizes count
+//! string contents plus a fixed overhead per entry, so they are estimates
+//! of the heap in use, not exact figures.
+
+use std::collections::BTreeMap;
+use std::mem::size_of;
+
+use serde::Serialize;
+
+/// Default byte caps per buffer
+pub const DEFAULT_OUTPUT_BUDGET: usize = 16 * 1024 * 1024;
+pub const DEFAULT_CAPTURES_BUDGET: usize = 4 * 1024 * 1024;
+pub const DEFAULT_HISTORY_BUDGET: usize = 1024 * 1024;
+pub const DEFAULT_ARTIFACTS_BUDGET: usize = 1024 * 1024;
+
+/// Byte caps of the evictable buffers
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct MemoryBudget {
+    pub output: usize,
+    pub captures: usize,
+    pub history: usize,
+    pub artifacts: usize,
+}
+
+impl Default for MemoryBudget {
+    fn default() -> Self {
+        MemoryBudget {
+            output: DEFAULT_OUTPUT_BUDGET,
+            captures: DEFAULT_CAPTURES_BUDGET,
+            history: DEFAULT_HISTORY_BUDGET,
+            artifacts: DEFAULT_ARTIFACTS_BUDGET,
+        }
+    }
+}
+
+impl MemoryBudget {
+    /// Set the cap named by a `memory.<buffer>` key; false for unknown names
+    pub fn set(&mut self, buffer: &str, bytes: usize) -> bool {
+        let cap = match buffer {
+            "output" => &mut self.output,
+            "captures" => &mut self.captures,
+            "history" => &mut self.history,
+            "artifacts" => &mut self.artifacts,
+            _ => return false,
+        };
+        *cap = bytes;
+        true
+    }
+}
+
+/// Estimated bytes held by each buffer
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct MemoryUsage {
+    pub output: usize,
+    pub captures: usize,
+    pub history: usize,
+    pub artifacts: usize,
+    pub tasks: usize,
+    pub turns: usize,
+    pub events: usize,
+    pub total: usize,
+    /// Entries evicted to stay within budget since the plugin loaded
+    pub evicted: BTreeMap<&'static str, u64>,
+}
+
+impl MemoryUsage {
+    /// Fill in `total` from the per-buffer figures
+    pub fn with_total(mut self) -> Self {
+        self.total = self.output + self.captures + self.history + self.artifacts + self.tasks + self.turns + self.events;
+        self
+    }
+}
+
+/// Estimated heap bytes of a string
+pub fn string_bytes(s: &str) -> usize {
+    s.len() + size_of::<String>()
+}
+
+/// Estimated heap bytes of lines of text
+pub fn lines_bytes(lines: &[String]) -> usize {
+    lines.iter().map(|line| string_bytes(line)).sum()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_keys_and_totals() {
+        let mut budget = MemoryBudget::default();
+        assert!(budget.set("output", 100));
+        assert!(!budget.set("heap", 100));
+        assert_eq!(budget.output, 100);
+
+        let usage = MemoryUsage { output: 10, tasks: 5, ..MemoryUsage::default() }.with_total();
+        assert_eq!(usage.total, 15);
+        assert_eq!(lines_bytes(&["ab".to_string()]), 2 + size_of::<String>());
+    }
+}
@@ -0,0 +1,175 @@
+//! Compact state frames streamed to `mirror_state` subscribers
+//!
+//! Each frame is one JSON line. Panes are encoded as `[id, title, flags]`
+//! arrays, where flags has bit 0 for focused and bit 1 for floating:
+//!
+//! - full: `{"m":<rev>,"full":[[1,"proj__cc_1",1],...]}`
+//! - delta: `{"m":<rev>,"a":[...added],"c":[...changed],"r":[...removed ids]}`
+//! - event: `{"m":<rev>,"event":{"type":"safe_word",...}}`
+
+use serde_json::{json, Value};
+use std::collections::BTreeMap;
+use zellij_tile::prelude::PaneInfo;
+
+use crate::state::State;
+
+const FLAG_FOCUSED: u8 = 1;
+const FLAG_FLOATING: u8 = 2;
+
+/// Encode one pane as `[id, title, flags]`
+pub fn encode_pane(pane: &PaneInfo) -> Value {
+    let mut flags = 0;
+    if pane.is_focused {
+        flags |= FLAG_FOCUSED;
+    }
+    if pane.is_floating {
+        flags |= FLAG_FLOATING;
+    }
+    json!([pane.id, pane.title, flags])
+}
+
+/// Frame carrying the whole pane list
+pub fn full_frame(state: &State) -> String {
+    let panes: Vec<Value> = state.panes().iter().map(encode_pane).collect();
+    json!({ "m": state.revision(), "full": panes }).to_string() + "\n"
+}
+
+/// Frame carrying the changes since `revision`, or a full frame when the
+/// delta can no longer be computed
+pub fn frame_since(state: &State, revision: u64) -> String {
+    let Some(delta) = state.panes_since(revision) else {
+        return full_frame(state);
+    };
+    let added: Vec<Value> = delta.added.into_iter().map(encode_pane).collect();
+    let changed: Vec<Value> = delta.changed.into_iter().map(encode_pane).collect();
+    json!({
+        "m": state.revision(),
+        "a": added,
+        "c": changed,
+        "r": delta.removed,
+    })
+    .to_string()
+        + "\n"
+}
+
+/// CLI pipes subscribed to state updates and the revision each last saw
+#[derive(Default)]
+pub struct Mirrors {
+    subscribers: BTreeMap<String, u64>,
+}
+
+impl Mirrors {
+    /// Subscribe a pipe, returning its initial full frame
+    pub fn subscribe(&mut self, cli_id: &str, state: &State) -> String {
+        self.subscribers.insert(cli_id.to_string(), state.revision());
+        full_frame(state)
+    }
+
+    /// An event frame for every subscriber
+    pub fn event_frames(&self, state: &State, kind: &str, event: &Value) -> Vec<(String, String)> {
+        let mut event = event.clone();
+        event["type"] = json!(kind);
+        let frame = json!({ "m": state.revision(), "event": event }).to_string() + "\n";
+        self.subscribers.keys().map(|cli_id| (cli_id.clone(), frame.clone())).collect()
+    }
+
+    /// Frames owed to subscribers that have not seen the current revision
+    pub fn pending_frames(&mut self, state: &State) -> Vec<(String, String)> {
+        let mut frames = Vec::new();
+        for (cli_id, seen) in self.subscribers.iter_mut() {
+            if *seen != state.revision() {
+                frames.push((cli_id.clone(), frame_since(state, *seen)));
+                *seen = state.revision();
+            }
+        }
+        frames
+    }
+
+    /// Drop every subscriber, returning the pipes to release
+    pub fn close(&mut self) -> Vec<String> {
+        std::mem::take(&mut self.subscribers).into_keys().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zellij_tile::prelude::PaneManifest;
+
+    fn pane(id: u32, title: &str, is_focused: bool) -> PaneInfo {
+        PaneInfo {
+            id,
+            title: title.to_string(),
+            is_focused,
+            ..Default::default()
+        }
+    }
+
+    fn update(state: &mut State, panes: Vec<PaneInfo>) {
+        let mut manifest = PaneManifest::default();
+        manifest.panes.insert(0, panes);
+        state.update_panes(manifest);
+    }
+
+    fn parse(frame: &str) -> Value {
+        assert!(frame.ends_with('\n'));
+        serde_json::from_str(frame.trim_end()).unwrap()
+    }
+
+    #[test]
+    fn test_encode_pane_flags() {
+        let mut p = pane(4, "x", true);
+        p.is_floating = true;
+        assert_eq!(encode_pane(&p), json!([4, "x", 3]));
+    }
+
+    #[test]
+    fn test_subscriber_receives_full_then_deltas() {
+        let mut state = State::default();
+        update(&mut state, vec![pane(1, "a", true)]);
+        let mut mirrors = Mirrors::default();
+
+        let first = parse(&mirrors.subscribe("cli-1", &state));
+        assert_eq!(first["full"], json!([[1, "a", 1]]));
+        assert!(mirrors.pending_frames(&state).is_empty());
+
+        update(&mut state, vec![pane(1, "a", false), pane(2, "b", true)]);
+        let frames = mirrors.pending_frames(&state);
+        assert_eq!(frames.len(), 1);
+        assert_eq!(frames[0].0, "cli-1");
+        let delta = parse(&frames[0].1);
+        assert_eq!(delta["m"], state.revision());
+        assert_eq!(delta["a"], json!([[2, "b", 1]]));
+        assert_eq!(delta["c"], json!([[1, "a", 0]]));
+        assert_eq!(delta["r"], json!([]));
+
+        assert!(mirrors.pending_frames(&state).is_empty());
+    }
+
+    #[test]
+    fn test_event_frames_go_to_every_subscriber() {
+        let state = State::default();
+        let mut mirrors = Mirrors::default();
+        mirrors.subscribe("cli-1", &state);
+        mirrors.subscribe("cli-2", &state);
+
+        let frames = mirrors.event_frames(&state, "safe_word", &json!({"pane_id": 3}));
+
+        assert_eq!(frames.len(), 2);
+        assert_eq!(parse(&frames[1].1)["event"], json!({"pane_id": 3, "type": "safe_word"}));
+    }
+
+    #[test]
+    fn test_close_releases_every_subscriber() {
+        let mut state = State::default();
+        let mut mirrors = Mirrors::default();
+        mirrors.subscribe("cli-1", &state);
+        mirrors.subscribe("cli-2", &state);
+
+        assert_eq!(mirrors.close(), vec!["cli-1".to_string(), "cli-2".to_string()]);
+
+        update(&mut state, vec![pane(1, "a", true)]);
+        assert!(mirrors.pending_frames(&state).is_empty());
+        assert!(mirrors.close().is_empty());
+    }
+}
@@ -0,0 +1,156 @@
+//! Pane title conventions for agent panes
+//!
+//! The default convention is `project__kind_N` (e.g. `myapp__cc_1`). Teams
+//! can configure another format with `{project}`, `{kind}`, and `{index}`
+//! placeholders, or a regex with those named groups for parsing only.
+
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+
+/// Title format used when none is configured
+pub const DEFAULT_TITLE_FORMAT: &str = "{project}__{kind}_{index}";
+
+/// The parts of an agent pane title
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AgentName {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    pub kind: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub index: Option<u32>,
+    /// Preset the agent was spawned with; not part of the title
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub preset: Option<String>,
+}
+
+/// Compiled naming convention
+#[derive(Debug, Clone)]
+pub struct TitleSchema {
+    format: String,
+    regex: Regex,
+}
+
+impl Default for TitleSchema {
+    fn default() -> Self {
+        TitleSchema::new(DEFAULT_TITLE_FORMAT, None).expect("default title format is valid")
+    }
+}
+
+impl TitleSchema {
+    /// Build a schema from a format string and an optional parsing regex
+    ///
+    /// The regex must have a `kind` group; `project` and `index` are optional.
+    pub fn new(format: &str, pattern: Option<&str>) -> Result<Self, String> {
+        let source = match pattern {
+            Some(pattern) => pattern.to_string(),
+            None => format_to_regex(format)?,
+        };
+        let regex = Regex::new(&source).map_err(|e| format!("invalid title pattern: {}", e))?;
+        if !regex.capture_names().any(|n| n == Some("kind")) {
+            return Err("invalid title pattern: missing (?P<kind>...) group".to_string());
+        }
+        Ok(TitleSchema {
+            format: format.to_string(),
+            regex,
+        })
+    }
+
+    /// Parse a pane title; None when it does not follow the convention
+    pub fn parse(&self, title: &str) -> Option<AgentName> {
+        let caps = self.regex.captures(title)?;
+        Some(AgentName {
+            project: caps.name("project").map(|m| m.as_str().to_string()),
+            kind: caps.name("kind")?.as_str().to_string(),
+            index: caps.name("index").and_then(|m| m.as_str().parse().ok()),
+            preset: None,
+        })
+    }
+
+    /// Render a title in the configured format
+    pub fn render(&self, name: &AgentName) -> String {
+        self.format
+            .replace("{project}", name.project.as_deref().unwrap_or(""))
+            .replace("{kind}", &name.kind)
+            .replace("{index}", &name.index.map(|i| i.to_string()).unwrap_or_default())
+    }
+}
+
+/// Turn `{project}__{kind}_{index}` into an anchored regex
+fn format_to_regex(format: &str) -> Result<String, String> {
+    let mut out = String::from("^");
+    let mut rest = format;
+    let mut has_kind = false;
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..]
+            .find('}')
+            .map(|e| start + e)
+            .ok_or_else(|| format!("invalid title format: unclosed placeholder in {}", format))?;
+        out.push_str(&regex::escape(&rest[..start]));
+        out.push_str(match &rest[start + 1..end] {
+            "project" => "(?P<project>.+?)",
+            "kind" => {
+                has_kind = true;
+                "(?P<kind>[A-Za-z0-9-]+?)"
+            }
+            "index" => "(?P<index>\\d+)",
+            other => return Err(format!("invalid title format: unknown placeholder {{{}}}", other)),
+        });
+        rest = &rest[end + 1..];
+    }
+    out.push_str(&regex::escape(rest));
+    out.push('$');
+    if !has_kind {
+        return Err("invalid title format: missing {kind}".to_string());
+    }
+    Ok(out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn name(project: Option<&str>, kind: &str, index: Option<u32>) -> AgentName {
+        AgentName {
+            project: project.map(|p| p.to_string()),
+            kind: kind.to_string(),
+            index,
+            preset: None,
+        }
+    }
+
+    #[test]
+    fn test_default_convention() {
+        let schema = TitleSchema::default();
+
+        assert_eq!(schema.parse("my__app__cc_2"), Some(name(Some("my__app"), "cc", Some(2))));
+        assert_eq!(schema.parse("scratch"), None);
+        assert_eq!(schema.render(&name(Some("proj"), "cod", Some(1))), "proj__cod_1");
+    }
+
+    #[test]
+    fn test_custom_format() {
+        let schema = TitleSchema::new("{kind}/{project}#{index}", None).unwrap();
+
+        assert_eq!(schema.parse("aider/web.app#3"), Some(name(Some("web.app"), "aider", Some(3))));
+        assert_eq!(schema.parse("web__cc_1"), None);
+    }
+
+    #[test]
+    fn test_prefixless_format() {
+        let schema = TitleSchema::new("{kind}-{index}", None).unwrap();
+        assert_eq!(schema.parse("goose-4"), Some(name(None, "goose", Some(4))));
+    }
+
+    #[test]
+    fn test_custom_regex() {
+        let schema = TitleSchema::new(DEFAULT_TITLE_FORMAT, Some(r"^\[(?P<kind>\w+)\] (?P<project>.+)$")).unwrap();
+        assert_eq!(schema.parse("[cc] api"), Some(name(Some("api"), "cc", None)));
+    }
+
+    #[test]
+    fn test_invalid_schemas() {
+        assert!(TitleSchema::new("{project}_{n}", None).is_err());
+        assert!(TitleSchema::new("{project}", None).is_err());
+        assert!(TitleSchema::new("x", Some("(?P<project>.+)")).is_err());
+    }
+}
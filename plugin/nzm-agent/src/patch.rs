@@ -0,0 +1,94 @@
+//! Applying agent-produced unified diffs with `git apply`
+
+use serde::{Deserialize, Serialize};
+
+/// Outcome of a `git apply` run
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ApplyReport {
+    pub applied: bool,
+    pub dry_run: bool,
+    /// Files git reported as checked or applied
+    pub files: Vec<String>,
+    /// Human-readable conflict lines reported by git
+    pub conflicts: Vec<String>,
+}
+
+/// Build the command line that feeds `patch` to `git apply` on stdin
+///
+/// RunCommands cannot pipe stdin, so the patch travels as a positional
+/// argument to a small shell wrapper.
+#[cfg(any(target_arch = "wasm32", test))]
+pub fn git_apply_command(patch: &str, dry_run: bool) -> Vec<String> {
+    let mode = if dry_run { "--check" } else { "--whitespace=nowarn" };
+    vec![
+        "sh".to_string(),
+        "-c".to_string(),
+        "printf '%s\\n' \"$1\" | git apply --verbose \"$2\" -".to_string(),
+        "sh".to_string(),
+        patch.to_string(),
+        mode.to_string(),
+    ]
+}
+
+/// Interpret the exit code and verbose output of `git apply`
+#[cfg(any(target_arch = "wasm32", test))]
+pub fn parse_apply_output(exit_code: Option<i32>, stderr: &str, dry_run: bool) -> ApplyReport {
+    let mut files = Vec::new();
+    let mut conflicts = Vec::new();
+
+    for line in stderr.lines() {
+        if let Some(path) = line.strip_prefix("Checking patch ") {
+            let path = path.trim_end_matches("...").to_string();
+            if !files.contains(&path) {
+                files.push(path);
+            }
+        } else if let Some(message) = line.strip_prefix("error: ") {
+            conflicts.push(message.to_string());
+        }
+    }
+
+    ApplyReport {
+        applied: exit_code == Some(0),
+        dry_run,
+        files,
+        conflicts,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_git_apply_command_dry_run_uses_check() {
+        let cmd = git_apply_command("diff", true);
+        assert_eq!(cmd[0], "sh");
+        assert_eq!(cmd[4], "diff");
+        assert_eq!(cmd[5], "--check");
+        assert_ne!(git_apply_command("diff", false)[5], "--check");
+    }
+
+    #[test]
+    fn test_parse_clean_apply() {
+        let stderr = "Checking patch src/a.rs...\nApplied patch src/a.rs cleanly.\n";
+        let report = parse_apply_output(Some(0), stderr, false);
+
+        assert!(report.applied);
+        assert_eq!(report.files, vec!["src/a.rs"]);
+        assert!(report.conflicts.is_empty());
+    }
+
+    #[test]
+    fn test_parse_conflicts() {
+        let stderr = "Checking patch src/a.rs...\n\
+                      error: while searching for:\n\
+                      error: patch failed: src/a.rs:12\n\
+                      error: src/a.rs: patch does not apply\n";
+        let report = parse_apply_output(Some(1), stderr, true);
+
+        assert!(!report.applied);
+        assert!(report.dry_run);
+        assert_eq!(report.conflicts.len(), 3);
+        assert!(report.conflicts.contains(&"patch failed: src/a.rs:12".to_string()));
+    }
+}
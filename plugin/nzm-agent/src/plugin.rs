@@ -1,48 +1,1261 @@
 //! Zellij plugin entry point (WASM only)
 
+use std::collections::{BTreeMap, VecDeque};
+use std::path::PathBuf;
+use serde_json::Value;
 use zellij_tile::prelude::*;
-use crate::ipc::{Request, Response};
-use crate::state::State;
+use crate::ipc::{Error, ErrorCode, Request, Response};
+use crate::state::{PendingWait, State};
 use crate::commands;
+use crate::dashboard::{self, LastDrawn};
+use crate::keybind;
+use crate::config::Config;
+use crate::fanout::{FanoutJudge, PendingFanout, DEFAULT_FANOUT_TIMEOUT_SECS};
+use crate::patch;
+use crate::files;
+use crate::encoding;
+use crate::keys;
+use crate::events::{PendingPoll, DEFAULT_POLL_TIMEOUT_SECS};
+use crate::clock::{Clock, ZellijClock, TIMER_SLACK_MS};
+use crate::housekeeping::Housekeeping;
+use crate::git;
+use crate::integrity;
+use crate::secrets::{self, SecretRef};
+use crate::spawn;
+use crate::write_queue::{chunk_text, JobStatus, Pacer, Priority, SendJob, WriteQueue};
+use crate::mirror::Mirrors;
+use crate::chaos::{Chaos, Fate};
 
 #[derive(Default)]
 pub struct NzmAgent {
     state: State,
     initialized: bool,
+    /// Whether queued tasks were re-validated against the first pane list
+    tasks_recovered: bool,
+    /// Keeps writes to each pane in request arrival order
+    writes: WriteQueue,
+    /// When the next chunk of paced writes is due
+    pacer: Pacer,
+    /// Idle checks and schedules
+    housekeeping: Housekeeping,
+    /// Time and timers; every timer is armed through it
+    clock: ZellijClock,
+    /// CLI pipes streaming state frames via mirror_state
+    mirrors: Mirrors,
+    /// Dashboard content last drawn
+    drawn: LastDrawn,
+    /// Debug latency/loss injection, when configured
+    chaos: Option<Chaos>,
+    /// Work held back by chaos mode, each entry with when it is due in
+    /// milliseconds since the Unix epoch and one pending timer
+    delayed: VecDeque<(u64, Delayed)>,
+}
+
+/// An effect or event whose delivery chaos mode postponed
+enum Delayed {
+    Effect {
+        request_id: String,
+        cli_id: Option<String>,
+        response: Response,
+    },
+    Panes(PaneManifest),
 }
 
 impl NzmAgent {
     pub fn is_initialized(&self) -> bool {
         self.initialized
     }
+
+    /// Decide what chaos mode does with the next request or event
+    fn chaos_fate(&mut self) -> Fate {
+        self.chaos.as_mut().map_or(Fate::Now, |c| c.fate())
+    }
+
+    /// Apply a pane manifest and notify whoever depends on the pane list
+    fn apply_panes(&mut self, manifest: PaneManifest) {
+        self.state.update_panes(manifest);
+        if !self.tasks_recovered {
+            // Targets of tasks queued before a reload may be gone
+            self.tasks_recovered = self.state.recover_tasks().is_ok();
+        }
+        let state = &self.state;
+        self.writes.retain_panes(|id| state.get_pane(id).is_some());
+        for (cli_id, frame) in self.mirrors.pending_frames(&self.state) {
+            cli_pipe_output(&cli_id, &frame);
+        }
+        // Closing a pane completes its turn
+        self.finish_fanouts();
+        self.answer_waits();
+        for (spawn, pane_id) in self.state.take_spawned() {
+            if let Some(cwd) = spawn.reply.get("cwd").and_then(|v| v.as_str()).filter(|cwd| cwd.starts_with('/')) {
+                self.state.set_pane_cwd(pane_id, cwd);
+            }
+            let mut data = spawn.reply;
+            data["pane_id"] = pane_id.into();
+            if let Some(cli_id) = spawn.cli_id {
+                respond(&cli_id, &Response::ok(&spawn.request_id, data));
+                unblock_cli_pipe_input(&cli_id);
+            }
+        }
+    }
+
+    /// Send events raised in State to mirror_state subscribers and the
+    /// next_event polls waiting for them
+    fn send_events(&mut self) {
+        for (kind, event) in self.state.take_events() {
+            for (cli_id, frame) in self.mirrors.event_frames(&self.state, &kind, &event) {
+                cli_pipe_output(&cli_id, &frame);
+            }
+        }
+        self.answer_polls();
+        self.answer_waits();
+    }
+
+    /// Answer wait requests whose conditions now hold or that timed out
+    fn answer_waits(&mut self) {
+        let now = self.clock.now_ms();
+        for wait in self.state.take_waits() {
+            match commands::wait_result(&mut self.state, &wait.request_id, &wait.params, now >= wait.deadline_ms) {
+                Some(response) => {
+                    respond(&wait.cli_id, &response);
+                    unblock_cli_pipe_input(&wait.cli_id);
+                }
+                None => self.state.track_wait(wait),
+            }
+        }
+    }
+
+    /// Answer next_event polls that have an event or timed out
+    fn answer_polls(&mut self) {
+        for (poll, next) in self.state.take_answered_polls() {
+            let next = next.as_ref().map(|(event, gap)| (event, *gap));
+            let response = Response::ok(&poll.request_id, commands::next_event_data(next, poll.after));
+            respond(&poll.cli_id, &response);
+            unblock_cli_pipe_input(&poll.cli_id);
+        }
+    }
+
+    /// Answer the fanout requests whose agents replied or timed out
+    fn finish_fanouts(&mut self) {
+        let (finished, waits) = self.state.take_finished_fanouts();
+        // Judges were just sent the replies
+        self.run_state_effects();
+        for secs in waits {
+            self.clock.set_timeout(secs as f64);
+        }
+        for pending in finished {
+            let response = commands::fanout_response(&mut self.state, &pending);
+            if let Some(cli_id) = &pending.cli_id {
+                respond(cli_id, &response);
+                unblock_cli_pipe_input(cli_id);
+            }
+        }
+    }
+
+    /// Execute effects State raised outside a request, such as an agent's
+    /// initial messages
+    fn run_state_effects(&mut self) {
+        for effect in self.state.take_effects() {
+            self.execute_effect("state", None, &effect);
+        }
+    }
+
+    /// Hold back an effect or event for `secs`
+    fn delay(&mut self, secs: f64, item: Delayed) {
+        let due = self.clock.now_ms() + (secs * 1000.0) as u64;
+        self.delayed.push_back((due, item));
+        self.clock.set_timeout(secs);
+    }
+
+    /// Deliver postponed effects and events due by `until_ms`, oldest
+    /// first, returning whether a pane manifest was applied
+    ///
+    /// An item due later holds back those behind it, so they still arrive
+    /// in order.
+    fn run_delayed(&mut self, until_ms: u64) -> bool {
+        let mut applied = false;
+        while self.delayed.front().is_some_and(|(due, _)| *due <= until_ms.saturating_add(TIMER_SLACK_MS)) {
+            let Some((_, item)) = self.delayed.pop_front() else {
+                break;
+            };
+            match item {
+                Delayed::Effect { request_id, cli_id, response } => {
+                    let deferred = response
+                        .data
+                        .as_ref()
+                        .is_some_and(|data| self.execute_effect(&request_id, cli_id.as_deref(), data));
+                    if let (false, Some(cli_id)) = (deferred, cli_id) {
+                        respond(&cli_id, &response);
+                        unblock_cli_pipe_input(&cli_id);
+                    }
+                }
+                Delayed::Panes(manifest) => {
+                    self.apply_panes(manifest);
+                    applied = true;
+                }
+            }
+        }
+        applied
+    }
+
+    /// Queue bytes for a pane and perform whatever writes are now due
+    fn write_to_pane(&mut self, pane_id: u32, bytes: Vec<u8>, priority: Priority) {
+        let ready = self.writes.push(pane_id, bytes, priority);
+        flush_writes(pane_id, ready);
+        self.arm_pacing();
+    }
+
+    /// Start the pacing timer if a paced write is waiting for it
+    ///
+    /// Called after anything that can bring a paced write to the front of
+    /// its queue, such as a reservation being filled.
+    fn arm_pacing(&mut self) {
+        let pace_ms = self.state.config().send_pace_ms;
+        self.pacer.arm(&self.writes, pace_ms, &mut self.clock);
+    }
+
+    /// Release the next chunk of every paced write
+    fn tick_pacing(&mut self) {
+        for (pane_id, ready) in self.writes.tick() {
+            flush_writes(pane_id, ready);
+        }
+        self.sync_jobs();
+        self.arm_pacing();
+    }
+
+    /// Publish the failure of a request no CLI waits on, such as one
+    /// from a keybinding, as a `request_failed` event
+    fn report_unanswered(&mut self, action: &str, response: &Response) {
+        let mut event = serde_json::to_value(response).unwrap_or_default();
+        if let Some(fields) = event.as_object_mut() {
+            fields.insert("action".to_string(), Value::from(action));
+        }
+        self.state.emit_event("request_failed", event);
+        self.send_events();
+    }
+
+    /// Make ready agents that never showed their ready pattern
+    fn expire_startups(&mut self) {
+        let now = self.clock.now_ms();
+        self.state.expire_starting(now + TIMER_SLACK_MS);
+        self.run_state_effects();
+        self.send_events();
+    }
+
+    /// Fail new_pane requests whose pane never appeared
+    fn expire_spawns(&mut self) {
+        let now = self.clock.now_ms();
+        for spawn in self.state.take_expired_spawns(now + TIMER_SLACK_MS) {
+            if let Some(cli_id) = spawn.cli_id {
+                let error = format!("new_pane timed out: no pane appeared within {}s", spawn::SPAWN_TIMEOUT_SECS);
+                respond(&cli_id, &Response::err(&spawn.request_id, error));
+                unblock_cli_pipe_input(&cli_id);
+            }
+        }
+    }
+
+    /// Give up the places of secrets that never resolved
+    fn expire_reservations(&mut self) {
+        let now = self.clock.now_ms();
+        for (pane_id, ready) in self.writes.expire(now + TIMER_SLACK_MS) {
+            flush_writes(pane_id, ready);
+        }
+        self.arm_pacing();
+    }
+
+    /// Wind down idle agents and run due schedules
+    fn tick_housekeeping(&mut self) {
+        for response in self.housekeeping.tick(&mut self.state, &mut self.clock) {
+            if let Some(data) = response.data.as_ref().filter(|data| data.get("action").is_some()) {
+                self.execute_effect(&response.id, None, data);
+            }
+        }
+        self.run_state_effects();
+        self.send_events();
+    }
+
+    /// Copy paced send progress from the write queue into State
+    fn sync_jobs(&mut self) {
+        let active: Vec<(String, u32)> = self
+            .state
+            .send_jobs()
+            .filter(|j| matches!(j.status, JobStatus::Running | JobStatus::Paused))
+            .map(|j| (j.id.clone(), j.pane_id))
+            .collect();
+        for (id, pane_id) in active {
+            let remaining = self.writes.job_remaining(&id);
+            let pane_open = self.state.get_pane(pane_id).is_some();
+            let Some(job) = self.state.send_job_mut(&id) else {
+                continue;
+            };
+            match remaining {
+                Some(remaining) => job.chunks_sent = job.chunks_total - remaining,
+                // Dropped with its pane before the last chunk went out
+                None if !pane_open => job.status = JobStatus::Aborted,
+                None => {
+                    job.chunks_sent = job.chunks_total;
+                    job.status = JobStatus::Done;
+                }
+            }
+        }
+    }
+
+    /// Execute the Zellij side effects described by a validated response
+    ///
+    /// Returns true when the reply is deferred until a host command finishes.
+    fn execute_effect(&mut self, request_id: &str, cli_id: Option<&str>, data: &Value) -> bool {
+        let Some(action) = data.get("action").and_then(|v| v.as_str()) else {
+            return false;
+        };
+        let pane_id = data.get("pane_id").and_then(|v| v.as_u64()).map(|id| id as u32);
+        let priority = data
+            .get("priority")
+            .and_then(|v| serde_json::from_value(v.clone()).ok())
+            .unwrap_or_default();
+
+        match action {
+            "send_keys" => {
+                if let (Some(pane_id), Some(text)) = (pane_id, data.get("text").and_then(|v| v.as_str())) {
+                    let enter = data.get("enter").and_then(|v| v.as_bool()).unwrap_or(false);
+                    if enter {
+                        let task_id = data.get("task_id").and_then(|v| v.as_str()).map(String::from);
+                        let turn = self.state.begin_turn(pane_id, text, task_id);
+                        if let (Some(turn), Some(handover)) = (turn, data.get("handover_id").and_then(|v| v.as_str())) {
+                            self.state.attach_handover(handover, &turn);
+                        }
+                    }
+                    let chunk_bytes = data.get("chunk_bytes").and_then(|v| v.as_u64());
+                    match chunk_bytes {
+                        Some(n) if n > 0 => {
+                            let mut chunks = chunk_text(text, n as usize);
+                            if enter {
+                                match chunks.last_mut() {
+                                    Some(last) => last.push(b'\n'),
+                                    None => chunks.push(vec![b'\n']),
+                                }
+                            }
+                            let job_id = data.get("job_id").and_then(|v| v.as_str()).map(String::from);
+                            if let Some(id) = &job_id {
+                                self.state.track_send_job(SendJob {
+                                    id: id.clone(),
+                                    pane_id,
+                                    status: JobStatus::Running,
+                                    chunks_sent: 0,
+                                    chunks_total: chunks.len(),
+                                });
+                            }
+                            let ready = self.writes.push_paced(pane_id, chunks, priority, job_id);
+                            flush_writes(pane_id, ready);
+                            self.sync_jobs();
+                            self.arm_pacing();
+                        }
+                        _ => {
+                            // Text and enter go out as one write so nothing can land between them
+                            let mut bytes = text.as_bytes().to_vec();
+                            if enter {
+                                bytes.push(b'\n');
+                            }
+                            self.write_to_pane(pane_id, bytes, priority);
+                        }
+                    }
+                }
+                false
+            }
+            "close_pane" => {
+                if let Some(pane_id) = pane_id {
+                    close_terminal_pane(pane_id);
+                }
+                false
+            }
+            "focus_pane" => {
+                if let Some(pane_id) = pane_id {
+                    focus_terminal_pane(pane_id, false, false);
+                }
+                false
+            }
+            "toggle_floating" => {
+                if let Some(pane_id) = pane_id {
+                    toggle_pane_embed_or_eject_for_pane_id(PaneId::Terminal(pane_id));
+                }
+                false
+            }
+            "toggle_fullscreen" => {
+                if let Some(pane_id) = pane_id {
+                    toggle_pane_id_fullscreen(PaneId::Terminal(pane_id));
+                }
+                false
+            }
+            "pin_pane" => {
+                let Some(pane_id) = pane_id else {
+                    return false;
+                };
+                // Pinning is part of a floating pane's coordinates
+                let pinned = data.get("pinned").and_then(|v| v.as_bool());
+                if let Some(coordinates) = FloatingPaneCoordinates::new(None, None, None, None, pinned, None) {
+                    change_floating_panes_coordinates(vec![(PaneId::Terminal(pane_id), coordinates)]);
+                }
+                false
+            }
+            "swap_panes" => {
+                let other = data.get("other_pane_id").and_then(|v| v.as_u64()).map(|id| id as u32);
+                let steps = data.get("steps").and_then(|v| v.as_u64()).unwrap_or(0);
+                let directions = match data.get("direction").and_then(|v| v.as_str()) {
+                    Some("left") => Some((Direction::Left, Direction::Right)),
+                    Some("right") => Some((Direction::Right, Direction::Left)),
+                    Some("up") => Some((Direction::Up, Direction::Down)),
+                    Some("down") => Some((Direction::Down, Direction::Up)),
+                    _ => None,
+                };
+                if let (Some(pane_id), Some(other), Some((there, back))) = (pane_id, other, directions) {
+                    // Each move swaps a pane with its neighbour
+                    for _ in 0..steps {
+                        move_pane_with_pane_id_in_direction(PaneId::Terminal(pane_id), there);
+                    }
+                    for _ in 1..steps {
+                        move_pane_with_pane_id_in_direction(PaneId::Terminal(other), back);
+                    }
+                }
+                false
+            }
+            "stack_panes" | "unstack_panes" => {
+                let panes: Vec<PaneId> = data
+                    .get("pane_ids")
+                    .and_then(|v| serde_json::from_value::<Vec<u32>>(v.clone()).ok())
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(PaneId::Terminal)
+                    .collect();
+                if action == "stack_panes" {
+                    stack_panes(panes);
+                    // Focusing a stacked pane expands it
+                    if let Some(expand) = data.get("expand").and_then(|v| v.as_u64()) {
+                        focus_terminal_pane(expand as u32, false, false);
+                    }
+                } else {
+                    // Zellij cannot unstack; a floated pane is embedded on its own
+                    float_multiple_panes(panes.clone());
+                    embed_multiple_panes(panes);
+                }
+                false
+            }
+            "scroll_pane" => {
+                let Some(pane_id) = pane_id else {
+                    return false;
+                };
+                let pane = PaneId::Terminal(pane_id);
+                match data.get("from").and_then(|v| v.as_str()) {
+                    Some("top") => scroll_to_top_in_pane_id(pane),
+                    Some("bottom") => scroll_to_bottom_in_pane_id(pane),
+                    _ => {}
+                }
+                // Zellij scrolls one line or page per call
+                let lines = data.get("lines").and_then(|v| v.as_i64()).unwrap_or(0);
+                for _ in 0..lines.unsigned_abs() {
+                    if lines < 0 {
+                        scroll_up_in_pane_id(pane);
+                    } else {
+                        scroll_down_in_pane_id(pane);
+                    }
+                }
+                let pages = data.get("pages").and_then(|v| v.as_i64()).unwrap_or(0);
+                for _ in 0..pages.unsigned_abs() {
+                    if pages < 0 {
+                        page_scroll_up_in_pane_id(pane);
+                    } else {
+                        page_scroll_down_in_pane_id(pane);
+                    }
+                }
+                false
+            }
+            "new_tab" => {
+                let name = data.get("name").and_then(|v| v.as_str());
+                match data.get("layout").and_then(|v| v.as_str()) {
+                    Some(layout) => {
+                        new_tabs_with_layout_info(layout_info(layout));
+                        let position = data.get("tab_index").and_then(|v| v.as_u64());
+                        if let (Some(name), Some(position)) = (name, position) {
+                            self.state.name_tab_when_open(position as usize, name);
+                        }
+                    }
+                    None => {
+                        new_tab(name, None);
+                    }
+                }
+                false
+            }
+            "go_to_tab" => {
+                if let Some(index) = data.get("tab_index").and_then(|v| v.as_u64()) {
+                    // Zellij numbers tabs from 1 here
+                    go_to_tab(index as u32 + 1);
+                }
+                false
+            }
+            "close_tab" => {
+                if let Some(index) = data.get("tab_index").and_then(|v| v.as_u64()) {
+                    close_tab_with_index(index as usize);
+                }
+                false
+            }
+            "rename_tab" => {
+                let index = data.get("tab_index").and_then(|v| v.as_u64());
+                let name = data.get("name").and_then(|v| v.as_str());
+                if let (Some(index), Some(name)) = (index, name) {
+                    // Zellij numbers tabs from 1 here
+                    rename_tab(index as u32 + 1, name);
+                }
+                false
+            }
+            "move_pane_to_tab" => {
+                let Some(pane_id) = pane_id else {
+                    return false;
+                };
+                let focus = data.get("focus").and_then(|v| v.as_bool()).unwrap_or(false);
+                match data.get("tab_index").and_then(|v| v.as_u64()) {
+                    Some(index) => {
+                        break_panes_to_tab_with_index(&[PaneId::Terminal(pane_id)], index as usize, focus);
+                    }
+                    None => {
+                        let name = data.get("tab_name").and_then(|v| v.as_str()).map(String::from);
+                        break_panes_to_new_tab(&[PaneId::Terminal(pane_id)], name, focus);
+                    }
+                }
+                false
+            }
+            "fanout" => {
+                // Prompts already went out as state effects; only a CLI
+                // client is waiting for the replies
+                let Some(cli_id) = cli_id else {
+                    return false;
+                };
+                let targets = data
+                    .get("targets")
+                    .and_then(|v| v.as_array())
+                    .map(|targets| {
+                        targets
+                            .iter()
+                            .filter_map(|t| {
+                                let pane_id = t.get("pane_id")?.as_u64()? as u32;
+                                Some((pane_id, t.get("task_id").and_then(|v| v.as_str()).map(String::from)))
+                            })
+                            .collect()
+                    })
+                    .unwrap_or_default();
+                let prompt = data.get("prompt").and_then(|v| v.as_str()).unwrap_or_default().to_string();
+                let timeout_secs = data.get("timeout_secs").and_then(|v| v.as_u64()).unwrap_or(DEFAULT_FANOUT_TIMEOUT_SECS);
+                let mut pending = PendingFanout::new(request_id, Some(cli_id), prompt, targets, timeout_secs);
+                pending.judge = data.get("judge").and_then(|judge| {
+                    Some(FanoutJudge {
+                        pane_id: judge.get("pane_id")?.as_u64()? as u32,
+                        template: judge.get("template")?.as_str()?.to_string(),
+                        asked: false,
+                        task_id: None,
+                    })
+                });
+                self.state.track_fanout(pending);
+                self.clock.set_timeout(timeout_secs as f64);
+                true
+            }
+            "wait" => {
+                let Some(cli_id) = cli_id else {
+                    return false;
+                };
+                let timeout_secs = data.get("timeout_secs").and_then(|v| v.as_u64()).unwrap_or(DEFAULT_POLL_TIMEOUT_SECS);
+                self.state.track_wait(PendingWait {
+                    request_id: request_id.to_string(),
+                    cli_id: cli_id.to_string(),
+                    params: data.get("params").cloned().unwrap_or_default(),
+                    deadline_ms: self.clock.now_ms().saturating_add(timeout_secs * 1000),
+                });
+                self.clock.set_timeout(timeout_secs as f64);
+                true
+            }
+            "next_event" => {
+                let Some(cli_id) = cli_id else {
+                    return false;
+                };
+                let timeout_secs = data.get("timeout_secs").and_then(|v| v.as_u64()).unwrap_or(DEFAULT_POLL_TIMEOUT_SECS);
+                self.state.track_poll(PendingPoll {
+                    request_id: request_id.to_string(),
+                    cli_id: cli_id.to_string(),
+                    after: data.get("after").and_then(|v| v.as_u64()).unwrap_or_default(),
+                    kinds: data
+                        .get("types")
+                        .and_then(|v| serde_json::from_value(v.clone()).ok())
+                        .unwrap_or_default(),
+                    deadline_ms: self.clock.now_ms().saturating_add(timeout_secs * 1000),
+                });
+                self.clock.set_timeout(timeout_secs as f64);
+                true
+            }
+            "resize_pane" => {
+                let Some(pane_id) = pane_id else {
+                    return false;
+                };
+                if let Some(steps) = data.get("steps").and_then(|v| v.as_i64()) {
+                    let resize = if steps > 0 { Resize::Increase } else { Resize::Decrease };
+                    let direction = match data.get("direction").and_then(|v| v.as_str()) {
+                        Some("left") => Some(Direction::Left),
+                        Some("right") => Some(Direction::Right),
+                        Some("up") => Some(Direction::Up),
+                        Some("down") => Some(Direction::Down),
+                        _ => None,
+                    };
+                    // Zellij resizes one step per call
+                    for _ in 0..steps.unsigned_abs() {
+                        resize_pane_with_id(ResizeStrategy::new(resize, direction), PaneId::Terminal(pane_id));
+                    }
+                } else {
+                    let size = |key: &str| data.get(key).and_then(|v| v.as_str()).map(String::from);
+                    if let Some(coordinates) = FloatingPaneCoordinates::new(None, None, size("width"), size("height"), None, None) {
+                        change_floating_panes_coordinates(vec![(PaneId::Terminal(pane_id), coordinates)]);
+                    }
+                }
+                false
+            }
+            "rename_pane" => {
+                if let (Some(pane_id), Some(title)) = (pane_id, data.get("title").and_then(|v| v.as_str())) {
+                    rename_terminal_pane(pane_id, title);
+                }
+                false
+            }
+            "open_pane" => {
+                let Some(cwd) = data.get("cwd").and_then(|v| v.as_str()) else {
+                    open_pane(data, None);
+                    return false;
+                };
+                // Never start an agent somewhere other than where it was asked to run
+                let mut context = BTreeMap::new();
+                context.insert("cwd".to_string(), cwd.to_string());
+                context.insert("effect".to_string(), data.to_string());
+                run_deferred(&spawn::resolve_cwd_command(cwd), None, "open_pane", request_id, cli_id, context);
+                true
+            }
+            "new_pane" => {
+                let command: Vec<String> = data
+                    .get("command")
+                    .and_then(|v| serde_json::from_value(v.clone()).ok())
+                    .unwrap_or_default();
+                let cwd = data.get("cwd").and_then(|v| v.as_str());
+                let direction = data.get("direction").and_then(|v| serde_json::from_value(v.clone()).ok());
+                let name = data.get("name").and_then(|v| v.as_str());
+                let Some(session) = data.get("session").and_then(|v| v.as_str()) else {
+                    return false;
+                };
+                // The reply waits for the pane to show up in a pane update
+                let mut reply = data.clone();
+                if let Some(obj) = reply.as_object_mut() {
+                    obj.remove("action");
+                    obj.remove("command");
+                    obj.remove("session");
+                }
+                self.state.track_spawn(request_id, cli_id, name, reply);
+                self.clock.set_timeout(spawn::SPAWN_TIMEOUT_SECS as f64);
+                let args = spawn::new_pane_command(&command, session, cwd, direction, name);
+                run_deferred(&args, None, "new_pane", request_id, cli_id, BTreeMap::new());
+                true
+            }
+            "run_command" => {
+                // CommandPaneOpened replies with the pane id
+                let mut data = data.clone();
+                data["request_id"] = request_id.into();
+                if let Some(cli_id) = cli_id {
+                    data["cli_id"] = cli_id.into();
+                }
+                let Some(cwd) = data.get("cwd").and_then(|v| v.as_str()) else {
+                    open_pane(&data, None);
+                    return true;
+                };
+                let mut context = BTreeMap::new();
+                context.insert("cwd".to_string(), cwd.to_string());
+                context.insert("effect".to_string(), data.to_string());
+                run_deferred(&spawn::resolve_cwd_command(cwd), None, "run_command", request_id, cli_id, context);
+                true
+            }
+            "control_send" => {
+                let (Some(op), Some(job_id)) = (
+                    data.get("op").and_then(|v| v.as_str()),
+                    data.get("job_id").and_then(|v| v.as_str()),
+                ) else {
+                    return false;
+                };
+                let released = match op {
+                    "pause" => {
+                        self.writes.pause(job_id);
+                        None
+                    }
+                    "resume" => self.writes.resume(job_id),
+                    "abort" => self.writes.abort(job_id),
+                    _ => None,
+                };
+                if let Some((pane_id, ready)) = released {
+                    flush_writes(pane_id, ready);
+                }
+                self.sync_jobs();
+                self.arm_pacing();
+                false
+            }
+            "interrupt_all" => {
+                // Drop the rest of paced sends first so nothing follows the interrupt
+                let job_ids = data.get("job_ids").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+                for job_id in job_ids.iter().filter_map(|v| v.as_str()) {
+                    if let Some((pane_id, ready)) = self.writes.abort(job_id) {
+                        flush_writes(pane_id, ready);
+                    }
+                }
+                self.arm_pacing();
+                let pane_ids = data.get("pane_ids").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+                let interrupt = keys::key_sequence(keys::INTERRUPT_KEY).unwrap_or_default();
+                for pane_id in pane_ids.iter().filter_map(|v| v.as_u64()) {
+                    self.write_to_pane(pane_id as u32, interrupt.clone().into_bytes(), Priority::Urgent);
+                }
+                self.sync_jobs();
+                false
+            }
+            "send_raw" => {
+                let bytes = data.get("bytes").and_then(|v| v.as_str()).map(|b| encoding::decode_base64("bytes", b));
+                if let (Some(pane_id), Some(Ok(bytes))) = (pane_id, bytes) {
+                    self.write_to_pane(pane_id, bytes, priority);
+                }
+                false
+            }
+            "send_interrupt" => {
+                if let Some(pane_id) = pane_id {
+                    let sequence = match data.get("keys").and_then(|v| v.as_str()) {
+                        Some(sequence) => sequence.to_string(),
+                        None => keys::key_sequence(keys::INTERRUPT_KEY).unwrap_or_default(),
+                    };
+                    self.write_to_pane(pane_id, sequence.into_bytes(), priority);
+                }
+                false
+            }
+            "apply_patch" => {
+                let (Some(cwd), Some(patch_text)) = (
+                    data.get("cwd").and_then(|v| v.as_str()),
+                    data.get("patch").and_then(|v| v.as_str()),
+                ) else {
+                    return false;
+                };
+                let dry_run = data.get("dry_run").and_then(|v| v.as_bool()).unwrap_or(false);
+                let mut context = BTreeMap::new();
+                context.insert("dry_run".to_string(), dry_run.to_string());
+                run_deferred(
+                    &patch::git_apply_command(patch_text, dry_run),
+                    Some(cwd),
+                    "apply_patch",
+                    request_id,
+                    cli_id,
+                    context,
+                );
+                true
+            }
+            "put_file" => {
+                let (Some(cwd), Some(path), Some(content)) = (
+                    data.get("cwd").and_then(|v| v.as_str()),
+                    data.get("path").and_then(|v| v.as_str()),
+                    data.get("content").and_then(|v| v.as_str()),
+                ) else {
+                    return false;
+                };
+                let mut context = BTreeMap::new();
+                context.insert("path".to_string(), path.to_string());
+                let bytes = data.get("bytes").and_then(|v| v.as_u64()).unwrap_or(0);
+                context.insert("bytes".to_string(), bytes.to_string());
+                // Actions writing through put_file give their own reply
+                if let Some(reply) = data.get("reply") {
+                    context.insert("reply".to_string(), reply.to_string());
+                }
+                run_deferred(&files::put_file_command(path, content), Some(cwd), "put_file", request_id, cli_id, context);
+                true
+            }
+            "git_info" => {
+                let Some(cwd) = data.get("cwd").and_then(|v| v.as_str()) else {
+                    return false;
+                };
+                let mut context = BTreeMap::new();
+                context.insert("cwd".to_string(), cwd.to_string());
+                run_deferred(&git::git_status_command(), Some(cwd), "git_info", request_id, cli_id, context);
+                true
+            }
+            "get_file" => {
+                let (Some(cwd), Some(path)) = (
+                    data.get("cwd").and_then(|v| v.as_str()),
+                    data.get("path").and_then(|v| v.as_str()),
+                ) else {
+                    return false;
+                };
+                let mut context = BTreeMap::new();
+                context.insert("path".to_string(), path.to_string());
+                let encoding = data.get("encoding").and_then(|v| v.as_str()).unwrap_or("text");
+                context.insert("encoding".to_string(), encoding.to_string());
+                run_deferred(&files::get_file_command(path), Some(cwd), "get_file", request_id, cli_id, context);
+                true
+            }
+            "send_secret" => {
+                let (Some(pane_id), Some(Ok(secret))) = (
+                    data.get("pane_id").and_then(|v| v.as_u64()),
+                    data.get("secret_ref")
+                        .and_then(|v| v.as_str())
+                        .map(SecretRef::parse),
+                ) else {
+                    return false;
+                };
+                let enter = data.get("enter").and_then(|v| v.as_bool()).unwrap_or(false);
+                // Writes behind the secret go out without it if the
+                // resolver never answers
+                let ttl_secs = self.state.config().write_reservation_ttl_secs;
+                let expires_ms = self.clock.now_ms() + ttl_secs * 1000;
+                let ticket = self.writes.reserve(pane_id as u32, priority, expires_ms);
+                self.clock.set_timeout(ttl_secs as f64);
+                let mut context = BTreeMap::new();
+                context.insert("pane_id".to_string(), pane_id.to_string());
+                context.insert("write_ticket".to_string(), ticket.to_string());
+                context.insert("secret_ref".to_string(), secret.to_string());
+                context.insert("enter".to_string(), enter.to_string());
+                run_deferred(&secret.resolve_command(self.state.config()), None, "send_secret", request_id, cli_id, context);
+                true
+            }
+            "composite" => {
+                // Host commands started by a step report nowhere: the reply
+                // to the composite request is sent as soon as it validates
+                let steps = data.get("steps").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+                for result in steps.iter().filter_map(|step| step.get("result")) {
+                    self.execute_effect(request_id, None, result);
+                }
+                false
+            }
+            "mirror_state" => {
+                // Only a CLI pipe can carry the stream; it stays open afterwards
+                let Some(cli_id) = cli_id else {
+                    return false;
+                };
+                respond(cli_id, &Response::ok(request_id, serde_json::json!({
+                    "mirroring": true,
+                    "revision": self.state.revision(),
+                })));
+                cli_pipe_output(cli_id, &self.mirrors.subscribe(cli_id, &self.state));
+                true
+            }
+            "shutdown" => {
+                // Flush work held back by chaos mode, then end mirror streams
+                self.run_delayed(u64::MAX);
+                for cli_id in self.mirrors.close() {
+                    unblock_cli_pipe_input(&cli_id);
+                }
+                if data.get("unload").and_then(|v| v.as_bool()).unwrap_or(false) {
+                    if let Some(cli_id) = cli_id {
+                        respond(cli_id, &Response::ok(request_id, data.clone()));
+                    }
+                    close_self();
+                    return true;
+                }
+                false
+            }
+            "broadcast" => {
+                // Targets failed independently; run the effects of the rest
+                let targets = data.get("targets").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+                for effect in targets.iter().filter_map(|t| t.get("effect")) {
+                    self.execute_effect(request_id, None, effect);
+                }
+                false
+            }
+            "transaction" => {
+                // Every operation validated; run their effects in order
+                let results = data.get("results").and_then(|v| v.as_array()).cloned().unwrap_or_default();
+                for result in &results {
+                    self.execute_effect(request_id, None, result);
+                }
+                false
+            }
+            _ => false,
+        }
+    }
+
+    /// Complete a request whose effect ran as a host command
+    fn finish_deferred(&mut self, exit_code: Option<i32>, stdout: &[u8], stderr: &[u8], context: &BTreeMap<String, String>) {
+        let Some(request_id) = context.get("request_id") else {
+            return;
+        };
+
+        let response = match context.get("action").map(|a| a.as_str()) {
+            Some("apply_patch") => {
+                let dry_run = context.get("dry_run").is_some_and(|v| v == "true");
+                let report = patch::parse_apply_output(exit_code, &String::from_utf8_lossy(stderr), dry_run);
+                let mut response = Response::ok(request_id, serde_json::json!({ "report": report }));
+                if !report.applied {
+                    response.success = false;
+                    response.error = Some("patch does not apply".to_string());
+                    response.code = Some(ErrorCode::Failed);
+                }
+                response
+            }
+            Some("send_secret") => self.finish_send_secret(request_id, exit_code, stdout, context),
+            Some("open_pane") => {
+                let cwd = context.get("cwd").cloned().unwrap_or_default();
+                let effect = context.get("effect").and_then(|e| serde_json::from_str::<Value>(e).ok());
+                match (exit_code, effect) {
+                    (Some(0), Some(mut data)) => {
+                        let resolved = String::from_utf8_lossy(stdout).trim_end().to_string();
+                        open_pane(&data, Some(&resolved));
+                        if let Some(obj) = data.as_object_mut() {
+                            obj.remove("action");
+                            obj.insert("cwd".to_string(), Value::String(resolved));
+                        }
+                        Response::ok(request_id, data)
+                    }
+                    _ => {
+                        let stderr = String::from_utf8_lossy(stderr);
+                        Response::err(request_id, format!("cannot open pane in {}: {}", cwd, stderr.trim()))
+                    }
+                }
+            }
+            Some("run_command") => {
+                let effect = context.get("effect").and_then(|e| serde_json::from_str::<Value>(e).ok());
+                if let (Some(0), Some(data)) = (exit_code, effect) {
+                    open_pane(&data, Some(String::from_utf8_lossy(stdout).trim_end()));
+                    return;
+                }
+                let cwd = context.get("cwd").cloned().unwrap_or_default();
+                let stderr = String::from_utf8_lossy(stderr);
+                Response::err(request_id, format!("cannot open pane in {}: {}", cwd, stderr.trim()))
+            }
+            Some("new_pane") => {
+                if exit_code == Some(0) {
+                    return;
+                }
+                self.state.cancel_spawn(request_id, context.get("cli_id").map(|c| c.as_str()));
+                let stderr = String::from_utf8_lossy(stderr);
+                Response::err(request_id, format!("cannot open pane: {}", stderr.trim()))
+            }
+            Some("git_info") => {
+                let cwd = context.get("cwd").cloned().unwrap_or_default();
+                if exit_code == Some(0) {
+                    let info = git::parse_status(&String::from_utf8_lossy(stdout));
+                    let data = commands::git_info_data(&cwd, &info, false);
+                    self.state.cache_git_info(&cwd, info);
+                    Response::ok(request_id, data)
+                } else {
+                    let stderr = String::from_utf8_lossy(stderr);
+                    Response::err(request_id, format!("git_info failed for {}: {}", cwd, stderr.trim()))
+                }
+            }
+            Some("get_file") => {
+                let path = context.get("path").cloned().unwrap_or_default();
+                if exit_code == Some(0) {
+                    let encoding = context
+                        .get("encoding")
+                        .and_then(|v| serde_json::from_value(Value::String(v.clone())).ok())
+                        .unwrap_or_default();
+                    commands::file_response(&mut self.state, request_id, &path, stdout, encoding)
+                } else {
+                    let stderr = String::from_utf8_lossy(stderr);
+                    Response::err(request_id, format!("get_file failed for {}: {}", path, stderr.trim()))
+                }
+            }
+            Some("put_file") => {
+                let path = context.get("path").cloned().unwrap_or_default();
+                if exit_code == Some(0) {
+                    let bytes = context.get("bytes").and_then(|v| v.parse::<u64>().ok()).unwrap_or(0);
+                    let mut data = serde_json::Map::new();
+                    if let Some(Ok(Value::Object(reply))) = context.get("reply").map(|r| serde_json::from_str(r)) {
+                        data = reply;
+                    }
+                    data.insert("path".to_string(), Value::from(path));
+                    data.insert("bytes".to_string(), Value::from(bytes));
+                    Response::ok(request_id, Value::Object(data))
+                } else {
+                    let stderr = String::from_utf8_lossy(stderr);
+                    Response::err(request_id, format!("put_file failed for {}: {}", path, stderr.trim()))
+                }
+            }
+            _ => return,
+        };
+
+        if let Some(cli_id) = context.get("cli_id") {
+            respond(cli_id, &response);
+            unblock_cli_pipe_input(cli_id);
+        }
+    }
+
+    /// Write a resolved secret to its pane; the value never enters the response
+    ///
+    /// The write fills the place reserved when the request arrived, so
+    /// sends queued behind it to the same pane go out afterwards.
+    fn finish_send_secret(&mut self, request_id: &str, exit_code: Option<i32>, stdout: &[u8], context: &BTreeMap<String, String>) -> Response {
+        let secret_ref = context.get("secret_ref").cloned().unwrap_or_default();
+        let value = secrets::trim_secret_output(stdout);
+        let pane_id = context.get("pane_id").and_then(|v| v.parse::<u32>().ok());
+        let ticket = context.get("write_ticket").and_then(|v| v.parse::<u64>().ok());
+
+        let (Some(pane_id), Some(ticket)) = (pane_id, ticket) else {
+            return Response::err_code(request_id, ErrorCode::NotFound, "secret not found");
+        };
+        if !self.writes.holds(pane_id, ticket) {
+            // Writing now would put the secret after sends that were meant
+            // to follow it
+            return Response::err(request_id, "write reservation expired");
+        }
+        let check = if exit_code != Some(0) || value.is_empty() {
+            Err(Error::new(ErrorCode::NotFound, "secret not found"))
+        } else if self.state.get_pane(pane_id).is_none() {
+            Err(Error::new(ErrorCode::NotFound, format!("pane not found: {}", pane_id)))
+        } else {
+            Ok(())
+        };
+        if let Err(e) = check {
+            flush_writes(pane_id, self.writes.complete(pane_id, ticket, None));
+            self.arm_pacing();
+            return Response::err(request_id, e);
+        }
+
+        let mut bytes = value.to_vec();
+        if context.get("enter").is_some_and(|v| v == "true") {
+            bytes.push(b'\n');
+        }
+        flush_writes(pane_id, self.writes.complete(pane_id, ticket, Some(bytes)));
+        self.arm_pacing();
+        // Scrub the value from anything the pane echoes back
+        self.state.redact_literal("secret", &String::from_utf8_lossy(value));
+
+        Response::ok(request_id, serde_json::json!({
+            "pane_id": pane_id,
+            "secret_ref": secret_ref,
+            "sent": true,
+        }))
+    }
+}
+
+/// A layout given by path when it looks like one, else by built-in name
+fn layout_info(layout: &str) -> LayoutInfo {
+    if layout.contains('/') || layout.ends_with(".kdl") {
+        LayoutInfo::File(layout.to_string(), LayoutMetadata::default())
+    } else {
+        LayoutInfo::BuiltIn(layout.to_string())
+    }
+}
+
+/// Perform writes released by the write queue, in order
+fn flush_writes(pane_id: u32, writes: Vec<Vec<u8>>) {
+    for bytes in writes {
+        write_to_pane_id(bytes, PaneId::Terminal(pane_id));
+    }
+}
+
+/// Run a host command whose result completes a pending request
+fn run_deferred(
+    args: &[String],
+    cwd: Option<&str>,
+    action: &str,
+    request_id: &str,
+    cli_id: Option<&str>,
+    mut context: BTreeMap<String, String>,
+) {
+    context.insert("request_id".to_string(), request_id.to_string());
+    context.insert("action".to_string(), action.to_string());
+    if let Some(cli_id) = cli_id {
+        context.insert("cli_id".to_string(), cli_id.to_string());
+    }
+    let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+    match cwd {
+        Some(cwd) => run_command_with_env_variables_and_cwd(&args, BTreeMap::new(), PathBuf::from(cwd), context),
+        None => run_command(&args, context),
+    }
+}
+
+/// Open the command pane an open_pane effect describes
+fn open_pane(data: &Value, cwd: Option<&str>) {
+    let args: Vec<String> = data
+        .get("command")
+        .and_then(|v| serde_json::from_value(v.clone()).ok())
+        .unwrap_or_default();
+    let Some((program, args)) = args.split_first() else {
+        return;
+    };
+    let command = CommandToRun {
+        path: PathBuf::from(program),
+        args: args.to_vec(),
+        cwd: cwd.map(PathBuf::from),
+    };
+    // Carried to CommandPaneOpened, which names and registers the pane
+    let mut context = BTreeMap::new();
+    if let Some(title) = data.get("title").and_then(|v| v.as_str()) {
+        context.insert("title".to_string(), title.to_string());
+    }
+    if let Some(agent) = data.get("agent") {
+        context.insert("agent".to_string(), agent.to_string());
+    }
+    if let Some(cwd) = cwd {
+        context.insert("cwd".to_string(), cwd.to_string());
+    }
+    // A run_command request waiting for the pane id
+    for key in ["request_id", "cli_id"] {
+        if let Some(value) = data.get(key).and_then(|v| v.as_str()) {
+            context.insert(key.to_string(), value.to_string());
+        }
+    }
+    if data.get("floating").and_then(|v| v.as_bool()).unwrap_or(false) {
+        // Placement from open_floating_command, as percentages
+        let coordinates = data.get("coordinates").and_then(|c| {
+            let at = |key: &str| c.get(key).and_then(|v| v.as_str()).map(String::from);
+            FloatingPaneCoordinates::new(at("x"), at("y"), at("width"), at("height"), None, None)
+        });
+        open_command_pane_floating(command, coordinates, context);
+    } else {
+        open_command_pane(command, context);
+    }
+}
+
+/// Send a response back over a CLI pipe as one JSON line
+fn respond(cli_id: &str, response: &Response) {
+    if let Some(line) = response.to_line() {
+        cli_pipe_output(cli_id, &line);
+    }
 }
 
 register_plugin!(NzmAgent);
 
 impl ZellijPlugin for NzmAgent {
-    fn load(&mut self, _config: std::collections::BTreeMap<String, String>) {
+    fn load(&mut self, config: BTreeMap<String, String>) {
+        self.state.set_config(Config::from_map(&config));
+        self.chaos = Chaos::from_config(self.state.config());
+        self.tick_housekeeping();
         request_permission(&[
             PermissionType::ReadApplicationState,
+            PermissionType::ChangeApplicationState,
             PermissionType::WriteToStdin,
             PermissionType::RunCommands,
             PermissionType::MessageAndLaunchOtherPlugins,
+            PermissionType::ReadPaneContents,
         ]);
         subscribe(&[
             EventType::PaneUpdate,
             EventType::PermissionRequestResult,
+            EventType::PaneRenderReport,
+            EventType::RunCommandResult,
+            EventType::CommandPaneOpened,
+            EventType::TabUpdate,
+            EventType::ModeUpdate,
+            EventType::Timer,
         ]);
         self.initialized = true;
     }
 
     fn update(&mut self, event: Event) -> bool {
         match event {
-            Event::PaneUpdate(manifest) => {
-                self.state.update_panes(manifest);
-                true
+            Event::PaneUpdate(manifest) => match self.chaos_fate() {
+                Fate::Now => {
+                    // A held manifest is older than this one; never apply it later
+                    self.delayed.retain(|(_, d)| !matches!(d, Delayed::Panes(_)));
+                    self.apply_panes(manifest);
+                    true
+                }
+                Fate::Drop => false,
+                Fate::Delay(secs) => {
+                    match self.delayed.iter_mut().find(|(_, d)| matches!(d, Delayed::Panes(_))) {
+                        Some((_, held)) => *held = Delayed::Panes(manifest),
+                        None => self.delay(secs, Delayed::Panes(manifest)),
+                    }
+                    false
+                }
+            },
+            // Every subsystem shares Zellij's timer and runs when its own
+            // deadline has passed, whichever timer fired
+            Event::Timer(_) => {
+                if self.pacer.take_due(self.clock.now_ms()) {
+                    self.tick_pacing();
+                }
+                self.expire_reservations();
+                self.expire_spawns();
+                self.expire_startups();
+                self.tick_housekeeping();
+                self.answer_polls();
+                self.answer_waits();
+                self.finish_fanouts();
+                let now = self.clock.now_ms();
+                self.run_delayed(now)
+            }
+            Event::PaneRenderReport(report) => {
+                for (pane_id, contents) in report {
+                    if let PaneId::Terminal(id) = pane_id {
+                        // Reports carry only the viewport; the scrollback
+                        // has to be asked for
+                        let contents = get_pane_scrollback(pane_id, true).unwrap_or(contents);
+                        let viewport_rows = contents.viewport.len();
+                        let mut lines = contents.lines_above_viewport;
+                        lines.extend(contents.viewport);
+                        lines.extend(contents.lines_below_viewport);
+                        self.state.update_pane_contents(id, lines);
+                        self.state.set_viewport_rows(id, viewport_rows);
+                        if let Some(hit) = self.state.detect_safe_word(id) {
+                            let event = serde_json::to_value(&hit).unwrap_or_default();
+                            self.state.emit_event("safe_word", event);
+                        }
+                    }
+                }
+                // Launching agents are marked ready from their output
+                self.run_state_effects();
+                self.send_events();
+                self.finish_fanouts();
+                // Agent statuses on the dashboard come from pane contents,
+                // but most output leaves them as they were
+                self.drawn.changed(&self.state)
+            }
+            Event::RunCommandResult(exit_code, stdout, stderr, context) => {
+                if context.get("action").is_some_and(|a| a == "plugin_hash") {
+                    let hash = match integrity::parse_hash(&String::from_utf8_lossy(&stdout)) {
+                        Some(hash) if exit_code == Some(0) => Ok(hash),
+                        _ => Err(String::from_utf8_lossy(&stderr).trim().to_string()),
+                    };
+                    self.state.set_plugin_hash(hash);
+                    return false;
+                }
+                let git = context.get("action").is_some_and(|a| a == "git_info");
+                self.finish_deferred(exit_code, &stdout, &stderr, &context);
+                // A refreshed branch shows in the dashboard
+                git && self.state.config().dashboard_git
+            }
+            Event::ModeUpdate(mode) => {
+                // new_pane names the session on the host
+                if let Some(name) = mode.session_name {
+                    self.state.set_session_name(name);
+                }
+                false
+            }
+            Event::TabUpdate(tabs) => {
+                self.state.update_tabs(&tabs);
+                // Tabs opened from a layout get their names
+                self.run_state_effects();
+                false
+            }
+            Event::CommandPaneOpened(pane_id, context) => {
+                if let Some(title) = context.get("title") {
+                    rename_terminal_pane(pane_id, title);
+                }
+                if let Some(cwd) = context.get("cwd") {
+                    self.state.set_pane_cwd(pane_id, cwd);
+                }
+                if let (Some(request_id), Some(cli_id)) = (context.get("request_id"), context.get("cli_id")) {
+                    let data = serde_json::json!({ "pane_id": pane_id, "name": context.get("title") });
+                    respond(cli_id, &Response::ok(request_id, data));
+                    unblock_cli_pipe_input(cli_id);
+                }
+                if let Some(name) = context.get("agent").and_then(|a| serde_json::from_str(a).ok()) {
+                    self.state.adopt_pane(pane_id, name);
+                    if let Some(timeout_secs) = self.state.mark_starting(pane_id) {
+                        self.clock.set_timeout(timeout_secs as f64);
+                    }
+                    self.run_state_effects();
+                    self.send_events();
+                }
+                false
             }
             Event::PermissionRequestResult(result) => {
                 if result == PermissionStatus::Granted {
-                    // Permissions granted, we're ready
+                    // Hash the loaded file for get_version
+                    if let Some(path) = &self.state.config().plugin_path {
+                        let args = integrity::hash_command(path);
+                        let args: Vec<&str> = args.iter().map(|a| a.as_str()).collect();
+                        run_command(&args, BTreeMap::from([("action".to_string(), "plugin_hash".to_string())]));
+                    }
                 }
                 false
             }
@@ -51,60 +1264,75 @@ impl ZellijPlugin for NzmAgent {
     }
 
     fn pipe(&mut self, pipe_message: PipeMessage) -> bool {
+        let cli_id = match &pipe_message.source {
+            PipeSource::Cli(cli_id) => Some(cli_id.to_string()),
+            _ => None,
+        };
+
+        // Keybindings name the action and target the focused pane
+        let request = match pipe_message.source {
+            PipeSource::Keybind => {
+                Some(keybind::keybind_request(&pipe_message.name, pipe_message.payload.as_deref(), &self.state))
+            }
+            // Blank lines from a streaming pipe are ignored
+            _ => pipe_message
+                .payload
+                .filter(|p| !p.trim().is_empty())
+                .map(|payload| serde_json::from_str::<Request>(&payload).map_err(|e| e.to_string())),
+        };
+
         // Handle incoming IPC messages
-        if let Some(payload) = pipe_message.payload {
-            match serde_json::from_str::<Request>(&payload) {
+        if let Some(request) = request {
+            match request {
                 Ok(request) => {
-                    let mut response = commands::dispatch_command(&request, &self.state);
+                    // Chaos decides before dispatch, so a dropped request is
+                    // lost whole: no state change, no effect, no reply
+                    let fate = match request.explain {
+                        true => Fate::Now,
+                        false => self.chaos_fate(),
+                    };
+                    if fate == Fate::Drop {
+                        return false;
+                    }
+                    let mut response = commands::dispatch_command(&request, &mut self.state);
                     response.id = request.id.clone();
+                    self.run_state_effects();
+                    self.send_events();
 
                     // Execute actual Zellij commands if needed
-                    if response.success {
-                        if let Some(ref data) = response.data {
-                            if let Some(action) = data.get("action").and_then(|v| v.as_str()) {
-                                match action {
-                                    "send_keys" => {
-                                        if let (Some(pane_id), Some(text)) = (
-                                            data.get("pane_id").and_then(|v| v.as_u64()),
-                                            data.get("text").and_then(|v| v.as_str()),
-                                        ) {
-                                            let enter = data.get("enter").and_then(|v| v.as_bool()).unwrap_or(false);
-                                            write_chars_to_pane_id(text, PaneId::Terminal(pane_id as u32));
-                                            if enter {
-                                                write_chars_to_pane_id("\n", PaneId::Terminal(pane_id as u32));
-                                            }
-                                        }
-                                    }
-                                    "send_interrupt" => {
-                                        if let Some(pane_id) = data.get("pane_id").and_then(|v| v.as_u64()) {
-                                            // Send Ctrl+C (ASCII 3)
-                                            write_chars_to_pane_id("\x03", PaneId::Terminal(pane_id as u32));
-                                        }
-                                    }
-                                    _ => {}
-                                }
+                    let has_effect = response.data.as_ref().is_some_and(|d| d.get("action").is_some());
+                    let deferred = match (&response.data, response.success) {
+                        _ if request.explain => false,
+                        (Some(data), true) if has_effect => match fate {
+                            Fate::Delay(secs) => {
+                                self.delay(secs, Delayed::Effect {
+                                    request_id: request.id.clone(),
+                                    cli_id: cli_id.clone(),
+                                    response: response.clone(),
+                                });
+                                true
                             }
-                        }
-                    }
+                            _ => self.execute_effect(&request.id, cli_id.as_deref(), data),
+                        },
+                        _ => false,
+                    };
 
-                    if let Ok(response_json) = serde_json::to_string(&response) {
-                        // Send response back via CLI pipe
-                        if let PipeSource::Cli(cli_id) = pipe_message.source {
-                            cli_pipe_output(&cli_id.to_string(), &response_json);
+                    if cli_id.is_none() && !response.success {
+                        self.report_unanswered(&request.action, &response);
+                    } else if let Some(ref cli_id) = cli_id {
+                        if deferred {
+                            // Hold the CLI pipe open until the command result arrives
+                            block_cli_pipe_input(cli_id);
+                        } else {
+                            respond(cli_id, &response);
                         }
                     }
                 }
                 Err(e) => {
-                    let error_response = Response {
-                        id: String::new(),
-                        success: false,
-                        data: None,
-                        error: Some(format!("Failed to parse request: {}", e)),
-                    };
-                    if let Ok(response_json) = serde_json::to_string(&error_response) {
-                        if let PipeSource::Cli(cli_id) = pipe_message.source {
-                            cli_pipe_output(&cli_id.to_string(), &response_json);
-                        }
+                    let error_response = Response::err("", format!("Failed to parse request: {}", e));
+                    match cli_id {
+                        Some(ref cli_id) => respond(cli_id, &error_response),
+                        None => self.report_unanswered("", &error_response),
                     }
                 }
             }
@@ -112,8 +1340,9 @@ impl ZellijPlugin for NzmAgent {
         false
     }
 
-    fn render(&mut self, _rows: usize, _cols: usize) {
-        // Plugin UI is minimal - just show status
-        println!("NZM Agent | Panes: {}", self.state.panes().len());
+    fn render(&mut self, rows: usize, cols: usize) {
+        for line in dashboard::render(&self.state, rows, cols) {
+            println!("{}", line);
+        }
     }
 }
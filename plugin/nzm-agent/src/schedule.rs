@@ -0,0 +1,274 @@
+//! Recurring actions run on cron schedules declared in config
+//!
+//! A `schedule.<name>` config key holds JSON naming an action, composite
+//! ones included, and when to run it:
+//!
+//! ```json
+//! {"cron": "0 3 * * 1-5",
+//!  "action": "enqueue_task",
+//!  "params": {"selector": "api__cc_1", "prompt": "update deps and run tests"},
+//!  "jitter_secs": 300}
+//! ```
+//!
+//! `cron` takes the usual five fields (minute, hour, day of month, month,
+//! day of week, Sunday being 0 or 7) with `*`, lists, ranges and `/` steps,
+//! or one of `@hourly`, `@daily`, `@weekly`, `@monthly`. When both day
+//! fields are restricted, either one matching is enough. Times are at the
+//! `utc_offset` config key, as for dispatch hours.
+//!
+//! Each run is delayed by up to `jitter_secs`, the same amount for the same
+//! schedule and time, so schedules sharing a time do not all fire at once.
+//! A schedule's first run is the first time after the plugin loads; runs
+//! missed while it was not loaded are not made up. Runs are skipped while
+//! automation is paused for every project.
+
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::clock::civil_from_days;
+
+/// Longest time between checks for due schedules
+#[cfg(any(target_arch = "wasm32", test))]
+pub const SCHEDULE_CHECK_SECS: u64 = 15;
+
+/// Days searched for a schedule's next run, enough for February 29th
+const SEARCH_DAYS: i64 = 4 * 366 + 1;
+
+/// A parsed cron expression
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Cron {
+    minutes: u64,
+    hours: u32,
+    days: u32,
+    months: u16,
+    weekdays: u8,
+    /// Whether the day of month or the day of week field is `*`
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Cron {
+    /// Parse a five-field expression or an `@` alias
+    pub fn parse(expr: &str) -> Result<Self, String> {
+        let expr = match expr.trim() {
+            "@hourly" => "0 * * * *",
+            "@daily" | "@midnight" => "0 0 * * *",
+            "@weekly" => "0 0 * * 0",
+            "@monthly" => "0 0 1 * *",
+            expr => expr,
+        };
+        let fields: Vec<&str> = expr.split_whitespace().collect();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(format!("cron expression needs 5 fields: {}", expr));
+        };
+        let weekdays = parse_field(weekday, 0, 7)?;
+        Ok(Cron {
+            minutes: parse_field(minute, 0, 59)?,
+            hours: parse_field(hour, 0, 23)? as u32,
+            days: parse_field(day, 1, 31)? as u32,
+            months: parse_field(month, 1, 12)? as u16,
+            // 7 is Sunday too
+            weekdays: ((weekdays | weekdays >> 7) & 0x7f) as u8,
+            any_day: day == "*",
+            any_weekday: weekday == "*",
+        })
+    }
+
+    /// Whether the expression runs on a date
+    fn matches_day(&self, day: u32, month: u32, weekday: u32) -> bool {
+        if self.months & (1 << month) == 0 {
+            return false;
+        }
+        let day_ok = self.days & (1 << day) != 0;
+        let weekday_ok = self.weekdays & (1 << weekday) != 0;
+        match (self.any_day, self.any_weekday) {
+            (false, false) => day_ok || weekday_ok,
+            _ => day_ok && weekday_ok,
+        }
+    }
+
+    /// The first minute the expression runs after `minute`, counting
+    /// minutes since 1970-01-01 00:00 at the same offset
+    pub fn next_after(&self, minute: i64) -> Option<i64> {
+        let start = minute + 1;
+        let first_day = start.div_euclid(1440);
+        for day in first_day..first_day + SEARCH_DAYS {
+            let (_, month, date) = civil_from_days(day);
+            let weekday = (day + 4).rem_euclid(7) as u32;
+            if !self.matches_day(date, month, weekday) {
+                continue;
+            }
+            let from = if day == first_day { start.rem_euclid(1440) } else { 0 };
+            let found = (from..1440).find(|m| self.hours & (1 << (m / 60)) != 0 && self.minutes & (1 << (m % 60)) != 0);
+            if let Some(m) = found {
+                return Some(day * 1440 + m);
+            }
+        }
+        None
+    }
+}
+
+/// Bits set for the values a cron field allows, from `min` to `max`
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64, String> {
+    let mut bits = 0u64;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().ok().filter(|&s| s > 0)),
+            None => (part, Some(1)),
+        };
+        let step = step.ok_or_else(|| format!("bad step in cron field: {}", field))?;
+        let value = |s: &str| s.parse::<u32>().ok().filter(|v| (min..=max).contains(v));
+        let bounds = match range.split_once('-') {
+            _ if range == "*" => Some((min, max)),
+            Some((from, to)) => value(from).zip(value(to)),
+            // `5/15` runs from 5 to the end of the field
+            None if part.contains('/') => value(range).map(|from| (from, max)),
+            None => value(range).map(|v| (v, v)),
+        };
+        let (from, to) = bounds.ok_or_else(|| format!("bad cron field: {}", field))?;
+        if from > to {
+            return Err(format!("bad range in cron field: {}", field));
+        }
+        for v in (from..=to).step_by(step as usize) {
+            bits |= 1 << v;
+        }
+    }
+    Ok(bits)
+}
+
+/// A recurring action from a `schedule.<name>` key
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Schedule {
+    pub name: String,
+    /// The cron expression as written
+    pub expr: String,
+    pub cron: Cron,
+    pub action: String,
+    pub params: Value,
+    pub jitter_secs: u64,
+}
+
+/// The JSON of a `schedule.<name>` key
+#[derive(Deserialize)]
+struct ScheduleSpec {
+    cron: String,
+    action: String,
+    #[serde(default)]
+    params: Value,
+    #[serde(default)]
+    jitter_secs: u64,
+}
+
+impl Schedule {
+    /// Parse the JSON value of a `schedule.<name>` config key
+    pub fn parse(name: &str, json: &str) -> Result<Self, String> {
+        let spec: ScheduleSpec =
+            serde_json::from_str(json).map_err(|e| format!("invalid schedule {}: {}", name, e))?;
+        let cron = Cron::parse(&spec.cron).map_err(|e| format!("invalid schedule {}: {}", name, e))?;
+        Ok(Schedule {
+            name: name.to_string(),
+            expr: spec.cron,
+            cron,
+            action: spec.action,
+            params: spec.params,
+            jitter_secs: spec.jitter_secs,
+        })
+    }
+
+    /// When the schedule next runs after `now_ms`, jitter included, in
+    /// milliseconds since the Unix epoch
+    pub fn next_run(&self, now_ms: u64, offset_minutes: i32) -> Option<u64> {
+        // Jitter can move a run past the next minute; search from the
+        // minute of the last run that could still be ahead
+        let jitter_minutes = self.jitter_secs.div_ceil(60) as i64;
+        let local = (now_ms / 60_000) as i64 + offset_minutes as i64;
+        let mut minute = local - jitter_minutes - 1;
+        loop {
+            minute = self.cron.next_after(minute)?;
+            let at = ((minute - offset_minutes as i64) * 60_000) as u64 + self.jitter_ms(minute);
+            if at > now_ms {
+                return Some(at);
+            }
+        }
+    }
+
+    /// Delay of the run at a minute, the same every time for that minute
+    fn jitter_ms(&self, minute: i64) -> u64 {
+        if self.jitter_secs == 0 {
+            return 0;
+        }
+        // FNV-1a over the name and minute
+        let mut hash: u64 = 0xcbf2_9ce4_8422_2325;
+        for byte in self.name.bytes().chain(minute.to_le_bytes()) {
+            hash = (hash ^ byte as u64).wrapping_mul(0x0100_0000_01b3);
+        }
+        hash % (self.jitter_secs * 1000 + 1)
+    }
+}
+
+/// Outcome of a schedule's last run
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScheduleRun {
+    /// Milliseconds since the Unix epoch
+    pub at_ms: u64,
+    pub error: Option<String>,
+}
+
+/// When a schedule runs next and how its last run went
+#[derive(Debug, Clone, Default)]
+pub struct ScheduleState {
+    /// Milliseconds since the Unix epoch; None when it never runs again
+    pub next_ms: Option<u64>,
+    pub last_run: Option<ScheduleRun>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Minutes since the epoch of a UTC date and time
+    fn minute(days: i64, hour: i64, min: i64) -> i64 {
+        days * 1440 + hour * 60 + min
+    }
+
+    #[test]
+    fn test_parse_fields() {
+        let cron = Cron::parse("*/15 9-17 * * 1-5").unwrap();
+        assert_eq!(cron.minutes, 1 | 1 << 15 | 1 << 30 | 1 << 45);
+        assert_eq!(cron.weekdays, 0b0111110);
+        assert_eq!(Cron::parse("0 0 * * 7").unwrap().weekdays, 1);
+        assert_eq!(Cron::parse("@daily").unwrap(), Cron::parse("0 0 * * *").unwrap());
+        assert!(Cron::parse("60 * * * *").is_err());
+        assert!(Cron::parse("* * *").is_err());
+        assert!(Cron::parse("*/0 * * * *").is_err());
+    }
+
+    #[test]
+    fn test_next_after() {
+        // 1970-01-01 was a Thursday
+        let weekdays = Cron::parse("30 3 * * 1-5").unwrap();
+        assert_eq!(weekdays.next_after(minute(0, 3, 30)), Some(minute(1, 3, 30)));
+        assert_eq!(weekdays.next_after(minute(1, 4, 0)), Some(minute(4, 3, 30)));
+
+        // Either day field matches when both are restricted
+        let either = Cron::parse("0 0 13 * 5").unwrap();
+        assert_eq!(either.next_after(minute(0, 0, 0)), Some(minute(1, 0, 0)));
+        let leap = Cron::parse("0 0 29 2 *").unwrap();
+        assert_eq!(leap.next_after(0), Some(minute(789, 0, 0)));
+    }
+
+    #[test]
+    fn test_next_run_with_offset_and_jitter() {
+        let schedule = Schedule::parse("nightly", r#"{"cron": "0 22 * * *", "action": "list_panes"}"#).unwrap();
+        assert_eq!(schedule.next_run(0, 0), Some(22 * 3_600_000));
+        // 22:00 at +02:00 is 20:00 UTC
+        assert_eq!(schedule.next_run(0, 120), Some(20 * 3_600_000));
+
+        let jittered = Schedule { jitter_secs: 600, ..schedule };
+        let at = jittered.next_run(0, 0).unwrap();
+        assert!((22 * 3_600_000..=22 * 3_600_000 + 600_000).contains(&at));
+        assert_eq!(jittered.next_run(at - 1, 0), Some(at));
+        assert!(jittered.next_run(at, 0).unwrap() > at + 23 * 3_600_000);
+        assert!(Schedule::parse("x", r#"{"cron": "bad", "action": "a"}"#).is_err());
+    }
+}
@@ -0,0 +1,249 @@
+//! Embedded Rhai scripting for user-defined classifiers and routing hooks
+//!
+//! Scripts come from the `script` / `script_file` config keys and may define:
+//! - `fn classify(pane, lines)`: return a status string for a pane
+//! - `fn route(action, params, panes)`: return `()` to allow a request
+//!   unchanged, a map to replace its params, or a string to reject it
+//! - `fn idle(pane, idle_secs)`: return false or a reason string to keep an
+//!   idle agent from its idle action
+//!
+//! Scripts only see read-only snapshots of panes; they cannot touch State or
+//! emit effects directly, and the engine enforces operation/size limits.
+//! A `script_file` must live in the plugin's data directory, which a
+//! checked-out project cannot write to.
+
+use std::path::{Component, Path, PathBuf};
+
+use rhai::{Array, Dynamic, Engine, Map, Scope, AST};
+use serde_json::Value;
+use zellij_tile::prelude::PaneInfo;
+
+/// Outcome of the `route` hook for a request
+#[derive(Debug, Clone, PartialEq)]
+pub enum RouteDecision {
+    Allow,
+    Rewrite(Value),
+    Deny(String),
+}
+
+/// Directory `script_file` is resolved against and confined to
+pub const SCRIPT_DIR: &str = "/data";
+
+/// Resolve a `script_file` setting, rejecting paths outside `SCRIPT_DIR`
+pub fn script_path(path: &str) -> Result<PathBuf, String> {
+    let resolved = Path::new(SCRIPT_DIR).join(path);
+    if !resolved.starts_with(SCRIPT_DIR) || resolved.components().any(|c| c == Component::ParentDir) {
+        return Err(format!("script_file must be inside {}", SCRIPT_DIR));
+    }
+    Ok(resolved)
+}
+
+/// A compiled user script
+pub struct ScriptHooks {
+    engine: Engine,
+    ast: AST,
+}
+
+impl ScriptHooks {
+    /// Compile a script in a sandboxed engine
+    pub fn compile(source: &str) -> Result<Self, String> {
+        let mut engine = Engine::new();
+        engine
+            .set_max_operations(100_000)
+            .set_max_call_levels(32)
+            .set_max_expr_depths(64, 32)
+            .set_max_string_size(1 << 20)
+            .set_max_array_size(10_000)
+            .set_max_map_size(10_000)
+            .disable_symbol("eval");
+
+        let ast = engine.compile(source).map_err(|e| format!("script error: {}", e))?;
+        Ok(ScriptHooks { engine, ast })
+    }
+
+    fn has_fn(&self, name: &str, params: usize) -> bool {
+        self.ast.iter_functions().any(|f| f.name == name && f.params.len() == params)
+    }
+
+    /// Run the `classify` hook, if the script defines one
+    pub fn classify(&self, pane: &PaneInfo, lines: &[String]) -> Option<Result<String, String>> {
+        if !self.has_fn("classify", 2) {
+            return None;
+        }
+        let lines: Array = lines.iter().map(|l| Dynamic::from(l.clone())).collect();
+        let result = self
+            .engine
+            .call_fn::<Dynamic>(&mut Scope::new(), &self.ast, "classify", (pane_map(pane), lines))
+            .map_err(|e| format!("classify hook failed: {}", e))
+            .and_then(|status| {
+                status
+                    .into_string()
+                    .map_err(|t| format!("classify hook must return a string, got {}", t))
+            });
+        Some(result)
+    }
+
+    /// Run the `route` hook for a request (allow when undefined)
+    pub fn route(&self, action: &str, params: &Value, panes: &[PaneInfo]) -> Result<RouteDecision, String> {
+        if !self.has_fn("route", 3) {
+            return Ok(RouteDecision::Allow);
+        }
+
+        let params = rhai::serde::to_dynamic(params).map_err(|e| format!("route hook failed: {}", e))?;
+        let panes: Array = panes.iter().map(|p| Dynamic::from_map(pane_map(p))).collect();
+        let result = self
+            .engine
+            .call_fn::<Dynamic>(&mut Scope::new(), &self.ast, "route", (action.to_string(), params, panes))
+            .map_err(|e| format!("route hook failed: {}", e))?;
+
+        if result.is_unit() {
+            Ok(RouteDecision::Allow)
+        } else if result.is_string() {
+            Ok(RouteDecision::Deny(result.into_string().unwrap_or_default()))
+        } else if result.is_map() {
+            rhai::serde::from_dynamic::<Value>(&result)
+                .map(RouteDecision::Rewrite)
+                .map_err(|e| format!("route hook failed: {}", e))
+        } else {
+            Err(format!("route hook returned unsupported {}", result.type_name()))
+        }
+    }
+
+    /// Run the `idle` hook for an agent past its idle timeout; Some gives
+    /// the reason it is kept as it is (none when undefined)
+    pub fn idle(&self, pane: &PaneInfo, idle_secs: u64) -> Result<Option<String>, String> {
+        if !self.has_fn("idle", 2) {
+            return Ok(None);
+        }
+
+        let result = self
+            .engine
+            .call_fn::<Dynamic>(&mut Scope::new(), &self.ast, "idle", (pane_map(pane), idle_secs as i64))
+            .map_err(|e| format!("idle hook failed: {}", e))?;
+
+        if result.as_bool() == Ok(false) {
+            Ok(Some("vetoed by idle hook".to_string()))
+        } else if result.is_string() {
+            Ok(Some(result.into_string().unwrap_or_default()))
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+/// Read-only view of a pane handed to scripts
+fn pane_map(pane: &PaneInfo) -> Map {
+    let mut map = Map::new();
+    map.insert("id".into(), Dynamic::from(pane.id as i64));
+    map.insert("title".into(), Dynamic::from(pane.title.clone()));
+    map.insert("is_focused".into(), Dynamic::from(pane.is_focused));
+    map.insert("is_floating".into(), Dynamic::from(pane.is_floating));
+    map.insert(
+        "command".into(),
+        pane.terminal_command.clone().map(Dynamic::from).unwrap_or(Dynamic::UNIT),
+    );
+    map
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pane(id: u32, title: &str) -> PaneInfo {
+        PaneInfo {
+            id,
+            title: title.to_string(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_compile_error_is_reported() {
+        let err = ScriptHooks::compile("fn broken( {").err().unwrap();
+        assert!(err.starts_with("script error"));
+    }
+
+    #[test]
+    fn test_script_path_is_confined_to_data_dir() {
+        assert_eq!(script_path("hooks.rhai").unwrap(), PathBuf::from("/data/hooks.rhai"));
+        assert_eq!(script_path("/data/nzm/hooks.rhai").unwrap(), PathBuf::from("/data/nzm/hooks.rhai"));
+        for path in ["/host/hooks.rhai", "../host/hooks.rhai", "/data/../host/hooks.rhai"] {
+            assert_eq!(script_path(path).unwrap_err(), "script_file must be inside /data");
+        }
+    }
+
+    #[test]
+    fn test_classify_hook() {
+        let hooks = ScriptHooks::compile(r#"
+            fn classify(pane, lines) {
+                if lines.len() > 0 && lines[lines.len() - 1].contains("> ") { "idle" } else { "busy" }
+            }
+        "#).unwrap();
+
+        let status = hooks.classify(&pane(1, "p"), &["working".into(), "> ".into()]);
+        assert_eq!(status, Some(Ok("idle".to_string())));
+        let status = hooks.classify(&pane(1, "p"), &["working".into()]);
+        assert_eq!(status, Some(Ok("busy".to_string())));
+    }
+
+    #[test]
+    fn test_missing_hooks_default() {
+        let hooks = ScriptHooks::compile("let x = 1;").unwrap();
+        assert!(hooks.classify(&pane(1, "p"), &[]).is_none());
+        assert_eq!(hooks.route("list_panes", &Value::Null, &[]).unwrap(), RouteDecision::Allow);
+    }
+
+    #[test]
+    fn test_route_hook_can_deny_and_rewrite() {
+        let hooks = ScriptHooks::compile(r#"
+            fn route(action, params, panes) {
+                if action == "send_interrupt" { return "interrupts disabled"; }
+                if action == "send_keys" { params.enter = true; return params; }
+            }
+        "#).unwrap();
+
+        assert_eq!(
+            hooks.route("send_interrupt", &serde_json::json!({"pane_id": 1}), &[]).unwrap(),
+            RouteDecision::Deny("interrupts disabled".to_string())
+        );
+        assert_eq!(
+            hooks.route("send_keys", &serde_json::json!({"pane_id": 1, "text": "hi"}), &[]).unwrap(),
+            RouteDecision::Rewrite(serde_json::json!({"pane_id": 1, "text": "hi", "enter": true}))
+        );
+        assert_eq!(hooks.route("list_panes", &Value::Null, &[]).unwrap(), RouteDecision::Allow);
+    }
+
+    #[test]
+    fn test_route_sees_pane_snapshot() {
+        let hooks = ScriptHooks::compile(r#"
+            fn route(action, params, panes) {
+                if panes.len() < 2 { "need two panes" }
+            }
+        "#).unwrap();
+
+        assert!(matches!(hooks.route("x", &Value::Null, &[pane(1, "a")]).unwrap(), RouteDecision::Deny(_)));
+        assert_eq!(hooks.route("x", &Value::Null, &[pane(1, "a"), pane(2, "b")]).unwrap(), RouteDecision::Allow);
+    }
+
+    #[test]
+    fn test_idle_hook_can_veto() {
+        let hooks = ScriptHooks::compile(r#"
+            fn idle(pane, idle_secs) {
+                if pane.title.contains("lead") { return "lead stays up"; }
+                idle_secs > 60
+            }
+        "#).unwrap();
+
+        assert_eq!(hooks.idle(&pane(1, "p__cc_1"), 120).unwrap(), None);
+        assert_eq!(hooks.idle(&pane(1, "p__cc_1"), 30).unwrap(), Some("vetoed by idle hook".to_string()));
+        assert_eq!(hooks.idle(&pane(2, "lead"), 120).unwrap(), Some("lead stays up".to_string()));
+        assert_eq!(ScriptHooks::compile("let x = 1;").unwrap().idle(&pane(1, "p"), 120).unwrap(), None);
+    }
+
+    #[test]
+    fn test_runaway_script_is_stopped() {
+        let hooks = ScriptHooks::compile("fn classify(pane, lines) { loop {} }").unwrap();
+        let result = hooks.classify(&pane(1, "p"), &[]).unwrap();
+        assert!(result.unwrap_err().contains("classify hook failed"));
+    }
+}
@@ -0,0 +1,152 @@
+//! Secret references resolved on the host at execution time
+//!
+//! Requests only ever carry a reference such as `env:OPENAI_API_KEY` or
+//! `pass:work/openai`; the plaintext is fetched by a host command and
+//! written straight to the pane.
+
+use std::fmt;
+
+use crate::config::Config;
+
+/// A reference to a secret value held outside the plugin
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SecretRef {
+    /// Environment variable of the Zellij server process
+    Env(String),
+    /// Entry in the `pass` password store (first line is used)
+    Pass(String),
+    /// File encrypted with `age`, decrypted with the configured identity
+    Age(String),
+    /// macOS keychain generic password, looked up by service name
+    Keychain(String),
+}
+
+impl SecretRef {
+    /// Parse a `provider:name` reference
+    ///
+    /// The provider is required: a bare string is more likely a value
+    /// pasted by mistake than a name, and must not be looked up anywhere.
+    pub fn parse(s: &str) -> Result<SecretRef, String> {
+        // Errors never echo the input: a confused caller may have passed the value itself
+        let Some((provider, name)) = s.split_once(':') else {
+            return Err("secret_ref needs a provider, as in env:NAME or pass:NAME".to_string());
+        };
+        if name.is_empty() || name.starts_with('-') {
+            return Err("invalid secret name in secret_ref".to_string());
+        }
+        match provider {
+            "env" => {
+                let valid = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                    && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+                if !valid {
+                    return Err("invalid environment variable name in secret_ref".to_string());
+                }
+                Ok(SecretRef::Env(name.to_string()))
+            }
+            "pass" => Ok(SecretRef::Pass(name.to_string())),
+            "age" => Ok(SecretRef::Age(name.to_string())),
+            "keychain" => Ok(SecretRef::Keychain(name.to_string())),
+            _ => Err("unknown secret provider in secret_ref; use env, pass, age or keychain".to_string()),
+        }
+    }
+
+    /// Host command whose stdout is the secret value
+    ///
+    /// Names are passed as positional arguments, never interpolated.
+    pub fn resolve_command(&self, config: &Config) -> Vec<String> {
+        let (script, args): (&str, Vec<&str>) = match self {
+            SecretRef::Env(name) => ("printenv \"$1\"", vec![name]),
+            SecretRef::Pass(name) => ("pass show -- \"$1\" | head -n 1", vec![name]),
+            SecretRef::Age(path) => (
+                "age --decrypt -i \"${2:-$HOME/.config/age/keys.txt}\" -- \"$1\"",
+                vec![path, config.age_identity.as_deref().unwrap_or("")],
+            ),
+            SecretRef::Keychain(service) => ("security find-generic-password -w -s \"$1\"", vec![service]),
+        };
+
+        let mut cmd = vec![
+            "sh".to_string(),
+            "-c".to_string(),
+            script.to_string(),
+            "sh".to_string(),
+        ];
+        cmd.extend(args.into_iter().map(|a| a.to_string()));
+        cmd
+    }
+}
+
+impl fmt::Display for SecretRef {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SecretRef::Env(name) => write!(f, "env:{}", name),
+            SecretRef::Pass(name) => write!(f, "pass:{}", name),
+            SecretRef::Age(path) => write!(f, "age:{}", path),
+            SecretRef::Keychain(service) => write!(f, "keychain:{}", service),
+        }
+    }
+}
+
+/// Strip the single trailing newline most secret tools append
+#[cfg(any(target_arch = "wasm32", test))]
+pub fn trim_secret_output(stdout: &[u8]) -> &[u8] {
+    let stdout = stdout.strip_suffix(b"\n").unwrap_or(stdout);
+    stdout.strip_suffix(b"\r").unwrap_or(stdout)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_env_ref() {
+        assert_eq!(SecretRef::parse("env:API_KEY").unwrap(), SecretRef::Env("API_KEY".to_string()));
+        assert_eq!(SecretRef::parse("env:API_KEY").unwrap().to_string(), "env:API_KEY");
+    }
+
+    #[test]
+    fn test_parse_provider_refs() {
+        assert_eq!(SecretRef::parse("pass:work/openai").unwrap(), SecretRef::Pass("work/openai".to_string()));
+        assert_eq!(SecretRef::parse("age:keys/gh.age").unwrap(), SecretRef::Age("keys/gh.age".to_string()));
+        assert_eq!(SecretRef::parse("keychain:anthropic").unwrap(), SecretRef::Keychain("anthropic".to_string()));
+    }
+
+    #[test]
+    fn test_bare_value_is_rejected_without_echo() {
+        let err = SecretRef::parse("sk-live-123").unwrap_err();
+        assert!(err.contains("needs a provider") && !err.contains("sk-live"));
+        let err = SecretRef::parse("sk-live:123").unwrap_err();
+        assert!(err.contains("unknown secret provider") && !err.contains("sk-live"));
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_refs() {
+        assert!(SecretRef::parse("env:$(rm -rf)").is_err());
+        assert!(SecretRef::parse("env:1BAD").is_err());
+        assert!(SecretRef::parse("pass:--help").is_err());
+        assert!(SecretRef::parse("vault:x").unwrap_err().contains("unknown secret provider"));
+    }
+
+    #[test]
+    fn test_resolve_command_passes_name_as_argument() {
+        let cmd = SecretRef::Env("TOKEN".to_string()).resolve_command(&Config::default());
+        assert_eq!(cmd.last().unwrap(), "TOKEN");
+        assert!(!cmd[2].contains("TOKEN"));
+    }
+
+    #[test]
+    fn test_age_command_uses_configured_identity() {
+        let config = Config {
+            age_identity: Some("/keys/id.txt".to_string()),
+            ..Config::default()
+        };
+        let cmd = SecretRef::Age("s.age".to_string()).resolve_command(&config);
+        assert_eq!(&cmd[4..], ["s.age", "/keys/id.txt"]);
+    }
+
+    #[test]
+    fn test_trim_secret_output() {
+        assert_eq!(trim_secret_output(b"s3cret\n"), b"s3cret");
+        assert_eq!(trim_secret_output(b"s3cret\r\n"), b"s3cret");
+        assert_eq!(trim_secret_output(b"a\n\n"), b"a\n");
+    }
+}
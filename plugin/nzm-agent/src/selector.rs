@@ -0,0 +1,377 @@
+//! Pane selectors: target panes by id, handle, exact title, title prefix,
+//! focus, or their relation to another pane
+
+use serde::{Deserialize, Deserializer};
+use zellij_tile::prelude::PaneInfo;
+
+use crate::handles::is_handle;
+use crate::ipc::{Error, ErrorCode};
+use crate::state::State;
+
+/// A reference to one or more panes
+///
+/// Grammar:
+/// - `42` or `"42"` or `"id:42"`: pane id
+/// - `"<handle>"` or `"handle:<handle>"`: the pane holding that handle, if
+///   it is still open
+/// - `"title:<title>"`: exact title
+/// - `"<prefix>*"`: every pane whose title starts with `<prefix>`
+/// - `"focused"` or `"."`: the focused pane of the active tab
+/// - `"next"` / `"prev"`: the pane after or before the focused one in its
+///   tab, in reading order (top to bottom, left to right), wrapping around
+/// - `"right-of:<sel>"`, `"left-of:<sel>"`, `"above:<sel>"`, `"below:<sel>"`:
+///   the nearest tiled panes on that side of the pane `<sel>` names
+/// - `"same-project-as:<sel>"`: the other agents of that pane's project
+/// - anything else: exact title
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Selector {
+    Id(u32),
+    Handle(String),
+    Title(String),
+    Prefix(String),
+    Focused,
+    Next,
+    Prev,
+    Beside(Side, Box<Selector>),
+    SameProjectAs(Box<Selector>),
+}
+
+/// Which side of a pane a relational selector looks at
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Side {
+    Right,
+    Left,
+    Above,
+    Below,
+}
+
+impl Side {
+    fn name(self) -> &'static str {
+        match self {
+            Side::Right => "right-of",
+            Side::Left => "left-of",
+            Side::Above => "above",
+            Side::Below => "below",
+        }
+    }
+}
+
+impl Selector {
+    /// Parse a selector from its string form
+    pub fn parse(s: &str) -> Selector {
+        if let Ok(id) = s.parse::<u32>() {
+            return Selector::Id(id);
+        }
+        match s {
+            "focused" | "." => return Selector::Focused,
+            "next" => return Selector::Next,
+            "prev" => return Selector::Prev,
+            _ => {}
+        }
+        if let Some((relation, anchor)) = s.split_once(':') {
+            let anchor = Box::new(Selector::parse(anchor));
+            for side in [Side::Right, Side::Left, Side::Above, Side::Below] {
+                if relation == side.name() {
+                    return Selector::Beside(side, anchor);
+                }
+            }
+            if relation == "same-project-as" {
+                return Selector::SameProjectAs(anchor);
+            }
+        }
+        if let Some(rest) = s.strip_prefix("id:") {
+            if let Ok(id) = rest.parse::<u32>() {
+                return Selector::Id(id);
+            }
+        }
+        if let Some(rest) = s.strip_prefix("handle:") {
+            return Selector::Handle(rest.to_string());
+        }
+        if is_handle(s) {
+            return Selector::Handle(s.to_string());
+        }
+        if let Some(rest) = s.strip_prefix("title:") {
+            return Selector::Title(rest.to_string());
+        }
+        if let Some(prefix) = s.strip_suffix('*') {
+            return Selector::Prefix(prefix.to_string());
+        }
+        Selector::Title(s.to_string())
+    }
+
+    /// Whether a single pane is matched by this selector
+    ///
+    /// Without the tab list, `focused` matches a pane focused in any tab;
+    /// handles and relational selectors, which need the state, match
+    /// nothing.
+    pub fn matches(&self, pane: &PaneInfo) -> bool {
+        match self {
+            Selector::Id(id) => pane.id == *id,
+            Selector::Title(title) => pane.title == *title,
+            Selector::Prefix(prefix) => pane.title.starts_with(prefix.as_str()),
+            Selector::Focused => pane.is_focused,
+            _ => false,
+        }
+    }
+
+    /// Return every pane matched by this selector
+    pub fn resolve<'a>(&self, state: &'a State) -> Vec<&'a PaneInfo> {
+        match self {
+            Selector::Id(id) => state.get_pane(*id).into_iter().collect(),
+            Selector::Handle(handle) => state.get_pane_by_handle(handle).into_iter().collect(),
+            Selector::Title(title) => state.get_pane_by_title(title).into_iter().collect(),
+            Selector::Prefix(prefix) => state.get_panes_by_prefix(prefix),
+            Selector::Focused => state.focused_pane().into_iter().collect(),
+            Selector::Next => step_from_focus(state, 1),
+            Selector::Prev => step_from_focus(state, -1),
+            Selector::Beside(side, anchor) => match anchor.resolve_one(state) {
+                Ok(anchor) => beside(state, anchor, *side),
+                Err(_) => Vec::new(),
+            },
+            Selector::SameProjectAs(anchor) => {
+                let project_of = |pane: &PaneInfo| state.agent_name(pane).and_then(|(name, _)| name.project);
+                let Some((anchor, project)) = anchor
+                    .resolve_one(state)
+                    .ok()
+                    .and_then(|pane| Some((pane.id, project_of(pane)?)))
+                else {
+                    return Vec::new();
+                };
+                state
+                    .panes()
+                    .iter()
+                    .filter(|pane| pane.id != anchor && project_of(pane).as_ref() == Some(&project))
+                    .collect()
+            }
+        }
+    }
+
+    /// Resolve to exactly one pane, erroring on zero or multiple matches
+    pub fn resolve_one<'a>(&self, state: &'a State) -> Result<&'a PaneInfo, Error> {
+        let mut panes = self.resolve(state);
+        match panes.len() {
+            0 => Err(Error::new(ErrorCode::NotFound, format!("pane not found: {}", self))),
+            1 => Ok(panes.remove(0)),
+            n => Err(format!("selector {} matches {} panes", self, n).into()),
+        }
+    }
+}
+
+impl std::fmt::Display for Selector {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Selector::Id(id) => write!(f, "{}", id),
+            Selector::Handle(handle) => write!(f, "handle:{}", handle),
+            Selector::Title(title) => write!(f, "title:{}", title),
+            Selector::Prefix(prefix) => write!(f, "{}*", prefix),
+            Selector::Focused => write!(f, "focused"),
+            Selector::Next => write!(f, "next"),
+            Selector::Prev => write!(f, "prev"),
+            Selector::Beside(side, anchor) => write!(f, "{}:{}", side.name(), anchor),
+            Selector::SameProjectAs(anchor) => write!(f, "same-project-as:{}", anchor),
+        }
+    }
+}
+
+/// The pane `step` places after the focused one among its tab's panes
+/// of the same layer, in reading order
+fn step_from_focus(state: &State, step: isize) -> Vec<&PaneInfo> {
+    let Some(focused) = state.focused_pane() else {
+        return Vec::new();
+    };
+    let mut peers: Vec<&PaneInfo> = state
+        .panes()
+        .iter()
+        .filter(|pane| pane.is_floating == focused.is_floating && state.pane_tab(pane.id) == state.pane_tab(focused.id))
+        .collect();
+    if peers.len() < 2 {
+        return Vec::new();
+    }
+    peers.sort_by_key(|pane| (pane.pane_y, pane.pane_x, pane.id));
+    let at = peers.iter().position(|pane| pane.id == focused.id).unwrap_or(0) as isize;
+    let len = peers.len() as isize;
+    vec![peers[(at + step).rem_euclid(len) as usize]]
+}
+
+/// The nearest tiled panes of the anchor's tab on one side of it
+fn beside<'a>(state: &'a State, anchor: &PaneInfo, side: Side) -> Vec<&'a PaneInfo> {
+    let overlaps = |a: usize, a_len: usize, b: usize, b_len: usize| a < b + b_len && b < a + a_len;
+    // Distance from the anchor's edge, for panes on the requested side
+    let distance = |pane: &PaneInfo| match side {
+        Side::Right if overlaps(pane.pane_y, pane.pane_rows, anchor.pane_y, anchor.pane_rows) => {
+            pane.pane_x.checked_sub(anchor.pane_x + anchor.pane_columns)
+        }
+        Side::Left if overlaps(pane.pane_y, pane.pane_rows, anchor.pane_y, anchor.pane_rows) => {
+            anchor.pane_x.checked_sub(pane.pane_x + pane.pane_columns)
+        }
+        Side::Below if overlaps(pane.pane_x, pane.pane_columns, anchor.pane_x, anchor.pane_columns) => {
+            pane.pane_y.checked_sub(anchor.pane_y + anchor.pane_rows)
+        }
+        Side::Above if overlaps(pane.pane_x, pane.pane_columns, anchor.pane_x, anchor.pane_columns) => {
+            anchor.pane_y.checked_sub(pane.pane_y + pane.pane_rows)
+        }
+        _ => None,
+    };
+    let candidates: Vec<(usize, &PaneInfo)> = state
+        .panes()
+        .iter()
+        .filter(|pane| pane.id != anchor.id && !pane.is_floating && state.pane_tab(pane.id) == state.pane_tab(anchor.id))
+        .filter_map(|pane| Some((distance(pane)?, pane)))
+        .collect();
+    let nearest = candidates.iter().map(|(d, _)| *d).min();
+    candidates
+        .into_iter()
+        .filter(|(d, _)| Some(*d) == nearest)
+        .map(|(_, pane)| pane)
+        .collect()
+}
+
+impl<'de> Deserialize<'de> for Selector {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        #[derive(Deserialize)]
+        #[serde(untagged)]
+        enum Raw {
+            Id(u32),
+            Text(String),
+        }
+
+        Ok(match Raw::deserialize(deserializer)? {
+            Raw::Id(id) => Selector::Id(id),
+            Raw::Text(s) => Selector::parse(&s),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use zellij_tile::prelude::PaneManifest;
+
+    fn create_test_state() -> State {
+        let mut manifest = PaneManifest::default();
+        manifest.panes.insert(0, ["proj__cc_1", "proj__cc_2", "other__cod_1"]
+            .iter()
+            .enumerate()
+            .map(|(i, title)| PaneInfo {
+                id: i as u32 + 1,
+                title: title.to_string(),
+                ..Default::default()
+            })
+            .collect());
+        let mut state = State::default();
+        state.update_panes(manifest);
+        state
+    }
+
+    #[test]
+    fn test_parse_selector_forms() {
+        assert_eq!(Selector::parse("7"), Selector::Id(7));
+        assert_eq!(Selector::parse("id:7"), Selector::Id(7));
+        assert_eq!(Selector::parse("title:7"), Selector::Title("7".to_string()));
+        assert_eq!(Selector::parse("proj__*"), Selector::Prefix("proj__".to_string()));
+        assert_eq!(Selector::parse("proj__cc_1"), Selector::Title("proj__cc_1".to_string()));
+        assert_eq!(Selector::parse("focused"), Selector::Focused);
+        assert_eq!(Selector::parse("."), Selector::Focused);
+        assert_eq!(Selector::parse("title:focused"), Selector::Title("focused".to_string()));
+    }
+
+    #[test]
+    fn test_deserialize_number_or_string() {
+        let sel: Selector = serde_json::from_str("3").unwrap();
+        assert_eq!(sel, Selector::Id(3));
+        let sel: Selector = serde_json::from_str(r#""proj__*""#).unwrap();
+        assert_eq!(sel, Selector::Prefix("proj__".to_string()));
+    }
+
+    #[test]
+    fn test_resolve_prefix_matches_many() {
+        let state = create_test_state();
+        assert_eq!(Selector::parse("proj__*").resolve(&state).len(), 2);
+        assert!(Selector::parse("proj__*").resolve_one(&state).is_err());
+    }
+
+    #[test]
+    fn test_resolve_one() {
+        let state = create_test_state();
+        assert_eq!(Selector::parse("other__cod_1").resolve_one(&state).unwrap().id, 3);
+        assert_eq!(Selector::Id(2).resolve_one(&state).unwrap().title, "proj__cc_2");
+
+        let err = Selector::Id(99).resolve_one(&state).unwrap_err();
+        assert_eq!(err.code, ErrorCode::NotFound);
+        assert!(err.message.contains("pane not found"));
+    }
+
+    #[test]
+    fn test_resolve_focused() {
+        let state = create_test_state();
+        assert!(Selector::Focused.resolve_one(&state).unwrap_err().message.contains("pane not found: focused"));
+
+        let mut manifest = PaneManifest::default();
+        manifest.panes.insert(0, vec![
+            PaneInfo { id: 1, title: "a".to_string(), ..Default::default() },
+            PaneInfo { id: 2, title: "b".to_string(), is_focused: true, ..Default::default() },
+        ]);
+        let mut state = State::default();
+        state.update_panes(manifest);
+        assert_eq!(Selector::parse(".").resolve_one(&state).unwrap().id, 2);
+    }
+
+    fn grid_state() -> State {
+        // +-------------+---------+
+        // | 1 proj__cc_1| 2 editor|
+        // +------+------+---------+
+        // | 3    | 4 proj__cc_2   |
+        // +------+----------------+
+        let pane = |id: u32, title: &str, (x, y, columns, rows): (usize, usize, usize, usize)| PaneInfo {
+            id,
+            title: title.to_string(),
+            pane_x: x,
+            pane_y: y,
+            pane_columns: columns,
+            pane_rows: rows,
+            is_focused: id == 1,
+            ..Default::default()
+        };
+        let mut manifest = PaneManifest::default();
+        manifest.panes.insert(0, vec![
+            pane(1, "proj__cc_1", (0, 0, 60, 20)),
+            pane(2, "editor", (60, 0, 40, 20)),
+            pane(3, "shell", (0, 20, 30, 20)),
+            pane(4, "proj__cc_2", (30, 20, 70, 20)),
+        ]);
+        manifest.panes.insert(1, vec![pane(5, "proj__cod_1", (0, 0, 100, 40))]);
+        let mut state = State::default();
+        state.update_panes(manifest);
+        state
+    }
+
+    fn ids(selector: &str, state: &State) -> Vec<u32> {
+        let mut ids: Vec<u32> = Selector::parse(selector).resolve(state).iter().map(|p| p.id).collect();
+        ids.sort();
+        ids
+    }
+
+    #[test]
+    fn test_relational_selectors_by_geometry() {
+        let state = grid_state();
+
+        assert_eq!(ids("right-of:1", &state), vec![2]);
+        assert_eq!(ids("left-of:editor", &state), vec![1]);
+        assert_eq!(ids("below:1", &state), vec![3, 4]);
+        assert_eq!(ids("above:4", &state), vec![1, 2]);
+        assert_eq!(ids("right-of:3", &state), vec![4]);
+        assert!(ids("right-of:2", &state).is_empty());
+        assert!(ids("right-of:99", &state).is_empty());
+        assert_eq!(Selector::parse("below:focused").to_string(), "below:focused");
+    }
+
+    #[test]
+    fn test_next_prev_and_same_project() {
+        let state = grid_state();
+
+        assert_eq!(ids("next", &state), vec![2]);
+        assert_eq!(ids("prev", &state), vec![4]);
+        assert_eq!(ids("same-project-as:1", &state), vec![4, 5]);
+        assert!(ids("same-project-as:editor", &state).is_empty());
+    }
+}
@@ -0,0 +1,405 @@
+//! Command lines for panes opened by new_pane and spawn_agent
+//!
+//! A spawned command runs under `sh -c` with its environment exported
+//! first. Values may be given inline or as a secret reference, which the
+//! pane's shell resolves on the host, so the plaintext never passes
+//! through the plugin:
+//!
+//! ```json
+//! {"MODEL": "opus", "ANTHROPIC_API_KEY": {"secret_ref": "pass:work/anthropic"}}
+//! ```
+//!
+//! Working directories may be templates such as `~/src/{project}`. The
+//! directory is checked on the host before the pane opens, so an agent
+//! never starts somewhere else.
+//!
+//! A kind may also name a container, in which case the command runs there
+//! through `docker exec` or `devcontainer exec`.
+//!
+//! new_pane opens its pane with `zellij --session <name> action new-pane`,
+//! the one way to choose where a tiled pane goes. The session is the one
+//! the plugin runs in, as Zellij reported it in a mode update. The reply waits for the pane to appear,
+//! for at most [`SPAWN_TIMEOUT_SECS`].
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+use crate::config::Config;
+use crate::ipc::{Error, ErrorCode};
+use crate::naming::AgentName;
+use crate::secrets::SecretRef;
+
+/// Seconds new_pane waits for its pane before failing
+pub const SPAWN_TIMEOUT_SECS: u64 = 30;
+
+/// Value of an environment variable for a spawned command
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+#[serde(untagged)]
+pub enum EnvValue {
+    Plain(String),
+    Secret { secret_ref: String },
+}
+
+/// An environment variable ready to export, its secret reference parsed
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum EnvEntry {
+    Plain(String, String),
+    Secret(String, SecretRef),
+}
+
+/// Where new_pane puts its pane
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PaneDirection {
+    Right,
+    Down,
+    Floating,
+}
+
+/// Container an agent kind's command runs in, instead of the host shell
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "runtime", rename_all = "lowercase")]
+pub enum Container {
+    /// `docker exec` into a running container, named by a template such
+    /// as `{project}-dev`
+    Docker {
+        name: String,
+        /// Working directory inside the container, also a template
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        workdir: Option<String>,
+    },
+    /// `devcontainer exec` into the dev container of the pane's working
+    /// directory
+    Devcontainer,
+}
+
+/// Tool whose environment for the working directory is loaded before an
+/// agent starts, as an interactive shell there would have it
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EnvLoader {
+    Direnv,
+    Mise,
+    Asdf,
+}
+
+impl EnvLoader {
+    /// Shell line loading the environment; skipped when the tool is missing
+    fn script(self) -> &'static str {
+        match self {
+            EnvLoader::Direnv => "command -v direnv >/dev/null && eval \"$(direnv export sh)\"\n",
+            EnvLoader::Mise => "command -v mise >/dev/null && eval \"$(mise env -s bash)\"\n",
+            // asdf resolves versions through its shims
+            EnvLoader::Asdf => "[ -d \"${ASDF_DATA_DIR:-$HOME/.asdf}/shims\" ] && export PATH=\"${ASDF_DATA_DIR:-$HOME/.asdf}/shims:$PATH\"\n",
+        }
+    }
+}
+
+/// Command line running `command` in `container`
+///
+/// The environment is exported on the host first, so variables are passed
+/// through by name and secrets never appear on the command line.
+pub fn container_command(container: &Container, command: &str, env: &[EnvEntry], name: &AgentName) -> String {
+    let vars = env.iter().map(|entry| match entry {
+        EnvEntry::Plain(var, _) | EnvEntry::Secret(var, _) => var,
+    });
+    let mut line = match container {
+        Container::Docker { name: container, workdir } => {
+            let mut line = "docker exec -it".to_string();
+            for var in vars {
+                line.push_str(&format!(" -e {}", var));
+            }
+            if let Some(workdir) = workdir {
+                line.push_str(&format!(" -w {}", shell_quote(&render_template(workdir, name))));
+            }
+            line.push_str(&format!(" {}", shell_quote(&render_template(container, name))));
+            line
+        }
+        Container::Devcontainer => {
+            let mut line = "devcontainer exec --workspace-folder .".to_string();
+            for var in vars {
+                line.push_str(&format!(" --remote-env \"{var}=${var}\"", var = var));
+            }
+            line
+        }
+    };
+    line.push_str(&format!(" sh -c {}", shell_quote(command)));
+    line
+}
+
+/// Validate names and secret references of a requested environment
+pub fn parse_env(env: &BTreeMap<String, EnvValue>) -> Result<Vec<EnvEntry>, Error> {
+    env.iter()
+        .map(|(name, value)| {
+            let valid = name.chars().next().is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+                && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+            if !valid {
+                return Err(Error::new(
+                    ErrorCode::InvalidParams,
+                    format!("invalid params: bad environment variable name: {:?}", name),
+                ));
+            }
+            Ok(match value {
+                EnvValue::Plain(v) => EnvEntry::Plain(name.clone(), v.clone()),
+                EnvValue::Secret { secret_ref } => {
+                    let secret = SecretRef::parse(secret_ref).map_err(|e| {
+                        Error::new(ErrorCode::InvalidParams, format!("invalid params: env {}: {}", name, e))
+                    })?;
+                    EnvEntry::Secret(name.clone(), secret)
+                }
+            })
+        })
+        .collect()
+}
+
+/// Command line running `command` with the `loaders` environments loaded
+/// and `env` exported, in that order
+///
+/// A secret that cannot be resolved stops the pane before the command
+/// starts, rather than starting it without credentials.
+pub fn spawn_command(command: &str, loaders: &[EnvLoader], env: &[EnvEntry], config: &Config) -> Vec<String> {
+    let mut script = String::new();
+    for loader in loaders {
+        script.push_str(loader.script());
+    }
+    for entry in env {
+        match entry {
+            EnvEntry::Plain(name, value) => {
+                script.push_str(&format!("export {}={}\n", name, shell_quote(value)));
+            }
+            EnvEntry::Secret(name, secret) => {
+                let resolve: Vec<String> = secret.resolve_command(config).iter().map(|a| shell_quote(a)).collect();
+                // Only the variable is named; the reference could be a
+                // secret pasted by mistake
+                let message = shell_quote(&format!("nzm: cannot resolve the secret for {}", name));
+                script.push_str(&format!(
+                    "{name}=\"$({resolve})\" || {{ echo {message} >&2; exit 1; }}\nexport {name}\n",
+                    name = name,
+                    resolve = resolve.join(" "),
+                    message = message,
+                ));
+            }
+        }
+    }
+    script.push_str("exec ");
+    script.push_str(command);
+    vec!["sh".to_string(), "-c".to_string(), script]
+}
+
+/// Shell command line of a command and its arguments; the user's shell
+/// when no command is given
+pub fn command_line(command: Option<&str>, args: &[String]) -> Result<String, Error> {
+    let Some(command) = command else {
+        if !args.is_empty() {
+            return Err(Error::new(ErrorCode::InvalidParams, "invalid params: args given without command"));
+        }
+        return Ok("\"${SHELL:-sh}\"".to_string());
+    };
+    let mut line = command.to_string();
+    for arg in args {
+        line.push(' ');
+        line.push_str(&shell_quote(arg));
+    }
+    Ok(line)
+}
+
+/// Host command opening a pane that runs `command` (the arguments of a
+/// spawn_command) in `session`
+///
+/// `cwd` is checked and resolved like [`resolve_cwd_command`] does first.
+#[cfg(any(target_arch = "wasm32", test))]
+pub fn new_pane_command(
+    command: &[String],
+    session: &str,
+    cwd: Option<&str>,
+    direction: Option<PaneDirection>,
+    name: Option<&str>,
+) -> Vec<String> {
+    let mut script = String::new();
+    let mut open = vec![format!("exec zellij --session {} action new-pane", shell_quote(session))];
+    if let Some(cwd) = cwd {
+        script.push_str(&format!("d={}; {}; d=\"$(cd -- \"$d\" && pwd -P)\" || exit 1\n", shell_quote(cwd), CHECK_CWD));
+        open.push("--cwd \"$d\"".to_string());
+    }
+    match direction {
+        Some(PaneDirection::Right) => open.push("--direction right".to_string()),
+        Some(PaneDirection::Down) => open.push("--direction down".to_string()),
+        Some(PaneDirection::Floating) => open.push("--floating".to_string()),
+        None => {}
+    }
+    if let Some(name) = name {
+        open.push(format!("--name {}", shell_quote(name)));
+    }
+    open.push("--".to_string());
+    open.extend(command.iter().map(|a| shell_quote(a)));
+    script.push_str(&open.join(" "));
+    vec!["sh".to_string(), "-c".to_string(), script]
+}
+
+/// Fill a template's `{project}`, `{kind}` and `{index}` from an agent name
+pub fn render_template(template: &str, name: &AgentName) -> String {
+    template
+        .replace("{project}", name.project.as_deref().unwrap_or(""))
+        .replace("{kind}", &name.kind)
+        .replace("{index}", &name.index.map(|i| i.to_string()).unwrap_or_default())
+}
+
+/// Fill a working directory template
+///
+/// The result must be absolute or start with `~`, which the host expands.
+pub fn render_cwd(template: &str, name: &AgentName) -> Result<String, Error> {
+    let cwd = render_template(template, name);
+    check_cwd(&cwd)?;
+    Ok(cwd)
+}
+
+/// Check that a working directory is absolute or home-relative
+pub fn check_cwd(cwd: &str) -> Result<(), Error> {
+    if !(cwd.starts_with('/') || cwd == "~" || cwd.starts_with("~/")) {
+        return Err(Error::new(
+            ErrorCode::InvalidParams,
+            format!("invalid params: cwd must be absolute or start with ~/: {:?}", cwd),
+        ));
+    }
+    if cwd.split('/').any(|part| part == "..") {
+        return Err(Error::new(ErrorCode::InvalidParams, format!("invalid params: cwd may not contain '..': {}", cwd)));
+    }
+    Ok(())
+}
+
+/// Shell snippet expanding a leading `~` of `$d` and failing unless it is
+/// an existing directory
+#[cfg(any(target_arch = "wasm32", test))]
+const CHECK_CWD: &str = "case \"$d\" in \"~\"|\"~/\"*) d=\"$HOME${d#\\~}\";; esac; \
+     [ -d \"$d\" ] || { echo \"no such directory: $d\" >&2; exit 1; }";
+
+/// Command line printing the physical path of `cwd`, expanding a leading
+/// `~`; fails when it is not an existing directory
+#[cfg(target_arch = "wasm32")]
+pub fn resolve_cwd_command(cwd: &str) -> Vec<String> {
+    vec![
+        "sh".to_string(),
+        "-c".to_string(),
+        format!("d=\"$1\"; {}; cd -- \"$d\" && pwd -P", CHECK_CWD),
+        "sh".to_string(),
+        cwd.to_string(),
+    ]
+}
+
+/// Quote a string as a single shell word
+fn shell_quote(s: &str) -> String {
+    format!("'{}'", s.replace('\'', "'\\''"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_env() {
+        let env: BTreeMap<String, EnvValue> = serde_json::from_str(
+            r#"{"MODEL": "opus", "API_KEY": {"secret_ref": "pass:work/anthropic"}}"#,
+        )
+        .unwrap();
+        let entries = parse_env(&env).unwrap();
+
+        assert_eq!(entries[0], EnvEntry::Secret("API_KEY".to_string(), SecretRef::Pass("work/anthropic".to_string())));
+        assert_eq!(entries[1], EnvEntry::Plain("MODEL".to_string(), "opus".to_string()));
+    }
+
+    #[test]
+    fn test_parse_env_rejects_bad_names_and_refs() {
+        let bad_name: BTreeMap<String, EnvValue> = serde_json::from_str(r#"{"A;B": "x"}"#).unwrap();
+        assert!(parse_env(&bad_name).unwrap_err().message.contains("bad environment variable name"));
+
+        let bad_ref: BTreeMap<String, EnvValue> = serde_json::from_str(r#"{"K": {"secret_ref": "vault:x"}}"#).unwrap();
+        assert!(parse_env(&bad_ref).unwrap_err().message.contains("unknown secret provider"));
+    }
+
+    #[test]
+    fn test_render_cwd() {
+        let name = AgentName {
+            project: Some("api".to_string()),
+            kind: "cc".to_string(),
+            index: Some(2),
+            preset: None,
+        };
+
+        assert_eq!(render_cwd("~/src/{project}", &name).unwrap(), "~/src/api");
+        assert_eq!(render_cwd("/work/{project}/{kind}-{index}", &name).unwrap(), "/work/api/cc-2");
+        assert!(render_cwd("src/{project}", &name).unwrap_err().message.contains("must be absolute"));
+        assert!(render_cwd("/work/{project}/../..", &name).is_err());
+    }
+
+    #[test]
+    fn test_spawn_command_exports_env() {
+        let env = vec![
+            EnvEntry::Plain("NOTE".to_string(), "it's".to_string()),
+            EnvEntry::Secret("KEY".to_string(), SecretRef::Env("HOST_KEY".to_string())),
+        ];
+        let args = spawn_command("claude --model opus", &[], &env, &Config::default());
+
+        assert_eq!(args[..2], ["sh", "-c"]);
+        assert!(args[2].starts_with("export NOTE='it'\\''s'\n"));
+        assert!(args[2].contains("KEY=\"$('sh' '-c' 'printenv \"$1\"' 'sh' 'HOST_KEY')\" || {"));
+        assert!(args[2].ends_with("export KEY\nexec claude --model opus"));
+        assert!(args[2].contains("echo 'nzm: cannot resolve the secret for KEY' >&2"));
+    }
+
+    #[test]
+    fn test_command_line() {
+        let args = vec!["-n".to_string(), "it's".to_string()];
+
+        assert_eq!(command_line(Some("echo"), &args).unwrap(), "echo '-n' 'it'\\''s'");
+        assert_eq!(command_line(None, &[]).unwrap(), "\"${SHELL:-sh}\"");
+        assert!(command_line(None, &args).unwrap_err().message.contains("without command"));
+    }
+
+    #[test]
+    fn test_new_pane_command() {
+        let command = spawn_command("htop", &[], &[], &Config::default());
+        let args = new_pane_command(&command, "main", Some("~/src"), Some(PaneDirection::Right), Some("top"));
+
+        assert_eq!(args[..2], ["sh", "-c"]);
+        assert!(args[2].starts_with("d='~/src'; case"));
+        assert!(args[2].ends_with(
+            "exec zellij --session 'main' action new-pane --cwd \"$d\" --direction right --name 'top' -- 'sh' '-c' 'exec htop'"
+        ));
+
+        let args = new_pane_command(&command, "main", None, Some(PaneDirection::Floating), None);
+        assert_eq!(args[2], "exec zellij --session 'main' action new-pane --floating -- 'sh' '-c' 'exec htop'");
+    }
+
+    #[test]
+    fn test_container_command() {
+        let name = AgentName {
+            project: Some("api".to_string()),
+            kind: "cc".to_string(),
+            index: Some(1),
+            preset: None,
+        };
+        let env = vec![
+            EnvEntry::Plain("MODEL".to_string(), "opus".to_string()),
+            EnvEntry::Secret("KEY".to_string(), SecretRef::Env("HOST_KEY".to_string())),
+        ];
+        let docker = Container::Docker { name: "{project}-dev".to_string(), workdir: Some("/work/{project}".to_string()) };
+        assert_eq!(
+            container_command(&docker, "echo it's", &env, &name),
+            "docker exec -it -e MODEL -e KEY -w '/work/api' 'api-dev' sh -c 'echo it'\\''s'"
+        );
+        assert_eq!(
+            container_command(&Container::Devcontainer, "claude", &env, &name),
+            "devcontainer exec --workspace-folder . --remote-env \"MODEL=$MODEL\" --remote-env \"KEY=$KEY\" sh -c 'claude'"
+        );
+    }
+
+    #[test]
+    fn test_spawn_command_loads_env_first() {
+        let env = vec![EnvEntry::Plain("MODEL".to_string(), "opus".to_string())];
+        let args = spawn_command("claude", &[EnvLoader::Direnv, EnvLoader::Asdf], &env, &Config::default());
+
+        assert!(args[2].starts_with("command -v direnv >/dev/null && eval \"$(direnv export sh)\"\n[ -d "));
+        assert!(args[2].ends_with("/shims:$PATH\"\nexport MODEL='opus'\nexec claude"));
+    }
+}
@@ -0,0 +1,437 @@
+//! Durable queue of prompts to deliver to agent panes
+//!
+//! Every state transition is written to `/data/tasks.json` before the
+//! caller acts on it. A task is marked dispatched *before* its prompt is
+//! written, so a plugin reload never sends it twice; a completed result is
+//! on disk before the completion is acknowledged.
+//!
+//! A task also records the handle of its target pane. Zellij reuses pane
+//! ids, so at dispatch the handle tells whether the pane with the task's
+//! id is still the one the prompt was queued for; when it is not, the
+//! configured [`StaleTaskPolicy`] fails the task or re-resolves the
+//! selector it was queued with.
+//!
+//! A journal that exists but cannot be parsed is left untouched: the store
+//! refuses every change and reports the problem, rather than starting
+//! empty and overwriting the queue on its next write.
+//!
+//! Completed and failed tasks stay readable until more than `task_history`
+//! of them pile up; the oldest then leave the journal when the next task
+//! is queued.
+
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+use crate::config::DEFAULT_TASK_HISTORY;
+use crate::ipc::{Error, ErrorCode};
+
+/// Default location of the task journal inside the plugin sandbox
+pub const DEFAULT_TASKS_PATH: &str = "/data/tasks.json";
+
+/// Lifecycle of a task
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TaskStatus {
+    Pending,
+    Dispatched,
+    Completed,
+    Failed,
+}
+
+/// What to do with a task whose target pane is gone or was replaced
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum StaleTaskPolicy {
+    /// Mark the task failed
+    #[default]
+    Fail,
+    /// Send to whichever single pane the task's selector now matches,
+    /// failing when it matches none or several
+    Reroute,
+}
+
+impl StaleTaskPolicy {
+    /// Parse the `stale_task_policy` config value
+    pub fn parse(name: &str) -> Option<StaleTaskPolicy> {
+        match name {
+            "fail" => Some(StaleTaskPolicy::Fail),
+            "reroute" => Some(StaleTaskPolicy::Reroute),
+            _ => None,
+        }
+    }
+}
+
+/// The pane a task is queued for
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TaskTarget {
+    pub pane_id: u32,
+    /// Handle the pane held at enqueue time
+    pub handle: Option<String>,
+    /// Selector the pane was chosen with
+    pub selector: Option<String>,
+}
+
+impl From<u32> for TaskTarget {
+    fn from(pane_id: u32) -> Self {
+        TaskTarget { pane_id, ..TaskTarget::default() }
+    }
+}
+
+/// A prompt queued for a pane
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Task {
+    pub id: String,
+    pub pane_id: u32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub handle: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub selector: Option<String>,
+    pub prompt: String,
+    pub enter: bool,
+    pub status: TaskStatus,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub result: Option<String>,
+}
+
+/// Task queue persisted as a JSON journal on the host
+pub struct TaskStore {
+    path: PathBuf,
+    tasks: Vec<Task>,
+    next_id: u64,
+    loaded: bool,
+    /// Why the journal on disk could not be read
+    unreadable: Option<String>,
+    /// Finished tasks kept
+    history: usize,
+}
+
+impl Default for TaskStore {
+    fn default() -> Self {
+        TaskStore::new(DEFAULT_TASKS_PATH)
+    }
+}
+
+impl TaskStore {
+    /// Create a store backed by `path` (read lazily, written on every change)
+    pub fn new(path: impl Into<PathBuf>) -> Self {
+        TaskStore {
+            path: path.into(),
+            tasks: Vec::new(),
+            next_id: 1,
+            loaded: false,
+            unreadable: None,
+            history: DEFAULT_TASK_HISTORY,
+        }
+    }
+
+    /// Set how many completed or failed tasks are kept
+    pub fn set_history(&mut self, history: usize) {
+        self.history = history;
+    }
+
+    /// Queue a prompt for a pane
+    pub fn enqueue(&mut self, target: TaskTarget, prompt: &str, enter: bool) -> io::Result<Task> {
+        self.load();
+        self.check().map_err(io::Error::other)?;
+        let task = Task {
+            id: format!("t{}", self.next_id),
+            pane_id: target.pane_id,
+            handle: target.handle,
+            selector: target.selector,
+            prompt: prompt.to_string(),
+            enter,
+            status: TaskStatus::Pending,
+            result: None,
+        };
+        self.next_id += 1;
+        self.prune();
+        self.tasks.push(task.clone());
+        self.save()?;
+        Ok(task)
+    }
+
+    /// Get a task by id
+    pub fn get(&mut self, id: &str) -> Option<&Task> {
+        self.load();
+        self.tasks.iter().find(|t| t.id == id)
+    }
+
+    /// Oldest pending task, optionally limited to one pane
+    pub fn next_pending(&mut self, pane_id: Option<u32>) -> Option<&Task> {
+        self.load();
+        self.tasks
+            .iter()
+            .find(|t| t.status == TaskStatus::Pending && pane_id.is_none_or(|id| t.pane_id == id))
+    }
+
+    /// Number of tasks waiting to be dispatched
+    pub fn pending_count(&mut self) -> usize {
+        self.load();
+        self.tasks.iter().filter(|t| t.status == TaskStatus::Pending).count()
+    }
+
+    /// Move a task from `from` to `to`, persisting before returning
+    ///
+    /// Fails without changing anything if the task is not in `from`.
+    pub fn transition(&mut self, id: &str, from: TaskStatus, to: TaskStatus, result: Option<String>) -> Result<Task, Error> {
+        self.load();
+        self.check().map_err(|e| format!("task store failed: {}", e))?;
+        let task = self
+            .tasks
+            .iter_mut()
+            .find(|t| t.id == id)
+            .ok_or_else(|| Error::new(ErrorCode::NotFound, format!("task not found: {}", id)))?;
+        if task.status != from {
+            return Err(format!("task {} is {:?}, not {:?}", id, task.status, from).to_lowercase().into());
+        }
+        let previous = task.clone();
+        task.status = to;
+        if result.is_some() {
+            task.result = result;
+        }
+        let updated = task.clone();
+
+        if let Err(e) = self.save() {
+            // Keep memory consistent with what is on disk
+            if let Some(task) = self.tasks.iter_mut().find(|t| t.id == id) {
+                *task = previous;
+            }
+            return Err(format!("task store failed: {}", e).into());
+        }
+        Ok(updated)
+    }
+
+    /// Point a pending task at another pane, persisting before returning
+    pub fn retarget(&mut self, id: &str, target: TaskTarget) -> Result<Task, String> {
+        self.load();
+        self.check().map_err(|e| format!("task store failed: {}", e))?;
+        let task = self
+            .tasks
+            .iter_mut()
+            .find(|t| t.id == id && t.status == TaskStatus::Pending)
+            .ok_or_else(|| format!("no pending task {}", id))?;
+        let previous = task.clone();
+        task.pane_id = target.pane_id;
+        task.handle = target.handle;
+        task.selector = target.selector;
+        let updated = task.clone();
+
+        if let Err(e) = self.save() {
+            if let Some(task) = self.tasks.iter_mut().find(|t| t.id == id) {
+                *task = previous;
+            }
+            return Err(format!("task store failed: {}", e));
+        }
+        Ok(updated)
+    }
+
+    /// Fail pending tasks whose pane no longer exists
+    ///
+    /// Run after a reload once the pane list is known, with the handle of
+    /// each open pane. Handles do not survive a reload, so pending tasks
+    /// whose pane still exists take its new handle. Dispatched tasks are
+    /// left alone: their prompt may already have been delivered.
+    pub fn recover(&mut self, handle_of: impl Fn(u32) -> Option<String>) -> io::Result<Vec<String>> {
+        self.load();
+        self.check().map_err(io::Error::other)?;
+        let mut failed = Vec::new();
+        let mut changed = false;
+        for task in &mut self.tasks {
+            if task.status != TaskStatus::Pending {
+                continue;
+            }
+            match handle_of(task.pane_id) {
+                None => {
+                    task.status = TaskStatus::Failed;
+                    task.result = Some(format!("target pane {} no longer exists", task.pane_id));
+                    failed.push(task.id.clone());
+                    changed = true;
+                }
+                Some(handle) if task.handle.as_ref().is_some_and(|h| *h != handle) => {
+                    task.handle = Some(handle);
+                    changed = true;
+                }
+                Some(_) => {}
+            }
+        }
+        if changed {
+            self.save()?;
+        }
+        Ok(failed)
+    }
+
+    /// All tasks in queue order
+    pub fn list(&mut self) -> &[Task] {
+        self.load();
+        &self.tasks
+    }
+
+    /// Drop the oldest finished tasks beyond the history limit
+    fn prune(&mut self) {
+        let finished = |t: &Task| matches!(t.status, TaskStatus::Completed | TaskStatus::Failed);
+        let mut excess = self.tasks.iter().filter(|t| finished(t)).count().saturating_sub(self.history);
+        self.tasks.retain(|t| {
+            let drop = excess > 0 && finished(t);
+            excess -= usize::from(drop);
+            !drop
+        });
+    }
+
+    /// Why the journal could not be read, if it could not
+    pub fn load_error(&mut self) -> Option<&str> {
+        self.load();
+        self.unreadable.as_deref()
+    }
+
+    /// Fail while the journal on disk is unreadable
+    fn check(&self) -> Result<(), String> {
+        match &self.unreadable {
+            Some(e) => Err(e.clone()),
+            None => Ok(()),
+        }
+    }
+
+    fn load(&mut self) {
+        if self.loaded {
+            return;
+        }
+        self.loaded = true;
+        match fs::read_to_string(&self.path) {
+            Ok(json) => match serde_json::from_str(&json) {
+                Ok(tasks) => self.tasks = tasks,
+                Err(e) => {
+                    self.unreadable = Some(format!(
+                        "task journal {} is unreadable ({}); fix or remove it to use the queue",
+                        self.path.display(),
+                        e
+                    ));
+                }
+            },
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => self.unreadable = Some(format!("task journal {} cannot be read: {}", self.path.display(), e)),
+        }
+        self.next_id = self
+            .tasks
+            .iter()
+            .filter_map(|t| t.id.strip_prefix('t')?.parse::<u64>().ok())
+            .max()
+            .map_or(1, |n| n + 1);
+    }
+
+    /// Write the journal atomically: a torn write must not lose the queue
+    fn save(&self) -> io::Result<()> {
+        if let Some(dir) = self.path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let json = serde_json::to_string(&self.tasks).map_err(io::Error::other)?;
+        let tmp = self.path.with_extension("json.tmp");
+        fs::write(&tmp, json)?;
+        fs::rename(tmp, &self.path)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn temp_store(name: &str) -> TaskStore {
+        let path = std::env::temp_dir()
+            .join(format!("nzm-tasks-{}-{}", name, std::process::id()))
+            .join("tasks.json");
+        let _ = fs::remove_file(&path);
+        TaskStore::new(path)
+    }
+
+    #[test]
+    fn test_enqueue_and_dispatch_exactly_once() {
+        let mut store = temp_store("once");
+        let task = store.enqueue(1.into(), "hello", true).unwrap();
+        assert_eq!(task.id, "t1");
+
+        store.transition("t1", TaskStatus::Pending, TaskStatus::Dispatched, None).unwrap();
+        let err = store.transition("t1", TaskStatus::Pending, TaskStatus::Dispatched, None).unwrap_err();
+        assert!(err.message.contains("dispatched"));
+    }
+
+    #[test]
+    fn test_transitions_survive_reload() {
+        let mut store = temp_store("reload");
+        store.enqueue(1.into(), "a", true).unwrap();
+        store.enqueue(2.into(), "b", false).unwrap();
+        store.transition("t1", TaskStatus::Pending, TaskStatus::Dispatched, None).unwrap();
+        store.transition("t1", TaskStatus::Dispatched, TaskStatus::Completed, Some("done".to_string())).unwrap();
+
+        let mut reopened = TaskStore::new(store.path.clone());
+        assert_eq!(reopened.get("t1").unwrap().status, TaskStatus::Completed);
+        assert_eq!(reopened.get("t1").unwrap().result.as_deref(), Some("done"));
+        assert_eq!(reopened.next_pending(None).unwrap().id, "t2");
+        assert_eq!(reopened.enqueue(1.into(), "c", true).unwrap().id, "t3");
+    }
+
+    #[test]
+    fn test_recover_fails_pending_tasks_for_missing_panes() {
+        let mut store = temp_store("recover");
+        store.enqueue(1.into(), "a", true).unwrap();
+        store.enqueue(2.into(), "b", true).unwrap();
+        store.enqueue(2.into(), "c", true).unwrap();
+        store.transition("t3", TaskStatus::Pending, TaskStatus::Dispatched, None).unwrap();
+
+        let failed = store.recover(|id| (id == 1).then(|| "h1".to_string())).unwrap();
+
+        assert_eq!(failed, vec!["t2"]);
+        assert_eq!(store.get("t3").unwrap().status, TaskStatus::Dispatched);
+    }
+
+    #[test]
+    fn test_recover_rebinds_handles_and_retarget_persists() {
+        let mut store = temp_store("rebind");
+        let target = TaskTarget {
+            pane_id: 1,
+            handle: Some("old".to_string()),
+            selector: Some("title:proj__cc_1".to_string()),
+        };
+        store.enqueue(target, "a", true).unwrap();
+
+        store.recover(|_| Some("new".to_string())).unwrap();
+        assert_eq!(store.get("t1").unwrap().handle.as_deref(), Some("new"));
+
+        store.retarget("t1", TaskTarget { pane_id: 5, handle: Some("h5".to_string()), selector: None }).unwrap();
+        let mut reopened = TaskStore::new(store.path.clone());
+        let task = reopened.get("t1").unwrap();
+        assert_eq!((task.pane_id, task.handle.as_deref(), task.selector.as_deref()), (5, Some("h5"), None));
+        assert!(reopened.retarget("t9", TaskTarget::from(1)).is_err());
+    }
+
+    #[test]
+    fn test_enqueue_prunes_oldest_finished_tasks() {
+        let mut store = temp_store("prune");
+        store.set_history(1);
+        for prompt in ["a", "b", "c"] {
+            store.enqueue(1.into(), prompt, true).unwrap();
+        }
+        store.transition("t1", TaskStatus::Pending, TaskStatus::Failed, None).unwrap();
+        store.transition("t3", TaskStatus::Pending, TaskStatus::Dispatched, None).unwrap();
+        store.transition("t3", TaskStatus::Dispatched, TaskStatus::Completed, None).unwrap();
+
+        store.enqueue(1.into(), "d", true).unwrap();
+        let ids: Vec<_> = store.list().iter().map(|t| t.id.as_str()).collect();
+        assert_eq!(ids, ["t2", "t3", "t4"]);
+        // Ids are not reused after a reload
+        let mut reopened = TaskStore::new(store.path.clone());
+        assert_eq!(reopened.enqueue(1.into(), "e", true).unwrap().id, "t5");
+    }
+
+    #[test]
+    fn test_corrupt_journal_is_reported_not_overwritten() {
+        let store = temp_store("corrupt");
+        fs::create_dir_all(store.path.parent().unwrap()).unwrap();
+        fs::write(&store.path, "[{\"id\": \"t1\"").unwrap();
+
+        let mut store = TaskStore::new(store.path.clone());
+        assert!(store.load_error().unwrap().contains("is unreadable"));
+        assert!(store.enqueue(1.into(), "a", true).is_err());
+        assert!(store.transition("t1", TaskStatus::Pending, TaskStatus::Failed, None).is_err());
+        assert_eq!(fs::read_to_string(&store.path).unwrap(), "[{\"id\": \"t1\"");
+    }
+}
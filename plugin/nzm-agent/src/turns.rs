@@ -0,0 +1,315 @@
+//! Conversation turns of agent panes
+//!
+//! A turn starts when a prompt is sent to an agent pane with Enter and
+//! collects the output that follows it. The turn is complete once the
+//! kind's status patterns see the agent idle in that output, or when the
+//! next prompt goes to the same pane. Turns outlive their pane, so the
+//! dialogue of a closed agent can still be read, and the oldest are
+//! dropped past the `turn_history` limit.
+//!
+//! `export_conversation` renders turns as markdown, with each reply in a
+//! code block since it is terminal output, or as a chat messages array of
+//! `user` and `assistant` roles.
+//!
+//! Output is located by absolute line offsets into the pane's output, as
+//! checkpoints are, so lines trimmed by the output memory budget do not
+//! move a response. Once its start is trimmed, a turn keeps the part it
+//! already captured and appends what follows. Old scrollback dropped by
+//! Zellij itself still shifts the start of a response.
+
+use std::collections::{HashMap, VecDeque};
+use std::mem::size_of;
+use serde::{Deserialize, Serialize};
+
+use crate::clock::{self, now_ms};
+
+/// Whether a turn's response is still being captured
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TurnStatus {
+    Open,
+    Complete,
+}
+
+/// A prompt sent to an agent and the output it produced
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Turn {
+    pub id: String,
+    pub pane_id: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub handle: Option<String>,
+    /// Pane title when the prompt was sent
+    pub title: String,
+    /// Agent kind and project of the pane
+    pub kind: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub project: Option<String>,
+    pub prompt: String,
+    /// Output lines after the prompt, trailing blank lines dropped
+    pub response: Vec<String>,
+    pub status: TurnStatus,
+    /// Task the prompt was dispatched for
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_id: Option<String>,
+    /// Milliseconds since the Unix epoch, sent as RFC 3339 text
+    #[serde(rename = "sent_at", serialize_with = "clock::serialize_ms")]
+    pub sent_at_ms: u64,
+    #[serde(rename = "completed_at", serialize_with = "clock::serialize_opt_ms", skip_serializing_if = "Option::is_none")]
+    pub completed_at_ms: Option<u64>,
+}
+
+impl Turn {
+    /// The response without the echo of the prompt that starts it
+    pub fn reply_lines(&self) -> &[String] {
+        self.response.get(1..).unwrap_or(&[])
+    }
+
+    /// Estimated heap bytes of the turn
+    pub fn approx_bytes(&self) -> usize {
+        size_of::<Turn>()
+            + self.title.len()
+            + self.prompt.len()
+            + self.response.iter().map(|line| line.len() + size_of::<String>()).sum::<usize>()
+    }
+}
+
+/// Recent turns of every agent pane, oldest first
+#[derive(Debug, Default)]
+pub struct TurnLog {
+    turns: VecDeque<Turn>,
+    next_id: u64,
+    /// Open turn of each pane and the absolute line offset its response
+    /// starts at
+    open: HashMap<u32, (String, usize)>,
+}
+
+impl TurnLog {
+    /// Start a turn for a prompt, completing the pane's open one
+    ///
+    /// `lines` is the pane's captured output when the prompt was sent, and
+    /// `first_line` the absolute offset of its first line. Returns the new
+    /// turn's id.
+    pub fn begin(&mut self, mut turn: Turn, lines: &[String], first_line: usize, cap: usize) -> String {
+        self.complete(turn.pane_id);
+        self.next_id += 1;
+        turn.id = format!("u{}", self.next_id);
+        turn.sent_at_ms = now_ms();
+        // The prompt is echoed on the last line with text, the cursor's;
+        // the blank rows below it fill with the response
+        let offset = first_line + content_end(lines).saturating_sub(1);
+        self.open.insert(turn.pane_id, (turn.id.clone(), offset));
+        let id = turn.id.clone();
+        self.turns.push_back(turn);
+        self.evict(cap);
+        id
+    }
+
+    /// Capture new output of a pane's open turn; `idle` completes it
+    ///
+    /// `first_line` is the absolute offset of the first of `lines`.
+    pub fn update(&mut self, pane_id: u32, lines: &[String], first_line: usize, idle: impl Fn(&[String]) -> bool) {
+        let Some((id, offset)) = self.open.get(&pane_id) else {
+            return;
+        };
+        let Some(turn) = self.turns.iter_mut().find(|t| &t.id == id) else {
+            return;
+        };
+        let start = offset.saturating_sub(first_line).min(lines.len());
+        let mut response = lines[start..content_end(lines).max(start)].to_vec();
+        // Lines of the response trimmed since the last update are only in
+        // what the turn already holds
+        let trimmed = first_line.saturating_sub(*offset);
+        if trimmed > 0 {
+            turn.response.truncate(trimmed);
+            turn.response.append(&mut response);
+        } else {
+            turn.response = response;
+        }
+        // The prompt's echo comes first; idle output after it ends the turn
+        if turn.response.len() > 1 && idle(&turn.response[1..]) {
+            self.complete(pane_id);
+        }
+    }
+
+    /// Complete a pane's open turn, as when the pane closes
+    pub fn complete(&mut self, pane_id: u32) {
+        let Some((id, _)) = self.open.remove(&pane_id) else {
+            return;
+        };
+        if let Some(turn) = self.turns.iter_mut().find(|t| t.id == id) {
+            turn.status = TurnStatus::Complete;
+            turn.completed_at_ms = Some(now_ms());
+        }
+    }
+
+    /// All kept turns, oldest first
+    pub fn turns(&self) -> impl Iterator<Item = &Turn> {
+        self.turns.iter()
+    }
+
+    /// Look up a turn by id
+    pub fn get(&self, id: &str) -> Option<&Turn> {
+        self.turns.iter().find(|t| t.id == id)
+    }
+
+    /// Estimated heap bytes of the kept turns
+    pub fn approx_bytes(&self) -> usize {
+        self.turns.iter().map(Turn::approx_bytes).sum()
+    }
+
+    fn evict(&mut self, cap: usize) {
+        while self.turns.len() > cap.max(1) {
+            if let Some(turn) = self.turns.pop_front() {
+                if self.open.get(&turn.pane_id).is_some_and(|(id, _)| *id == turn.id) {
+                    self.open.remove(&turn.pane_id);
+                }
+            }
+        }
+    }
+}
+
+/// Format of an exported conversation
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ConversationFormat {
+    #[default]
+    Markdown,
+    Messages,
+}
+
+/// Render turns as markdown, a heading whenever the pane title changes
+///
+/// `reply` gives the text of each turn's response.
+pub fn to_markdown(turns: &[&Turn], reply: impl Fn(&Turn) -> String) -> String {
+    let mut out = String::new();
+    let mut title = None;
+    for turn in turns {
+        if title != Some(&turn.title) {
+            title = Some(&turn.title);
+            out.push_str(&format!("# {}\n\n", turn.title));
+        }
+        out.push_str(&format!("## User\n\n{}\n\n", turn.prompt));
+        let reply = reply(turn);
+        if !reply.is_empty() {
+            out.push_str(&format!("## Assistant\n\n{}\n\n", code_block(&reply)));
+        }
+    }
+    out.truncate(out.trim_end().len());
+    out.push('\n');
+    out
+}
+
+/// Render turns as chat messages; turns without output have no reply
+pub fn to_messages(turns: &[&Turn], reply: impl Fn(&Turn) -> String) -> Vec<serde_json::Value> {
+    let mut messages = Vec::new();
+    for turn in turns {
+        messages.push(serde_json::json!({ "role": "user", "content": turn.prompt }));
+        let reply = reply(turn);
+        if !reply.is_empty() {
+            messages.push(serde_json::json!({ "role": "assistant", "content": reply }));
+        }
+    }
+    messages
+}
+
+/// Put terminal output in a markdown code block, fenced past any backticks
+pub fn code_block(text: &str) -> String {
+    let fence = "`".repeat(longest_backtick_run(text).max(2) + 1);
+    format!("{}text\n{}\n{}", fence, text, fence)
+}
+
+/// Length of the longest run of backticks, so a fence can enclose them
+fn longest_backtick_run(text: &str) -> usize {
+    text.split(|c| c != '`').map(str::len).max().unwrap_or(0)
+}
+
+/// Index just past the last non-blank line
+fn content_end(lines: &[String]) -> usize {
+    lines.iter().rposition(|line| !line.trim().is_empty()).map_or(0, |i| i + 1)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn turn(pane_id: u32, prompt: &str) -> Turn {
+        Turn {
+            id: String::new(),
+            pane_id,
+            handle: None,
+            title: "proj__cc_1".to_string(),
+            kind: "cc".to_string(),
+            project: None,
+            prompt: prompt.to_string(),
+            response: Vec::new(),
+            status: TurnStatus::Open,
+            task_id: None,
+            sent_at_ms: 0,
+            completed_at_ms: None,
+        }
+    }
+
+    fn lines(text: &[&str]) -> Vec<String> {
+        text.iter().map(|s| s.to_string()).collect()
+    }
+
+    #[test]
+    fn test_turn_captures_output_until_idle() {
+        let mut log = TurnLog::default();
+        let idle = |lines: &[String]| lines.iter().any(|l| l == "> ");
+        let id = log.begin(turn(1, "run tests"), &lines(&["old", "> ", ""]), 0, 10);
+
+        log.update(1, &lines(&["old", "> run tests", "running", ""]), 0, idle);
+        assert_eq!(log.get(&id).unwrap().response, ["> run tests", "running"]);
+        assert_eq!(log.get(&id).unwrap().status, TurnStatus::Open);
+
+        log.update(1, &lines(&["old", "> run tests", "running", "ok", "> "]), 0, idle);
+        let done = log.get(&id).unwrap();
+        assert_eq!((done.status, done.response.len()), (TurnStatus::Complete, 4));
+        assert!(done.completed_at_ms.is_some());
+    }
+
+    #[test]
+    fn test_turn_keeps_its_place_when_output_is_trimmed() {
+        let mut log = TurnLog::default();
+        let idle = |lines: &[String]| lines.iter().any(|l| l == "> ");
+        let id = log.begin(turn(1, "go"), &lines(&["old", "> "]), 0, 10);
+
+        log.update(1, &lines(&["old", "> go", "one"]), 0, idle);
+        // Two lines trimmed from the front: "old" and the prompt echo
+        log.update(1, &lines(&["one", "two"]), 2, idle);
+        assert_eq!(log.get(&id).unwrap().response, ["> go", "one", "two"]);
+        log.update(1, &lines(&["two", "> "]), 3, idle);
+        let done = log.get(&id).unwrap();
+        assert_eq!((done.status, done.response.len()), (TurnStatus::Complete, 4));
+    }
+
+    #[test]
+    fn test_next_prompt_completes_turn_and_cap_evicts() {
+        let mut log = TurnLog::default();
+        let first = log.begin(turn(1, "a"), &[], 0, 2);
+        log.begin(turn(1, "b"), &[], 0, 2);
+        assert_eq!(log.get(&first).unwrap().status, TurnStatus::Complete);
+
+        let third = log.begin(turn(2, "c"), &[], 0, 2);
+        assert!(log.get(&first).is_none());
+        assert_eq!(log.turns().count(), 2);
+        assert_eq!(third, "u3");
+    }
+
+    #[test]
+    fn test_export_formats() {
+        let mut a = turn(1, "run tests");
+        a.response = lines(&["> run tests", "```", "ok"]);
+        let b = turn(1, "stop");
+        let reply = |t: &Turn| t.reply_lines().join("\n");
+
+        assert_eq!(
+            to_markdown(&[&a, &b], reply),
+            "# proj__cc_1\n\n## User\n\nrun tests\n\n## Assistant\n\n````text\n```\nok\n````\n\n## User\n\nstop\n"
+        );
+        let messages = to_messages(&[&a, &b], reply);
+        assert_eq!(messages.len(), 3);
+        assert_eq!((messages[1]["role"].as_str(), messages[1]["content"].as_str()), (Some("assistant"), Some("```\nok")));
+    }
+}
@@ -0,0 +1,642 @@
+//! Per-pane ordering of writes made by the effect executor
+//!
+//! Writes to a pane are released in the order requests arrived, except that
+//! a higher priority write goes ahead of lower priority ones still queued.
+//! A write whose bytes are not known yet (a secret being resolved on the
+//! host) reserves its place, and later writes of the same or lower priority
+//! wait behind it instead of landing mid-prompt.
+//!
+//! A paced write is released one chunk per `tick`. Once its first chunk is
+//! out nothing goes ahead of it, since bytes landing between chunks would
+//! end up inside its paste. Only an urgent write goes ahead of a paced
+//! write still queued, so an interrupt lands between jobs. Paced writes are
+//! jobs that can be paused, resumed, or aborted; while one is paused,
+//! writes queued behind it wait too.
+
+use serde::{Deserialize, Serialize};
+#[cfg(any(target_arch = "wasm32", test))]
+use std::collections::{HashMap, VecDeque};
+
+#[cfg(any(target_arch = "wasm32", test))]
+use crate::clock::{Clock, TIMER_SLACK_MS};
+
+/// Bracketed-paste markers, so multi-line text arrives as one paste
+const PASTE_START: &str = "\x1b[200~";
+const PASTE_END: &str = "\x1b[201~";
+
+/// Wrap text in bracketed-paste markers
+pub fn bracketed_paste(text: &str) -> String {
+    // An end marker inside the text would end the paste early
+    format!("{}{}{}", PASTE_START, text.replace(PASTE_END, ""), PASTE_END)
+}
+
+/// Text without the bracketed-paste markers around it, if any
+pub fn strip_paste(text: &str) -> &str {
+    text.strip_prefix(PASTE_START)
+        .and_then(|t| t.strip_suffix(PASTE_END))
+        .unwrap_or(text)
+}
+
+/// How urgently a write should reach its pane
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Priority {
+    /// Scheduler and broadcast traffic
+    Bulk,
+    /// Interactive commands
+    #[default]
+    Normal,
+    /// Interrupts; also go ahead of paced writes not started yet
+    Urgent,
+}
+
+/// Lifecycle of a paced send
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum JobStatus {
+    Running,
+    Paused,
+    Aborted,
+    Done,
+}
+
+/// Progress of a paced send, as reported by list_sends
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SendJob {
+    pub id: String,
+    pub pane_id: u32,
+    pub status: JobStatus,
+    pub chunks_sent: usize,
+    pub chunks_total: usize,
+}
+
+#[cfg(any(target_arch = "wasm32", test))]
+struct Slot {
+    ticket: Option<u64>,
+    /// Paced send this slot belongs to
+    job: Option<String>,
+    chunks: VecDeque<Vec<u8>>,
+    ready: bool,
+    paced: bool,
+    /// A paced slot released its first chunk; the rest wait for ticks
+    started: bool,
+    /// Bytes of a paced slot released so far
+    sent: usize,
+    /// Where the end marker of a bracketed paste starts, for a paced slot
+    /// that holds one
+    paste_end: Option<usize>,
+    paused: bool,
+    priority: Priority,
+    /// When an unfilled reservation gives up its place, in milliseconds
+    /// since the Unix epoch
+    expires_ms: Option<u64>,
+}
+
+/// Ordered write queues keyed by pane id
+#[cfg(any(target_arch = "wasm32", test))]
+#[derive(Default)]
+pub struct WriteQueue {
+    next_ticket: u64,
+    panes: HashMap<u32, VecDeque<Slot>>,
+}
+
+#[cfg(any(target_arch = "wasm32", test))]
+impl WriteQueue {
+    /// Hold a place for a write whose bytes arrive later via `complete`
+    ///
+    /// A reservation not filled by `expires_ms` is dropped by `expire`, so
+    /// a caller that never answers cannot hold the pane's writes forever.
+    pub fn reserve(&mut self, pane_id: u32, priority: Priority, expires_ms: u64) -> u64 {
+        self.next_ticket += 1;
+        let ticket = self.next_ticket;
+        self.insert(pane_id, Slot {
+            ticket: Some(ticket),
+            job: None,
+            chunks: VecDeque::new(),
+            ready: false,
+            paced: false,
+            started: false,
+            sent: 0,
+            paste_end: None,
+            paused: false,
+            priority,
+            expires_ms: Some(expires_ms),
+        });
+        ticket
+    }
+
+    /// Queue bytes for a pane, returning the writes that may happen now
+    pub fn push(&mut self, pane_id: u32, bytes: Vec<u8>, priority: Priority) -> Vec<Vec<u8>> {
+        self.insert(pane_id, Slot {
+            ticket: None,
+            job: None,
+            chunks: VecDeque::from([bytes]),
+            ready: true,
+            paced: false,
+            started: false,
+            sent: 0,
+            paste_end: None,
+            paused: false,
+            priority,
+            expires_ms: None,
+        });
+        self.drain(pane_id)
+    }
+
+    /// Queue chunks released one per `tick`, returning the writes that may
+    /// happen now (at most the first chunk)
+    pub fn push_paced(&mut self, pane_id: u32, chunks: Vec<Vec<u8>>, priority: Priority, job: Option<String>) -> Vec<Vec<u8>> {
+        let bytes = chunks.concat();
+        let paste_end = bytes
+            .starts_with(PASTE_START.as_bytes())
+            .then(|| find(&bytes, PASTE_END.as_bytes()))
+            .flatten();
+        self.insert(pane_id, Slot {
+            ticket: None,
+            job,
+            chunks: chunks.into(),
+            ready: true,
+            paced: true,
+            started: false,
+            sent: 0,
+            paste_end,
+            paused: false,
+            priority,
+            expires_ms: None,
+        });
+        self.drain(pane_id)
+    }
+
+    /// Fill a reservation (None when nothing is written after all),
+    /// returning the writes that may happen now
+    pub fn complete(&mut self, pane_id: u32, ticket: u64, bytes: Option<Vec<u8>>) -> Vec<Vec<u8>> {
+        if let Some(slot) = self
+            .panes
+            .get_mut(&pane_id)
+            .and_then(|q| q.iter_mut().find(|s| s.ticket == Some(ticket)))
+        {
+            slot.chunks = bytes.into_iter().collect();
+            slot.ready = true;
+        }
+        self.drain(pane_id)
+    }
+
+    /// Whether a reservation is still waiting to be filled
+    pub fn holds(&self, pane_id: u32, ticket: u64) -> bool {
+        self.panes
+            .get(&pane_id)
+            .is_some_and(|q| q.iter().any(|s| s.ticket == Some(ticket) && !s.ready))
+    }
+
+    /// Drop reservations unfilled at `now_ms`, returning the writes that
+    /// were waiting behind them and may happen now
+    pub fn expire(&mut self, now_ms: u64) -> Vec<(u32, Vec<Vec<u8>>)> {
+        let expired: Vec<u32> = self
+            .panes
+            .iter_mut()
+            .filter_map(|(id, q)| {
+                let before = q.len();
+                q.retain(|s| s.ready || s.expires_ms.is_none_or(|at| at > now_ms));
+                (q.len() < before).then_some(*id)
+            })
+            .collect();
+        expired
+            .into_iter()
+            .map(|pane_id| (pane_id, self.drain(pane_id)))
+            .collect()
+    }
+
+    /// Release the next chunk of every paced write, and what queued behind
+    /// any that finished
+    pub fn tick(&mut self) -> Vec<(u32, Vec<Vec<u8>>)> {
+        let pacing: Vec<u32> = self
+            .panes
+            .iter()
+            .filter(|(_, q)| q.front().is_some_and(is_ticking))
+            .map(|(id, _)| *id)
+            .collect();
+        let mut released = Vec::new();
+        for pane_id in pacing {
+            let mut ready = Vec::new();
+            if let Some(bytes) = self
+                .panes
+                .get_mut(&pane_id)
+                .and_then(|q| q.front_mut())
+                .and_then(|s| {
+                    let bytes = s.chunks.pop_front()?;
+                    s.sent += bytes.len();
+                    Some(bytes)
+                })
+            {
+                ready.push(bytes);
+            }
+            ready.extend(self.drain(pane_id));
+            released.push((pane_id, ready));
+        }
+        released
+    }
+
+    /// Whether a paced write is waiting for its next `tick`
+    pub fn is_pacing(&self) -> bool {
+        self.panes.values().any(|q| q.front().is_some_and(is_ticking))
+    }
+
+    /// Chunks of a paced send not yet written; None once it is finished
+    pub fn job_remaining(&self, job: &str) -> Option<usize> {
+        self.panes
+            .values()
+            .flatten()
+            .find(|s| s.job.as_deref() == Some(job))
+            .map(|s| s.chunks.len())
+    }
+
+    /// Stop releasing a paced send's chunks until `resume`
+    pub fn pause(&mut self, job: &str) -> bool {
+        self.job_slot(job).map(|s| s.paused = true).is_some()
+    }
+
+    /// Continue a paused send from where it stopped, returning the writes
+    /// that may happen now
+    pub fn resume(&mut self, job: &str) -> Option<(u32, Vec<Vec<u8>>)> {
+        let pane_id = self.job_pane(job)?;
+        self.job_slot(job)?.paused = false;
+        Some((pane_id, self.drain(pane_id)))
+    }
+
+    /// Drop the unsent rest of a paced send, returning the writes that were
+    /// waiting behind it and may happen now
+    ///
+    /// A bracketed paste already opened is closed first, as an urgent write
+    /// in the aborted send's place, so the pane does not stay in paste mode.
+    pub fn abort(&mut self, job: &str) -> Option<(u32, Vec<Vec<u8>>)> {
+        let pane_id = self.job_pane(job)?;
+        let queue = self.panes.get_mut(&pane_id)?;
+        let at = queue.iter().position(|s| s.job.as_deref() == Some(job))?;
+        match closing_bytes(&queue[at]) {
+            Some(close) => {
+                queue[at] = Slot {
+                    ticket: None,
+                    job: None,
+                    chunks: VecDeque::from([close]),
+                    ready: true,
+                    paced: false,
+                    started: false,
+                    sent: 0,
+                    paste_end: None,
+                    paused: false,
+                    priority: Priority::Urgent,
+                    expires_ms: None,
+                };
+            }
+            None => {
+                queue.remove(at);
+            }
+        }
+        Some((pane_id, self.drain(pane_id)))
+    }
+
+    fn job_pane(&self, job: &str) -> Option<u32> {
+        self.panes
+            .iter()
+            .find(|(_, q)| q.iter().any(|s| s.job.as_deref() == Some(job)))
+            .map(|(id, _)| *id)
+    }
+
+    fn job_slot(&mut self, job: &str) -> Option<&mut Slot> {
+        self.panes
+            .values_mut()
+            .flatten()
+            .find(|s| s.job.as_deref() == Some(job))
+    }
+
+    /// Discard the queues of panes that have closed
+    pub fn retain_panes(&mut self, mut keep: impl FnMut(u32) -> bool) {
+        self.panes.retain(|id, _| keep(*id));
+    }
+
+    /// Queue a slot behind everything it may not go ahead of: started
+    /// slots, slots of the same or higher priority, and paced slots unless
+    /// it is urgent
+    fn insert(&mut self, pane_id: u32, slot: Slot) {
+        let queue = self.panes.entry(pane_id).or_default();
+        let at = queue
+            .iter()
+            .rposition(|s| {
+                s.started || s.priority >= slot.priority || (s.paced && slot.priority < Priority::Urgent)
+            })
+            .map_or(0, |i| i + 1);
+        queue.insert(at, slot);
+    }
+
+    /// Release ready writes from the front of a pane's queue
+    ///
+    /// A paced slot gives up one chunk on reaching the front and then holds
+    /// the queue until the next tick.
+    fn drain(&mut self, pane_id: u32) -> Vec<Vec<u8>> {
+        let mut ready = Vec::new();
+        let Some(queue) = self.panes.get_mut(&pane_id) else {
+            return ready;
+        };
+        while let Some(slot) = queue.front_mut().filter(|s| s.ready) {
+            if slot.paced && !slot.started && !slot.paused {
+                slot.started = true;
+                if let Some(bytes) = slot.chunks.pop_front() {
+                    slot.sent += bytes.len();
+                    ready.push(bytes);
+                }
+            }
+            if slot.paced && !slot.chunks.is_empty() {
+                break;
+            }
+            if let Some(slot) = queue.pop_front() {
+                ready.extend(slot.chunks);
+            }
+        }
+        if queue.is_empty() {
+            self.panes.remove(&pane_id);
+        }
+        ready
+    }
+}
+
+/// When the next chunk of paced writes is due
+///
+/// Zellij's timer is shared with chaos delays, waits, polls, fanouts and
+/// housekeeping, so pacing goes by its own deadline rather than by which
+/// timer fired.
+#[cfg(any(target_arch = "wasm32", test))]
+#[derive(Debug, Default)]
+pub struct Pacer {
+    /// Milliseconds since the Unix epoch; None when no pacing timer is set
+    due_ms: Option<u64>,
+}
+
+#[cfg(any(target_arch = "wasm32", test))]
+impl Pacer {
+    /// Set a timer for the next chunk if a paced write waits for one and
+    /// no pacing timer is pending
+    pub fn arm(&mut self, writes: &WriteQueue, pace_ms: u64, clock: &mut impl Clock) {
+        if self.due_ms.is_none() && writes.is_pacing() {
+            self.due_ms = Some(clock.now_ms() + pace_ms);
+            clock.set_timeout(pace_ms as f64 / 1000.0);
+        }
+    }
+
+    /// Whether the next chunk is due at `now_ms`; the deadline is used up
+    /// when it is
+    pub fn take_due(&mut self, now_ms: u64) -> bool {
+        let due = self.due_ms.is_some_and(|due| now_ms + TIMER_SLACK_MS >= due);
+        if due {
+            self.due_ms = None;
+        }
+        due
+    }
+}
+
+/// A paced slot whose next chunk is due at the next tick
+#[cfg(any(target_arch = "wasm32", test))]
+fn is_ticking(slot: &Slot) -> bool {
+    slot.paced && slot.started && !slot.paused
+}
+
+/// What must still be written to end the bracketed paste a paced slot
+/// opened, or None when it opened none or already closed it
+#[cfg(any(target_arch = "wasm32", test))]
+fn closing_bytes(slot: &Slot) -> Option<Vec<u8>> {
+    let end = slot.paste_end?;
+    let (start, close) = (PASTE_START.as_bytes(), PASTE_END.as_bytes());
+    match slot.sent {
+        0 => None,
+        // The start marker itself was cut; finish it so the end marker
+        // is read as one
+        sent if sent < start.len() => Some([&start[sent..], close].concat()),
+        sent if sent <= end => Some(close.to_vec()),
+        sent if sent < end + close.len() => Some(close[sent - end..].to_vec()),
+        _ => None,
+    }
+}
+
+/// Offset of the first occurrence of `needle` in `bytes`
+#[cfg(any(target_arch = "wasm32", test))]
+fn find(bytes: &[u8], needle: &[u8]) -> Option<usize> {
+    bytes.windows(needle.len()).position(|w| w == needle)
+}
+
+/// Split text into chunks of at most `max_bytes`, never inside a character
+#[cfg(any(target_arch = "wasm32", test))]
+pub fn chunk_text(text: &str, max_bytes: usize) -> Vec<Vec<u8>> {
+    let mut chunks = Vec::new();
+    let mut rest = text;
+    while !rest.is_empty() {
+        let mut end = max_bytes.max(1).min(rest.len());
+        while !rest.is_char_boundary(end) {
+            end += 1;
+        }
+        chunks.push(rest.as_bytes()[..end].to_vec());
+        rest = &rest[end..];
+    }
+    chunks
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pending(queue: &WriteQueue, pane_id: u32) -> usize {
+        queue.panes.get(&pane_id).map_or(0, |q| q.len())
+    }
+
+    #[test]
+    fn test_push_without_reservation_writes_immediately() {
+        let mut queue = WriteQueue::default();
+        assert_eq!(queue.push(1, b"ls\n".to_vec(), Priority::Normal), vec![b"ls\n".to_vec()]);
+        assert_eq!(pending(&queue, 1), 0);
+    }
+
+    #[test]
+    fn test_writes_wait_behind_reservation() {
+        let mut queue = WriteQueue::default();
+        let ticket = queue.reserve(1, Priority::Normal, u64::MAX);
+
+        assert!(queue.push(1, b"second".to_vec(), Priority::Normal).is_empty());
+        // Other panes are unaffected
+        assert_eq!(queue.push(2, b"other".to_vec(), Priority::Normal), vec![b"other".to_vec()]);
+
+        let ready = queue.complete(1, ticket, Some(b"first".to_vec()));
+        assert_eq!(ready, vec![b"first".to_vec(), b"second".to_vec()]);
+        assert_eq!(pending(&queue, 1), 0);
+    }
+
+    #[test]
+    fn test_reservations_complete_out_of_order() {
+        let mut queue = WriteQueue::default();
+        let a = queue.reserve(1, Priority::Normal, u64::MAX);
+        let b = queue.reserve(1, Priority::Normal, u64::MAX);
+
+        assert!(queue.complete(1, b, Some(b"b".to_vec())).is_empty());
+        assert_eq!(queue.complete(1, a, None), vec![b"b".to_vec()]);
+    }
+
+    #[test]
+    fn test_retain_panes_discards_closed_queues() {
+        let mut queue = WriteQueue::default();
+        queue.reserve(1, Priority::Normal, u64::MAX);
+        queue.reserve(2, Priority::Normal, u64::MAX);
+        queue.retain_panes(|id| id == 2);
+        assert_eq!(pending(&queue, 1), 0);
+        assert_eq!(pending(&queue, 2), 1);
+    }
+
+    fn chunks(parts: &[&str]) -> Vec<Vec<u8>> {
+        parts.iter().map(|p| p.as_bytes().to_vec()).collect()
+    }
+
+    #[test]
+    fn test_higher_priority_jumps_the_queue() {
+        let mut queue = WriteQueue::default();
+        let ticket = queue.reserve(1, Priority::Bulk, u64::MAX);
+        assert!(queue.push(1, b"bulk".to_vec(), Priority::Bulk).is_empty());
+
+        assert_eq!(queue.push(1, b"cli".to_vec(), Priority::Normal), vec![b"cli".to_vec()]);
+        assert_eq!(queue.complete(1, ticket, Some(b"first".to_vec())), chunks(&["first", "bulk"]));
+    }
+
+    #[test]
+    fn test_paced_write_releases_one_chunk_per_tick() {
+        let mut queue = WriteQueue::default();
+        assert_eq!(queue.push_paced(1, chunks(&["a", "b", "c"]), Priority::Bulk, None), chunks(&["a"]));
+        assert!(queue.push(1, b"next".to_vec(), Priority::Bulk).is_empty());
+        assert!(queue.is_pacing());
+
+        assert_eq!(queue.tick(), vec![(1, chunks(&["b"]))]);
+        assert_eq!(queue.tick(), vec![(1, chunks(&["c", "next"]))]);
+        assert!(!queue.is_pacing());
+        assert!(queue.tick().is_empty());
+    }
+
+    #[test]
+    fn test_nothing_goes_ahead_of_a_started_paced_write() {
+        let mut queue = WriteQueue::default();
+        queue.push_paced(1, chunks(&["\x1b[200~a", "b\x1b[201~"]), Priority::Bulk, None);
+
+        assert!(queue.push(1, b"cli".to_vec(), Priority::Normal).is_empty());
+        assert!(queue.push(1, vec![0x03], Priority::Urgent).is_empty());
+        assert_eq!(queue.tick(), vec![(1, chunks(&["b\x1b[201~", "\x03", "cli"]))]);
+    }
+
+    #[test]
+    fn test_only_urgent_writes_go_ahead_of_queued_paced_write() {
+        let mut queue = WriteQueue::default();
+        let ticket = queue.reserve(1, Priority::Bulk, u64::MAX);
+        assert!(queue.push_paced(1, chunks(&["a", "b"]), Priority::Bulk, None).is_empty());
+
+        assert!(queue.push(1, b"cli".to_vec(), Priority::Normal).is_empty());
+        assert_eq!(queue.push(1, vec![0x03], Priority::Urgent), vec![vec![0x03]]);
+        assert_eq!(queue.complete(1, ticket, Some(b"first".to_vec())), chunks(&["first", "a"]));
+        assert_eq!(queue.tick(), vec![(1, chunks(&["b", "cli"]))]);
+    }
+
+    #[test]
+    fn test_chunk_text_keeps_characters_whole() {
+        assert_eq!(chunk_text("abcde", 2), chunks(&["ab", "cd", "e"]));
+        assert_eq!(chunk_text("héllo", 2), chunks(&["hé", "ll", "o"]));
+        assert!(chunk_text("", 4).is_empty());
+    }
+
+    #[test]
+    fn test_pause_resume_and_abort_jobs() {
+        let mut queue = WriteQueue::default();
+        let job = Some("j1".to_string());
+        queue.push_paced(1, chunks(&["a", "b", "c"]), Priority::Normal, job);
+        queue.push(1, b"after".to_vec(), Priority::Normal);
+
+        assert!(queue.pause("j1"));
+        assert!(!queue.is_pacing());
+        assert!(queue.tick().is_empty());
+        assert_eq!(queue.job_remaining("j1"), Some(2));
+
+        assert_eq!(queue.resume("j1"), Some((1, Vec::new())));
+        assert_eq!(queue.tick(), vec![(1, chunks(&["b"]))]);
+
+        assert_eq!(queue.abort("j1"), Some((1, chunks(&["after"]))));
+        assert_eq!(queue.job_remaining("j1"), None);
+        assert!(!queue.pause("j1"));
+    }
+
+    #[test]
+    fn test_abort_closes_an_open_paste() {
+        let mut queue = WriteQueue::default();
+        let paste = chunk_text(&format!("{}\n", bracketed_paste("abcdef")), 4);
+        assert_eq!(queue.push_paced(1, paste.clone(), Priority::Bulk, Some("j1".to_string())), vec![paste[0].clone()]);
+        queue.tick();
+        assert!(queue.push(1, b"after".to_vec(), Priority::Normal).is_empty());
+
+        assert_eq!(queue.abort("j1"), Some((1, chunks(&["\x1b[201~", "after"]))));
+
+        // Mid end marker, only its rest is written
+        queue.push_paced(1, paste.clone(), Priority::Bulk, Some("j2".to_string()));
+        for _ in 0..3 {
+            queue.tick();
+        }
+        assert_eq!(queue.abort("j2"), Some((1, chunks(&["1~"]))));
+
+        // Not a paste, or not started: nothing to close
+        queue.push_paced(1, chunks(&["a", "b"]), Priority::Bulk, Some("j3".to_string()));
+        assert_eq!(queue.abort("j3"), Some((1, Vec::new())));
+    }
+
+    #[test]
+    fn test_pacing_starts_after_reservation_ahead_of_it_fills() {
+        let mut queue = WriteQueue::default();
+        let ticket = queue.reserve(1, Priority::Normal, u64::MAX);
+        assert!(queue.push_paced(1, chunks(&["a", "b"]), Priority::Normal, None).is_empty());
+        assert!(!queue.is_pacing());
+
+        assert_eq!(queue.complete(1, ticket, Some(b"secret".to_vec())), chunks(&["secret", "a"]));
+        assert!(queue.is_pacing());
+        assert_eq!(queue.tick(), vec![(1, chunks(&["b"]))]);
+    }
+
+    #[test]
+    fn test_pacer_ignores_other_timers_of_the_same_length() {
+        let mut clock = crate::clock::ManualClock::new(0);
+        let mut queue = WriteQueue::default();
+        let mut pacer = Pacer::default();
+        queue.push_paced(1, chunks(&["a", "b", "c"]), Priority::Normal, None);
+        pacer.arm(&queue, 1000, &mut clock);
+        pacer.arm(&queue, 1000, &mut clock);
+        // Another subsystem's timers, one as long as the pace
+        clock.set_timeout(0.5);
+        clock.set_timeout(1.0);
+
+        let mut ticks = 0;
+        for _ in 0..2 {
+            for _ in clock.advance(1000) {
+                if pacer.take_due(clock.now_ms()) {
+                    ticks += 1;
+                    queue.tick();
+                    pacer.arm(&queue, 1000, &mut clock);
+                }
+            }
+        }
+        assert_eq!(ticks, 2);
+        assert!(!queue.is_pacing());
+        assert_eq!(clock.pending(), 0);
+    }
+
+    #[test]
+    fn test_unfilled_reservation_expires() {
+        let mut queue = WriteQueue::default();
+        let stale = queue.reserve(1, Priority::Normal, 1000);
+        let filled = queue.reserve(2, Priority::Normal, 1000);
+        queue.push(1, b"behind".to_vec(), Priority::Normal);
+        queue.complete(2, filled, None);
+
+        assert!(queue.expire(999).is_empty());
+        assert!(queue.holds(1, stale));
+        assert_eq!(queue.expire(1000), vec![(1, chunks(&["behind"]))]);
+        assert!(!queue.holds(1, stale));
+        // A late answer writes nothing
+        assert!(queue.complete(1, stale, Some(b"late".to_vec())).is_empty());
+    }
+}
@@ -0,0 +1,49 @@
+#!/bin/sh
+# Print the release manifest for an nzm-agent build as JSON.
+#
+# Usage: nzm-release-manifest.sh VERSION WASM_FILE
+#
+# nzm self-update checks the downloaded plugin against it, and nzm doctor
+# compares it with what the running plugin reports through get_version.
+# build_sha256 is computed the way plugin/nzm-agent/build.rs does it: the
+# sha256 of each source path and its content, NUL-terminated, in byte order.
+set -eu
+
+if [ "$#" -ne 2 ]; then
+    echo "usage: $0 VERSION WASM_FILE" >&2
+    exit 2
+fi
+version="$1"
+wasm="$2"
+root="$(cd "$(dirname "$0")/../plugin/nzm-agent" && pwd)"
+
+sha256() {
+    if command -v sha256sum >/dev/null 2>&1; then
+        sha256sum | cut -d' ' -f1
+    else
+        shasum -a 256 | cut -d' ' -f1
+    fi
+}
+
+build_sha256="$(
+    cd "$root"
+    { echo Cargo.toml; find src -type f; } | LC_ALL=C sort | while IFS= read -r f; do
+        printf '%s\0' "$f"
+        cat "$f"
+        printf '\0'
+    done | sha256
+)"
+file_sha256="$(sha256 <"$wasm")"
+protocol="$(sed -n 's/^pub const PROTOCOL_VERSION: u32 = \([0-9]*\);$/\1/p' "$root/src/ipc.rs")"
+
+cat <<JSON
+{
+  "version": "$version",
+  "protocol": $protocol,
+  "plugin": {
+    "file": "$(basename "$wasm")",
+    "sha256": "$file_sha256",
+    "build_sha256": "$build_sha256"
+  }
+}
+JSON